@@ -5,6 +5,7 @@ use std::sync::atomic::{self, AtomicUsize};
 use std::{cmp, fmt, hash, mem, ptr, ptr::NonNull, slice, usize};
 
 use crate::pool::{PoolId, PoolRef};
+use crate::slab::{self, Slab};
 use crate::{buf::IntoIter, buf::UninitSlice, debug, Buf, BufMut};
 
 /// A reference counted contiguous slice of memory.
@@ -393,6 +394,9 @@ const KIND_UNMASK: usize = !KIND_MASK;
 const MIN_NON_ZERO_CAP: usize = 64;
 const SHARED_VEC_SIZE: usize = mem::size_of::<SharedVec>();
 
+// Slab chunks reserve space for the header right before buffer data
+const _: () = assert!(SHARED_VEC_SIZE == slab::HEADER_SIZE);
+
 // Bit op constants for extracting the inline length value from the `arc` field.
 const INLINE_LEN_MASK: usize = 0b1111_1100;
 const INLINE_LEN_OFFSET: usize = 2;
@@ -2652,6 +2656,12 @@ impl InnerVec {
 
     #[inline]
     fn from_slice(cap: usize, src: &[u8], pool: PoolRef) -> InnerVec {
+        if let Some(slab) = pool.slab() {
+            if slab.fits(cap) {
+                return InnerVec::from_slab(slab, src, pool);
+            }
+        }
+
         // vec must be aligned to SharedVec instead of u8
         let vec_cap = if cap % SHARED_VEC_SIZE != 0 {
             (cap / SHARED_VEC_SIZE) + 2
@@ -2686,6 +2696,31 @@ impl InnerVec {
         }
     }
 
+    fn from_slab(slab: &'static Slab, src: &[u8], pool: PoolRef) -> InnerVec {
+        unsafe {
+            let cap = slab.capacity() + SHARED_VEC_SIZE;
+            let shared_ptr = slab.alloc() as *mut SharedVec;
+            pool.acquire(cap);
+
+            let ptr = shared_ptr.add(1) as *mut u8;
+            if !src.is_empty() {
+                ptr::copy_nonoverlapping(src.as_ptr(), ptr, src.len());
+            }
+            ptr::write(
+                shared_ptr,
+                SharedVec {
+                    cap,
+                    pool,
+                    len: src.len() as u32,
+                    ref_count: AtomicUsize::new(1),
+                    offset: SHARED_VEC_SIZE as u32,
+                },
+            );
+
+            InnerVec(NonNull::new_unchecked(shared_ptr))
+        }
+    }
+
     #[inline]
     fn move_to_pool(&mut self, pool: PoolRef) {
         unsafe {
@@ -3034,6 +3069,12 @@ impl Inner {
 
     #[inline]
     fn from_slice(cap: usize, src: &[u8], pool: PoolRef) -> Inner {
+        if let Some(slab) = pool.slab() {
+            if slab.fits(cap) {
+                return Inner::from_slab(slab, src, pool);
+            }
+        }
+
         // vec must be aligned to SharedVec instead of u8
         let mut vec_cap = (cap / SHARED_VEC_SIZE) + 1;
         if cap % SHARED_VEC_SIZE != 0 {
@@ -3069,6 +3110,33 @@ impl Inner {
         Inner { len, cap, ptr, arc }
     }
 
+    fn from_slab(slab: &'static Slab, src: &[u8], pool: PoolRef) -> Inner {
+        let len = src.len();
+        let cap = slab.capacity();
+        pool.acquire(cap + SHARED_VEC_SIZE);
+
+        unsafe {
+            let shared_ptr = slab.alloc() as *mut SharedVec;
+            ptr::write(
+                shared_ptr,
+                SharedVec {
+                    pool,
+                    cap: cap + SHARED_VEC_SIZE,
+                    ref_count: AtomicUsize::new(1),
+                    len: 0,
+                    offset: 0,
+                },
+            );
+
+            let ptr = shared_ptr.add(1) as *mut u8;
+            ptr::copy_nonoverlapping(src.as_ptr(), ptr, len);
+            let arc =
+                NonNull::new_unchecked((shared_ptr as usize ^ KIND_VEC) as *mut Shared);
+
+            Inner { len, cap, ptr, arc }
+        }
+    }
+
     #[inline]
     fn from_slice_inline(src: &[u8]) -> Inner {
         unsafe { Inner::from_ptr_inline(src.as_ptr(), src.len()) }
@@ -3715,7 +3783,9 @@ fn release_shared_vec(ptr: *mut SharedVec) {
         let cap = (*ptr).cap;
         (*ptr).pool.release(cap);
         ptr::drop_in_place(ptr);
-        Vec::<u8>::from_raw_parts(ptr as *mut u8, 0, cap);
+        if !slab::release(ptr as *mut u8, cap) {
+            Vec::<u8>::from_raw_parts(ptr as *mut u8, 0, cap);
+        }
     }
 }
 
@@ -61,6 +61,7 @@ mod debug;
 mod hex;
 mod pool;
 mod serde;
+mod slab;
 mod string;
 
 pub use crate::bytes::{Bytes, BytesMut, BytesVec};
@@ -68,3 +69,6 @@ pub use crate::string::ByteString;
 
 #[doc(hidden)]
 pub use crate::pool::{Pool, PoolId, PoolRef};
+
+#[doc(hidden)]
+pub use crate::slab::SlabParams;
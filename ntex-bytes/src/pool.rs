@@ -2,10 +2,13 @@
 use std::sync::atomic::Ordering::{Relaxed, Release};
 use std::sync::atomic::{AtomicBool, AtomicUsize};
 use std::task::{Context, Poll, Waker};
-use std::{cell::Cell, cell::RefCell, fmt, future::Future, mem, pin::Pin, ptr, rc::Rc};
+use std::{
+    cell::Cell, cell::RefCell, cmp, fmt, future::Future, mem, pin::Pin, ptr, rc::Rc,
+};
 
 use futures_core::task::__internal::AtomicWaker;
 
+use crate::slab::{Slab, SlabParams};
 use crate::{BufMut, BytesMut, BytesVec};
 
 pub struct Pool {
@@ -54,6 +57,7 @@ struct MemoryPool {
     read_cache: RefCell<Vec<BytesVec>>,
     write_wm: Cell<BufParams>,
     write_cache: RefCell<Vec<BytesVec>>,
+    slab: Cell<Option<&'static Slab>>,
 
     spawn: RefCell<Option<Rc<dyn Fn(Pin<Box<dyn Future<Output = ()>>>)>>>,
 }
@@ -112,6 +116,13 @@ impl PoolId {
         self
     }
 
+    #[inline]
+    /// Allocate io buffers from aligned slabs
+    pub fn set_slab_params(self, params: SlabParams) -> Self {
+        self.pool_ref().set_slab_params(params);
+        self
+    }
+
     /// Set future spawn fn
     pub fn set_spawn_fn<T>(self, f: T) -> Self
     where
@@ -282,6 +293,27 @@ impl PoolRef {
         self
     }
 
+    /// Allocate io buffers from aligned slabs
+    ///
+    /// Slab chunk size is max of read and write buffers high watermarks
+    /// rounded up to power of two, so params must be set before enabling
+    /// slabs. Buffers with capacity between half and full chunk size get
+    /// allocated from slab, buffer data is aligned to the page size (4096), this
+    /// is suitable for `O_DIRECT` and zero-copy io. Slabs are shared between
+    /// pools with the same chunk size. Slab memory is allocated with 2Mb
+    /// regions, on linux regions could be backed by transparent huge pages.
+    /// Memory of slab regions is never returned to the system.
+    pub fn set_slab_params(self, params: SlabParams) -> Self {
+        let size = cmp::max(self.0.read_wm.get().high, self.0.write_wm.get().high) as usize;
+        self.0.slab.set(Some(Slab::get(size, params)));
+        self
+    }
+
+    #[inline]
+    pub(crate) fn slab(self) -> Option<&'static Slab> {
+        self.0.slab.get()
+    }
+
     #[doc(hidden)]
     #[inline]
     pub fn get_read_buf(self) -> BytesVec {
@@ -446,6 +478,7 @@ impl MemoryPool {
                 low: 1024,
             }),
             write_cache: RefCell::new(Vec::with_capacity(CACHE_SIZE)),
+            slab: Cell::new(None),
             spawn: RefCell::new(None),
         }))
     }
@@ -0,0 +1,172 @@
+//! Aligned buffer slabs
+//!
+//! Slab allocates large regions and splits them to equally sized chunks,
+//! buffer data is aligned to the page size. There is one static slab per
+//! power of two chunk size. Regions are never returned to the system,
+//! released chunks are reused for new buffers.
+use std::alloc::{alloc, handle_alloc_error, Layout};
+use std::sync::atomic::{AtomicBool, Ordering::Acquire, Ordering::Release};
+use std::{fmt, sync::Mutex};
+
+/// Size of the slab region, matches size of the huge page
+const REGION_SIZE: usize = 2 * 1024 * 1024;
+
+/// Alignment of buffer data
+const MAX_ALIGN: usize = 4096;
+
+/// Smallest slab chunk size
+const MIN_SIZE: usize = 4096;
+
+/// Largest slab chunk size
+const MAX_SIZE: usize = 4 * 1024 * 1024;
+
+/// Header size, header is stored right before buffer data
+#[cfg(target_pointer_width = "64")]
+pub(crate) const HEADER_SIZE: usize = 32;
+#[cfg(target_pointer_width = "32")]
+pub(crate) const HEADER_SIZE: usize = 20;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+static SLABS: [Slab; 11] = [
+    Slab::new(1 << 12),
+    Slab::new(1 << 13),
+    Slab::new(1 << 14),
+    Slab::new(1 << 15),
+    Slab::new(1 << 16),
+    Slab::new(1 << 17),
+    Slab::new(1 << 18),
+    Slab::new(1 << 19),
+    Slab::new(1 << 20),
+    Slab::new(1 << 21),
+    Slab::new(1 << 22),
+];
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+/// Slab allocation params
+pub struct SlabParams {
+    /// Use transparent huge pages for slab regions (linux only)
+    pub huge_pages: bool,
+}
+
+pub(crate) struct Slab {
+    size: usize,
+    huge_pages: AtomicBool,
+    inner: Mutex<SlabInner>,
+}
+
+struct SlabInner {
+    free: Vec<usize>,
+    regions: Vec<(usize, usize)>,
+}
+
+impl Slab {
+    const fn new(size: usize) -> Slab {
+        Slab {
+            size,
+            huge_pages: AtomicBool::new(false),
+            inner: Mutex::new(SlabInner {
+                free: Vec::new(),
+                regions: Vec::new(),
+            }),
+        }
+    }
+
+    /// Get static slab for chunks of requested size
+    pub(crate) fn get(size: usize, params: SlabParams) -> &'static Slab {
+        let size = size.max(MIN_SIZE).next_power_of_two();
+        assert!(size <= MAX_SIZE, "Slab chunk size is too large: {}", size);
+
+        let slab = &SLABS[(size / MIN_SIZE).trailing_zeros() as usize];
+        if params.huge_pages {
+            slab.huge_pages.store(true, Release);
+        }
+        slab
+    }
+
+    #[inline]
+    /// Data capacity of the chunk
+    pub(crate) fn capacity(&self) -> usize {
+        self.size
+    }
+
+    #[inline]
+    /// Check if buffer with requested capacity fits slab chunk
+    pub(crate) fn fits(&self, cap: usize) -> bool {
+        cap <= self.size && cap > self.size / 2
+    }
+
+    /// Allocate chunk, returns pointer to the chunk header
+    pub(crate) fn alloc(&self) -> *mut u8 {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.free.is_empty() {
+            self.grow(&mut inner);
+        }
+        inner.free.pop().unwrap() as *mut u8
+    }
+
+    fn grow(&self, inner: &mut SlabInner) {
+        let stride = self.size + MAX_ALIGN;
+        let region = (stride + REGION_SIZE - 1) & !(REGION_SIZE - 1);
+        let layout = Layout::from_size_align(region, REGION_SIZE).unwrap();
+
+        let start = unsafe { alloc(layout) };
+        if start.is_null() {
+            handle_alloc_error(layout);
+        }
+        #[cfg(target_os = "linux")]
+        if self.huge_pages.load(Acquire) {
+            unsafe {
+                libc::madvise(start as *mut libc::c_void, region, libc::MADV_HUGEPAGE);
+            }
+        }
+
+        let start = start as usize;
+        let num = region / stride;
+        inner.free.reserve(num);
+        for idx in (0..num).rev() {
+            inner
+                .free
+                .push(start + idx * stride + MAX_ALIGN - HEADER_SIZE);
+        }
+        inner.regions.push((start, start + region));
+        ENABLED.store(true, Release);
+    }
+}
+
+/// Return chunk to the slab, if pointer belongs to one of slab regions
+///
+/// `cap` is full capacity of the chunk including header.
+pub(crate) fn release(ptr: *mut u8, cap: usize) -> bool {
+    if !ENABLED.load(Acquire) {
+        return false;
+    }
+
+    let size = cap.wrapping_sub(HEADER_SIZE);
+    if !size.is_power_of_two() || !(MIN_SIZE..=MAX_SIZE).contains(&size) {
+        return false;
+    }
+
+    let addr = ptr as usize;
+    let slab = &SLABS[(size / MIN_SIZE).trailing_zeros() as usize];
+    let mut inner = slab.inner.lock().unwrap();
+    if inner
+        .regions
+        .iter()
+        .any(|(start, end)| addr >= *start && addr < *end)
+    {
+        inner.free.push(addr);
+        true
+    } else {
+        false
+    }
+}
+
+impl fmt::Debug for Slab {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Slab")
+            .field("size", &self.size)
+            .field("huge_pages", &self.huge_pages.load(Acquire))
+            .finish()
+    }
+}
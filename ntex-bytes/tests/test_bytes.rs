@@ -1,7 +1,9 @@
 #![allow(clippy::op_ref, clippy::let_underscore_future)]
 use std::{borrow::Borrow, borrow::BorrowMut, task::Poll};
 
-use ntex_bytes::{Buf, BufMut, Bytes, BytesMut, BytesVec, Pool, PoolId, PoolRef};
+use ntex_bytes::{
+    Buf, BufMut, Bytes, BytesMut, BytesVec, Pool, PoolId, PoolRef, SlabParams,
+};
 
 const LONG: &[u8] = b"mary had a little lamb, little lamb, little lamb";
 const SHORT: &[u8] = b"hello world";
@@ -1128,6 +1130,37 @@ fn pool() {
     assert_eq!(p3.allocated(), 2080 + shared_vec());
 }
 
+#[test]
+fn pool_slab() {
+    let p = PoolId::P4
+        .set_read_params(8 * 1024, 1024)
+        .set_write_params(4 * 1024, 1024)
+        .set_slab_params(SlabParams { huge_pages: false })
+        .pool_ref();
+
+    let buf = p.get_read_buf();
+    assert_eq!(buf.capacity(), 8192);
+    assert_eq!(buf.as_ptr() as usize % 4096, 0);
+    assert_eq!(p.allocated(), 8192 + shared_vec());
+    let ptr = buf.as_ptr();
+    drop(buf);
+    assert_eq!(p.allocated(), 0);
+
+    // released chunk is reused
+    let mut buf = BytesMut::with_capacity_in(5000, p);
+    assert_eq!(buf.as_ptr(), ptr);
+    buf.extend_from_slice(b"hello");
+    let b = buf.split().freeze();
+    assert_eq!(b, "hello");
+
+    // small buffers are not allocated from slab
+    let buf2 = BytesMut::with_capacity_in(1024, p);
+    assert_eq!(buf2.capacity(), 1024);
+    assert_eq!(p.allocated(), 8192 + 1024 + 2 * shared_vec());
+    drop((b, buf, buf2));
+    assert_eq!(p.allocated(), 0);
+}
+
 #[ntex::test]
 async fn pool_usage() {
     use ntex::{time, util};
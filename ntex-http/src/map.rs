@@ -1,5 +1,7 @@
 use std::collections::{self, hash_map, hash_map::Entry, VecDeque};
 
+use ntex_bytes::ByteString;
+
 use crate::{HeaderName, HeaderValue};
 
 type HashMap<K, V> = collections::HashMap<K, V, fxhash::FxBuildHasher>;
@@ -19,9 +21,10 @@ pub enum Either<A, B> {
 /// `HeaderMap` is an multimap of [`HeaderName`] to values.
 ///
 /// [`HeaderName`]: struct.HeaderName.html
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 pub struct HeaderMap {
     pub(crate) inner: HashMap<HeaderName, Value>,
+    case: Option<Box<HashMap<HeaderName, ByteString>>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -145,6 +148,7 @@ impl HeaderMap {
     pub fn new() -> Self {
         HeaderMap {
             inner: HashMap::default(),
+            case: None,
         }
     }
 
@@ -159,6 +163,7 @@ impl HeaderMap {
     pub fn with_capacity(capacity: usize) -> HeaderMap {
         HeaderMap {
             inner: HashMap::with_capacity_and_hasher(capacity, Default::default()),
+            case: None,
         }
     }
 
@@ -179,6 +184,7 @@ impl HeaderMap {
     /// for reuse.
     pub fn clear(&mut self) {
         self.inner.clear();
+        self.case = None;
     }
 
     /// Returns the number of headers the map can hold without reallocating.
@@ -333,16 +339,65 @@ impl HeaderMap {
         match key.as_name() {
             Either::Left(name) => {
                 let _ = self.inner.remove(name);
+                if let Some(ref mut case) = self.case {
+                    let _ = case.remove(name);
+                }
             }
             Either::Right(s) => {
                 if let Ok(name) = HeaderName::try_from(s) {
                     let _ = self.inner.remove(&name);
+                    if let Some(ref mut case) = self.case {
+                        let _ = case.remove(&name);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns original casing of the header name.
+    ///
+    /// Returns `None` if original casing is not recorded for the name.
+    pub fn original_case<N: AsName>(&self, name: N) -> Option<&str> {
+        let case = self.case.as_ref()?;
+        match name.as_name() {
+            Either::Left(name) => case.get(name).map(|s| s.as_ref()),
+            Either::Right(s) => {
+                if let Ok(name) = HeaderName::try_from(s) {
+                    case.get(&name).map(|s| s.as_ref())
+                } else {
+                    None
                 }
             }
         }
     }
+
+    /// Record original casing of the header name.
+    ///
+    /// Http/1 encoder writes recorded casing to the wire instead of
+    /// lowercase name. Casing is ignored if it does not match the name.
+    pub fn set_original_case<T>(&mut self, name: &HeaderName, case: T)
+    where
+        ByteString: From<T>,
+    {
+        let case = ByteString::from(case);
+        if case.as_bytes().eq_ignore_ascii_case(name.as_str().as_bytes()) {
+            let _ = self
+                .case
+                .get_or_insert_with(Default::default)
+                .insert(name.clone(), case);
+        }
+    }
+}
+
+// header name casing is not compared
+impl PartialEq for HeaderMap {
+    fn eq(&self, other: &HeaderMap) -> bool {
+        self.inner == other.inner
+    }
 }
 
+impl Eq for HeaderMap {}
+
 #[doc(hidden)]
 pub trait AsName {
     fn as_name(&self) -> Either<&HeaderName, &str>;
@@ -416,7 +471,10 @@ where
                 }
                 map
             });
-        HeaderMap { inner: map }
+        HeaderMap {
+            inner: map,
+            case: None,
+        }
     }
 }
 
@@ -642,6 +700,27 @@ mod tests {
         assert_eq!(map.get(ACCEPT_ENCODING), None);
     }
 
+    #[test]
+    fn test_original_case() {
+        let mut map = HeaderMap::new();
+        map.insert(CONTENT_TYPE, HeaderValue::from_static("text"));
+        assert_eq!(map.original_case(CONTENT_TYPE), None);
+
+        map.set_original_case(&CONTENT_TYPE, "Content-TYPE");
+        map.set_original_case(&ACCEPT_ENCODING, "Accept");
+        assert_eq!(map.original_case(CONTENT_TYPE), Some("Content-TYPE"));
+        assert_eq!(map.original_case("content-type"), Some("Content-TYPE"));
+        assert_eq!(map.original_case(ACCEPT_ENCODING), None);
+        assert_eq!(map, map.clone());
+
+        let mut map2 = HeaderMap::new();
+        map2.insert(CONTENT_TYPE, HeaderValue::from_static("text"));
+        assert_eq!(map, map2);
+
+        map.remove(CONTENT_TYPE);
+        assert_eq!(map.original_case(CONTENT_TYPE), None);
+    }
+
     #[test]
     fn test_from_http() {
         let mut map = http::HeaderMap::new();
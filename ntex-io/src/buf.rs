@@ -235,6 +235,12 @@ impl Stack {
         result
     }
 
+    /// Take read and write buffers of the first level
+    pub(crate) fn take_buffers(&self) -> (Option<BytesVec>, Option<BytesVec>) {
+        let item = self.get_first_level();
+        (item.0.take(), item.1.take())
+    }
+
     pub(crate) fn read_destination_size(&self) -> usize {
         let item = self.get_first_level();
         let rb = item.0.take();
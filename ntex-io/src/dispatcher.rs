@@ -1,13 +1,17 @@
 //! Framed transport dispatcher
 #![allow(clippy::let_underscore_future)]
-use std::{cell::Cell, future, pin::Pin, rc::Rc, task::Context, task::Poll};
+use std::{cell::Cell, future, io, marker::PhantomData, pin::Pin, rc::Rc};
+use std::{task::Context, task::Poll, time};
 
 use ntex_bytes::Pool;
 use ntex_codec::{Decoder, Encoder};
 use ntex_service::{IntoService, Pipeline, Service};
-use ntex_util::{future::Either, ready, spawn, time::Seconds};
+use ntex_util::time::{now, Millis, Seconds, Sleep};
+use ntex_util::{future::Either, ready, spawn};
 
-use crate::{Decoded, DispatchItem, IoBoxed, IoStatusUpdate, RecvError};
+use crate::{
+    Decoded, DisconnectReason, DispatchItem, IoBoxed, IoError, IoStatusUpdate, RecvError,
+};
 
 type Response<U> = <U as Encoder>::Item;
 
@@ -23,6 +27,17 @@ struct DispatcherConfigInner {
     frame_read_rate: Cell<u16>,
     frame_read_timeout: Cell<Seconds>,
     frame_read_max_timeout: Cell<Seconds>,
+    frame_rate_limit: Cell<u32>,
+    frame_rate_action: Cell<FrameRateAction>,
+}
+
+/// Action for connections that exceed decoded frames rate limit
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FrameRateAction {
+    /// Pause reading until the end of current one second period
+    Delay,
+    /// Close connection
+    Close,
 }
 
 impl Default for DispatcherConfig {
@@ -34,6 +49,8 @@ impl Default for DispatcherConfig {
             frame_read_enabled: Cell::new(false),
             frame_read_timeout: Cell::new(Seconds::ZERO),
             frame_read_max_timeout: Cell::new(Seconds::ZERO),
+            frame_rate_limit: Cell::new(0),
+            frame_rate_action: Cell::new(FrameRateAction::Delay),
         }))
     }
 }
@@ -65,6 +82,17 @@ impl DispatcherConfig {
         }
     }
 
+    #[inline]
+    /// Get decoded frames rate limit
+    pub fn frame_rate_limit(&self) -> Option<(u32, FrameRateAction)> {
+        let limit = self.0.frame_rate_limit.get();
+        if limit != 0 {
+            Some((limit, self.0.frame_rate_action.get()))
+        } else {
+            None
+        }
+    }
+
     /// Set keep-alive timeout in seconds.
     ///
     /// To disable timeout set value to 0.
@@ -107,18 +135,33 @@ impl DispatcherConfig {
         self.0.frame_read_rate.set(rate);
         self
     }
+
+    /// Set max number of decoded frames per second for single connection.
+    ///
+    /// If connection exceeds the limit, dispatcher either pauses reading
+    /// until the end of current second or closes connection.
+    ///
+    /// To disable limit set value to 0. By default limit is disabled.
+    pub fn set_frame_rate_limit(&self, max: u32, action: FrameRateAction) -> &Self {
+        self.0.frame_rate_limit.set(max);
+        self.0.frame_rate_action.set(action);
+        self
+    }
 }
 
 pin_project_lite::pin_project! {
     /// Dispatcher - is a future that reads frames from bytes stream
     /// and pass then to the service.
-    pub struct Dispatcher<S, U>
+    ///
+    /// Io errors are classified as `IoError` and passed to the service
+    /// as `DispatchItem::Disconnect` converted to `E`.
+    pub struct Dispatcher<S, U, E = io::Error>
     where
-        S: Service<DispatchItem<U>, Response = Option<Response<U>>>,
+        S: Service<DispatchItem<U, E>, Response = Option<Response<U>>>,
         U: Encoder,
         U: Decoder,
     {
-        inner: DispatcherInner<S, U>,
+        inner: DispatcherInner<S, U, E>,
     }
 }
 
@@ -133,25 +176,28 @@ bitflags::bitflags! {
     }
 }
 
-struct DispatcherInner<S, U>
+struct DispatcherInner<S, U, E>
 where
-    S: Service<DispatchItem<U>, Response = Option<Response<U>>>,
+    S: Service<DispatchItem<U, E>, Response = Option<Response<U>>>,
     U: Encoder + Decoder,
 {
     st: DispatcherState,
     error: Option<S::Error>,
     flags: Flags,
-    shared: Rc<DispatcherShared<S, U>>,
+    shared: Rc<DispatcherShared<S, U, E>>,
     pool: Pool,
     cfg: DispatcherConfig,
     read_remains: u32,
     read_remains_prev: u32,
     read_max_timeout: Seconds,
+    frames: u32,
+    frames_start: time::Instant,
+    frames_delay: Option<Sleep>,
 }
 
-pub(crate) struct DispatcherShared<S, U>
+pub(crate) struct DispatcherShared<S, U, E>
 where
-    S: Service<DispatchItem<U>, Response = Option<Response<U>>>,
+    S: Service<DispatchItem<U, E>, Response = Option<Response<U>>>,
     U: Encoder + Decoder,
 {
     io: IoBoxed,
@@ -159,6 +205,7 @@ where
     service: Pipeline<S>,
     error: Cell<Option<DispatcherError<S::Error, <U as Encoder>::Error>>>,
     inflight: Cell<usize>,
+    _t: PhantomData<E>,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -175,8 +222,8 @@ enum DispatcherError<S, U> {
     Service(S),
 }
 
-enum PollService<U: Encoder + Decoder> {
-    Item(DispatchItem<U>),
+enum PollService<U: Encoder + Decoder, E> {
+    Item(DispatchItem<U, E>),
     Continue,
     Ready,
 }
@@ -190,9 +237,9 @@ impl<S, U> From<Either<S, U>> for DispatcherError<S, U> {
     }
 }
 
-impl<S, U> Dispatcher<S, U>
+impl<S, U, E> Dispatcher<S, U, E>
 where
-    S: Service<DispatchItem<U>, Response = Option<Response<U>>>,
+    S: Service<DispatchItem<U, E>, Response = Option<Response<U>>>,
     U: Decoder + Encoder,
 {
     /// Construct new `Dispatcher` instance.
@@ -201,10 +248,10 @@ where
         codec: U,
         service: F,
         cfg: &DispatcherConfig,
-    ) -> Dispatcher<S, U>
+    ) -> Dispatcher<S, U, E>
     where
         IoBoxed: From<Io>,
-        F: IntoService<S, DispatchItem<U>>,
+        F: IntoService<S, DispatchItem<U, E>>,
     {
         let io = IoBoxed::from(io);
         io.set_disconnect_timeout(cfg.disconnect_timeout());
@@ -222,6 +269,7 @@ where
             error: Cell::new(None),
             inflight: Cell::new(0),
             service: Pipeline::new(service.into_service()),
+            _t: PhantomData,
         });
 
         Dispatcher {
@@ -234,15 +282,18 @@ where
                 read_remains: 0,
                 read_remains_prev: 0,
                 read_max_timeout: Seconds::ZERO,
+                frames: 0,
+                frames_start: now(),
+                frames_delay: None,
                 st: DispatcherState::Processing,
             },
         }
     }
 }
 
-impl<S, U> DispatcherShared<S, U>
+impl<S, U, E> DispatcherShared<S, U, E>
 where
-    S: Service<DispatchItem<U>, Response = Option<Response<U>>>,
+    S: Service<DispatchItem<U, E>, Response = Option<Response<U>>>,
     U: Encoder + Decoder,
 {
     fn handle_result(&self, item: Result<S::Response, S::Error>, io: &IoBoxed) {
@@ -260,10 +311,11 @@ where
     }
 }
 
-impl<S, U> future::Future for Dispatcher<S, U>
+impl<S, U, E> future::Future for Dispatcher<S, U, E>
 where
-    S: Service<DispatchItem<U>, Response = Option<Response<U>>> + 'static,
+    S: Service<DispatchItem<U, E>, Response = Option<Response<U>>> + 'static,
     U: Decoder + Encoder + 'static,
+    E: From<IoError> + 'static,
 {
     type Output = Result<(), S::Error>;
 
@@ -284,11 +336,18 @@ where
                 DispatcherState::Processing => {
                     let item = match ready!(slf.poll_service(cx)) {
                         PollService::Ready => {
+                            // frame rate limit is reached, wait for next period
+                            ready!(slf.poll_frames_delay(cx));
+
                             // decode incoming bytes if buffer is ready
                             match slf.shared.io.poll_recv_decode(&slf.shared.codec, cx) {
                                 Ok(decoded) => {
                                     slf.update_timer(&decoded);
                                     if let Some(el) = decoded.item {
+                                        if !slf.check_frame_rate() {
+                                            slf.st = DispatcherState::Stop;
+                                            continue;
+                                        }
                                         DispatchItem::Item(el)
                                     } else {
                                         return Poll::Pending;
@@ -331,7 +390,7 @@ where
                                         err
                                     );
                                     slf.st = DispatcherState::Stop;
-                                    DispatchItem::Disconnect(err)
+                                    DispatchItem::Disconnect(err.map(io_error))
                                 }
                             }
                         }
@@ -353,7 +412,7 @@ where
                         PollService::Ready => {
                             if let Err(err) = ready!(slf.shared.io.poll_flush(cx, false)) {
                                 slf.st = DispatcherState::Stop;
-                                DispatchItem::Disconnect(Some(err))
+                                DispatchItem::Disconnect(Some(io_error(err)))
                             } else {
                                 slf.st = DispatcherState::Processing;
                                 DispatchItem::WBackPressureDisabled
@@ -427,12 +486,13 @@ where
     }
 }
 
-impl<S, U> DispatcherInner<S, U>
+impl<S, U, E> DispatcherInner<S, U, E>
 where
-    S: Service<DispatchItem<U>, Response = Option<Response<U>>> + 'static,
+    S: Service<DispatchItem<U, E>, Response = Option<Response<U>>> + 'static,
     U: Decoder + Encoder + 'static,
+    E: From<IoError> + 'static,
 {
-    fn poll_service(&mut self, cx: &mut Context<'_>) -> Poll<PollService<U>> {
+    fn poll_service(&mut self, cx: &mut Context<'_>) -> Poll<PollService<U, E>> {
         match self.shared.service.poll_ready(cx) {
             Poll::Ready(Ok(_)) => {
                 // check for errors
@@ -473,6 +533,9 @@ where
                             "{}: Keep-alive error, stopping dispatcher during pause",
                             self.shared.io.tag()
                         );
+                        self.shared
+                            .io
+                            .set_disconnect_reason(DisconnectReason::KeepAlive);
                         self.st = DispatcherState::Stop;
                         Poll::Ready(PollService::Item(DispatchItem::KeepAliveTimeout))
                     }
@@ -491,7 +554,9 @@ where
                             err
                         );
                         self.st = DispatcherState::Stop;
-                        Poll::Ready(PollService::Item(DispatchItem::Disconnect(err)))
+                        Poll::Ready(PollService::Item(DispatchItem::Disconnect(
+                            err.map(io_error),
+                        )))
                     }
                     IoStatusUpdate::WriteBackpressure => Poll::Pending,
                 }
@@ -543,7 +608,59 @@ where
         }
     }
 
-    fn handle_timeout(&mut self) -> Result<(), DispatchItem<U>> {
+    /// Check decoded frames rate, returns false if connection must be closed
+    fn check_frame_rate(&mut self) -> bool {
+        if let Some((max, action)) = self.cfg.frame_rate_limit() {
+            let now = now();
+            if now.duration_since(self.frames_start) >= time::Duration::from_secs(1) {
+                self.frames = 0;
+                self.frames_start = now;
+            }
+            self.frames += 1;
+
+            match action {
+                FrameRateAction::Close if self.frames > max => {
+                    log::trace!(
+                        "{}: Frame rate limit is exceeded, closing connection",
+                        self.shared.io.tag()
+                    );
+                    self.shared
+                        .io
+                        .set_disconnect_reason(DisconnectReason::RateLimit);
+                    return false;
+                }
+                FrameRateAction::Delay if self.frames >= max => {
+                    let elapsed = Millis::from(now.duration_since(self.frames_start));
+                    let delay = Millis(Millis::ONE_SEC.0.saturating_sub(elapsed.0));
+                    if !delay.is_zero() {
+                        log::trace!(
+                            "{}: Frame rate limit is reached, pause reading for {:?}",
+                            self.shared.io.tag(),
+                            delay
+                        );
+                        self.frames_delay = Some(Sleep::new(delay));
+                    }
+                }
+                _ => (),
+            }
+        }
+        true
+    }
+
+    fn poll_frames_delay(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        if let Some(ref delay) = self.frames_delay {
+            if delay.poll_elapsed(cx).is_pending() {
+                self.flags.remove(Flags::KA_TIMEOUT | Flags::READ_TIMEOUT);
+                self.shared.io.stop_timer();
+                self.shared.io.pause();
+                return Poll::Pending;
+            }
+            self.frames_delay = None;
+        }
+        Poll::Ready(())
+    }
+
+    fn handle_timeout(&mut self) -> Result<(), DispatchItem<U, E>> {
         // check read timer
         if self.flags.contains(Flags::READ_TIMEOUT) {
             if let Some((timeout, max, rate)) = self.cfg.frame_read_rate() {
@@ -583,10 +700,18 @@ where
             "{}: Keep-alive error, stopping dispatcher",
             self.shared.io.tag()
         );
+        self.shared
+            .io
+            .set_disconnect_reason(DisconnectReason::KeepAlive);
         Err(DispatchItem::KeepAliveTimeout)
     }
 }
 
+/// Classify io error and convert it to dispatch item error
+fn io_error<E: From<IoError>>(err: io::Error) -> E {
+    E::from(IoError::from(err))
+}
+
 #[cfg(test)]
 mod tests {
     use rand::Rng;
@@ -648,13 +773,13 @@ mod tests {
         }
     }
 
-    impl<S, U> Dispatcher<S, U>
+    impl<S, U, E> Dispatcher<S, U, E>
     where
-        S: Service<DispatchItem<U>, Response = Option<Response<U>>> + 'static,
+        S: Service<DispatchItem<U, E>, Response = Option<Response<U>>> + 'static,
         U: Decoder + Encoder + 'static,
     {
         /// Construct new `Dispatcher` instance
-        pub(crate) fn debug<T: IoStream, F: IntoService<S, DispatchItem<U>>>(
+        pub(crate) fn debug<T: IoStream, F: IntoService<S, DispatchItem<U, E>>>(
             io: T,
             codec: U,
             service: F,
@@ -666,7 +791,7 @@ mod tests {
         }
 
         /// Construct new `Dispatcher` instance
-        pub(crate) fn debug_cfg<T: IoStream, F: IntoService<S, DispatchItem<U>>>(
+        pub(crate) fn debug_cfg<T: IoStream, F: IntoService<S, DispatchItem<U, E>>>(
             io: T,
             codec: U,
             service: F,
@@ -692,6 +817,7 @@ mod tests {
                 error: Cell::new(None),
                 inflight: Cell::new(0),
                 service: Pipeline::new(service.into_service()),
+                _t: PhantomData,
             });
 
             (
@@ -702,6 +828,9 @@ mod tests {
                         read_remains: 0,
                         read_remains_prev: 0,
                         read_max_timeout: Seconds::ZERO,
+                        frames: 0,
+                        frames_start: now(),
+                        frames_delay: None,
                         pool,
                         shared,
                         cfg,
@@ -1203,6 +1332,87 @@ mod tests {
         assert_eq!(&data.lock().unwrap().borrow()[..], &[0, 1]);
     }
 
+    #[ntex::test]
+    async fn test_frame_rate_limit() {
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+
+        let data = Arc::new(Mutex::new(RefCell::new(Vec::new())));
+        let data2 = data.clone();
+
+        let cfg = DispatcherConfig::default()
+            .set_keepalive_timeout(Seconds::ZERO)
+            .set_frame_rate_limit(2, FrameRateAction::Delay)
+            .clone();
+
+        let (disp, state) = Dispatcher::debug_cfg(
+            server,
+            BCodec(2),
+            ntex_service::fn_service(move |msg: DispatchItem<BCodec>| {
+                let data = data2.clone();
+                async move {
+                    if let DispatchItem::Item(bytes) = msg {
+                        data.lock().unwrap().borrow_mut().push(0);
+                        return Ok::<_, ()>(Some(bytes.freeze()));
+                    }
+                    Ok(None)
+                }
+            }),
+            cfg,
+        );
+        spawn(async move {
+            let _ = disp.await;
+        });
+
+        client.write("112233");
+        sleep(Millis(250)).await;
+        assert_eq!(data.lock().unwrap().borrow().len(), 2);
+
+        sleep(Millis(1250)).await;
+        assert_eq!(data.lock().unwrap().borrow().len(), 3);
+        assert!(!state.flags().contains(Flags::IO_STOPPING));
+        assert!(!client.is_closed());
+    }
+
+    #[ntex::test]
+    async fn test_frame_rate_limit_close() {
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+
+        let data = Arc::new(Mutex::new(RefCell::new(Vec::new())));
+        let data2 = data.clone();
+
+        let cfg = DispatcherConfig::default()
+            .set_keepalive_timeout(Seconds::ZERO)
+            .set_frame_rate_limit(2, FrameRateAction::Close)
+            .clone();
+
+        let (disp, state) = Dispatcher::debug_cfg(
+            server,
+            BCodec(2),
+            ntex_service::fn_service(move |msg: DispatchItem<BCodec>| {
+                let data = data2.clone();
+                async move {
+                    if let DispatchItem::Item(bytes) = msg {
+                        data.lock().unwrap().borrow_mut().push(0);
+                        return Ok::<_, ()>(Some(bytes.freeze()));
+                    }
+                    Ok(None)
+                }
+            }),
+            cfg,
+        );
+        spawn(async move {
+            let _ = disp.await;
+        });
+
+        client.write("112233");
+        sleep(Millis(250)).await;
+        assert!(state.flags().contains(Flags::IO_STOPPING));
+        assert!(client.is_closed());
+        assert_eq!(&data.lock().unwrap().borrow()[..], &[0, 0]);
+    }
+
     #[ntex::test]
     async fn test_unhandled_data() {
         let handled = Arc::new(AtomicBool::new(false));
@@ -1235,4 +1445,31 @@ mod tests {
 
         assert!(handled.load(Relaxed));
     }
+
+    #[ntex::test]
+    async fn test_disconnect_io_error() {
+        let kind = Arc::new(Mutex::new(None));
+        let kind2 = kind.clone();
+
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+
+        let (disp, _) = Dispatcher::debug(
+            server,
+            BytesCodec,
+            ntex_service::fn_service(move |msg: DispatchItem<BytesCodec, IoError>| {
+                if let DispatchItem::Disconnect(Some(err)) = msg {
+                    *kind2.lock().unwrap() = Some(err.kind());
+                }
+                async move { Ok::<_, ()>(None) }
+            }),
+        );
+        spawn(async move {
+            let _ = disp.await;
+        });
+
+        client.read_error(io::Error::new(io::ErrorKind::ConnectionReset, "reset"));
+        sleep(Millis(50)).await;
+        assert_eq!(*kind.lock().unwrap(), Some(crate::IoErrorKind::PeerReset));
+    }
 }
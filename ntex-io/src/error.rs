@@ -0,0 +1,211 @@
+use std::{error, fmt, io};
+
+/// Io error kind
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum IoErrorKind {
+    /// Peer reset or closed connection unexpectedly
+    PeerReset,
+    /// Io operation timed out
+    Timeout,
+    /// Filter (tls, compression etc) failed to process data
+    Filter,
+    /// Memory or connection pool is exhausted
+    PoolExhausted,
+    /// Any other io error
+    Other,
+}
+
+impl IoErrorKind {
+    /// Classify io error
+    ///
+    /// Kind of the `io::Error` created from `IoError` is preserved.
+    pub fn classify(err: &io::Error) -> IoErrorKind {
+        if let Some(err) = err.get_ref().and_then(|e| e.downcast_ref::<IoError>()) {
+            return err.kind;
+        }
+
+        match err.kind() {
+            io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::UnexpectedEof => IoErrorKind::PeerReset,
+            io::ErrorKind::TimedOut => IoErrorKind::Timeout,
+            _ => IoErrorKind::Other,
+        }
+    }
+}
+
+impl fmt::Display for IoErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IoErrorKind::PeerReset => write!(f, "Peer reset"),
+            IoErrorKind::Timeout => write!(f, "Timeout"),
+            IoErrorKind::Filter => write!(f, "Filter error"),
+            IoErrorKind::PoolExhausted => write!(f, "Pool exhausted"),
+            IoErrorKind::Other => write!(f, "Io error"),
+        }
+    }
+}
+
+/// Io error
+///
+/// Wraps `std::io::Error` and classifies cause of the failure, so
+/// disconnects could be handled the same way on all platforms.
+///
+/// `IoError` converts into `io::Error` without losing its kind, converting
+/// such `io::Error` back restores original `IoError`.
+pub struct IoError {
+    kind: IoErrorKind,
+    err: io::Error,
+}
+
+impl IoError {
+    /// Create new io error
+    pub fn new(kind: IoErrorKind, err: io::Error) -> Self {
+        IoError { kind, err }
+    }
+
+    /// Create filter error
+    pub fn filter<E>(err: E) -> Self
+    where
+        E: Into<Box<dyn error::Error + Send + Sync>>,
+    {
+        let err = err.into();
+        match err.downcast::<io::Error>() {
+            // error is already classified
+            Ok(err) if err.get_ref().map(|e| e.is::<IoError>()).unwrap_or(false) => {
+                IoError::from(*err)
+            }
+            Ok(err) => IoError::new(IoErrorKind::Filter, *err),
+            Err(err) => IoError::new(IoErrorKind::Filter, io::Error::other(err)),
+        }
+    }
+
+    /// Create pool exhausted error
+    pub fn pool_exhausted() -> Self {
+        IoError::new(
+            IoErrorKind::PoolExhausted,
+            io::Error::new(io::ErrorKind::WouldBlock, "Pool is exhausted"),
+        )
+    }
+
+    /// Get error kind
+    pub fn kind(&self) -> IoErrorKind {
+        self.kind
+    }
+
+    /// Check if peer reset connection
+    pub fn is_peer_reset(&self) -> bool {
+        self.kind == IoErrorKind::PeerReset
+    }
+
+    /// Check if io operation timed out
+    pub fn is_timeout(&self) -> bool {
+        self.kind == IoErrorKind::Timeout
+    }
+
+    /// Check if error is caused by filter
+    pub fn is_filter(&self) -> bool {
+        self.kind == IoErrorKind::Filter
+    }
+
+    /// Check if memory or connection pool is exhausted
+    pub fn is_pool_exhausted(&self) -> bool {
+        self.kind == IoErrorKind::PoolExhausted
+    }
+
+    /// Get reference to underlying io error
+    pub fn get_ref(&self) -> &io::Error {
+        &self.err
+    }
+
+    /// Consume and return underlying io error
+    pub fn into_inner(self) -> io::Error {
+        self.err
+    }
+}
+
+impl From<io::Error> for IoError {
+    fn from(err: io::Error) -> Self {
+        // restore wrapped error
+        if err.get_ref().map(|e| e.is::<IoError>()).unwrap_or(false) {
+            return *err.into_inner().unwrap().downcast::<IoError>().unwrap();
+        }
+
+        IoError {
+            kind: IoErrorKind::classify(&err),
+            err,
+        }
+    }
+}
+
+impl From<IoError> for io::Error {
+    fn from(err: IoError) -> Self {
+        io::Error::new(err.err.kind(), err)
+    }
+}
+
+impl fmt::Debug for IoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IoError")
+            .field("kind", &self.kind)
+            .field("err", &self.err)
+            .finish()
+    }
+}
+
+impl fmt::Display for IoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind, self.err)
+    }
+}
+
+impl error::Error for IoError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_io_error() {
+        let err = IoError::from(io::Error::new(io::ErrorKind::ConnectionReset, "reset"));
+        assert!(err.is_peer_reset());
+        assert_eq!(err.get_ref().kind(), io::ErrorKind::ConnectionReset);
+        assert!(format!("{}", err).contains("Peer reset"));
+        assert!(format!("{:?}", err).contains("IoError"));
+        assert!(error::Error::source(&err).is_some());
+
+        let err = IoError::from(io::Error::new(io::ErrorKind::TimedOut, "timeout"));
+        assert!(err.is_timeout());
+        let err = IoError::from(io::Error::other("other"));
+        assert_eq!(err.kind(), IoErrorKind::Other);
+        assert_eq!(err.into_inner().kind(), io::ErrorKind::Other);
+
+        // kind survives conversion to io::Error
+        let err = IoError::filter(io::Error::new(io::ErrorKind::InvalidData, "tls"));
+        assert!(err.is_filter());
+        let err: io::Error = err.into();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = IoError::from(err);
+        assert!(err.is_filter());
+        assert_eq!(err.get_ref().kind(), io::ErrorKind::InvalidData);
+
+        let err = IoError::filter("bad record");
+        assert!(err.is_filter());
+        assert_eq!(err.get_ref().kind(), io::ErrorKind::Other);
+        let err: io::Error = IoError::filter("bad record").into();
+        assert_eq!(IoErrorKind::classify(&err), IoErrorKind::Filter);
+
+        let err = IoError::pool_exhausted();
+        assert!(err.is_pool_exhausted());
+        assert_eq!(format!("{}", err.kind()), "Pool exhausted");
+        let err: io::Error = err.into();
+        assert_eq!(IoErrorKind::classify(&err), IoErrorKind::PoolExhausted);
+    }
+}
@@ -3,7 +3,7 @@ use std::future::{poll_fn, Future};
 use std::task::{Context, Poll};
 use std::{fmt, hash, io, marker, mem, ops, pin::Pin, ptr, rc::Rc};
 
-use ntex_bytes::{PoolId, PoolRef};
+use ntex_bytes::{BytesVec, PoolId, PoolRef};
 use ntex_codec::{Decoder, Encoder};
 use ntex_util::{future::Either, task::LocalWaker, time::Seconds};
 
@@ -12,7 +12,9 @@ use crate::filter::{Base, Filter, Layer, NullFilter};
 use crate::seal::Sealed;
 use crate::tasks::{ReadContext, WriteContext};
 use crate::timer::TimerHandle;
-use crate::{Decoded, FilterLayer, Handle, IoStatusUpdate, IoStream, RecvError};
+use crate::{
+    Decoded, FilterLayer, Handle, IoErrorKind, IoStatusUpdate, IoStream, RecvError,
+};
 
 bitflags::bitflags! {
     #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
@@ -46,12 +48,27 @@ bitflags::bitflags! {
         const DSP_STOP            = 0b0001_0000_0000_0000;
         /// timeout occured
         const DSP_TIMEOUT         = 0b0010_0000_0000_0000;
+
+        /// io stream is detached from io object
+        const IO_DETACHED         = 0b1000_0000_0000_0000;
     }
 }
 
 /// Interface object to underlying io stream
 pub struct Io<F = Base>(pub(super) IoRef, FilterItem<F>);
 
+#[cfg(unix)]
+#[derive(Debug)]
+/// Raw parts of detached io stream
+pub struct IoRawParts {
+    /// File descriptor of io stream, it is in non-blocking mode
+    pub fd: std::os::fd::OwnedFd,
+    /// Received data that is not consumed yet
+    pub read_buf: BytesVec,
+    /// Pending data that is not written to io stream yet
+    pub write_buf: BytesVec,
+}
+
 #[derive(Clone)]
 pub struct IoRef(pub(super) Rc<IoState>);
 
@@ -70,6 +87,7 @@ pub(crate) struct IoState {
     pub(super) tag: Cell<&'static str>,
     #[allow(clippy::box_collection)]
     pub(super) on_disconnect: Cell<Option<Box<Vec<LocalWaker>>>>,
+    pub(super) disconnect_reason: Cell<Option<DisconnectReason>>,
 }
 
 const DEFAULT_TAG: &str = "IO";
@@ -111,7 +129,25 @@ impl IoState {
         }
     }
 
+    /// Record disconnect reason, first recorded reason is kept
+    pub(super) fn set_disconnect_reason(&self, reason: DisconnectReason) {
+        if self.disconnect_reason.get().is_none() {
+            self.disconnect_reason.set(Some(reason));
+        }
+    }
+
     pub(super) fn io_stopped(&self, err: Option<io::Error>) {
+        self.set_disconnect_reason(if let Some(ref err) = err {
+            DisconnectReason::Error(IoErrorKind::classify(err))
+        } else if self
+            .flags
+            .get()
+            .intersects(Flags::IO_STOPPING | Flags::IO_STOPPING_FILTERS)
+        {
+            DisconnectReason::Shutdown
+        } else {
+            DisconnectReason::PeerClosed
+        });
         if err.is_some() {
             self.error.set(err);
         }
@@ -176,6 +212,7 @@ impl fmt::Debug for IoState {
             .field("disconnect_timeout", &self.disconnect_timeout)
             .field("timeout", &self.timeout)
             .field("error", &err)
+            .field("disconnect_reason", &self.disconnect_reason)
             .field("buffer", &self.buffer)
             .finish();
         self.error.set(err);
@@ -206,6 +243,7 @@ impl Io {
             handle: Cell::new(None),
             timeout: Cell::new(TimerHandle::default()),
             on_disconnect: Cell::new(None),
+            disconnect_reason: Cell::new(None),
             tag: Cell::new(DEFAULT_TAG),
         });
 
@@ -226,6 +264,80 @@ impl Io {
     }
 }
 
+#[cfg(unix)]
+impl Io {
+    /// Detach underlying io stream from `Io` object.
+    ///
+    /// Returns file descriptor of io stream with all buffered data, so
+    /// connection could be passed to other event loop or ffi code, for
+    /// example after protocol negotiation. Io tasks get terminated
+    /// without shutting down io stream.
+    ///
+    /// Returns `Io` back if io is closed or io stream does not support
+    /// detaching. Filtered io streams could not be detached.
+    pub fn into_raw_parts(self) -> Result<IoRawParts, Self> {
+        let st = &self.0 .0;
+        if st
+            .flags
+            .get()
+            .intersects(Flags::IO_STOPPED | Flags::IO_STOPPING | Flags::IO_STOPPING_FILTERS)
+        {
+            return Err(self);
+        }
+
+        let fd = if let Some(hnd) = st.handle.take() {
+            let res = hnd.try_clone_fd();
+            st.handle.set(Some(hnd));
+            match res {
+                Some(Ok(fd)) => fd,
+                Some(Err(err)) => {
+                    log::trace!("{}: Cannot detach io stream: {:?}", self.tag(), err);
+                    return Err(self);
+                }
+                None => return Err(self),
+            }
+        } else {
+            return Err(self);
+        };
+
+        let (read_buf, write_buf) = st.buffer.take_buffers();
+        st.insert_flags(Flags::IO_DETACHED);
+        log::trace!("{}: Io stream is detached", self.tag());
+
+        Ok(IoRawParts {
+            fd,
+            read_buf: read_buf.unwrap_or_default(),
+            write_buf: write_buf.unwrap_or_default(),
+        })
+    }
+
+    /// Create `Io` instance from raw parts of detached io stream.
+    ///
+    /// `f` creates io object from file descriptor, for example with
+    /// `ntex::rt::from_tcp_stream()`. Buffered data is restored,
+    /// pending write data gets flushed to io stream.
+    pub fn from_raw_parts<T>(parts: IoRawParts, f: T) -> io::Result<Self>
+    where
+        T: FnOnce(std::os::fd::OwnedFd) -> io::Result<Io>,
+    {
+        let io = f(parts.fd)?;
+        let st = &io.0 .0;
+
+        if !parts.read_buf.is_empty() {
+            st.buffer
+                .with_read_destination(&io.0, |buf| buf.extend_from_slice(&parts.read_buf));
+            st.insert_flags(Flags::RD_READY);
+            st.dispatch_task.wake();
+        }
+        if !parts.write_buf.is_empty() {
+            st.buffer
+                .with_write_source(&io.0, |buf| buf.extend_from_slice(&parts.write_buf));
+            st.write_task.wake();
+        }
+        Ok(io)
+    }
+}
+
 impl<F> Io<F> {
     #[inline]
     /// Set memory pool
@@ -263,6 +375,7 @@ impl<F> Io<F> {
             handle: Cell::new(None),
             timeout: Cell::new(TimerHandle::default()),
             on_disconnect: Cell::new(None),
+            disconnect_reason: Cell::new(None),
             tag: Cell::new(DEFAULT_TAG),
         });
 
@@ -860,6 +973,22 @@ impl<F: Filter> FilterItem<F> {
     }
 }
 
+/// Reason of io stream disconnect
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DisconnectReason {
+    /// Peer closed connection
+    PeerClosed,
+    /// Keep-alive timeout expired
+    KeepAlive,
+    /// Io stream failed with error
+    Error(IoErrorKind),
+    /// Connection is closed locally
+    Shutdown,
+    /// Peer exceeded frame rate limit
+    RateLimit,
+}
+
 #[derive(Debug)]
 /// OnDisconnect future resolves when socket get disconnected
 #[must_use = "OnDisconnect do nothing unless polled"]
@@ -892,6 +1021,14 @@ impl OnDisconnect {
         Self { token, inner }
     }
 
+    #[inline]
+    /// Get disconnect reason
+    ///
+    /// Reason is available after future resolves.
+    pub fn reason(&self) -> Option<DisconnectReason> {
+        self.inner.disconnect_reason.get()
+    }
+
     #[inline]
     /// Check if connection is disconnected
     pub fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
@@ -1040,4 +1177,28 @@ mod tests {
 
         assert_eq!(p.get(), 1);
     }
+
+    #[cfg(unix)]
+    #[ntex::test]
+    async fn test_raw_parts() {
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+
+        // test io does not support detaching
+        let io = Io::new(server).into_raw_parts().unwrap_err();
+        assert!(!io.flags().contains(Flags::IO_DETACHED));
+
+        let (sock, _) = std::os::unix::net::UnixStream::pair().unwrap();
+        let parts = IoRawParts {
+            fd: sock.into(),
+            read_buf: BytesVec::copy_from_slice(BIN),
+            write_buf: BytesVec::copy_from_slice(b"test"),
+        };
+        let io = Io::from_raw_parts(parts, |_| Ok(io)).unwrap();
+
+        let msg = io.recv(&BytesCodec).await.unwrap().unwrap();
+        assert_eq!(msg, Bytes::from_static(BIN));
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"test"));
+    }
 }
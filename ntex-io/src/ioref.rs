@@ -4,7 +4,10 @@ use ntex_bytes::{BytesVec, PoolRef};
 use ntex_codec::{Decoder, Encoder};
 use ntex_util::time::Seconds;
 
-use super::{io::Flags, timer, types, Decoded, Filter, IoRef, OnDisconnect, WriteBuf};
+use super::{
+    io::Flags, timer, types, Decoded, DisconnectReason, Filter, IoError, IoRef,
+    OnDisconnect, WriteBuf,
+};
 
 impl IoRef {
     #[inline]
@@ -47,6 +50,12 @@ impl IoRef {
         self.0.dispatch_task.wake();
     }
 
+    #[inline]
+    /// Notify dispatcher about timeout
+    pub fn notify_timeout(&self) {
+        self.0.notify_timeout()
+    }
+
     #[inline]
     /// Gracefully close connection
     ///
@@ -63,6 +72,7 @@ impl IoRef {
     /// without any graceful period.
     pub fn force_close(&self) {
         log::trace!("{}: Force close io stream object", self.tag());
+        self.0.set_disconnect_reason(DisconnectReason::Shutdown);
         self.0.insert_flags(
             Flags::DSP_STOP
                 | Flags::IO_STOPPED
@@ -72,6 +82,7 @@ impl IoRef {
         self.0.read_task.wake();
         self.0.write_task.wake();
         self.0.dispatch_task.wake();
+        self.0.notify_disconnect();
     }
 
     #[inline]
@@ -189,7 +200,8 @@ impl IoRef {
         self.0
             .filter
             .get()
-            .process_write_buf(self, &self.0.buffer, 0)?;
+            .process_write_buf(self, &self.0.buffer, 0)
+            .map_err(IoError::filter)?;
         Ok(result)
     }
 
@@ -203,7 +215,8 @@ impl IoRef {
         self.0
             .filter
             .get()
-            .process_write_buf(self, &self.0.buffer, 0)?;
+            .process_write_buf(self, &self.0.buffer, 0)
+            .map_err(IoError::filter)?;
         Ok(result)
     }
 
@@ -278,6 +291,23 @@ impl IoRef {
     pub fn on_disconnect(&self) -> OnDisconnect {
         OnDisconnect::new(self.0.clone())
     }
+
+    #[inline]
+    /// Get disconnect reason
+    ///
+    /// Reason is recorded when io stream stops.
+    pub fn disconnect_reason(&self) -> Option<DisconnectReason> {
+        self.0.disconnect_reason.get()
+    }
+
+    #[inline]
+    /// Record disconnect reason
+    ///
+    /// Dispatchers record protocol level reasons, like keep-alive timeout,
+    /// before closing io stream. Only first recorded reason is kept.
+    pub fn set_disconnect_reason(&self, reason: DisconnectReason) {
+        self.0.set_disconnect_reason(reason)
+    }
 }
 
 impl Eq for IoRef {}
@@ -315,7 +345,7 @@ mod tests {
     use ntex_util::time::{sleep, Millis};
 
     use super::*;
-    use crate::{testing::IoTest, FilterLayer, Io, ReadBuf};
+    use crate::{testing::IoTest, FilterLayer, Io, IoErrorKind, ReadBuf};
 
     const BIN: &[u8] = b"GET /test HTTP/1\r\n\r\n";
     const TEXT: &str = "GET /test HTTP/1\r\n\r\n";
@@ -422,9 +452,15 @@ mod tests {
             lazy(|cx| Pin::new(&mut waiter2).poll(cx)).await,
             Poll::Pending
         );
+        assert_eq!(waiter2.reason(), None);
         client.close().await;
         assert_eq!(waiter.await, ());
+        assert_eq!(waiter2.reason(), Some(DisconnectReason::PeerClosed));
         assert_eq!(waiter2.await, ());
+        assert_eq!(
+            state.disconnect_reason(),
+            Some(DisconnectReason::PeerClosed)
+        );
 
         let mut waiter = state.on_disconnect();
         assert_eq!(
@@ -441,6 +477,25 @@ mod tests {
         );
         client.read_error(io::Error::new(io::ErrorKind::Other, "err"));
         assert_eq!(waiter.await, ());
+        assert_eq!(
+            state.disconnect_reason(),
+            Some(DisconnectReason::Error(IoErrorKind::Other))
+        );
+
+        let (_client, server) = IoTest::create();
+        let state = Io::new(server);
+        let waiter = state.on_disconnect();
+        state.set_disconnect_reason(DisconnectReason::KeepAlive);
+        state.force_close();
+        assert_eq!(waiter.await, ());
+        assert_eq!(state.disconnect_reason(), Some(DisconnectReason::KeepAlive));
+
+        let (_client, server) = IoTest::create();
+        let state = Io::new(server);
+        let waiter = state.on_disconnect();
+        state.force_close();
+        assert_eq!(waiter.await, ());
+        assert_eq!(state.disconnect_reason(), Some(DisconnectReason::Shutdown));
     }
 
     #[derive(Debug)]
@@ -1,15 +1,14 @@
 //! Utilities for abstructing io streams
 #![deny(rust_2018_idioms, unreachable_pub, missing_debug_implementations)]
 
-use std::{
-    any::Any, any::TypeId, fmt, io as sio, io::Error as IoError, task::Context, task::Poll,
-};
+use std::{any::Any, any::TypeId, fmt, io as sio, task::Context, task::Poll};
 
 pub mod testing;
 pub mod types;
 
 mod buf;
 mod dispatcher;
+mod error;
 mod filter;
 mod framed;
 mod io;
@@ -23,10 +22,13 @@ use ntex_codec::{Decoder, Encoder};
 use ntex_util::time::Millis;
 
 pub use self::buf::{ReadBuf, WriteBuf};
-pub use self::dispatcher::{Dispatcher, DispatcherConfig};
+pub use self::dispatcher::{Dispatcher, DispatcherConfig, FrameRateAction};
+pub use self::error::{IoError, IoErrorKind};
 pub use self::filter::{Base, Filter, Layer};
 pub use self::framed::Framed;
-pub use self::io::{Io, IoRef, OnDisconnect};
+#[cfg(unix)]
+pub use self::io::IoRawParts;
+pub use self::io::{DisconnectReason, Io, IoRef, OnDisconnect};
 pub use self::seal::{IoBoxed, Sealed};
 pub use self::tasks::{ReadContext, WriteContext};
 pub use self::timer::TimerHandle;
@@ -97,6 +99,16 @@ pub trait IoStream {
 
 pub trait Handle {
     fn query(&self, id: TypeId) -> Option<Box<dyn Any>>;
+
+    #[cfg(unix)]
+    #[inline]
+    /// Duplicate file descriptor of underlying io stream
+    ///
+    /// Used by `Io::into_raw_parts()`. Io tasks are terminated right after,
+    /// io stream must not be shutdown if io is detached.
+    fn try_clone_fd(&self) -> Option<sio::Result<std::os::fd::OwnedFd>> {
+        None
+    }
 }
 
 /// Io status
@@ -128,7 +140,11 @@ pub enum RecvError<U: Decoder> {
 }
 
 /// Dispatcher item
-pub enum DispatchItem<U: Encoder + Decoder> {
+///
+/// `E` is the type of the disconnect error. Dispatcher classifies io errors
+/// as `IoError` and converts them to `E`, use `DispatchItem<U, IoError>` to
+/// branch on disconnect causes.
+pub enum DispatchItem<U: Encoder + Decoder, E = sio::Error> {
     Item(<U as Decoder>::Item),
     /// Write back-pressure enabled
     WBackPressureEnabled,
@@ -143,13 +159,14 @@ pub enum DispatchItem<U: Encoder + Decoder> {
     /// Encoder parse error
     EncoderError(<U as Encoder>::Error),
     /// Socket is disconnected
-    Disconnect(Option<IoError>),
+    Disconnect(Option<E>),
 }
 
-impl<U> fmt::Debug for DispatchItem<U>
+impl<U, E> fmt::Debug for DispatchItem<U, E>
 where
     U: Encoder + Decoder,
     <U as Decoder>::Item: fmt::Debug,
+    E: fmt::Debug,
 {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
@@ -197,6 +214,10 @@ mod tests {
         assert!(format!("{:?}", err).contains("DispatchItem::Decoder"));
         let err = T::Disconnect(Some(io::Error::new(io::ErrorKind::Other, "err")));
         assert!(format!("{:?}", err).contains("DispatchItem::Disconnect"));
+        let err = DispatchItem::<BytesCodec, IoError>::Disconnect(Some(
+            io::Error::new(io::ErrorKind::ConnectionReset, "err").into(),
+        ));
+        assert!(format!("{:?}", err).contains("PeerReset"));
 
         assert!(format!("{:?}", T::WBackPressureEnabled)
             .contains("DispatchItem::WBackPressureEnabled"));
@@ -2,7 +2,7 @@ use std::{io, task::Context, task::Poll};
 
 use ntex_bytes::{BytesVec, PoolRef};
 
-use super::{io::Flags, IoRef, ReadStatus, WriteStatus};
+use super::{io::Flags, IoError, IoRef, ReadStatus, WriteStatus};
 
 #[derive(Debug)]
 /// Context for io read task
@@ -100,7 +100,7 @@ impl ReadContext {
                 })
                 .map_err(|err| {
                     inner.dispatch_task.wake();
-                    inner.io_stopped(Some(err));
+                    inner.io_stopped(Some(IoError::filter(err).into()));
                     inner.insert_flags(Flags::RD_READY);
                 });
         }
@@ -188,6 +188,14 @@ impl WriteContext {
         result
     }
 
+    #[inline]
+    /// Check if io stream is detached with `Io::into_raw_parts()`
+    ///
+    /// Detached io stream must not be shutdown on termination.
+    pub fn is_detached(&self) -> bool {
+        self.0.flags().contains(Flags::IO_DETACHED)
+    }
+
     #[inline]
     /// Indicate that write io task is stopped
     pub fn close(&self, err: Option<io::Error>) {
@@ -217,11 +225,11 @@ fn shutdown_filters(io: &IoRef) {
                 }
             }
             Err(err) => {
-                st.io_stopped(Some(err));
+                st.io_stopped(Some(IoError::filter(err).into()));
             }
         }
         if let Err(err) = filter.process_write_buf(io, &st.buffer, 0) {
-            st.io_stopped(Some(err));
+            st.io_stopped(Some(IoError::filter(err).into()));
         }
     }
 }
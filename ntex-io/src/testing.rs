@@ -67,6 +67,9 @@ struct Channel {
     waker: AtomicWaker,
     read: IoTestState,
     write: IoTestState,
+    latency: Millis,
+    write_chunk: usize,
+    write_fail: Option<(usize, io::Error)>,
 }
 
 unsafe impl Sync for Channel {}
@@ -93,6 +96,23 @@ enum IoTestState {
     Err(io::Error),
 }
 
+/// Scripted io step, see [`IoTest::play`]
+#[derive(Debug)]
+pub enum IoTestStep {
+    /// Send data to remote side
+    Send(Bytes),
+    /// Wait until remote side writes expected data
+    Expect(Bytes),
+    /// Wait before next step
+    Delay(Millis),
+    /// Fail remote side read with error
+    ReadError(io::Error),
+    /// Fail remote side write with error
+    WriteError(io::Error),
+    /// Close remote side
+    Close,
+}
+
 impl IoTest {
     /// Create a two interconnected streams
     pub fn create() -> (IoTest, IoTest) {
@@ -155,6 +175,27 @@ impl IoTest {
         self.remote.lock().unwrap().borrow().waker.wake();
     }
 
+    /// Fail write on remote side after `size` bytes are written
+    pub fn write_error_after(&self, size: usize, err: io::Error) {
+        self.local.lock().unwrap().borrow_mut().write_fail = Some((size, err));
+        self.remote.lock().unwrap().borrow().waker.wake();
+    }
+
+    /// Limit size of single write on remote side
+    ///
+    /// Remote side writes data in chunks of `size` bytes, so partial
+    /// writes could be tested. Zero size disables limit.
+    pub fn write_chunk(&self, size: usize) {
+        self.local.lock().unwrap().borrow_mut().write_chunk = size;
+    }
+
+    /// Set read latency on remote side
+    ///
+    /// Remote side reads available data only after specified delay.
+    pub fn read_latency(&self, latency: Millis) {
+        self.remote.lock().unwrap().borrow_mut().latency = latency;
+    }
+
     /// Access read buffer.
     pub fn local_buffer<F, R>(&self, f: F) -> R
     where
@@ -208,6 +249,63 @@ impl IoTest {
         self.local.lock().unwrap().borrow_mut().buf.split().freeze()
     }
 
+    /// Wait until remote side writes expected data
+    ///
+    /// ## Panics
+    ///
+    /// Panics if written data does not match expected data or
+    /// if remote side is closed.
+    pub async fn expect<T: AsRef<[u8]>>(&self, data: T) {
+        let data = data.as_ref();
+        let mut buf = Vec::new();
+        while buf.len() < data.len() {
+            let chunk = self.read().await.unwrap();
+            if chunk.is_empty() {
+                panic!(
+                    "Remote side is closed, expected {:?}, got {:?}",
+                    Bytes::copy_from_slice(data),
+                    Bytes::from(buf)
+                );
+            }
+            buf.extend_from_slice(&chunk);
+        }
+        if buf.len() > data.len() {
+            let tail = buf.split_off(data.len());
+            self.local
+                .lock()
+                .unwrap()
+                .borrow_mut()
+                .buf
+                .extend_from_slice(&tail);
+        }
+        assert_eq!(
+            Bytes::from(buf),
+            Bytes::copy_from_slice(data),
+            "Unexpected written data"
+        );
+    }
+
+    /// Run scripted io steps
+    ///
+    /// Steps are executed in order, so protocol exchange could be
+    /// described as a sequence of sent and expected chunks with delays
+    /// and failures in between.
+    pub async fn play<I>(&self, steps: I)
+    where
+        I: IntoIterator<Item = IoTestStep>,
+    {
+        for step in steps {
+            match step {
+                IoTestStep::Send(data) => self.write(data),
+                IoTestStep::Expect(data) => self.expect(data).await,
+                IoTestStep::Delay(delay) => sleep(delay).await,
+                IoTestStep::ReadError(err) => self.read_error(err),
+                IoTestStep::WriteError(err) => self.write_error(err),
+                IoTestStep::Close => self.close().await,
+            }
+        }
+    }
+
     /// Read data, if data is not available wait for it
     pub async fn read(&self) -> Result<Bytes, io::Error> {
         if self.local.lock().unwrap().borrow().buf.is_empty() {
@@ -276,7 +374,18 @@ impl IoTest {
 
         match mem::take(&mut ch.write) {
             IoTestState::Ok => {
-                let cap = cmp::min(buf.len(), ch.buf_cap);
+                let mut cap = cmp::min(buf.len(), ch.buf_cap);
+                if ch.write_chunk > 0 {
+                    cap = cmp::min(cap, ch.write_chunk);
+                }
+                if let Some((size, _)) = ch.write_fail {
+                    if size == 0 {
+                        let (_, err) = ch.write_fail.take().unwrap();
+                        return Poll::Ready(Err(err));
+                    }
+                    cap = cmp::min(cap, size);
+                    ch.write_fail.as_mut().unwrap().0 -= cap;
+                }
                 if cap > 0 {
                     ch.buf.extend(&buf[..cap]);
                     ch.buf_cap -= cap;
@@ -359,6 +468,7 @@ impl IoStream for IoTest {
         let _ = ntex_util::spawn(ReadTask {
             io: io.clone(),
             state: read,
+            delay: None,
         });
         let _ = ntex_util::spawn(WriteTask {
             io: io.clone(),
@@ -385,13 +495,32 @@ impl Handle for Rc<IoTest> {
 struct ReadTask {
     io: Rc<IoTest>,
     state: ReadContext,
+    delay: Option<Sleep>,
 }
 
 impl Future for ReadTask {
     type Output = ();
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let this = self.as_ref();
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.as_mut().get_mut();
+
+        // artificial read latency
+        let latency = {
+            let guard = this.io.local.lock().unwrap();
+            let ch = guard.borrow();
+            if ch.buf.is_empty() {
+                Millis::ZERO
+            } else {
+                ch.latency
+            }
+        };
+        if !latency.is_zero() {
+            let delay = this.delay.get_or_insert_with(|| sleep(latency));
+            if delay.poll_elapsed(cx).is_pending() {
+                return Poll::Pending;
+            }
+            this.delay = None;
+        }
 
         this.state.with_buf(|buf, hw, lw| {
             match this.state.poll_ready(cx) {
@@ -686,4 +815,68 @@ mod tests {
         let res = lazy(|cx| server2.poll_write_buf(cx, b"123")).await;
         assert!(res.is_pending());
     }
+
+    #[ntex::test]
+    async fn faults() {
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+
+        // partial writes
+        client.write_chunk(2);
+        let res = lazy(|cx| server.poll_write_buf(cx, b"12345")).await;
+        assert!(matches!(res, Poll::Ready(Ok(2))));
+        client.write_chunk(0);
+        let res = lazy(|cx| server.poll_write_buf(cx, b"345")).await;
+        assert!(matches!(res, Poll::Ready(Ok(3))));
+        client.expect(b"123").await;
+        client.expect(b"45").await;
+
+        // mid-stream write error
+        client.write_error_after(2, io::Error::other("err"));
+        let res = lazy(|cx| server.poll_write_buf(cx, b"678")).await;
+        assert!(matches!(res, Poll::Ready(Ok(2))));
+        let res = lazy(|cx| server.poll_write_buf(cx, b"8")).await;
+        assert!(matches!(res, Poll::Ready(Err(_))));
+        assert_eq!(client.read_any(), Bytes::from_static(b"67"));
+
+        // scripted steps
+        let srv = server.clone();
+        let _ = ntex_util::spawn(async move {
+            let data = srv.read().await.unwrap();
+            assert_eq!(data, Bytes::from_static(b"ping"));
+            let _ = lazy(|cx| srv.poll_write_buf(cx, b"pong")).await;
+        });
+        client
+            .play([
+                IoTestStep::Delay(Millis(10)),
+                IoTestStep::Send(Bytes::from_static(b"ping")),
+                IoTestStep::Expect(Bytes::from_static(b"pong")),
+                IoTestStep::ReadError(io::Error::other("err")),
+            ])
+            .await;
+        let mut buf = BytesVec::new();
+        let res = lazy(|cx| server.poll_read_buf(cx, &mut buf)).await;
+        assert!(matches!(res, Poll::Ready(Err(_))));
+    }
+
+    #[ntex::test]
+    async fn read_latency() {
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+        let io = crate::Io::new(client);
+
+        server.write(b"1");
+        let start = std::time::Instant::now();
+        io.read_ready().await.unwrap();
+        assert!(start.elapsed() < std::time::Duration::from_millis(50));
+        io.with_read_buf(|buf| buf.clear());
+
+        // read is delayed
+        server.read_latency(Millis(100));
+        server.write(b"2");
+        let start = std::time::Instant::now();
+        io.read_ready().await.unwrap();
+        assert!(start.elapsed() >= std::time::Duration::from_millis(90));
+        assert_eq!(io.with_read_buf(|buf| buf.split().freeze()), b"2"[..]);
+    }
 }
@@ -95,6 +95,11 @@ pub(crate) fn update(hnd: TimerHandle, timeout: Seconds, io: &IoRef) -> TimerHan
     })
 }
 
+/// Register io for timeout notification
+///
+/// Timers are stored in per-second buckets that are shared by all io
+/// objects of the worker, so keep-alive timers of idle connections with
+/// the same deadline share one bucket and do not allocate own timers.
 pub(crate) fn register(timeout: Seconds, io: &IoRef) -> TimerHandle {
     TIMER.with(|timer| {
         // setup current delta
@@ -182,3 +187,33 @@ impl Drop for TimerGuard {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testing::IoTest, Io};
+
+    #[ntex::test]
+    async fn test_shared_buckets() {
+        let ios: Vec<_> = (0..16).map(|_| Io::new(IoTest::create().0)).collect();
+
+        // keep-alive timers started within the same second share one bucket
+        let handles: Vec<_> = ios.iter().map(|io| io.start_timer(Seconds(5))).collect();
+        assert!(handles.iter().all(|hnd| *hnd == handles[0]));
+        TIMER.with(|timer| {
+            let inner = timer.storage.borrow();
+            assert_eq!(inner.notifications.len(), 1);
+            assert_eq!(inner.notifications[&handles[0].0].len(), 16);
+        });
+
+        // restarting timer with the same deadline does not re-register io
+        assert_eq!(ios[0].start_timer(Seconds(5)), handles[0]);
+
+        // stopped timers are removed from the bucket
+        ios.iter().for_each(|io| io.stop_timer());
+        TIMER.with(|timer| {
+            let inner = timer.storage.borrow();
+            assert!(inner.notifications[&handles[0].0].is_empty());
+        });
+    }
+}
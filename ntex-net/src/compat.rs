@@ -1,7 +1,7 @@
 //! Utility for async runtime abstraction
 
 #[cfg(feature = "tokio")]
-pub use ntex_tokio::{from_tcp_stream, tcp_connect, tcp_connect_in};
+pub use ntex_tokio::{from_tcp_stream, tcp_connect, tcp_connect_bind_in, tcp_connect_in};
 
 #[cfg(all(unix, feature = "tokio"))]
 pub use ntex_tokio::{from_unix_stream, unix_connect, unix_connect_in};
@@ -36,6 +36,22 @@ pub use ntex_glommio::{from_tcp_stream, tcp_connect, tcp_connect_in};
 ))]
 pub use ntex_async_std::{from_unix_stream, unix_connect, unix_connect_in};
 
+#[cfg(not(feature = "tokio"))]
+/// Opens a TCP connection to a remote host from specified local address
+/// and use specified memory pool.
+///
+/// Local address binding is supported by tokio runtime only.
+pub async fn tcp_connect_bind_in(
+    _: std::net::SocketAddr,
+    _: std::net::IpAddr,
+    _: ntex_bytes::PoolRef,
+) -> std::io::Result<ntex_io::Io> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "local address binding is not supported by runtime",
+    ))
+}
+
 #[cfg(all(
     not(feature = "tokio"),
     not(feature = "async-std"),
@@ -0,0 +1,40 @@
+use std::{io, net::SocketAddr, rc::Rc, time::Duration};
+
+use super::ConnectError;
+
+pub(super) type OnConnectEvent = Rc<dyn Fn(&ConnectEvent<'_>)>;
+
+/// Connector diagnostic event
+///
+/// Events are emitted by [`Connector`](super::Connector) if event hook is
+/// set with `Connector::on_connect_event()`.
+#[derive(Debug)]
+pub enum ConnectEvent<'a> {
+    /// Host name is resolved
+    Resolved {
+        host: &'a str,
+        addrs: &'a [SocketAddr],
+        elapsed: Duration,
+    },
+    /// Host name resolution failed
+    ResolveFailed {
+        host: &'a str,
+        error: &'a ConnectError,
+        elapsed: Duration,
+    },
+    /// Connecting to address
+    Connecting { host: &'a str, addr: SocketAddr },
+    /// Connection is established
+    Connected {
+        host: &'a str,
+        addr: SocketAddr,
+        elapsed: Duration,
+    },
+    /// Connection to address failed
+    ConnectFailed {
+        host: &'a str,
+        addr: SocketAddr,
+        error: &'a io::Error,
+        elapsed: Duration,
+    },
+}
@@ -1,5 +1,5 @@
 use std::collections::{vec_deque, VecDeque};
-use std::{fmt, iter::FusedIterator, net::SocketAddr};
+use std::{fmt, iter::FusedIterator, net::IpAddr, net::SocketAddr};
 
 use ntex_util::future::Either;
 
@@ -57,6 +57,7 @@ pub struct Connect<T> {
     pub(super) req: T,
     pub(super) port: u16,
     pub(super) addr: Option<Either<SocketAddr, VecDeque<SocketAddr>>>,
+    pub(super) local_addr: Option<IpAddr>,
 }
 
 impl<T: Address> Connect<T> {
@@ -67,6 +68,7 @@ impl<T: Address> Connect<T> {
             req,
             port: port.unwrap_or(0),
             addr: None,
+            local_addr: None,
         }
     }
 
@@ -76,6 +78,7 @@ impl<T: Address> Connect<T> {
             req,
             port: 0,
             addr: Some(Either::Left(addr)),
+            local_addr: None,
         }
     }
 
@@ -109,6 +112,19 @@ impl<T: Address> Connect<T> {
         self
     }
 
+    /// Use local address for outgoing connection.
+    ///
+    /// Local address binding is supported by tokio runtime only.
+    pub fn set_local_addr(mut self, addr: Option<IpAddr>) -> Self {
+        self.local_addr = addr;
+        self
+    }
+
+    /// Local address of outgoing connection
+    pub fn local_addr(&self) -> Option<IpAddr> {
+        self.local_addr
+    }
+
     /// Host name
     pub fn host(&self) -> &str {
         self.req.host()
@@ -165,6 +181,7 @@ impl<T: Clone> Clone for Connect<T> {
             req: self.req.clone(),
             port: self.port,
             addr: self.addr.clone(),
+            local_addr: self.local_addr,
         }
     }
 }
@@ -245,7 +262,7 @@ impl ExactSizeIterator for ConnectTakeAddrsIter {}
 
 impl FusedIterator for ConnectTakeAddrsIter {}
 
-fn parse(host: &str) -> (&str, Option<u16>) {
+pub(super) fn parse(host: &str) -> (&str, Option<u16>) {
     let mut parts_iter = host.splitn(2, ':');
     if let Some(host) = parts_iter.next() {
         let port_str = parts_iter.next().unwrap_or("");
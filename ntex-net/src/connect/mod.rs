@@ -1,12 +1,18 @@
 //! Tcp connector service
 mod error;
+mod event;
 mod message;
+mod policy;
+mod pool;
 mod resolve;
 mod service;
 mod uri;
 
 pub use self::error::ConnectError;
+pub use self::event::ConnectEvent;
 pub use self::message::{Address, Connect};
+pub use self::policy::AddressPolicy;
+pub use self::pool::{ConnectionPool, PoolStats, PooledIo};
 pub use self::resolve::Resolver;
 pub use self::service::Connector;
 
@@ -0,0 +1,163 @@
+use std::collections::{hash_map::RandomState, VecDeque};
+use std::hash::{BuildHasher, Hasher};
+use std::{cell::Cell, cell::RefCell, net::SocketAddr};
+
+use ntex_util::HashMap;
+
+/// Address selection policy
+///
+/// Policy defines which address is used first if host name resolves
+/// to multiple addresses. If connection to selected address fails,
+/// connector tries remaining addresses.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum AddressPolicy {
+    /// Use addresses in resolution order
+    #[default]
+    First,
+    /// Rotate addresses for each connection
+    RoundRobin,
+    /// Start from random address
+    Random,
+    /// Use same address for each host while it is resolved
+    ///
+    /// Address is selected randomly on first connection and is kept
+    /// until it fails or disappears from resolution results. At most 1024
+    /// hosts are remembered.
+    Sticky,
+}
+
+/// Max number of remembered sticky hosts
+const MAX_STICKY: usize = 1024;
+
+pub(super) struct AddressSelector {
+    policy: AddressPolicy,
+    next: Cell<usize>,
+    sticky: RefCell<HashMap<(String, u16), SocketAddr>>,
+}
+
+impl AddressSelector {
+    pub(super) fn new(policy: AddressPolicy) -> Self {
+        AddressSelector {
+            policy,
+            next: Cell::new(0),
+            sticky: RefCell::new(HashMap::default()),
+        }
+    }
+
+    pub(super) fn policy(&self) -> AddressPolicy {
+        self.policy
+    }
+
+    /// Reorder addresses according to policy
+    pub(super) fn select(&self, host: &str, port: u16, addrs: &mut VecDeque<SocketAddr>) {
+        if addrs.len() < 2 {
+            return;
+        }
+
+        let idx = match self.policy {
+            AddressPolicy::First => 0,
+            AddressPolicy::RoundRobin => {
+                let idx = self.next.get();
+                self.next.set(idx.wrapping_add(1));
+                idx
+            }
+            AddressPolicy::Random => random(),
+            AddressPolicy::Sticky => {
+                let key = (host.to_string(), port);
+                let mut sticky = self.sticky.borrow_mut();
+                let idx = sticky
+                    .get(&key)
+                    .and_then(|addr| addrs.iter().position(|a| a == addr));
+                if idx.is_none() {
+                    // address disappeared from resolution results
+                    sticky.remove(&key);
+                }
+                idx.unwrap_or_else(random)
+            }
+        };
+        addrs.rotate_left(idx % addrs.len());
+    }
+
+    /// Remember connected address
+    pub(super) fn connected(&self, host: &str, port: u16, addr: SocketAddr) {
+        if self.policy == AddressPolicy::Sticky {
+            let key = (host.to_string(), port);
+            let mut sticky = self.sticky.borrow_mut();
+            if sticky.len() >= MAX_STICKY && !sticky.contains_key(&key) {
+                // evict arbitrary host
+                if let Some(k) = sticky.keys().next().cloned() {
+                    sticky.remove(&k);
+                }
+            }
+            sticky.insert(key, addr);
+        }
+    }
+}
+
+fn random() -> usize {
+    // hasher keys are randomized on each construction
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_usize(0);
+    hasher.finish() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addrs() -> VecDeque<SocketAddr> {
+        (1..4)
+            .map(|p| SocketAddr::from(([127, 0, 0, 1], p)))
+            .collect()
+    }
+
+    fn first(sel: &AddressSelector, host: &str) -> u16 {
+        let mut addrs = addrs();
+        sel.select(host, 80, &mut addrs);
+        assert_eq!(addrs.len(), 3);
+        addrs[0].port()
+    }
+
+    #[test]
+    fn test_policy() {
+        let sel = AddressSelector::new(AddressPolicy::First);
+        assert_eq!(first(&sel, "a"), 1);
+        assert_eq!(first(&sel, "a"), 1);
+
+        let sel = AddressSelector::new(AddressPolicy::RoundRobin);
+        assert_eq!(first(&sel, "a"), 1);
+        assert_eq!(first(&sel, "a"), 2);
+        assert_eq!(first(&sel, "b"), 3);
+        assert_eq!(first(&sel, "a"), 1);
+
+        let sel = AddressSelector::new(AddressPolicy::Random);
+        assert!((1..4).contains(&first(&sel, "a")));
+
+        let sel = AddressSelector::new(AddressPolicy::Sticky);
+        sel.connected("a", 80, SocketAddr::from(([127, 0, 0, 1], 2)));
+        sel.connected("b", 80, SocketAddr::from(([127, 0, 0, 1], 9)));
+        assert_eq!(first(&sel, "a"), 2);
+        assert_eq!(first(&sel, "a"), 2);
+        assert!((1..4).contains(&first(&sel, "b")));
+
+        let mut addrs = addrs();
+        sel.select("a", 443, &mut addrs);
+        assert!((1..4).contains(&addrs[0].port()));
+
+        // unresolved address is forgotten
+        assert!(!sel.sticky.borrow().contains_key(&("b".to_string(), 80)));
+    }
+
+    #[test]
+    fn test_sticky_eviction() {
+        let sel = AddressSelector::new(AddressPolicy::Sticky);
+        let addr = SocketAddr::from(([127, 0, 0, 1], 1));
+        for idx in 0..MAX_STICKY + 10 {
+            sel.connected(&format!("host{}", idx), 80, addr);
+        }
+        assert_eq!(sel.sticky.borrow().len(), MAX_STICKY);
+
+        let last = format!("host{}", MAX_STICKY + 9);
+        assert!(sel.sticky.borrow().contains_key(&(last, 80)));
+    }
+}
@@ -0,0 +1,468 @@
+use std::time::{Duration, Instant};
+use std::{cell::Cell, cell::RefCell, collections::VecDeque, fmt, ops, rc::Rc, rc::Weak};
+
+use ntex_io::{Base, Io};
+use ntex_service::{Pipeline, Service, ServiceCtx, ServiceFactory};
+use ntex_util::{time::now, time::sleep, time::Millis, HashMap};
+
+use super::{message::parse, Address, Connect};
+
+type HealthCheck<F> = Rc<dyn Fn(&Io<F>) -> bool>;
+type Connections<F> = HashMap<String, VecDeque<(Io<F>, Instant)>>;
+
+/// Pool of established connections.
+///
+/// Pool keeps idle connections per authority (host and port). Connection
+/// returns to the pool when [`PooledIo`] is dropped, if connection is closed
+/// or has unread data it is not returned. Idle connections are closed
+/// after idle timeout.
+///
+/// Clones of the pool share connections, configuration is not shared,
+/// each clone uses configuration it was built with.
+///
+/// ```rust
+/// use ntex::connect::{ConnectError, ConnectionPool, Connector};
+/// use ntex::time::Seconds;
+///
+/// async fn query(pool: &ConnectionPool<Connector<String>>) -> Result<(), ConnectError> {
+///     let io = pool.acquire("localhost:6379".to_string()).await?;
+///     // use connection, it returns to the pool on drop
+///     Ok(())
+/// }
+///
+/// let pool: ConnectionPool<_> = ConnectionPool::new(Connector::<String>::new())
+///     .max_idle(8)
+///     .idle_timeout(Seconds(30));
+/// ```
+pub struct ConnectionPool<S, F: 'static = Base> {
+    connector: Pipeline<S>,
+    config: Rc<Config<F>>,
+    inner: Rc<Inner<F>>,
+}
+
+struct Config<F> {
+    max_idle: usize,
+    idle_timeout: Millis,
+    health_check: Option<HealthCheck<F>>,
+}
+
+struct Inner<F: 'static> {
+    idle: RefCell<Connections<F>>,
+    reaper: Cell<bool>,
+    active: Cell<usize>,
+    hits: Cell<u64>,
+    misses: Cell<u64>,
+    evicted: Cell<u64>,
+}
+
+/// Connection pool statistics
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Number of idle connections
+    pub idle: usize,
+    /// Number of connections in use
+    pub active: usize,
+    /// Number of checkouts served by idle connections
+    pub hits: u64,
+    /// Number of checkouts that required new connection
+    pub misses: u64,
+    /// Number of idle connections dropped because of timeout or failed health check
+    pub evicted: u64,
+}
+
+impl<S, F> ConnectionPool<S, F> {
+    /// Construct new connection pool
+    pub fn new(connector: S) -> Self {
+        ConnectionPool {
+            connector: Pipeline::new(connector),
+            config: Rc::new(Config {
+                max_idle: 16,
+                idle_timeout: Millis(15_000),
+                health_check: None,
+            }),
+            inner: Rc::new(Inner {
+                idle: RefCell::new(HashMap::default()),
+                reaper: Cell::new(false),
+                active: Cell::new(0),
+                hits: Cell::new(0),
+                misses: Cell::new(0),
+                evicted: Cell::new(0),
+            }),
+        }
+    }
+
+    /// Set max number of idle connections per authority.
+    ///
+    /// By default max idle is set to 16.
+    pub fn max_idle(mut self, max: usize) -> Self {
+        Rc::make_mut(&mut self.config).max_idle = max;
+        self
+    }
+
+    /// Set idle timeout for connections.
+    ///
+    /// Idle connections older than timeout are closed.
+    /// By default idle timeout is set to 15 seconds.
+    pub fn idle_timeout<T: Into<Millis>>(mut self, timeout: T) -> Self {
+        Rc::make_mut(&mut self.config).idle_timeout = timeout.into();
+        self
+    }
+
+    /// Set health check for idle connections.
+    ///
+    /// Health check runs on checkout in addition to default checks,
+    /// connection is dropped if check returns `false`.
+    pub fn health_check<C>(mut self, check: C) -> Self
+    where
+        C: Fn(&Io<F>) -> bool + 'static,
+    {
+        Rc::make_mut(&mut self.config).health_check = Some(Rc::new(check));
+        self
+    }
+
+    /// Get pool statistics
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            idle: self.inner.idle.borrow().values().map(|q| q.len()).sum(),
+            active: self.inner.active.get(),
+            hits: self.inner.hits.get(),
+            misses: self.inner.misses.get(),
+            evicted: self.inner.evicted.get(),
+        }
+    }
+
+    /// Close all idle connections
+    pub fn clear(&self) {
+        self.inner.idle.borrow_mut().clear();
+    }
+}
+
+impl<S, F> ConnectionPool<S, F> {
+    /// Get idle connection or establish new one
+    pub async fn acquire<T, U>(&self, message: U) -> Result<PooledIo<F>, S::Error>
+    where
+        T: Address,
+        Connect<T>: From<U>,
+        S: Service<Connect<T>, Response = Io<F>>,
+    {
+        self.checkout(message.into(), |req| self.connector.call(req))
+            .await
+    }
+
+    async fn checkout<T, R, Fut, E>(&self, req: Connect<T>, f: R) -> Result<PooledIo<F>, E>
+    where
+        T: Address,
+        R: FnOnce(Connect<T>) -> Fut,
+        Fut: std::future::Future<Output = Result<Io<F>, E>>,
+    {
+        let key = authority(&req);
+        let io = if let Some(io) = self.inner.get(&key, &self.config) {
+            self.inner.hits.set(self.inner.hits.get() + 1);
+            io
+        } else {
+            self.inner.misses.set(self.inner.misses.get() + 1);
+            f(req).await?
+        };
+        self.inner.active.set(self.inner.active.get() + 1);
+
+        Ok(PooledIo {
+            key,
+            io: Some(io),
+            config: self.config.clone(),
+            pool: self.inner.clone(),
+        })
+    }
+}
+
+impl<F> Inner<F> {
+    fn get(&self, key: &str, cfg: &Config<F>) -> Option<Io<F>> {
+        let mut idle = self.idle.borrow_mut();
+        let connections = idle.get_mut(key)?;
+        let now = now();
+
+        while let Some((io, expires)) = connections.pop_back() {
+            if now >= expires
+                || io.is_closed()
+                || !io.with_read_buf(|buf| buf.is_empty())
+                || !cfg
+                    .health_check
+                    .as_ref()
+                    .map(|check| check(&io))
+                    .unwrap_or(true)
+            {
+                log::trace!("{}: Drop idle connection for {:?}", io.tag(), key);
+                self.evicted.set(self.evicted.get() + 1);
+                continue;
+            }
+            return Some(io);
+        }
+        None
+    }
+
+    fn release(self: &Rc<Self>, key: String, io: Io<F>, cfg: &Config<F>) {
+        let mut idle = self.idle.borrow_mut();
+        let connections = idle.entry(key).or_default();
+        if connections.len() < cfg.max_idle {
+            let timeout: Duration = cfg.idle_timeout.into();
+            connections.push_back((io, now() + timeout));
+
+            if !self.reaper.get() {
+                self.reaper.set(true);
+                ntex_rt::spawn(reaper(Rc::downgrade(self)));
+            }
+        }
+    }
+
+    /// Close expired idle connections, returns nearest expiration time
+    fn reap(&self) -> Option<Instant> {
+        let now = now();
+        let mut next: Option<Instant> = None;
+        self.idle.borrow_mut().retain(|key, connections| {
+            connections.retain(|(io, expires)| {
+                if now >= *expires {
+                    log::trace!("{}: Idle connection expired for {:?}", io.tag(), key);
+                    self.evicted.set(self.evicted.get() + 1);
+                    false
+                } else {
+                    next = Some(next.map_or(*expires, |next| next.min(*expires)));
+                    true
+                }
+            });
+            !connections.is_empty()
+        });
+        next
+    }
+}
+
+/// Close idle connections after idle timeout, stops if pool is empty
+async fn reaper<F>(inner: Weak<Inner<F>>) {
+    loop {
+        let delay = if let Some(inner) = inner.upgrade() {
+            if let Some(next) = inner.reap() {
+                next.saturating_duration_since(now())
+            } else {
+                inner.reaper.set(false);
+                return;
+            }
+        } else {
+            return;
+        };
+        sleep(Millis::from(delay) + Millis(1)).await;
+    }
+}
+
+impl<F> Clone for Config<F> {
+    fn clone(&self) -> Self {
+        Config {
+            max_idle: self.max_idle,
+            idle_timeout: self.idle_timeout,
+            health_check: self.health_check.clone(),
+        }
+    }
+}
+
+impl<S, F> Clone for ConnectionPool<S, F> {
+    fn clone(&self) -> Self {
+        ConnectionPool {
+            connector: self.connector.clone(),
+            config: self.config.clone(),
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<S: fmt::Debug, F> fmt::Debug for ConnectionPool<S, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectionPool")
+            .field("connector", self.connector.get_ref())
+            .field("max_idle", &self.config.max_idle)
+            .field("idle_timeout", &self.config.idle_timeout)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+impl<T, S, F, C> ServiceFactory<Connect<T>, C> for ConnectionPool<S, F>
+where
+    T: Address,
+    S: Service<Connect<T>, Response = Io<F>>,
+{
+    type Response = PooledIo<F>;
+    type Error = S::Error;
+    type Service = ConnectionPool<S, F>;
+    type InitError = ();
+
+    async fn create(&self, _: C) -> Result<Self::Service, Self::InitError> {
+        Ok(self.clone())
+    }
+}
+
+impl<T, S, F> Service<Connect<T>> for ConnectionPool<S, F>
+where
+    T: Address,
+    S: Service<Connect<T>, Response = Io<F>>,
+{
+    type Response = PooledIo<F>;
+    type Error = S::Error;
+
+    ntex_service::forward_poll_ready!(connector);
+
+    async fn call(
+        &self,
+        req: Connect<T>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        self.checkout(req, |req| ctx.call(self.connector.get_ref(), req))
+            .await
+    }
+}
+
+/// Connection checked out from the pool
+///
+/// Connection returns to the pool on drop.
+pub struct PooledIo<F: 'static = Base> {
+    key: String,
+    io: Option<Io<F>>,
+    config: Rc<Config<F>>,
+    pool: Rc<Inner<F>>,
+}
+
+impl<F> PooledIo<F> {
+    /// Get authority of the connection
+    pub fn authority(&self) -> &str {
+        &self.key
+    }
+
+    /// Detach connection from the pool
+    pub fn into_inner(mut self) -> Io<F> {
+        self.io.take().unwrap()
+    }
+}
+
+impl<F> ops::Deref for PooledIo<F> {
+    type Target = Io<F>;
+
+    fn deref(&self) -> &Io<F> {
+        self.io.as_ref().unwrap()
+    }
+}
+
+impl<F> Drop for PooledIo<F> {
+    fn drop(&mut self) {
+        self.pool.active.set(self.pool.active.get() - 1);
+
+        if let Some(io) = self.io.take() {
+            if !io.is_closed() && io.with_read_buf(|buf| buf.is_empty()) {
+                self.pool
+                    .release(std::mem::take(&mut self.key), io, &self.config);
+            }
+        }
+    }
+}
+
+impl<F> fmt::Debug for PooledIo<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PooledIo")
+            .field("authority", &self.key)
+            .field("io", &self.io)
+            .finish()
+    }
+}
+
+fn authority<T: Address>(req: &Connect<T>) -> String {
+    if let Some(addr) = req.get_ref().addr() {
+        addr.to_string()
+    } else {
+        format!("{}:{}", parse(req.host()).0, req.port())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex_util::time::{sleep, Millis, Seconds};
+
+    use super::*;
+    use crate::connect::Connector;
+
+    #[ntex::test]
+    async fn test_pool() {
+        let server = ntex::server::test_server(|| {
+            ntex_service::fn_service(|io: Io| async move {
+                let _ = io.read_ready().await;
+                Ok::<_, ()>(())
+            })
+        });
+        let addr = format!("127.0.0.1:{}", server.addr().port());
+
+        let pool = ConnectionPool::new(Connector::<String>::new())
+            .max_idle(1)
+            .idle_timeout(Seconds(1));
+        assert!(format!("{:?}", pool).contains("ConnectionPool"));
+
+        let io1 = pool.acquire(addr.clone()).await.unwrap();
+        let io2 = pool.acquire(addr.clone()).await.unwrap();
+        assert_eq!(io1.authority(), addr);
+        assert!(format!("{:?}", io1).contains("PooledIo"));
+        assert_eq!(
+            pool.stats(),
+            PoolStats {
+                idle: 0,
+                active: 2,
+                hits: 0,
+                misses: 2,
+                evicted: 0
+            }
+        );
+
+        // only one idle connection is allowed
+        drop(io1);
+        drop(io2);
+        let stats = pool.stats();
+        assert_eq!((stats.idle, stats.active), (1, 0));
+
+        let io = pool.acquire(addr.clone()).await.unwrap();
+        assert_eq!(pool.stats().hits, 1);
+        assert_eq!(pool.stats().idle, 0);
+
+        // detached connection does not return to the pool
+        let io = io.into_inner();
+        assert_eq!(pool.stats().idle, 0);
+        assert_eq!(pool.stats().active, 0);
+
+        // closed connections are not returned
+        let srv = Pipeline::new(pool.clone());
+        let pooled = srv.call(Connect::new(addr.clone())).await.unwrap();
+        pooled.force_close();
+        drop(pooled);
+        assert_eq!(pool.stats().idle, 0);
+
+        // health check
+        let pool = pool.health_check(|_| false);
+        drop(pool.acquire(addr.clone()).await.unwrap());
+        assert_eq!(pool.stats().idle, 1);
+        let _io = pool.acquire(addr.clone()).await.unwrap();
+        assert_eq!(pool.stats().evicted, 1);
+        assert_eq!(pool.stats().misses, 5);
+        drop(io);
+
+        // idle connections are closed after timeout
+        let pool = ConnectionPool::new(Connector::<String>::new()).idle_timeout(Millis(50));
+        drop(pool.acquire(addr.clone()).await.unwrap());
+        assert_eq!(pool.stats().idle, 1);
+        sleep(Millis(150)).await;
+        assert_eq!(pool.stats().evicted, 1);
+        assert_eq!(pool.stats().idle, 0);
+
+        // configuration is not shared between clones
+        let pool2 = pool.clone().max_idle(0);
+        drop(pool.acquire(addr.clone()).await.unwrap());
+        drop(pool2.acquire(addr.clone()).await.unwrap());
+        assert_eq!(pool.stats().hits, 1);
+        assert_eq!(pool.stats().idle, 0);
+        drop(pool.acquire(addr.clone()).await.unwrap());
+        assert_eq!(pool2.stats().idle, 1);
+
+        pool.clear();
+        assert_eq!(pool.stats().idle, 0);
+    }
+}
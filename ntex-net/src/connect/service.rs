@@ -1,19 +1,24 @@
+use std::net::{IpAddr, SocketAddr};
 use std::task::{Context, Poll};
-use std::{collections::VecDeque, fmt, future::Future, io, net::SocketAddr, pin::Pin};
+use std::time::Instant;
+use std::{collections::VecDeque, fmt, future::Future, io, pin::Pin, rc::Rc};
 
 use ntex_bytes::{PoolId, PoolRef};
 use ntex_io::{types, Io};
 use ntex_service::{Service, ServiceCtx, ServiceFactory};
 use ntex_util::future::{BoxFuture, Either};
 
+use super::event::{ConnectEvent, OnConnectEvent};
+use super::policy::{AddressPolicy, AddressSelector};
 use super::{Address, Connect, ConnectError, Resolver};
-use crate::tcp_connect_in;
+use crate::{tcp_connect_bind_in, tcp_connect_in};
 
-#[derive(Copy)]
 pub struct Connector<T> {
     resolver: Resolver<T>,
     pool: PoolRef,
     tag: &'static str,
+    on_event: Option<OnConnectEvent>,
+    selector: Rc<AddressSelector>,
 }
 
 impl<T> Connector<T> {
@@ -23,6 +28,8 @@ impl<T> Connector<T> {
             resolver: Resolver::new(),
             pool: PoolId::P0.pool_ref(),
             tag: "TCP-CLIENT",
+            on_event: None,
+            selector: Rc::new(AddressSelector::new(AddressPolicy::First)),
         }
     }
 
@@ -42,6 +49,28 @@ impl<T> Connector<T> {
         self.tag = tag;
         self
     }
+
+    /// Set connect events hook
+    ///
+    /// Hook is called for each host resolution and each connection
+    /// attempt, see [`ConnectEvent`].
+    pub fn on_connect_event<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&ConnectEvent<'_>) + 'static,
+    {
+        self.on_event = Some(Rc::new(f));
+        self
+    }
+
+    /// Set address selection policy
+    ///
+    /// Policy is used if host name resolves to multiple addresses,
+    /// see [`AddressPolicy`]. Clones of the connector share policy state.
+    /// By default addresses are used in resolution order.
+    pub fn address_policy(mut self, policy: AddressPolicy) -> Self {
+        self.selector = Rc::new(AddressSelector::new(policy));
+        self
+    }
 }
 
 impl<T: Address> Connector<T> {
@@ -50,24 +79,83 @@ impl<T: Address> Connector<T> {
     where
         Connect<T>: From<U>,
     {
+        let message: Connect<T> = message.into();
+
+        // report only actual dns lookups
+        let host = if self.on_event.is_some()
+            && message.addr.is_none()
+            && message.req.addr().is_none()
+            && message.host().parse::<IpAddr>().is_err()
+        {
+            Some(message.host().to_string())
+        } else {
+            None
+        };
+        let start = Instant::now();
+
         // resolve first
-        let address = self
-            .resolver
-            .lookup_with_tag(message.into(), self.tag)
-            .await?;
+        let result = self.resolver.lookup_with_tag(message, self.tag).await;
+        if let (Some(on_event), Some(host)) = (self.on_event.as_ref(), host.as_deref()) {
+            match result {
+                Ok(ref address) => {
+                    let addrs: Vec<_> = address.addrs().collect();
+                    on_event(&ConnectEvent::Resolved {
+                        host,
+                        addrs: &addrs,
+                        elapsed: start.elapsed(),
+                    })
+                }
+                Err(ref error) => on_event(&ConnectEvent::ResolveFailed {
+                    host,
+                    error,
+                    elapsed: start.elapsed(),
+                }),
+            }
+        }
+        let address = result?;
 
         let port = address.port();
-        let Connect { req, addr, .. } = address;
+        let Connect {
+            req,
+            addr,
+            local_addr,
+            ..
+        } = address;
 
-        if let Some(addr) = addr {
-            TcpConnectorResponse::new(req, port, addr, self.tag, self.pool).await
+        if let Some(mut addr) = addr {
+            if let Either::Right(ref mut addrs) = addr {
+                self.selector.select(req.host(), port, addrs);
+            }
+            let host = if self.selector.policy() == AddressPolicy::Sticky {
+                Some(req.host().to_string())
+            } else {
+                None
+            };
+
+            let io = TcpConnectorResponse::new(
+                req,
+                port,
+                addr,
+                local_addr,
+                self.tag,
+                self.pool,
+                self.on_event.clone(),
+            )
+            .await?;
+
+            if let (Some(host), Some(addr)) = (host, io.query::<types::PeerAddr>().get()) {
+                self.selector.connected(&host, port, addr.0);
+            }
+            Ok(io)
         } else if let Some(addr) = req.addr() {
             TcpConnectorResponse::new(
                 req,
                 addr.port(),
                 Either::Left(addr),
+                local_addr,
                 self.tag,
                 self.pool,
+                self.on_event.clone(),
             )
             .await
         } else {
@@ -89,6 +177,8 @@ impl<T> Clone for Connector<T> {
             resolver: self.resolver.clone(),
             tag: self.tag,
             pool: self.pool,
+            on_event: self.on_event.clone(),
+            selector: self.selector.clone(),
         }
     }
 }
@@ -99,6 +189,8 @@ impl<T> fmt::Debug for Connector<T> {
             .field("tag", &self.tag)
             .field("resolver", &self.resolver)
             .field("memory_pool", &self.pool)
+            .field("on_connect_event", &self.on_event.is_some())
+            .field("address_policy", &self.selector.policy())
             .finish()
     }
 }
@@ -131,11 +223,14 @@ impl<T: Address> Service<Connect<T>> for Connector<T> {
 struct TcpConnectorResponse<T> {
     req: Option<T>,
     port: u16,
-    addrs: Option<VecDeque<SocketAddr>>,
+    addrs: VecDeque<SocketAddr>,
     #[allow(clippy::type_complexity)]
     stream: Option<BoxFuture<'static, Result<Io, io::Error>>>,
     tag: &'static str,
     pool: PoolRef,
+    on_event: Option<OnConnectEvent>,
+    current: Option<(SocketAddr, Instant)>,
+    local_addr: Option<IpAddr>,
 }
 
 impl<T: Address> TcpConnectorResponse<T> {
@@ -143,8 +238,10 @@ impl<T: Address> TcpConnectorResponse<T> {
         req: T,
         port: u16,
         addr: Either<SocketAddr, VecDeque<SocketAddr>>,
+        local_addr: Option<IpAddr>,
         tag: &'static str,
         pool: PoolRef,
+        on_event: Option<OnConnectEvent>,
     ) -> TcpConnectorResponse<T> {
         log::trace!(
             "{}: TCP connector - connecting to {:?} addr:{:?} port:{}",
@@ -154,24 +251,39 @@ impl<T: Address> TcpConnectorResponse<T> {
             port
         );
 
-        match addr {
-            Either::Left(addr) => TcpConnectorResponse {
-                req: Some(req),
-                addrs: None,
-                stream: Some(Box::pin(tcp_connect_in(addr, pool))),
-                tag,
-                pool,
-                port,
-            },
-            Either::Right(addrs) => TcpConnectorResponse {
-                tag,
-                port,
-                pool,
-                req: Some(req),
-                addrs: Some(addrs),
-                stream: None,
-            },
+        let addrs = match addr {
+            Either::Left(addr) => VecDeque::from([addr]),
+            Either::Right(addrs) => addrs,
+        };
+        let mut res = TcpConnectorResponse {
+            tag,
+            port,
+            pool,
+            addrs,
+            on_event,
+            req: Some(req),
+            stream: None,
+            current: None,
+            local_addr,
+        };
+        res.connect_next();
+        res
+    }
+
+    fn connect_next(&mut self) {
+        let addr = self.addrs.pop_front().unwrap();
+        if let Some(ref on_event) = self.on_event {
+            on_event(&ConnectEvent::Connecting {
+                host: self.req.as_ref().unwrap().host(),
+                addr,
+            });
+            self.current = Some((addr, Instant::now()));
         }
+        self.stream = Some(if let Some(local) = self.local_addr {
+            Box::pin(tcp_connect_bind_in(addr, local, self.pool))
+        } else {
+            Box::pin(tcp_connect_in(addr, self.pool))
+        });
     }
 
     fn can_continue(&self, err: &io::Error) -> bool {
@@ -182,7 +294,15 @@ impl<T: Address> TcpConnectorResponse<T> {
             self.port,
             err
         );
-        !(self.addrs.is_none() || self.addrs.as_ref().unwrap().is_empty())
+        if let (Some(on_event), Some((addr, start))) = (&self.on_event, self.current) {
+            on_event(&ConnectEvent::ConnectFailed {
+                host: self.req.as_ref().unwrap().host(),
+                addr,
+                error: err,
+                elapsed: start.elapsed(),
+            });
+        }
+        !self.addrs.is_empty()
     }
 }
 
@@ -204,6 +324,15 @@ impl<T: Address> Future for TcpConnectorResponse<T> {
                             req.host(),
                             sock.query::<types::PeerAddr>().get()
                         );
+                        if let (Some(on_event), Some((addr, start))) =
+                            (&this.on_event, this.current)
+                        {
+                            on_event(&ConnectEvent::Connected {
+                                host: req.host(),
+                                addr,
+                                elapsed: start.elapsed(),
+                            });
+                        }
                         sock.set_tag(this.tag);
                         return Poll::Ready(Ok(sock));
                     }
@@ -217,8 +346,7 @@ impl<T: Address> Future for TcpConnectorResponse<T> {
             }
 
             // try to connect
-            let addr = this.addrs.as_mut().unwrap().pop_front().unwrap();
-            this.stream = Some(Box::pin(tcp_connect_in(addr, this.pool)));
+            this.connect_next();
         }
     }
 }
@@ -257,4 +385,100 @@ mod tests {
         let result = crate::connect::connect(msg).await;
         assert!(result.is_ok());
     }
+
+    #[ntex::test]
+    async fn test_address_policy() {
+        let srv1 = ntex::server::test_server(|| {
+            ntex_service::fn_service(|_| async { Ok::<_, ()>(()) })
+        });
+        let srv2 = ntex::server::test_server(|| {
+            ntex_service::fn_service(|_| async { Ok::<_, ()>(()) })
+        });
+        let peer = |io: Io| io.query::<types::PeerAddr>().get().unwrap().0;
+        let msg = || Connect::new("localhost").set_addrs(vec![srv1.addr(), srv2.addr()]);
+
+        let srv = Connector::default().address_policy(AddressPolicy::RoundRobin);
+        assert!(format!("{:?}", srv).contains("RoundRobin"));
+        assert_eq!(peer(srv.connect(msg()).await.unwrap()), srv1.addr());
+        assert_eq!(peer(srv.clone().connect(msg()).await.unwrap()), srv2.addr());
+        assert_eq!(peer(srv.connect(msg()).await.unwrap()), srv1.addr());
+
+        let srv = Connector::default().address_policy(AddressPolicy::Sticky);
+        let addr = peer(srv.connect(msg()).await.unwrap());
+        for _ in 0..4 {
+            assert_eq!(peer(srv.connect(msg()).await.unwrap()), addr);
+        }
+
+        // failed sticky address gets replaced
+        let bad = closed_addr();
+        srv.selector.connected("localhost", 0, bad);
+        let msg = Connect::new("localhost").set_addrs(vec![bad, srv2.addr()]);
+        assert_eq!(peer(srv.connect(msg.clone()).await.unwrap()), srv2.addr());
+        assert_eq!(peer(srv.connect(msg).await.unwrap()), srv2.addr());
+    }
+
+    #[ntex::test]
+    async fn test_local_addr() {
+        let server = ntex::server::test_server(|| {
+            ntex_service::fn_service(|_| async { Ok::<_, ()>(()) })
+        });
+
+        let msg = Connect::new(server.addr()).set_local_addr(Some([127, 0, 0, 1].into()));
+        assert_eq!(msg.local_addr(), Some([127, 0, 0, 1].into()));
+        assert!(crate::connect::connect(msg).await.is_ok());
+
+        // address is not available
+        let msg = Connect::new(server.addr()).set_local_addr(Some([192, 0, 2, 1].into()));
+        assert!(crate::connect::connect(msg).await.is_err());
+    }
+
+    /// address of closed local port
+    fn closed_addr() -> SocketAddr {
+        let lst = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        lst.local_addr().unwrap()
+    }
+
+    #[ntex::test]
+    async fn test_connect_events() {
+        let server = ntex::server::test_server(|| {
+            ntex_service::fn_service(|_| async { Ok::<_, ()>(()) })
+        });
+
+        let events = Rc::new(std::cell::RefCell::new(Vec::new()));
+        let ev = events.clone();
+        let srv = Connector::default().on_connect_event(move |e| {
+            ev.borrow_mut().push(match e {
+                ConnectEvent::Resolved { host, .. } => format!("resolved {}", host),
+                ConnectEvent::ResolveFailed { host, .. } => format!("failed {}", host),
+                ConnectEvent::Connecting { addr, .. } => format!("connecting {}", addr),
+                ConnectEvent::Connected { addr, .. } => format!("connected {}", addr),
+                ConnectEvent::ConnectFailed { addr, .. } => format!("error {}", addr),
+            })
+        });
+        assert!(format!("{:?}", srv).contains("on_connect_event: true"));
+
+        let bad = closed_addr();
+        let msg =
+            Connect::new(format!("{}", server.addr())).set_addrs(vec![bad, server.addr()]);
+        assert!(srv.connect(msg).await.is_ok());
+        assert_eq!(
+            &*events.borrow(),
+            &[
+                format!("connecting {}", bad),
+                format!("error {}", bad),
+                format!("connecting {}", server.addr()),
+                format!("connected {}", server.addr()),
+            ]
+        );
+
+        events.borrow_mut().clear();
+        let result = srv
+            .connect(format!("localhost:{}", server.addr().port()))
+            .await;
+        assert!(result.is_ok());
+        assert_eq!(
+            events.borrow()[0],
+            format!("resolved localhost:{}", server.addr().port())
+        );
+    }
 }
@@ -92,6 +92,24 @@ impl ResourceDef {
     /// with segment separator. Static segments could be
     /// case insensitive.
     ///
+    /// Dynamic segment could be constrained with custom regex,
+    /// `{id:[0-9]+}`, or with one of named constraints:
+    ///
+    /// * `int` - signed integer
+    /// * `uint` - unsigned integer
+    /// * `hex` - hex digits
+    /// * `alpha` - ascii letters
+    /// * `alnum` - ascii letters and digits
+    /// * `uuid` - hyphenated uuid
+    ///
+    /// Path does not match if segment does not satisfy constraint.
+    ///
+    /// Dynamic segment with `*` suffix, `{tail}*` or `{tail:[a-z/]+}*`, matches
+    /// the rest of the path including segment separators. Last segment with
+    /// `.*` or `.+` regex, i.e. `/assets/{path:.*}`, is also tail match.
+    /// Such segment matches multiple path segments, `{path:[^/]+}` could be used
+    /// to match single segment.
+    ///
     /// Panics if path pattern is malformed.
     pub fn new<T: IntoPattern>(path: T) -> Self {
         let set = path.patterns();
@@ -285,11 +303,15 @@ impl ResourceDef {
 
             let (name, pat) = match param.find(':') {
                 Some(idx) => {
+                    let (name, pattern) = param.split_at(idx);
+                    let pattern = constraint(&pattern[1..]);
                     if tail {
-                        panic!("Custom regex is not supported for remainder match");
+                        rem = &rem[1..];
+                    } else if rem.is_empty() && (pattern == ".*" || pattern == ".+") {
+                        // wildcard in last segment matches the rest of the path
+                        tail = true;
                     }
-                    let (name, pattern) = param.split_at(idx);
-                    (name, &pattern[1..])
+                    (name, pattern)
                 }
                 None => (
                     param,
@@ -456,6 +478,21 @@ impl From<String> for ResourceDef {
     }
 }
 
+/// Regex for named parameter constraint
+fn constraint(pattern: &str) -> &str {
+    match pattern {
+        "int" => "-?[0-9]+",
+        "uint" => "[0-9]+",
+        "hex" => "[0-9a-fA-F]+",
+        "alpha" => "[a-zA-Z]+",
+        "alnum" => "[a-zA-Z0-9]+",
+        "uuid" => {
+            "[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}"
+        }
+        _ => pattern,
+    }
+}
+
 pub(crate) fn insert_slash(path: &str) -> String {
     let mut path = path.to_owned();
     if !path.is_empty() && !path.starts_with('/') {
@@ -562,6 +599,40 @@ mod tests {
         assert_eq!(tree.find(&mut resource), Some(1));
         assert_eq!(resource.get("id").unwrap(), "012345");
 
+        let re = ResourceDef::new("/user/{id:uint}");
+        let tree = Tree::new(&re, 1);
+        assert_eq!(tree.find(&mut Path::new("/user/123")), Some(1));
+        assert_eq!(tree.find(&mut Path::new("/user/-123")), None);
+        assert_eq!(tree.find(&mut Path::new("/user/profile")), None);
+
+        let re = ResourceDef::new("/user/{id:int}/{key:uuid}");
+        let tree = Tree::new(&re, 1);
+        let mut resource = Path::new("/user/-123/67e55044-10b1-426f-9247-bb680e5fe0c8");
+        assert_eq!(tree.find(&mut resource), Some(1));
+        assert_eq!(resource.get("id").unwrap(), "-123");
+        assert_eq!(
+            resource.get("key").unwrap(),
+            "67e55044-10b1-426f-9247-bb680e5fe0c8"
+        );
+        assert_eq!(
+            tree.find(&mut Path::new("/user/123/67e55044-10b1-426f-9247")),
+            None
+        );
+        assert_eq!(
+            tree.find(&mut Path::new(
+                "/user/abc/67e55044-10b1-426f-9247-bb680e5fe0c8"
+            )),
+            None
+        );
+
+        let re = ResourceDef::new("/{name:alpha}-{code:hex}");
+        let tree = Tree::new(&re, 1);
+        let mut resource = Path::new("/test-fF09");
+        assert_eq!(tree.find(&mut resource), Some(1));
+        assert_eq!(resource.get("name").unwrap(), "test");
+        assert_eq!(resource.get("code").unwrap(), "fF09");
+        assert_eq!(tree.find(&mut Path::new("/test1-ff")), None);
+
         let re = ResourceDef::new("/u/test/v{version}-no-{minor}xx/resource/{id}/{name}");
         let tree = Tree::new(&re, 1);
         let mut resource = Path::new("/u/test/v1-no-3xx/resource/320120/name");
@@ -753,6 +824,42 @@ mod tests {
         assert_eq!(resource.get("id").unwrap(), "2345/sdg");
     }
 
+    #[test]
+    fn test_parse_tail_regex() {
+        let re = ResourceDef::new("/assets/{path:.*}");
+        let tree = Tree::new(&re, 1);
+
+        let mut resource = Path::new("/assets/css/main.css");
+        assert_eq!(tree.find(&mut resource), Some(1));
+        assert_eq!(resource.get("path").unwrap(), "css/main.css");
+
+        let mut resource = Path::new("/assets/");
+        assert_eq!(tree.find(&mut resource), Some(1));
+        assert_eq!(resource.get("path").unwrap(), "");
+
+        let re = ResourceDef::new("/assets/{path:.+}");
+        let tree = Tree::new(&re, 1);
+        assert_eq!(tree.find(&mut Path::new("/assets/")), None);
+        let mut resource = Path::new("/assets/css/main.css");
+        assert_eq!(tree.find(&mut resource), Some(1));
+        assert_eq!(resource.get("path").unwrap(), "css/main.css");
+
+        let re = ResourceDef::new("/user/{id:[a-z/]+}*");
+        let tree = Tree::new(&re, 1);
+        assert_eq!(tree.find(&mut Path::new("/user/a1/b")), None);
+        let mut resource = Path::new("/user/a/b");
+        assert_eq!(tree.find(&mut resource), Some(1));
+        assert_eq!(resource.get("id").unwrap(), "a/b");
+
+        // wildcard in the middle of the path matches single segment
+        let re = ResourceDef::new("/{name:.*}/info");
+        let tree = Tree::new(&re, 1);
+        assert_eq!(tree.find(&mut Path::new("/a/b/info")), None);
+        let mut resource = Path::new("/a/info");
+        assert_eq!(tree.find(&mut resource), Some(1));
+        assert_eq!(resource.get("name").unwrap(), "a");
+    }
+
     #[test]
     #[allow(clippy::needless_borrows_for_generic_args)]
     fn test_static_tail() {
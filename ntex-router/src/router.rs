@@ -182,6 +182,7 @@ impl<T, U> RouterBuilder<T, U> {
 mod tests {
     use crate::path::Path;
     use crate::router::{ResourceId, Router};
+    use crate::ResourceDef;
 
     #[test]
     fn test_recognizer_1() {
@@ -353,6 +354,20 @@ mod tests {
         assert_eq!(&path["val"], "ttt");
     }
 
+    #[test]
+    fn test_recognizer_empty_prefix() {
+        let mut router = Router::<usize>::build();
+        router.rdef(ResourceDef::root_prefix(""), 10);
+        let router = router.finish();
+
+        for p in ["/", "/name", "/name/value"] {
+            let mut path = Path::new(p);
+            let (h, _) = router.recognize(&mut path).unwrap();
+            assert_eq!(*h, 10);
+            assert_eq!(path.unprocessed(), p);
+        }
+    }
+
     #[test]
     fn test_recognizer_checked() {
         let mut router = Router::<usize, usize>::build();
@@ -261,8 +261,9 @@ impl Tree {
                 for val in &self.items {
                     match val {
                         Item::Value(val) => {
+                            // empty prefix matches any path
                             let v = match val {
-                                Value::PrefixSlash(v) => *v,
+                                Value::Prefix(v) | Value::PrefixSlash(v) => *v,
                                 _ => continue,
                             };
                             if check(v, resource) {
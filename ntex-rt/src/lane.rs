@@ -0,0 +1,367 @@
+//! High priority tasks lane.
+//!
+//! Tasks spawned with [`spawn_high`] are kept in a thread local queue. Ready
+//! high priority tasks get polled before any task spawned with [`spawn`](crate::spawn)
+//! and by a dedicated driver task, so control-plane work is not delayed
+//! by bulk tasks.
+use std::cell::{Cell, RefCell};
+use std::future::{poll_fn, Future};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::{fmt, mem, pin::Pin};
+
+thread_local! {
+    static LANE: Lane = Lane::new();
+}
+
+/// Number of live high priority tasks of all threads
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+/// Spawn a high priority future on the current thread.
+///
+/// Ready high priority tasks are polled before bulk tasks spawned with
+/// [`spawn`](crate::spawn). Use it for short control-plane work like
+/// protocol acks or pings, long running work starves bulk tasks.
+///
+/// # Panics
+///
+/// This function panics if ntex system is not running.
+pub fn spawn_high<F>(f: F) -> PriorityJoinHandle<F::Output>
+where
+    F: Future + 'static,
+    F::Output: 'static,
+{
+    let ptr = crate::CB.with(|cb| (cb.borrow().0)());
+    let (tx, rx) = oneshot::channel();
+
+    LANE.with(|lane| {
+        lane.spawn(Box::pin(async move {
+            let mut f = std::pin::pin!(f);
+            let result = if let Some(ptr) = ptr {
+                let result = poll_fn(|ctx| {
+                    let new_ptr = crate::CB.with(|cb| (cb.borrow().1)(ptr));
+                    let result = f.as_mut().poll(ctx);
+                    crate::CB.with(|cb| (cb.borrow().2)(new_ptr));
+                    result
+                })
+                .await;
+                crate::CB.with(|cb| (cb.borrow().3)(ptr));
+                result
+            } else {
+                f.await
+            };
+            let _ = tx.send(result);
+        }))
+    });
+    PriorityJoinHandle { rx }
+}
+
+/// Poll ready high priority tasks
+///
+/// Called by bulk tasks before polling its own future, it is no-op
+/// if there are no high priority tasks.
+#[cfg(any(
+    feature = "tokio",
+    feature = "async-std",
+    all(feature = "glommio", target_os = "linux")
+))]
+pub(crate) fn run() {
+    if ACTIVE.load(Ordering::Relaxed) != 0 {
+        let _ = LANE.try_with(|lane| lane.run());
+    }
+}
+
+/// High priority task completion future.
+///
+/// Resolves to an error if task panicked.
+pub struct PriorityJoinHandle<T> {
+    rx: oneshot::Receiver<T>,
+}
+
+impl<T> fmt::Debug for PriorityJoinHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PriorityJoinHandle").finish()
+    }
+}
+
+impl<T> Future for PriorityJoinHandle<T> {
+    type Output = Result<T, oneshot::RecvError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.rx).poll(cx)
+    }
+}
+
+type Task = Pin<Box<dyn Future<Output = ()>>>;
+
+struct Entry {
+    fut: Task,
+    inner: Arc<TaskWaker>,
+    waker: Waker,
+}
+
+struct Lane {
+    tasks: RefCell<Vec<Option<Entry>>>,
+    free: RefCell<Vec<usize>>,
+    shared: Arc<Shared>,
+    driver: Cell<bool>,
+    running: Cell<bool>,
+}
+
+/// State shared with task wakers, wakers could be used from other threads
+struct Shared {
+    ready: Mutex<Vec<usize>>,
+    pending: AtomicBool,
+    driver: Mutex<Option<Waker>>,
+}
+
+struct TaskWaker {
+    idx: usize,
+    queued: AtomicBool,
+    shared: Arc<Shared>,
+}
+
+impl Lane {
+    fn new() -> Self {
+        Lane {
+            tasks: RefCell::new(Vec::new()),
+            free: RefCell::new(Vec::new()),
+            shared: Arc::new(Shared {
+                ready: Mutex::new(Vec::new()),
+                pending: AtomicBool::new(false),
+                driver: Mutex::new(None),
+            }),
+            driver: Cell::new(false),
+            running: Cell::new(false),
+        }
+    }
+
+    fn spawn(&self, fut: Task) {
+        let idx = self.free.borrow_mut().pop();
+        let mut tasks = self.tasks.borrow_mut();
+        let idx = idx.unwrap_or_else(|| {
+            tasks.push(None);
+            tasks.len() - 1
+        });
+
+        let inner = Arc::new(TaskWaker {
+            idx,
+            queued: AtomicBool::new(false),
+            shared: self.shared.clone(),
+        });
+        let waker = Waker::from(inner.clone());
+        inner.schedule();
+        tasks[idx] = Some(Entry { fut, inner, waker });
+        drop(tasks);
+        ACTIVE.fetch_add(1, Ordering::Relaxed);
+
+        // driver polls the lane if there are no active bulk tasks
+        if !self.driver.get() {
+            self.driver.set(true);
+            drop(crate::spawn(Driver));
+        }
+    }
+
+    fn run(&self) {
+        if self.running.get() || !self.shared.pending.swap(false, Ordering::AcqRel) {
+            return;
+        }
+        self.running.set(true);
+        let _guard = RunGuard(&self.running);
+
+        // tasks woken during this pass are polled on the next pass
+        let ready = mem::take(&mut *self.shared.ready.lock().unwrap());
+        for idx in ready {
+            let entry = self.tasks.borrow_mut().get_mut(idx).and_then(|e| e.take());
+            if let Some(mut entry) = entry {
+                entry.inner.queued.store(false, Ordering::Release);
+
+                let mut cx = Context::from_waker(&entry.waker);
+                match catch_unwind(AssertUnwindSafe(|| entry.fut.as_mut().poll(&mut cx))) {
+                    Ok(Poll::Pending) => {
+                        self.tasks.borrow_mut()[idx] = Some(entry);
+                    }
+                    Ok(Poll::Ready(())) => {
+                        self.free.borrow_mut().push(idx);
+                        ACTIVE.fetch_sub(1, Ordering::Relaxed);
+                    }
+                    Err(_) => {
+                        log::error!("High priority task panicked");
+                        self.free.borrow_mut().push(idx);
+                        ACTIVE.fetch_sub(1, Ordering::Relaxed);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Drop all tasks, runtime is stopped
+    fn clear(&self) {
+        let tasks = mem::take(&mut *self.tasks.borrow_mut());
+        self.free.borrow_mut().clear();
+        self.shared.ready.lock().unwrap().clear();
+        self.shared.pending.store(false, Ordering::Release);
+
+        let num = tasks.iter().filter(|e| e.is_some()).count();
+        ACTIVE.fetch_sub(num, Ordering::Relaxed);
+        drop(tasks);
+    }
+}
+
+impl Shared {
+    fn wake_driver(&self) {
+        if let Some(waker) = self.driver.lock().unwrap().as_ref() {
+            waker.wake_by_ref();
+        }
+    }
+}
+
+impl TaskWaker {
+    fn schedule(&self) {
+        if !self.queued.swap(true, Ordering::AcqRel) {
+            self.shared.ready.lock().unwrap().push(self.idx);
+            self.shared.pending.store(true, Ordering::Release);
+            self.shared.wake_driver();
+        }
+    }
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.schedule()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.schedule()
+    }
+}
+
+struct RunGuard<'a>(&'a Cell<bool>);
+
+impl<'a> Drop for RunGuard<'a> {
+    fn drop(&mut self) {
+        self.0.set(false);
+    }
+}
+
+/// Polls high priority tasks lane
+struct Driver;
+
+impl Future for Driver {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        LANE.with(|lane| {
+            // register waker first, so wake ups during run are not lost
+            *lane.shared.driver.lock().unwrap() = Some(cx.waker().clone());
+            lane.run();
+        });
+        Poll::Pending
+    }
+}
+
+impl Drop for Driver {
+    fn drop(&mut self) {
+        // runtime is stopped, tasks of stopped runtime must not be
+        // polled by next runtime. next spawn starts new driver
+        let _ = LANE.try_with(|lane| {
+            lane.driver.set(false);
+            lane.shared.driver.lock().unwrap().take();
+            lane.clear();
+        });
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::System;
+
+    #[test]
+    fn test_spawn_high() {
+        let order = Rc::new(RefCell::new(Vec::new()));
+
+        let o = order.clone();
+        let res = System::new("test").block_on(async move {
+            let o2 = o.clone();
+            let bulk = crate::spawn(async move {
+                o2.borrow_mut().push("bulk");
+            });
+            let o2 = o.clone();
+            let high = spawn_high(async move {
+                o2.borrow_mut().push("high");
+                1
+            });
+            let _ = bulk.await;
+            high.await
+        });
+        assert_eq!(res.unwrap(), 1);
+        assert_eq!(&*order.borrow(), &["high", "bulk"]);
+
+        // driver polls high priority tasks without bulk tasks
+        let res = System::new("test").block_on(async move {
+            spawn_high(async move {
+                crate::spawn(async {}).await.unwrap();
+                2
+            })
+            .await
+        });
+        assert_eq!(res.unwrap(), 2);
+    }
+
+    #[test]
+    fn test_high_before_queued_bulk() {
+        let order = Rc::new(RefCell::new(Vec::new()));
+
+        let o = order.clone();
+        System::new("test").block_on(async move {
+            let (tx, rx) = oneshot::channel::<()>();
+            let o2 = o.clone();
+            let high = spawn_high(async move {
+                let _ = rx.await;
+                o2.borrow_mut().push("high");
+            });
+            // high priority task waits for notification
+            crate::spawn(async {}).await.unwrap();
+
+            let bulk: Vec<_> = (0..3)
+                .map(|_| {
+                    let o2 = o.clone();
+                    crate::spawn(async move { o2.borrow_mut().push("bulk") })
+                })
+                .collect();
+
+            // high priority task is woken after bulk tasks are queued
+            let _ = tx.send(());
+            for item in bulk {
+                item.await.unwrap();
+            }
+            high.await.unwrap();
+        });
+        assert_eq!(&*order.borrow(), &["high", "bulk", "bulk", "bulk"]);
+    }
+
+    #[test]
+    fn test_stopped_system() {
+        // pending task of stopped system must not be polled by next system
+        let polled = Rc::new(RefCell::new(0));
+        let p = polled.clone();
+        System::new("test").block_on(async move {
+            std::mem::forget(spawn_high(async move {
+                *p.borrow_mut() += 1;
+                std::future::pending::<()>().await
+            }));
+            crate::spawn(async {}).await.unwrap();
+        });
+        assert_eq!(*polled.borrow(), 1);
+        assert_eq!(Rc::strong_count(&polled), 1);
+
+        let res = System::new("test").block_on(async { spawn_high(async { 1 }).await });
+        assert_eq!(res.unwrap(), 1);
+        assert_eq!(*polled.borrow(), 1);
+    }
+}
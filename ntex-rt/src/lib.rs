@@ -3,10 +3,12 @@ use std::{cell::RefCell, ptr};
 
 mod arbiter;
 mod builder;
+mod lane;
 mod system;
 
 pub use self::arbiter::Arbiter;
 pub use self::builder::{Builder, SystemRunner};
+pub use self::lane::{spawn_high, PriorityJoinHandle};
 pub use self::system::System;
 
 thread_local! {
@@ -88,6 +90,7 @@ mod glommio {
                         glomm_io::executor().yield_now().await;
                         let mut f = unsafe { Pin::new_unchecked(&mut f) };
                         let result = poll_fn(|ctx| {
+                            crate::lane::run();
                             let new_ptr = crate::CB.with(|cb| (cb.borrow().1)(ptr));
                             let result = f.as_mut().poll(ctx);
                             crate::CB.with(|cb| (cb.borrow().2)(new_ptr));
@@ -98,7 +101,12 @@ mod glommio {
                         result
                     } else {
                         glomm_io::executor().yield_now().await;
-                        f.await
+                        let mut f = unsafe { Pin::new_unchecked(&mut f) };
+                        poll_fn(|ctx| {
+                            crate::lane::run();
+                            f.as_mut().poll(ctx)
+                        })
+                        .await
                     }
                 })
                 .detach(),
@@ -190,9 +198,10 @@ mod tokio {
     {
         let ptr = crate::CB.with(|cb| (cb.borrow().0)());
         tok_io::task::spawn_local(async move {
+            tok_io::pin!(f);
             if let Some(ptr) = ptr {
-                tok_io::pin!(f);
                 let result = poll_fn(|ctx| {
+                    crate::lane::run();
                     let new_ptr = crate::CB.with(|cb| (cb.borrow().1)(ptr));
                     let result = f.as_mut().poll(ctx);
                     crate::CB.with(|cb| (cb.borrow().2)(new_ptr));
@@ -202,7 +211,11 @@ mod tokio {
                 crate::CB.with(|cb| (cb.borrow().3)(ptr));
                 result
             } else {
-                f.await
+                poll_fn(|ctx| {
+                    crate::lane::run();
+                    f.as_mut().poll(ctx)
+                })
+                .await
             }
         })
     }
@@ -255,6 +268,7 @@ mod asyncstd {
                 if let Some(ptr) = ptr {
                     let mut f = unsafe { Pin::new_unchecked(&mut f) };
                     let result = poll_fn(|ctx| {
+                        crate::lane::run();
                         let new_ptr = crate::CB.with(|cb| (cb.borrow().1)(ptr));
                         let result = f.as_mut().poll(ctx);
                         crate::CB.with(|cb| (cb.borrow().2)(new_ptr));
@@ -264,7 +278,12 @@ mod asyncstd {
                     crate::CB.with(|cb| (cb.borrow().3)(ptr));
                     result
                 } else {
-                    f.await
+                    let mut f = unsafe { Pin::new_unchecked(&mut f) };
+                    poll_fn(|ctx| {
+                        crate::lane::run();
+                        f.as_mut().poll(ctx)
+                    })
+                    .await
                 }
             }),
         }
@@ -8,7 +8,7 @@ mod manager;
 pub mod net;
 mod pool;
 mod server;
-mod signals;
+pub mod signal;
 mod wrk;
 
 pub use self::pool::WorkerPool;
@@ -20,6 +20,11 @@ pub use self::wrk::{Worker, WorkerStatus, WorkerStop};
 pub struct WorkerId(usize);
 
 impl WorkerId {
+    /// Worker index
+    pub fn index(&self) -> usize {
+        self.0
+    }
+
     pub(self) fn next(&mut self) -> WorkerId {
         let id = WorkerId(self.0);
         self.0 += 1;
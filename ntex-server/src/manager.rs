@@ -1,12 +1,13 @@
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::{cell::Cell, cell::RefCell, collections::VecDeque, rc::Rc, sync::Arc};
+use std::sync::{Arc, Mutex};
+use std::{cell::Cell, cell::RefCell, collections::VecDeque, rc::Rc};
 
 use async_channel::{unbounded, Receiver, Sender};
 use ntex_rt::System;
 use ntex_util::{future::join_all, time::sleep, time::Millis};
 
 use crate::server::ServerShared;
-use crate::signals::Signal;
+use crate::signal::Signal;
 use crate::{Server, ServerConfiguration, Worker, WorkerId, WorkerPool, WorkerStatus};
 
 const STOP_DELAY: Millis = Millis(500);
@@ -27,6 +28,7 @@ pub(crate) enum ServerCommand<T> {
     },
     NotifyStopped(oneshot::Sender<()>),
     Worker(Update<T>),
+    Workers(oneshot::Sender<Vec<Worker<T>>>),
 }
 
 #[derive(Debug)]
@@ -54,6 +56,8 @@ impl<F: ServerConfiguration> ServerManager<F> {
         let no_signals = cfg.no_signals;
         let shared = Arc::new(ServerShared {
             paused: AtomicBool::new(true),
+            reloading: AtomicBool::new(false),
+            addrs: Mutex::default(),
         });
         let mgr = ServerManager(Rc::new(Inner {
             cfg,
@@ -77,7 +81,7 @@ impl<F: ServerConfiguration> ServerManager<F> {
 
         // handle signals
         if !no_signals {
-            crate::signals::start(srv.clone());
+            crate::signal::start(srv.clone());
         }
 
         srv
@@ -307,6 +311,9 @@ async fn handle_cmd<F: ServerConfiguration>(
                 let _ = tx.send(());
             }
             ServerCommand::NotifyStopped(tx) => state.mgr.add_stop_notify(tx),
+            ServerCommand::Workers(tx) => {
+                let _ = tx.send(state.workers.clone());
+            }
             ServerCommand::Stop {
                 graceful,
                 completion,
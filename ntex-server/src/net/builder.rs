@@ -1,4 +1,4 @@
-use std::{fmt, future::Future, io, net};
+use std::{any::Any, fmt, future::Future, io, net, sync::Arc};
 
 use socket2::{Domain, SockAddr, Socket, Type};
 
@@ -9,9 +9,13 @@ use ntex_util::time::Millis;
 use crate::{Server, WorkerPool};
 
 use super::accept::AcceptLoop;
-use super::config::{Config, ServiceConfig};
+use super::config::{Config, ServiceConfig, WorkerDataFn};
 use super::factory::{self, FactoryServiceType, OnWorkerStart, OnWorkerStartWrapper};
-use super::{socket::Listener, Connection, ServerStatus, StreamServer, Token};
+use super::{
+    socket::Listener, Connection, ServerStatus, StartupReport, StreamServer, Token,
+};
+
+type OnStartup = Box<dyn FnOnce(&StartupReport) + Send>;
 
 /// Server builder
 pub struct ServerBuilder {
@@ -20,6 +24,8 @@ pub struct ServerBuilder {
     services: Vec<FactoryServiceType>,
     sockets: Vec<(Token, String, Listener)>,
     on_worker_start: Vec<Box<dyn OnWorkerStart + Send>>,
+    worker_data: Option<WorkerDataFn>,
+    on_startup: Option<OnStartup>,
     accept: AcceptLoop,
     pool: WorkerPool,
 }
@@ -38,6 +44,8 @@ impl ServerBuilder {
             services: Vec::new(),
             sockets: Vec::new(),
             on_worker_start: Vec::new(),
+            worker_data: None,
+            on_startup: None,
             accept: AcceptLoop::default(),
             backlog: 2048,
             pool: WorkerPool::new(),
@@ -118,6 +126,28 @@ impl ServerBuilder {
         self
     }
 
+    /// Set server startup handler.
+    ///
+    /// Server calls this handler once, after all sockets are bound
+    /// and workers are started.
+    ///
+    /// ```rust
+    /// use ntex_server::net::ServerBuilder;
+    ///
+    /// let builder = ServerBuilder::new().startup_handler(|report| {
+    ///     for (name, addr) in report.bound_addrs() {
+    ///         println!("Service {:?} listens on {}", name, addr);
+    ///     }
+    /// });
+    /// ```
+    pub fn startup_handler<F>(mut self, handler: F) -> Self
+    where
+        F: FnOnce(&StartupReport) + Send + 'static,
+    {
+        self.on_startup = Some(Box::new(handler));
+        self
+    }
+
     /// Execute external configuration as part of the server building
     /// process.
     ///
@@ -175,6 +205,31 @@ impl ServerBuilder {
         self
     }
 
+    /// Set per-worker data factory.
+    ///
+    /// Function get called once in each worker thread with worker index,
+    /// result is available to service factories via `Config::worker()`
+    /// and `ServiceRuntime::worker()`. It could be used for creating worker
+    /// specific resources, like shard assignments or pinned caches.
+    ///
+    /// ```rust
+    /// use ntex_server::net::ServerBuilder;
+    ///
+    /// struct Shard(usize);
+    ///
+    /// let builder = ServerBuilder::new()
+    ///     .workers(4)
+    ///     .worker_data(|idx| Shard(idx % 2));
+    /// ```
+    pub fn worker_data<F, T>(mut self, f: F) -> Self
+    where
+        F: Fn(usize) -> T + Send + Sync + 'static,
+        T: 'static,
+    {
+        self.worker_data = Some(Arc::new(move |idx| Box::new(f(idx)) as Box<dyn Any>));
+        self
+    }
+
     /// Add new service to the server.
     pub fn bind<F, U, N, R>(mut self, name: N, addr: U, factory: F) -> io::Result<Self>
     where
@@ -301,10 +356,20 @@ impl ServerBuilder {
         if self.sockets.is_empty() {
             panic!("Server should have at least one bound socket");
         } else {
+            let report = StartupReport {
+                workers: self.pool.num,
+                addrs: self
+                    .sockets
+                    .iter()
+                    .map(|sock| (sock.1.clone(), sock.2.local_addr()))
+                    .collect(),
+            };
+
             let srv = StreamServer::new(
                 self.accept.notify(),
                 self.services,
                 self.on_worker_start,
+                self.worker_data,
             );
             let svc = self.pool.run(srv);
 
@@ -318,6 +383,12 @@ impl ServerBuilder {
                 .collect();
             self.accept.start(sockets, svc.clone());
 
+            log::info!("{}", report);
+            *svc.shared.addrs.lock().unwrap() = report.addrs.clone();
+            if let Some(f) = self.on_startup {
+                f(&report);
+            }
+
             svc
         }
     }
@@ -1,4 +1,5 @@
-use std::{cell::Cell, cell::RefCell, fmt, future::Future, io, marker, mem, net, rc::Rc};
+use std::{any::Any, cell::Cell, cell::RefCell, fmt, future::Future, io, marker, mem};
+use std::{net, rc::Rc, sync::Arc};
 
 use ntex_bytes::PoolId;
 use ntex_net::Io;
@@ -9,6 +10,54 @@ use super::factory::{
     self, BoxServerService, FactoryService, FactoryServiceType, NetService,
 };
 use super::{builder::bind_addr, socket::Listener, Token};
+use crate::WorkerId;
+
+pub(super) type WorkerDataFn = Arc<dyn Fn(usize) -> Box<dyn Any> + Send + Sync>;
+
+#[derive(Clone, Default)]
+/// Worker context
+///
+/// Provides worker index and per-worker data created by
+/// [`ServerBuilder::worker_data()`](super::ServerBuilder::worker_data).
+pub struct WorkerCtx {
+    id: WorkerId,
+    data: Option<Rc<dyn Any>>,
+}
+
+impl WorkerCtx {
+    pub(super) fn new(id: WorkerId, f: Option<&WorkerDataFn>) -> Self {
+        Self {
+            id,
+            data: f.map(|f| Rc::from(f(id.index()))),
+        }
+    }
+
+    /// Worker id
+    pub fn id(&self) -> WorkerId {
+        self.id
+    }
+
+    /// Worker index
+    pub fn index(&self) -> usize {
+        self.id.index()
+    }
+
+    /// Get reference to worker data
+    ///
+    /// Returns `None` if worker data is not set or has different type.
+    pub fn data<T: 'static>(&self) -> Option<&T> {
+        self.data.as_ref().and_then(|data| data.downcast_ref())
+    }
+}
+
+impl fmt::Debug for WorkerCtx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WorkerCtx")
+            .field("id", &self.id)
+            .field("data", &self.data.is_some())
+            .finish()
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct Config(Rc<InnerServiceConfig>);
@@ -16,17 +65,31 @@ pub struct Config(Rc<InnerServiceConfig>);
 #[derive(Debug)]
 pub(super) struct InnerServiceConfig {
     pub(super) pool: Cell<PoolId>,
+    pub(super) worker: WorkerCtx,
 }
 
 impl Default for Config {
     fn default() -> Self {
+        Self::new(WorkerCtx::default())
+    }
+}
+
+impl Config {
+    pub(super) fn new(worker: WorkerCtx) -> Self {
         Self(Rc::new(InnerServiceConfig {
+            worker,
             pool: Cell::new(PoolId::DEFAULT),
         }))
     }
-}
 
-impl Config {
+    /// Worker context
+    ///
+    /// Service factory get called in each worker, context could be used
+    /// for creating worker specific resources.
+    pub fn worker(&self) -> &WorkerCtx {
+        &self.0.worker
+    }
+
     /// Set memory pool for the service.
     ///
     /// Use specified memory pool for memory allocations.
@@ -179,9 +242,9 @@ impl FactoryService for ConfiguredService {
         })
     }
 
-    fn create(&self) -> BoxFuture<'static, Result<Vec<NetService>, ()>> {
+    fn create(&self, worker: WorkerCtx) -> BoxFuture<'static, Result<Vec<NetService>, ()>> {
         // configure services
-        let rt = ServiceRuntime::new(self.names.clone());
+        let rt = ServiceRuntime::new(self.names.clone(), worker);
         let cfg_fut = self.rt.run(ServiceRuntime(rt.0.clone()));
 
         // construct services
@@ -194,9 +257,10 @@ impl FactoryService for ConfiguredService {
 
             let mut res = Vec::new();
             while let Some(Some(svc)) = services.pop() {
-                for entry in names.values() {
+                for (name, entry) in &names {
                     if entry.idx == services.len() {
                         res.push(NetService {
+                            name: name.clone(),
                             pool: entry.pool,
                             tokens: entry.tokens.clone(),
                             factory: svc,
@@ -224,6 +288,7 @@ struct Entry {
 }
 
 struct ServiceRuntimeInner {
+    worker: WorkerCtx,
     names: HashMap<String, Entry>,
     services: Vec<Option<BoxServerService>>,
 }
@@ -239,13 +304,19 @@ impl fmt::Debug for ServiceRuntime {
 }
 
 impl ServiceRuntime {
-    fn new(names: HashMap<String, Entry>) -> Self {
+    fn new(names: HashMap<String, Entry>, worker: WorkerCtx) -> Self {
         ServiceRuntime(Rc::new(RefCell::new(ServiceRuntimeInner {
             services: (0..names.len()).map(|_| None).collect(),
             names,
+            worker,
         })))
     }
 
+    /// Worker context
+    pub fn worker(&self) -> WorkerCtx {
+        self.0.borrow().worker.clone()
+    }
+
     fn validate(&self) {
         let inner = self.0.as_ref().borrow();
         for (name, item) in &inner.names {
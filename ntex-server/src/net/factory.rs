@@ -6,12 +6,13 @@ use ntex_net::Io;
 use ntex_service::{boxed, Service, ServiceCtx, ServiceFactory};
 use ntex_util::future::{BoxFuture, Ready};
 
-use super::{Config, Token};
+use super::{config::WorkerCtx, Config, Token};
 
 pub(super) type BoxServerService = boxed::BoxServiceFactory<(), Io, (), (), ()>;
 pub(crate) type FactoryServiceType = Box<dyn FactoryService>;
 
 pub(crate) struct NetService {
+    pub(crate) name: String,
     pub(crate) tokens: Vec<(Token, &'static str)>,
     pub(crate) factory: BoxServerService,
     pub(crate) pool: PoolId,
@@ -26,7 +27,7 @@ pub(crate) trait FactoryService: Send {
 
     fn clone_factory(&self) -> Box<dyn FactoryService>;
 
-    fn create(&self) -> BoxFuture<'static, Result<Vec<NetService>, ()>>;
+    fn create(&self, worker: WorkerCtx) -> BoxFuture<'static, Result<Vec<NetService>, ()>>;
 }
 
 pub(crate) fn create_boxed_factory<S>(name: String, factory: S) -> BoxServerService
@@ -89,8 +90,8 @@ where
         }
     }
 
-    fn create(&self) -> BoxFuture<'static, Result<Vec<NetService>, ()>> {
-        let cfg = Config::default();
+    fn create(&self, worker: WorkerCtx) -> BoxFuture<'static, Result<Vec<NetService>, ()>> {
+        let cfg = Config::new(worker);
         let pool = cfg.get_pool_id();
         let name = self.name.clone();
         let tokens = self.tokens.clone();
@@ -102,6 +103,7 @@ where
             })?;
 
             Ok(vec![NetService {
+                name,
                 tokens,
                 factory,
                 pool,
@@ -12,9 +12,9 @@ mod test;
 
 pub use self::accept::{AcceptLoop, AcceptNotify, AcceptorCommand};
 pub use self::builder::{bind_addr, create_tcp_listener, ServerBuilder};
-pub use self::config::{Config, ServiceConfig, ServiceRuntime};
+pub use self::config::{Config, ServiceConfig, ServiceRuntime, WorkerCtx};
 pub use self::service::{ServerMessage, StreamServer};
-pub use self::socket::{Connection, Stream};
+pub use self::socket::{Connection, SocketAddr, Stream};
 pub use self::test::{build_test_server, test_server, TestServer};
 
 pub type Server = crate::Server<Connection>;
@@ -28,6 +28,39 @@ pub enum ServerStatus {
     WorkerFailed,
 }
 
+/// Server startup report
+///
+/// Report contains actual addresses of bound sockets, including os
+/// assigned ports for `:0` binds.
+#[derive(Clone, Debug)]
+pub struct StartupReport {
+    workers: usize,
+    addrs: Vec<(String, SocketAddr)>,
+}
+
+impl StartupReport {
+    /// Number of started workers
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    /// Bound addresses with service names, in bind order
+    pub fn bound_addrs(&self) -> &[(String, SocketAddr)] {
+        &self.addrs
+    }
+}
+
+impl std::fmt::Display for StartupReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Server started with {} workers", self.workers)?;
+        for (idx, (name, addr)) in self.addrs.iter().enumerate() {
+            let sep = if idx == 0 { ": " } else { ", " };
+            write!(f, "{}{:?} on {}", sep, name, addr)?;
+        }
+        Ok(())
+    }
+}
+
 /// Socket id token
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Token(usize);
@@ -1,25 +1,35 @@
-use std::{task::Context, task::Poll};
+use std::sync::{atomic::Ordering, Arc, Mutex};
+use std::{
+    cell::RefCell, future::poll_fn, io, rc::Rc, rc::Weak, task::Context, task::Poll,
+};
 
 use ntex_bytes::{Pool, PoolRef};
 use ntex_net::Io;
-use ntex_service::{boxed, Service, ServiceCtx, ServiceFactory};
-use ntex_util::HashMap;
+use ntex_rt::Arbiter;
+use ntex_service::{boxed, Pipeline, Service, ServiceCtx, ServiceFactory};
+use ntex_util::{future::join_all, HashMap};
 
-use crate::{ServerConfiguration, WorkerMessage};
+use crate::{ServerConfiguration, WorkerId, WorkerMessage};
 
 use super::accept::{AcceptNotify, AcceptorCommand};
-use super::counter::Counter;
-use super::factory::{FactoryServiceType, NetService, OnWorkerStart};
-use super::{socket::Connection, Token, MAX_CONNS_COUNTER};
+use super::config::{Config, WorkerCtx, WorkerDataFn};
+use super::counter::{Counter, CounterGuard};
+use super::factory::{self, FactoryServiceType, NetService, OnWorkerStart};
+use super::{socket::Connection, SocketAddr, Token, MAX_CONNS_COUNTER};
 
 pub type ServerMessage = WorkerMessage<Connection>;
 
 pub(super) type BoxService = boxed::BoxService<Io, (), ()>;
 
+/// Reloaded service factories
+type Reloads = Arc<Mutex<HashMap<String, FactoryServiceType>>>;
+
 pub struct StreamServer {
     notify: AcceptNotify,
     services: Vec<FactoryServiceType>,
     on_worker_start: Vec<Box<dyn OnWorkerStart + Send>>,
+    worker_data: Option<WorkerDataFn>,
+    reloads: Reloads,
 }
 
 impl StreamServer {
@@ -27,11 +37,120 @@ impl StreamServer {
         notify: AcceptNotify,
         services: Vec<FactoryServiceType>,
         on_worker_start: Vec<Box<dyn OnWorkerStart + Send>>,
+        worker_data: Option<WorkerDataFn>,
     ) -> Self {
         Self {
             notify,
             services,
             on_worker_start,
+            worker_data,
+            reloads: Arc::default(),
+        }
+    }
+}
+
+impl crate::Server<Connection> {
+    /// Get bound addresses with service names.
+    ///
+    /// Addresses are actual addresses of listening sockets, so os assigned
+    /// ports for `:0` binds could be discovered after server start.
+    ///
+    /// ```rust,no_run
+    /// # fn addr(srv: ntex_server::net::Server) {
+    /// for (name, addr) in srv.bound_addrs() {
+    ///     println!("Service {:?} listens on {}", name, addr);
+    /// }
+    /// # }
+    /// ```
+    pub fn bound_addrs(&self) -> Vec<(String, SocketAddr)> {
+        self.shared.addrs.lock().unwrap().clone()
+    }
+
+    /// Replace service factory for named service.
+    ///
+    /// New service is created in each available worker, new connections
+    /// are switched to it only after all workers succeed. Otherwise new
+    /// services get dropped and old ones keep handling connections.
+    /// Old service instances get shut down and dropped after active
+    /// connections complete. Listeners are not affected, restarted workers
+    /// use new factory as well. Returned future resolves after all workers
+    /// switched to new service, concurrent reloads are rejected.
+    ///
+    /// ```rust,no_run
+    /// use ntex_service::fn_service;
+    ///
+    /// # async fn reload(srv: ntex_server::net::Server) {
+    /// let result = srv
+    ///     .reload_factory("test", |_| fn_service(|_| async { Ok::<_, ()>(()) }))
+    ///     .await;
+    /// if let Err(e) = result {
+    ///     log::error!("Service reload failed: {}", e);
+    /// }
+    /// # }
+    /// ```
+    pub async fn reload_factory<F, R>(&self, name: &str, factory: F) -> io::Result<()>
+    where
+        F: Fn(Config) -> R + Send + Clone + 'static,
+        R: ServiceFactory<Io> + 'static,
+    {
+        if self.shared.reloading.swap(true, Ordering::AcqRel) {
+            return Err(io::Error::other("Reload is in progress"));
+        }
+        let _guard = ReloadGuard(self.shared.clone());
+
+        let workers = self.workers().await;
+        if workers.is_empty() {
+            return Err(io::Error::other("No available workers"));
+        }
+        let factory =
+            factory::create_factory_service(name.to_string(), Vec::new(), factory);
+
+        // create new service in each worker
+        let mut results = Vec::new();
+        for wrk in &workers {
+            let (tx, rx) = oneshot::channel();
+            let name = name.to_string();
+            let factory = factory.clone_factory();
+            wrk.arbiter().exec_fn(move || {
+                let _ = ntex_rt::spawn(async move {
+                    let _ = tx.send(WorkerServices::prepare(name, factory).await);
+                });
+            });
+            results.push(rx);
+        }
+        let success = join_all(results)
+            .await
+            .into_iter()
+            .all(|res| matches!(res, Ok(Ok(()))));
+
+        // switch connections to new services or drop them
+        let mut results = Vec::new();
+        for wrk in &workers {
+            let (tx, rx) = oneshot::channel();
+            let name = name.to_string();
+            let factory = factory.clone_factory();
+            wrk.arbiter().exec_fn(move || {
+                if let Some(st) = WorkerServices::current() {
+                    if success {
+                        st.commit(name, factory);
+                    } else {
+                        st.pending.borrow_mut().remove(&name);
+                    }
+                }
+                let _ = tx.send(());
+            });
+            results.push(rx);
+        }
+        join_all(results).await;
+
+        if success {
+            log::info!("Service {:?} has been reloaded", name);
+            Ok(())
+        } else {
+            Err(io::Error::other(format!(
+                "Cannot create {:?} service",
+                name
+            )))
         }
     }
 }
@@ -48,13 +167,38 @@ impl ServerConfiguration for StreamServer {
             cb.run().await?;
         }
 
+        // worker context
+        let id = if Arbiter::contains_item::<WorkerId>() {
+            Arbiter::get_item(|id: &WorkerId| *id)
+        } else {
+            WorkerId::default()
+        };
+        let worker = WorkerCtx::new(id, self.worker_data.as_ref());
+
         // construct services
         let mut services = Vec::new();
         for svc in &self.services {
-            services.extend(svc.create().await?);
+            for net in svc.create(worker.clone()).await? {
+                let reload = self
+                    .reloads
+                    .lock()
+                    .unwrap()
+                    .get(&net.name)
+                    .map(|f| f.clone_factory());
+                if let Some(factory) = reload {
+                    let net = create_service(factory, worker.clone(), net.tokens).await?;
+                    services.push(Rc::new(net));
+                } else {
+                    services.push(Rc::new(net));
+                }
+            }
         }
 
-        Ok(StreamService { services })
+        Ok(StreamService {
+            worker,
+            services: Rc::new(RefCell::new(services)),
+            reloads: self.reloads.clone(),
+        })
     }
 
     /// Server is paused
@@ -86,12 +230,110 @@ impl Clone for StreamServer {
             notify: self.notify.clone(),
             services: self.services.iter().map(|s| s.clone_factory()).collect(),
             on_worker_start: self.on_worker_start.iter().map(|f| f.clone_fn()).collect(),
+            worker_data: self.worker_data.clone(),
+            reloads: self.reloads.clone(),
         }
     }
 }
 
+/// Create service with reloaded factory
+async fn create_service(
+    factory: FactoryServiceType,
+    worker: WorkerCtx,
+    tokens: Vec<(Token, &'static str)>,
+) -> Result<NetService, ()> {
+    let mut net = factory.create(worker).await?.pop().ok_or(())?;
+    net.tokens = tokens;
+    Ok(net)
+}
+
+/// Resets reload flag
+struct ReloadGuard(Arc<crate::server::ServerShared>);
+
+impl Drop for ReloadGuard {
+    fn drop(&mut self) {
+        self.0.reloading.store(false, Ordering::Release);
+    }
+}
+
+/// Worker services state, owned by worker service
+struct WorkerServices {
+    worker: WorkerCtx,
+    factories: Rc<RefCell<Vec<Rc<NetService>>>>,
+    active: RefCell<Services>,
+    pending: RefCell<HashMap<String, (NetService, BoxService)>>,
+    reloads: Reloads,
+}
+
+/// Worker's arbiter storage keeps weak reference only, so services
+/// get dropped with worker service
+struct WorkerServicesRef(Weak<WorkerServices>);
+
+impl WorkerServices {
+    fn current() -> Option<Rc<Self>> {
+        if Arbiter::contains_item::<WorkerServicesRef>() {
+            Arbiter::get_item(|st: &WorkerServicesRef| st.0.upgrade())
+        } else {
+            None
+        }
+    }
+
+    /// Create new service, service is not used until commit
+    async fn prepare(name: String, factory: FactoryServiceType) -> Result<(), ()> {
+        let st = Self::current().ok_or(())?;
+        let tokens = st
+            .factories
+            .borrow()
+            .iter()
+            .find(|svc| svc.name == name)
+            .map(|svc| svc.tokens.clone())
+            .ok_or_else(|| log::error!("Unknown service: {:?}", name))?;
+
+        let net = create_service(factory, st.worker.clone(), tokens).await?;
+        let svc = net.factory.create(()).await?;
+        st.pending.borrow_mut().insert(name, (net, svc));
+        Ok(())
+    }
+
+    /// Switch new connections to prepared service
+    fn commit(&self, name: String, factory: FactoryServiceType) {
+        if let Some((net, svc)) = self.pending.borrow_mut().remove(&name) {
+            let mut factories = self.factories.borrow_mut();
+            if let Some(idx) = factories.iter().position(|svc| svc.name == name) {
+                let mut active = self.active.borrow_mut();
+                for (token, _) in &net.tokens {
+                    if let Some(item) = active.tokens.get_mut(token) {
+                        item.2 = net.pool.pool();
+                        item.3 = net.pool.pool_ref();
+                    }
+                }
+                let old =
+                    std::mem::replace(&mut active.services[idx], ActiveService::new(svc));
+                factories[idx] = Rc::new(net);
+
+                // old service handles active connections,
+                // shutdown it after last connection completes
+                let _ = ntex_rt::spawn(async move {
+                    poll_fn(|cx| {
+                        if old.conns.available(cx) {
+                            Poll::Ready(())
+                        } else {
+                            Poll::Pending
+                        }
+                    })
+                    .await;
+                    poll_fn(|cx| old.svc.poll_shutdown(cx)).await;
+                });
+            }
+        }
+        self.reloads.lock().unwrap().insert(name, factory);
+    }
+}
+
 pub struct StreamService {
-    services: Vec<NetService>,
+    worker: WorkerCtx,
+    services: Rc<RefCell<Vec<Rc<NetService>>>>,
+    reloads: Reloads,
 }
 
 impl ServiceFactory<ServerMessage> for StreamService {
@@ -104,10 +346,11 @@ impl ServiceFactory<ServerMessage> for StreamService {
         let mut tokens = HashMap::default();
         let mut services = Vec::new();
 
-        for info in &self.services {
+        let factories = self.services.borrow().clone();
+        for info in &factories {
             match info.factory.create(()).await {
                 Ok(svc) => {
-                    services.push(svc);
+                    services.push(ActiveService::new(svc));
                     let idx = services.len() - 1;
                     for (token, tag) in &info.tokens {
                         tokens.insert(
@@ -124,18 +367,46 @@ impl ServiceFactory<ServerMessage> for StreamService {
         }
 
         let conns = MAX_CONNS_COUNTER.with(|conns| conns.priv_clone());
+        let state = Rc::new(WorkerServices {
+            worker: self.worker.clone(),
+            factories: self.services.clone(),
+            active: RefCell::new(Services { tokens, services }),
+            pending: RefCell::default(),
+            reloads: self.reloads.clone(),
+        });
+        Arbiter::set_item(WorkerServicesRef(Rc::downgrade(&state)));
 
-        Ok(StreamServiceImpl {
-            tokens,
-            services,
-            conns,
-        })
+        Ok(StreamServiceImpl { state, conns })
     }
 }
 
-pub struct StreamServiceImpl {
+struct Services {
     tokens: HashMap<Token, (usize, &'static str, Pool, PoolRef)>,
-    services: Vec<BoxService>,
+    services: Vec<ActiveService>,
+}
+
+/// Service instance with its active connections
+struct ActiveService {
+    svc: Pipeline<BoxService>,
+    conns: Counter,
+}
+
+impl ActiveService {
+    fn new(svc: BoxService) -> Self {
+        // counter notifies when last connection completes
+        Self {
+            svc: Pipeline::new(svc),
+            conns: Counter::new(1),
+        }
+    }
+
+    fn get(&self) -> (Pipeline<BoxService>, CounterGuard) {
+        (self.svc.clone(), self.conns.get())
+    }
+}
+
+pub struct StreamServiceImpl {
+    state: Rc<WorkerServices>,
     conns: Counter,
 }
 
@@ -144,13 +415,14 @@ impl Service<ServerMessage> for StreamServiceImpl {
     type Error = ();
 
     fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let active = self.state.active.borrow();
         let mut ready = self.conns.available(cx);
-        for (idx, svc) in self.services.iter().enumerate() {
-            match svc.poll_ready(cx) {
+        for (idx, item) in active.services.iter().enumerate() {
+            match item.svc.poll_ready(cx) {
                 Poll::Pending => ready = false,
                 Poll::Ready(Ok(())) => (),
                 Poll::Ready(Err(_)) => {
-                    for (idx_, tag, _, _) in self.tokens.values() {
+                    for (idx_, tag, _, _) in active.tokens.values() {
                         if idx == *idx_ {
                             log::error!("{}: Service readiness has failed", tag);
                             break;
@@ -162,7 +434,7 @@ impl Service<ServerMessage> for StreamServiceImpl {
         }
 
         // check memory pools
-        for (_, _, pool, _) in self.tokens.values() {
+        for (_, _, pool, _) in active.tokens.values() {
             ready = pool.poll_ready(cx).is_ready() && ready;
         }
 
@@ -175,8 +447,8 @@ impl Service<ServerMessage> for StreamServiceImpl {
 
     fn poll_shutdown(&self, cx: &mut Context<'_>) -> Poll<()> {
         let mut ready = true;
-        for svc in &self.services {
-            match svc.poll_shutdown(cx) {
+        for item in &self.state.active.borrow().services {
+            match item.svc.poll_shutdown(cx) {
                 Poll::Pending => ready = false,
                 Poll::Ready(_) => (),
             }
@@ -192,18 +464,25 @@ impl Service<ServerMessage> for StreamServiceImpl {
         }
     }
 
-    async fn call(&self, req: ServerMessage, ctx: ServiceCtx<'_, Self>) -> Result<(), ()> {
+    async fn call(&self, req: ServerMessage, _: ServiceCtx<'_, Self>) -> Result<(), ()> {
         match req {
             ServerMessage::New(con) => {
-                if let Some((idx, tag, _, pool)) = self.tokens.get(&con.token) {
+                let item = {
+                    let active = self.state.active.borrow();
+                    active.tokens.get(&con.token).map(|(idx, tag, _, pool)| {
+                        (active.services[*idx].get(), *tag, *pool)
+                    })
+                };
+
+                if let Some(((svc, _active), tag, pool)) = item {
                     let stream: Io<_> = con.io.try_into().map_err(|e| {
                         log::error!("Cannot convert to an async io stream: {}", e);
                     })?;
 
                     stream.set_tag(tag);
-                    stream.set_memory_pool(*pool);
+                    stream.set_memory_pool(pool);
                     let guard = self.conns.get();
-                    let _ = ctx.call(&self.services[*idx], stream).await;
+                    let _ = svc.call(stream).await;
                     drop(guard);
                     Ok(())
                 } else {
@@ -38,12 +38,34 @@ impl fmt::Display for Listener {
     }
 }
 
-pub(crate) enum SocketAddr {
+#[derive(Clone)]
+/// Bound socket address
+pub enum SocketAddr {
     Tcp(net::SocketAddr),
     #[cfg(unix)]
     Uds(std::os::unix::net::SocketAddr),
 }
 
+impl SocketAddr {
+    /// Get tcp socket address
+    pub fn as_tcp(&self) -> Option<net::SocketAddr> {
+        match self {
+            SocketAddr::Tcp(addr) => Some(*addr),
+            #[cfg(unix)]
+            SocketAddr::Uds(_) => None,
+        }
+    }
+
+    #[cfg(unix)]
+    /// Get unix domain socket address
+    pub fn as_uds(&self) -> Option<&std::os::unix::net::SocketAddr> {
+        match self {
+            SocketAddr::Tcp(_) => None,
+            SocketAddr::Uds(addr) => Some(addr),
+        }
+    }
+}
+
 impl fmt::Display for SocketAddr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
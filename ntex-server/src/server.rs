@@ -1,20 +1,22 @@
-use std::sync::{atomic::AtomicBool, atomic::Ordering, Arc};
+use std::sync::{atomic::AtomicBool, atomic::Ordering, Arc, Mutex};
 use std::task::{ready, Context, Poll};
 use std::{future::Future, io, pin::Pin};
 
 use async_channel::Sender;
 
-use crate::{manager::ServerCommand, signals::Signal};
+use crate::{manager::ServerCommand, signal::Signal, Worker};
 
 #[derive(Debug)]
 pub(crate) struct ServerShared {
     pub(crate) paused: AtomicBool,
+    pub(crate) reloading: AtomicBool,
+    pub(crate) addrs: Mutex<Vec<(String, crate::net::SocketAddr)>>,
 }
 
 /// Server controller
 #[derive(Debug)]
 pub struct Server<T> {
-    shared: Arc<ServerShared>,
+    pub(crate) shared: Arc<ServerShared>,
     cmd: Sender<ServerCommand<T>>,
     stop: Option<oneshot::Receiver<()>>,
 }
@@ -37,6 +39,13 @@ impl<T> Server<T> {
         let _ = self.cmd.try_send(ServerCommand::Signal(sig));
     }
 
+    /// Get available workers
+    pub(crate) async fn workers(&self) -> Vec<Worker<T>> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.cmd.try_send(ServerCommand::Workers(tx));
+        rx.await.unwrap_or_default()
+    }
+
     /// Send item to worker pool
     pub fn process(&mut self, item: T) -> Result<(), T> {
         if self.shared.paused.load(Ordering::Acquire) {
@@ -0,0 +1,215 @@
+//! Process signals handling
+//!
+//! Applications could subscribe to process signals, for example to reload
+//! configuration on `SIGHUP`. Subscriptions are independent from server's
+//! own signals processing, server still handles `SIGINT`, `SIGTERM` and
+//! `SIGQUIT` signals.
+use std::{io, thread};
+
+use async_channel::{unbounded, Receiver};
+
+use crate::server::Server;
+
+/// Different types of process signals
+#[non_exhaustive]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Signal {
+    /// SIGHUP
+    Hup,
+    /// SIGINT
+    Int,
+    /// SIGTERM
+    Term,
+    /// SIGQUIT
+    Quit,
+    /// SIGUSR1
+    Usr1,
+    /// SIGUSR2
+    Usr2,
+}
+
+/// Stream of process signals
+///
+/// Signals are not delivered to the stream after it is dropped.
+#[derive(Debug)]
+pub struct SignalStream {
+    rx: Receiver<Signal>,
+    #[cfg(target_family = "unix")]
+    handle: signal_hook::iterator::Handle,
+}
+
+impl SignalStream {
+    /// Wait for next signal.
+    ///
+    /// Returns `None` if none of subscribed signals is supported by the platform.
+    pub async fn recv(&self) -> Option<Signal> {
+        self.rx.recv().await.ok()
+    }
+}
+
+/// Subscribe to process signal.
+pub fn signal(sig: Signal) -> io::Result<SignalStream> {
+    signals([sig])
+}
+
+#[cfg(target_family = "unix")]
+impl Signal {
+    fn raw(self) -> std::os::raw::c_int {
+        use signal_hook::consts::signal::*;
+
+        match self {
+            Signal::Hup => SIGHUP,
+            Signal::Int => SIGINT,
+            Signal::Term => SIGTERM,
+            Signal::Quit => SIGQUIT,
+            Signal::Usr1 => SIGUSR1,
+            Signal::Usr2 => SIGUSR2,
+        }
+    }
+
+    fn from_raw(sig: std::os::raw::c_int) -> Option<Signal> {
+        [
+            Signal::Hup,
+            Signal::Int,
+            Signal::Term,
+            Signal::Quit,
+            Signal::Usr1,
+            Signal::Usr2,
+        ]
+        .into_iter()
+        .find(|s| s.raw() == sig)
+    }
+}
+
+#[cfg(target_family = "unix")]
+/// Subscribe to process signals.
+pub fn signals<I>(sigs: I) -> io::Result<SignalStream>
+where
+    I: IntoIterator<Item = Signal>,
+{
+    let mut signals = signal_hook::iterator::Signals::new(sigs.into_iter().map(Signal::raw))?;
+    let handle = signals.handle();
+    let (tx, rx) = unbounded();
+
+    thread::Builder::new()
+        .name("ntex-server user signals".to_string())
+        .spawn(move || {
+            for info in &mut signals {
+                if let Some(sig) = Signal::from_raw(info) {
+                    if tx.send_blocking(sig).is_err() {
+                        return;
+                    }
+                }
+            }
+        })?;
+
+    Ok(SignalStream { rx, handle })
+}
+
+#[cfg(target_family = "unix")]
+impl Drop for SignalStream {
+    fn drop(&mut self) {
+        self.handle.close();
+    }
+}
+
+#[cfg(target_family = "unix")]
+/// Register signal handler.
+///
+/// Signals are handled by oneshots, you have to re-register
+/// after each signal.
+pub(crate) fn start<T: Send + 'static>(srv: Server<T>) {
+    let _ = thread::Builder::new()
+        .name("ntex-server signals".to_string())
+        .spawn(move || {
+            use signal_hook::consts::signal::*;
+            use signal_hook::iterator::Signals;
+
+            let sigs = vec![SIGHUP, SIGINT, SIGTERM, SIGQUIT];
+            let mut signals = match Signals::new(sigs) {
+                Ok(signals) => signals,
+                Err(e) => {
+                    log::error!("Cannot initialize signals handler: {}", e);
+                    return;
+                }
+            };
+            for info in &mut signals {
+                match info {
+                    SIGHUP => srv.signal(Signal::Hup),
+                    SIGTERM => srv.signal(Signal::Term),
+                    SIGINT => {
+                        srv.signal(Signal::Int);
+                        return;
+                    }
+                    SIGQUIT => {
+                        srv.signal(Signal::Quit);
+                        return;
+                    }
+                    _ => {}
+                }
+            }
+        });
+}
+
+#[cfg(target_family = "windows")]
+/// Ctrl-C handler could be set only once, subscribers share it
+static SUBSCRIBERS: std::sync::Mutex<Option<Vec<async_channel::Sender<Signal>>>> =
+    std::sync::Mutex::new(None);
+
+#[cfg(target_family = "windows")]
+/// Subscribe to process signals.
+///
+/// Only `Signal::Int` (Ctrl-C) is supported on windows.
+pub fn signals<I>(sigs: I) -> io::Result<SignalStream>
+where
+    I: IntoIterator<Item = Signal>,
+{
+    let (tx, rx) = unbounded();
+
+    if sigs.into_iter().any(|sig| sig == Signal::Int) {
+        let mut subscribers = SUBSCRIBERS.lock().unwrap();
+        if subscribers.is_none() {
+            ctrlc::set_handler(|| {
+                if let Some(ref mut subscribers) = *SUBSCRIBERS.lock().unwrap() {
+                    subscribers.retain(|tx| tx.try_send(Signal::Int).is_ok());
+                }
+            })
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            *subscribers = Some(Vec::new());
+        }
+        subscribers.as_mut().unwrap().push(tx);
+    }
+
+    Ok(SignalStream { rx })
+}
+
+#[cfg(target_family = "windows")]
+/// Register signal handler.
+pub(crate) fn start<T: Send + 'static>(srv: Server<T>) {
+    match signals([Signal::Int]) {
+        Ok(stream) => {
+            let _ = thread::Builder::new()
+                .name("ntex-server signals".to_string())
+                .spawn(move || {
+                    if let Ok(sig) = stream.rx.recv_blocking() {
+                        srv.signal(sig);
+                    }
+                });
+        }
+        Err(e) => log::error!("Cannot initialize signals handler: {}", e),
+    }
+}
+
+#[cfg(all(test, target_family = "unix"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signals() {
+        let stream = signals([Signal::Usr1, Signal::Usr2]).unwrap();
+        signal_hook::low_level::raise(signal_hook::consts::SIGUSR2).unwrap();
+        assert_eq!(stream.rx.recv_blocking(), Ok(Signal::Usr2));
+        signal_hook::low_level::raise(signal_hook::consts::SIGUSR1).unwrap();
+        assert_eq!(stream.rx.recv_blocking(), Ok(Signal::Usr1));
+    }
+}
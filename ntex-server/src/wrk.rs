@@ -40,6 +40,7 @@ pub struct Worker<T> {
     tx2: Sender<Shutdown>,
     avail: WorkerAvailability,
     failed: Arc<AtomicBool>,
+    arbiter: Arbiter,
 }
 
 impl<T> cmp::Ord for Worker<T> {
@@ -86,7 +87,9 @@ impl<T> Worker<T> {
         let (tx2, rx2) = unbounded();
         let (avail, avail_tx) = WorkerAvailability::create();
 
-        Arbiter::default().exec_fn(move || {
+        let arbiter = Arbiter::default();
+        arbiter.exec_fn(move || {
+            Arbiter::set_item(id);
             let _ = spawn(async move {
                 log::info!("Starting worker {:?}", id);
 
@@ -111,6 +114,7 @@ impl<T> Worker<T> {
             tx1,
             tx2,
             avail,
+            arbiter,
             failed: Arc::new(AtomicBool::new(false)),
         }
     }
@@ -120,6 +124,11 @@ impl<T> Worker<T> {
         self.id
     }
 
+    /// Worker's arbiter.
+    pub(crate) fn arbiter(&self) -> &Arbiter {
+        &self.arbiter
+    }
+
     /// Send message to the worker.
     ///
     /// Returns `Ok` if message got accepted by the worker.
@@ -172,6 +181,7 @@ impl<T> Clone for Worker<T> {
             tx2: self.tx2.clone(),
             avail: self.avail.clone(),
             failed: self.failed.clone(),
+            arbiter: self.arbiter.clone(),
         }
     }
 }
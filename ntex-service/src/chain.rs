@@ -7,7 +7,9 @@ use crate::ctx::ServiceCtx;
 use crate::map::{Map, MapFactory};
 use crate::map_err::{MapErr, MapErrFactory};
 use crate::map_init_err::MapInitErr;
-use crate::middleware::{ApplyMiddleware, Middleware};
+use crate::middleware::{
+    ApplyMiddleware, ApplyMiddlewareFactory, Middleware, MiddlewareFactory,
+};
 use crate::then::{Then, ThenFactory};
 use crate::{IntoService, IntoServiceFactory, Pipeline, Service, ServiceFactory};
 
@@ -220,6 +222,22 @@ impl<T: ServiceFactory<Req, C>, Req, C> ServiceChainFactory<T, Req, C> {
         }
     }
 
+    /// Apply async middleware factory to current service factory.
+    ///
+    /// Short version of `apply_factory(middleware, chain_factory(...))`
+    pub fn apply_factory<U>(
+        self,
+        tr: U,
+    ) -> ServiceChainFactory<ApplyMiddlewareFactory<U, T, C>, Req, C>
+    where
+        U: MiddlewareFactory<T::Service, C>,
+    {
+        ServiceChainFactory {
+            factory: ApplyMiddlewareFactory::new(tr, self.factory),
+            _t: PhantomData,
+        }
+    }
+
     /// Apply function middleware to current service factory.
     ///
     /// Short version of `apply_fn_factory(chain_factory(...), fn)`
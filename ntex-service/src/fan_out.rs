@@ -0,0 +1,377 @@
+use std::{fmt, future::poll_fn, future::Future, pin::Pin, task::Context, task::Poll};
+
+use super::{Service, ServiceCtx, ServiceFactory};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// Responses aggregation policy for `FanOut` service
+pub enum FanOutPolicy {
+    /// Wait for responses of all services, fail on first error
+    All,
+    /// Wait for first successful response, fail if all services fail
+    FirstOk,
+    /// Wait for specified number of successful responses, fail if quorum
+    /// cannot be reached
+    Quorum(usize),
+}
+
+/// Error for `FanOut` service
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FanOutError<E> {
+    /// Service error
+    Service(E),
+    /// No services to call for `FirstOk` policy
+    Empty,
+}
+
+impl<E> FanOutError<E> {
+    /// Get service error
+    pub fn into_service_error(self) -> Option<E> {
+        match self {
+            FanOutError::Service(e) => Some(e),
+            FanOutError::Empty => None,
+        }
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for FanOutError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FanOutError::Service(e) => e.fmt(f),
+            FanOutError::Empty => write!(f, "No services to call"),
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for FanOutError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FanOutError::Service(e) => Some(e),
+            FanOutError::Empty => None,
+        }
+    }
+}
+
+/// Create `FanOut` service for list of services.
+///
+/// Request is cloned and sent to all services concurrently, responses
+/// are aggregated according to the policy. By default `FanOutPolicy::All`
+/// policy is used. Services of different types could be combined with
+/// [`boxed::service()`](crate::boxed::service).
+///
+/// ```rust
+/// use ntex_service::{boxed, fan_out, fn_service, FanOutPolicy, Pipeline};
+///
+/// # async fn run() {
+/// let srv = Pipeline::new(
+///     fan_out(vec![
+///         boxed::service(fn_service(|req: u32| async move { Ok::<_, ()>(req + 1) })),
+///         boxed::service(fn_service(|req: u32| async move { Ok::<_, ()>(req + 2) })),
+///         boxed::service(fn_service(|req: u32| async move { Ok::<_, ()>(req + 3) })),
+///     ])
+///     .policy(FanOutPolicy::Quorum(2)),
+/// );
+///
+/// let res = srv.call(1).await.unwrap();
+/// assert_eq!(res.len(), 2);
+/// # }
+/// ```
+pub fn fan_out<S>(services: Vec<S>) -> FanOut<S> {
+    FanOut {
+        services,
+        policy: FanOutPolicy::All,
+    }
+}
+
+/// Create `FanOutFactory` for list of service factories.
+pub fn fan_out_factory<F>(factories: Vec<F>) -> FanOutFactory<F> {
+    FanOutFactory {
+        factories,
+        policy: FanOutPolicy::All,
+    }
+}
+
+fn check_policy(policy: FanOutPolicy, num: usize) {
+    if let FanOutPolicy::Quorum(quorum) = policy {
+        assert!(
+            quorum > 0 && quorum <= num,
+            "Quorum must be in range 1..={}",
+            num
+        );
+    }
+}
+
+#[derive(Clone, Debug)]
+/// Service for the `fan_out` combinator, sends request to all services
+/// concurrently and aggregates responses.
+pub struct FanOut<S> {
+    services: Vec<S>,
+    policy: FanOutPolicy,
+}
+
+impl<S> FanOut<S> {
+    /// Set responses aggregation policy.
+    ///
+    /// Panics if quorum is zero or larger than number of services.
+    pub fn policy(mut self, policy: FanOutPolicy) -> Self {
+        check_policy(policy, self.services.len());
+        self.policy = policy;
+        self
+    }
+}
+
+impl<S, Req> Service<Req> for FanOut<S>
+where
+    S: Service<Req>,
+    Req: Clone,
+{
+    /// Responses in services order for `All` policy, otherwise in
+    /// completion order
+    type Response = Vec<S::Response>;
+    type Error = FanOutError<S::Error>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let mut ready = true;
+        for svc in &self.services {
+            ready &= svc.poll_ready(cx).map_err(FanOutError::Service)?.is_ready();
+        }
+        if ready {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn poll_shutdown(&self, cx: &mut Context<'_>) -> Poll<()> {
+        let mut ready = true;
+        for svc in &self.services {
+            ready &= svc.poll_shutdown(cx).is_ready();
+        }
+        if ready {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    async fn call(
+        &self,
+        req: Req,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        let num = self.services.len();
+        if num == 0 && self.policy == FanOutPolicy::FirstOk {
+            return Err(FanOutError::Empty);
+        }
+
+        let mut futs: Vec<_> = self
+            .services
+            .iter()
+            .map(|svc| Some(Box::pin(ctx.call(svc, req.clone()))))
+            .collect();
+        drop(req);
+
+        let mut results: Vec<Option<S::Response>> = Vec::new();
+        results.resize_with(num, || None);
+        let mut completed = Vec::new();
+        let mut failed = 0;
+
+        poll_fn(|cx| {
+            for (idx, slot) in futs.iter_mut().enumerate() {
+                let res = if let Some(fut) = slot {
+                    match Pin::new(fut).poll(cx) {
+                        Poll::Ready(res) => res,
+                        Poll::Pending => continue,
+                    }
+                } else {
+                    continue;
+                };
+                *slot = None;
+
+                match (self.policy, res) {
+                    (FanOutPolicy::All, Ok(res)) => results[idx] = Some(res),
+                    (FanOutPolicy::All, Err(e)) => {
+                        return Poll::Ready(Err(FanOutError::Service(e)))
+                    }
+                    (FanOutPolicy::FirstOk, Ok(res)) => return Poll::Ready(Ok(vec![res])),
+                    (FanOutPolicy::Quorum(quorum), Ok(res)) => {
+                        completed.push(res);
+                        if completed.len() >= quorum {
+                            return Poll::Ready(Ok(std::mem::take(&mut completed)));
+                        }
+                    }
+                    (FanOutPolicy::FirstOk, Err(e)) => {
+                        failed += 1;
+                        if failed == num {
+                            return Poll::Ready(Err(FanOutError::Service(e)));
+                        }
+                    }
+                    (FanOutPolicy::Quorum(quorum), Err(e)) => {
+                        failed += 1;
+                        if num - failed < quorum {
+                            return Poll::Ready(Err(FanOutError::Service(e)));
+                        }
+                    }
+                }
+            }
+
+            if futs.iter().all(Option::is_none) {
+                Poll::Ready(Ok(results.drain(..).flatten().collect()))
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+#[derive(Clone, Debug)]
+/// `fan_out_factory()` service factory combinator
+pub struct FanOutFactory<F> {
+    factories: Vec<F>,
+    policy: FanOutPolicy,
+}
+
+impl<F> FanOutFactory<F> {
+    /// Set responses aggregation policy.
+    ///
+    /// Panics if quorum is zero or larger than number of factories.
+    pub fn policy(mut self, policy: FanOutPolicy) -> Self {
+        check_policy(policy, self.factories.len());
+        self.policy = policy;
+        self
+    }
+}
+
+impl<F, Req, Cfg> ServiceFactory<Req, Cfg> for FanOutFactory<F>
+where
+    F: ServiceFactory<Req, Cfg>,
+    Req: Clone,
+    Cfg: Clone,
+{
+    type Response = Vec<F::Response>;
+    type Error = FanOutError<F::Error>;
+
+    type Service = FanOut<F::Service>;
+    type InitError = F::InitError;
+
+    async fn create(&self, cfg: Cfg) -> Result<Self::Service, Self::InitError> {
+        let mut services = Vec::with_capacity(self.factories.len());
+        for factory in &self.factories {
+            services.push(factory.create(cfg.clone()).await?);
+        }
+        Ok(FanOut {
+            services,
+            policy: self.policy,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use ntex_util::{future::lazy, time};
+
+    use super::*;
+    use crate::{boxed, fn_factory, fn_service, Pipeline};
+
+    #[ntex::test]
+    async fn test_poll_ready() {
+        let cnt = Rc::new(Cell::new(0));
+        let cnt2 = cnt.clone();
+        let srv = fan_out(vec![
+            boxed::service(fn_service(|_: ()| async { Ok::<_, ()>(()) })),
+            boxed::service(fn_service(|_: ()| async { Ok::<_, ()>(()) })),
+        ]);
+        let res = lazy(|cx| Service::<()>::poll_ready(&srv, cx)).await;
+        assert_eq!(res, Poll::Ready(Ok(())));
+        let res = lazy(|cx| Service::<()>::poll_shutdown(&srv, cx)).await;
+        assert_eq!(res, Poll::Ready(()));
+
+        let srv = Pipeline::new(fan_out(vec![
+            boxed::service(fn_service(move |_: ()| {
+                cnt2.set(cnt2.get() + 1);
+                async { Ok::<_, ()>(1) }
+            })),
+            boxed::service(fn_service(|_: ()| async { Ok::<_, ()>(2) })),
+        ]));
+        assert_eq!(srv.call(()).await, Ok(vec![1, 2]));
+        assert_eq!(cnt.get(), 1);
+    }
+
+    #[ntex::test]
+    async fn test_policies() {
+        let srv = || {
+            fan_out(vec![
+                boxed::service(fn_service(|_: ()| async {
+                    time::sleep(time::Millis(50)).await;
+                    Ok::<_, &'static str>(1)
+                })),
+                boxed::service(fn_service(|_: ()| async { Err::<usize, _>("err") })),
+                boxed::service(fn_service(|_: ()| async { Ok(3) })),
+            ])
+        };
+
+        let res = Pipeline::new(srv()).call(()).await;
+        assert_eq!(res, Err(FanOutError::Service("err")));
+
+        let res = Pipeline::new(srv().policy(FanOutPolicy::FirstOk))
+            .call(())
+            .await;
+        assert_eq!(res, Ok(vec![3]));
+
+        let res = Pipeline::new(srv().policy(FanOutPolicy::Quorum(2)))
+            .call(())
+            .await;
+        assert_eq!(res, Ok(vec![3, 1]));
+
+        let res = Pipeline::new(srv().policy(FanOutPolicy::Quorum(3)))
+            .call(())
+            .await;
+        assert_eq!(res, Err(FanOutError::Service("err")));
+        assert_eq!(res.unwrap_err().into_service_error(), Some("err"));
+    }
+
+    #[ntex::test]
+    async fn test_empty() {
+        let srv = Pipeline::new(fan_out(Vec::<boxed::BoxService<(), (), ()>>::new()));
+        assert_eq!(srv.call(()).await, Ok(vec![]));
+
+        let srv = Pipeline::new(
+            fan_out(Vec::<boxed::BoxService<(), (), ()>>::new())
+                .policy(FanOutPolicy::FirstOk),
+        );
+        let err = srv.call(()).await.unwrap_err();
+        assert_eq!(err, FanOutError::Empty);
+        assert_eq!(err.into_service_error(), None);
+        assert_eq!(
+            format!("{}", FanOutError::<&str>::Empty),
+            "No services to call"
+        );
+    }
+
+    #[ntex::test]
+    #[should_panic]
+    async fn test_invalid_quorum() {
+        let _ = fan_out(vec![boxed::service(fn_service(|_: ()| async {
+            Ok::<_, ()>(())
+        }))])
+        .policy(FanOutPolicy::Quorum(2));
+    }
+
+    #[ntex::test]
+    async fn test_factory() {
+        let factory = fan_out_factory(vec![
+            boxed::factory(fn_factory(|| async {
+                Ok::<_, ()>(fn_service(|r: u32| async move { Ok::<_, ()>(r) }))
+            })),
+            boxed::factory(fn_factory(|| async {
+                Ok::<_, ()>(fn_service(|r: u32| async move { Ok::<_, ()>(r * 2) }))
+            })),
+        ])
+        .policy(FanOutPolicy::FirstOk);
+
+        let srv = factory.pipeline(&()).await.unwrap();
+        assert_eq!(srv.call(1).await, Ok(vec![1]));
+    }
+}
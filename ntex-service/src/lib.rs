@@ -13,6 +13,7 @@ mod apply;
 pub mod boxed;
 mod chain;
 mod ctx;
+mod fan_out;
 mod fn_service;
 mod fn_shutdown;
 mod macros;
@@ -27,10 +28,15 @@ mod then;
 pub use self::apply::{apply_fn, apply_fn_factory};
 pub use self::chain::{chain, chain_factory};
 pub use self::ctx::ServiceCtx;
+pub use self::fan_out::{
+    fan_out, fan_out_factory, FanOut, FanOutError, FanOutFactory, FanOutPolicy,
+};
 pub use self::fn_service::{fn_factory, fn_factory_with_config, fn_service};
 pub use self::fn_shutdown::fn_shutdown;
 pub use self::map_config::{map_config, unit_config};
-pub use self::middleware::{apply, Identity, Middleware, Stack};
+pub use self::middleware::{
+    apply, apply_factory, Identity, Middleware, MiddlewareFactory, Stack,
+};
 pub use self::pipeline::{Pipeline, PipelineCall};
 
 #[allow(unused_variables)]
@@ -372,6 +378,6 @@ pub mod dev {
     pub use crate::map_config::{MapConfig, UnitConfig};
     pub use crate::map_err::{MapErr, MapErrFactory};
     pub use crate::map_init_err::MapInitErr;
-    pub use crate::middleware::ApplyMiddleware;
+    pub use crate::middleware::{ApplyMiddleware, ApplyMiddlewareFactory};
     pub use crate::then::{Then, ThenFactory};
 }
@@ -36,3 +36,53 @@ macro_rules! forward_poll_shutdown {
         }
     };
 }
+
+/// Construct service factory pipeline.
+///
+/// First element is a service factory, every next element is either
+/// a middleware or one of `ServiceChainFactory` combinators, like
+/// `map_err(..)`, `map_init_err(..)`, `map(..)`, `and_then(..)`, `then(..)`
+/// or `apply_fn(..)`. Elements are applied in order, so the last element
+/// wraps all previous ones.
+///
+/// ```rust,ignore
+/// let factory = chain!(
+///     fn_service(|req: Request| async move { Ok::<_, Error>(req) })
+///         => Logger::default()
+///         => Timeout::new(Millis(500))
+///         => map_err(|e| AppError::from(e))
+/// );
+/// ```
+///
+/// Expands to `chain_factory(factory).apply(Logger::default()).apply(...).map_err(...)`.
+#[macro_export]
+macro_rules! chain {
+    ($factory:expr $(=> $($rest:tt)+)?) => {
+        $crate::chain!(@chain $crate::chain_factory($factory); $($($rest)+)?)
+    };
+
+    (@chain $acc:expr;) => {
+        $acc
+    };
+    (@chain $acc:expr; map_err($($args:tt)*) $(=> $($rest:tt)+)?) => {
+        $crate::chain!(@chain $acc.map_err($($args)*); $($($rest)+)?)
+    };
+    (@chain $acc:expr; map_init_err($($args:tt)*) $(=> $($rest:tt)+)?) => {
+        $crate::chain!(@chain $acc.map_init_err($($args)*); $($($rest)+)?)
+    };
+    (@chain $acc:expr; map($($args:tt)*) $(=> $($rest:tt)+)?) => {
+        $crate::chain!(@chain $acc.map($($args)*); $($($rest)+)?)
+    };
+    (@chain $acc:expr; and_then($($args:tt)*) $(=> $($rest:tt)+)?) => {
+        $crate::chain!(@chain $acc.and_then($($args)*); $($($rest)+)?)
+    };
+    (@chain $acc:expr; then($($args:tt)*) $(=> $($rest:tt)+)?) => {
+        $crate::chain!(@chain $acc.then($($args)*); $($($rest)+)?)
+    };
+    (@chain $acc:expr; apply_fn($($args:tt)*) $(=> $($rest:tt)+)?) => {
+        $crate::chain!(@chain $acc.apply_fn($($args)*); $($($rest)+)?)
+    };
+    (@chain $acc:expr; $mw:expr $(=> $($rest:tt)+)?) => {
+        $crate::chain!(@chain $acc.apply($mw); $($($rest)+)?)
+    };
+}
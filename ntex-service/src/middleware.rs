@@ -1,4 +1,4 @@
-use std::{fmt, marker::PhantomData, rc::Rc};
+use std::{fmt, future::Future, marker::PhantomData, rc::Rc};
 
 use crate::{IntoServiceFactory, Service, ServiceFactory};
 
@@ -140,6 +140,111 @@ where
     }
 }
 
+/// Apply async middleware factory to a service.
+pub fn apply_factory<T, S, R, C, U>(t: T, factory: U) -> ApplyMiddlewareFactory<T, S, C>
+where
+    S: ServiceFactory<R, C>,
+    T: MiddlewareFactory<S::Service, C>,
+    U: IntoServiceFactory<S, R, C>,
+{
+    ApplyMiddlewareFactory::new(t, factory.into_factory())
+}
+
+/// The `MiddlewareFactory` trait defines the interface of a middleware factory
+/// that initializes asynchronously with access to factory's config.
+///
+/// Unlike [`Middleware`], it could use per-worker config (keys, limits) during
+/// construction and could fail with typed init error. Inner service factory
+/// receives the same config, so config must be `Clone`.
+///
+/// ```rust,ignore
+/// impl<S> MiddlewareFactory<S, AppConfig> for RateLimit {
+///     type Service = RateLimitService<S>;
+///     type InitError = RateLimitError;
+///
+///     async fn create(&self, service: S, cfg: AppConfig) -> Result<Self::Service, Self::InitError> {
+///         let limits = load_limits(cfg.limits_path()).await?;
+///         Ok(RateLimitService { service, limits })
+///     }
+/// }
+/// ```
+pub trait MiddlewareFactory<S, C = ()> {
+    /// The middleware `Service` value created by this factory
+    type Service;
+
+    /// Errors potentially raised while building a middleware service.
+    type InitError;
+
+    /// Creates and returns a new middleware Service
+    fn create(
+        &self,
+        service: S,
+        cfg: C,
+    ) -> impl Future<Output = Result<Self::Service, Self::InitError>>;
+}
+
+impl<T, S, C> MiddlewareFactory<S, C> for Rc<T>
+where
+    T: MiddlewareFactory<S, C>,
+{
+    type Service = T::Service;
+    type InitError = T::InitError;
+
+    async fn create(&self, service: S, cfg: C) -> Result<T::Service, T::InitError> {
+        self.as_ref().create(service, cfg).await
+    }
+}
+
+/// `Apply` async middleware factory to a service factory.
+pub struct ApplyMiddlewareFactory<T, S, C>(Rc<(T, S)>, PhantomData<C>);
+
+impl<T, S, C> ApplyMiddlewareFactory<T, S, C> {
+    /// Create new `ApplyMiddlewareFactory` service factory instance
+    pub(crate) fn new(mw: T, svc: S) -> Self {
+        Self(Rc::new((mw, svc)), PhantomData)
+    }
+}
+
+impl<T, S, C> Clone for ApplyMiddlewareFactory<T, S, C> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone(), PhantomData)
+    }
+}
+
+impl<T, S, C> fmt::Debug for ApplyMiddlewareFactory<T, S, C>
+where
+    T: fmt::Debug,
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApplyMiddlewareFactory")
+            .field("service", &self.0 .1)
+            .field("middleware", &self.0 .0)
+            .finish()
+    }
+}
+
+impl<T, S, R, C> ServiceFactory<R, C> for ApplyMiddlewareFactory<T, S, C>
+where
+    S: ServiceFactory<R, C>,
+    T: MiddlewareFactory<S::Service, C>,
+    T::Service: Service<R>,
+    T::InitError: From<S::InitError>,
+    C: Clone,
+{
+    type Response = <T::Service as Service<R>>::Response;
+    type Error = <T::Service as Service<R>>::Error;
+
+    type Service = T::Service;
+    type InitError = T::InitError;
+
+    #[inline]
+    async fn create(&self, cfg: C) -> Result<Self::Service, Self::InitError> {
+        let svc = self.0 .1.create(cfg.clone()).await?;
+        self.0 .0.create(svc, cfg).await
+    }
+}
+
 /// Identity is a middleware.
 ///
 /// It returns service without modifications.
@@ -257,4 +362,71 @@ mod tests {
         let res = lazy(|cx| srv.poll_shutdown(cx)).await;
         assert_eq!(res, Poll::Ready(()));
     }
+
+    #[derive(Debug, Clone)]
+    struct TrCfg;
+
+    impl<S> MiddlewareFactory<S, usize> for TrCfg {
+        type Service = Mul<S>;
+        type InitError = ();
+
+        async fn create(&self, service: S, cfg: usize) -> Result<Self::Service, ()> {
+            if cfg == 0 {
+                Err(())
+            } else {
+                Ok(Mul(service, cfg))
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    struct Mul<S>(S, usize);
+
+    impl<S: Service<usize, Response = usize>> Service<usize> for Mul<S> {
+        type Response = usize;
+        type Error = S::Error;
+
+        async fn call(
+            &self,
+            req: usize,
+            ctx: ServiceCtx<'_, Self>,
+        ) -> Result<usize, S::Error> {
+            Ok(ctx.call(&self.0, req).await? * self.1)
+        }
+    }
+
+    #[ntex::test]
+    async fn middleware_factory() {
+        let factory = apply_factory(
+            Rc::new(TrCfg),
+            fn_service(|i: usize| Ready::<_, ()>::Ok(i * 2)),
+        )
+        .clone();
+
+        let srv = Pipeline::new(factory.create(3).await.unwrap());
+        assert_eq!(srv.call(10).await, Ok(60));
+        assert!(factory.create(0).await.is_err());
+        format!("{:?} {:?}", factory, srv);
+
+        let factory =
+            crate::chain_factory(fn_service(|i: usize| Ready::<_, ()>::Ok(i * 2)))
+                .apply_factory(TrCfg);
+        let srv = Pipeline::new(factory.create(2).await.unwrap());
+        assert_eq!(srv.call(10).await, Ok(40));
+    }
+
+    #[ntex::test]
+    async fn chain_macro() {
+        let factory = crate::chain!(
+            fn_service(|i: usize| Ready::<_, ()>::Ok(i * 2))
+                => Rc::new(Tr(PhantomData))
+                => map(|i: usize| i + 1)
+                => Tr(PhantomData)
+                => map_err(|_: ()| "error")
+        );
+
+        let srv = Pipeline::new(factory.create(&()).await.unwrap());
+        let res = srv.call(10).await;
+        assert_eq!(res, Ok(21));
+    }
 }
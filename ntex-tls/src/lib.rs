@@ -9,6 +9,9 @@ pub mod openssl;
 #[cfg(feature = "rustls")]
 pub mod rustls;
 
+#[cfg(feature = "native-tls")]
+pub mod native_tls;
+
 mod counter;
 
 /// Sets the maximum per-worker concurrent ssl connection establish process.
@@ -27,6 +30,28 @@ thread_local! {
     static MAX_SSL_ACCEPT_COUNTER: counter::Counter = counter::Counter::new(MAX_SSL_ACCEPT.load(Ordering::Relaxed));
 }
 
+/// Tls handshake timeout error.
+///
+/// Client connectors fail with `io::ErrorKind::TimedOut` error that wraps
+/// this type if handshake does not complete in time.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HandshakeTimeout;
+
+impl std::fmt::Display for HandshakeTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Tls handshake timeout")
+    }
+}
+
+impl std::error::Error for HandshakeTimeout {}
+
+impl HandshakeTimeout {
+    /// Check if io error is caused by handshake timeout
+    pub fn is(err: &std::io::Error) -> bool {
+        err.get_ref().is_some_and(|e| e.is::<HandshakeTimeout>())
+    }
+}
+
 /// A TLS PSK identity.
 ///
 /// Used in conjunction with [`ntex_io::Filter::query`]:
@@ -38,3 +63,60 @@ pub struct PskIdentity(pub Vec<u8>);
 /// Used in conjunction with [`ntex_io::Filter::query`]:
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Servername(pub String);
+
+/// Client certificate and private key for mutual tls authentication.
+///
+/// Certificate is parsed by the tls backend it is used with.
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+#[derive(Clone)]
+pub struct ClientCert(ClientCertInner);
+
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+#[derive(Clone)]
+enum ClientCertInner {
+    Pem { cert: Vec<u8>, key: Vec<u8> },
+    #[cfg_attr(not(feature = "openssl"), allow(dead_code))]
+    Pkcs12 { der: Vec<u8>, password: String },
+}
+
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+impl ClientCert {
+    /// Create client certificate from PEM encoded certificate chain and private key.
+    ///
+    /// First certificate in the chain is the client certificate.
+    pub fn from_pem<C, K>(cert: C, key: K) -> Self
+    where
+        C: Into<Vec<u8>>,
+        K: Into<Vec<u8>>,
+    {
+        ClientCert(ClientCertInner::Pem {
+            cert: cert.into(),
+            key: key.into(),
+        })
+    }
+
+    /// Create client certificate from DER encoded PKCS#12 archive.
+    ///
+    /// PKCS#12 archives are not supported by rustls backend.
+    pub fn from_pkcs12<D, P>(der: D, password: P) -> Self
+    where
+        D: Into<Vec<u8>>,
+        P: Into<String>,
+    {
+        ClientCert(ClientCertInner::Pkcs12 {
+            der: der.into(),
+            password: password.into(),
+        })
+    }
+}
+
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+impl std::fmt::Debug for ClientCert {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.0 {
+            ClientCertInner::Pem { .. } => "pem",
+            ClientCertInner::Pkcs12 { .. } => "pkcs12",
+        };
+        f.debug_tuple("ClientCert").field(&kind).finish()
+    }
+}
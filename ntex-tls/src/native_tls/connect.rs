@@ -0,0 +1,192 @@
+use std::{fmt, io};
+
+use ntex_bytes::PoolId;
+use ntex_io::{Io, Layer};
+use ntex_net::connect::{
+    Address, AddressPolicy, Connect, ConnectError, Connector as BaseConnector,
+};
+use ntex_service::{Pipeline, Service, ServiceCtx, ServiceFactory};
+use tls_native::TlsConnector as BaseTlsConnector;
+
+use super::{connect as connect_io, TlsFilter};
+
+/// Native tls connector factory
+pub struct TlsConnector<T> {
+    connector: Pipeline<BaseConnector<T>>,
+    tls: BaseTlsConnector,
+    sni_host: Option<String>,
+}
+
+impl<T: Address> From<BaseTlsConnector> for TlsConnector<T> {
+    fn from(tls: BaseTlsConnector) -> Self {
+        TlsConnector::new(tls)
+    }
+}
+
+impl<T: Address> TlsConnector<T> {
+    /// Construct new native tls connector factory
+    pub fn new(connector: BaseTlsConnector) -> Self {
+        TlsConnector {
+            connector: BaseConnector::default().into(),
+            tls: connector,
+            sni_host: None,
+        }
+    }
+
+    /// Construct connector that verifies certificates against system trust store.
+    ///
+    /// Advertises `h2` and `http/1.1` protocols via ALPN.
+    pub fn platform() -> io::Result<Self> {
+        let tls = BaseTlsConnector::builder()
+            .request_alpns(&["h2", "http/1.1"])
+            .build()
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        Ok(TlsConnector::new(tls))
+    }
+
+    /// Set server name for SNI and certificate verification.
+    ///
+    /// By default host name from connect request is used. This is useful
+    /// when connecting by ip address but certificate must be verified
+    /// against a dns name.
+    pub fn sni_host<S: Into<String>>(mut self, host: S) -> Self {
+        self.sni_host = Some(host.into());
+        self
+    }
+
+    /// Set memory pool.
+    ///
+    /// Use specified memory pool for memory allocations. By default P0
+    /// memory pool is used.
+    pub fn memory_pool(self, id: PoolId) -> Self {
+        let connector = self
+            .connector
+            .into_service()
+            .expect("Connector has been cloned")
+            .memory_pool(id)
+            .into();
+
+        Self {
+            connector,
+            tls: self.tls,
+            sni_host: self.sni_host,
+        }
+    }
+
+    /// Set address selection policy.
+    ///
+    /// Policy is used if host name resolves to multiple addresses.
+    /// By default addresses are used in resolution order.
+    pub fn address_policy(self, policy: AddressPolicy) -> Self {
+        let connector = self
+            .connector
+            .into_service()
+            .expect("Connector has been cloned")
+            .address_policy(policy)
+            .into();
+
+        Self {
+            connector,
+            tls: self.tls,
+            sni_host: self.sni_host,
+        }
+    }
+}
+
+impl<T: Address> TlsConnector<T> {
+    /// Resolve and connect to remote host
+    pub async fn connect<U>(&self, message: U) -> Result<Io<Layer<TlsFilter>>, ConnectError>
+    where
+        Connect<T>: From<U>,
+    {
+        let message = Connect::from(message);
+        let host = if let Some(ref host) = self.sni_host {
+            host.clone()
+        } else {
+            message.host().split(':').next().unwrap().to_string()
+        };
+        let io = self.connector.call(message).await?;
+
+        log::trace!("{}: TLS Handshake start for: {:?}", io.tag(), host);
+
+        let tag = io.tag();
+        match connect_io(io, self.tls.clone(), &host).await {
+            Ok(io) => {
+                log::trace!("{}: TLS Handshake success: {:?}", tag, host);
+                Ok(io)
+            }
+            Err(e) => {
+                log::trace!("{}: TLS Handshake error: {:?}", tag, e);
+                Err(e.into())
+            }
+        }
+    }
+}
+
+impl<T> Clone for TlsConnector<T> {
+    fn clone(&self) -> Self {
+        Self {
+            connector: self.connector.clone(),
+            tls: self.tls.clone(),
+            sni_host: self.sni_host.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for TlsConnector<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsConnector(native-tls)")
+            .field("connector", &self.connector)
+            .field("sni_host", &self.sni_host)
+            .finish()
+    }
+}
+
+impl<T: Address, C> ServiceFactory<Connect<T>, C> for TlsConnector<T> {
+    type Response = Io<Layer<TlsFilter>>;
+    type Error = ConnectError;
+    type Service = TlsConnector<T>;
+    type InitError = ();
+
+    async fn create(&self, _: C) -> Result<Self::Service, Self::InitError> {
+        Ok(self.clone())
+    }
+}
+
+impl<T: Address> Service<Connect<T>> for TlsConnector<T> {
+    type Response = Io<Layer<TlsFilter>>;
+    type Error = ConnectError;
+
+    async fn call(
+        &self,
+        req: Connect<T>,
+        _: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        self.connect(req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[ntex::test]
+    async fn test_native_tls_connect() {
+        let server = ntex::server::test_server(|| {
+            ntex::service::fn_service(|_| async { Ok::<_, ()>(()) })
+        });
+
+        let factory = TlsConnector::platform()
+            .unwrap()
+            .sni_host("www.rust-lang.org")
+            .memory_pool(PoolId::P5)
+            .clone();
+
+        let srv = factory.pipeline(&()).await.unwrap();
+        let result = srv
+            .call(Connect::new("127.0.0.1").set_addr(Some(server.addr())))
+            .await;
+        assert!(result.is_err());
+        assert!(format!("{:?}", srv).contains("native-tls"));
+    }
+}
@@ -0,0 +1,304 @@
+//! An implementation of TLS streams for ntex backed by platform native tls
+//!
+//! Uses SChannel on Windows, Secure Transport on macOS and OpenSSL on
+//! other platforms. Certificates are verified against the system trust store.
+use std::{any, cell::RefCell, cmp, fmt, io, io::Read, io::Write, mem, task::Poll};
+
+use ntex_bytes::{BufMut, BytesVec};
+use ntex_io::{types, Filter, FilterLayer, Io, Layer, ReadBuf, WriteBuf};
+use tls_native::{Certificate, HandshakeError, MidHandshakeTlsStream, TlsStream};
+
+mod connect;
+pub use self::connect::TlsConnector;
+
+/// Connection's peer cert
+pub struct PeerCert(pub Certificate);
+
+impl fmt::Debug for PeerCert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("PeerCert").finish()
+    }
+}
+
+/// An implementation of native tls client stream
+#[derive(Debug)]
+pub struct TlsFilter {
+    inner: RefCell<State>,
+}
+
+#[derive(Debug)]
+enum State {
+    Start(tls_native::TlsConnector, String),
+    Handshake(MidHandshakeTlsStream<IoInner>),
+    Stream(TlsStream<IoInner>),
+    Empty,
+}
+
+#[derive(Debug)]
+struct IoInner {
+    source: Option<BytesVec>,
+    destination: Option<BytesVec>,
+}
+
+impl io::Read for IoInner {
+    fn read(&mut self, dst: &mut [u8]) -> io::Result<usize> {
+        if let Some(ref mut buf) = self.source {
+            if buf.is_empty() {
+                Err(io::Error::from(io::ErrorKind::WouldBlock))
+            } else {
+                let len = cmp::min(buf.len(), dst.len());
+                dst[..len].copy_from_slice(&buf.split_to(len));
+                Ok(len)
+            }
+        } else {
+            Err(io::Error::from(io::ErrorKind::WouldBlock))
+        }
+    }
+}
+
+impl io::Write for IoInner {
+    fn write(&mut self, src: &[u8]) -> io::Result<usize> {
+        if let Some(ref mut buf) = self.destination {
+            buf.extend_from_slice(src);
+            Ok(src.len())
+        } else {
+            Err(io::Error::from(io::ErrorKind::WouldBlock))
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl State {
+    fn io_inner(&mut self) -> Option<&mut IoInner> {
+        match self {
+            State::Handshake(ref mut st) => Some(st.get_mut()),
+            State::Stream(ref mut st) => Some(st.get_mut()),
+            State::Start(..) | State::Empty => None,
+        }
+    }
+}
+
+impl TlsFilter {
+    fn with_buffers<F, R>(&self, buf: &WriteBuf<'_>, f: F) -> R
+    where
+        F: FnOnce(&mut State) -> R,
+    {
+        let mut state = self.inner.borrow_mut();
+        if let Some(io) = state.io_inner() {
+            io.destination = Some(buf.take_dst());
+            io.source = buf.with_read_buf(|b| b.take_src());
+        }
+        let result = f(&mut state);
+        if let Some(io) = state.io_inner() {
+            buf.set_dst(io.destination.take());
+            buf.with_read_buf(|b| b.set_src(io.source.take()));
+        }
+        result
+    }
+
+    /// Drive tls handshake, returns `true` if handshake is completed
+    fn handshake(&self, buf: &WriteBuf<'_>) -> io::Result<bool> {
+        let mut state = self.inner.borrow_mut();
+        if let State::Start(ref connector, ref host) = *state {
+            let io = IoInner {
+                destination: Some(buf.take_dst()),
+                source: buf.with_read_buf(|b| b.take_src()),
+            };
+            let result = connector.connect(host, io);
+            let done = handle_handshake(&mut state, result);
+            if let Some(io) = state.io_inner() {
+                buf.set_dst(io.destination.take());
+                buf.with_read_buf(|b| b.set_src(io.source.take()));
+            }
+            return done;
+        }
+        drop(state);
+        self.with_buffers(buf, continue_handshake)
+    }
+}
+
+fn continue_handshake(state: &mut State) -> io::Result<bool> {
+    match mem::replace(state, State::Empty) {
+        State::Handshake(st) => {
+            let result = st.handshake();
+            handle_handshake(state, result)
+        }
+        st @ State::Stream(_) => {
+            *state = st;
+            Ok(true)
+        }
+        st @ State::Start(..) => {
+            *state = st;
+            Ok(false)
+        }
+        State::Empty => Err(io::Error::new(io::ErrorKind::Other, "Handshake failed")),
+    }
+}
+
+fn handle_handshake(
+    state: &mut State,
+    result: Result<TlsStream<IoInner>, HandshakeError<IoInner>>,
+) -> io::Result<bool> {
+    match result {
+        Ok(st) => {
+            *state = State::Stream(st);
+            Ok(true)
+        }
+        Err(HandshakeError::WouldBlock(st)) => {
+            *state = State::Handshake(st);
+            Ok(false)
+        }
+        Err(HandshakeError::Failure(e)) => Err(io::Error::new(io::ErrorKind::Other, e)),
+    }
+}
+
+impl FilterLayer for TlsFilter {
+    fn query(&self, id: any::TypeId) -> Option<Box<dyn any::Any>> {
+        const H2: &[u8] = b"h2";
+
+        if let State::Stream(ref st) = *self.inner.borrow() {
+            if id == any::TypeId::of::<types::HttpProtocol>() {
+                let h2 = st
+                    .negotiated_alpn()
+                    .ok()
+                    .flatten()
+                    .map(|proto| proto.as_slice() == H2)
+                    .unwrap_or(false);
+                let proto = if h2 {
+                    types::HttpProtocol::Http2
+                } else {
+                    types::HttpProtocol::Http1
+                };
+                Some(Box::new(proto))
+            } else if id == any::TypeId::of::<PeerCert>() {
+                if let Ok(Some(cert)) = st.peer_certificate() {
+                    Some(Box::new(PeerCert(cert)))
+                } else {
+                    None
+                }
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    }
+
+    fn shutdown(&self, buf: &WriteBuf<'_>) -> io::Result<Poll<()>> {
+        self.with_buffers(buf, |state| {
+            if let State::Stream(ref mut st) = state {
+                match st.shutdown() {
+                    Ok(_) => Ok(Poll::Ready(())),
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                        Ok(Poll::Pending)
+                    }
+                    Err(e) => Err(e),
+                }
+            } else {
+                Ok(Poll::Ready(()))
+            }
+        })
+    }
+
+    fn process_read_buf(&self, buf: &ReadBuf<'_>) -> io::Result<usize> {
+        buf.with_write_buf(|b| {
+            self.with_buffers(b, |state| {
+                // incoming data could complete handshake
+                continue_handshake(state)?;
+                let st = if let State::Stream(ref mut st) = state {
+                    st
+                } else {
+                    return Ok(0);
+                };
+
+                buf.with_dst(|dst| {
+                    let mut new_bytes = 0;
+                    loop {
+                        buf.resize_buf(dst);
+
+                        let chunk: &mut [u8] =
+                            unsafe { std::mem::transmute(&mut *dst.chunk_mut()) };
+                        return match st.read(chunk) {
+                            Ok(0) => {
+                                buf.want_shutdown();
+                                Ok(new_bytes)
+                            }
+                            Ok(v) => {
+                                unsafe { dst.advance_mut(v) };
+                                new_bytes += v;
+                                continue;
+                            }
+                            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                                Ok(new_bytes)
+                            }
+                            Err(e) => {
+                                log::trace!("TLS Error: {:?}", e);
+                                Err(e)
+                            }
+                        };
+                    }
+                })
+            })
+        })
+    }
+
+    fn process_write_buf(&self, wb: &WriteBuf<'_>) -> io::Result<()> {
+        wb.with_src(|b| {
+            if let Some(src) = b {
+                self.with_buffers(wb, |state| {
+                    let st = if let State::Stream(ref mut st) = state {
+                        st
+                    } else {
+                        // handshake is not completed yet
+                        return Ok(());
+                    };
+
+                    loop {
+                        if src.is_empty() {
+                            return Ok(());
+                        }
+                        match st.write(src) {
+                            Ok(v) => {
+                                src.split_to(v);
+                                continue;
+                            }
+                            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                                return Ok(())
+                            }
+                            Err(e) => return Err(e),
+                        }
+                    }
+                })
+            } else {
+                Ok(())
+            }
+        })
+    }
+}
+
+/// Create native tls connector filter
+pub async fn connect<F: Filter>(
+    io: Io<F>,
+    connector: tls_native::TlsConnector,
+    host: &str,
+) -> Result<Io<Layer<TlsFilter, F>>, io::Error> {
+    let filter = TlsFilter {
+        inner: RefCell::new(State::Start(connector, host.to_string())),
+    };
+    let io = io.add_filter(filter);
+
+    loop {
+        let done = io.with_buf(|buf| io.filter().handshake(buf))??;
+        if done {
+            break;
+        }
+        if io.force_read_ready().await?.is_none() {
+            return Err(io::Error::new(io::ErrorKind::Other, "disconnected"));
+        }
+    }
+
+    Ok(io)
+}
@@ -1,16 +1,99 @@
-use std::{fmt, io};
+use std::{fmt, io, rc::Rc};
 
 use ntex_bytes::PoolId;
 use ntex_io::{Io, Layer};
-use ntex_net::connect::{Address, Connect, ConnectError, Connector as BaseConnector};
+use ntex_net::connect::{
+    Address, AddressPolicy, Connect, ConnectError, Connector as BaseConnector,
+};
 use ntex_service::{Pipeline, Service, ServiceCtx, ServiceFactory};
-use tls_openssl::ssl::SslConnector as BaseSslConnector;
+use ntex_util::{time::timeout_checked, time::Millis, HashMap};
+use tls_openssl::pkey::{PKey, Private};
+use tls_openssl::ssl::{SslConnector as BaseSslConnector, SslRef, SslVerifyMode};
+use tls_openssl::{pkcs12::Pkcs12, x509::X509};
 
 use super::{connect as connect_io, SslFilter};
+use crate::{ClientCert, ClientCertInner, HandshakeTimeout};
 
 pub struct SslConnector<T> {
     connector: Pipeline<BaseConnector<T>>,
     openssl: BaseSslConnector,
+    sni_host: Option<String>,
+    alpn: Option<Vec<u8>>,
+    certs: Rc<ClientCerts>,
+    accept_invalid_certs: bool,
+    handshake_timeout: Millis,
+}
+
+#[derive(Clone, Default)]
+struct ClientCerts {
+    default: Option<Identity>,
+    hosts: HashMap<String, Identity>,
+}
+
+#[derive(Clone)]
+struct Identity {
+    cert: X509,
+    chain: Vec<X509>,
+    key: PKey<Private>,
+}
+
+impl Identity {
+    fn load(cert: &ClientCert) -> io::Result<Self> {
+        match cert.0 {
+            ClientCertInner::Pem { ref cert, ref key } => {
+                let mut chain = X509::stack_from_pem(cert).map_err(map_err)?;
+                if chain.is_empty() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "Client certificate is not found",
+                    ));
+                }
+                Ok(Identity {
+                    cert: chain.remove(0),
+                    chain,
+                    key: PKey::private_key_from_pem(key).map_err(map_err)?,
+                })
+            }
+            ClientCertInner::Pkcs12 {
+                ref der,
+                ref password,
+            } => {
+                let parsed = Pkcs12::from_der(der)
+                    .and_then(|p| p.parse2(password))
+                    .map_err(map_err)?;
+                match (parsed.cert, parsed.pkey) {
+                    (Some(cert), Some(key)) => Ok(Identity {
+                        cert,
+                        key,
+                        chain: parsed
+                            .ca
+                            .map(|ca| ca.into_iter().collect())
+                            .unwrap_or_default(),
+                    }),
+                    _ => Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "PKCS#12 archive does not contain certificate or private key",
+                    )),
+                }
+            }
+        }
+    }
+
+    fn apply(&self, ssl: &mut SslRef) -> io::Result<()> {
+        ssl.set_certificate(&self.cert).map_err(map_err)?;
+        ssl.set_private_key(&self.key).map_err(map_err)?;
+        for cert in &self.chain {
+            ssl.add_chain_cert(cert.clone()).map_err(map_err)?;
+        }
+        Ok(())
+    }
+}
+
+fn map_err<E>(err: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidInput, err)
 }
 
 impl<T: Address> SslConnector<T> {
@@ -19,7 +102,85 @@ impl<T: Address> SslConnector<T> {
         SslConnector {
             connector: BaseConnector::default().into(),
             openssl: connector,
+            sni_host: None,
+            alpn: None,
+            certs: Rc::default(),
+            accept_invalid_certs: false,
+            handshake_timeout: Millis::ZERO,
+        }
+    }
+
+    /// Set client certificate for mutual tls authentication.
+    ///
+    /// Certificate is used for all hosts without host specific certificate.
+    pub fn client_cert(mut self, cert: &ClientCert) -> io::Result<Self> {
+        Rc::make_mut(&mut self.certs).default = Some(Identity::load(cert)?);
+        Ok(self)
+    }
+
+    /// Set client certificate for specific host.
+    pub fn client_cert_for<H: Into<String>>(
+        mut self,
+        host: H,
+        cert: &ClientCert,
+    ) -> io::Result<Self> {
+        let identity = Identity::load(cert)?;
+        Rc::make_mut(&mut self.certs)
+            .hosts
+            .insert(host.into(), identity);
+        Ok(self)
+    }
+
+    /// Set server name for SNI and certificate verification.
+    ///
+    /// By default host name from connect request is used. This is useful
+    /// when connecting by ip address but certificate must be verified
+    /// against a dns name.
+    pub fn sni_host<S: Into<String>>(mut self, host: S) -> Self {
+        self.sni_host = Some(host.into());
+        self
+    }
+
+    #[cfg(feature = "dangerous")]
+    /// Disable server certificate and host name verification.
+    ///
+    /// **Dangerous**: any certificate is accepted, connection is open
+    /// to man-in-the-middle attacks. Use it only for tooling and diagnostics.
+    pub fn dangerous_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.accept_invalid_certs = accept;
+        self
+    }
+
+    /// Set ssl handshake timeout.
+    ///
+    /// Connect fails with `io::ErrorKind::TimedOut` error that wraps
+    /// [`HandshakeTimeout`](crate::HandshakeTimeout) if handshake does not
+    /// complete in time. By default handshake timeout is not set.
+    pub fn handshake_timeout<U: Into<Millis>>(mut self, timeout: U) -> Self {
+        self.handshake_timeout = timeout.into();
+        self
+    }
+
+    /// Set list of protocols to advertise via ALPN.
+    ///
+    /// Overrides ALPN configuration of the openssl connector.
+    pub fn alpn_protocols<I, P>(mut self, protos: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<[u8]>,
+    {
+        let mut wire = Vec::new();
+        for proto in protos {
+            let proto = proto.as_ref();
+            assert!(
+                !proto.is_empty() && proto.len() <= 255,
+                "ALPN protocol name length must be in 1..=255"
+            );
+            wire.push(proto.len() as u8);
+            wire.extend_from_slice(proto);
         }
+        self.alpn = Some(wire);
+        self
     }
 
     /// Set memory pool.
@@ -37,6 +198,34 @@ impl<T: Address> SslConnector<T> {
         Self {
             connector,
             openssl: self.openssl,
+            sni_host: self.sni_host,
+            alpn: self.alpn,
+            certs: self.certs,
+            accept_invalid_certs: self.accept_invalid_certs,
+            handshake_timeout: self.handshake_timeout,
+        }
+    }
+
+    /// Set address selection policy.
+    ///
+    /// Policy is used if host name resolves to multiple addresses.
+    /// By default addresses are used in resolution order.
+    pub fn address_policy(self, policy: AddressPolicy) -> Self {
+        let connector = self
+            .connector
+            .into_service()
+            .expect("Connector has been cloned")
+            .address_policy(policy)
+            .into();
+
+        Self {
+            connector,
+            openssl: self.openssl,
+            sni_host: self.sni_host,
+            alpn: self.alpn,
+            certs: self.certs,
+            accept_invalid_certs: self.accept_invalid_certs,
+            handshake_timeout: self.handshake_timeout,
         }
     }
 }
@@ -48,7 +237,17 @@ impl<T: Address> SslConnector<T> {
         Connect<T>: From<U>,
     {
         let message = Connect::from(message);
-        let host = message.host().split(':').next().unwrap().to_string();
+        let addr_host = message.host().split(':').next().unwrap();
+        let identity = self
+            .certs
+            .hosts
+            .get(addr_host)
+            .or(self.certs.default.as_ref());
+        let host = if let Some(ref host) = self.sni_host {
+            host.clone()
+        } else {
+            addr_host.to_string()
+        };
         let conn = self.connector.call(message);
         let openssl = self.openssl.clone();
 
@@ -57,20 +256,37 @@ impl<T: Address> SslConnector<T> {
 
         match openssl.configure() {
             Err(e) => Err(io::Error::new(io::ErrorKind::Other, e).into()),
-            Ok(config) => {
+            Ok(mut config) => {
+                if let Some(ref alpn) = self.alpn {
+                    config
+                        .set_alpn_protos(alpn)
+                        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                }
+                if let Some(identity) = identity {
+                    identity.apply(&mut config)?;
+                }
+                if self.accept_invalid_certs {
+                    config.set_verify(SslVerifyMode::NONE);
+                    config.set_verify_hostname(false);
+                }
                 let ssl = config
                     .into_ssl(&host)
                     .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
                 let tag = io.tag();
-                match connect_io(io, ssl).await {
-                    Ok(io) => {
+                match timeout_checked(self.handshake_timeout, connect_io(io, ssl)).await {
+                    Ok(Ok(io)) => {
                         log::trace!("{}: SSL Handshake success: {:?}", tag, host);
                         Ok(io)
                     }
-                    Err(e) => {
+                    Ok(Err(e)) => {
                         log::trace!("{}: SSL Handshake error: {:?}", tag, e);
                         Err(io::Error::new(io::ErrorKind::Other, format!("{}", e)).into())
                     }
+                    Err(_) => {
+                        log::trace!("{}: SSL Handshake timeout: {:?}", tag, host);
+                        Err(io::Error::new(io::ErrorKind::TimedOut, HandshakeTimeout)
+                            .into())
+                    }
                 }
             }
         }
@@ -82,6 +298,11 @@ impl<T> Clone for SslConnector<T> {
         Self {
             connector: self.connector.clone(),
             openssl: self.openssl.clone(),
+            sni_host: self.sni_host.clone(),
+            alpn: self.alpn.clone(),
+            certs: self.certs.clone(),
+            accept_invalid_certs: self.accept_invalid_certs,
+            handshake_timeout: self.handshake_timeout,
         }
     }
 }
@@ -91,6 +312,9 @@ impl<T> fmt::Debug for SslConnector<T> {
         f.debug_struct("SslConnector(openssl)")
             .field("connector", &self.connector)
             .field("openssl", &self.openssl)
+            .field("sni_host", &self.sni_host)
+            .field("accept_invalid_certs", &self.accept_invalid_certs)
+            .field("handshake_timeout", &self.handshake_timeout)
             .finish()
     }
 }
@@ -143,4 +367,24 @@ mod tests {
         assert!(result.is_err());
         assert!(format!("{:?}", srv).contains("SslConnector"));
     }
+
+    #[ntex::test]
+    async fn test_openssl_sni_alpn() {
+        let server = ntex::server::test_server(|| {
+            ntex::service::fn_service(|_| async { Ok::<_, ()>(()) })
+        });
+
+        let ssl = BaseSslConnector::builder(SslMethod::tls()).unwrap();
+        let factory = SslConnector::new(ssl.build())
+            .sni_host("www.rust-lang.org")
+            .alpn_protocols([&b"h2"[..], &b"http/1.1"[..]]);
+        assert_eq!(factory.sni_host.as_deref(), Some("www.rust-lang.org"));
+        assert_eq!(factory.alpn.as_deref(), Some(&b"\x02h2\x08http/1.1"[..]));
+
+        let srv = factory.pipeline(&()).await.unwrap();
+        let result = srv
+            .call(Connect::new("127.0.0.1").set_addr(Some(server.addr())))
+            .await;
+        assert!(result.is_err());
+    }
 }
@@ -1,17 +1,140 @@
-use std::{fmt, io, sync::Arc};
+use std::{fmt, io, rc::Rc, sync::Arc};
 
 use ntex_bytes::PoolId;
 use ntex_io::{Io, Layer};
-use ntex_net::connect::{Address, Connect, ConnectError, Connector as BaseConnector};
+use ntex_net::connect::{
+    Address, AddressPolicy, Connect, ConnectError, Connector as BaseConnector,
+};
 use ntex_service::{Pipeline, Service, ServiceCtx, ServiceFactory};
-use tls_rust::{pki_types::ServerName, ClientConfig};
+use ntex_util::{time::timeout_checked, time::Millis, HashMap};
+use tls_rust::client::danger::{HandshakeSignatureValid, ServerCertVerified};
+use tls_rust::client::{danger::ServerCertVerifier, ResolvesClientCert};
+use tls_rust::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use tls_rust::pki_types::{CertificateDer, ServerName, UnixTime};
+use tls_rust::{
+    sign::CertifiedKey, ClientConfig, DigitallySignedStruct, Error, SignatureScheme,
+};
 
 use super::TlsClientFilter;
+use crate::{ClientCert, ClientCertInner, HandshakeTimeout};
 
 /// Rustls connector factory
 pub struct TlsConnector<T> {
     connector: Pipeline<BaseConnector<T>>,
     config: Arc<ClientConfig>,
+    sni_host: Option<String>,
+    hosts: Rc<HashMap<String, Arc<ClientConfig>>>,
+    handshake_timeout: Millis,
+    accept_invalid_certs: bool,
+}
+
+#[derive(Debug)]
+struct ClientCertResolver(Arc<CertifiedKey>);
+
+impl ResolvesClientCert for ClientCertResolver {
+    fn resolve(&self, _: &[&[u8]], _: &[SignatureScheme]) -> Option<Arc<CertifiedKey>> {
+        Some(self.0.clone())
+    }
+
+    fn has_certs(&self) -> bool {
+        true
+    }
+}
+
+/// Certificate verifier that accepts any server certificate
+#[derive(Debug)]
+struct NoCertVerifier(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for NoCertVerifier {
+    fn verify_server_cert(
+        &self,
+        _: &CertificateDer<'_>,
+        _: &[CertificateDer<'_>],
+        _: &ServerName<'_>,
+        _: &[u8],
+        _: UnixTime,
+    ) -> Result<ServerCertVerified, Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// Create client config that accepts any server certificate
+fn with_no_verifier(config: &ClientConfig) -> ClientConfig {
+    let mut config = config.clone();
+    let provider = config.crypto_provider().clone();
+    config
+        .dangerous()
+        .set_certificate_verifier(Arc::new(NoCertVerifier(provider)));
+    config
+}
+
+/// Create client config that uses provided client certificate
+fn with_client_cert(config: &ClientConfig, cert: &ClientCert) -> io::Result<ClientConfig> {
+    let (certs, key) = match cert.0 {
+        ClientCertInner::Pem { ref cert, ref key } => {
+            let certs = rustls_pemfile::certs(&mut cert.as_slice())
+                .collect::<Result<Vec<_>, _>>()?;
+            let key =
+                rustls_pemfile::private_key(&mut key.as_slice())?.ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "Private key is not found")
+                })?;
+            (certs, key)
+        }
+        ClientCertInner::Pkcs12 { .. } => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "PKCS#12 client certificates are not supported by rustls",
+            ))
+        }
+    };
+    if certs.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Client certificate is not found",
+        ));
+    }
+    let key = config
+        .crypto_provider()
+        .key_provider
+        .load_private_key(key)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+    let mut config = config.clone();
+    config.client_auth_cert_resolver =
+        Arc::new(ClientCertResolver(Arc::new(CertifiedKey::new(certs, key))));
+    Ok(config)
 }
 
 impl<T: Address> From<Arc<ClientConfig>> for TlsConnector<T> {
@@ -19,6 +142,10 @@ impl<T: Address> From<Arc<ClientConfig>> for TlsConnector<T> {
         TlsConnector {
             config,
             connector: BaseConnector::default().into(),
+            sni_host: None,
+            hosts: Rc::default(),
+            handshake_timeout: Millis::ZERO,
+            accept_invalid_certs: false,
         }
     }
 }
@@ -28,7 +155,80 @@ impl<T: Address> TlsConnector<T> {
         TlsConnector {
             config: Arc::new(config),
             connector: BaseConnector::default().into(),
+            sni_host: None,
+            hosts: Rc::default(),
+            handshake_timeout: Millis::ZERO,
+            accept_invalid_certs: false,
+        }
+    }
+
+    /// Set server name for SNI and certificate verification.
+    ///
+    /// By default host name from connect request is used. This is useful
+    /// when connecting by ip address but certificate must be verified
+    /// against a dns name.
+    pub fn sni_host<S: Into<String>>(mut self, host: S) -> Self {
+        self.sni_host = Some(host.into());
+        self
+    }
+
+    /// Set tls handshake timeout.
+    ///
+    /// Connect fails with `io::ErrorKind::TimedOut` error that wraps
+    /// [`HandshakeTimeout`](crate::HandshakeTimeout) if handshake does not
+    /// complete in time. By default handshake timeout is not set.
+    pub fn handshake_timeout<U: Into<Millis>>(mut self, timeout: U) -> Self {
+        self.handshake_timeout = timeout.into();
+        self
+    }
+
+    /// Set list of protocols to advertise via ALPN.
+    ///
+    /// Overrides `alpn_protocols` of the client config.
+    pub fn alpn_protocols<I, P>(mut self, protos: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<Vec<u8>>,
+    {
+        let protos: Vec<Vec<u8>> = protos.into_iter().map(|p| p.into()).collect();
+        let mut config = (*self.config).clone();
+        config.alpn_protocols = protos.clone();
+        self.config = Arc::new(config);
+
+        for cfg in Rc::make_mut(&mut self.hosts).values_mut() {
+            Arc::make_mut(cfg).alpn_protocols = protos.clone();
         }
+        self
+    }
+
+    #[cfg(feature = "dangerous")]
+    /// Disable server certificate verification.
+    ///
+    /// **Dangerous**: any certificate is accepted, connection is open
+    /// to man-in-the-middle attacks. Use it only for tooling and diagnostics.
+    pub fn dangerous_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.accept_invalid_certs = accept;
+        self
+    }
+
+    /// Set client certificate for mutual tls authentication.
+    ///
+    /// Certificate is used for all hosts without host specific certificate.
+    /// PKCS#12 archives are not supported.
+    pub fn client_cert(mut self, cert: &ClientCert) -> io::Result<Self> {
+        self.config = Arc::new(with_client_cert(&self.config, cert)?);
+        Ok(self)
+    }
+
+    /// Set client certificate for specific host.
+    pub fn client_cert_for<H: Into<String>>(
+        mut self,
+        host: H,
+        cert: &ClientCert,
+    ) -> io::Result<Self> {
+        let config = with_client_cert(&self.config, cert)?;
+        Rc::make_mut(&mut self.hosts).insert(host.into(), Arc::new(config));
+        Ok(self)
     }
 
     /// Set memory pool.
@@ -45,6 +245,31 @@ impl<T: Address> TlsConnector<T> {
         Self {
             connector,
             config: self.config,
+            sni_host: self.sni_host,
+            hosts: self.hosts,
+            handshake_timeout: self.handshake_timeout,
+            accept_invalid_certs: self.accept_invalid_certs,
+        }
+    }
+
+    /// Set address selection policy.
+    ///
+    /// Policy is used if host name resolves to multiple addresses.
+    /// By default addresses are used in resolution order.
+    pub fn address_policy(self, policy: AddressPolicy) -> Self {
+        let connector = self
+            .connector
+            .into_service()
+            .unwrap()
+            .address_policy(policy)
+            .into();
+        Self {
+            connector,
+            config: self.config,
+            sni_host: self.sni_host,
+            hosts: self.hosts,
+            handshake_timeout: self.handshake_timeout,
+            accept_invalid_certs: self.accept_invalid_certs,
         }
     }
 }
@@ -59,25 +284,38 @@ impl<T: Address> TlsConnector<T> {
         Connect<T>: From<U>,
     {
         let req = Connect::from(message);
-        let host = req.host().split(':').next().unwrap().to_owned();
+        let addr_host = req.host().split(':').next().unwrap();
+        let mut config = self.hosts.get(addr_host).unwrap_or(&self.config).clone();
+        if self.accept_invalid_certs {
+            config = Arc::new(with_no_verifier(&config));
+        }
+        let host = if let Some(ref host) = self.sni_host {
+            host.clone()
+        } else {
+            addr_host.to_owned()
+        };
         let io = self.connector.call(req).await?;
 
         log::trace!("{}: SSL Handshake start for: {:?}", io.tag(), host);
 
         let tag = io.tag();
-        let config = self.config.clone();
         let host = ServerName::try_from(host)
             .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("{}", e)))?;
 
-        match TlsClientFilter::create(io, config, host.clone()).await {
-            Ok(io) => {
+        let fut = TlsClientFilter::create(io, config, host.clone());
+        match timeout_checked(self.handshake_timeout, fut).await {
+            Ok(Ok(io)) => {
                 log::trace!("{}: TLS Handshake success: {:?}", tag, &host);
                 Ok(io)
             }
-            Err(e) => {
+            Ok(Err(e)) => {
                 log::trace!("{}: TLS Handshake error: {:?}", tag, e);
                 Err(e.into())
             }
+            Err(_) => {
+                log::trace!("{}: TLS Handshake timeout: {:?}", tag, &host);
+                Err(io::Error::new(io::ErrorKind::TimedOut, HandshakeTimeout).into())
+            }
         }
     }
 }
@@ -87,6 +325,10 @@ impl<T> Clone for TlsConnector<T> {
         Self {
             config: self.config.clone(),
             connector: self.connector.clone(),
+            sni_host: self.sni_host.clone(),
+            hosts: self.hosts.clone(),
+            handshake_timeout: self.handshake_timeout,
+            accept_invalid_certs: self.accept_invalid_certs,
         }
     }
 }
@@ -95,6 +337,9 @@ impl<T> fmt::Debug for TlsConnector<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("TlsConnector(rustls)")
             .field("connector", &self.connector)
+            .field("sni_host", &self.sni_host)
+            .field("handshake_timeout", &self.handshake_timeout)
+            .field("accept_invalid_certs", &self.accept_invalid_certs)
             .finish()
     }
 }
@@ -154,4 +399,31 @@ mod tests {
             .await;
         assert!(result.is_err());
     }
+
+    #[ntex::test]
+    async fn test_rustls_sni_alpn() {
+        let server = ntex::server::test_server(|| {
+            ntex::service::fn_service(|_| async { Ok::<_, ()>(()) })
+        });
+
+        let cert_store =
+            RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        let config = ClientConfig::builder()
+            .with_root_certificates(cert_store)
+            .with_no_client_auth();
+        let factory = TlsConnector::new(config)
+            .sni_host("www.rust-lang.org")
+            .alpn_protocols([&b"h2"[..], &b"http/1.1"[..]]);
+        assert_eq!(factory.sni_host.as_deref(), Some("www.rust-lang.org"));
+        assert_eq!(
+            factory.config.alpn_protocols,
+            vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+        );
+
+        let srv = factory.pipeline(&()).await.unwrap();
+        let result = srv
+            .call(Connect::new("127.0.0.1").set_addr(Some(server.addr())))
+            .await;
+        assert!(result.is_err());
+    }
 }
@@ -33,6 +33,13 @@ impl Handle for HandleWrapper {
         }
         None
     }
+
+    #[cfg(unix)]
+    fn try_clone_fd(&self) -> Option<io::Result<std::os::fd::OwnedFd>> {
+        use std::os::fd::AsFd;
+
+        Some(self.0.borrow().as_fd().try_clone_to_owned())
+    }
 }
 
 /// Read io task
@@ -197,10 +204,13 @@ impl Future for WriteTask {
                             this.state.tag()
                         );
 
-                        if !matches!(
-                            this.io.borrow().linger(),
-                            Ok(Some(std::time::Duration::ZERO))
-                        ) {
+                        // detached io stream is used by new owner
+                        if !this.state.is_detached()
+                            && !matches!(
+                                this.io.borrow().linger(),
+                                Ok(Some(std::time::Duration::ZERO))
+                            )
+                        {
                             // call shutdown to prevent flushing data on terminated Io. when
                             // linger is set to zero, closing will reset the connection, so
                             // shutdown is not neccessary.
@@ -466,9 +476,23 @@ mod unixstream {
             let io = Rc::new(RefCell::new(self.0));
 
             tokio::task::spawn_local(ReadTask::new(io.clone(), read));
-            tokio::task::spawn_local(WriteTask::new(io, write));
+            tokio::task::spawn_local(WriteTask::new(io.clone(), write));
+            Some(Box::new(HandleWrapper(io)))
+        }
+    }
+
+    struct HandleWrapper(Rc<RefCell<UnixStream>>);
+
+    impl Handle for HandleWrapper {
+        fn query(&self, _: any::TypeId) -> Option<Box<dyn any::Any>> {
             None
         }
+
+        fn try_clone_fd(&self) -> Option<io::Result<std::os::fd::OwnedFd>> {
+            use std::os::fd::AsFd;
+
+            Some(self.0.borrow().as_fd().try_clone_to_owned())
+        }
     }
 
     /// Read io task
@@ -619,7 +643,10 @@ mod unixstream {
                                 this.state.tag()
                             );
 
-                            let _ = Pin::new(&mut *this.io.borrow_mut()).poll_shutdown(cx);
+                            if !this.state.is_detached() {
+                                let _ =
+                                    Pin::new(&mut *this.io.borrow_mut()).poll_shutdown(cx);
+                            }
                             this.state.close(None);
                             Poll::Ready(())
                         }
@@ -28,6 +28,24 @@ pub async fn tcp_connect_in(addr: SocketAddr, pool: PoolRef) -> Result<Io> {
     Ok(Io::with_memory_pool(TcpStream(sock), pool))
 }
 
+/// Opens a TCP connection to a remote host from specified local address
+/// and use specified memory pool.
+pub async fn tcp_connect_bind_in(
+    addr: SocketAddr,
+    local: net::IpAddr,
+    pool: PoolRef,
+) -> Result<Io> {
+    let sock = if addr.is_ipv4() {
+        tokio::net::TcpSocket::new_v4()?
+    } else {
+        tokio::net::TcpSocket::new_v6()?
+    };
+    sock.bind(SocketAddr::new(local, 0))?;
+    let sock = sock.connect(addr).await?;
+    sock.set_nodelay(true)?;
+    Ok(Io::with_memory_pool(TcpStream(sock), pool))
+}
+
 #[cfg(unix)]
 /// Opens a unix stream connection.
 pub async fn unix_connect<'a, P>(addr: P) -> Result<Io>
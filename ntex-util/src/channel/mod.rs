@@ -18,3 +18,29 @@ impl std::fmt::Display for Canceled {
 }
 
 impl std::error::Error for Canceled {}
+
+/// Error returned from `Receiver::recv_timeout()`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RecvTimeoutError {
+    /// Value is not received before timeout elapsed
+    Timeout,
+    /// Corresponding `Sender` is dropped
+    Canceled,
+}
+
+impl From<Canceled> for RecvTimeoutError {
+    fn from(_: Canceled) -> Self {
+        RecvTimeoutError::Canceled
+    }
+}
+
+impl std::fmt::Display for RecvTimeoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecvTimeoutError::Timeout => std::write!(f, "oneshot timed out"),
+            RecvTimeoutError::Canceled => std::write!(f, "oneshot canceled"),
+        }
+    }
+}
+
+impl std::error::Error for RecvTimeoutError {}
@@ -1,8 +1,8 @@
 //! A one-shot, futures-aware channel.
 use std::{future::poll_fn, future::Future, pin::Pin, task::Context, task::Poll};
 
-use super::{cell::Cell, Canceled};
-use crate::task::LocalWaker;
+use super::{cell::Cell, Canceled, RecvTimeoutError};
+use crate::{task::LocalWaker, time::timeout, time::Millis};
 
 /// Creates a new futures-aware, one-shot channel.
 pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
@@ -83,6 +83,19 @@ impl<T> Receiver<T> {
         poll_fn(|cx| self.poll_recv(cx)).await
     }
 
+    /// Wait until the oneshot is ready or timeout elapsed
+    ///
+    /// Value could still be received after timeout.
+    pub async fn recv_timeout<U: Into<Millis>>(
+        &self,
+        dur: U,
+    ) -> Result<T, RecvTimeoutError> {
+        match timeout(dur, self.recv()).await {
+            Ok(res) => Ok(res?),
+            Err(_) => Err(RecvTimeoutError::Timeout),
+        }
+    }
+
     /// Polls the oneshot to determine if value is ready
     pub fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Result<T, Canceled>> {
         // If we've got a value, then skip the logic below as we're done.
@@ -146,4 +159,23 @@ mod tests {
         drop(tx);
         assert!(rx.await.is_err());
     }
+
+    #[ntex_macros::rt_test2]
+    async fn test_recv_timeout() {
+        let (tx, rx) = channel::<&'static str>();
+        assert_eq!(
+            rx.recv_timeout(Millis(50)).await,
+            Err(RecvTimeoutError::Timeout)
+        );
+        tx.send("test").unwrap();
+        assert_eq!(rx.recv_timeout(Millis(50)).await, Ok("test"));
+
+        let (tx, rx) = channel::<&'static str>();
+        drop(tx);
+        assert_eq!(
+            rx.recv_timeout(Millis(50)).await,
+            Err(RecvTimeoutError::Canceled)
+        );
+        assert!(format!("{}", RecvTimeoutError::Timeout).contains("timed out"));
+    }
 }
@@ -1,15 +1,23 @@
 //! A one-shot pool, futures-aware channel.
 use slab::Slab;
-use std::{fmt, future::Future, pin::Pin, task::Context, task::Poll};
+use std::{fmt, future::poll_fn, future::Future, pin::Pin, task::Context, task::Poll};
 
-use super::{cell::Cell, Canceled};
-use crate::task::LocalWaker;
+use super::{cell::Cell, Canceled, RecvTimeoutError};
+use crate::{task::LocalWaker, time::timeout, time::Millis};
 
 /// Creates a new futures-aware, pool of one-shot's.
+///
+/// Pool reuses slots of completed one-shot's, so dispatchers that create
+/// one-shot for each in-flight call do not allocate per call.
 pub fn new<T>() -> Pool<T> {
     Pool(Cell::new(Slab::new()))
 }
 
+/// Creates a new pool of one-shot's with pre-allocated capacity.
+pub fn with_capacity<T>(capacity: usize) -> Pool<T> {
+    Pool(Cell::new(Slab::with_capacity(capacity)))
+}
+
 #[doc(hidden)]
 /// Futures-aware, pool of one-shot's.
 pub type OneshotsPool<T> = Pool<T>;
@@ -69,6 +77,21 @@ impl<T> Pool<T> {
         )
     }
 
+    /// Number of active one-shot's.
+    pub fn len(&self) -> usize {
+        self.0.get_ref().len()
+    }
+
+    /// Returns `true` if pool has no active one-shot's.
+    pub fn is_empty(&self) -> bool {
+        self.0.get_ref().is_empty()
+    }
+
+    /// Number of one-shot's pool can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.0.get_ref().capacity()
+    }
+
     /// Shrinks the capacity of the pool as much as possible.
     pub fn shrink_to_fit(&self) {
         self.0.get_mut().shrink_to_fit()
@@ -163,6 +186,24 @@ impl<T> Drop for Sender<T> {
 }
 
 impl<T> Receiver<T> {
+    /// Wait until the oneshot is ready and return value
+    pub async fn recv(&self) -> Result<T, Canceled> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Wait until the oneshot is ready or timeout elapsed
+    ///
+    /// Value could still be received after timeout.
+    pub async fn recv_timeout<U: Into<Millis>>(
+        &self,
+        dur: U,
+    ) -> Result<T, RecvTimeoutError> {
+        match timeout(dur, self.recv()).await {
+            Ok(res) => Ok(res?),
+            Err(_) => Err(RecvTimeoutError::Timeout),
+        }
+    }
+
     /// Polls the oneshot to determine if value is ready
     pub fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Result<T, Canceled>> {
         let inner = get_inner(&self.inner, self.token);
@@ -261,4 +302,37 @@ mod tests {
         tx.send("test").unwrap();
         assert_eq!(rx.await.unwrap(), "test");
     }
+
+    #[ntex_macros::rt_test2]
+    async fn test_pool_reuse() {
+        let p = with_capacity(2);
+        assert!(p.is_empty());
+        assert!(p.capacity() >= 2);
+
+        let (tx, rx) = p.channel();
+        assert_eq!(p.len(), 1);
+        assert_eq!(
+            rx.recv_timeout(Millis(50)).await,
+            Err(RecvTimeoutError::Timeout)
+        );
+        tx.send("test").unwrap();
+        assert_eq!(rx.recv_timeout(Millis(50)).await, Ok("test"));
+        drop(rx);
+        assert!(p.is_empty());
+
+        let capacity = p.capacity();
+        for _ in 0..10 {
+            let (tx, rx) = p.channel();
+            tx.send("test").unwrap();
+            assert_eq!(rx.recv().await.unwrap(), "test");
+        }
+        assert_eq!(p.capacity(), capacity);
+
+        let (tx, rx) = p.channel();
+        drop(tx);
+        assert_eq!(
+            rx.recv_timeout(Millis(50)).await,
+            Err(RecvTimeoutError::Canceled)
+        );
+    }
 }
@@ -0,0 +1,272 @@
+//! Service that converts panics of the inner service into errors.
+//!
+//! Panic inside service call future is caught and returned as
+//! `CatchPanicError::Panic` error, worker and other connections stay alive.
+use std::{any::Any, fmt, future::poll_fn, future::Future, panic, rc::Rc};
+
+use ntex_service::{IntoService, Middleware, Service, ServiceCtx};
+
+type PanicFn = Rc<dyn Fn(&str)>;
+
+/// Converts panics of the inner service into errors.
+///
+/// Inner service could stay in inconsistent state after panic,
+/// service must be panic safe to be used with this middleware.
+pub struct CatchPanic {
+    on_panic: Option<PanicFn>,
+}
+
+/// CatchPanic error
+pub enum CatchPanicError<E> {
+    /// Service error
+    Service(E),
+    /// Service call panicked, contains panic message
+    Panic(String),
+}
+
+impl<E> From<E> for CatchPanicError<E> {
+    fn from(err: E) -> Self {
+        CatchPanicError::Service(err)
+    }
+}
+
+impl<E: fmt::Debug> fmt::Debug for CatchPanicError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CatchPanicError::Service(e) => write!(f, "CatchPanicError::Service({:?})", e),
+            CatchPanicError::Panic(msg) => write!(f, "CatchPanicError::Panic({:?})", msg),
+        }
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for CatchPanicError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CatchPanicError::Service(e) => e.fmt(f),
+            CatchPanicError::Panic(msg) => write!(f, "Service call panicked: {}", msg),
+        }
+    }
+}
+
+impl<E: fmt::Display + fmt::Debug> std::error::Error for CatchPanicError<E> {}
+
+impl<E: PartialEq> PartialEq for CatchPanicError<E> {
+    fn eq(&self, other: &CatchPanicError<E>) -> bool {
+        match (self, other) {
+            (CatchPanicError::Service(e1), CatchPanicError::Service(e2)) => e1 == e2,
+            (CatchPanicError::Panic(m1), CatchPanicError::Panic(m2)) => m1 == m2,
+            _ => false,
+        }
+    }
+}
+
+impl CatchPanic {
+    pub fn new() -> Self {
+        CatchPanic { on_panic: None }
+    }
+
+    /// Set callback for panic reporting
+    ///
+    /// Callback receives panic message.
+    pub fn on_panic<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&str) + 'static,
+    {
+        self.on_panic = Some(Rc::new(f));
+        self
+    }
+}
+
+impl Default for CatchPanic {
+    fn default() -> Self {
+        CatchPanic::new()
+    }
+}
+
+impl Clone for CatchPanic {
+    fn clone(&self) -> Self {
+        CatchPanic {
+            on_panic: self.on_panic.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for CatchPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CatchPanic")
+            .field("on_panic", &self.on_panic.is_some())
+            .finish()
+    }
+}
+
+impl<S> Middleware<S> for CatchPanic {
+    type Service = CatchPanicService<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        CatchPanicService {
+            service,
+            on_panic: self.on_panic.clone(),
+        }
+    }
+}
+
+/// Converts panics of the inner service into errors.
+pub struct CatchPanicService<S> {
+    service: S,
+    on_panic: Option<PanicFn>,
+}
+
+impl<S> CatchPanicService<S> {
+    pub fn new<U, R>(service: U) -> Self
+    where
+        S: Service<R>,
+        U: IntoService<S, R>,
+    {
+        CatchPanicService {
+            service: service.into_service(),
+            on_panic: None,
+        }
+    }
+}
+
+impl<S: Clone> Clone for CatchPanicService<S> {
+    fn clone(&self) -> Self {
+        CatchPanicService {
+            service: self.service.clone(),
+            on_panic: self.on_panic.clone(),
+        }
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for CatchPanicService<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CatchPanicService")
+            .field("service", &self.service)
+            .finish()
+    }
+}
+
+/// Extract message from panic payload
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Ok(msg) = payload.downcast::<String>() {
+        *msg
+    } else {
+        "Box<dyn Any>".to_string()
+    }
+}
+
+impl<S, R> Service<R> for CatchPanicService<S>
+where
+    S: Service<R>,
+{
+    type Response = S::Response;
+    type Error = CatchPanicError<S::Error>;
+
+    async fn call(
+        &self,
+        request: R,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        let fut = ctx.call(&self.service, request);
+        let mut fut = std::pin::pin!(fut);
+
+        let result = poll_fn(|cx| {
+            match panic::catch_unwind(panic::AssertUnwindSafe(|| fut.as_mut().poll(cx))) {
+                Ok(res) => res.map(Ok),
+                Err(payload) => std::task::Poll::Ready(Err(payload)),
+            }
+        })
+        .await;
+
+        match result {
+            Ok(res) => res.map_err(CatchPanicError::Service),
+            Err(payload) => {
+                let msg = panic_message(payload);
+                log::error!("Service call panicked: {}", msg);
+                if let Some(ref f) = self.on_panic {
+                    f(&msg);
+                }
+                Err(CatchPanicError::Panic(msg))
+            }
+        }
+    }
+
+    ntex_service::forward_poll_ready!(service, CatchPanicError::Service);
+    ntex_service::forward_poll_shutdown!(service);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use ntex_service::{apply, fn_factory, Pipeline, ServiceFactory};
+
+    use super::*;
+    use crate::future::lazy;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct PanicService;
+
+    impl Service<u32> for PanicService {
+        type Response = u32;
+        type Error = &'static str;
+
+        async fn call(
+            &self,
+            req: u32,
+            _: ServiceCtx<'_, Self>,
+        ) -> Result<u32, Self::Error> {
+            crate::time::sleep(crate::time::Millis(10)).await;
+            match req {
+                0 => Err("error"),
+                1 => panic!("test panic"),
+                2 => panic!("{} panic", "formatted"),
+                n => Ok(n),
+            }
+        }
+    }
+
+    #[ntex_macros::rt_test2]
+    async fn test_catch_panic() {
+        let srv = Pipeline::new(CatchPanicService::new(PanicService).clone());
+        assert_eq!(srv.call(3).await, Ok(3));
+        assert_eq!(srv.call(0).await, Err(CatchPanicError::Service("error")));
+        assert_eq!(
+            srv.call(1).await,
+            Err(CatchPanicError::Panic("test panic".to_string()))
+        );
+        assert_eq!(
+            srv.call(2).await,
+            Err(CatchPanicError::Panic("formatted panic".to_string()))
+        );
+
+        // service is still alive
+        assert_eq!(srv.call(4).await, Ok(4));
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert!(lazy(|cx| srv.poll_shutdown(cx)).await.is_ready());
+        assert!(format!("{:?}", srv).contains("CatchPanicService"));
+    }
+
+    #[ntex_macros::rt_test2]
+    #[allow(clippy::redundant_clone)]
+    async fn test_catch_panic_middleware() {
+        let panics = Rc::new(RefCell::new(Vec::new()));
+        let panics2 = panics.clone();
+
+        let factory = apply(
+            CatchPanic::new()
+                .on_panic(move |msg| panics2.borrow_mut().push(msg.to_string()))
+                .clone(),
+            fn_factory(|| async { Ok::<_, ()>(PanicService) }),
+        );
+        let srv = factory.pipeline(&()).await.unwrap();
+
+        assert_eq!(srv.call(5).await, Ok(5));
+        let err = srv.call(1).await.unwrap_err();
+        assert_eq!(err.to_string(), "Service call panicked: test panic");
+        assert_eq!(*panics.borrow(), vec!["test panic".to_string()]);
+        assert!(format!("{:?}", CatchPanic::default()).contains("CatchPanic"));
+    }
+}
@@ -1,9 +1,11 @@
 pub mod buffer;
+pub mod catch_panic;
 pub mod counter;
 mod extensions;
 pub mod inflight;
 pub mod keepalive;
 pub mod onerequest;
+pub mod registry;
 pub mod timeout;
 pub mod variant;
 
@@ -0,0 +1,269 @@
+//! Dynamic registry of services keyed by name.
+//!
+//! Services could be added, replaced or removed at runtime. Requests
+//! that are already in flight complete on the service they were routed to.
+use std::{cell::RefCell, fmt, hash::Hash, rc::Rc, task::Context, task::Poll};
+
+use ntex_service::boxed::{self, BoxService};
+use ntex_service::{Service, ServiceCtx};
+
+use crate::HashMap;
+
+type Services<K, Req, Res, Err> = HashMap<K, Rc<BoxService<Req, Res, Err>>>;
+
+/// Registry of boxed services
+pub struct ServiceRegistry<K, Req, Res, Err> {
+    services: Rc<RefCell<Services<K, Req, Res, Err>>>,
+}
+
+/// Routed service error
+pub enum RoutedError<E> {
+    /// Service error
+    Service(E),
+    /// Cannot find service for request
+    NotFound,
+}
+
+impl<K, Req, Res, Err> ServiceRegistry<K, Req, Res, Err>
+where
+    K: Eq + Hash,
+    Req: 'static,
+{
+    /// Create empty registry
+    pub fn new() -> Self {
+        Self {
+            services: Rc::new(RefCell::new(HashMap::default())),
+        }
+    }
+
+    /// Register service under the key.
+    ///
+    /// Replaces existing service, returns `true` if entry with the same key
+    /// has been registered before.
+    pub fn insert<S>(&self, key: K, service: S) -> bool
+    where
+        S: Service<Req, Response = Res, Error = Err> + 'static,
+    {
+        self.services
+            .borrow_mut()
+            .insert(key, Rc::new(boxed::service(service)))
+            .is_some()
+    }
+
+    /// Remove service from the registry
+    pub fn remove(&self, key: &K) -> bool {
+        self.services.borrow_mut().remove(key).is_some()
+    }
+
+    /// Check if service for the key is registered
+    pub fn contains(&self, key: &K) -> bool {
+        self.services.borrow().contains_key(key)
+    }
+
+    /// Number of registered services
+    pub fn len(&self) -> usize {
+        self.services.borrow().len()
+    }
+
+    /// Check if registry is empty
+    pub fn is_empty(&self) -> bool {
+        self.services.borrow().is_empty()
+    }
+
+    /// Get registered keys
+    pub fn keys(&self) -> Vec<K>
+    where
+        K: Clone,
+    {
+        self.services.borrow().keys().cloned().collect()
+    }
+
+    /// Create service that dispatches requests by the key extracted from request.
+    ///
+    /// Requests for unknown keys fail with `RoutedError::NotFound`.
+    pub fn router<F>(&self, f: F) -> RoutedService<K, F, Req, Res, Err>
+    where
+        F: Fn(&Req) -> Option<K>,
+    {
+        RoutedService {
+            f,
+            registry: self.clone(),
+        }
+    }
+
+    fn get(&self, key: &K) -> Option<Rc<BoxService<Req, Res, Err>>> {
+        self.services.borrow().get(key).cloned()
+    }
+}
+
+impl<K, Req, Res, Err> Default for ServiceRegistry<K, Req, Res, Err>
+where
+    K: Eq + Hash,
+    Req: 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, Req, Res, Err> Clone for ServiceRegistry<K, Req, Res, Err> {
+    fn clone(&self) -> Self {
+        Self {
+            services: self.services.clone(),
+        }
+    }
+}
+
+impl<K: fmt::Debug, Req, Res, Err> fmt::Debug for ServiceRegistry<K, Req, Res, Err> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServiceRegistry")
+            .field("keys", &self.services.borrow().keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Service that dispatches requests to registered services
+pub struct RoutedService<K, F, Req, Res, Err> {
+    f: F,
+    registry: ServiceRegistry<K, Req, Res, Err>,
+}
+
+impl<K, F, Req, Res, Err> RoutedService<K, F, Req, Res, Err> {
+    /// Get reference to services registry
+    pub fn registry(&self) -> &ServiceRegistry<K, Req, Res, Err> {
+        &self.registry
+    }
+}
+
+impl<K, F, Req, Res, Err> Clone for RoutedService<K, F, Req, Res, Err>
+where
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            f: self.f.clone(),
+            registry: self.registry.clone(),
+        }
+    }
+}
+
+impl<K: fmt::Debug, F, Req, Res, Err> fmt::Debug for RoutedService<K, F, Req, Res, Err> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RoutedService")
+            .field("registry", &self.registry)
+            .finish()
+    }
+}
+
+impl<K, F, Req, Res, Err> Service<Req> for RoutedService<K, F, Req, Res, Err>
+where
+    K: Eq + Hash,
+    F: Fn(&Req) -> Option<K>,
+    Req: 'static,
+{
+    type Response = Res;
+    type Error = RoutedError<Err>;
+
+    fn poll_shutdown(&self, cx: &mut Context<'_>) -> Poll<()> {
+        let mut ready = true;
+        for srv in self.registry.services.borrow().values() {
+            ready &= srv.poll_shutdown(cx).is_ready();
+        }
+        if ready {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    async fn call(
+        &self,
+        req: Req,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        let srv = (self.f)(&req)
+            .and_then(|key| self.registry.get(&key))
+            .ok_or(RoutedError::NotFound)?;
+        ctx.call(srv.as_ref(), req)
+            .await
+            .map_err(RoutedError::Service)
+    }
+}
+
+impl<E> From<E> for RoutedError<E> {
+    fn from(err: E) -> Self {
+        RoutedError::Service(err)
+    }
+}
+
+impl<E: fmt::Debug> fmt::Debug for RoutedError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoutedError::Service(e) => write!(f, "RoutedError::Service({:?})", e),
+            RoutedError::NotFound => write!(f, "RoutedError::NotFound"),
+        }
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for RoutedError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoutedError::Service(e) => e.fmt(f),
+            RoutedError::NotFound => write!(f, "Service is not found"),
+        }
+    }
+}
+
+impl<E: fmt::Display + fmt::Debug> std::error::Error for RoutedError<E> {}
+
+impl<E: PartialEq> PartialEq for RoutedError<E> {
+    fn eq(&self, other: &RoutedError<E>) -> bool {
+        match (self, other) {
+            (RoutedError::Service(e1), RoutedError::Service(e2)) => e1 == e2,
+            (RoutedError::NotFound, RoutedError::NotFound) => true,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex_service::{fn_service, Pipeline};
+
+    use super::*;
+
+    #[ntex_macros::rt_test2]
+    async fn test_registry() {
+        let registry = ServiceRegistry::<&'static str, (&'static str, u32), u32, ()>::new();
+        assert!(registry.is_empty());
+        assert!(!registry.insert("a", fn_service(|(_, v): (_, u32)| async move { Ok(v) })));
+        assert!(!registry.insert(
+            "b",
+            fn_service(|(_, v): (_, u32)| async move { Ok(v * 10) })
+        ));
+        assert_eq!(registry.len(), 2);
+        assert!(registry.contains(&"a"));
+        assert!(format!("{:?}", registry).contains("ServiceRegistry"));
+
+        let srv = Pipeline::new(registry.router(|req: &(&'static str, u32)| Some(req.0)));
+        assert_eq!(srv.call(("a", 1)).await, Ok(1));
+        assert_eq!(srv.call(("b", 1)).await, Ok(10));
+        assert_eq!(srv.call(("c", 1)).await, Err(RoutedError::NotFound));
+
+        // hot swap
+        assert!(registry.insert(
+            "a",
+            fn_service(|(_, v): (_, u32)| async move { Ok(v + 100) })
+        ));
+        assert_eq!(srv.call(("a", 1)).await, Ok(101));
+
+        assert!(registry.remove(&"b"));
+        assert!(!registry.remove(&"b"));
+        assert_eq!(srv.call(("b", 1)).await, Err(RoutedError::NotFound));
+        assert_eq!(srv.get_ref().registry().keys(), vec!["a"]);
+
+        let err = RoutedError::<String>::NotFound;
+        assert_eq!(format!("{}", err), "Service is not found");
+        assert_eq!(format!("{:?}", err), "RoutedError::NotFound");
+    }
+}
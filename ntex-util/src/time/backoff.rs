@@ -0,0 +1,262 @@
+//! Backoff delays for retries.
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use std::{cmp, fmt, future::poll_fn, pin::Pin};
+
+use nanorand::{Rng, WyRand};
+
+use super::{now, Millis, Sleep};
+use crate::Stream;
+
+/// Strategy for computing delay between attempts
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Strategy {
+    /// Same delay for every attempt
+    Constant(Millis),
+    /// Delay doubles after each attempt, starting from `min` up to `max`
+    Exponential { min: Millis, max: Millis },
+    /// Exponential delay with random jitter
+    ///
+    /// Delay is random value between `min` and three times previous delay,
+    /// but no more than `max`. Jitter spreads attempts of many clients
+    /// that failed at the same time.
+    DecorrelatedJitter { min: Millis, max: Millis },
+}
+
+impl Default for Strategy {
+    /// Exponential delays from 100 millis to 30 seconds
+    fn default() -> Self {
+        Strategy::Exponential {
+            min: Millis(100),
+            max: Millis(30_000),
+        }
+    }
+}
+
+/// Sequence of delays between attempts
+///
+/// `Backoff` is an iterator of delays and a stream that yields delay
+/// after it elapsed on the timer wheel. Sequence ends when max number of
+/// attempts is reached or when next delay exceeds max elapsed time.
+///
+/// ```rust
+/// use ntex::time::{backoff::{Backoff, Strategy}, Millis};
+///
+/// #[ntex::main]
+/// async fn main() {
+///     let mut backoff = Backoff::new(Strategy::DecorrelatedJitter {
+///         min: Millis(10),
+///         max: Millis(100),
+///     })
+///     .max_attempts(3);
+///
+///     while let Some(delay) = backoff.next_delay().await {
+///         println!("Retry after {:?}", delay);
+///     }
+/// }
+/// ```
+pub struct Backoff {
+    strategy: Strategy,
+    attempt: usize,
+    max_attempts: usize,
+    max_elapsed: Millis,
+    started: Option<Instant>,
+    prev: Millis,
+    rng: WyRand,
+    sleep: Option<(Sleep, Millis)>,
+}
+
+impl Backoff {
+    /// Create backoff with specified strategy.
+    ///
+    /// By default number of attempts and elapsed time are not limited.
+    pub fn new(strategy: Strategy) -> Self {
+        Backoff {
+            strategy,
+            attempt: 0,
+            max_attempts: 0,
+            max_elapsed: Millis::ZERO,
+            started: None,
+            prev: Millis::ZERO,
+            rng: WyRand::new(),
+            sleep: None,
+        }
+    }
+
+    /// Set max number of attempts.
+    ///
+    /// Zero means unlimited.
+    pub fn max_attempts(mut self, num: usize) -> Self {
+        self.max_attempts = num;
+        self
+    }
+
+    /// Set max total time spent on attempts.
+    ///
+    /// Time is counted from the first delay. Zero means unlimited.
+    pub fn max_elapsed<T: Into<Millis>>(mut self, timeout: T) -> Self {
+        self.max_elapsed = timeout.into();
+        self
+    }
+
+    /// Number of delays produced since creation or last reset
+    pub fn attempt(&self) -> usize {
+        self.attempt
+    }
+
+    /// Start sequence from the beginning, i.e. after successful attempt.
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+        self.started = None;
+        self.prev = Millis::ZERO;
+        self.sleep = None;
+    }
+
+    /// Wait for next delay to elapse.
+    ///
+    /// Returns `None` if sequence is exhausted, in that case it does not wait.
+    pub async fn next_delay(&mut self) -> Option<Millis> {
+        poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+
+    fn delay(&mut self) -> Millis {
+        match self.strategy {
+            Strategy::Constant(delay) => delay,
+            Strategy::Exponential { min, max } => {
+                let delay = min
+                    .0
+                    .saturating_mul(2u32.saturating_pow(self.attempt as u32));
+                Millis(cmp::min(delay, max.0))
+            }
+            Strategy::DecorrelatedJitter { min, max } => {
+                let upper = cmp::max(self.prev.0, min.0).saturating_mul(3);
+                let delay = if upper > min.0 {
+                    self.rng.generate_range(min.0..=upper)
+                } else {
+                    min.0
+                };
+                Millis(cmp::min(delay, max.0))
+            }
+        }
+    }
+}
+
+impl Iterator for Backoff {
+    type Item = Millis;
+
+    /// Next delay, without waiting
+    fn next(&mut self) -> Option<Millis> {
+        if self.max_attempts != 0 && self.attempt >= self.max_attempts {
+            return None;
+        }
+
+        let delay = self.delay();
+        if self.max_elapsed != Millis::ZERO {
+            let now = now();
+            let started = *self.started.get_or_insert(now);
+            if now.duration_since(started) + delay > Duration::from(self.max_elapsed) {
+                return None;
+            }
+        }
+        self.attempt += 1;
+        self.prev = delay;
+        Some(delay)
+    }
+}
+
+impl Stream for Backoff {
+    type Item = Millis;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Millis>> {
+        if self.sleep.is_none() {
+            if let Some(delay) = self.next() {
+                self.sleep = Some((Sleep::new(delay), delay));
+            } else {
+                return Poll::Ready(None);
+            }
+        }
+
+        let (sleep, delay) = self.sleep.as_ref().unwrap();
+        let delay = *delay;
+        if sleep.poll_elapsed(cx).is_ready() {
+            self.sleep = None;
+            Poll::Ready(Some(delay))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl fmt::Debug for Backoff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Backoff")
+            .field("strategy", &self.strategy)
+            .field("attempt", &self.attempt)
+            .field("max_attempts", &self.max_attempts)
+            .field("max_elapsed", &self.max_elapsed)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strategies() {
+        let delays: Vec<_> = Backoff::new(Strategy::Constant(Millis(10)))
+            .max_attempts(3)
+            .collect();
+        assert_eq!(delays, vec![Millis(10), Millis(10), Millis(10)]);
+
+        let delays: Vec<_> = Backoff::new(Strategy::Exponential {
+            min: Millis(100),
+            max: Millis(1000),
+        })
+        .take(6)
+        .collect();
+        assert_eq!(
+            delays,
+            vec![
+                Millis(100),
+                Millis(200),
+                Millis(400),
+                Millis(800),
+                Millis(1000),
+                Millis(1000)
+            ]
+        );
+
+        let mut prev = Millis(100);
+        for delay in Backoff::new(Strategy::DecorrelatedJitter {
+            min: Millis(100),
+            max: Millis(5000),
+        })
+        .take(100)
+        {
+            assert!(delay.0 >= 100 && delay.0 <= 5000);
+            assert!(delay.0 <= prev.0 * 3);
+            prev = delay;
+        }
+    }
+
+    #[ntex_macros::rt_test2]
+    async fn test_next_delay() {
+        let mut backoff = Backoff::new(Strategy::Constant(Millis(50))).max_attempts(2);
+        let start = Instant::now();
+        assert_eq!(backoff.next_delay().await, Some(Millis(50)));
+        assert_eq!(backoff.next_delay().await, Some(Millis(50)));
+        assert_eq!(backoff.next_delay().await, None);
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert_eq!(backoff.attempt(), 2);
+
+        backoff.reset();
+        assert_eq!(backoff.next(), Some(Millis(50)));
+
+        let mut backoff =
+            Backoff::new(Strategy::Constant(Millis(100))).max_elapsed(Millis(250));
+        assert_eq!(backoff.next_delay().await, Some(Millis(100)));
+        assert_eq!(backoff.next_delay().await, Some(Millis(100)));
+        assert_eq!(backoff.next_delay().await, None);
+    }
+}
@@ -0,0 +1,256 @@
+//! A queue of delayed elements.
+use std::collections::BTreeSet;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use std::{fmt, mem, pin::Pin};
+
+use slab::Slab;
+
+use super::{now, Millis, Sleep};
+use crate::{task::LocalWaker, Stream};
+
+/// Token to a value stored in a `DelayQueue`.
+///
+/// Key is invalidated after value get expired or removed from the queue.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Key {
+    idx: usize,
+    seq: u64,
+}
+
+/// A queue of delayed elements.
+///
+/// Once an element is inserted into the `DelayQueue`, it is yielded once the
+/// specified deadline has been reached. Expiration is driven by the ntex timer
+/// wheel, so it has the same granularity as [`sleep`](super::sleep).
+///
+/// `DelayQueue` implements `Stream` of expired values. Stream never terminates,
+/// if queue is empty it waits for new values.
+///
+/// ```rust
+/// use ntex::time::{delay_queue::DelayQueue, Millis};
+/// use ntex::util::stream_recv;
+///
+/// #[ntex::main]
+/// async fn main() {
+///     let mut queue = DelayQueue::new();
+///     let key = queue.insert("session-1", Millis(100));
+///     queue.insert("session-2", Millis(50));
+///
+///     // extend session
+///     queue.reset(&key, Millis(200));
+///
+///     assert_eq!(stream_recv(&mut queue).await, Some("session-2"));
+///     assert_eq!(stream_recv(&mut queue).await, Some("session-1"));
+/// }
+/// ```
+pub struct DelayQueue<T> {
+    entries: Slab<Entry<T>>,
+    expirations: BTreeSet<(Instant, usize)>,
+    delay: Option<Sleep>,
+    waker: LocalWaker,
+    seq: u64,
+}
+
+struct Entry<T> {
+    value: T,
+    deadline: Instant,
+    seq: u64,
+}
+
+impl<T> Default for DelayQueue<T> {
+    fn default() -> Self {
+        DelayQueue::new()
+    }
+}
+
+impl<T> DelayQueue<T> {
+    /// Create new empty `DelayQueue`
+    pub fn new() -> Self {
+        DelayQueue {
+            entries: Slab::new(),
+            expirations: BTreeSet::new(),
+            delay: None,
+            waker: LocalWaker::new(),
+            seq: 0,
+        }
+    }
+
+    /// Insert value into the queue, value expires after `timeout`.
+    pub fn insert<U: Into<Millis>>(&mut self, value: T, timeout: U) -> Key {
+        let deadline = now() + Duration::from(timeout.into());
+        self.seq = self.seq.wrapping_add(1);
+
+        let seq = self.seq;
+        let idx = self.entries.insert(Entry {
+            value,
+            deadline,
+            seq,
+        });
+        self.expirations.insert((deadline, idx));
+        self.waker.wake();
+
+        Key { idx, seq }
+    }
+
+    /// Reset value's expiration, value expires after `timeout` from now.
+    ///
+    /// Returns `false` if key is not valid.
+    pub fn reset<U: Into<Millis>>(&mut self, key: &Key, timeout: U) -> bool {
+        let deadline = now() + Duration::from(timeout.into());
+
+        if let Some(entry) = self.entries.get_mut(key.idx).filter(|e| e.seq == key.seq) {
+            let prev = mem::replace(&mut entry.deadline, deadline);
+            self.expirations.remove(&(prev, key.idx));
+            self.expirations.insert((deadline, key.idx));
+            self.waker.wake();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Remove value from the queue.
+    ///
+    /// Returns `None` if key is not valid.
+    pub fn remove(&mut self, key: &Key) -> Option<T> {
+        let deadline = self.get_entry(key)?.deadline;
+        self.expirations.remove(&(deadline, key.idx));
+        Some(self.entries.remove(key.idx).value)
+    }
+
+    /// Get reference to the value.
+    pub fn get(&self, key: &Key) -> Option<&T> {
+        self.get_entry(key).map(|entry| &entry.value)
+    }
+
+    /// Check if key is valid.
+    pub fn contains(&self, key: &Key) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns the number of values in the queue.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the queue contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Remove all values from the queue.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.expirations.clear();
+        self.delay = None;
+    }
+
+    /// Attempt to pull out the next expired value.
+    pub fn poll_expired(&mut self, cx: &mut Context<'_>) -> Poll<T> {
+        self.waker.register(cx.waker());
+
+        loop {
+            let (deadline, idx) = if let Some(item) = self.expirations.first() {
+                *item
+            } else {
+                self.delay = None;
+                return Poll::Pending;
+            };
+
+            let now = now();
+            if deadline <= now {
+                self.expirations.remove(&(deadline, idx));
+                return Poll::Ready(self.entries.remove(idx).value);
+            }
+
+            let timeout = Millis::from(deadline - now);
+            if let Some(ref delay) = self.delay {
+                delay.reset(timeout);
+            } else {
+                self.delay = Some(Sleep::new(timeout));
+            }
+            if self.delay.as_ref().unwrap().poll_elapsed(cx).is_pending() {
+                return Poll::Pending;
+            }
+        }
+    }
+
+    fn get_entry(&self, key: &Key) -> Option<&Entry<T>> {
+        self.entries
+            .get(key.idx)
+            .filter(|entry| entry.seq == key.seq)
+    }
+}
+
+impl<T> Unpin for DelayQueue<T> {}
+
+impl<T> Stream for DelayQueue<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.poll_expired(cx).map(Some)
+    }
+}
+
+impl<T> fmt::Debug for DelayQueue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DelayQueue")
+            .field("len", &self.entries.len())
+            .field("next", &self.expirations.first().map(|item| item.0))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::future::{lazy, stream_recv};
+    use crate::time::sleep;
+
+    #[ntex_macros::rt_test2]
+    async fn test_delay_queue() {
+        let mut queue = DelayQueue::new();
+        assert!(queue.is_empty());
+        assert!(lazy(|cx| queue.poll_expired(cx)).await.is_pending());
+
+        let k1 = queue.insert(1, Millis(50));
+        let k2 = queue.insert(2, Millis(25));
+        let k3 = queue.insert(3, Millis(100));
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.get(&k1), Some(&1));
+        assert!(format!("{:?}", queue).contains("DelayQueue"));
+
+        assert_eq!(queue.remove(&k3), Some(3));
+        assert_eq!(queue.remove(&k3), None);
+        assert!(!queue.contains(&k3));
+        assert!(!queue.reset(&k3, Millis(10)));
+
+        assert_eq!(stream_recv(&mut queue).await, Some(2));
+        assert!(!queue.contains(&k2));
+        assert_eq!(stream_recv(&mut queue).await, Some(1));
+        assert!(queue.is_empty());
+
+        // stale key does not affect new value in the same slot
+        let k4 = queue.insert(4, Millis(25));
+        assert_eq!(k4.idx, k1.idx);
+        assert_eq!(queue.remove(&k1), None);
+        assert_eq!(queue.get(&k4), Some(&4));
+        queue.clear();
+        assert!(queue.is_empty());
+    }
+
+    #[ntex_macros::rt_test2]
+    async fn test_delay_queue_reset() {
+        let mut queue = DelayQueue::new();
+        let k1 = queue.insert(1, Millis(25));
+        queue.insert(2, Millis(150));
+        assert!(queue.reset(&k1, Millis(300)));
+
+        sleep(Millis(50)).await;
+        assert!(lazy(|cx| queue.poll_expired(cx)).await.is_pending());
+
+        assert_eq!(stream_recv(&mut queue).await, Some(2));
+        assert_eq!(stream_recv(&mut queue).await, Some(1));
+    }
+}
@@ -1,9 +1,13 @@
 //! Utilities for tracking time.
 use std::{cmp, future::poll_fn, future::Future, pin::Pin, task, task::Poll};
 
+pub mod backoff;
+pub mod delay_queue;
 mod types;
 mod wheel;
 
+pub use self::backoff::Backoff;
+pub use self::delay_queue::DelayQueue;
 pub use self::types::{Millis, Seconds};
 pub use self::wheel::{now, query_system_time, system_time, TimerHandle};
 
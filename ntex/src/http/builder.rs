@@ -1,7 +1,7 @@
 use std::{error::Error, fmt, marker::PhantomData};
 
 use crate::http::body::MessageBody;
-use crate::http::config::{KeepAlive, ServiceConfig};
+use crate::http::config::{FlushStrategy, KeepAlive, ServiceConfig};
 use crate::http::error::{H2Error, ResponseError};
 use crate::http::h1::{self, H1Service};
 use crate::http::h2::{self, H2Service};
@@ -136,6 +136,40 @@ where
         self
     }
 
+    /// Set http/2 graceful shutdown drain window.
+    ///
+    /// On server shutdown each http/2 connection receives GOAWAY frame, after
+    /// drain window final GOAWAY frame gets sent and connection is closed
+    /// once in-flight streams complete. To disable set value to 0.
+    ///
+    /// By default drain window is equal to client disconnect timeout.
+    pub fn h2_drain_timeout(mut self, timeout: Seconds) -> Self {
+        self.config.h2_drain_timeout(timeout);
+        self
+    }
+
+    /// Preserve original casing of http/1 header names.
+    ///
+    /// Original casing is stored in request's `HeaderMap` and header names
+    /// with recorded casing are written verbatim.
+    ///
+    /// By default header case preservation is disabled.
+    pub fn preserve_header_case(mut self, enabled: bool) -> Self {
+        self.config.preserve_header_case(enabled);
+        self
+    }
+
+    /// Set http/1 response flushing strategy.
+    ///
+    /// Response data could be passed to the io immediately or coalesced
+    /// to reduce number of socket writes, see [`FlushStrategy`].
+    ///
+    /// By default response data is flushed immediately.
+    pub fn flush_strategy(mut self, strategy: FlushStrategy) -> Self {
+        self.config.flush_strategy(strategy);
+        self
+    }
+
     /// Provide control service for http/1.
     pub fn h1_control<CF, CT>(self, control: CF) -> HttpServiceBuilder<F, S, CT, C2>
     where
@@ -0,0 +1,179 @@
+//! Send multiple requests concurrently
+//!
+//! ```rust,no_run
+//! use ntex::http::client::{batch, Client};
+//! use ntex::util::stream_recv;
+//!
+//! #[ntex::main]
+//! async fn main() {
+//!     let client = Client::default();
+//!     let reqs = ["http://www.rust-lang.org", "https://crates.io"]
+//!         .into_iter()
+//!         .map(|url| client.get(url));
+//!
+//!     let mut results = batch::send_all(reqs, 8).limit_per_host(2);
+//!     while let Some((idx, res)) = stream_recv(&mut results).await {
+//!         println!("Request #{}: {:?}", idx, res.map(|res| res.status()));
+//!     }
+//! }
+//! ```
+use std::task::{Context, Poll};
+use std::{collections::VecDeque, fmt, future::Future, pin::Pin};
+
+use crate::util::{HashMap, Stream};
+
+use super::{error::SendRequestError, ClientRequest, ClientResponse, SendClientRequest};
+
+/// Send requests with concurrency limit.
+///
+/// Returns stream of request indexes and results in order of completion.
+/// Requests are pulled from iterator lazily, at most `concurrency` requests
+/// are in-flight at the same time.
+///
+/// Request is in-flight until response head is received. Response holds pool's
+/// connection until response body is read or response is dropped, so responses
+/// should be processed as they arrive, otherwise pool could starve.
+pub fn send_all<I>(reqs: I, concurrency: usize) -> SendAll<I::IntoIter>
+where
+    I: IntoIterator<Item = ClientRequest>,
+{
+    SendAll {
+        reqs: reqs.into_iter(),
+        next: 0,
+        concurrency: std::cmp::max(concurrency, 1),
+        limit_per_host: 0,
+        exhausted: false,
+        hosts: HashMap::default(),
+        deferred: VecDeque::new(),
+        inflight: Vec::new(),
+    }
+}
+
+/// Stream of concurrently sent requests results
+pub struct SendAll<I> {
+    reqs: I,
+    next: usize,
+    concurrency: usize,
+    limit_per_host: usize,
+    exhausted: bool,
+    hosts: HashMap<String, usize>,
+    deferred: VecDeque<(usize, String, ClientRequest)>,
+    inflight: Vec<(usize, String, SendClientRequest)>,
+}
+
+impl<I> SendAll<I> {
+    /// Set max number of in-flight requests per host.
+    ///
+    /// Requests to hosts at limit are deferred and requests to other hosts
+    /// get started instead, so single slow host does not occupy all
+    /// concurrency slots. Limit should not exceed connector's
+    /// `limit_per_host()`. By default is not limited.
+    pub fn limit_per_host(mut self, limit: usize) -> Self {
+        self.limit_per_host = limit;
+        self
+    }
+
+    fn available(&self, host: &str) -> bool {
+        self.limit_per_host == 0
+            || self.hosts.get(host).copied().unwrap_or(0) < self.limit_per_host
+    }
+
+    fn start(&mut self, idx: usize, host: String, req: ClientRequest) {
+        *self.hosts.entry(host.clone()).or_insert(0) += 1;
+        self.inflight.push((idx, host, req.send()));
+    }
+
+    fn release(&mut self, host: &str) {
+        if let Some(cnt) = self.hosts.get_mut(host) {
+            *cnt -= 1;
+            if *cnt == 0 {
+                self.hosts.remove(host);
+            }
+        }
+    }
+}
+
+impl<I> SendAll<I>
+where
+    I: Iterator<Item = ClientRequest>,
+{
+    fn fill(&mut self) {
+        while self.inflight.len() < self.concurrency {
+            // deferred requests go first
+            if let Some(pos) = self
+                .deferred
+                .iter()
+                .position(|(_, host, _)| self.available(host))
+            {
+                let (idx, host, req) = self.deferred.remove(pos).unwrap();
+                self.start(idx, host, req);
+                continue;
+            }
+
+            if self.exhausted || self.deferred.len() >= self.concurrency {
+                break;
+            }
+
+            if let Some(req) = self.reqs.next() {
+                let idx = self.next;
+                self.next += 1;
+
+                let host = req
+                    .get_uri()
+                    .authority()
+                    .map(|auth| auth.as_str().to_string())
+                    .unwrap_or_default();
+                if self.available(&host) {
+                    self.start(idx, host, req);
+                } else {
+                    self.deferred.push_back((idx, host, req));
+                }
+            } else {
+                self.exhausted = true;
+            }
+        }
+    }
+}
+
+impl<I> Stream for SendAll<I>
+where
+    I: Iterator<Item = ClientRequest> + Unpin,
+{
+    type Item = (usize, Result<ClientResponse, SendRequestError>);
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        this.fill();
+
+        for i in 0..this.inflight.len() {
+            if let Poll::Ready(res) = Pin::new(&mut this.inflight[i].2).poll(cx) {
+                let (idx, host, _) = this.inflight.swap_remove(i);
+                this.release(&host);
+                return Poll::Ready(Some((idx, res)));
+            }
+        }
+
+        if this.inflight.is_empty() && this.deferred.is_empty() && this.exhausted {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.reqs.size_hint();
+        let pending = self.inflight.len() + self.deferred.len();
+        (lower + pending, upper.map(|upper| upper + pending))
+    }
+}
+
+impl<I> fmt::Debug for SendAll<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SendAll")
+            .field("concurrency", &self.concurrency)
+            .field("limit_per_host", &self.limit_per_host)
+            .field("inflight", &self.inflight.len())
+            .field("deferred", &self.deferred.len())
+            .finish()
+    }
+}
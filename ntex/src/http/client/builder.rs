@@ -4,11 +4,14 @@ use base64::{engine::general_purpose::STANDARD as base64, Engine};
 
 use crate::http::error::HttpError;
 use crate::http::header::{self, HeaderMap, HeaderName, HeaderValue};
-use crate::{service::Service, time::Millis};
+use crate::service::{boxed, Middleware, Pipeline, Service};
+use crate::time::Millis;
 
 use super::connect::ConnectorWrapper;
-use super::error::ConnectError;
-use super::{Client, ClientConfig, Connect, Connection, Connector};
+use super::error::{ConnectError, SendRequestError};
+use super::middleware::SendService;
+use super::{Client, ClientConfig, ClientResponse, ClientService, ClientServiceRequest};
+use super::{Connect, Connection, Connector};
 
 /// An HTTP Client builder
 ///
@@ -20,6 +23,7 @@ pub struct ClientBuilder {
     default_headers: bool,
     allow_redirects: bool,
     max_redirects: usize,
+    service: Option<ClientService>,
 }
 
 impl Default for ClientBuilder {
@@ -34,12 +38,21 @@ impl ClientBuilder {
             default_headers: true,
             allow_redirects: true,
             max_redirects: 10,
+            service: None,
             config: ClientConfig {
                 headers: HeaderMap::new(),
                 timeout: Millis(5_000),
+                connect_timeout: Millis::ZERO,
                 response_pl_limit: 262_144,
                 response_pl_timeout: Millis(10_000),
-                connector: Box::new(ConnectorWrapper(Connector::default().finish().into())),
+                response_decompress: true,
+                response_decompress_limit: 0,
+                max_redirects: 10,
+                preserve_header_case: false,
+                #[cfg(feature = "cookie")]
+                cookie_store: None,
+                service: None,
+                connector: Box::new(ConnectorWrapper::new(Connector::default().finish())),
             },
         }
     }
@@ -51,14 +64,15 @@ impl ClientBuilder {
             + fmt::Debug
             + 'static,
     {
-        self.config.connector = Box::new(ConnectorWrapper(connector.into()));
+        self.config.connector = Box::new(ConnectorWrapper::new(connector));
         self
     }
 
     /// Set request timeout.
     ///
-    /// Request timeout is the total time before a response must be received.
-    /// Default value is 5 seconds.
+    /// Request timeout is the time before a response head must be received
+    /// after request is sent. Request fails with `SendRequestError::Timeout`
+    /// error. Default value is 5 seconds.
     pub fn timeout<T: Into<Millis>>(mut self, timeout: T) -> Self {
         self.config.timeout = timeout.into();
         self
@@ -70,6 +84,17 @@ impl ClientBuilder {
         self
     }
 
+    /// Set connect timeout.
+    ///
+    /// Connect timeout is the total time to acquire connection from the pool,
+    /// including time to establish new connection. Request fails with
+    /// `ConnectError::Timeout` error. By default timeout is not set and
+    /// only connector's timeout applies.
+    pub fn connect_timeout<T: Into<Millis>>(mut self, timeout: T) -> Self {
+        self.config.connect_timeout = timeout.into();
+        self
+    }
+
     /// Do not follow redirects.
     ///
     /// Redirects are allowed by default.
@@ -80,7 +105,9 @@ impl ClientBuilder {
 
     /// Set max number of redirects.
     ///
-    /// Max redirects is set to 10 by default.
+    /// Request fails with `SendRequestError::TooManyRedirects` error
+    /// if number of redirects exceeds max value. Max redirects is set
+    /// to 10 by default.
     pub fn max_redirects(mut self, num: usize) -> Self {
         self.max_redirects = num;
         self
@@ -103,12 +130,45 @@ impl ClientBuilder {
     /// Set response timeout.
     ///
     /// Response payload timeout is the total time before a payload must be received.
+    /// Reading payload fails with `PayloadError::Timeout` error.
     /// Default value is 10 seconds.
     pub fn response_payload_timeout(mut self, timeout: Millis) -> Self {
         self.config.response_pl_timeout = timeout;
         self
     }
 
+    /// Disable automatic decompression of response payloads.
+    ///
+    /// By default client sends `Accept-Encoding` header and transparently
+    /// decompresses response payload. Decompression could be disabled
+    /// for specific request with `ClientRequest::no_decompress()` method.
+    pub fn disable_decompress(mut self) -> Self {
+        self.config.response_decompress = false;
+        self
+    }
+
+    /// Max size of decompressed response payload.
+    ///
+    /// Reading payload fails with `PayloadError::Overflow` error if size of
+    /// decompressed payload exceeds limit. By default limit is not set,
+    /// `ClientResponse::body()` and `ClientResponse::json()` methods are
+    /// limited by response payload limit.
+    pub fn response_decompress_limit(mut self, limit: usize) -> Self {
+        self.config.response_decompress_limit = limit;
+        self
+    }
+
+    /// Preserve original casing of response header names.
+    ///
+    /// Applies to http/1 connections only. Could be overridden for specific
+    /// request with `ClientRequest::preserve_header_case()` method.
+    ///
+    /// By default header case is not preserved.
+    pub fn preserve_header_case(mut self, enabled: bool) -> Self {
+        self.config.preserve_header_case = enabled;
+        self
+    }
+
     /// Add default header. Headers added by this method
     /// get added to every request.
     pub fn header<K, V>(mut self, key: K, value: V) -> Self
@@ -153,8 +213,48 @@ impl ClientBuilder {
         self.header(header::AUTHORIZATION, format!("Bearer {}", token))
     }
 
+    /// Use cookie store.
+    ///
+    /// Cookies from `Set-Cookie` response headers are stored in the store,
+    /// matching cookies are attached to subsequent requests. Cookie store
+    /// is disabled by default.
+    #[cfg(feature = "cookie")]
+    pub fn cookie_store(mut self, store: super::CookieStore) -> Self {
+        self.config.cookie_store = Some(store);
+        self
+    }
+
+    /// Register client middleware.
+    ///
+    /// Middleware intercepts every request sent by the client and its
+    /// response, it could be used for auth token injection, logging,
+    /// retries, etc. Middleware wraps whole request processing, including
+    /// redirects. Last registered middleware is called first.
+    pub fn wrap<M>(mut self, mw: M) -> Self
+    where
+        M: Middleware<ClientService>,
+        M::Service: Service<
+                ClientServiceRequest,
+                Response = ClientResponse,
+                Error = SendRequestError,
+            > + 'static,
+    {
+        let inner = self
+            .service
+            .take()
+            .unwrap_or_else(|| boxed::service(SendService));
+        self.service = Some(boxed::service(mw.create(inner)));
+        self
+    }
+
     /// Finish build process and create `Client` instance.
-    pub fn finish(self) -> Client {
+    pub fn finish(mut self) -> Client {
+        self.config.max_redirects = if self.allow_redirects {
+            self.max_redirects
+        } else {
+            0
+        };
+        self.config.service = self.service.map(Pipeline::new);
         Client(Rc::new(self.config))
     }
 }
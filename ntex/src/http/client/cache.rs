@@ -0,0 +1,593 @@
+//! Client side http cache, rfc9111
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime};
+use std::{cell::RefCell, cmp, fmt, pin::Pin, rc::Rc};
+
+use crate::http::error::PayloadError;
+use crate::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use crate::http::{Method, Payload, ResponseHead, StatusCode, Version};
+use crate::service::{Middleware, Service, ServiceCtx};
+use crate::util::{Bytes, BytesMut, HashMap, Stream};
+
+use super::{error::SendRequestError, ClientConfig, ClientResponse, ClientServiceRequest};
+
+/// Default max size of cached response body
+const MAX_ENTRY_SIZE: usize = 1_048_576;
+
+/// Response cache status
+///
+/// Status is available via [`ClientResponse::cache_status()`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CacheStatus {
+    /// Response is received from the server
+    Miss,
+    /// Fresh response is served from the cache
+    Hit,
+    /// Stale response is served from the cache, server is not reachable
+    Stale,
+    /// Stored response is validated with conditional request
+    Revalidated,
+}
+
+/// Cache storage backend
+pub trait CacheStorage: 'static {
+    /// Get stored response
+    fn get(&self, key: &str) -> Option<CacheEntry>;
+
+    /// Store response
+    fn set(&self, key: &str, entry: CacheEntry);
+
+    /// Remove stored response
+    fn remove(&self, key: &str);
+}
+
+/// Stored response
+#[derive(Clone, Debug)]
+pub struct CacheEntry {
+    status: StatusCode,
+    version: Version,
+    headers: HeaderMap,
+    body: Bytes,
+    vary: Vec<(HeaderName, Option<HeaderValue>)>,
+    request_time: SystemTime,
+    response_time: SystemTime,
+}
+
+impl CacheEntry {
+    /// Response status
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Response headers
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Response body
+    pub fn body(&self) -> &Bytes {
+        &self.body
+    }
+
+    /// Request headers match headers selected by `Vary` response header
+    fn matches(&self, headers: &HeaderMap) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| headers.get(name) == value.as_ref())
+    }
+
+    fn date(&self, name: &HeaderName) -> Option<SystemTime> {
+        self.headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| httpdate::parse_http_date(v).ok())
+    }
+
+    fn freshness_lifetime(&self, cc: &CacheControl) -> Duration {
+        if let Some(max_age) = cc.max_age {
+            return Duration::from_secs(max_age);
+        }
+
+        let date = self.date(&header::DATE).unwrap_or(self.response_time);
+        if self.headers.contains_key(header::EXPIRES) {
+            // invalid expires value means response is already expired
+            return self
+                .date(&header::EXPIRES)
+                .and_then(|expires| expires.duration_since(date).ok())
+                .unwrap_or_default();
+        }
+
+        // heuristic freshness
+        self.date(&header::LAST_MODIFIED)
+            .and_then(|modified| date.duration_since(modified).ok())
+            .map(|d| d / 10)
+            .unwrap_or_default()
+    }
+
+    fn current_age(&self, now: SystemTime) -> Duration {
+        let date = self.date(&header::DATE).unwrap_or(self.response_time);
+        let apparent_age = self.response_time.duration_since(date).unwrap_or_default();
+        let age = self
+            .headers
+            .get(header::AGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or_default();
+        let response_delay = self
+            .response_time
+            .duration_since(self.request_time)
+            .unwrap_or_default();
+
+        cmp::max(apparent_age, age + response_delay)
+            + now.duration_since(self.response_time).unwrap_or_default()
+    }
+
+    fn is_fresh(&self, now: SystemTime, req_cc: &CacheControl) -> bool {
+        let mut lifetime = self.freshness_lifetime(&CacheControl::parse(&self.headers));
+        if let Some(max_age) = req_cc.max_age {
+            lifetime = cmp::min(lifetime, Duration::from_secs(max_age));
+        }
+        let min_fresh = Duration::from_secs(req_cc.min_fresh.unwrap_or(0));
+
+        self.current_age(now) + min_fresh < lifetime
+    }
+
+    /// Update stored headers with headers of `304 Not Modified` response
+    fn update(&mut self, res: &ClientResponse, request_time: SystemTime) {
+        for name in res.headers().keys() {
+            if name != header::CONTENT_LENGTH {
+                self.headers.remove(name);
+                for value in res.headers().get_all(name) {
+                    self.headers.append(name.clone(), value.clone());
+                }
+            }
+        }
+        self.request_time = request_time;
+        self.response_time = SystemTime::now();
+    }
+
+    fn response(&self, config: Rc<ClientConfig>, status: CacheStatus) -> ClientResponse {
+        let mut head = ResponseHead::new(self.status);
+        head.version = self.version;
+        head.headers = self.headers.clone();
+
+        let age = self.current_age(SystemTime::now()).as_secs();
+        head.headers.insert(header::AGE, HeaderValue::from(age));
+
+        let payload = Payload::from_stream(StoredPayload(Some(self.body.clone())));
+        let res = ClientResponse::new(head, payload, config);
+        res.extensions_mut().insert(status);
+        res
+    }
+}
+
+/// In-memory cache storage
+///
+/// Least recently used responses are evicted if storage is full.
+pub struct MemoryStorage {
+    capacity: usize,
+    inner: RefCell<MemoryInner>,
+}
+
+struct MemoryInner {
+    tick: u64,
+    entries: HashMap<String, (u64, CacheEntry)>,
+}
+
+impl MemoryStorage {
+    /// Create storage for `capacity` responses
+    pub fn new(capacity: usize) -> Self {
+        MemoryStorage {
+            capacity: cmp::max(capacity, 1),
+            inner: RefCell::new(MemoryInner {
+                tick: 0,
+                entries: HashMap::default(),
+            }),
+        }
+    }
+
+    /// Number of stored responses
+    pub fn len(&self) -> usize {
+        self.inner.borrow().entries.len()
+    }
+
+    /// Storage is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for MemoryStorage {
+    fn default() -> Self {
+        MemoryStorage::new(1024)
+    }
+}
+
+impl fmt::Debug for MemoryStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryStorage")
+            .field("capacity", &self.capacity)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl CacheStorage for MemoryStorage {
+    fn get(&self, key: &str) -> Option<CacheEntry> {
+        let mut inner = self.inner.borrow_mut();
+        inner.tick += 1;
+        let tick = inner.tick;
+        inner.entries.get_mut(key).map(|(used, entry)| {
+            *used = tick;
+            entry.clone()
+        })
+    }
+
+    fn set(&self, key: &str, entry: CacheEntry) {
+        let mut inner = self.inner.borrow_mut();
+        inner.tick += 1;
+        let tick = inner.tick;
+        inner.entries.insert(key.to_string(), (tick, entry));
+
+        if inner.entries.len() > self.capacity {
+            let lru = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, (used, _))| *used)
+                .map(|(key, _)| key.clone());
+            if let Some(key) = lru {
+                inner.entries.remove(&key);
+            }
+        }
+    }
+
+    fn remove(&self, key: &str) {
+        self.inner.borrow_mut().entries.remove(key);
+    }
+}
+
+/// Client middleware for caching responses
+///
+/// Cache honors `Cache-Control`, `Expires`, `ETag` and `Last-Modified`
+/// headers. Stale responses are revalidated with conditional requests.
+/// Only responses for `GET` requests are stored, successful unsafe requests
+/// invalidate stored response for request's uri.
+///
+/// ```rust
+/// use ntex::http::client::{Cache, Client, MemoryStorage};
+///
+/// #[ntex::main]
+/// async fn main() {
+///     let client = Client::build()
+///         .wrap(Cache::new(MemoryStorage::new(512)))
+///         .finish();
+/// }
+/// ```
+pub struct Cache<S = MemoryStorage> {
+    storage: Rc<S>,
+    max_entry_size: usize,
+}
+
+impl<S: CacheStorage> Cache<S> {
+    /// Create cache middleware with storage backend
+    pub fn new(storage: S) -> Self {
+        Cache {
+            storage: Rc::new(storage),
+            max_entry_size: MAX_ENTRY_SIZE,
+        }
+    }
+
+    /// Max size of response body to store
+    ///
+    /// By default max size is 1Mb.
+    pub fn max_entry_size(mut self, size: usize) -> Self {
+        self.max_entry_size = size;
+        self
+    }
+}
+
+impl Default for Cache<MemoryStorage> {
+    fn default() -> Self {
+        Cache::new(MemoryStorage::default())
+    }
+}
+
+impl<S> fmt::Debug for Cache<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cache")
+            .field("max_entry_size", &self.max_entry_size)
+            .finish()
+    }
+}
+
+impl<Svc, S> Middleware<Svc> for Cache<S> {
+    type Service = CacheService<Svc, S>;
+
+    fn create(&self, service: Svc) -> Self::Service {
+        CacheService {
+            service,
+            storage: self.storage.clone(),
+            max_entry_size: self.max_entry_size,
+        }
+    }
+}
+
+pub struct CacheService<Svc, S> {
+    service: Svc,
+    storage: Rc<S>,
+    max_entry_size: usize,
+}
+
+impl<Svc, S> fmt::Debug for CacheService<Svc, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CacheService")
+            .field("max_entry_size", &self.max_entry_size)
+            .finish()
+    }
+}
+
+impl<Svc, S> Service<ClientServiceRequest> for CacheService<Svc, S>
+where
+    Svc: Service<ClientServiceRequest, Response = ClientResponse, Error = SendRequestError>,
+    S: CacheStorage,
+{
+    type Response = ClientResponse;
+    type Error = SendRequestError;
+
+    crate::forward_poll_ready!(service);
+    crate::forward_poll_shutdown!(service);
+
+    async fn call(
+        &self,
+        mut req: ClientServiceRequest,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<ClientResponse, SendRequestError> {
+        let key = req.uri().to_string();
+
+        // unsafe methods invalidate stored response
+        if req.method() != Method::GET {
+            let unsafe_method =
+                req.method() != Method::HEAD && req.method() != Method::OPTIONS;
+            let res = ctx.call(&self.service, req).await?;
+            if unsafe_method && (res.status().is_success() || res.status().is_redirection())
+            {
+                self.storage.remove(&key);
+            }
+            return Ok(res);
+        }
+
+        // conditional requests are handled by caller
+        let headers = req.all_headers();
+        let req_cc = CacheControl::parse(&headers);
+        if req_cc.no_store
+            || headers.contains_key(header::IF_NONE_MATCH)
+            || headers.contains_key(header::IF_MODIFIED_SINCE)
+        {
+            return ctx.call(&self.service, req).await;
+        }
+
+        let config = req.config().clone();
+        let entry = self.storage.get(&key).filter(|e| e.matches(&headers));
+        let request_time = SystemTime::now();
+
+        if let Some(mut entry) = entry {
+            let cc = CacheControl::parse(&entry.headers);
+            if !req_cc.no_cache && !cc.no_cache && entry.is_fresh(request_time, &req_cc) {
+                return Ok(entry.response(config, CacheStatus::Hit));
+            }
+
+            // revalidate stored response
+            if let Some(etag) = entry.headers.get(header::ETAG) {
+                req.headers_mut()
+                    .insert(header::IF_NONE_MATCH, etag.clone());
+            }
+            if let Some(modified) = entry.headers.get(header::LAST_MODIFIED) {
+                req.headers_mut()
+                    .insert(header::IF_MODIFIED_SINCE, modified.clone());
+            }
+
+            return match ctx.call(&self.service, req).await {
+                Ok(res) if res.status() == StatusCode::NOT_MODIFIED => {
+                    entry.update(&res, request_time);
+                    self.storage.set(&key, entry.clone());
+                    Ok(entry.response(config, CacheStatus::Revalidated))
+                }
+                Ok(res) => Ok(self.store(key, &headers, res, request_time)),
+                // server is not reachable
+                Err(SendRequestError::Connect(_))
+                    if !cc.must_revalidate && !cc.no_cache =>
+                {
+                    Ok(entry.response(config, CacheStatus::Stale))
+                }
+                Err(e) => Err(e),
+            };
+        }
+
+        let res = ctx.call(&self.service, req).await?;
+        Ok(self.store(key, &headers, res, request_time))
+    }
+}
+
+impl<Svc, S: CacheStorage> CacheService<Svc, S> {
+    /// Store response, body is stored when response payload is read
+    fn store(
+        &self,
+        key: String,
+        req_headers: &HeaderMap,
+        mut res: ClientResponse,
+        request_time: SystemTime,
+    ) -> ClientResponse {
+        res.extensions_mut().insert(CacheStatus::Miss);
+
+        if !is_cacheable(&res) {
+            return res;
+        }
+        let length = res
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<usize>().ok());
+        if length.map(|len| len > self.max_entry_size).unwrap_or(false) {
+            return res;
+        }
+
+        // headers selected by vary header
+        let mut vary = Vec::new();
+        for value in res.headers().get_all(header::VARY) {
+            for name in value.to_str().unwrap_or("*").split(',') {
+                match HeaderName::try_from(name.trim()) {
+                    Ok(name) => {
+                        let value = req_headers.get(&name).cloned();
+                        vary.push((name, value));
+                    }
+                    // `Vary: *` never matches
+                    Err(_) => return res,
+                }
+            }
+        }
+
+        let entry = CacheEntry {
+            vary,
+            request_time,
+            status: res.status(),
+            version: res.version(),
+            headers: res.headers().clone(),
+            body: Bytes::new(),
+            response_time: SystemTime::now(),
+        };
+        let payload = res.take_payload();
+        res.set_payload(Payload::from_stream(CachingPayload {
+            payload,
+            key,
+            buf: BytesMut::new(),
+            entry: Some(entry),
+            storage: self.storage.clone(),
+            max_size: self.max_entry_size,
+        }));
+        res
+    }
+}
+
+fn is_cacheable(res: &ClientResponse) -> bool {
+    let cc = CacheControl::parse(res.headers());
+    if cc.no_store {
+        return false;
+    }
+
+    // explicit freshness or validators are required
+    let headers = res.headers();
+    let stored = cc.max_age.is_some()
+        || headers.contains_key(header::EXPIRES)
+        || headers.contains_key(header::ETAG)
+        || headers.contains_key(header::LAST_MODIFIED);
+
+    stored
+        && matches!(
+            res.status().as_u16(),
+            200 | 203 | 204 | 300 | 301 | 308 | 404 | 405 | 410 | 414 | 501
+        )
+}
+
+/// Parsed `Cache-Control` directives
+#[derive(Default, Debug)]
+struct CacheControl {
+    no_cache: bool,
+    no_store: bool,
+    must_revalidate: bool,
+    max_age: Option<u64>,
+    min_fresh: Option<u64>,
+}
+
+impl CacheControl {
+    fn parse(headers: &HeaderMap) -> Self {
+        let mut cc = CacheControl::default();
+
+        for value in headers.get_all(header::CACHE_CONTROL) {
+            for directive in value.to_str().unwrap_or_default().split(',') {
+                let (name, value) = match directive.split_once('=') {
+                    Some((name, value)) => {
+                        (name.trim(), Some(value.trim().trim_matches('"')))
+                    }
+                    None => (directive.trim(), None),
+                };
+                let value = value.and_then(|v| v.parse::<u64>().ok());
+
+                if name.eq_ignore_ascii_case("no-cache") {
+                    cc.no_cache = true;
+                } else if name.eq_ignore_ascii_case("no-store") {
+                    cc.no_store = true;
+                } else if name.eq_ignore_ascii_case("must-revalidate") {
+                    cc.must_revalidate = true;
+                } else if name.eq_ignore_ascii_case("max-age") {
+                    // invalid max-age means response is stale
+                    cc.max_age = Some(value.unwrap_or(0));
+                } else if name.eq_ignore_ascii_case("min-fresh") {
+                    cc.min_fresh = value;
+                }
+            }
+        }
+        cc
+    }
+}
+
+/// Payload of stored response
+struct StoredPayload(Option<Bytes>);
+
+impl Stream for StoredPayload {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        Poll::Ready(self.0.take().filter(|b| !b.is_empty()).map(Ok))
+    }
+}
+
+/// Response payload, stores response when payload is read
+struct CachingPayload<S> {
+    payload: Payload,
+    key: String,
+    buf: BytesMut,
+    entry: Option<CacheEntry>,
+    storage: Rc<S>,
+    max_size: usize,
+}
+
+impl<S: CacheStorage> Stream for CachingPayload<S> {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.as_mut().get_mut();
+
+        match Pin::new(&mut this.payload).poll_next(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                if this.entry.is_some() {
+                    if this.buf.len() + chunk.len() > this.max_size {
+                        this.entry = None;
+                        this.buf = BytesMut::new();
+                    } else {
+                        this.buf.extend_from_slice(&chunk);
+                    }
+                }
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            Poll::Ready(None) => {
+                if let Some(mut entry) = this.entry.take() {
+                    entry.body = this.buf.split().freeze();
+                    this.storage.set(&this.key, entry);
+                }
+                Poll::Ready(None)
+            }
+            Poll::Ready(Some(Err(e))) => {
+                this.entry = None;
+                Poll::Ready(Some(Err(e)))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
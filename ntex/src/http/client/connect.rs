@@ -1,13 +1,41 @@
-use std::{fmt, net, rc::Rc};
+use std::{fmt, rc::Rc};
 
-use crate::http::{body::Body, RequestHeadType};
-use crate::{service::Pipeline, service::Service, time::Millis, util::BoxFuture};
+use crate::http::{body::Body, RequestHeadType, Uri};
+use crate::time::{timeout_checked, Millis};
+use crate::{service::Pipeline, service::Service, util::BoxFuture};
 
+use super::connector::CertVerify;
 use super::error::{ConnectError, SendRequestError};
-use super::response::ClientResponse;
+use super::pool::{PoolStatus, PoolTracker};
+use super::{response::ClientResponse, sender::SendOptions};
 use super::{ClientConfig, Connect as ClientConnect, Connection};
 
-pub(super) struct ConnectorWrapper<T>(pub(crate) Pipeline<T>);
+/// Client connector
+///
+/// Provides access to pooled connections of the client, it is useful for
+/// custom protocols over http connections.
+#[derive(Debug, Clone)]
+pub struct ClientConnector(pub(super) Rc<ClientConfig>);
+
+impl ClientConnector {
+    /// Acquire connection for the uri.
+    ///
+    /// Connection is taken from the pool or new connection is established,
+    /// including tls handshake for secure uris. Client's connect timeout
+    /// applies. Use [`Connection::release()`] to return connection back
+    /// to the pool, dropped connections get closed.
+    pub async fn acquire(&self, uri: Uri) -> Result<Connection, ConnectError> {
+        self.0.connector.acquire(uri, self.0.connect_timeout).await
+    }
+}
+
+pub(super) struct ConnectorWrapper<T>(Pipeline<T>, PoolTracker);
+
+impl<T> ConnectorWrapper<T> {
+    pub(super) fn new(connector: T) -> Self {
+        ConnectorWrapper(Pipeline::new(connector), PoolTracker::default())
+    }
+}
 
 impl<T> fmt::Debug for ConnectorWrapper<T>
 where
@@ -25,38 +53,87 @@ pub(super) trait Connect: fmt::Debug {
         &self,
         head: RequestHeadType,
         body: Body,
-        addr: Option<net::SocketAddr>,
-        timeout: Millis,
+        opts: SendOptions,
         cfg: Rc<ClientConfig>,
     ) -> BoxFuture<'_, Result<ClientResponse, SendRequestError>>;
+
+    fn acquire(
+        &self,
+        uri: Uri,
+        timeout: Millis,
+    ) -> BoxFuture<'_, Result<Connection, ConnectError>>;
+
+    fn pool_status(&self) -> PoolStatus;
 }
 
 impl<T> Connect for ConnectorWrapper<T>
 where
-    T: Service<ClientConnect, Response = Connection, Error = ConnectError> + fmt::Debug,
+    T: Service<ClientConnect, Response = Connection, Error = ConnectError>
+        + fmt::Debug
+        + 'static,
 {
     fn send_request(
         &self,
         head: RequestHeadType,
         body: Body,
-        addr: Option<net::SocketAddr>,
-        timeout: Millis,
+        opts: SendOptions,
         cfg: Rc<ClientConfig>,
     ) -> BoxFuture<'_, Result<ClientResponse, SendRequestError>> {
         Box::pin(async move {
             // connect to the host
             let fut = self.0.call(ClientConnect {
                 uri: head.as_ref().uri.clone(),
-                addr,
+                addr: opts.addr,
+                verify: opts.verify,
+                local_addr: None,
             });
 
-            let connection = fut.await?;
+            let connection = timeout_checked(opts.connect_timeout, fut)
+                .await
+                .map_err(|_| ConnectError::Timeout)??;
+            self.1.track(&connection);
 
             // send request
-            connection
-                .send_request(head, body, timeout)
+            let (head, payload) = connection
+                .send_request(
+                    head,
+                    body,
+                    opts.timeout,
+                    opts.expect_continue,
+                    opts.informational,
+                )
+                .await?;
+
+            let mut res = ClientResponse::new(head, payload, cfg);
+            if let Some(timeout) = opts.response_pl_timeout {
+                res.pl_timeout = timeout;
+            }
+            Ok(res)
+        })
+    }
+
+    fn acquire(
+        &self,
+        uri: Uri,
+        timeout: Millis,
+    ) -> BoxFuture<'_, Result<Connection, ConnectError>> {
+        Box::pin(async move {
+            let fut = self.0.call(ClientConnect {
+                uri,
+                addr: None,
+                verify: CertVerify::Default,
+                local_addr: None,
+            });
+
+            let connection = timeout_checked(timeout, fut)
                 .await
-                .map(|(head, payload)| ClientResponse::new(head, payload, cfg))
+                .map_err(|_| ConnectError::Timeout)??;
+            self.1.track(&connection);
+            Ok(connection)
         })
     }
+
+    fn pool_status(&self) -> PoolStatus {
+        self.1.status()
+    }
 }
@@ -4,8 +4,9 @@ use crate::http::body::MessageBody;
 use crate::http::message::{RequestHeadType, ResponseHead};
 use crate::http::payload::Payload;
 use crate::io::{types::HttpProtocol, IoBoxed};
-use crate::time::Millis;
+use crate::{time::Millis, util::Bytes};
 
+use super::sender::Informational;
 use super::{error::SendRequestError, h1proto, h2proto, pool::Acquired};
 
 pub(super) enum ConnectionType {
@@ -22,8 +23,9 @@ impl fmt::Debug for ConnectionType {
     }
 }
 
-#[doc(hidden)]
 /// HTTP client connection
+///
+/// Connection could be acquired with [`ClientConnector`](super::ClientConnector).
 pub struct Connection {
     io: Option<ConnectionType>,
     created: time::Instant,
@@ -53,8 +55,15 @@ impl Connection {
         }
     }
 
-    pub(super) fn release(self, close: bool) {
+    /// Release connection back to the pool
+    ///
+    /// If `close` is true, connection is closed instead. Http/2 connections
+    /// are shared and stay in the pool until closed.
+    pub fn release(self, close: bool) {
         if let Some(mut pool) = self.pool {
+            if !close && matches!(self.io, Some(ConnectionType::H2(_))) {
+                return;
+            }
             pool.release(
                 Self {
                     io: self.io,
@@ -66,10 +75,35 @@ impl Connection {
         }
     }
 
+    pub(super) fn pool(&self) -> Option<&Acquired> {
+        self.pool.as_ref()
+    }
+
     pub(super) fn into_inner(self) -> (ConnectionType, time::Instant, Option<Acquired>) {
         (self.io.unwrap(), self.created, self.pool)
     }
 
+    /// Peer certificate in DER format
+    pub(super) fn peer_cert(&self) -> Option<Bytes> {
+        match self.io {
+            Some(ConnectionType::H1(ref io)) => peer_cert(io),
+            Some(ConnectionType::H2(ref client)) => client.peer_cert(),
+            None => None,
+        }
+    }
+
+    /// Connection io
+    ///
+    /// Io is available for http/1 connections only, http/2 connections
+    /// are multiplexed and could not be used for custom protocols.
+    pub fn io(&self) -> Option<&IoBoxed> {
+        match self.io {
+            Some(ConnectionType::H1(ref io)) => Some(io),
+            _ => None,
+        }
+    }
+
+    /// Negotiated http protocol
     pub fn protocol(&self) -> HttpProtocol {
         match self.io {
             Some(ConnectionType::H1(_)) => HttpProtocol::Http1,
@@ -83,6 +117,8 @@ impl Connection {
         head: H,
         body: B,
         timeout: Millis,
+        expect_continue: Option<Millis>,
+        informational: Option<Informational>,
     ) -> Result<(ResponseHead, Payload), SendRequestError> {
         match self.io.take().unwrap() {
             ConnectionType::H1(io) => {
@@ -92,6 +128,8 @@ impl Connection {
                     body,
                     self.created,
                     timeout,
+                    expect_continue,
+                    informational,
                     self.pool,
                 )
                 .await
@@ -102,3 +140,17 @@ impl Connection {
         }
     }
 }
+
+/// Get peer certificate in DER format
+#[allow(unused_variables)]
+pub(super) fn peer_cert(io: &IoBoxed) -> Option<Bytes> {
+    #[cfg(feature = "openssl")]
+    if let Some(cert) = io.query::<ntex_tls::openssl::PeerCert>().as_ref() {
+        return cert.0.to_der().ok().map(Bytes::from);
+    }
+    #[cfg(feature = "rustls")]
+    if let Some(cert) = io.query::<ntex_tls::rustls::PeerCert<'_>>().as_ref() {
+        return Some(Bytes::copy_from_slice(cert.0.as_ref()));
+    }
+    None
+}
@@ -1,14 +1,16 @@
-use std::{fmt, task::Context, task::Poll, time::Duration};
+use std::{fmt, io, rc::Rc, task::Context, task::Poll, time::Duration};
 
 use ntex_h2::{self as h2};
 
-use crate::connect::{Connect as TcpConnect, Connector as TcpConnector};
+use crate::connect::{AddressPolicy, Connect as TcpConnect, Connector as TcpConnector};
 use crate::service::{apply_fn, boxed, Service, ServiceCtx};
 use crate::time::{Millis, Seconds};
-use crate::util::{timeout::TimeoutError, timeout::TimeoutService};
+use crate::util::{timeout::TimeoutError, timeout::TimeoutService, ByteString, HashMap};
 use crate::{http::Uri, io::IoBoxed};
 
-use super::{connection::Connection, error::ConnectError, pool::ConnectionPool, Connect};
+use super::pool::{ConnectionPool, PoolConfig, PoolEvent, PoolEventHandler};
+use super::router::{ConnectorRouter, Route, RouterHandler};
+use super::{connection::Connection, error::ConnectError, Connect};
 
 #[cfg(feature = "openssl")]
 use tls_openssl::ssl::SslConnector as OpensslConnector;
@@ -16,7 +18,40 @@ use tls_openssl::ssl::SslConnector as OpensslConnector;
 #[cfg(feature = "rustls")]
 use tls_rustls::ClientConfig;
 
+#[cfg(feature = "native-tls")]
+use tls_native::TlsConnector as NativeTlsConnector;
+
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+use crate::connect::ClientCert;
+
 type BoxedConnector = boxed::BoxService<TcpConnect<Uri>, IoBoxed, ConnectError>;
+type PoolConnector = boxed::BoxService<Connect, IoBoxed, ConnectError>;
+
+/// Server certificate verification mode
+#[derive(Clone, Default)]
+#[cfg_attr(
+    not(all(feature = "dangerous", any(feature = "openssl", feature = "rustls"))),
+    allow(dead_code)
+)]
+pub(crate) enum CertVerify {
+    /// Use connector's verification
+    #[default]
+    Default,
+    /// Accept any certificate
+    AcceptInvalid,
+    /// Verify peer certificate with custom function
+    Custom(Rc<dyn Fn(&[u8]) -> bool>),
+}
+
+impl fmt::Debug for CertVerify {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CertVerify::Default => write!(f, "Default"),
+            CertVerify::AcceptInvalid => write!(f, "AcceptInvalid"),
+            CertVerify::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
 
 #[derive(Debug)]
 /// Manages http client network connectivity.
@@ -34,13 +69,32 @@ type BoxedConnector = boxed::BoxService<TcpConnect<Uri>, IoBoxed, ConnectError>;
 /// ```
 pub struct Connector {
     timeout: Millis,
+    handshake_timeout: Millis,
     conn_lifetime: Duration,
     conn_keep_alive: Duration,
     disconnect_timeout: Seconds,
     limit: usize,
+    limit_per_host: usize,
+    max_idle: usize,
+    on_pool_event: Option<PoolEventHandler>,
     h2config: h2::Config,
-    connector: BoxedConnector,
-    ssl_connector: Option<BoxedConnector>,
+    h2_prior_knowledge: bool,
+    address_policy: AddressPolicy,
+    connector: Option<BoxedConnector>,
+    ssl_connector: Option<SslConnector>,
+    router: Option<RouterHandler>,
+    routes: Vec<(ByteString, BoxedConnector)>,
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    client_certs: Vec<(Option<String>, ClientCert)>,
+}
+
+#[derive(Debug)]
+enum SslConnector {
+    #[cfg(feature = "openssl")]
+    Openssl(OpensslConnector),
+    #[cfg(feature = "rustls")]
+    Rustls(Box<ClientConfig>),
+    Custom(BoxedConnector),
 }
 
 impl Default for Connector {
@@ -52,18 +106,24 @@ impl Default for Connector {
 impl Connector {
     pub fn new() -> Connector {
         let conn = Connector {
-            connector: boxed::service(
-                TcpConnector::new()
-                    .map(IoBoxed::from)
-                    .map_err(ConnectError::from),
-            ),
+            address_policy: AddressPolicy::First,
+            connector: None,
             ssl_connector: None,
+            router: None,
+            routes: Vec::new(),
+            #[cfg(any(feature = "openssl", feature = "rustls"))]
+            client_certs: Vec::new(),
             timeout: Millis(1_000),
+            handshake_timeout: Millis::ZERO,
             conn_lifetime: Duration::from_secs(75),
             conn_keep_alive: Duration::from_secs(15),
             disconnect_timeout: Seconds(3),
             limit: 100,
+            limit_per_host: 0,
+            max_idle: 0,
+            on_pool_event: None,
             h2config: h2::Config::client(),
+            h2_prior_knowledge: false,
         };
 
         #[cfg(feature = "openssl")]
@@ -92,7 +152,24 @@ impl Connector {
             config.alpn_protocols = protos;
             conn.rustls(config)
         }
-        #[cfg(not(any(feature = "openssl", feature = "rustls")))]
+        #[cfg(all(
+            not(feature = "openssl"),
+            not(feature = "rustls"),
+            feature = "native-tls"
+        ))]
+        {
+            match NativeTlsConnector::builder()
+                .request_alpns(&["h2", "http/1.1"])
+                .build()
+            {
+                Ok(connector) => conn.native_tls(connector),
+                Err(e) => {
+                    log::error!("Cannot create native-tls connector: {:?}", e);
+                    conn
+                }
+            }
+        }
+        #[cfg(not(any(feature = "openssl", feature = "rustls", feature = "native-tls")))]
         {
             conn
         }
@@ -109,18 +186,71 @@ impl Connector {
         self
     }
 
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    /// Tls handshake timeout.
+    ///
+    /// Connect fails with `ConnectError::HandshakeTimeout` error if tls
+    /// handshake does not complete in time, connection timeout is extended
+    /// by handshake timeout for secure connections. Applies to openssl and
+    /// rustls connectors, native-tls handshake is always covered by connection
+    /// timeout. By default handshake is covered by connection timeout.
+    pub fn handshake_timeout<T: Into<Millis>>(mut self, timeout: T) -> Self {
+        self.handshake_timeout = timeout.into();
+        self
+    }
+
     #[cfg(feature = "openssl")]
     /// Use openssl connector for secured connections.
-    pub fn openssl(self, connector: OpensslConnector) -> Self {
-        use crate::connect::openssl::SslConnector;
-
-        self.secure_connector(SslConnector::new(connector))
+    pub fn openssl(mut self, connector: OpensslConnector) -> Self {
+        self.ssl_connector = Some(SslConnector::Openssl(connector));
+        self
     }
 
     #[cfg(feature = "rustls")]
     /// Use rustls connector for secured connections.
-    pub fn rustls(self, connector: ClientConfig) -> Self {
-        use crate::connect::rustls::TlsConnector;
+    pub fn rustls(mut self, connector: ClientConfig) -> Self {
+        self.ssl_connector = Some(SslConnector::Rustls(Box::new(connector)));
+        self
+    }
+
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    /// Use client certificate for mutual tls authentication.
+    ///
+    /// Certificate is used for all hosts without host specific certificate.
+    /// Client certificates are supported by openssl and rustls connectors,
+    /// `finish()` panics if certificate cannot be loaded. Connector set with
+    /// `native_tls()` uses client identity from its own configuration.
+    ///
+    /// ```rust,no_run
+    /// use ntex::{connect::ClientCert, http::client::Connector};
+    ///
+    /// let cert = ClientCert::from_pem(
+    ///     std::fs::read("client.pem").unwrap(),
+    ///     std::fs::read("client.key").unwrap(),
+    /// );
+    /// let connector = Connector::default().client_cert(cert).finish();
+    /// ```
+    pub fn client_cert(mut self, cert: ClientCert) -> Self {
+        self.client_certs.push((None, cert));
+        self
+    }
+
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    /// Use client certificate for mutual tls authentication with specific host.
+    pub fn client_cert_for<H: Into<String>>(mut self, host: H, cert: ClientCert) -> Self {
+        self.client_certs.push((Some(host.into()), cert));
+        self
+    }
+
+    #[cfg(feature = "native-tls")]
+    /// Use native-tls connector for secured connections.
+    ///
+    /// Certificates are verified against platform trust store. Client identity
+    /// is configured with `native_tls::TlsConnectorBuilder::identity()`,
+    /// `handshake_timeout()`, `client_cert()` and `address_policy()` settings
+    /// do not apply to native-tls connector.
+    pub fn native_tls(self, connector: NativeTlsConnector) -> Self {
+        use crate::connect::native_tls::TlsConnector;
 
         self.secure_connector(TlsConnector::new(connector))
     }
@@ -134,6 +264,55 @@ impl Connector {
         self
     }
 
+    /// Set number of simultaneous connections per host.
+    ///
+    /// Limit applies to each type of scheme separately.
+    /// By default number of connections per host is not limited.
+    pub fn limit_per_host(mut self, limit: usize) -> Self {
+        self.limit_per_host = limit;
+        self
+    }
+
+    /// Set max number of idle connections per host.
+    ///
+    /// Least recently used connection is closed if number of idle
+    /// connections exceeds limit. By default limit is not set.
+    pub fn max_idle(mut self, max: usize) -> Self {
+        self.max_idle = max;
+        self
+    }
+
+    /// Register connection pool events handler.
+    ///
+    /// Handler is called when pool establishes new connection and
+    /// when pool connection get closed.
+    ///
+    /// ```rust
+    /// use ntex::http::client::{Connector, PoolEvent};
+    ///
+    /// #[ntex::main]
+    /// async fn main() {
+    ///     let connector = Connector::default()
+    ///         .on_pool_event(|ev| match ev {
+    ///             PoolEvent::Connected { authority, .. } => {
+    ///                 println!("Connected to {}", authority)
+    ///             }
+    ///             PoolEvent::Disconnected { authority, reason, .. } => {
+    ///                 println!("Disconnected from {}: {:?}", authority, reason)
+    ///             }
+    ///             _ => (),
+    ///         })
+    ///         .finish();
+    /// }
+    /// ```
+    pub fn on_pool_event<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&PoolEvent) + 'static,
+    {
+        self.on_pool_event = Some(PoolEventHandler(Rc::new(f)));
+        self
+    }
+
     /// Set keep-alive period for opened connection.
     ///
     /// Keep-alive period is the period between connection usage. If
@@ -168,8 +347,23 @@ impl Connector {
         self
     }
 
-    #[doc(hidden)]
-    /// Configure http2 connection settings
+    /// Configure http2 connection settings.
+    ///
+    /// Http2 is used if server selects `h2` protocol during tls handshake or
+    /// if http2 prior knowledge is enabled. Requests to the same host share
+    /// one http2 connection.
+    ///
+    /// ```rust
+    /// use ntex::http::client::Connector;
+    ///
+    /// let connector = Connector::default()
+    ///     .configure_http2(|cfg| {
+    ///         // stream and connection level flow control windows
+    ///         cfg.initial_window_size(1024 * 1024)
+    ///             .initial_connection_window_size(4 * 1024 * 1024)
+    ///             .max_concurrent_streams(256);
+    ///     });
+    /// ```
     pub fn configure_http2<O, R>(self, f: O) -> Self
     where
         O: FnOnce(&h2::Config) -> R,
@@ -178,14 +372,48 @@ impl Connector {
         self
     }
 
+    /// Use http2 for un-secured connections without protocol negotiation.
+    ///
+    /// Server must support http2 prior knowledge, secure connections
+    /// negotiate protocol with ALPN. By default is disabled.
+    pub fn http2_prior_knowledge(mut self) -> Self {
+        self.h2_prior_knowledge = true;
+        self
+    }
+
+    /// Set address selection policy.
+    ///
+    /// Policy is used if host name resolves to multiple addresses, i.e.
+    /// connections could be distributed across all resolved addresses
+    /// with `AddressPolicy::RoundRobin`. Applies to default tcp connector
+    /// and to openssl and rustls connectors, custom and native-tls connectors
+    /// must be configured separately. By default addresses are used in
+    /// resolution order.
+    ///
+    /// ```rust
+    /// use ntex::{connect::AddressPolicy, http::client::Connector};
+    ///
+    /// #[ntex::main]
+    /// async fn main() {
+    ///     let connector = Connector::default()
+    ///         .address_policy(AddressPolicy::RoundRobin)
+    ///         .finish();
+    /// }
+    /// ```
+    pub fn address_policy(mut self, policy: AddressPolicy) -> Self {
+        self.address_policy = policy;
+        self
+    }
+
     /// Use custom connector to open un-secured connections.
     pub fn connector<T>(mut self, connector: T) -> Self
     where
         T: Service<TcpConnect<Uri>, Error = crate::connect::ConnectError> + 'static,
         IoBoxed: From<T::Response>,
     {
-        self.connector =
-            boxed::service(connector.map(IoBoxed::from).map_err(ConnectError::from));
+        self.connector = Some(boxed::service(
+            connector.map(IoBoxed::from).map_err(ConnectError::from),
+        ));
         self
     }
 
@@ -195,46 +423,267 @@ impl Connector {
         T: Service<TcpConnect<Uri>, Error = crate::connect::ConnectError> + 'static,
         IoBoxed: From<T::Response>,
     {
-        self.ssl_connector = Some(boxed::service(
+        self.ssl_connector = Some(SslConnector::Custom(boxed::service(
             connector.map(IoBoxed::from).map_err(ConnectError::from),
-        ));
+        )));
+        self
+    }
+
+    /// Set connections router.
+    ///
+    /// Router selects transport for each new connection, connection could
+    /// be opened directly, to specific address, from specific local address
+    /// or with named connector. Connections of different routes are pooled
+    /// separately.
+    ///
+    /// ```rust
+    /// use ntex::http::client::{Connector, Route};
+    ///
+    /// #[ntex::main]
+    /// async fn main() {
+    ///     let connector = Connector::default()
+    ///         .router(|uri: &ntex::http::Uri| match uri.host() {
+    ///             Some("internal.example.com") => {
+    ///                 Route::Address("10.0.0.1:80".parse().unwrap())
+    ///             }
+    ///             Some("tenant.example.com") => Route::Bind("10.0.1.1".parse().unwrap()),
+    ///             _ => Route::Direct,
+    ///         })
+    ///         .finish();
+    /// }
+    /// ```
+    pub fn router<R>(mut self, router: R) -> Self
+    where
+        R: ConnectorRouter,
+    {
+        self.router = Some(RouterHandler(Rc::new(router)));
+        self
+    }
+
+    /// Register named connector for routed connections.
+    ///
+    /// Connector is used for connections with `Route::Connector(name)` route
+    /// for both secure and un-secured uris, connector is responsible for
+    /// establishing tunnel through proxy, binding local address and tls.
+    pub fn route_connector<T>(mut self, name: &str, connector: T) -> Self
+    where
+        T: Service<TcpConnect<Uri>, Error = crate::connect::ConnectError> + 'static,
+        IoBoxed: From<T::Response>,
+    {
+        let connector =
+            boxed::service(connector.map(IoBoxed::from).map_err(ConnectError::from));
+        self.routes.push((ByteString::from(name), connector));
         self
     }
 
     /// Finish configuration process and create connector service.
     /// The Connector builder always concludes by calling `finish()` last in
     /// its combinator chain.
+    ///
+    /// Panics if client certificate cannot be loaded, use `try_finish()`
+    /// to handle certificate errors.
     pub fn finish(
         self,
     ) -> impl Service<Connect, Response = Connection, Error = ConnectError> + fmt::Debug
     {
-        let tcp_service = connector(self.connector, self.timeout, self.disconnect_timeout);
-
-        let ssl_pool = if let Some(ssl_connector) = self.ssl_connector {
-            let srv = connector(ssl_connector, self.timeout, self.disconnect_timeout);
-            Some(ConnectionPool::new(
-                srv,
-                self.conn_lifetime,
-                self.conn_keep_alive,
-                self.disconnect_timeout,
-                self.limit,
-                self.h2config.clone(),
-            ))
+        match self.try_finish() {
+            Ok(srv) => srv,
+            Err(err) => panic!("Cannot load client certificate: {}", err),
+        }
+    }
+
+    /// Finish configuration process and create connector service.
+    ///
+    /// Returns error if client certificate cannot be loaded.
+    pub fn try_finish(
+        self,
+    ) -> io::Result<
+        impl Service<Connect, Response = Connection, Error = ConnectError> + fmt::Debug,
+    > {
+        let policy = self.address_policy;
+        let tcp_connector = self.connector.unwrap_or_else(|| {
+            boxed::service(
+                TcpConnector::new()
+                    .address_policy(policy)
+                    .map(IoBoxed::from)
+                    .map_err(ConnectError::from),
+            )
+        });
+        let tcp_service = connector(tcp_connector, self.timeout, self.disconnect_timeout);
+        let ssl_timeout = if self.timeout.is_zero() {
+            self.timeout
         } else {
-            None
+            self.timeout + self.handshake_timeout
         };
 
-        InnerConnector {
+        // connections with disabled certificate verification
+        // are kept in separate pool
+        #[cfg(all(feature = "dangerous", any(feature = "openssl", feature = "rustls")))]
+        let insecure_connector = self
+            .ssl_connector
+            .as_ref()
+            .and_then(|conn| conn.try_clone())
+            .map(|conn| {
+                conn.into_service(&self.client_certs, self.handshake_timeout, policy, true)
+            })
+            .transpose()?;
+        #[cfg(not(all(
+            feature = "dangerous",
+            any(feature = "openssl", feature = "rustls")
+        )))]
+        let insecure_connector: Option<BoxedConnector> = None;
+
+        #[cfg(any(feature = "openssl", feature = "rustls"))]
+        let ssl_connector = self
+            .ssl_connector
+            .map(|conn| {
+                conn.into_service(&self.client_certs, self.handshake_timeout, policy, false)
+            })
+            .transpose()?;
+        #[cfg(not(any(feature = "openssl", feature = "rustls")))]
+        let ssl_connector = self.ssl_connector.map(|conn| conn.into_service());
+
+        let config = PoolConfig {
+            conn_lifetime: self.conn_lifetime,
+            conn_keep_alive: self.conn_keep_alive,
+            disconnect_timeout: self.disconnect_timeout,
+            limit: self.limit,
+            limit_per_host: self.limit_per_host,
+            max_idle: self.max_idle,
+            h2config: self.h2config,
+            h2_prior_knowledge: false,
+            on_event: self.on_pool_event,
+        };
+
+        let insecure_pool = insecure_connector.map(|conn| {
+            ConnectionPool::new(
+                connector(conn, ssl_timeout, self.disconnect_timeout),
+                config.clone(),
+            )
+        });
+
+        let ssl_pool = ssl_connector.map(|conn| {
+            ConnectionPool::new(
+                connector(conn, ssl_timeout, self.disconnect_timeout),
+                config.clone(),
+            )
+        });
+
+        let routes = self
+            .routes
+            .into_iter()
+            .map(|(name, conn)| {
+                let pool = ConnectionPool::new(
+                    connector(conn, ssl_timeout, self.disconnect_timeout),
+                    config.clone(),
+                );
+                (name, pool)
+            })
+            .collect();
+
+        Ok(InnerConnector {
             tcp_pool: ConnectionPool::new(
                 tcp_service,
-                self.conn_lifetime,
-                self.conn_keep_alive,
-                self.disconnect_timeout,
-                self.limit,
-                self.h2config.clone(),
+                PoolConfig {
+                    h2_prior_knowledge: self.h2_prior_knowledge,
+                    ..config
+                },
             ),
             ssl_pool,
+            insecure_pool,
+            router: self.router,
+            routes,
+        })
+    }
+}
+
+impl SslConnector {
+    #[cfg(not(any(feature = "openssl", feature = "rustls")))]
+    fn into_service(self) -> BoxedConnector {
+        match self {
+            SslConnector::Custom(srv) => srv,
+        }
+    }
+
+    #[cfg(all(feature = "dangerous", any(feature = "openssl", feature = "rustls")))]
+    fn try_clone(&self) -> Option<Self> {
+        match self {
+            #[cfg(feature = "openssl")]
+            SslConnector::Openssl(conn) => Some(SslConnector::Openssl(conn.clone())),
+            #[cfg(feature = "rustls")]
+            SslConnector::Rustls(config) => Some(SslConnector::Rustls(config.clone())),
+            SslConnector::Custom(_) => None,
+        }
+    }
+
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    #[cfg_attr(not(feature = "dangerous"), allow(unused_variables))]
+    fn into_service(
+        self,
+        certs: &[(Option<String>, ClientCert)],
+        handshake_timeout: Millis,
+        policy: AddressPolicy,
+        accept_invalid_certs: bool,
+    ) -> io::Result<BoxedConnector> {
+        match self {
+            #[cfg(feature = "openssl")]
+            SslConnector::Openssl(conn) => {
+                use crate::connect::openssl::SslConnector;
+
+                let mut conn = SslConnector::new(conn)
+                    .address_policy(policy)
+                    .handshake_timeout(handshake_timeout);
+                #[cfg(feature = "dangerous")]
+                {
+                    conn = conn.dangerous_accept_invalid_certs(accept_invalid_certs);
+                }
+                for (host, cert) in certs {
+                    conn = if let Some(host) = host {
+                        conn.client_cert_for(host.clone(), cert)?
+                    } else {
+                        conn.client_cert(cert)?
+                    };
+                }
+                Ok(boxed::service(conn.map(IoBoxed::from).map_err(tls_error)))
+            }
+            #[cfg(feature = "rustls")]
+            SslConnector::Rustls(config) => {
+                use crate::connect::rustls::TlsConnector;
+
+                let mut conn = TlsConnector::new(*config)
+                    .address_policy(policy)
+                    .handshake_timeout(handshake_timeout);
+                #[cfg(feature = "dangerous")]
+                {
+                    conn = conn.dangerous_accept_invalid_certs(accept_invalid_certs);
+                }
+                for (host, cert) in certs {
+                    conn = if let Some(host) = host {
+                        conn.client_cert_for(host.clone(), cert)?
+                    } else {
+                        conn.client_cert(cert)?
+                    };
+                }
+                Ok(boxed::service(conn.map(IoBoxed::from).map_err(tls_error)))
+            }
+            SslConnector::Custom(srv) => {
+                if !certs.is_empty() {
+                    log::warn!("Client certificates are not supported by custom connector");
+                }
+                Ok(srv)
+            }
+        }
+    }
+}
+
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+/// Tls connectors report handshake timeout as `TimedOut` io error
+fn tls_error(err: crate::connect::ConnectError) -> ConnectError {
+    match err {
+        crate::connect::ConnectError::Io(e) if ntex_tls::HandshakeTimeout::is(&e) => {
+            ConnectError::HandshakeTimeout
         }
+        err => err.into(),
     }
 }
 
@@ -242,28 +691,38 @@ fn connector(
     connector: BoxedConnector,
     timeout: Millis,
     disconnect_timeout: Seconds,
-) -> impl Service<Connect, Response = IoBoxed, Error = ConnectError> + fmt::Debug {
-    TimeoutService::new(
-        timeout,
-        apply_fn(connector, |msg: Connect, svc| async move {
-            svc.call(TcpConnect::new(msg.uri).set_addr(msg.addr)).await
-        })
-        .map(move |io: IoBoxed| {
-            io.set_disconnect_timeout(disconnect_timeout);
-            io
-        })
-        .map_err(ConnectError::from),
+) -> PoolConnector {
+    boxed::service(
+        TimeoutService::new(
+            timeout,
+            apply_fn(connector, |msg: Connect, svc| async move {
+                svc.call(
+                    TcpConnect::new(msg.uri)
+                        .set_addr(msg.addr)
+                        .set_local_addr(msg.local_addr),
+                )
+                .await
+            })
+            .map(move |io: IoBoxed| {
+                io.set_disconnect_timeout(disconnect_timeout);
+                io
+            })
+            .map_err(ConnectError::from),
+        )
+        .map_err(|e| match e {
+            TimeoutError::Service(e) => e,
+            TimeoutError::Timeout => ConnectError::Timeout,
+        }),
     )
-    .map_err(|e| match e {
-        TimeoutError::Service(e) => e,
-        TimeoutError::Timeout => ConnectError::Timeout,
-    })
 }
 
 #[derive(Debug)]
-struct InnerConnector<T> {
+pub(super) struct InnerConnector<T = PoolConnector> {
     tcp_pool: ConnectionPool<T>,
     ssl_pool: Option<ConnectionPool<T>>,
+    insecure_pool: Option<ConnectionPool<T>>,
+    router: Option<RouterHandler>,
+    routes: HashMap<ByteString, ConnectionPool<T>>,
 }
 
 impl<T> Service<Connect> for InnerConnector<T>
@@ -280,6 +739,15 @@ where
         } else {
             ready
         };
+        let ready = if let Some(ref pool) = self.insecure_pool {
+            pool.poll_ready(cx)?.is_ready() && ready
+        } else {
+            ready
+        };
+        let mut ready = ready;
+        for pool in self.routes.values() {
+            ready = pool.poll_ready(cx)?.is_ready() && ready;
+        }
         if ready {
             Poll::Ready(Ok(()))
         } else {
@@ -294,7 +762,16 @@ where
             .as_ref()
             .map(|pool| pool.poll_shutdown(cx).is_ready())
             .unwrap_or(true);
-        if tcp_ready && ssl_ready {
+        let insecure_ready = self
+            .insecure_pool
+            .as_ref()
+            .map(|pool| pool.poll_shutdown(cx).is_ready())
+            .unwrap_or(true);
+        let mut routes_ready = true;
+        for pool in self.routes.values() {
+            routes_ready = pool.poll_shutdown(cx).is_ready() && routes_ready;
+        }
+        if tcp_ready && ssl_ready && insecure_ready && routes_ready {
             Poll::Ready(())
         } else {
             Poll::Pending
@@ -303,31 +780,236 @@ where
 
     async fn call(
         &self,
-        req: Connect,
+        mut req: Connect,
         ctx: ServiceCtx<'_, Self>,
     ) -> Result<Self::Response, Self::Error> {
-        match req.uri.scheme_str() {
-            Some("https") | Some("wss") => {
-                if let Some(ref conn) = self.ssl_pool {
-                    ctx.call(conn, req).await
-                } else {
-                    Err(ConnectError::SslIsNotSupported)
+        if let Some(ref router) = self.router {
+            match router.0.route(&req.uri) {
+                Route::Direct => (),
+                Route::Address(addr) => {
+                    if req.addr.is_none() {
+                        req.addr = Some(addr);
+                    }
+                }
+                Route::Bind(addr) => req.local_addr = Some(addr),
+                Route::Connector(name) => {
+                    return if let Some(pool) = self.routes.get(&name) {
+                        let secure = matches!(req.uri.scheme_str(), Some("https" | "wss"));
+                        match req.verify {
+                            CertVerify::Custom(ref verify) if secure => {
+                                let verify = verify.clone();
+                                verify_peer(ctx.call(pool, req).await?, &verify)
+                            }
+                            _ => ctx.call(pool, req).await,
+                        }
+                    } else {
+                        Err(ConnectError::UnknownRoute(name))
+                    };
                 }
             }
+        }
+
+        match req.uri.scheme_str() {
+            Some("https") | Some("wss") => match req.verify {
+                CertVerify::Default => {
+                    if let Some(ref conn) = self.ssl_pool {
+                        ctx.call(conn, req).await
+                    } else {
+                        Err(ConnectError::SslIsNotSupported)
+                    }
+                }
+                CertVerify::AcceptInvalid => {
+                    if let Some(ref conn) = self.insecure_pool {
+                        ctx.call(conn, req).await
+                    } else {
+                        Err(ConnectError::SslIsNotSupported)
+                    }
+                }
+                CertVerify::Custom(ref verify) => {
+                    if let Some(ref conn) = self.insecure_pool {
+                        let verify = verify.clone();
+                        verify_peer(ctx.call(conn, req).await?, &verify)
+                    } else {
+                        Err(ConnectError::SslIsNotSupported)
+                    }
+                }
+            },
             _ => ctx.call(&self.tcp_pool, req).await,
         }
     }
 }
 
+/// Check peer certificate with custom verifier
+fn verify_peer(
+    conn: Connection,
+    verify: &Rc<dyn Fn(&[u8]) -> bool>,
+) -> Result<Connection, ConnectError> {
+    if conn.peer_cert().map(|cert| verify(&cert)).unwrap_or(false) {
+        Ok(conn)
+    } else {
+        Err(ConnectError::CertificateRejected)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::service::{fn_service, Pipeline};
     use crate::util::lazy;
 
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    #[test]
+    fn test_tls_error() {
+        use std::io;
+
+        let err = io::Error::new(io::ErrorKind::TimedOut, ntex_tls::HandshakeTimeout);
+        assert!(matches!(
+            tls_error(crate::connect::ConnectError::Io(err)),
+            ConnectError::HandshakeTimeout
+        ));
+
+        // tcp connect timeout is not handshake timeout
+        let err = io::Error::new(io::ErrorKind::TimedOut, "connect timeout");
+        assert!(matches!(
+            tls_error(crate::connect::ConnectError::Io(err)),
+            ConnectError::Disconnected(Some(_))
+        ));
+    }
+
     #[crate::rt_test]
     async fn test_readiness() {
         let conn = Connector::default().finish();
         assert!(lazy(|cx| conn.poll_ready(cx).is_ready()).await);
         assert!(lazy(|cx| conn.poll_shutdown(cx).is_ready()).await);
     }
+
+    #[cfg(feature = "openssl")]
+    #[crate::rt_test]
+    async fn test_invalid_client_cert() {
+        use tls_openssl::ssl::SslMethod;
+
+        let cert = ClientCert::from_pem(b"cert".to_vec(), b"key".to_vec());
+        let result = Connector::default()
+            .openssl(OpensslConnector::builder(SslMethod::tls()).unwrap().build())
+            .client_cert(cert)
+            .try_finish();
+        assert!(result.is_err());
+    }
+
+    #[crate::rt_test]
+    async fn test_router() {
+        use std::{cell::Cell, rc::Rc};
+
+        let used = Rc::new(Cell::new(false));
+        let used2 = used.clone();
+        let conn = Pipeline::new(
+            Connector::default()
+                .router(|uri: &Uri| match uri.host() {
+                    Some("proxied") => Route::Connector("proxy".into()),
+                    Some("unknown") => Route::Connector("unknown".into()),
+                    _ => Route::Direct,
+                })
+                .route_connector(
+                    "proxy",
+                    fn_service(move |_: TcpConnect<Uri>| {
+                        used2.set(true);
+                        async {
+                            Err::<IoBoxed, _>(crate::connect::ConnectError::Unresolved)
+                        }
+                    }),
+                )
+                .finish(),
+        );
+
+        let req = |uri| Connect {
+            uri: Uri::try_from(uri).unwrap(),
+            addr: None,
+            verify: CertVerify::Default,
+            local_addr: None,
+        };
+        let res = conn.call(req("https://proxied/test")).await;
+        assert!(matches!(res, Err(ConnectError::Unresolved)));
+        assert!(used.get());
+
+        let res = conn.call(req("http://unknown/test")).await;
+        assert!(matches!(res, Err(ConnectError::UnknownRoute(name)) if name == "unknown"));
+    }
+
+    #[crate::rt_test]
+    async fn test_router_address() {
+        use std::{cell::RefCell, net};
+
+        use crate::{io as nio, testing::Io};
+
+        let routed: net::SocketAddr = "10.0.0.1:80".parse().unwrap();
+        let user: net::SocketAddr = "10.0.0.2:80".parse().unwrap();
+        let local: net::IpAddr = "127.0.0.1".parse().unwrap();
+
+        let store = Rc::new(RefCell::new(Vec::new()));
+        let store2 = store.clone();
+        let conn = Pipeline::new(
+            Connector::default()
+                .router(move |uri: &Uri| match uri.path() {
+                    "/address" => Route::Address(routed),
+                    "/bind" => Route::Bind(local),
+                    _ => Route::Direct,
+                })
+                .connector(fn_service(move |req: TcpConnect<Uri>| {
+                    let (client, server) = Io::create();
+                    store2.borrow_mut().push((
+                        req.addrs().next(),
+                        req.local_addr(),
+                        server,
+                    ));
+                    async move { Ok(nio::Io::new(client)) }
+                }))
+                .finish(),
+        );
+        let req = |uri| Connect {
+            uri: Uri::try_from(uri).unwrap(),
+            addr: None,
+            verify: CertVerify::Default,
+            local_addr: None,
+        };
+        let last = || {
+            let store = store.borrow();
+            let (addr, local, _) = store.last().unwrap();
+            (*addr, *local)
+        };
+
+        // route address
+        let c = conn.call(req("http://localhost/address")).await.unwrap();
+        assert_eq!(last(), (Some(routed), None));
+        c.release(true);
+
+        // route does not override request address
+        let c = conn
+            .call(Connect {
+                addr: Some(user),
+                ..req("http://localhost/address")
+            })
+            .await
+            .unwrap();
+        assert_eq!(last(), (Some(user), None));
+        c.release(true);
+
+        // local address
+        let c = conn.call(req("http://localhost/bind")).await.unwrap();
+        assert_eq!(last(), (None, Some(local)));
+        c.release(false);
+        assert_eq!(store.borrow().len(), 3);
+
+        // direct connection does not use connection of other route
+        let c = conn.call(req("http://localhost/")).await.unwrap();
+        assert_eq!(last(), (None, None));
+        c.release(false);
+        assert_eq!(store.borrow().len(), 4);
+
+        // connections are reused within route
+        let c = conn.call(req("http://localhost/bind")).await.unwrap();
+        c.release(false);
+        let c = conn.call(req("http://localhost/")).await.unwrap();
+        c.release(false);
+        assert_eq!(store.borrow().len(), 4);
+    }
 }
@@ -0,0 +1,393 @@
+use std::{cell::RefCell, fmt, fmt::Write, rc::Rc, time::Duration, time::SystemTime};
+
+use coo_kie::Cookie;
+use percent_encoding::percent_encode;
+
+use crate::http::header::{HeaderMap, HeaderValue, SET_COOKIE};
+use crate::http::{helpers::USERINFO, Uri};
+
+/// Client cookie store
+///
+/// Cookie store captures cookies from `Set-Cookie` response headers and
+/// attaches matching cookies to subsequent requests. Cookies are matched
+/// by domain, path, secure attribute and expiration time. Store could be
+/// shared between several clients, clones of the store refer to the same
+/// set of cookies.
+///
+/// ```rust
+/// use coo_kie::Cookie;
+/// use ntex::http::client::{Client, CookieStore};
+/// use ntex::http::Uri;
+///
+/// #[ntex::main]
+/// async fn main() {
+///     let store = CookieStore::new();
+///     store.insert(
+///         Cookie::new("session", "secret"),
+///         &Uri::from_static("https://example.com/"),
+///     );
+///
+///     let client = Client::build().cookie_store(store.clone()).finish();
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct CookieStore(Rc<RefCell<Vec<StoredCookie>>>);
+
+struct StoredCookie {
+    cookie: Cookie<'static>,
+    domain: String,
+    host_only: bool,
+    path: String,
+    secure: bool,
+    expires: Option<SystemTime>,
+}
+
+impl StoredCookie {
+    fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires.map(|exp| exp <= now).unwrap_or(false)
+    }
+
+    fn matches(&self, host: &str, path: &str, secure: bool) -> bool {
+        let domain = if self.host_only {
+            host == self.domain
+        } else {
+            domain_match(host, &self.domain)
+        };
+        domain && path_match(path, &self.path) && (secure || !self.secure)
+    }
+}
+
+impl CookieStore {
+    /// Create new empty cookie store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store cookie as if it was received from `url`.
+    ///
+    /// Returns `false` if cookie is rejected, for example if cookie's
+    /// domain does not match url host. Cookies with top-level domain
+    /// attribute and secure cookies received over insecure connection are
+    /// rejected as well, public suffix list is not used. Expired cookie
+    /// removes stored cookie with the same name, domain and path.
+    pub fn insert(&self, cookie: Cookie<'static>, url: &Uri) -> bool {
+        let host = if let Some(host) = url.host() {
+            host.to_ascii_lowercase()
+        } else {
+            return false;
+        };
+        let secure = cookie.secure().unwrap_or(false);
+        if secure && !is_secure(url) {
+            return false;
+        }
+
+        let (domain, host_only) = match cookie.domain() {
+            Some(domain) => {
+                let domain = domain.trim_start_matches('.').to_ascii_lowercase();
+                if !domain_match(&host, &domain) {
+                    return false;
+                }
+                if !domain.contains('.') {
+                    // top-level domain is accepted only as host-only cookie
+                    if domain != host {
+                        return false;
+                    }
+                    (domain, true)
+                } else {
+                    (domain, false)
+                }
+            }
+            None => (host, true),
+        };
+        let path = match cookie.path() {
+            Some(path) if path.starts_with('/') => path.to_string(),
+            _ => default_path(url.path()).to_string(),
+        };
+        let now = SystemTime::now();
+        let expires = if let Some(max_age) = cookie.max_age() {
+            let secs = max_age.whole_seconds();
+            if secs > 0 {
+                now.checked_add(Duration::from_secs(secs as u64))
+            } else {
+                Some(SystemTime::UNIX_EPOCH)
+            }
+        } else {
+            cookie
+                .expires()
+                .and_then(|exp| exp.datetime())
+                .map(SystemTime::from)
+        };
+
+        let item = StoredCookie {
+            secure,
+            cookie,
+            domain,
+            host_only,
+            path,
+            expires,
+        };
+
+        let mut cookies = self.0.borrow_mut();
+        cookies.retain(|c| {
+            !(c.cookie.name() == item.cookie.name()
+                && c.domain == item.domain
+                && c.path == item.path)
+        });
+        if !item.is_expired(now) {
+            cookies.push(item);
+        }
+        true
+    }
+
+    /// Get cookies that match url.
+    pub fn matches(&self, url: &Uri) -> Vec<Cookie<'static>> {
+        let host = if let Some(host) = url.host() {
+            host.to_ascii_lowercase()
+        } else {
+            return Vec::new();
+        };
+        let secure = is_secure(url);
+        let path = if url.path().is_empty() {
+            "/"
+        } else {
+            url.path()
+        };
+
+        let now = SystemTime::now();
+        let mut cookies = self.0.borrow_mut();
+        cookies.retain(|c| !c.is_expired(now));
+
+        let mut items: Vec<_> = cookies
+            .iter()
+            .filter(|c| c.matches(&host, path, secure))
+            .collect();
+        // cookies with longer paths are listed first
+        items.sort_by_key(|c| std::cmp::Reverse(c.path.len()));
+        items.into_iter().map(|c| c.cookie.clone()).collect()
+    }
+
+    /// Get all stored cookies.
+    ///
+    /// Returned cookies have domain and path attributes set.
+    pub fn cookies(&self) -> Vec<Cookie<'static>> {
+        let now = SystemTime::now();
+        self.0
+            .borrow()
+            .iter()
+            .filter(|c| !c.is_expired(now))
+            .map(|c| {
+                let mut cookie = c.cookie.clone();
+                cookie.set_domain(c.domain.clone());
+                cookie.set_path(c.path.clone());
+                cookie
+            })
+            .collect()
+    }
+
+    /// Remove cookie by name, domain and path.
+    pub fn remove(&self, name: &str, domain: &str, path: &str) -> bool {
+        let mut cookies = self.0.borrow_mut();
+        let len = cookies.len();
+        cookies
+            .retain(|c| !(c.cookie.name() == name && c.domain == domain && c.path == path));
+        len != cookies.len()
+    }
+
+    /// Remove all cookies.
+    pub fn clear(&self) {
+        self.0.borrow_mut().clear()
+    }
+
+    /// Number of stored cookies.
+    pub fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+
+    /// Check if store is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().is_empty()
+    }
+
+    /// Store cookies from response headers.
+    pub(super) fn set_cookies(&self, url: &Uri, headers: &HeaderMap) {
+        for hdr in headers.get_all(&SET_COOKIE) {
+            if let Ok(s) = hdr.to_str() {
+                match Cookie::parse_encoded(s) {
+                    Ok(cookie) => {
+                        self.insert(cookie.into_owned(), url);
+                    }
+                    Err(e) => log::debug!("Cannot parse cookie {:?}: {:?}", s, e),
+                }
+            }
+        }
+    }
+
+    /// Build `Cookie` header value for url.
+    ///
+    /// Cookies already present in `current` header value are not overridden.
+    pub(super) fn header_value(
+        &self,
+        url: &Uri,
+        current: Option<&str>,
+    ) -> Option<HeaderValue> {
+        let cookies = self.matches(url);
+        if cookies.is_empty() {
+            return None;
+        }
+
+        let mut value = current.map(|s| s.to_string()).unwrap_or_default();
+        for c in cookies {
+            let exists = current
+                .map(|cur| {
+                    cur.split(';')
+                        .any(|item| item.trim().split('=').next() == Some(c.name()))
+                })
+                .unwrap_or(false);
+            if !exists {
+                if !value.is_empty() {
+                    value.push_str("; ");
+                }
+                let _ = write!(
+                    value,
+                    "{}={}",
+                    percent_encode(c.name().as_bytes(), USERINFO),
+                    percent_encode(c.value().as_bytes(), USERINFO)
+                );
+            }
+        }
+        HeaderValue::from_str(&value).ok()
+    }
+}
+
+impl fmt::Debug for CookieStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CookieStore")
+            .field("cookies", &self.0.borrow().len())
+            .finish()
+    }
+}
+
+/// Domain matching, rfc6265 section 5.1.3
+fn domain_match(host: &str, domain: &str) -> bool {
+    host == domain
+        || (host.len() > domain.len()
+            && host.ends_with(domain)
+            && host.as_bytes()[host.len() - domain.len() - 1] == b'.'
+            && host.parse::<std::net::IpAddr>().is_err())
+}
+
+/// Path matching, rfc6265 section 5.1.4
+fn path_match(path: &str, cookie_path: &str) -> bool {
+    path == cookie_path
+        || (path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/')
+                || path.as_bytes().get(cookie_path.len()) == Some(&b'/')))
+}
+
+/// Url uses secure protocol
+fn is_secure(url: &Uri) -> bool {
+    matches!(url.scheme_str(), Some("https") | Some("wss"))
+}
+
+/// Default cookie path, rfc6265 section 5.1.4
+fn default_path(path: &str) -> &str {
+    if !path.starts_with('/') {
+        return "/";
+    }
+    match path.rfind('/') {
+        Some(0) | None => "/",
+        Some(idx) => &path[..idx],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cookie_store() {
+        let store = CookieStore::new();
+        let url = Uri::from_static("http://www.example.com/app/login");
+
+        assert!(store.insert(Cookie::new("a", "1"), &url));
+        assert!(store.insert(
+            Cookie::parse("b=2; Domain=example.com; Path=/").unwrap(),
+            &url
+        ));
+        assert!(!store.insert(Cookie::parse("c=3; Secure").unwrap(), &url));
+        assert!(store.insert(
+            Cookie::parse("c=3; Secure").unwrap(),
+            &Uri::from_static("https://www.example.com/app/login")
+        ));
+        assert!(!store.insert(Cookie::parse("d=4; Domain=other.com").unwrap(), &url));
+        assert!(!store.insert(Cookie::parse("d=4; Domain=com").unwrap(), &url));
+        assert_eq!(store.len(), 3);
+
+        // top-level domain is host-only
+        let local = Uri::from_static("http://localhost/");
+        assert!(store.insert(Cookie::parse("e=5; Domain=localhost").unwrap(), &local));
+        assert_eq!(store.matches(&local).len(), 1);
+        assert!(store.remove("e", "localhost", "/"));
+        assert!(format!("{:?}", store).contains("CookieStore"));
+
+        // host only cookie with default path
+        let names = |url: &'static str| {
+            store
+                .matches(&Uri::from_static(url))
+                .iter()
+                .map(|c| c.name().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("http://www.example.com/app/x"), vec!["a", "b"]);
+        assert_eq!(names("http://www.example.com/other"), vec!["b"]);
+        assert_eq!(names("http://api.example.com/app"), vec!["b"]);
+        assert_eq!(names("https://www.example.com/app"), vec!["a", "c", "b"]);
+        assert!(names("http://example.org/").is_empty());
+
+        // replace and expire
+        store.insert(
+            Cookie::parse("b=5; Domain=example.com; Path=/").unwrap(),
+            &url,
+        );
+        assert_eq!(
+            store.matches(&Uri::from_static("http://example.com/"))[0].value(),
+            "5"
+        );
+        store.insert(
+            Cookie::parse("b=; Domain=example.com; Path=/; Max-Age=0").unwrap(),
+            &url,
+        );
+        assert!(names("http://example.com/").is_empty());
+
+        let hdr = store
+            .header_value(
+                &Uri::from_static("https://www.example.com/app"),
+                Some("a=0"),
+            )
+            .unwrap();
+        assert_eq!(hdr, "a=0; c=3");
+
+        let cookies = store.cookies();
+        assert_eq!(cookies.len(), 2);
+        assert_eq!(cookies[0].domain(), Some("www.example.com"));
+        assert_eq!(cookies[0].path(), Some("/app"));
+
+        assert!(store.remove("a", "www.example.com", "/app"));
+        assert!(!store.remove("a", "www.example.com", "/app"));
+        store.clear();
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn test_matching() {
+        assert!(domain_match("www.example.com", "example.com"));
+        assert!(!domain_match("wwwexample.com", "example.com"));
+        assert!(!domain_match("127.0.0.1", "0.0.1"));
+        assert!(path_match("/app/x", "/app"));
+        assert!(path_match("/app/x", "/app/"));
+        assert!(!path_match("/application", "/app"));
+        assert_eq!(default_path("/app/login"), "/app");
+        assert_eq!(default_path("/login"), "/");
+        assert_eq!(default_path(""), "/");
+    }
+}
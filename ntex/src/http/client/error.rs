@@ -8,7 +8,7 @@ use thiserror::Error;
 use tls_openssl::ssl::{Error as SslError, HandshakeError};
 
 use crate::http::error::{DecodeError, EncodeError, HttpError, PayloadError};
-use crate::util::Either;
+use crate::util::{ByteString, Either};
 
 /// A set of errors that can occur during parsing json payloads
 #[derive(Error, Debug)]
@@ -53,6 +53,10 @@ pub enum ConnectError {
     #[error("Timeout while establishing connection")]
     Timeout,
 
+    /// Tls handshake took too long
+    #[error("Timeout while performing tls handshake")]
+    HandshakeTimeout,
+
     /// Connector has been disconnected
     #[error("Connector has been disconnected")]
     Disconnected(Option<io::Error>),
@@ -60,6 +64,14 @@ pub enum ConnectError {
     /// Unresolved host name
     #[error("Connector received `Connect` method with unresolved host")]
     Unresolved,
+
+    /// Server certificate is rejected by custom verifier
+    #[error("Server certificate is rejected")]
+    CertificateRejected,
+
+    /// Router selected connector that is not registered
+    #[error("Connector for route {0:?} is not registered")]
+    UnknownRoute(ByteString),
 }
 
 impl Clone for ConnectError {
@@ -77,6 +89,7 @@ impl Clone for ConnectError {
             }
             ConnectError::NoRecords => ConnectError::NoRecords,
             ConnectError::Timeout => ConnectError::Timeout,
+            ConnectError::HandshakeTimeout => ConnectError::HandshakeTimeout,
             ConnectError::Disconnected(e) => {
                 if let Some(e) = e {
                     ConnectError::Disconnected(Some(io::Error::new(
@@ -88,6 +101,8 @@ impl Clone for ConnectError {
                 }
             }
             ConnectError::Unresolved => ConnectError::Unresolved,
+            ConnectError::CertificateRejected => ConnectError::CertificateRejected,
+            ConnectError::UnknownRoute(name) => ConnectError::UnknownRoute(name.clone()),
         }
     }
 }
@@ -157,6 +172,9 @@ pub enum SendRequestError {
     /// Response took too long
     #[error("Timeout while waiting for response")]
     Timeout,
+    /// Number of redirects exceeds max redirects
+    #[error("Too many redirects")]
+    TooManyRedirects,
     /// Tunnels are not supported for http2 connection
     #[error("Tunnels are not supported for http2 connection")]
     TunnelNotSupported,
@@ -1,21 +1,20 @@
-use std::{error::Error, fmt, net, rc::Rc};
+use std::{error::Error, fmt, rc::Rc};
 
 use crate::http::body::Body;
 use crate::http::error::HttpError;
 use crate::http::header::{self, HeaderMap, HeaderName, HeaderValue};
 use crate::http::{Method, RequestHead, RequestHeadType, Uri};
-use crate::{time::Millis, util::Bytes, util::Stream};
+use crate::util::{Bytes, Stream};
 
-use super::{sender::SendClientRequest, ClientConfig};
+use super::sender::{SendClientRequest, SendOptions};
+use super::ClientConfig;
 
 /// `FrozenClientRequest` struct represents clonable client request.
 /// It could be used to send same request multiple times.
 #[derive(Clone)]
 pub struct FrozenClientRequest {
     pub(super) head: Rc<RequestHead>,
-    pub(super) addr: Option<net::SocketAddr>,
-    pub(super) response_decompress: bool,
-    pub(super) timeout: Millis,
+    pub(super) opts: SendOptions,
     pub(super) config: Rc<ClientConfig>,
 }
 
@@ -41,9 +40,7 @@ impl FrozenClientRequest {
         B: Into<Body>,
     {
         RequestHeadType::Rc(self.head.clone(), None).send_body(
-            self.addr,
-            self.response_decompress,
-            self.timeout,
+            self.opts.clone(),
             self.config.clone(),
             body,
         )
@@ -52,9 +49,7 @@ impl FrozenClientRequest {
     /// Send a json body.
     pub fn send_json<T: serde::Serialize>(&self, value: &T) -> SendClientRequest {
         RequestHeadType::Rc(self.head.clone(), None).send_json(
-            self.addr,
-            self.response_decompress,
-            self.timeout,
+            self.opts.clone(),
             self.config.clone(),
             value,
         )
@@ -63,9 +58,7 @@ impl FrozenClientRequest {
     /// Send an urlencoded body.
     pub fn send_form<T: serde::Serialize>(&self, value: &T) -> SendClientRequest {
         RequestHeadType::Rc(self.head.clone(), None).send_form(
-            self.addr,
-            self.response_decompress,
-            self.timeout,
+            self.opts.clone(),
             self.config.clone(),
             value,
         )
@@ -78,9 +71,7 @@ impl FrozenClientRequest {
         E: Error + 'static,
     {
         RequestHeadType::Rc(self.head.clone(), None).send_stream(
-            self.addr,
-            self.response_decompress,
-            self.timeout,
+            self.opts.clone(),
             self.config.clone(),
             stream,
         )
@@ -88,12 +79,8 @@ impl FrozenClientRequest {
 
     /// Send an empty body.
     pub fn send(&self) -> SendClientRequest {
-        RequestHeadType::Rc(self.head.clone(), None).send(
-            self.addr,
-            self.response_decompress,
-            self.timeout,
-            self.config.clone(),
-        )
+        RequestHeadType::Rc(self.head.clone(), None)
+            .send(self.opts.clone(), self.config.clone())
     }
 
     /// Create a `FrozenSendBuilder` with extra headers
@@ -178,9 +165,7 @@ impl FrozenSendBuilder {
         }
 
         RequestHeadType::Rc(self.req.head, Some(self.extra_headers)).send_body(
-            self.req.addr,
-            self.req.response_decompress,
-            self.req.timeout,
+            self.req.opts,
             self.req.config,
             body,
         )
@@ -193,9 +178,7 @@ impl FrozenSendBuilder {
         }
 
         RequestHeadType::Rc(self.req.head, Some(self.extra_headers)).send_json(
-            self.req.addr,
-            self.req.response_decompress,
-            self.req.timeout,
+            self.req.opts,
             self.req.config,
             value,
         )
@@ -208,9 +191,7 @@ impl FrozenSendBuilder {
         }
 
         RequestHeadType::Rc(self.req.head, Some(self.extra_headers)).send_form(
-            self.req.addr,
-            self.req.response_decompress,
-            self.req.timeout,
+            self.req.opts,
             self.req.config,
             value,
         )
@@ -227,9 +208,7 @@ impl FrozenSendBuilder {
         }
 
         RequestHeadType::Rc(self.req.head, Some(self.extra_headers)).send_stream(
-            self.req.addr,
-            self.req.response_decompress,
-            self.req.timeout,
+            self.req.opts,
             self.req.config,
             stream,
         )
@@ -241,11 +220,7 @@ impl FrozenSendBuilder {
             return e.into();
         }
 
-        RequestHeadType::Rc(self.req.head, Some(self.extra_headers)).send(
-            self.req.addr,
-            self.req.response_decompress,
-            self.req.timeout,
-            self.req.config,
-        )
+        RequestHeadType::Rc(self.req.head, Some(self.extra_headers))
+            .send(self.req.opts, self.req.config)
     }
 }
@@ -5,9 +5,10 @@ use std::{
 use crate::http::body::{BodySize, MessageBody};
 use crate::http::error::PayloadError;
 use crate::http::h1;
-use crate::http::header::{HeaderMap, HeaderValue, HOST};
+use crate::http::header::{HeaderMap, HeaderValue, CONTENT_LENGTH, EXPECT, HOST};
 use crate::http::message::{RequestHeadType, ResponseHead};
 use crate::http::payload::{Payload, PayloadStream};
+use crate::http::StatusCode;
 use crate::io::{IoBoxed, RecvError};
 use crate::time::{timeout_checked, Millis};
 use crate::util::{ready, BufMut, Bytes, BytesMut, Stream};
@@ -15,6 +16,7 @@ use crate::util::{ready, BufMut, Bytes, BytesMut, Stream};
 use super::connection::{Connection, ConnectionType};
 use super::error::{ConnectError, SendRequestError};
 use super::pool::Acquired;
+use super::sender::Informational;
 
 pub(super) async fn send_request<B>(
     io: IoBoxed,
@@ -22,11 +24,33 @@ pub(super) async fn send_request<B>(
     body: B,
     created: Instant,
     timeout: Millis,
+    expect_continue: Option<Millis>,
+    informational: Option<Informational>,
     pool: Option<Acquired>,
 ) -> Result<(ResponseHead, Payload), SendRequestError>
 where
     B: MessageBody,
 {
+    let expect_continue = match body.size() {
+        BodySize::None | BodySize::Empty | BodySize::Sized(0) => None,
+        _ => expect_continue,
+    };
+
+    // without chunking, streaming body must be delimited by content-length
+    if body.size() == BodySize::Stream
+        && !head.as_ref().chunked()
+        && !head.as_ref().headers.contains_key(CONTENT_LENGTH)
+        && !head
+            .extra_headers()
+            .iter()
+            .any(|h| h.contains_key(CONTENT_LENGTH))
+    {
+        return Err(SendRequestError::Send(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Streaming body requires content-length header if chunking is disabled",
+        )));
+    }
+
     // set request host header
     if !head.as_ref().headers.contains_key(HOST)
         && !head.extra_headers().iter().any(|h| h.contains_key(HOST))
@@ -54,6 +78,20 @@ where
         }
     }
 
+    // set expect header
+    if expect_continue.is_some()
+        && !head.as_ref().headers.contains_key(EXPECT)
+        && !head.extra_headers().iter().any(|h| h.contains_key(EXPECT))
+    {
+        let value = HeaderValue::from_static("100-continue");
+        match head {
+            RequestHeadType::Owned(ref mut head) => head.headers.insert(EXPECT, value),
+            RequestHeadType::Rc(_, ref mut extra_headers) => extra_headers
+                .get_or_insert(HeaderMap::new())
+                .insert(EXPECT, value),
+        }
+    }
+
     log::trace!(
         "sending http1 request {:?} body size: {:?}",
         head,
@@ -61,11 +99,24 @@ where
     );
 
     // send request
-    let codec = h1::ClientCodec::default();
+    let codec = h1::ClientCodec::default()
+        .preserve_header_case(head.as_ref().preserve_header_case());
     io.send((head, body.size()).into(), &codec).await?;
 
     log::trace!("http1 request has been sent");
 
+    // wait for interim response
+    if let Some(expect_timeout) = expect_continue {
+        if let Some(head) =
+            recv_continue(&io, &codec, expect_timeout, &informational).await?
+        {
+            log::trace!("http1 request is rejected, body is not sent: {:?}", head);
+
+            // body is not sent, connection cannot be reused
+            return Ok(response(head, io, codec, true, created, pool));
+        }
+    }
+
     // send request body
     match body.size() {
         BodySize::None | BodySize::Empty | BodySize::Sized(0) => (),
@@ -78,16 +129,13 @@ where
 
     // read response and init read body
     let fut = async {
-        if let Some(result) = io.recv(&codec).await? {
-            log::trace!(
-                "http1 response is received, type: {:?}, response: {:#?}",
-                codec.message_type(),
-                result
-            );
-            Ok(result)
-        } else {
-            Err(SendRequestError::from(ConnectError::Disconnected(None)))
-        }
+        let result = recv_head(&io, &codec, &informational, false).await?;
+        log::trace!(
+            "http1 response is received, type: {:?}, response: {:#?}",
+            codec.message_type(),
+            result
+        );
+        Ok(result)
     };
 
     let head = timeout_checked(timeout, fut)
@@ -95,14 +143,84 @@ where
         .map_err(|_| SendRequestError::Timeout)
         .and_then(|res| res)?;
 
+    Ok(response(head, io, codec, false, created, pool))
+}
+
+/// wait for `100 Continue` response
+///
+/// returns final response if server rejected request
+async fn recv_continue(
+    io: &IoBoxed,
+    codec: &h1::ClientCodec,
+    timeout: Millis,
+    informational: &Option<Informational>,
+) -> Result<Option<ResponseHead>, SendRequestError> {
+    let fut = recv_head(io, codec, informational, true);
+
+    match timeout_checked(timeout, fut).await {
+        Ok(Ok(head)) if head.status == StatusCode::CONTINUE => Ok(None),
+        Ok(Ok(head)) => Ok(Some(head)),
+        Ok(Err(e)) => Err(e),
+        Err(_) => {
+            log::trace!("http1 interim response timeout, sending body");
+            Ok(None)
+        }
+    }
+}
+
+/// read response head
+///
+/// if callback is set, interim responses are passed to callback and skipped,
+/// `100 Continue` is returned if `stop_continue` is set. otherwise first
+/// received response is returned
+async fn recv_head(
+    io: &IoBoxed,
+    codec: &h1::ClientCodec,
+    informational: &Option<Informational>,
+    stop_continue: bool,
+) -> Result<ResponseHead, SendRequestError> {
+    loop {
+        let head = if let Some(head) = io.recv(codec).await? {
+            head
+        } else {
+            return Err(SendRequestError::from(ConnectError::Disconnected(None)));
+        };
+
+        let f = match informational {
+            Some(ref f)
+                if head.status.is_informational()
+                    && head.status != StatusCode::SWITCHING_PROTOCOLS =>
+            {
+                f
+            }
+            _ => return Ok(head),
+        };
+
+        log::trace!("http1 interim response is received: {:?}", head.status);
+        (f.0)(&head);
+        if stop_continue && head.status == StatusCode::CONTINUE {
+            return Ok(head);
+        }
+    }
+}
+
+fn response(
+    head: ResponseHead,
+    io: IoBoxed,
+    codec: h1::ClientCodec,
+    force_close: bool,
+    created: Instant,
+    pool: Option<Acquired>,
+) -> (ResponseHead, Payload) {
     match codec.message_type() {
         h1::MessageType::None => {
-            release_connection(io, !codec.keepalive(), created, pool);
-            Ok((head, Payload::None))
+            release_connection(io, force_close || !codec.keepalive(), created, pool);
+            (head, Payload::None)
         }
         _ => {
-            let pl: PayloadStream = Box::pin(PlStream::new(io, codec, created, pool));
-            Ok((head, pl.into()))
+            let pl: PayloadStream =
+                Box::pin(PlStream::new(io, codec, force_close, created, pool));
+            (head, pl.into())
         }
     }
 }
@@ -136,6 +254,7 @@ where
 pub(super) struct PlStream {
     io: Option<IoBoxed>,
     codec: h1::ClientPayloadCodec,
+    force_close: bool,
     created: Instant,
     pool: Option<Acquired>,
 }
@@ -144,12 +263,14 @@ impl PlStream {
     fn new(
         io: IoBoxed,
         codec: h1::ClientCodec,
+        force_close: bool,
         created: Instant,
         pool: Option<Acquired>,
     ) -> Self {
         PlStream {
             io: Some(io),
             codec: codec.into_payload_codec(),
+            force_close,
             created,
             pool,
         }
@@ -173,7 +294,7 @@ impl Stream for PlStream {
                         } else {
                             release_connection(
                                 this.io.take().unwrap(),
-                                !this.codec.keepalive(),
+                                this.force_close || !this.codec.keepalive(),
                                 this.created,
                                 this.pool.take(),
                             );
@@ -223,11 +223,16 @@ async fn send_body<B: MessageBody>(
 #[derive(Clone)]
 pub(super) struct H2Client {
     client: SimpleClient,
+    peer_cert: Option<Bytes>,
 }
 
 impl H2Client {
-    pub(super) fn new(client: SimpleClient) -> Self {
-        Self { client }
+    pub(super) fn new(client: SimpleClient, peer_cert: Option<Bytes>) -> Self {
+        Self { client, peer_cert }
+    }
+
+    pub(super) fn peer_cert(&self) -> Option<Bytes> {
+        self.peer_cert.clone()
     }
 
     pub(super) fn close(&self) {
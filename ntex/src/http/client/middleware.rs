@@ -0,0 +1,241 @@
+use std::{fmt, mem, rc::Rc};
+
+use crate::http::body::Body;
+use crate::http::header::{HeaderMap, HeaderName, HeaderValue};
+use crate::http::{Method, RequestHead, RequestHeadType, Uri};
+use crate::service::{boxed::BoxService, Service, ServiceCtx};
+use crate::time::Millis;
+
+use super::error::SendRequestError;
+use super::sender::SendOptions;
+use super::{ClientConfig, ClientResponse};
+
+/// Type-erased client service, inner service for client middlewares
+pub type ClientService = BoxService<ClientServiceRequest, ClientResponse, SendRequestError>;
+
+/// Outgoing request passed through client middlewares
+///
+/// Middlewares are registered with [`ClientBuilder::wrap()`](super::ClientBuilder::wrap).
+///
+/// ```rust
+/// use ntex::http::client::error::SendRequestError;
+/// use ntex::http::client::{Client, ClientResponse, ClientServiceRequest};
+/// use ntex::service::{Middleware, Service, ServiceCtx};
+///
+/// struct RequestId;
+///
+/// impl<S> Middleware<S> for RequestId {
+///     type Service = RequestIdService<S>;
+///
+///     fn create(&self, service: S) -> Self::Service {
+///         RequestIdService { service }
+///     }
+/// }
+///
+/// struct RequestIdService<S> {
+///     service: S,
+/// }
+///
+/// impl<S> Service<ClientServiceRequest> for RequestIdService<S>
+/// where
+///     S: Service<ClientServiceRequest, Response = ClientResponse, Error = SendRequestError>,
+/// {
+///     type Response = ClientResponse;
+///     type Error = SendRequestError;
+///
+///     ntex::forward_poll_ready!(service);
+///
+///     async fn call(
+///         &self,
+///         mut req: ClientServiceRequest,
+///         ctx: ServiceCtx<'_, Self>,
+///     ) -> Result<ClientResponse, SendRequestError> {
+///         req.set_header("x-request-id", "1");
+///         ctx.call(&self.service, req).await
+///     }
+/// }
+///
+/// #[ntex::main]
+/// async fn main() {
+///     let client = Client::build().wrap(RequestId).finish();
+/// }
+/// ```
+pub struct ClientServiceRequest {
+    head: RequestHeadType,
+    body: Body,
+    opts: SendOptions,
+    config: Rc<ClientConfig>,
+}
+
+impl ClientServiceRequest {
+    /// Request head
+    ///
+    /// Shared request heads (frozen requests, redirects) could have
+    /// extra headers, use [`ClientServiceRequest::header()`] to
+    /// check actual header value.
+    pub fn head(&self) -> &RequestHead {
+        self.head.as_ref()
+    }
+
+    /// Request's uri
+    pub fn uri(&self) -> &Uri {
+        &self.head.as_ref().uri
+    }
+
+    /// Request's method
+    pub fn method(&self) -> &Method {
+        &self.head.as_ref().method
+    }
+
+    /// Get request header value
+    pub fn header(&self, key: &HeaderName) -> Option<&HeaderValue> {
+        self.head
+            .extra_headers()
+            .and_then(|h| h.get(key))
+            .or_else(|| self.head.as_ref().headers.get(key))
+    }
+
+    /// Set request header, replaces existing value
+    pub fn set_header<K, V>(&mut self, key: K, value: V)
+    where
+        HeaderName: TryFrom<K>,
+        HeaderValue: TryFrom<V>,
+    {
+        if let (Ok(key), Ok(value)) =
+            (HeaderName::try_from(key), HeaderValue::try_from(value))
+        {
+            self.headers_mut().insert(key, value);
+        }
+    }
+
+    /// Mutable request headers
+    ///
+    /// For shared request heads this method returns extra headers,
+    /// which override headers of the request head.
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        match self.head {
+            RequestHeadType::Owned(ref mut head) => &mut head.headers,
+            RequestHeadType::Rc(_, ref mut extra) => {
+                extra.get_or_insert_with(HeaderMap::new)
+            }
+        }
+    }
+
+    /// Request body
+    pub fn body(&self) -> &Body {
+        &self.body
+    }
+
+    /// Replace request body
+    pub fn set_body<B: Into<Body>>(&mut self, body: B) {
+        self.body = body.into();
+    }
+
+    /// Request timeout
+    pub fn timeout(&self) -> Millis {
+        self.opts.timeout
+    }
+
+    /// Set request timeout
+    pub fn set_timeout<T: Into<Millis>>(&mut self, timeout: T) {
+        self.opts.timeout = timeout.into();
+    }
+
+    /// Request headers merged with extra headers
+    pub(super) fn all_headers(&self) -> HeaderMap {
+        let mut headers = self.head.as_ref().headers.clone();
+        if let Some(extra) = self.head.extra_headers() {
+            for key in extra.keys() {
+                headers.remove(key);
+                for value in extra.get_all(key) {
+                    headers.append(key.clone(), value.clone());
+                }
+            }
+        }
+        headers
+    }
+
+    pub(super) fn config(&self) -> &Rc<ClientConfig> {
+        &self.config
+    }
+
+    /// Clone request if body could be re-sent
+    ///
+    /// Streaming bodies could not be cloned. Request head is converted
+    /// to shared head, so clone is cheap.
+    pub fn try_clone(&mut self) -> Option<Self> {
+        let body = match self.body {
+            Body::None => Body::None,
+            Body::Empty => Body::Empty,
+            Body::Bytes(ref bytes) => Body::Bytes(bytes.clone()),
+            Body::Message(_) => return None,
+        };
+
+        if let RequestHeadType::Owned(_) = self.head {
+            let head =
+                mem::replace(&mut self.head, RequestHeadType::Rc(Rc::default(), None));
+            if let RequestHeadType::Owned(head) = head {
+                self.head = RequestHeadType::Rc(Rc::new(head), None);
+            }
+        }
+        let head = match self.head {
+            RequestHeadType::Rc(ref head, ref extra) => {
+                RequestHeadType::Rc(head.clone(), extra.clone())
+            }
+            RequestHeadType::Owned(_) => unreachable!(),
+        };
+
+        Some(ClientServiceRequest {
+            head,
+            body,
+            opts: self.opts.clone(),
+            config: self.config.clone(),
+        })
+    }
+}
+
+impl fmt::Debug for ClientServiceRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientServiceRequest")
+            .field("head", &self.head)
+            .field("body", &self.body)
+            .finish()
+    }
+}
+
+/// Inner-most client service, sends request and follows redirects
+#[derive(Debug)]
+pub(super) struct SendService;
+
+impl Service<ClientServiceRequest> for SendService {
+    type Response = ClientResponse;
+    type Error = SendRequestError;
+
+    async fn call(
+        &self,
+        req: ClientServiceRequest,
+        _: ServiceCtx<'_, Self>,
+    ) -> Result<ClientResponse, SendRequestError> {
+        super::redirect::send_request(req.head, req.body, req.opts, req.config).await
+    }
+}
+
+/// Send request through client middlewares
+pub(super) async fn send_request(
+    head: RequestHeadType,
+    body: Body,
+    opts: SendOptions,
+    config: Rc<ClientConfig>,
+) -> Result<ClientResponse, SendRequestError> {
+    if let Some(svc) = config.service.clone() {
+        svc.call(ClientServiceRequest {
+            head,
+            body,
+            opts,
+            config,
+        })
+        .await
+    } else {
+        super::redirect::send_request(head, body, opts, config).await
+    }
+}
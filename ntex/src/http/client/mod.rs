@@ -18,39 +18,77 @@
 //! ```
 use std::rc::Rc;
 
+pub mod batch;
 mod builder;
+mod cache;
 mod connect;
 mod connection;
 mod connector;
+#[cfg(feature = "cookie")]
+mod cookie;
 pub mod error;
 mod frozen;
 mod h1proto;
 mod h2proto;
+mod middleware;
+pub mod multipart;
 mod pool;
+mod reader;
+mod redirect;
 mod request;
 mod response;
+mod router;
 mod sender;
 mod test;
 
 pub use self::builder::ClientBuilder;
+pub use self::cache::{Cache, CacheEntry, CacheStatus, CacheStorage, MemoryStorage};
+pub use self::connect::ClientConnector;
 pub use self::connection::Connection;
 pub use self::connector::Connector;
+#[cfg(feature = "cookie")]
+pub use self::cookie::CookieStore;
 pub use self::frozen::{FrozenClientRequest, FrozenSendBuilder};
+pub use self::middleware::{ClientService, ClientServiceRequest};
+pub use self::pool::{HostStatus, PoolEvent, PoolStatus};
+pub use self::reader::ReaderStream;
 pub use self::request::ClientRequest;
 pub use self::response::{ClientResponse, JsonBody, MessageBody};
+pub use self::router::{ConnectorRouter, Route};
 pub use self::sender::SendClientRequest;
 pub use self::test::TestResponse;
 
 use crate::http::error::HttpError;
 use crate::http::{HeaderMap, Method, RequestHead, Uri};
-use crate::time::Millis;
+use crate::{service::Pipeline, time::Millis};
 
 use self::connect::{Connect as HttpConnect, ConnectorWrapper};
+use self::connector::CertVerify;
 
 #[derive(Debug, Clone)]
 pub struct Connect {
     pub uri: Uri,
     pub addr: Option<std::net::SocketAddr>,
+    pub(crate) verify: CertVerify,
+    pub(crate) local_addr: Option<std::net::IpAddr>,
+}
+
+impl Connect {
+    /// Create connect request for the uri
+    pub fn new(uri: Uri) -> Self {
+        Connect {
+            uri,
+            addr: None,
+            verify: CertVerify::Default,
+            local_addr: None,
+        }
+    }
+
+    /// Set remote address, host name resolution is skipped
+    pub fn set_addr(mut self, addr: Option<std::net::SocketAddr>) -> Self {
+        self.addr = addr;
+        self
+    }
 }
 
 /// An HTTP Client
@@ -78,8 +116,16 @@ pub(crate) struct ClientConfig {
     pub(self) connector: Box<dyn HttpConnect>,
     pub(self) headers: HeaderMap,
     pub(self) timeout: Millis,
+    pub(self) connect_timeout: Millis,
     pub(self) response_pl_limit: usize,
     pub(self) response_pl_timeout: Millis,
+    pub(self) response_decompress: bool,
+    pub(self) response_decompress_limit: usize,
+    pub(self) max_redirects: usize,
+    pub(self) preserve_header_case: bool,
+    #[cfg(feature = "cookie")]
+    pub(self) cookie_store: Option<CookieStore>,
+    pub(self) service: Option<Pipeline<ClientService>>,
 }
 
 impl Default for ClientConfig {
@@ -87,9 +133,17 @@ impl Default for ClientConfig {
         ClientConfig {
             headers: HeaderMap::new(),
             timeout: Millis(5_000),
+            connect_timeout: Millis::ZERO,
             response_pl_limit: 262_144,
             response_pl_timeout: Millis(10_000),
-            connector: Box::new(ConnectorWrapper(Connector::default().finish().into())),
+            response_decompress: true,
+            response_decompress_limit: 0,
+            max_redirects: 10,
+            preserve_header_case: false,
+            #[cfg(feature = "cookie")]
+            cookie_store: None,
+            service: None,
+            connector: Box::new(ConnectorWrapper::new(Connector::default().finish())),
         }
     }
 }
@@ -132,6 +186,11 @@ impl Client {
         for (key, value) in head.headers.iter() {
             req = req.set_header_if_none(key.clone(), value.clone());
         }
+        for key in head.headers.keys() {
+            if let Some(case) = head.headers.original_case(key) {
+                req.head.headers.set_original_case(key, case);
+            }
+        }
         req
     }
 
@@ -197,4 +256,26 @@ impl Client {
     {
         self.request(Method::OPTIONS, url)
     }
+
+    /// Snapshot of client's connection pools status.
+    ///
+    /// Status includes pools of connections acquired by the client, pools
+    /// are reported once first connection is acquired. Custom connector
+    /// services that do not use [`Connector`] pools report empty status.
+    pub fn pool_status(&self) -> PoolStatus {
+        self.0.connector.pool_status()
+    }
+
+    /// Get client's connector.
+    ///
+    /// Connector provides low-level access to client's connections.
+    pub fn connector(&self) -> ClientConnector {
+        ClientConnector(self.0.clone())
+    }
+
+    /// Get client's cookie store, if configured.
+    #[cfg(feature = "cookie")]
+    pub fn cookie_store(&self) -> Option<&CookieStore> {
+        self.0.cookie_store.as_ref()
+    }
 }
@@ -0,0 +1,373 @@
+//! Multipart form builder for client requests
+use std::task::{Context, Poll};
+use std::{collections::VecDeque, error::Error, fmt, pin::Pin};
+
+use nanorand::{Rng, WyRand};
+
+use crate::http::body::{Body, BodySize, MessageBody};
+use crate::util::{Bytes, BytesMut, Stream};
+
+type BoxedStream = Box<dyn Stream<Item = Result<Bytes, Box<dyn Error>>> + Unpin>;
+
+/// Multipart/form-data request body builder
+///
+/// ```rust
+/// use ntex::http::client::{multipart::{Form, Part}, Client};
+///
+/// #[ntex::main]
+/// async fn main() {
+///     let form = Form::new()
+///         .text("name", "ntex")
+///         .part(
+///             "file",
+///             Part::bytes("file content")
+///                 .file_name("file.txt")
+///                 .content_type(mime::TEXT_PLAIN),
+///         );
+///
+///     let res = Client::new()
+///         .post("http://www.rust-lang.org")
+///         .send_multipart(form)
+///         .await;
+///     println!("Response: {:?}", res);
+/// }
+/// ```
+pub struct Form {
+    boundary: String,
+    parts: Vec<(String, Part)>,
+}
+
+/// Single field of multipart form
+pub struct Part {
+    body: PartBody,
+    file_name: Option<String>,
+    content_type: Option<mime::Mime>,
+}
+
+enum PartBody {
+    Bytes(Bytes),
+    Stream(BoxedStream, Option<u64>),
+}
+
+impl Default for Form {
+    fn default() -> Self {
+        Form::new()
+    }
+}
+
+impl Form {
+    /// Create new form with random boundary
+    pub fn new() -> Self {
+        let mut rng = WyRand::new();
+        let boundary = (0..4)
+            .map(|_| format!("{:016x}", rng.generate::<u64>()))
+            .collect::<String>();
+
+        Form {
+            boundary,
+            parts: Vec::new(),
+        }
+    }
+
+    /// Get form boundary
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// Add text field
+    pub fn text<N, V>(self, name: N, value: V) -> Self
+    where
+        N: Into<String>,
+        V: Into<String>,
+    {
+        self.part(name, Part::text(value))
+    }
+
+    /// Add form field
+    pub fn part<N: Into<String>>(mut self, name: N, part: Part) -> Self {
+        self.parts.push((name.into(), part));
+        self
+    }
+
+    /// Value of `Content-Type` header for this form
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// Convert form to a request body
+    ///
+    /// Body has known size if all parts have known size,
+    /// otherwise body is sent with chunked encoding.
+    pub fn into_body(self) -> Body {
+        let mut size = Some(0u64);
+        let mut chunks = VecDeque::new();
+        let mut buf = BytesMut::new();
+
+        for (name, part) in self.parts {
+            buf.extend_from_slice(b"--");
+            buf.extend_from_slice(self.boundary.as_bytes());
+            buf.extend_from_slice(b"\r\ncontent-disposition: form-data; name=\"");
+            buf.extend_from_slice(escape(&name).as_bytes());
+            buf.extend_from_slice(b"\"");
+            if let Some(ref file_name) = part.file_name {
+                buf.extend_from_slice(b"; filename=\"");
+                buf.extend_from_slice(escape(file_name).as_bytes());
+                buf.extend_from_slice(b"\"");
+            }
+            buf.extend_from_slice(b"\r\n");
+            if let Some(ref ct) = part.content_type {
+                buf.extend_from_slice(b"content-type: ");
+                buf.extend_from_slice(ct.as_ref().as_bytes());
+                buf.extend_from_slice(b"\r\n");
+            }
+            buf.extend_from_slice(b"\r\n");
+
+            match part.body {
+                PartBody::Bytes(data) => buf.extend_from_slice(&data),
+                PartBody::Stream(stream, len) => {
+                    size = size.and_then(|s| len.map(|l| s + l + buf.len() as u64));
+                    chunks.push_back(Chunk::Bytes(buf.split().freeze()));
+                    chunks.push_back(Chunk::Stream(stream));
+                }
+            }
+            buf.extend_from_slice(b"\r\n");
+        }
+        buf.extend_from_slice(b"--");
+        buf.extend_from_slice(self.boundary.as_bytes());
+        buf.extend_from_slice(b"--\r\n");
+
+        if chunks.is_empty() {
+            Body::Bytes(buf.freeze())
+        } else {
+            let size = size.map(|s| s + buf.len() as u64);
+            chunks.push_back(Chunk::Bytes(buf.freeze()));
+            Body::from_message(FormBody { size, chunks })
+        }
+    }
+}
+
+impl fmt::Debug for Form {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Form")
+            .field("boundary", &self.boundary)
+            .field("parts", &self.parts)
+            .finish()
+    }
+}
+
+impl Part {
+    /// Create text part
+    pub fn text<V: Into<String>>(value: V) -> Self {
+        Part::new(PartBody::Bytes(Bytes::from(value.into())))
+    }
+
+    /// Create in-memory part
+    pub fn bytes<B: Into<Bytes>>(data: B) -> Self {
+        Part::new(PartBody::Bytes(data.into()))
+    }
+
+    /// Create streaming part with unknown size
+    ///
+    /// Form with streaming part is sent with chunked encoding.
+    pub fn stream<S, E>(stream: S) -> Self
+    where
+        S: Stream<Item = Result<Bytes, E>> + Unpin + 'static,
+        E: Error + 'static,
+    {
+        Part::new(PartBody::Stream(Box::new(MapErr(stream)), None))
+    }
+
+    /// Create streaming part with known size
+    ///
+    /// Stream must produce exactly `size` bytes.
+    pub fn sized_stream<S, E>(stream: S, size: u64) -> Self
+    where
+        S: Stream<Item = Result<Bytes, E>> + Unpin + 'static,
+        E: Error + 'static,
+    {
+        Part::new(PartBody::Stream(Box::new(MapErr(stream)), Some(size)))
+    }
+
+    fn new(body: PartBody) -> Self {
+        Part {
+            body,
+            file_name: None,
+            content_type: None,
+        }
+    }
+
+    /// Set part's file name
+    pub fn file_name<N: Into<String>>(mut self, name: N) -> Self {
+        self.file_name = Some(name.into());
+        self
+    }
+
+    /// Set part's content type
+    pub fn content_type(mut self, ct: mime::Mime) -> Self {
+        self.content_type = Some(ct);
+        self
+    }
+}
+
+impl fmt::Debug for Part {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut f = f.debug_struct("Part");
+        match self.body {
+            PartBody::Bytes(ref data) => f.field("size", &data.len()),
+            PartBody::Stream(_, ref size) => f.field("stream", size),
+        };
+        f.field("file_name", &self.file_name)
+            .field("content_type", &self.content_type)
+            .finish()
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+struct MapErr<S>(S);
+
+impl<S, E> Stream for MapErr<S>
+where
+    S: Stream<Item = Result<Bytes, E>> + Unpin,
+    E: Error + 'static,
+{
+    type Item = Result<Bytes, Box<dyn Error>>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.0)
+            .poll_next(cx)
+            .map(|item| item.map(|res| res.map_err(|e| Box::new(e) as Box<dyn Error>)))
+    }
+}
+
+enum Chunk {
+    Bytes(Bytes),
+    Stream(BoxedStream),
+}
+
+struct FormBody {
+    size: Option<u64>,
+    chunks: VecDeque<Chunk>,
+}
+
+impl fmt::Debug for FormBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FormBody")
+            .field("size", &self.size)
+            .finish()
+    }
+}
+
+impl MessageBody for FormBody {
+    fn size(&self) -> BodySize {
+        match self.size {
+            Some(size) => BodySize::Sized(size),
+            None => BodySize::Stream,
+        }
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        loop {
+            match self.chunks.front_mut() {
+                None => return Poll::Ready(None),
+                Some(Chunk::Bytes(_)) => {
+                    if let Some(Chunk::Bytes(data)) = self.chunks.pop_front() {
+                        return Poll::Ready(Some(Ok(data)));
+                    }
+                }
+                Some(Chunk::Stream(stream)) => match Pin::new(stream).poll_next(cx) {
+                    Poll::Ready(Some(Ok(data))) if data.is_empty() => continue,
+                    Poll::Ready(Some(res)) => return Poll::Ready(Some(res)),
+                    Poll::Ready(None) => {
+                        self.chunks.pop_front();
+                    }
+                    Poll::Pending => return Poll::Pending,
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::poll_fn;
+
+    use super::*;
+
+    async fn read_body(body: Body) -> (BodySize, Bytes) {
+        let size = body.size();
+        let mut body = body;
+        let mut buf = BytesMut::new();
+        while let Some(chunk) = poll_fn(|cx| body.poll_next_chunk(cx)).await {
+            buf.extend_from_slice(&chunk.unwrap());
+        }
+        (size, buf.freeze())
+    }
+
+    #[crate::rt_test]
+    async fn test_form() {
+        let form = Form::new().text("name", "value").part(
+            "file",
+            Part::bytes("data")
+                .file_name("a\"b.txt")
+                .content_type(mime::TEXT_PLAIN),
+        );
+        let boundary = form.boundary().to_string();
+        assert_eq!(boundary.len(), 64);
+        assert_eq!(
+            form.content_type(),
+            format!("multipart/form-data; boundary={}", boundary)
+        );
+        assert!(format!("{:?}", form).contains("Form"));
+
+        let (size, data) = read_body(form.into_body()).await;
+        let expected = format!(
+            "--{b}\r\ncontent-disposition: form-data; name=\"name\"\r\n\r\nvalue\r\n\
+             --{b}\r\ncontent-disposition: form-data; name=\"file\"; filename=\"a\\\"b.txt\"\r\n\
+             content-type: text/plain\r\n\r\ndata\r\n--{b}--\r\n",
+            b = boundary
+        );
+        assert_eq!(data, Bytes::from(expected.clone()));
+        assert_eq!(size, BodySize::Sized(expected.len() as u64));
+    }
+
+    #[crate::rt_test]
+    async fn test_form_stream() {
+        let (tx, rx) = crate::channel::mpsc::channel::<Result<Bytes, std::io::Error>>();
+        tx.send(Ok(Bytes::from_static(b"chunk1"))).unwrap();
+        tx.send(Ok(Bytes::from_static(b"chunk2"))).unwrap();
+        drop(tx);
+
+        let form = Form::new().text("a", "b").part("s", Part::stream(rx));
+        let boundary = form.boundary().to_string();
+        let (size, data) = read_body(form.into_body()).await;
+        assert_eq!(size, BodySize::Stream);
+        assert_eq!(
+            data,
+            Bytes::from(format!(
+                "--{b}\r\ncontent-disposition: form-data; name=\"a\"\r\n\r\nb\r\n\
+                 --{b}\r\ncontent-disposition: form-data; name=\"s\"\r\n\r\n\
+                 chunk1chunk2\r\n--{b}--\r\n",
+                b = boundary
+            ))
+        );
+
+        let (tx, rx) = crate::channel::mpsc::channel::<Result<Bytes, std::io::Error>>();
+        tx.send(Ok(Bytes::from_static(b"data"))).unwrap();
+        drop(tx);
+        let form = Form::new().part("s", Part::sized_stream(rx, 4));
+        let (size, data) = read_body(form.into_body()).await;
+        assert_eq!(size, BodySize::Sized(data.len() as u64));
+    }
+}
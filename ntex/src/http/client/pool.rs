@@ -1,11 +1,12 @@
 use std::task::{Context, Poll};
 use std::time::{Duration, Instant};
-use std::{cell::RefCell, collections::VecDeque, future::Future, pin::Pin, rc::Rc};
+use std::{cell::RefCell, collections::VecDeque, fmt, future::Future, net, pin::Pin};
+use std::{rc::Rc, rc::Weak};
 
 use ntex_h2::{self as h2};
 
 use crate::http::uri::{Authority, Scheme, Uri};
-use crate::io::{types::HttpProtocol, IoBoxed};
+use crate::io::{types::HttpProtocol, DisconnectReason, IoBoxed, IoError};
 use crate::service::{Pipeline, PipelineCall, Service, ServiceCtx};
 use crate::time::{now, Seconds};
 use crate::util::{ready, ByteString, HashMap, HashSet};
@@ -17,11 +18,19 @@ use super::{error::ConnectError, h2proto::H2Client, Connect};
 #[derive(Hash, Eq, PartialEq, Clone, Debug)]
 pub(super) struct Key {
     authority: Authority,
+    addr: Option<net::SocketAddr>,
+    local_addr: Option<net::IpAddr>,
 }
 
-impl From<Authority> for Key {
-    fn from(authority: Authority) -> Key {
-        Key { authority }
+impl Key {
+    /// Connections to the same authority with different
+    /// addresses are kept separately
+    fn new(req: &Connect) -> Option<Key> {
+        req.uri.authority().map(|authority| Key {
+            authority: authority.clone(),
+            addr: req.addr,
+            local_addr: req.local_addr,
+        })
     }
 }
 
@@ -41,6 +50,130 @@ struct AvailableConnection {
     created: Instant,
 }
 
+/// Connection pool event
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum PoolEvent {
+    /// New connection is established
+    Connected {
+        authority: Authority,
+        protocol: HttpProtocol,
+    },
+    /// Connection is closed
+    Disconnected {
+        authority: Authority,
+        reason: Option<DisconnectReason>,
+    },
+}
+
+#[derive(Clone)]
+pub(super) struct PoolEventHandler(pub(super) Rc<dyn Fn(&PoolEvent)>);
+
+impl PoolEventHandler {
+    /// Notify about new connection and watch for disconnect
+    fn connected(&self, authority: &Authority, protocol: HttpProtocol, io: &IoBoxed) {
+        (self.0)(&PoolEvent::Connected {
+            authority: authority.clone(),
+            protocol,
+        });
+
+        let handler = self.clone();
+        let authority = authority.clone();
+        let mut on_disconnect = io.on_disconnect();
+        spawn(async move {
+            (&mut on_disconnect).await;
+            (handler.0)(&PoolEvent::Disconnected {
+                authority,
+                reason: on_disconnect.reason(),
+            });
+        });
+    }
+}
+
+impl fmt::Debug for PoolEventHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PoolEventHandler").finish()
+    }
+}
+
+/// Snapshot of connection pool status
+///
+/// Http/2 connections are counted as idle, single http/2 connection
+/// is shared between concurrent requests.
+#[derive(Clone, Debug, Default)]
+pub struct PoolStatus(HashMap<Authority, HostStatus>);
+
+/// Connection pool status for specific authority
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct HostStatus {
+    idle: usize,
+    active: usize,
+    connecting: bool,
+    waiters: usize,
+}
+
+impl PoolStatus {
+    /// Get status for specific authority
+    pub fn get(&self, authority: &Authority) -> Option<&HostStatus> {
+        self.0.get(authority)
+    }
+
+    /// Iterate over authorities statuses
+    pub fn iter(&self) -> impl Iterator<Item = (&Authority, &HostStatus)> {
+        self.0.iter()
+    }
+
+    /// Total number of idle connections
+    pub fn idle(&self) -> usize {
+        self.0.values().map(|st| st.idle).sum()
+    }
+
+    /// Total number of active connections
+    pub fn active(&self) -> usize {
+        self.0.values().map(|st| st.active).sum()
+    }
+
+    fn host(&mut self, key: &Key) -> &mut HostStatus {
+        self.0.entry(key.authority.clone()).or_default()
+    }
+}
+
+impl HostStatus {
+    /// Number of idle connections
+    pub fn idle(&self) -> usize {
+        self.idle
+    }
+
+    /// Number of connections in use
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    /// Check if new connection is being established
+    pub fn is_connecting(&self) -> bool {
+        self.connecting
+    }
+
+    /// Number of requests waiting for available connection
+    pub fn waiters(&self) -> usize {
+        self.waiters
+    }
+}
+
+/// Connections pool configuration
+#[derive(Clone, Debug)]
+pub(super) struct PoolConfig {
+    pub(super) conn_lifetime: Duration,
+    pub(super) conn_keep_alive: Duration,
+    pub(super) disconnect_timeout: Seconds,
+    pub(super) limit: usize,
+    pub(super) limit_per_host: usize,
+    pub(super) max_idle: usize,
+    pub(super) h2config: h2::Config,
+    pub(super) h2_prior_knowledge: bool,
+    pub(super) on_event: Option<PoolEventHandler>,
+}
+
 /// Connections pool
 #[derive(Debug)]
 pub(super) struct ConnectionPool<T> {
@@ -53,26 +186,24 @@ impl<T> ConnectionPool<T>
 where
     T: Service<Connect, Response = IoBoxed, Error = ConnectError> + 'static,
 {
-    pub(super) fn new(
-        connector: T,
-        conn_lifetime: Duration,
-        conn_keep_alive: Duration,
-        disconnect_timeout: Seconds,
-        limit: usize,
-        h2config: h2::Config,
-    ) -> Self {
+    pub(super) fn new(connector: T, config: PoolConfig) -> Self {
         let connector = Pipeline::new(connector);
         let waiters = Rc::new(RefCell::new(Waiters {
             waiters: HashMap::default(),
             pool: pool::new(),
         }));
         let inner = Rc::new(RefCell::new(Inner {
-            conn_lifetime,
-            conn_keep_alive,
-            disconnect_timeout,
-            limit,
-            h2config,
+            conn_lifetime: config.conn_lifetime,
+            conn_keep_alive: config.conn_keep_alive,
+            disconnect_timeout: config.disconnect_timeout,
+            limit: config.limit,
+            limit_per_host: config.limit_per_host,
+            max_idle: config.max_idle,
+            h2config: config.h2config,
+            h2_prior_knowledge: config.h2_prior_knowledge,
+            on_event: config.on_event,
             acquired: 0,
+            acquired_per_host: HashMap::default(),
             available: HashMap::default(),
             connecting: HashSet::default(),
             waker: LocalWaker::new(),
@@ -129,8 +260,8 @@ where
         let inner = self.inner.clone();
         let waiters = self.waiters.clone();
 
-        let key = if let Some(authority) = req.uri.authority() {
-            authority.clone().into()
+        let key = if let Some(key) = Key::new(&req) {
+            key
         } else {
             return Err(ConnectError::Unresolved);
         };
@@ -167,7 +298,9 @@ where
                 );
                 let rx = waiters.borrow_mut().wait_for(req);
                 match rx.await {
-                    Err(_) => Err(ConnectError::Disconnected(None)),
+                    Err(_) => Err(ConnectError::Disconnected(Some(
+                        IoError::pool_exhausted().into(),
+                    ))),
                     Ok(res) => res,
                 }
             }
@@ -181,14 +314,68 @@ pub(super) struct Inner {
     conn_keep_alive: Duration,
     disconnect_timeout: Seconds,
     limit: usize,
+    limit_per_host: usize,
+    max_idle: usize,
     h2config: h2::Config,
+    h2_prior_knowledge: bool,
+    on_event: Option<PoolEventHandler>,
     acquired: usize,
+    acquired_per_host: HashMap<Key, usize>,
     available: HashMap<Key, VecDeque<AvailableConnection>>,
     connecting: HashSet<Key>,
     waker: LocalWaker,
     waiters: Rc<RefCell<Waiters>>,
 }
 
+impl Inner {
+    /// Add pool status to the snapshot
+    fn status(&self, status: &mut PoolStatus) {
+        for (key, conns) in &self.available {
+            status.host(key).idle += conns.len();
+        }
+        for (key, num) in &self.acquired_per_host {
+            status.host(key).active += num;
+        }
+        for key in &self.connecting {
+            status.host(key).connecting = true;
+        }
+        for (key, waiters) in &self.waiters.borrow().waiters {
+            status.host(key).waiters += waiters.len();
+        }
+    }
+}
+
+/// Pools that provided connections
+///
+/// Pools are discovered from acquired connections, so status is available
+/// for any connector service that returns pooled connections.
+#[derive(Default)]
+pub(super) struct PoolTracker(RefCell<Vec<Weak<RefCell<Inner>>>>);
+
+impl PoolTracker {
+    /// Track pool of the connection
+    pub(super) fn track(&self, conn: &Connection) {
+        if let Some(Acquired(_, Some(ref inner))) = conn.pool() {
+            let mut pools = self.0.borrow_mut();
+            if !pools.iter().any(|p| p.as_ptr() == Rc::as_ptr(inner)) {
+                pools.retain(|p| p.strong_count() > 0);
+                pools.push(Rc::downgrade(inner));
+            }
+        }
+    }
+
+    /// Snapshot of tracked pools status
+    pub(super) fn status(&self) -> PoolStatus {
+        let mut status = PoolStatus::default();
+        for pool in self.0.borrow().iter() {
+            if let Some(inner) = pool.upgrade() {
+                inner.borrow().status(&mut status);
+            }
+        }
+        status
+    }
+}
+
 #[derive(Debug)]
 struct Waiters {
     waiters: HashMap<Key, VecDeque<(Connect, Waiter)>>,
@@ -199,7 +386,7 @@ impl Waiters {
     /// connection is not available, wait
     fn wait_for(&mut self, connect: Connect) -> WaiterReceiver {
         let (tx, rx) = self.pool.channel();
-        let key: Key = connect.uri.authority().unwrap().clone().into();
+        let key = Key::new(&connect).unwrap();
         self.waiters
             .entry(key)
             .or_default()
@@ -241,6 +428,11 @@ impl Inner {
         if self.limit > 0 && self.acquired >= self.limit {
             return Acquire::NotAvailable;
         }
+        if self.limit_per_host > 0
+            && self.acquired_per_host.get(key).copied().unwrap_or(0) >= self.limit_per_host
+        {
+            return Acquire::NotAvailable;
+        }
 
         // check if open connection is available
         // cleanup stale connections at the same time
@@ -252,9 +444,7 @@ impl Inner {
                     || (now - conn.created) > self.conn_lifetime
                 {
                     if let ConnectionType::H1(io) = conn.io {
-                        spawn(async move {
-                            let _ = io.shutdown().await;
-                        });
+                        close_connection(ConnectionType::H1(io));
                     }
                     continue;
                 }
@@ -303,10 +493,36 @@ impl Inner {
     fn check_availibility(&mut self) {
         let mut waiters = self.waiters.borrow_mut();
         waiters.cleanup();
-        if !waiters.waiters.is_empty() && self.acquired < self.limit {
+        if !waiters.waiters.is_empty() && (self.limit == 0 || self.acquired < self.limit) {
             self.waker.wake();
         }
     }
+
+    fn add_acquired(&mut self, key: &Key) {
+        self.acquired += 1;
+        *self.acquired_per_host.entry(key.clone()).or_default() += 1;
+    }
+
+    fn remove_acquired(&mut self, key: &Key) {
+        self.acquired -= 1;
+        if let Some(num) = self.acquired_per_host.get_mut(key) {
+            *num -= 1;
+            if *num == 0 {
+                self.acquired_per_host.remove(key);
+            }
+        }
+    }
+}
+
+fn close_connection(io: ConnectionType) {
+    match io {
+        ConnectionType::H1(io) => {
+            spawn(async move {
+                let _ = io.shutdown().await;
+            });
+        }
+        ConnectionType::H2(io) => io.close(),
+    }
 }
 
 struct ConnectionPoolSupport<T> {
@@ -460,13 +676,26 @@ where
             Ok(io) => {
                 io.set_disconnect_timeout(*this.disconnect_timeout);
 
+                let h2 = this.inner.borrow().h2_prior_knowledge
+                    || io.query::<HttpProtocol>().get() == Some(HttpProtocol::Http2);
+                let on_event = this.inner.borrow().on_event.clone();
+                if let Some(on_event) = on_event {
+                    let protocol = if h2 {
+                        HttpProtocol::Http2
+                    } else {
+                        HttpProtocol::Http1
+                    };
+                    on_event.connected(&this.key.authority, protocol, &io);
+                }
+
                 // handle http2 proto
-                if io.query::<HttpProtocol>().get() == Some(HttpProtocol::Http2) {
+                if h2 {
                     // init http2 handshake
                     log::trace!(
                         "Connection for {:?} is established, start http2 handshake",
                         &this.key.authority
                     );
+                    let peer_cert = super::connection::peer_cert(&io);
                     let auth = if let Some(auth) = this.uri.authority() {
                         format!("{}", auth).into()
                     } else {
@@ -479,7 +708,7 @@ where
                         this.uri.scheme().cloned().unwrap_or(Scheme::HTTPS),
                         auth,
                     );
-                    let client = H2Client::new(client);
+                    let client = H2Client::new(client, peer_cert);
                     let guard = this.guard.take().unwrap().consume();
                     let conn = Connection::new(
                         ConnectionType::H2(client.clone()),
@@ -495,8 +724,11 @@ where
                     }
 
                     // put h2 connection to list of available connections
-                    Connection::new(ConnectionType::H2(client), now(), Some(guard))
-                        .release(false);
+                    let mut guard = guard;
+                    guard.release(
+                        Connection::new(ConnectionType::H2(client), now(), None),
+                        false,
+                    );
 
                     Poll::Ready(())
                 } else {
@@ -556,7 +788,7 @@ pub(super) struct Acquired(Key, Option<Rc<RefCell<Inner>>>);
 
 impl Acquired {
     fn new(key: Key, inner: Rc<RefCell<Inner>>) -> Self {
-        inner.borrow_mut().acquired += 1;
+        inner.borrow_mut().add_acquired(&key);
         Acquired(key, Some(inner))
     }
 
@@ -568,31 +800,33 @@ impl Acquired {
         if let Some(inner) = self.1.take() {
             let (io, created, _) = conn.into_inner();
             let mut inner = inner.borrow_mut();
-            inner.acquired -= 1;
+            inner.remove_acquired(&self.0);
             if close {
                 log::trace!(
                     "Releasing and closing connection for {:?}",
                     self.0.authority
                 );
-                match io {
-                    ConnectionType::H1(io) => {
-                        spawn(async move {
-                            let _ = io.shutdown().await;
-                        });
-                    }
-                    ConnectionType::H2(io) => io.close(),
-                }
+                close_connection(io);
             } else {
                 log::trace!("Releasing connection for {:?}", self.0.authority);
-                inner
+                let max_idle = inner.max_idle;
+                let conns = inner
                     .available
                     .entry(self.0.clone())
-                    .or_insert_with(VecDeque::new)
-                    .push_back(AvailableConnection {
-                        io,
-                        created,
-                        used: now(),
-                    });
+                    .or_insert_with(VecDeque::new);
+                conns.push_back(AvailableConnection {
+                    io,
+                    created,
+                    used: now(),
+                });
+
+                // close least recently used connection
+                if max_idle > 0 && conns.len() > max_idle {
+                    if let Some(conn) = conns.pop_front() {
+                        log::trace!("Too many idle connections for {:?}", self.0.authority);
+                        close_connection(conn.io);
+                    }
+                }
             }
             inner.check_availibility();
         }
@@ -603,7 +837,7 @@ impl Drop for Acquired {
     fn drop(&mut self) {
         if let Some(inner) = self.1.take() {
             let mut inner = inner.borrow_mut();
-            inner.acquired -= 1;
+            inner.remove_acquired(&self.0);
             inner.check_availibility();
         }
     }
@@ -615,6 +849,20 @@ mod tests {
     use crate::time::{sleep, Millis};
     use crate::{io as nio, service::fn_service, testing::Io, util::lazy};
 
+    fn config(limit: usize) -> PoolConfig {
+        PoolConfig {
+            conn_lifetime: Duration::from_secs(10),
+            conn_keep_alive: Duration::from_secs(10),
+            disconnect_timeout: Seconds::ZERO,
+            limit,
+            limit_per_host: 0,
+            max_idle: 0,
+            h2config: h2::Config::client(),
+            h2_prior_knowledge: false,
+            on_event: None,
+        }
+    }
+
     #[crate::rt_test]
     async fn test_basics() {
         let store = Rc::new(RefCell::new(Vec::new()));
@@ -627,11 +875,7 @@ mod tests {
                     store2.borrow_mut().push((req, server));
                     Box::pin(async move { Ok(IoBoxed::from(nio::Io::new(client))) })
                 }),
-                Duration::from_secs(10),
-                Duration::from_secs(10),
-                Seconds::ZERO,
-                1,
-                h2::Config::client(),
+                config(1),
             )
             .clone(),
         );
@@ -640,6 +884,8 @@ mod tests {
         let req = Connect {
             uri: Uri::try_from("/test").unwrap(),
             addr: None,
+            verify: Default::default(),
+            local_addr: None,
         };
         match pool.call(req).await {
             Err(ConnectError::Unresolved) => (),
@@ -650,6 +896,8 @@ mod tests {
         let req = Connect {
             uri: Uri::try_from("http://localhost/test").unwrap(),
             addr: None,
+            verify: Default::default(),
+            local_addr: None,
         };
         let conn = pool.call(req.clone()).await.unwrap();
         assert_eq!(store.borrow().len(), 1);
@@ -703,6 +951,8 @@ mod tests {
         let req = Connect {
             uri: Uri::try_from("http://localhost2/test").unwrap(),
             addr: None,
+            verify: Default::default(),
+            local_addr: None,
         };
         let mut fut = std::pin::pin!(pool.call(req.clone()));
         assert!(lazy(|cx| fut.as_mut().poll(cx)).await.is_pending());
@@ -723,4 +973,133 @@ mod tests {
         assert!(lazy(|cx| pool.poll_ready(cx)).await.is_ready());
         assert!(lazy(|cx| pool.poll_shutdown(cx)).await.is_ready());
     }
+
+    #[crate::rt_test]
+    async fn test_pool_exhausted() {
+        let pool = Pipeline::new(ConnectionPool::new(
+            fn_service(move |_| {
+                let (client, _) = Io::create();
+                Box::pin(async move { Ok(IoBoxed::from(nio::Io::new(client))) })
+            }),
+            config(1),
+        ));
+        let req = Connect {
+            uri: Uri::try_from("http://localhost/test").unwrap(),
+            addr: None,
+            verify: Default::default(),
+            local_addr: None,
+        };
+        let _conn = pool.call(req.clone()).await.unwrap();
+
+        // pool is full, waiter is dropped by pool
+        let mut fut = std::pin::pin!(pool.call(req.clone()));
+        assert!(lazy(|cx| fut.as_mut().poll(cx)).await.is_pending());
+        pool.get_ref().waiters.borrow_mut().waiters.clear();
+
+        match fut.await {
+            Err(ConnectError::Disconnected(Some(err))) => {
+                assert_eq!(
+                    crate::io::IoErrorKind::classify(&err),
+                    crate::io::IoErrorKind::PoolExhausted
+                );
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[crate::rt_test]
+    async fn test_status() {
+        let store = Rc::new(RefCell::new(Vec::new()));
+        let store2 = store.clone();
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let events2 = events.clone();
+
+        let pool = Pipeline::new(ConnectionPool::new(
+            fn_service(move |_| {
+                let (client, server) = Io::create();
+                store2.borrow_mut().push(server);
+                Box::pin(async move { Ok(IoBoxed::from(nio::Io::new(client))) })
+            }),
+            PoolConfig {
+                limit_per_host: 2,
+                max_idle: 1,
+                on_event: Some(PoolEventHandler(Rc::new(move |ev: &PoolEvent| {
+                    events2.borrow_mut().push(ev.clone())
+                }))),
+                ..config(0)
+            },
+        ));
+        let status = || {
+            let mut status = PoolStatus::default();
+            pool.get_ref().inner.borrow().status(&mut status);
+            status
+        };
+
+        let req = Connect {
+            uri: Uri::try_from("http://localhost/test").unwrap(),
+            addr: None,
+            verify: Default::default(),
+            local_addr: None,
+        };
+        let authority = req.uri.authority().unwrap().clone();
+        let conn1 = pool.call(req.clone()).await.unwrap();
+        let conn2 = pool.call(req.clone()).await.unwrap();
+        assert_eq!(store.borrow().len(), 2);
+
+        // per host limit is reached
+        let mut fut = std::pin::pin!(pool.call(req.clone()));
+        assert!(lazy(|cx| fut.as_mut().poll(cx)).await.is_pending());
+
+        let st = status();
+        let host = st.get(&authority).unwrap();
+        assert_eq!(host.active(), 2);
+        assert_eq!(host.idle(), 0);
+        assert_eq!(host.waiters(), 1);
+        assert!(!host.is_connecting());
+        assert_eq!(st.active(), 2);
+
+        // other hosts are not affected
+        let req2 = Connect {
+            uri: Uri::try_from("http://localhost2/test").unwrap(),
+            addr: None,
+            verify: Default::default(),
+            local_addr: None,
+        };
+        let conn4 = pool.call(req2).await.unwrap();
+        assert_eq!(store.borrow().len(), 3);
+        conn4.release(true);
+
+        conn1.release(false);
+        let conn3 = fut.await.unwrap();
+        assert_eq!(store.borrow().len(), 3);
+
+        // only one idle connection is kept
+        conn2.release(false);
+        conn3.release(false);
+        let st = status();
+        assert_eq!(st.get(&authority).unwrap().idle(), 1);
+        assert_eq!(st.idle(), 1);
+        assert_eq!(st.active(), 0);
+
+        // peer closes idle connection
+        let io = store.borrow()[0].clone();
+        io.close().await;
+        sleep(Millis(50)).await;
+
+        let events = events.borrow();
+        assert_eq!(
+            events
+                .iter()
+                .filter(|ev| matches!(ev, PoolEvent::Connected { .. }))
+                .count(),
+            3
+        );
+        assert!(events.iter().any(|ev| matches!(
+            ev,
+            PoolEvent::Disconnected {
+                reason: Some(DisconnectReason::PeerClosed),
+                ..
+            }
+        )));
+    }
 }
@@ -0,0 +1,127 @@
+use std::io::{self, Read};
+use std::{fmt, future::Future, pin::Pin, task::Context, task::Poll};
+
+use crate::rt::spawn_blocking;
+use crate::util::{Bytes, BytesMut, Stream};
+
+/// Default size of chunks read from reader
+const CHUNK_SIZE: usize = 65_536;
+
+/// Stream of chunks read from blocking reader
+///
+/// Reader is used on blocking threads pool, next chunk is read only
+/// after previous one is consumed, so large files could be sent
+/// without buffering them in memory.
+///
+/// ```rust
+/// use ntex::http::client::{Client, ReaderStream};
+///
+/// #[ntex::main]
+/// async fn main() {
+///     let data = std::io::Cursor::new(b"data".to_vec());
+///
+///     let res = Client::new()
+///         .post("http://www.rust-lang.org")
+///         .send_stream(ReaderStream::new(data).chunk_size(1024))
+///         .await;
+/// }
+/// ```
+pub struct ReaderStream<R> {
+    reader: Option<(R, BytesMut)>,
+    fut: Option<Pin<Box<dyn Future<Output = Option<ReadResult<R>>>>>>,
+    chunk_size: usize,
+}
+
+type ReadResult<R> = (R, BytesMut, io::Result<Bytes>);
+
+impl<R> ReaderStream<R>
+where
+    R: Read + Send + 'static,
+{
+    /// Create stream from reader
+    pub fn new(reader: R) -> Self {
+        ReaderStream {
+            reader: Some((reader, BytesMut::new())),
+            fut: None,
+            chunk_size: CHUNK_SIZE,
+        }
+    }
+
+    /// Set max size of chunks
+    ///
+    /// By default chunk size is 64Kb.
+    pub fn chunk_size(mut self, size: usize) -> Self {
+        self.chunk_size = size.max(1);
+        self
+    }
+}
+
+impl<R> Unpin for ReaderStream<R> {}
+
+impl<R> fmt::Debug for ReaderStream<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReaderStream")
+            .field("chunk_size", &self.chunk_size)
+            .finish()
+    }
+}
+
+impl<R> Stream for ReaderStream<R>
+where
+    R: Read + Send + 'static,
+{
+    type Item = Result<Bytes, io::Error>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.as_mut().get_mut();
+
+        if let Some(ref mut fut) = this.fut {
+            let (reader, buf, result) = match fut.as_mut().poll(cx) {
+                Poll::Ready(Some(item)) => item,
+                Poll::Ready(None) => {
+                    this.fut = None;
+                    return Poll::Ready(Some(Err(io::Error::other("Canceled"))));
+                }
+                Poll::Pending => return Poll::Pending,
+            };
+            this.fut = None;
+
+            // empty chunk is eof, reader is dropped on eof and errors
+            return match result {
+                Ok(chunk) if chunk.is_empty() => Poll::Ready(None),
+                Ok(chunk) => {
+                    this.reader = Some((reader, buf));
+                    Poll::Ready(Some(Ok(chunk)))
+                }
+                Err(e) => Poll::Ready(Some(Err(e))),
+            };
+        }
+
+        if let Some((mut reader, mut buf)) = this.reader.take() {
+            let size = this.chunk_size;
+            let fut = spawn_blocking(move || {
+                // unused part of buffer is reused for next chunk
+                if buf.capacity() < size {
+                    buf.reserve(size);
+                }
+                buf.resize(size, 0);
+                let result = loop {
+                    match reader.read(&mut buf) {
+                        Ok(n) => break Ok(buf.split_to(n).freeze()),
+                        Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                        Err(e) => break Err(e),
+                    }
+                };
+                buf.clear();
+                (reader, buf, result)
+            });
+            this.fut = Some(Box::pin(async move { fut.await.ok() }));
+            self.poll_next(cx)
+        } else {
+            Poll::Ready(None)
+        }
+    }
+}
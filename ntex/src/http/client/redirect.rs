@@ -0,0 +1,326 @@
+use std::{rc::Rc, time::Duration, time::Instant};
+
+use crate::http::body::Body;
+use crate::http::header::{self, HeaderMap};
+use crate::http::{Method, Payload, RequestHead, RequestHeadType, StatusCode, Uri};
+use crate::time::{now, timeout_checked, Millis};
+use crate::util::stream_recv;
+
+use super::error::SendRequestError;
+use super::sender::SendOptions;
+use super::{ClientConfig, ClientResponse};
+
+/// Send request and follow redirects.
+///
+/// Redirects are followed according to rfc7231. `301` and `302` responses
+/// turn `POST` requests into `GET`, `303` response turns any request except
+/// `HEAD` into `GET`. Method and body are preserved otherwise, if body cannot
+/// be re-sent redirect response is returned as is. If client has cookie store,
+/// cookies are stored and attached on every hop. Request timeout applies
+/// to the whole redirect chain.
+pub(super) async fn send_request(
+    head: RequestHeadType,
+    mut body: Body,
+    mut opts: SendOptions,
+    config: Rc<ClientConfig>,
+) -> Result<ClientResponse, SendRequestError> {
+    if config.max_redirects == 0 && !has_cookie_store(&config) {
+        return config
+            .connector
+            .send_request(head, body, opts, config.clone())
+            .await;
+    }
+
+    // keep request head, so it could be re-used for next request
+    let (mut prev, mut extra_headers) = match head {
+        RequestHeadType::Owned(head) => (Rc::new(head), None),
+        RequestHeadType::Rc(head, extra) => (head, extra),
+    };
+    let mut redirects = Vec::new();
+    let deadline = if opts.timeout.is_zero() {
+        None
+    } else {
+        Some(now() + Duration::from(opts.timeout))
+    };
+
+    loop {
+        let replay = match body {
+            Body::None => Some(Body::None),
+            Body::Empty => Some(Body::Empty),
+            Body::Bytes(ref bytes) => Some(Body::Bytes(bytes.clone())),
+            Body::Message(_) => None,
+        };
+
+        opts.timeout = remaining(deadline)?;
+        let mut res = config
+            .connector
+            .send_request(
+                RequestHeadType::Rc(
+                    prev.clone(),
+                    request_headers(&prev, &extra_headers, &config),
+                ),
+                body,
+                opts.clone(),
+                config.clone(),
+            )
+            .await?;
+        #[cfg(feature = "cookie")]
+        if let Some(ref store) = config.cookie_store {
+            store.set_cookies(&prev.uri, res.headers());
+        }
+
+        let uri = match location(&res, &prev.uri) {
+            Some(uri) => uri,
+            None => {
+                res.redirects = redirects;
+                return Ok(res);
+            }
+        };
+        if config.max_redirects == 0 {
+            return Ok(res);
+        }
+        if redirects.len() >= config.max_redirects {
+            return Err(SendRequestError::TooManyRedirects);
+        }
+
+        let status = res.status();
+
+        // read rest of redirect response, so connection could be re-used
+        drain(res.take_payload(), remaining(deadline)?).await;
+
+        let (method, next_body) = if (status == StatusCode::SEE_OTHER
+            && prev.method != Method::HEAD)
+            || ((status == StatusCode::MOVED_PERMANENTLY || status == StatusCode::FOUND)
+                && prev.method == Method::POST)
+        {
+            (Method::GET, None)
+        } else if let Some(body) = replay {
+            (prev.method.clone(), Some(body))
+        } else {
+            // body stream is consumed
+            res.redirects = redirects;
+            return Ok(res);
+        };
+
+        let mut headers = prev.headers.clone();
+        if let Some(extra) = extra_headers.take() {
+            for (key, value) in extra.iter() {
+                headers.insert(key.clone(), value.clone());
+            }
+        }
+        if next_body.is_none() {
+            remove(
+                &mut headers,
+                &[
+                    header::CONTENT_TYPE,
+                    header::CONTENT_LENGTH,
+                    header::CONTENT_ENCODING,
+                    header::TRANSFER_ENCODING,
+                ],
+            );
+        }
+        if !same_origin(&prev.uri, &uri) {
+            remove(
+                &mut headers,
+                &[
+                    header::AUTHORIZATION,
+                    header::PROXY_AUTHORIZATION,
+                    header::COOKIE,
+                    header::HOST,
+                ],
+            );
+        }
+
+        let mut next = RequestHead {
+            uri,
+            method,
+            headers,
+            version: prev.version,
+            ..Default::default()
+        };
+        next.no_chunking(!prev.chunked());
+        next.set_absolute_form(prev.absolute_form());
+        next.set_preserve_header_case(prev.preserve_header_case());
+
+        log::trace!(
+            "Following {} redirect from {} to {}",
+            status,
+            prev.uri,
+            next.uri
+        );
+        redirects.push(prev.uri.clone());
+        prev = Rc::new(next);
+        body = next_body.unwrap_or(Body::None);
+    }
+}
+
+/// Time left until deadline, zero means no timeout
+fn remaining(deadline: Option<Instant>) -> Result<Millis, SendRequestError> {
+    if let Some(deadline) = deadline {
+        let timeout = Millis::from(deadline.saturating_duration_since(now()));
+        if timeout.is_zero() {
+            Err(SendRequestError::Timeout)
+        } else {
+            Ok(timeout)
+        }
+    } else {
+        Ok(Millis::ZERO)
+    }
+}
+
+/// Max size of redirect response body that is read before next hop
+const MAX_DRAIN_SIZE: usize = 65_536;
+
+/// Read and discard redirect response payload.
+///
+/// Payload that is larger than `MAX_DRAIN_SIZE` or fails is dropped,
+/// in that case connection is not re-used.
+async fn drain(mut payload: Payload, timeout: Millis) {
+    let _ = timeout_checked(timeout, async move {
+        let mut size = 0;
+        while let Some(Ok(chunk)) = stream_recv(&mut payload).await {
+            size += chunk.len();
+            if size > MAX_DRAIN_SIZE {
+                break;
+            }
+        }
+    })
+    .await;
+}
+
+fn has_cookie_store(_config: &ClientConfig) -> bool {
+    #[cfg(feature = "cookie")]
+    {
+        _config.cookie_store.is_some()
+    }
+    #[cfg(not(feature = "cookie"))]
+    {
+        false
+    }
+}
+
+/// Extra headers for request, adds cookies from cookie store
+fn request_headers(
+    _head: &RequestHead,
+    extra: &Option<HeaderMap>,
+    _config: &ClientConfig,
+) -> Option<HeaderMap> {
+    #[cfg(feature = "cookie")]
+    if let Some(ref store) = _config.cookie_store {
+        let current = extra
+            .as_ref()
+            .and_then(|h| h.get(&header::COOKIE))
+            .or_else(|| _head.headers.get(&header::COOKIE))
+            .and_then(|v| v.to_str().ok());
+        if let Some(value) = store.header_value(&_head.uri, current) {
+            let mut headers = extra.clone().unwrap_or_default();
+            headers.insert(header::COOKIE, value);
+            return Some(headers);
+        }
+    }
+    extra.clone()
+}
+
+/// Get redirect location for response
+fn location(res: &ClientResponse, base: &Uri) -> Option<Uri> {
+    match res.status() {
+        StatusCode::MOVED_PERMANENTLY
+        | StatusCode::FOUND
+        | StatusCode::SEE_OTHER
+        | StatusCode::TEMPORARY_REDIRECT
+        | StatusCode::PERMANENT_REDIRECT => (),
+        _ => return None,
+    }
+    let location = res.headers().get(&header::LOCATION)?.to_str().ok()?;
+    resolve(base, location)
+}
+
+/// Resolve location relative to base uri
+fn resolve(base: &Uri, location: &str) -> Option<Uri> {
+    let location = location.split('#').next().unwrap_or_default();
+    let scheme = base.scheme_str().unwrap_or("http");
+    let authority = base.authority()?.as_str();
+
+    let uri = if location.starts_with("//") {
+        format!("{}:{}", scheme, location).parse::<Uri>().ok()?
+    } else if location.starts_with('/') {
+        format!("{}://{}{}", scheme, authority, location)
+            .parse::<Uri>()
+            .ok()?
+    } else if location.contains("://") {
+        location.parse::<Uri>().ok()?
+    } else {
+        let path = base.path();
+        let dir = &path[..path.rfind('/').map(|idx| idx + 1).unwrap_or(0)];
+        format!("{}://{}{}{}", scheme, authority, dir, location)
+            .parse::<Uri>()
+            .ok()?
+    };
+
+    match uri.scheme_str() {
+        Some("http") | Some("https") if uri.host().is_some() => Some(uri),
+        _ => None,
+    }
+}
+
+fn same_origin(a: &Uri, b: &Uri) -> bool {
+    a.scheme() == b.scheme() && a.host() == b.host() && port(a) == port(b)
+}
+
+fn port(uri: &Uri) -> Option<u16> {
+    uri.port_u16().or_else(|| match uri.scheme_str() {
+        Some("http") | Some("ws") => Some(80),
+        Some("https") | Some("wss") => Some(443),
+        _ => None,
+    })
+}
+
+fn remove(headers: &mut HeaderMap, names: &[header::HeaderName]) {
+    for name in names {
+        headers.remove(name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let base = Uri::from_static("http://localhost:8080/a/b?q=1");
+        assert_eq!(
+            resolve(&base, "/c").unwrap(),
+            Uri::from_static("http://localhost:8080/c")
+        );
+        assert_eq!(
+            resolve(&base, "c?x=1#frag").unwrap(),
+            Uri::from_static("http://localhost:8080/a/c?x=1")
+        );
+        assert_eq!(
+            resolve(&base, "//example.com/d").unwrap(),
+            Uri::from_static("http://example.com/d")
+        );
+        assert_eq!(
+            resolve(&base, "https://example.com/e").unwrap(),
+            Uri::from_static("https://example.com/e")
+        );
+        assert!(resolve(&base, "ftp://example.com/f").is_none());
+
+        assert!(same_origin(
+            &base,
+            &Uri::from_static("http://localhost:8080/x")
+        ));
+        assert!(same_origin(
+            &Uri::from_static("https://example.com/"),
+            &Uri::from_static("https://example.com:443/x")
+        ));
+        assert!(!same_origin(
+            &base,
+            &Uri::from_static("http://localhost:8081/x")
+        ));
+        assert!(!same_origin(
+            &base,
+            &Uri::from_static("https://localhost:8080/x")
+        ));
+    }
+}
@@ -1,4 +1,4 @@
-use std::{error::Error, fmt, net, rc::Rc};
+use std::{error::Error, fmt, io, net, rc::Rc};
 
 use base64::{engine::general_purpose::STANDARD as base64, Engine};
 #[cfg(feature = "cookie")]
@@ -9,18 +9,28 @@ use crate::http::body::Body;
 use crate::http::error::HttpError;
 use crate::http::header::{self, HeaderMap, HeaderName, HeaderValue};
 use crate::http::{
-    uri, ConnectionType, Method, RequestHead, RequestHeadType, Uri, Version,
+    uri, ConnectionType, Method, RequestHead, RequestHeadType, ResponseHead, Uri, Version,
 };
 use crate::{time::Millis, util::Bytes, util::Stream};
 
 use super::error::{FreezeRequestError, InvalidUrl};
-use super::sender::{PrepForSendingError, SendClientRequest};
+use super::multipart::Form;
+use super::reader::ReaderStream;
+use super::sender::{Informational, PrepForSendingError, SendClientRequest, SendOptions};
 use super::{frozen::FrozenClientRequest, ClientConfig};
 
+#[cfg(all(feature = "dangerous", any(feature = "openssl", feature = "rustls")))]
+use super::connector::CertVerify;
+
+#[cfg(feature = "compress")]
+use crate::http::header::ContentEncoding;
+
 #[cfg(feature = "compress")]
-const HTTPS_ENCODING: &str = "br, gzip, deflate";
+const HTTPS_ENCODING: &str = "br, gzip, deflate, zstd";
 #[cfg(not(feature = "compress"))]
 const HTTPS_ENCODING: &str = "br";
+#[cfg(feature = "compress")]
+const HTTP_ENCODING: &str = "gzip, deflate, zstd";
 
 /// An HTTP Client request builder
 ///
@@ -47,11 +57,9 @@ const HTTPS_ENCODING: &str = "br";
 pub struct ClientRequest {
     pub(crate) head: RequestHead,
     err: Option<HttpError>,
-    addr: Option<net::SocketAddr>,
     #[cfg(feature = "cookie")]
     cookies: Option<CookieJar>,
-    response_decompress: bool,
-    timeout: Millis,
+    opts: SendOptions,
     config: Rc<ClientConfig>,
 }
 
@@ -62,15 +70,21 @@ impl ClientRequest {
         Uri: TryFrom<U>,
         <Uri as TryFrom<U>>::Error: Into<HttpError>,
     {
+        let opts = SendOptions {
+            response_decompress: config.response_decompress,
+            ..Default::default()
+        };
+
+        let mut head = RequestHead::default();
+        head.set_preserve_header_case(config.preserve_header_case);
+
         ClientRequest {
             config,
-            head: RequestHead::default(),
+            head,
             err: None,
-            addr: None,
             #[cfg(feature = "cookie")]
             cookies: None,
-            timeout: Millis::ZERO,
-            response_decompress: true,
+            opts,
         }
         .method(method)
         .uri(uri)
@@ -100,10 +114,45 @@ impl ClientRequest {
     /// This address is used for connection. If address is not
     /// provided url's host name get resolved.
     pub fn address(mut self, addr: net::SocketAddr) -> Self {
-        self.addr = Some(addr);
+        self.opts.addr = Some(addr);
+        self
+    }
+
+    /// Send request target in absolute form.
+    ///
+    /// Request line contains full uri, `GET http://host/path HTTP/1.1`,
+    /// it is required by forward proxies. Use [`ClientRequest::address()`]
+    /// to connect to a proxy. Applies to http/1 connections only.
+    pub fn absolute_form(mut self) -> Self {
+        self.head.set_absolute_form(true);
+        self
+    }
+
+    /// Preserve original casing of header names.
+    ///
+    /// Original casing of response header names is stored in response's
+    /// `HeaderMap`. Request header names with recorded casing are always
+    /// sent verbatim, [`Client::request_from()`](super::Client::request_from)
+    /// keeps casing of the source request. Applies to http/1 connections only.
+    ///
+    /// By default client's setting is used.
+    pub fn preserve_header_case(mut self, enabled: bool) -> Self {
+        self.head.set_preserve_header_case(enabled);
         self
     }
 
+    /// Override `Host` header.
+    ///
+    /// By default `Host` header is set from request's uri. Connection
+    /// authority does not change. Applies to http/1 connections only.
+    pub fn host<V>(self, host: V) -> Self
+    where
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<HttpError>,
+    {
+        self.set_header(header::HOST, host)
+    }
+
     /// Set HTTP method of this request.
     #[inline]
     pub fn method(mut self, method: Method) -> Self {
@@ -232,6 +281,22 @@ impl ClientRequest {
         self
     }
 
+    /// Send request as HTTP/1.0 request.
+    ///
+    /// Chunked transfer encoding is not used and connection is closed
+    /// after response, it is not returned back to connections pool. Streaming
+    /// body requires `Content-Length` header, otherwise request fails with
+    /// `SendRequestError::Send` error.
+    /// Compatibility mode for legacy servers, i.e. embedded devices.
+    /// This setting affect only http/1 connections.
+    #[inline]
+    pub fn http10(mut self) -> Self {
+        self.head.version = Version::HTTP_10;
+        self.head.no_chunking(true);
+        self.head.set_connection_type(ConnectionType::Close);
+        self
+    }
+
     /// Set request's content type
     #[inline]
     pub fn content_type<V>(mut self, value: V) -> Self
@@ -314,16 +379,151 @@ impl ClientRequest {
 
     /// Disable automatic decompress of response's body
     pub fn no_decompress(mut self) -> Self {
-        self.response_decompress = false;
+        self.opts.response_decompress = false;
+        self
+    }
+
+    #[cfg(feature = "compress")]
+    /// Compress request's body with provided encoding.
+    ///
+    /// Body is compressed on the fly and `Content-Encoding` header is set.
+    /// Bodies smaller than 1Kb and bodies with explicitly set
+    /// `Content-Length` or `Content-Encoding` headers are sent as is.
+    ///
+    /// ```rust
+    /// use ntex::http::{client::Client, header::ContentEncoding};
+    ///
+    /// #[ntex::main]
+    /// async fn main() {
+    ///     let resp = Client::new()
+    ///         .post("http://www.rust-lang.org")
+    ///         .compress(ContentEncoding::Zstd)
+    ///         .send_body("...")
+    ///         .await;
+    /// }
+    /// ```
+    pub fn compress(mut self, encoding: ContentEncoding) -> Self {
+        self.opts.compress = Some(encoding);
         self
     }
 
     /// Set request timeout in millis. Overrides client wide timeout setting.
     ///
-    /// Request timeout is the total time before a response must be received.
-    /// Default value is 5 seconds.
+    /// Request timeout is the time before a response head must be received
+    /// after request is sent. Default value is 5 seconds.
     pub fn timeout<T: Into<Millis>>(mut self, timeout: T) -> Self {
-        self.timeout = timeout.into();
+        self.opts.timeout = timeout.into();
+        self
+    }
+
+    /// Set connect timeout in millis. Overrides client wide connect timeout.
+    ///
+    /// Connect timeout is the total time to acquire connection, request fails
+    /// with `ConnectError::Timeout` error.
+    pub fn connect_timeout<T: Into<Millis>>(mut self, timeout: T) -> Self {
+        self.opts.connect_timeout = timeout.into();
+        self
+    }
+
+    /// Set response payload timeout. Overrides client wide payload timeout.
+    ///
+    /// Payload timeout is the total time to read response payload with
+    /// `ClientResponse::body()` or `ClientResponse::json()` methods.
+    pub fn response_payload_timeout<T: Into<Millis>>(mut self, timeout: T) -> Self {
+        self.opts.response_pl_timeout = Some(timeout.into());
+        self
+    }
+
+    /// Send `Expect: 100-continue` header and wait for interim response
+    /// before sending request body.
+    ///
+    /// Body is sent if server responds with `100 Continue` or if server
+    /// does not respond within `timeout`. If server responds with final
+    /// response, body is not sent and connection is closed after response
+    /// is read. Requests without body are sent as is. Applies to http/1 only.
+    pub fn expect_continue<T: Into<Millis>>(mut self, timeout: T) -> Self {
+        self.opts.expect_continue = Some(timeout.into());
+        self
+    }
+
+    /// Set interim responses callback.
+    ///
+    /// Callback is called for each informational response received before
+    /// final response, like `100 Continue` or `103 Early Hints`, such
+    /// responses are skipped. Without callback first received response is
+    /// returned, even if it is informational. Applies to http/1 only,
+    /// http/2 client does not support interim responses.
+    ///
+    /// ```rust
+    /// use ntex::http::{client::Client, header};
+    ///
+    /// # async fn send(client: Client) {
+    /// let response = client
+    ///     .get("http://www.rust-lang.org")
+    ///     .on_informational(|head| {
+    ///         for link in head.headers.get_all(header::LINK) {
+    ///             println!("Early hint: {:?}", link);
+    ///         }
+    ///     })
+    ///     .send()
+    ///     .await;
+    /// # }
+    /// ```
+    pub fn on_informational<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&ResponseHead) + 'static,
+    {
+        self.opts.informational = Some(Informational(Rc::new(f)));
+        self
+    }
+
+    #[cfg(all(feature = "dangerous", any(feature = "openssl", feature = "rustls")))]
+    /// Disable server certificate verification for this request.
+    ///
+    /// **Dangerous**: any certificate is accepted, connection is open
+    /// to man-in-the-middle attacks. Use it only for tooling and diagnostics.
+    /// Such connections are pooled separately and are never used for
+    /// requests with certificate verification. Not supported by custom
+    /// secure connectors. Requires `dangerous` feature.
+    pub fn dangerous_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.opts.verify = if accept {
+            CertVerify::AcceptInvalid
+        } else {
+            CertVerify::Default
+        };
+        self
+    }
+
+    #[cfg(all(feature = "dangerous", any(feature = "openssl", feature = "rustls")))]
+    /// Verify server certificate with custom function.
+    ///
+    /// **Dangerous**: connector's certificate verification is disabled,
+    /// function receives server certificate in DER format and decides if
+    /// connection could be used for this request. Request fails with
+    /// `ConnectError::CertificateRejected` error if function returns `false`.
+    /// Requires `dangerous` feature.
+    ///
+    /// ```rust,no_run
+    /// use ntex::http::client::Client;
+    ///
+    /// #[ntex::main]
+    /// async fn main() {
+    ///     let fingerprint = vec![0u8; 32];
+    ///     let res = Client::new()
+    ///         .get("https://self-signed.example.com")
+    ///         .dangerous_cert_verifier(move |cert| {
+    ///             // check certificate fingerprint
+    ///             !cert.is_empty() && fingerprint.len() == 32
+    ///         })
+    ///         .send()
+    ///         .await;
+    /// }
+    /// ```
+    pub fn dangerous_cert_verifier<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&[u8]) -> bool + 'static,
+    {
+        self.opts.verify = CertVerify::Custom(Rc::new(f));
         self
     }
 
@@ -384,9 +584,7 @@ impl ClientRequest {
 
         let request = FrozenClientRequest {
             head: Rc::new(slf.head),
-            addr: slf.addr,
-            response_decompress: slf.response_decompress,
-            timeout: slf.timeout,
+            opts: slf.opts,
             config: slf.config,
         };
 
@@ -403,13 +601,7 @@ impl ClientRequest {
             Err(e) => return e.into(),
         };
 
-        RequestHeadType::Owned(slf.head).send_body(
-            slf.addr,
-            slf.response_decompress,
-            slf.timeout,
-            slf.config,
-            body,
-        )
+        RequestHeadType::Owned(slf.head).send_body(slf.opts, slf.config, body)
     }
 
     /// Set a JSON body and generate `ClientRequest`
@@ -419,13 +611,7 @@ impl ClientRequest {
             Err(e) => return e.into(),
         };
 
-        RequestHeadType::Owned(slf.head).send_json(
-            slf.addr,
-            slf.response_decompress,
-            slf.timeout,
-            slf.config,
-            value,
-        )
+        RequestHeadType::Owned(slf.head).send_json(slf.opts, slf.config, value)
     }
 
     /// Set a urlencoded body and generate `ClientRequest`
@@ -437,13 +623,15 @@ impl ClientRequest {
             Err(e) => return e.into(),
         };
 
-        RequestHeadType::Owned(slf.head).send_form(
-            slf.addr,
-            slf.response_decompress,
-            slf.timeout,
-            slf.config,
-            value,
-        )
+        RequestHeadType::Owned(slf.head).send_form(slf.opts, slf.config, value)
+    }
+
+    /// Set a multipart/form-data body and generate `ClientRequest`
+    ///
+    /// `Content-Type` header is set to form's content type with boundary.
+    pub fn send_multipart(self, form: Form) -> SendClientRequest {
+        self.content_type(form.content_type())
+            .send_body(form.into_body())
     }
 
     /// Set an streaming body and generate `ClientRequest`.
@@ -457,13 +645,18 @@ impl ClientRequest {
             Err(e) => return e.into(),
         };
 
-        RequestHeadType::Owned(slf.head).send_stream(
-            slf.addr,
-            slf.response_decompress,
-            slf.timeout,
-            slf.config,
-            stream,
-        )
+        RequestHeadType::Owned(slf.head).send_stream(slf.opts, slf.config, stream)
+    }
+
+    /// Set a body read from blocking reader and generate `ClientRequest`.
+    ///
+    /// Body is sent with chunked transfer encoding, chunks are read on
+    /// blocking threads pool. See [`ReaderStream`](super::ReaderStream).
+    pub fn send_reader<R>(self, reader: R) -> SendClientRequest
+    where
+        R: io::Read + Send + 'static,
+    {
+        self.send_stream(ReaderStream::new(reader))
     }
 
     /// Set an empty body and generate `ClientRequest`.
@@ -473,12 +666,7 @@ impl ClientRequest {
             Err(e) => return e.into(),
         };
 
-        RequestHeadType::Owned(slf.head).send(
-            slf.addr,
-            slf.response_decompress,
-            slf.timeout,
-            slf.config,
-        )
+        RequestHeadType::Owned(slf.head).send(slf.opts, slf.config)
     }
 
     #[allow(unused_mut)]
@@ -528,7 +716,7 @@ impl ClientRequest {
 
         let mut slf = self;
 
-        if slf.response_decompress {
+        if slf.opts.response_decompress {
             let https = slf
                 .head
                 .uri
@@ -541,7 +729,7 @@ impl ClientRequest {
             } else {
                 #[cfg(feature = "compress")]
                 {
-                    slf = slf.set_header_if_none(header::ACCEPT_ENCODING, "gzip, deflate")
+                    slf = slf.set_header_if_none(header::ACCEPT_ENCODING, HTTP_ENCODING)
                 }
             };
         }
@@ -582,6 +770,18 @@ mod tests {
         assert!(repr.contains("x-test"));
     }
 
+    #[crate::rt_test]
+    async fn test_preserve_header_case() {
+        let req = Client::new().get("/");
+        assert!(!req.head.preserve_header_case());
+
+        let client = Client::build().preserve_header_case(true).finish();
+        let req = client.get("/");
+        assert!(req.head.preserve_header_case());
+        let req = client.get("/").preserve_header_case(false);
+        assert!(!req.head.preserve_header_case());
+    }
+
     #[crate::rt_test]
     #[allow(clippy::let_underscore_future)]
     async fn test_basics() {
@@ -612,6 +812,14 @@ mod tests {
         let _ = req.send_body("");
     }
 
+    #[crate::rt_test]
+    async fn test_http10() {
+        let req = Client::new().get("/").http10();
+        assert_eq!(req.get_version(), &Version::HTTP_10);
+        assert!(!req.head.chunked());
+        assert_eq!(req.head.connection_type(), ConnectionType::Close);
+    }
+
     #[crate::rt_test]
     async fn test_client_header() {
         let req = Client::build()
@@ -9,17 +9,21 @@ use coo_kie::{Cookie, ParseError as CookieParseError};
 
 use crate::http::error::PayloadError;
 use crate::http::header::{AsName, HeaderValue, CONTENT_LENGTH};
-use crate::http::{HeaderMap, HttpMessage, Payload, ResponseHead, StatusCode, Version};
+use crate::http::{
+    HeaderMap, HttpMessage, Payload, ResponseHead, StatusCode, Uri, Version,
+};
 use crate::time::{Deadline, Millis};
 use crate::util::{Bytes, BytesMut, Extensions, Stream};
 
-use super::{error::JsonPayloadError, ClientConfig};
+use super::{error::JsonPayloadError, CacheStatus, ClientConfig};
 
 /// Client Response
 pub struct ClientResponse {
     pub(crate) head: ResponseHead,
     pub(crate) payload: Payload,
-    config: Rc<ClientConfig>,
+    pub(super) config: Rc<ClientConfig>,
+    pub(super) redirects: Vec<Uri>,
+    pub(super) pl_timeout: Millis,
 }
 
 impl HttpMessage for ClientResponse {
@@ -67,7 +71,9 @@ impl ClientResponse {
         ClientResponse {
             head,
             payload,
+            pl_timeout: config.response_pl_timeout,
             config,
+            redirects: Vec::new(),
         }
     }
 
@@ -115,6 +121,23 @@ impl ClientResponse {
         &mut self.head_mut().headers
     }
 
+    /// Redirect chain
+    ///
+    /// Returns urls of requests that were redirected, in order.
+    /// Chain is empty if response is not result of redirects.
+    #[inline]
+    pub fn redirects(&self) -> &[Uri] {
+        &self.redirects
+    }
+
+    /// Cache status of the response
+    ///
+    /// Status is set for clients with [`Cache`](super::Cache) middleware only.
+    #[inline]
+    pub fn cache_status(&self) -> Option<CacheStatus> {
+        self.extensions().get::<CacheStatus>().copied()
+    }
+
     /// Set a body and return previous body value
     pub fn set_payload(&mut self, payload: Payload) {
         self.payload = payload;
@@ -205,7 +228,7 @@ impl MessageBody {
             fut: Some(ReadBody::new(
                 res.take_payload(),
                 res.config.response_pl_limit,
-                res.config.response_pl_timeout,
+                res.pl_timeout,
             )),
         }
     }
@@ -309,7 +332,7 @@ where
             fut: Some(ReadBody::new(
                 res.take_payload(),
                 res.config.response_pl_limit,
-                res.config.response_pl_timeout,
+                res.pl_timeout,
             )),
             _t: PhantomData,
         }
@@ -401,12 +424,7 @@ impl Future for ReadBody {
                 Poll::Ready(None) => Poll::Ready(Ok(this.buf.split().freeze())),
                 Poll::Pending => {
                     if this.timeout.poll_elapsed(cx).is_ready() {
-                        Poll::Ready(Err(PayloadError::Incomplete(Some(
-                            std::io::Error::new(
-                                std::io::ErrorKind::TimedOut,
-                                "Operation timed out",
-                            ),
-                        ))))
+                        Poll::Ready(Err(PayloadError::Timeout))
                     } else {
                         Poll::Pending
                     }
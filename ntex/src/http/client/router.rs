@@ -0,0 +1,54 @@
+use std::{fmt, net, rc::Rc};
+
+use crate::http::Uri;
+use crate::util::ByteString;
+
+/// Transport decision for client connection
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Route {
+    /// Connect to the uri's host
+    Direct,
+    /// Connect to specific socket address, i.e. egress gateway
+    ///
+    /// Address is ignored if request already has address.
+    Address(net::SocketAddr),
+    /// Connect from specific local address, i.e. per-tenant egress ip
+    ///
+    /// Local address binding is supported by tokio runtime only
+    /// and is not applied to custom connectors.
+    Bind(net::IpAddr),
+    /// Connect with named connector registered with
+    /// [`Connector::route_connector()`](super::Connector::route_connector)
+    ///
+    /// Custom certificate verifier of the request is applied to
+    /// connections of named connector, other verification settings
+    /// are defined by the connector.
+    Connector(ByteString),
+}
+
+/// Selects transport for client connections
+///
+/// Router is called for every new connection request, connections
+/// for different routes are pooled separately.
+pub trait ConnectorRouter: 'static {
+    /// Select route for the uri
+    fn route(&self, uri: &Uri) -> Route;
+}
+
+impl<F> ConnectorRouter for F
+where
+    F: Fn(&Uri) -> Route + 'static,
+{
+    fn route(&self, uri: &Uri) -> Route {
+        (self)(uri)
+    }
+}
+
+#[derive(Clone)]
+pub(super) struct RouterHandler(pub(super) Rc<dyn ConnectorRouter>);
+
+impl fmt::Debug for RouterHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectorRouter").finish()
+    }
+}
@@ -1,22 +1,30 @@
 use std::task::{Context, Poll};
-use std::{error::Error, future::Future, net, pin::Pin, rc::Rc};
+use std::{error::Error, fmt, future::Future, net, pin::Pin, rc::Rc};
 
 use serde::Serialize;
 
 use crate::http::body::{Body, BodyStream};
 use crate::http::error::HttpError;
 use crate::http::header::{self, HeaderMap, HeaderName, HeaderValue};
-use crate::http::RequestHeadType;
+use crate::http::{RequestHeadType, ResponseHead};
 use crate::time::Millis;
 use crate::util::{BoxFuture, Bytes, Stream};
 
 #[cfg(feature = "compress")]
-use crate::http::encoding::Decoder;
+use crate::http::body::{BodySize, MessageBody};
+#[cfg(feature = "compress")]
+use crate::http::encoding::{Decoder, Encoder};
+#[cfg(feature = "compress")]
+use crate::http::header::ContentEncoding;
 #[cfg(feature = "compress")]
 use crate::http::Payload;
 
 use super::error::{FreezeRequestError, InvalidUrl, SendRequestError};
-use super::{ClientConfig, ClientResponse};
+use super::{connector::CertVerify, ClientConfig, ClientResponse};
+
+/// Bodies smaller than this size are sent uncompressed
+#[cfg(feature = "compress")]
+const COMPRESS_MIN_SIZE: u64 = 1024;
 
 #[derive(thiserror::Error, Debug)]
 pub(crate) enum PrepForSendingError {
@@ -44,6 +52,48 @@ impl From<PrepForSendingError> for SendRequestError {
     }
 }
 
+/// Request sending options
+#[derive(Clone, Debug)]
+pub(super) struct SendOptions {
+    pub(super) addr: Option<net::SocketAddr>,
+    pub(super) response_decompress: bool,
+    #[cfg(feature = "compress")]
+    pub(super) compress: Option<ContentEncoding>,
+    pub(super) timeout: Millis,
+    pub(super) connect_timeout: Millis,
+    pub(super) response_pl_timeout: Option<Millis>,
+    pub(super) expect_continue: Option<Millis>,
+    pub(super) informational: Option<Informational>,
+    pub(super) verify: CertVerify,
+}
+
+/// Interim responses callback
+#[derive(Clone)]
+pub(super) struct Informational(pub(super) Rc<dyn Fn(&ResponseHead)>);
+
+impl fmt::Debug for Informational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Informational")
+    }
+}
+
+impl Default for SendOptions {
+    fn default() -> Self {
+        SendOptions {
+            addr: None,
+            response_decompress: true,
+            #[cfg(feature = "compress")]
+            compress: None,
+            timeout: Millis::ZERO,
+            connect_timeout: Millis::ZERO,
+            response_pl_timeout: None,
+            expect_continue: None,
+            informational: None,
+            verify: CertVerify::Default,
+        }
+    }
+}
+
 /// Future that sends request's payload and resolves to a server response.
 #[must_use = "futures do nothing unless polled"]
 pub enum SendClientRequest {
@@ -80,10 +130,10 @@ impl Future for SendClientRequest {
                 let res = res.map(|mut res| {
                     if *_response_decompress {
                         let payload = res.take_payload();
-                        res.set_payload(Payload::from_stream(Decoder::from_headers(
-                            payload,
-                            &res.head.headers,
-                        )))
+                        let limit = res.config.response_decompress_limit;
+                        res.set_payload(Payload::from_stream(
+                            Decoder::from_headers(payload, &res.head.headers).limit(limit),
+                        ))
                     }
                     res
                 });
@@ -119,36 +169,39 @@ impl From<PrepForSendingError> for SendClientRequest {
 impl RequestHeadType {
     pub(super) fn send_body<B>(
         self,
-        addr: Option<net::SocketAddr>,
-        response_decompress: bool,
-        mut timeout: Millis,
+        mut opts: SendOptions,
         config: Rc<ClientConfig>,
         body: B,
     ) -> SendClientRequest
     where
         B: Into<Body>,
     {
-        if timeout.is_zero() {
-            timeout = config.timeout;
+        if opts.timeout.is_zero() {
+            opts.timeout = config.timeout;
+        }
+        if opts.connect_timeout.is_zero() {
+            opts.connect_timeout = config.connect_timeout;
         }
-        let body = body.into();
+        #[cfg(feature = "compress")]
+        let (head, body) = match opts.compress {
+            Some(encoding) => match self.compress_body(encoding, body.into()) {
+                Ok(res) => res,
+                Err(e) => return e.into(),
+            },
+            None => (self, body.into()),
+        };
+        #[cfg(not(feature = "compress"))]
+        let (head, body) = (self, body.into());
 
-        let fut = Box::pin(async move {
-            config
-                .clone()
-                .connector
-                .send_request(self, body, addr, timeout, config)
-                .await
-        });
+        let response_decompress = opts.response_decompress;
+        let fut = Box::pin(super::middleware::send_request(head, body, opts, config));
 
         SendClientRequest::new(fut, response_decompress)
     }
 
     pub(super) fn send_json<T: Serialize>(
         mut self,
-        addr: Option<net::SocketAddr>,
-        response_decompress: bool,
-        timeout: Millis,
+        opts: SendOptions,
         config: Rc<ClientConfig>,
         value: &T,
     ) -> SendClientRequest {
@@ -161,20 +214,12 @@ impl RequestHeadType {
             return e.into();
         }
 
-        self.send_body(
-            addr,
-            response_decompress,
-            timeout,
-            config,
-            Body::Bytes(Bytes::from(body)),
-        )
+        self.send_body(opts, config, Body::Bytes(Bytes::from(body)))
     }
 
     pub(super) fn send_form<T: Serialize>(
         mut self,
-        addr: Option<net::SocketAddr>,
-        response_decompress: bool,
-        timeout: Millis,
+        opts: SendOptions,
         config: Rc<ClientConfig>,
         value: &T,
     ) -> SendClientRequest {
@@ -190,20 +235,12 @@ impl RequestHeadType {
             return e.into();
         }
 
-        self.send_body(
-            addr,
-            response_decompress,
-            timeout,
-            config,
-            Body::Bytes(Bytes::from(body)),
-        )
+        self.send_body(opts, config, Body::Bytes(Bytes::from(body)))
     }
 
     pub(super) fn send_stream<S, E>(
         self,
-        addr: Option<net::SocketAddr>,
-        response_decompress: bool,
-        timeout: Millis,
+        opts: SendOptions,
         config: Rc<ClientConfig>,
         stream: S,
     ) -> SendClientRequest
@@ -211,23 +248,53 @@ impl RequestHeadType {
         S: Stream<Item = Result<Bytes, E>> + Unpin + 'static,
         E: Error + 'static,
     {
-        self.send_body(
-            addr,
-            response_decompress,
-            timeout,
-            config,
-            Body::from_message(BodyStream::new(stream)),
-        )
+        self.send_body(opts, config, Body::from_message(BodyStream::new(stream)))
     }
 
     pub(super) fn send(
         self,
-        addr: Option<net::SocketAddr>,
-        response_decompress: bool,
-        timeout: Millis,
+        opts: SendOptions,
         config: Rc<ClientConfig>,
     ) -> SendClientRequest {
-        self.send_body(addr, response_decompress, timeout, config, Body::None)
+        self.send_body(opts, config, Body::None)
+    }
+
+    #[cfg(feature = "compress")]
+    /// Compress body with provided encoding.
+    ///
+    /// Small bodies and bodies with explicitly set content length
+    /// or content encoding are sent as is.
+    fn compress_body(
+        mut self,
+        encoding: ContentEncoding,
+        body: Body,
+    ) -> Result<(Self, Body), HttpError> {
+        let skip = match body.size() {
+            BodySize::None | BodySize::Empty => true,
+            BodySize::Sized(len) => len < COMPRESS_MIN_SIZE,
+            BodySize::Stream => false,
+        };
+        if skip
+            || !encoding.is_compressed()
+            || self.contains_header(&header::CONTENT_ENCODING)
+            || self.contains_header(&header::CONTENT_LENGTH)
+        {
+            return Ok((self, body));
+        }
+
+        self.set_header_if_none(header::CONTENT_ENCODING, encoding.as_str())?;
+        Ok((self, Encoder::request(encoding, body)))
+    }
+
+    #[cfg(feature = "compress")]
+    fn contains_header(&self, key: &HeaderName) -> bool {
+        match self {
+            RequestHeadType::Owned(head) => head.headers.contains_key(key),
+            RequestHeadType::Rc(head, extra_headers) => {
+                head.headers.contains_key(key)
+                    || extra_headers.iter().any(|h| h.contains_key(key))
+            }
+        }
     }
 
     fn set_header_if_none<V>(&mut self, key: HeaderName, value: V) -> Result<(), HttpError>
@@ -38,6 +38,27 @@ impl From<Option<usize>> for KeepAlive {
     }
 }
 
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+/// Http/1 response flushing strategy
+///
+/// Strategy defines when encoded response data is passed to the io
+/// write buffer. Held data is always flushed once response is complete.
+pub enum FlushStrategy {
+    /// Pass response data to the io as soon as it is encoded
+    #[default]
+    Immediate,
+    /// Hold response data for specified period
+    ///
+    /// Data is flushed when period elapses since first held write or
+    /// when held data reaches write buffer high watermark. Timer resolution
+    /// is 1 millisecond.
+    Coalesce(Millis),
+    /// Hold response data until it reaches specified size
+    ///
+    /// Held data is flushed as soon as response body has no ready data.
+    Threshold(usize),
+}
+
 #[derive(Debug, Clone)]
 /// Http service configuration
 pub struct ServiceConfig {
@@ -48,6 +69,9 @@ pub struct ServiceConfig {
     pub(super) h2config: h2::Config,
     pub(super) headers_read_rate: Option<ReadRate>,
     pub(super) payload_read_rate: Option<ReadRate>,
+    pub(super) h2_drain_timeout: Option<Seconds>,
+    pub(super) preserve_case: bool,
+    pub(super) flush_strategy: FlushStrategy,
     pub(super) timer: DateService,
 }
 
@@ -113,6 +137,9 @@ impl ServiceConfig {
                 max_timeout: client_timeout + Seconds(15),
             }),
             payload_read_rate: None,
+            h2_drain_timeout: None,
+            preserve_case: false,
+            flush_strategy: FlushStrategy::Immediate,
         }
     }
 
@@ -232,6 +259,45 @@ impl ServiceConfig {
         }
         self
     }
+
+    /// Set http/2 graceful shutdown drain window.
+    ///
+    /// On server shutdown each http/2 connection receives GOAWAY frame, so
+    /// clients stop opening new streams. After drain window final GOAWAY frame
+    /// gets sent and connection is closed once in-flight streams complete.
+    ///
+    /// To disable graceful shutdown set value to 0.
+    ///
+    /// By default drain window is equal to client disconnect timeout.
+    pub fn h2_drain_timeout(&mut self, timeout: Seconds) -> &mut Self {
+        self.h2_drain_timeout = Some(timeout);
+        self
+    }
+
+    /// Preserve original casing of http/1 header names.
+    ///
+    /// Original casing of request header names is stored in request's
+    /// `HeaderMap`, header names with recorded casing are written to the wire
+    /// verbatim. Some legacy upstreams and clients could not handle lowercase
+    /// header names, so proxies could pass names through unchanged.
+    ///
+    /// By default header case preservation is disabled.
+    pub fn preserve_header_case(&mut self, enabled: bool) -> &mut Self {
+        self.preserve_case = enabled;
+        self
+    }
+
+    /// Set http/1 response flushing strategy.
+    ///
+    /// Latency sensitive services could pass small writes to the io
+    /// immediately, throughput oriented services could coalesce response
+    /// chunks to reduce number of socket writes, see [`FlushStrategy`].
+    ///
+    /// By default response data is flushed immediately.
+    pub fn flush_strategy(&mut self, strategy: FlushStrategy) -> &mut Self {
+        self.flush_strategy = strategy;
+        self
+    }
 }
 
 pub(super) struct DispatcherConfig<S, C> {
@@ -243,6 +309,9 @@ pub(super) struct DispatcherConfig<S, C> {
     pub(super) ka_enabled: bool,
     pub(super) headers_read_rate: Option<ReadRate>,
     pub(super) payload_read_rate: Option<ReadRate>,
+    pub(super) h2_conns: super::h2::Connections,
+    pub(super) preserve_case: bool,
+    pub(super) flush_strategy: FlushStrategy,
     pub(super) timer: DateService,
 }
 
@@ -257,6 +326,11 @@ impl<S, C> DispatcherConfig<S, C> {
             headers_read_rate: cfg.headers_read_rate,
             payload_read_rate: cfg.payload_read_rate,
             h2config: cfg.h2config.clone(),
+            h2_conns: super::h2::Connections::new(
+                cfg.h2_drain_timeout.unwrap_or(cfg.client_disconnect).into(),
+            ),
+            preserve_case: cfg.preserve_case,
+            flush_strategy: cfg.flush_strategy,
             timer: cfg.timer.clone(),
         }
     }
@@ -2,6 +2,7 @@ use std::{future::Future, io, io::Write, pin::Pin, task::Context, task::Poll};
 
 use brotli2::write::BrotliDecoder;
 use flate2::write::{GzDecoder, ZlibDecoder};
+use zstd::stream::write::Decoder as ZstdDecoder;
 
 use super::Writer;
 use crate::http::error::PayloadError;
@@ -15,6 +16,8 @@ pub struct Decoder<S> {
     decoder: Option<ContentDecoder>,
     stream: S,
     eof: bool,
+    limit: usize,
+    decoded: usize,
     fut: Option<JoinHandle<Result<(Option<Bytes>, ContentDecoder), io::Error>>>,
 }
 
@@ -35,6 +38,9 @@ where
             ContentEncoding::Gzip => Some(ContentDecoder::Gzip(Box::new(GzDecoder::new(
                 Writer::new(),
             )))),
+            ContentEncoding::Zstd => ZstdDecoder::new(Writer::new())
+                .ok()
+                .map(|decoder| ContentDecoder::Zstd(Box::new(decoder))),
             _ => None,
         };
         Decoder {
@@ -42,9 +48,20 @@ where
             stream,
             fut: None,
             eof: false,
+            limit: 0,
+            decoded: 0,
         }
     }
 
+    /// Set max size of decoded payload.
+    ///
+    /// Decoder returns `PayloadError::Overflow` if decoded payload exceeds limit.
+    /// By default limit is not set.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
     /// Construct decoder based on headers.
     #[inline]
     pub fn from_headers(stream: S, headers: &HeaderMap) -> Decoder<S> {
@@ -73,6 +90,29 @@ where
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
     ) -> Poll<Option<Self::Item>> {
+        let result = self.as_mut().poll_decode(cx);
+
+        if let Poll::Ready(Some(Ok(ref chunk))) = result {
+            self.decoded += chunk.len();
+            if self.limit > 0 && self.decoded > self.limit {
+                self.eof = true;
+                self.decoder.take();
+                self.fut.take();
+                return Poll::Ready(Some(Err(PayloadError::Overflow)));
+            }
+        }
+        result
+    }
+}
+
+impl<S> Decoder<S>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>> + Unpin,
+{
+    fn poll_decode(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, PayloadError>>> {
         loop {
             if let Some(ref mut fut) = self.fut {
                 let (chunk, decoder) = match Pin::new(fut).poll(cx) {
@@ -136,6 +176,7 @@ enum ContentDecoder {
     Deflate(Box<ZlibDecoder<Writer>>),
     Gzip(Box<GzDecoder<Writer>>),
     Br(Box<BrotliDecoder<Writer>>),
+    Zstd(Box<ZstdDecoder<'static, Writer>>),
 }
 
 impl ContentDecoder {
@@ -174,6 +215,17 @@ impl ContentDecoder {
                 }
                 Err(e) => Err(e),
             },
+            ContentDecoder::Zstd(ref mut decoder) => match decoder.flush() {
+                Ok(()) => {
+                    let b = decoder.get_mut().take();
+                    if !b.is_empty() {
+                        Ok(Some(b))
+                    } else {
+                        Ok(None)
+                    }
+                }
+                Err(e) => Err(e),
+            },
         }
     }
 
@@ -215,6 +267,18 @@ impl ContentDecoder {
                 }
                 Err(e) => Err(e),
             },
+            ContentDecoder::Zstd(ref mut decoder) => match decoder.write_all(&data) {
+                Ok(_) => {
+                    decoder.flush()?;
+                    let b = decoder.get_mut().take();
+                    if !b.is_empty() {
+                        Ok(Some(b))
+                    } else {
+                        Ok(None)
+                    }
+                }
+                Err(e) => Err(e),
+            },
         }
     }
 }
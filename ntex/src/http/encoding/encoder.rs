@@ -3,6 +3,7 @@ use std::{fmt, future::Future, io, io::Write, pin::Pin, task::Context, task::Pol
 
 use brotli2::write::BrotliEncoder;
 use flate2::write::{GzEncoder, ZlibEncoder};
+use zstd::stream::write::Encoder as ZstdEncoder;
 
 use crate::http::body::{Body, BodySize, MessageBody, ResponseBody};
 use crate::http::header::{ContentEncoding, HeaderValue, CONTENT_ENCODING};
@@ -61,6 +62,30 @@ impl<B: MessageBody> Encoder<B> {
     }
 }
 
+impl Encoder<Body> {
+    /// Compress request body.
+    ///
+    /// Body is returned as is if encoding is not supported.
+    pub fn request(encoding: ContentEncoding, body: Body) -> Body {
+        if !ContentEncoder::can_encode(encoding) {
+            return body;
+        }
+
+        let body = match body {
+            Body::None => return Body::None,
+            Body::Empty => return Body::Empty,
+            Body::Bytes(buf) => EncoderBody::Bytes(buf),
+            Body::Message(stream) => EncoderBody::BoxedStream(stream),
+        };
+        Body::from_message(Encoder::<Body> {
+            body,
+            eof: false,
+            fut: None,
+            encoder: ContentEncoder::encoder(encoding),
+        })
+    }
+}
+
 impl<B: fmt::Debug> fmt::Debug for Encoder<B> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Encoder")
@@ -191,13 +216,17 @@ enum ContentEncoder {
     Deflate(ZlibEncoder<Writer>),
     Gzip(GzEncoder<Writer>),
     Br(BrotliEncoder<Writer>),
+    Zstd(ZstdEncoder<'static, Writer>),
 }
 
 impl ContentEncoder {
     fn can_encode(encoding: ContentEncoding) -> bool {
         matches!(
             encoding,
-            ContentEncoding::Deflate | ContentEncoding::Gzip | ContentEncoding::Br
+            ContentEncoding::Deflate
+                | ContentEncoding::Gzip
+                | ContentEncoding::Br
+                | ContentEncoding::Zstd
         )
     }
 
@@ -214,6 +243,9 @@ impl ContentEncoder {
             ContentEncoding::Br => {
                 Some(ContentEncoder::Br(BrotliEncoder::new(Writer::new(), 3)))
             }
+            ContentEncoding::Zstd => ZstdEncoder::new(Writer::new(), 3)
+                .ok()
+                .map(ContentEncoder::Zstd),
             _ => None,
         }
     }
@@ -223,6 +255,7 @@ impl ContentEncoder {
             ContentEncoder::Br(ref mut encoder) => encoder.get_mut().take(),
             ContentEncoder::Deflate(ref mut encoder) => encoder.get_mut().take(),
             ContentEncoder::Gzip(ref mut encoder) => encoder.get_mut().take(),
+            ContentEncoder::Zstd(ref mut encoder) => encoder.get_mut().take(),
         }
     }
 
@@ -240,6 +273,10 @@ impl ContentEncoder {
                 Ok(writer) => Ok(writer.buf.freeze()),
                 Err(err) => Err(err),
             },
+            ContentEncoder::Zstd(encoder) => match encoder.finish() {
+                Ok(writer) => Ok(writer.buf.freeze()),
+                Err(err) => Err(err),
+            },
         }
     }
 
@@ -266,6 +303,13 @@ impl ContentEncoder {
                     Err(err)
                 }
             },
+            ContentEncoder::Zstd(ref mut encoder) => match encoder.write_all(data) {
+                Ok(_) => Ok(()),
+                Err(err) => {
+                    log::trace!("Error decoding zstd encoding: {}", err);
+                    Err(err)
+                }
+            },
         }
     }
 }
@@ -276,6 +320,7 @@ impl fmt::Debug for ContentEncoder {
             ContentEncoder::Deflate(_) => write!(f, "ContentEncoder::Deflate"),
             ContentEncoder::Gzip(_) => write!(f, "ContentEncoder::Gzip"),
             ContentEncoder::Br(_) => write!(f, "ContentEncoder::Br"),
+            ContentEncoder::Zstd(_) => write!(f, "ContentEncoder::Zstd"),
         }
     }
 }
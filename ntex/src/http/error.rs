@@ -148,6 +148,12 @@ pub enum PayloadError {
     /// Io error
     #[error("{0}")]
     Io(#[from] io::Error),
+    /// Payload is not received in time
+    #[error("Timeout while reading payload")]
+    Timeout,
+    /// Payload digest does not match digest header
+    #[error("Payload digest does not match")]
+    DigestMismatch,
 }
 
 impl From<Either<PayloadError, io::Error>> for PayloadError {
@@ -7,7 +7,7 @@ use crate::http::body::BodySize;
 use crate::http::config::DateService;
 use crate::http::error::{DecodeError, EncodeError, PayloadError};
 use crate::http::message::{ConnectionType, RequestHeadType, ResponseHead};
-use crate::http::{Method, Version};
+use crate::http::{Method, StatusCode, Version};
 use crate::util::{Bytes, BytesMut};
 
 use super::decoder::{PayloadDecoder, PayloadItem, PayloadType};
@@ -17,19 +17,79 @@ bitflags! {
     #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
     struct Flags: u8 {
         const HEAD              = 0b0000_0001;
+        const CONNECT           = 0b0000_0010;
+        const UPGRADE           = 0b0000_0100;
         const KEEPALIVE_ENABLED = 0b0000_1000;
         const STREAM            = 0b0001_0000;
     }
 }
 
 #[derive(Debug)]
-/// HTTP/1 Codec
+/// HTTP/1 client codec
+///
+/// Codec encodes request head and request body chunks and decodes
+/// response head. It does not depend on any transport, so it could be
+/// used for sending http requests over any byte stream.
+///
+/// Request is encoded with `Message::Item((head, body_size))` followed by
+/// `Message::Chunk(Some(chunk))` for each body chunk and `Message::Chunk(None)`
+/// at the end of body. After response head is decoded, use
+/// [`ClientCodec::message_type()`] to check if response has payload and
+/// convert codec to [`ClientPayloadCodec`] to decode it.
+///
+/// If server accepts protocol upgrade (`101 Switching Protocols` response or
+/// successful response for `CONNECT` request), codec switches to upgrade mode.
+/// In upgrade mode payload codec returns all received bytes as is and encoder
+/// writes body chunks without any framing.
+///
+/// ```rust
+/// use ntex::codec::{Decoder, Encoder};
+/// use ntex::http::{body::BodySize, h1, Method, RequestHead, RequestHeadType};
+/// use ntex::util::BytesMut;
+///
+/// #[ntex::main]
+/// async fn main() {
+///     let codec = h1::ClientCodec::default();
+///
+///     // encode request
+///     let mut head = RequestHead::default();
+///     head.method = Method::POST;
+///     head.uri = "/index.html".parse().unwrap();
+///
+///     let mut buf = BytesMut::new();
+///     codec
+///         .encode(
+///             h1::Message::Item((RequestHeadType::Owned(head), BodySize::Sized(4))),
+///             &mut buf,
+///         )
+///         .unwrap();
+///     codec.encode(h1::Message::Chunk(Some("data".into())), &mut buf).unwrap();
+///     codec.encode(h1::Message::Chunk(None), &mut buf).unwrap();
+///     // write `buf` to the transport
+///
+///     // decode response
+///     let mut buf = BytesMut::from("HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok");
+///     let head = codec.decode(&mut buf).unwrap().unwrap();
+///     assert_eq!(head.status, 200);
+///     assert_eq!(codec.message_type(), h1::MessageType::Payload);
+///
+///     // decode response payload
+///     let codec = codec.into_payload_codec();
+///     assert_eq!(codec.decode(&mut buf).unwrap(), Some(Some("ok".into())));
+///     assert_eq!(codec.decode(&mut buf).unwrap(), Some(None));
+///
+///     // codec could be used for next request
+///     let codec = codec.into_message_codec();
+/// }
+/// ```
 pub struct ClientCodec {
     inner: ClientCodecInner,
 }
 
 #[derive(Debug)]
-/// HTTP/1 Payload Codec
+/// HTTP/1 client payload codec
+///
+/// Decodes response payload, `Some(None)` indicates end of payload.
 pub struct ClientPayloadCodec {
     inner: ClientCodecInner,
 }
@@ -76,11 +136,27 @@ impl ClientCodec {
         }
     }
 
+    /// Record original casing of response header names.
+    ///
+    /// Original casing is stored in response's `HeaderMap`.
+    pub fn preserve_header_case(mut self, enabled: bool) -> Self {
+        self.inner.decoder.preserve_case(enabled);
+        self
+    }
+
     /// Check if request is upgrade
     pub fn upgrade(&self) -> bool {
         self.inner.ctype.get() == ConnectionType::Upgrade
     }
 
+    /// Check if server accepted protocol upgrade
+    ///
+    /// Codec switches to upgrade mode after `101 Switching Protocols`
+    /// response or successful response for `CONNECT` request.
+    pub fn is_upgraded(&self) -> bool {
+        self.inner.flags.get().contains(Flags::UPGRADE)
+    }
+
     /// Check if last response is keep-alive
     pub fn keepalive(&self) -> bool {
         self.inner.ctype.get() == ConnectionType::KeepAlive
@@ -109,6 +185,11 @@ impl ClientPayloadCodec {
         self.inner.ctype.get() == ConnectionType::KeepAlive
     }
 
+    /// Check if server accepted protocol upgrade
+    pub fn is_upgraded(&self) -> bool {
+        self.inner.flags.get().contains(Flags::UPGRADE)
+    }
+
     /// Transform payload codec to a message codec
     pub fn into_message_codec(self) -> ClientCodec {
         ClientCodec { inner: self.inner }
@@ -133,7 +214,20 @@ impl Decoder for ClientCodec {
                 };
             }
 
-            if !self.inner.flags.get().contains(Flags::HEAD) {
+            let mut flags = self.inner.flags.get();
+            flags.remove(Flags::STREAM);
+            self.inner.flags.set(flags);
+
+            if req.status == StatusCode::SWITCHING_PROTOCOLS
+                || (flags.contains(Flags::CONNECT) && req.status.is_success())
+            {
+                // switch to upgrade mode, the rest of the stream belongs to new protocol
+                flags.insert(Flags::UPGRADE | Flags::STREAM);
+                self.inner.flags.set(flags);
+                self.inner.ctype.set(ConnectionType::Upgrade);
+                self.inner.encoder.set_eof();
+                *self.inner.payload.borrow_mut() = Some(PayloadDecoder::eof());
+            } else if !flags.contains(Flags::HEAD) {
                 match payload {
                     PayloadType::None => {
                         self.inner.payload.borrow_mut().take();
@@ -200,6 +294,12 @@ impl Encoder for ClientCodec {
                 inner.version.set(head.as_ref().version);
                 let mut flags = inner.flags.get();
                 flags.set(Flags::HEAD, head.as_ref().method == Method::HEAD);
+                flags.set(Flags::CONNECT, head.as_ref().method == Method::CONNECT);
+                if flags.contains(Flags::UPGRADE) {
+                    // previous upgraded stream is abandoned
+                    flags.remove(Flags::UPGRADE);
+                    inner.payload.borrow_mut().take();
+                }
                 inner.flags.set(flags);
 
                 // connection status
@@ -236,3 +336,117 @@ impl Encoder for ClientCodec {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{header, RequestHead};
+
+    fn request(method: Method, size: BodySize) -> Message<(RequestHeadType, BodySize)> {
+        let mut head = RequestHead::default();
+        head.method = method;
+        head.uri = "/test".parse().unwrap();
+        Message::Item((RequestHeadType::Owned(head), size))
+    }
+
+    #[crate::rt_test]
+    async fn test_client_codec() {
+        let codec = ClientCodec::default();
+        assert!(format!("{:?}", codec).contains("ClientCodec"));
+
+        let mut buf = BytesMut::new();
+        codec
+            .encode(request(Method::POST, BodySize::Stream), &mut buf)
+            .unwrap();
+        codec
+            .encode(Message::Chunk(Some(Bytes::from_static(b"data"))), &mut buf)
+            .unwrap();
+        codec.encode(Message::Chunk(None), &mut buf).unwrap();
+        let data = String::from_utf8(buf.to_vec()).unwrap();
+        assert!(data.starts_with("POST /test HTTP/1.1\r\n"));
+        assert!(data.contains("transfer-encoding: chunked\r\n"));
+        assert!(data.ends_with("\r\n\r\n4\r\ndata\r\n0\r\n\r\n"));
+
+        // response with streaming payload
+        let mut buf = BytesMut::from(
+            "HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n2\r\nok\r\n0\r\n\r\n",
+        );
+        let head = codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(head.status, StatusCode::OK);
+        assert_eq!(codec.message_type(), MessageType::Payload);
+        assert!(!codec.is_upgraded());
+
+        let codec = codec.into_payload_codec();
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Some(Bytes::from_static(b"ok")))
+        );
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(None));
+        let codec = codec.into_message_codec();
+
+        // next response without payload
+        let mut buf = BytesMut::from("HTTP/1.1 204 No Content\r\n\r\n");
+        codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(codec.message_type(), MessageType::None);
+    }
+
+    #[crate::rt_test]
+    async fn test_client_codec_upgrade() {
+        let codec = ClientCodec::default();
+        let mut head = RequestHead::default();
+        head.uri = "/ws".parse().unwrap();
+        head.set_connection_type(ConnectionType::Upgrade);
+        head.headers
+            .insert(header::UPGRADE, header::HeaderValue::from_static("custom"));
+
+        let mut buf = BytesMut::new();
+        codec
+            .encode(
+                Message::Item((RequestHeadType::Owned(head), BodySize::None)),
+                &mut buf,
+            )
+            .unwrap();
+        assert!(codec.upgrade());
+
+        let mut buf = BytesMut::from(
+            "HTTP/1.1 101 Switching Protocols\r\nupgrade: custom\r\nconnection: upgrade\r\n\r\nraw",
+        );
+        let head = codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(head.status, StatusCode::SWITCHING_PROTOCOLS);
+        assert!(codec.is_upgraded());
+        assert_eq!(codec.message_type(), MessageType::Stream);
+
+        // body chunks are written as is
+        let mut out = BytesMut::new();
+        codec
+            .encode(Message::Chunk(Some(Bytes::from_static(b"frame"))), &mut out)
+            .unwrap();
+        assert_eq!(&out[..], b"frame");
+
+        let codec = codec.into_payload_codec();
+        assert!(codec.is_upgraded());
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Some(Bytes::from_static(b"raw")))
+        );
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+
+        // tunnel
+        let codec = codec.into_message_codec();
+        let mut buf = BytesMut::new();
+        codec
+            .encode(request(Method::CONNECT, BodySize::None), &mut buf)
+            .unwrap();
+        assert!(!codec.is_upgraded());
+
+        let mut buf = BytesMut::from("HTTP/1.1 200 OK\r\n\r\ntunnel");
+        codec.decode(&mut buf).unwrap().unwrap();
+        assert!(codec.is_upgraded());
+        assert_eq!(codec.message_type(), MessageType::Stream);
+        let codec = codec.into_payload_codec();
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Some(Bytes::from_static(b"tunnel")))
+        );
+    }
+}
@@ -87,6 +87,14 @@ impl Codec {
         }
     }
 
+    /// Record original casing of request header names.
+    ///
+    /// Original casing is stored in request's `HeaderMap`.
+    pub fn preserve_header_case(mut self, enabled: bool) -> Self {
+        self.decoder.preserve_case(enabled);
+        self
+    }
+
     #[inline]
     /// Check if request is upgrade
     pub fn upgrade(&self) -> bool {
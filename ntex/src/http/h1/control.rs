@@ -2,7 +2,7 @@ use std::{future::Future, io};
 
 use crate::http::message::CurrentIo;
 use crate::http::{body::Body, h1::Codec, Request, Response, ResponseError};
-use crate::io::{Filter, Io, IoBoxed};
+use crate::io::{Filter, Io, IoBoxed, IoError};
 
 pub enum Control<F, Err> {
     /// New request is loaded
@@ -81,7 +81,7 @@ impl<F, Err> Control<F, Err> {
     }
 
     pub(super) fn peer_gone(err: Option<io::Error>) -> Self {
-        Control::PeerGone(PeerGone(err))
+        Control::PeerGone(PeerGone(err.map(IoError::from)))
     }
 
     pub(super) fn proto_err(err: super::ProtocolError) -> Self {
@@ -359,18 +359,18 @@ impl ProtocolError {
 }
 
 #[derive(Debug)]
-pub struct PeerGone(Option<io::Error>);
+pub struct PeerGone(Option<IoError>);
 
 impl PeerGone {
     #[inline]
     /// Returns error reference
-    pub fn err(&self) -> Option<&io::Error> {
+    pub fn err(&self) -> Option<&IoError> {
         self.0.as_ref()
     }
 
     #[inline]
     /// Take error
-    pub fn take(&mut self) -> Option<io::Error> {
+    pub fn take(&mut self) -> Option<IoError> {
         self.0.take()
     }
 
@@ -8,7 +8,7 @@ use crate::http::error::DecodeError;
 use crate::http::header::HeaderMap;
 use crate::http::message::{ConnectionType, ResponseHead};
 use crate::http::request::Request;
-use crate::util::{Buf, Bytes, BytesMut};
+use crate::util::{Buf, ByteString, Bytes, BytesMut};
 
 use super::MAX_BUFFER_SIZE;
 
@@ -16,7 +16,10 @@ const MAX_HEADERS: usize = 96;
 
 #[derive(Debug)]
 /// Incoming messagd decoder
-pub(super) struct MessageDecoder<T: MessageType>(PhantomData<T>);
+pub(super) struct MessageDecoder<T: MessageType> {
+    preserve_case: bool,
+    _t: PhantomData<T>,
+}
 
 #[derive(Debug, PartialEq, Eq)]
 /// Incoming request type
@@ -28,13 +31,26 @@ pub enum PayloadType {
 
 impl<T: MessageType> Default for MessageDecoder<T> {
     fn default() -> Self {
-        MessageDecoder(PhantomData)
+        MessageDecoder {
+            preserve_case: false,
+            _t: PhantomData,
+        }
     }
 }
 
 impl<T: MessageType> Clone for MessageDecoder<T> {
     fn clone(&self) -> Self {
-        MessageDecoder(PhantomData)
+        MessageDecoder {
+            preserve_case: self.preserve_case,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<T: MessageType> MessageDecoder<T> {
+    /// Record original casing of header names
+    pub(super) fn preserve_case(&mut self, enabled: bool) {
+        self.preserve_case = enabled;
     }
 }
 
@@ -43,7 +59,7 @@ impl<T: MessageType> Decoder for MessageDecoder<T> {
     type Error = DecodeError;
 
     fn decode(&self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        T::decode(src)
+        T::decode(src, self.preserve_case)
     }
 }
 
@@ -78,13 +94,17 @@ pub(super) trait MessageType: Sized {
 
     fn headers_mut(&mut self) -> &mut HeaderMap;
 
-    fn decode(src: &mut BytesMut) -> Result<Option<(Self, PayloadType)>, DecodeError>;
+    fn decode(
+        src: &mut BytesMut,
+        preserve_case: bool,
+    ) -> Result<Option<(Self, PayloadType)>, DecodeError>;
 
     fn set_headers(
         &mut self,
         slice: &Bytes,
         version: Version,
         raw_headers: &[HeaderIndex],
+        preserve_case: bool,
     ) -> Result<PayloadLength, DecodeError> {
         let mut ka = None;
         let mut has_upgrade = false;
@@ -97,7 +117,18 @@ pub(super) trait MessageType: Sized {
             let headers = self.headers_mut();
 
             for idx in raw_headers.iter() {
-                let name = HeaderName::from_bytes(&slice[idx.name.0..idx.name.1]).unwrap();
+                let raw_name = &slice[idx.name.0..idx.name.1];
+                let name = HeaderName::from_bytes(raw_name).unwrap();
+
+                if preserve_case && raw_name != name.as_str().as_bytes() {
+                    // Unsafe: httparse check header name for valid token chars
+                    let case = unsafe {
+                        ByteString::from_bytes_unchecked(
+                            slice.slice(idx.name.0..idx.name.1),
+                        )
+                    };
+                    headers.set_original_case(&name, case);
+                }
 
                 // Unsafe: httparse check header value for valid utf-8
                 let value = unsafe {
@@ -228,7 +259,10 @@ impl MessageType for Request {
         &mut self.head_mut().headers
     }
 
-    fn decode(src: &mut BytesMut) -> Result<Option<(Self, PayloadType)>, DecodeError> {
+    fn decode(
+        src: &mut BytesMut,
+        preserve_case: bool,
+    ) -> Result<Option<(Self, PayloadType)>, DecodeError> {
         let mut headers: [mem::MaybeUninit<HeaderIndex>; MAX_HEADERS] = uninit_array();
 
         let (len, method, uri, ver, headers) = {
@@ -269,7 +303,8 @@ impl MessageType for Request {
         let mut msg = Request::new();
 
         // convert headers
-        let mut length = msg.set_headers(&src.split_to(len).freeze(), ver, headers)?;
+        let mut length =
+            msg.set_headers(&src.split_to(len).freeze(), ver, headers, preserve_case)?;
 
         // disallow HTTP/1.0 POST requests that do not contain a Content-Length headers
         // see https://datatracker.ietf.org/doc/html/rfc1945#section-7.2.2
@@ -325,7 +360,10 @@ impl MessageType for ResponseHead {
         &mut self.headers
     }
 
-    fn decode(src: &mut BytesMut) -> Result<Option<(Self, PayloadType)>, DecodeError> {
+    fn decode(
+        src: &mut BytesMut,
+        preserve_case: bool,
+    ) -> Result<Option<(Self, PayloadType)>, DecodeError> {
         let mut headers: [mem::MaybeUninit<HeaderIndex>; MAX_HEADERS] = uninit_array();
 
         let (len, ver, status, headers) = {
@@ -369,7 +407,8 @@ impl MessageType for ResponseHead {
         msg.version = ver;
 
         // convert headers
-        let mut length = msg.set_headers(&src.split_to(len).freeze(), ver, headers)?;
+        let mut length =
+            msg.set_headers(&src.split_to(len).freeze(), ver, headers, preserve_case)?;
 
         // Remove CL value if 0 now that all headers and HTTP/1.0 special cases are processed.
         // Protects against some request smuggling attacks.
@@ -816,6 +855,30 @@ mod tests {
         assert_eq!(req.path(), "/test");
     }
 
+    #[test]
+    fn test_parse_preserve_case() {
+        let mut buf = BytesMut::from(
+            "GET /test HTTP/1.1\r\n\
+             X-Custom-HEADER: value\r\n\
+             accept: */*\r\n\r\n",
+        );
+
+        let mut reader = MessageDecoder::<Request>::default();
+        reader.preserve_case(true);
+        let (req, _) = reader.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(req.headers().get("x-custom-header").unwrap(), "value");
+        assert_eq!(
+            req.headers().original_case("x-custom-header"),
+            Some("X-Custom-HEADER")
+        );
+        assert_eq!(req.headers().original_case(header::ACCEPT), None);
+
+        let mut buf = BytesMut::from("GET /test HTTP/1.1\r\nX-Custom: value\r\n\r\n");
+        let reader = MessageDecoder::<Request>::default();
+        let (req, _) = reader.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(req.headers().original_case("x-custom"), None);
+    }
+
     #[test]
     fn parse_h10_get() {
         let mut buf = BytesMut::from(
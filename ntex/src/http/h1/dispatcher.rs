@@ -1,15 +1,19 @@
 //! HTTP/1 protocol dispatcher
-use std::{error, future, io, marker, pin::Pin, rc::Rc, task::Context, task::Poll};
+use std::{error, future, io, marker, mem, pin::Pin, rc::Rc, task::Context, task::Poll};
 
-use crate::io::{Decoded, Filter, Io, IoBoxed, IoStatusUpdate, RecvError};
+use crate::codec::Encoder;
+use crate::io::{
+    Decoded, DisconnectReason, Filter, Io, IoBoxed, IoStatusUpdate, RecvError,
+};
 use crate::service::{PipelineCall, Service};
-use crate::time::Seconds;
-use crate::util::{ready, Either};
+use crate::time::{sleep, Seconds, Sleep};
+use crate::util::{ready, BytesVec, Either};
 
 use crate::http::body::{BodySize, MessageBody, ResponseBody};
-use crate::http::error::{PayloadError, ResponseError};
+use crate::http::config::{DispatcherConfig, FlushStrategy, ReadRate};
+use crate::http::error::{EncodeError, PayloadError, ResponseError};
 use crate::http::message::{ConnectionType, CurrentIo};
-use crate::http::{self, config::DispatcherConfig, request::Request, response::Response};
+use crate::http::{self, request::Request, response::Response};
 
 use super::control::{Control, ControlAck, ControlFlags, ControlResult};
 use super::decoder::{PayloadDecoder, PayloadItem, PayloadType};
@@ -84,6 +88,8 @@ struct DispatcherInner<F, C, S, B> {
     read_remains: u32,
     read_consumed: u32,
     read_max_timeout: Seconds,
+    write_buf: Option<BytesVec>,
+    flush_timer: Option<Sleep>,
     _t: marker::PhantomData<(S, B)>,
 }
 
@@ -98,7 +104,8 @@ where
 {
     /// Construct new `Dispatcher` instance with outgoing messages stream.
     pub(in crate::http) fn new(io: Io<F>, config: Rc<DispatcherConfig<S, C>>) -> Self {
-        let codec = Codec::new(config.timer.clone(), config.keep_alive_enabled());
+        let codec = Codec::new(config.timer.clone(), config.keep_alive_enabled())
+            .preserve_header_case(config.preserve_case);
         io.set_disconnect_timeout(config.client_disconnect);
 
         // slow-request timer
@@ -120,6 +127,8 @@ where
                 read_remains: 0,
                 read_consumed: 0,
                 read_max_timeout: max_timeout,
+                write_buf: None,
+                flush_timer: None,
                 _t: marker::PhantomData,
             },
         }
@@ -321,6 +330,7 @@ where
                     }
                 } else {
                     log::trace!("{}: Keep-alive timeout, close connection", self.io.tag());
+                    self.io.set_disconnect_reason(DisconnectReason::KeepAlive);
                     self.stop()
                 }
             }
@@ -351,19 +361,19 @@ where
         if self.io.is_closed() {
             self.stop()
         } else {
-            let result = self
-                .io
-                .encode(Message::Item((msg, body.size())), &self.codec)
-                .map_err(|err| {
-                    if let Some(mut payload) = self.payload.take() {
-                        payload.1.set_error(PayloadError::Incomplete(None));
-                    }
-                    err
-                });
+            let result =
+                self.write_item(Message::Item((msg, body.size())))
+                    .map_err(|err| {
+                        if let Some(mut payload) = self.payload.take() {
+                            payload.1.set_error(PayloadError::Incomplete(None));
+                        }
+                        err
+                    });
 
             match result {
                 Ok(()) => match body.size() {
                     BodySize::None | BodySize::Empty => {
+                        self.flush_write_buf();
                         if self
                             .flags
                             .intersects(Flags::DISCONNECT | Flags::SENDPAYLOAD_AND_STOP)
@@ -377,8 +387,14 @@ where
                     }
                     _ => State::SendPayload { body },
                 },
-                Err(_) if self.flags.contains(Flags::DISCONNECT) => self.stop(),
-                Err(err) => self.ctl_proto_err(err.into()),
+                Err(_) if self.flags.contains(Flags::DISCONNECT) => {
+                    self.flush_write_buf();
+                    self.stop()
+                }
+                Err(err) => {
+                    self.flush_write_buf();
+                    self.ctl_proto_err(err.into())
+                }
             }
         }
     }
@@ -397,19 +413,33 @@ where
         }
         loop {
             let _ = ready!(self.io.poll_flush(cx, false));
-            let item = ready!(body.poll_next_chunk(cx));
+            if let Some(ref timer) = self.flush_timer {
+                if timer.poll_elapsed(cx).is_ready() {
+                    self.flush_write_buf();
+                }
+            }
+            let item = match body.poll_next_chunk(cx) {
+                Poll::Ready(item) => item,
+                Poll::Pending => {
+                    // no more ready data, pass held data to the io
+                    if let FlushStrategy::Threshold(_) = self.config.flush_strategy {
+                        self.flush_write_buf();
+                    }
+                    return Poll::Pending;
+                }
+            };
 
             let st = match item {
                 Some(Ok(item)) => {
                     log::trace!("{}: Got response chunk: {:?}", self.io.tag(), item.len());
-                    match self.io.encode(Message::Chunk(Some(item)), &self.codec) {
+                    match self.write_item(Message::Chunk(Some(item))) {
                         Ok(_) => continue,
                         Err(err) => self.ctl_proto_err(err.into()),
                     }
                 }
                 None => {
                     log::trace!("{}: Response payload eof {:?}", self.io.tag(), self.flags);
-                    if let Err(err) = self.io.encode(Message::Chunk(None), &self.codec) {
+                    if let Err(err) = self.write_item(Message::Chunk(None)) {
                         self.ctl_proto_err(err.into())
                     } else if self.flags.contains(Flags::DISCONNECT) {
                         self.stop()
@@ -428,10 +458,57 @@ where
                     self.ctl_proto_err(ProtocolError::ResponsePayload(err))
                 }
             };
+            self.flush_write_buf();
             return Poll::Ready(st);
         }
     }
 
+    /// Encode response item according to flush strategy
+    fn write_item(
+        &mut self,
+        item: Message<(Response<()>, BodySize)>,
+    ) -> Result<(), EncodeError> {
+        let limit = match self.config.flush_strategy {
+            FlushStrategy::Immediate => return self.io.encode(item, &self.codec),
+            FlushStrategy::Coalesce(_) => self.io.memory_pool().write_params_high(),
+            FlushStrategy::Threshold(size) => size,
+        };
+
+        let pool = self.io.memory_pool();
+        let buf = self.write_buf.get_or_insert_with(|| pool.get_write_buf());
+        pool.resize_write_buf(buf);
+        self.codec.encode_vec(item, buf)?;
+
+        if buf.len() >= limit {
+            self.flush_write_buf();
+        } else if let FlushStrategy::Coalesce(period) = self.config.flush_strategy {
+            if self.flush_timer.is_none() {
+                self.flush_timer = Some(sleep(period));
+            }
+        }
+        Ok(())
+    }
+
+    /// Pass held response data to the io
+    fn flush_write_buf(&mut self) {
+        self.flush_timer = None;
+        if let Some(mut buf) = self.write_buf.take() {
+            if !buf.is_empty() && !self.io.is_closed() {
+                let result = self.io.with_write_buf(|dst| {
+                    if dst.is_empty() {
+                        mem::swap(dst, &mut buf);
+                    } else {
+                        dst.extend_from_slice(&buf);
+                    }
+                });
+                if let Err(err) = result {
+                    log::trace!("{}: Cannot write response: {:?}", self.io.tag(), err);
+                }
+            }
+            self.io.memory_pool().release_write_buf(buf);
+        }
+    }
+
     fn send_response_to(
         &mut self,
         res: Response<()>,
@@ -665,15 +742,20 @@ where
     }
 
     fn handle_timeout(&mut self) -> Result<(), ProtocolError> {
+        // read rate is overridden for request payload
+        if self.flags.contains(Flags::READ_PL_TIMEOUT) && self.rearm_payload_timer() {
+            return Ok(());
+        }
+
         // check read rate
         if self
             .flags
             .intersects(Flags::READ_PL_TIMEOUT | Flags::READ_HDRS_TIMEOUT)
         {
             let cfg = if self.flags.contains(Flags::READ_HDRS_TIMEOUT) {
-                &self.config.headers_read_rate
+                self.config.headers_read_rate
             } else {
-                &self.config.payload_read_rate
+                self.payload_read_rate()
             };
 
             if let Some(ref cfg) = cfg {
@@ -776,11 +858,50 @@ where
         None
     }
 
+    /// Payload read rate, could be overridden per request
+    fn payload_read_rate(&self) -> Option<ReadRate> {
+        self.payload
+            .as_ref()
+            .and_then(|(_, pl)| pl.read_rate())
+            .unwrap_or(self.config.payload_read_rate)
+    }
+
+    /// Restart payload timer if read rate is overridden for request payload
+    fn rearm_payload_timer(&mut self) -> bool {
+        let update = self
+            .payload
+            .as_ref()
+            .and_then(|(_, pl)| pl.take_read_rate_update());
+
+        match update {
+            Some(Some(cfg)) => {
+                log::trace!("{}: Restart payload read timer {:?}", self.io.tag(), cfg);
+                self.flags.insert(Flags::READ_PL_TIMEOUT);
+                self.read_remains = 0;
+                self.read_consumed = 0;
+                self.read_max_timeout = cfg.max_timeout;
+                self.io.start_timer(cfg.timeout);
+                true
+            }
+            Some(None) => {
+                log::trace!("{}: Payload read rate is disabled", self.io.tag());
+                if self.flags.contains(Flags::READ_PL_TIMEOUT) {
+                    self.flags.remove(Flags::READ_PL_TIMEOUT);
+                    self.io.stop_timer();
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
     fn update_payload_timer(&mut self, decoded: &Decoded<PayloadItem>) {
+        self.rearm_payload_timer();
+
         if self.flags.contains(Flags::READ_PL_TIMEOUT) {
             self.read_remains = decoded.remains as u32;
             self.read_consumed += decoded.consumed as u32;
-        } else if let Some(ref cfg) = self.config.payload_read_rate {
+        } else if let Some(ref cfg) = self.payload_read_rate() {
             // start payload timer
             self.flags.insert(Flags::READ_PL_TIMEOUT);
 
@@ -821,6 +942,7 @@ where
     }
 
     fn stop(&mut self) -> State<F, C, S, B> {
+        self.flush_write_buf();
         State::Stop {
             io: None,
             fut: Some(self.config.control.call_nowait(Control::closed())),
@@ -1274,6 +1396,90 @@ mod tests {
         assert!(lazy(|cx| Pin::new(&mut h1).poll(cx)).await.is_ready());
     }
 
+    #[crate::rt_test]
+    async fn test_flush_strategy() {
+        // two ready chunks, then no data
+        struct Stream(usize);
+
+        impl body::MessageBody for Stream {
+            fn size(&self) -> body::BodySize {
+                body::BodySize::Stream
+            }
+            fn poll_next_chunk(
+                &mut self,
+                _: &mut Context<'_>,
+            ) -> Poll<Option<Result<Bytes, Box<dyn error::Error>>>> {
+                if self.0 == 0 {
+                    Poll::Pending
+                } else {
+                    self.0 -= 1;
+                    Poll::Ready(Some(Ok(Bytes::from_static(b"chunk"))))
+                }
+            }
+        }
+
+        fn h1_flush(
+            stream: Io,
+            strategy: FlushStrategy,
+        ) -> impl Future<Output = Result<(), Box<dyn error::Error>>> {
+            let mut config = ServiceConfig::new(
+                Seconds(5).into(),
+                Seconds(1),
+                Seconds::ZERO,
+                Millis(5_000),
+                Config::server(),
+            );
+            config.flush_strategy(strategy);
+
+            let service = fn_service(|_: Request| async {
+                Ok::<_, io::Error>(Response::Ok().message_body(Stream(2)))
+            });
+            Dispatcher::<Base, _, Stream, _>::new(
+                nio::Io::new(stream),
+                Rc::new(DispatcherConfig::new(
+                    config,
+                    service,
+                    DefaultControlService,
+                )),
+            )
+        }
+
+        // ready data is held until body stream has no data
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(4096);
+        let mut h1 = Box::pin(h1_flush(server, FlushStrategy::Threshold(4096)));
+
+        client.write("GET /test HTTP/1.1\r\n\r\n");
+        sleep(Millis(50)).await;
+        assert!(lazy(|cx| h1.as_mut().poll(cx)).await.is_pending());
+        sleep(Millis(50)).await;
+
+        let mut decoder = ClientCodec::default();
+        let mut buf = BytesMut::from(&client.read_any()[..]);
+        assert!(load(&mut decoder, &mut buf).status.is_success());
+        assert!(buf.ends_with(b"chunk\r\n5\r\nchunk\r\n"));
+
+        // data is held for coalesce period
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(4096);
+        let mut h1 = Box::pin(h1_flush(server, FlushStrategy::Coalesce(Millis(150))));
+
+        client.write("GET /test HTTP/1.1\r\n\r\n");
+        sleep(Millis(50)).await;
+        assert!(lazy(|cx| h1.as_mut().poll(cx)).await.is_pending());
+        sleep(Millis(20)).await;
+        assert!(client.read_any().is_empty());
+
+        sleep(Millis(200)).await;
+        assert!(lazy(|cx| h1.as_mut().poll(cx)).await.is_pending());
+        sleep(Millis(50)).await;
+
+        let mut decoder = ClientCodec::default();
+        let mut buf = BytesMut::from(&client.read_any()[..]);
+        assert!(load(&mut decoder, &mut buf).status.is_success());
+        assert!(buf.ends_with(b"chunk\r\n"));
+    }
+
     #[crate::rt_test]
     async fn test_service_error() {
         let (client, server) = Io::create();
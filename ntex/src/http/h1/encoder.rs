@@ -1,5 +1,5 @@
 use std::marker::PhantomData;
-use std::{cell::Cell, cmp, io::Write, mem, ptr, ptr::copy_nonoverlapping, slice};
+use std::{cell::Cell, cmp, fmt, io::Write, mem, ptr, ptr::copy_nonoverlapping, slice};
 
 use crate::http::body::BodySize;
 use crate::http::config::DateService;
@@ -123,7 +123,12 @@ pub(super) trait MessageType: Sized {
                 }
                 _ => (),
             }
-            let k = key.as_str().as_bytes();
+            // original header name casing, if recorded
+            let k = extra_headers
+                .original_case(key)
+                .or_else(|| self.headers().original_case(key))
+                .unwrap_or(key.as_str())
+                .as_bytes();
             match value {
                 Value::One(ref val) => {
                     let v = val.as_ref();
@@ -246,11 +251,20 @@ impl MessageType for RequestHeadType {
     fn encode_status(&self, dst: &mut BytesMut) -> Result<(), EncodeError> {
         let head = self.as_ref();
         dst.reserve(256 + head.headers.len() * AVERAGE_HEADER_SIZE);
+
+        // absolute form is possible for uris with scheme only
+        let path = head.uri.path_and_query().map(|u| u.as_str()).unwrap_or("/");
+        let target: &dyn fmt::Display =
+            if head.absolute_form() && head.uri.scheme().is_some() {
+                &head.uri
+            } else {
+                &path
+            };
         write!(
             helpers::Writer(dst),
             "{} {} {}",
             head.method,
-            head.uri.path_and_query().map(|u| u.as_str()).unwrap_or("/"),
+            target,
             // only HTTP-0.9/1.1
             match head.version {
                 Version::HTTP_09 => "HTTP/0.9",
@@ -276,6 +290,11 @@ impl<T: MessageType> MessageEncoder<T> {
         result
     }
 
+    /// Switch transfer encoding to eof, payload is written as is
+    pub(super) fn set_eof(&self) {
+        self.te.set(TransferEncoding::eof());
+    }
+
     /// Encode eof
     pub(super) fn encode_eof(&self, buf: &mut BytesMut) -> Result<(), EncodeError> {
         let mut te = self.te.get();
@@ -591,7 +610,7 @@ mod tests {
 
     use super::*;
     use crate::http::header::{HeaderValue, AUTHORIZATION};
-    use crate::http::RequestHead;
+    use crate::http::{RequestHead, Uri};
     use crate::util::Bytes;
 
     #[test]
@@ -638,6 +657,50 @@ mod tests {
         assert!(data.contains("date: date\r\n"));
     }
 
+    #[test]
+    fn test_original_case() {
+        let mut bytes = BytesMut::with_capacity(2048);
+
+        let mut head = RequestHead::default();
+        head.headers
+            .insert(AUTHORIZATION, HeaderValue::from_static("auth"));
+        head.headers
+            .set_original_case(&AUTHORIZATION, "AUTHorization");
+        head.headers.insert(DATE, HeaderValue::from_static("date"));
+
+        let head = RequestHeadType::Owned(head);
+        let _ = head.encode_headers(
+            &mut bytes,
+            Version::HTTP_11,
+            BodySize::Empty,
+            ConnectionType::Close,
+            &DateService::default(),
+        );
+        let data = String::from_utf8(Vec::from(bytes.split().as_ref())).unwrap();
+        assert!(data.contains("AUTHorization: auth\r\n"));
+        assert!(data.contains("date: date\r\n"));
+    }
+
+    #[test]
+    fn test_absolute_form() {
+        let mut bytes = BytesMut::with_capacity(2048);
+
+        let mut head = RequestHead::default();
+        head.uri = Uri::from_static("http://example.com/test?q=1");
+        let mut head = RequestHeadType::Owned(head);
+        head.encode_status(&mut bytes).unwrap();
+        assert_eq!(bytes.split(), b"GET /test?q=1 HTTP/1.1"[..]);
+
+        if let RequestHeadType::Owned(ref mut head) = head {
+            head.set_absolute_form(true);
+        }
+        head.encode_status(&mut bytes).unwrap();
+        assert_eq!(
+            bytes.split(),
+            b"GET http://example.com/test?q=1 HTTP/1.1"[..]
+        );
+    }
+
     #[test]
     fn test_write_content_length() {
         let mut bytes = BytesMut::new();
@@ -3,8 +3,8 @@ use std::rc::{Rc, Weak};
 use std::task::{Context, Poll};
 use std::{cell::RefCell, collections::VecDeque, pin::Pin};
 
-use crate::http::error::PayloadError;
-use crate::{task::LocalWaker, util::Bytes, util::Stream};
+use crate::http::{config::ReadRate, error::PayloadError};
+use crate::{task::LocalWaker, time::Seconds, util::Bytes, util::Stream};
 
 /// max buffer size 32k
 const MAX_BUFFER_SIZE: usize = 32_768;
@@ -62,6 +62,24 @@ impl Payload {
         self.inner.borrow_mut().unread_data(data);
     }
 
+    /// Override read rate parameters for this payload.
+    ///
+    /// Overrides server-wide `ServiceConfig::payload_read_rate()` setting.
+    /// To disable read rate check set `timeout` to 0.
+    pub fn set_read_rate(&self, timeout: Seconds, max_timeout: Seconds, rate: u16) {
+        let mut inner = self.inner.borrow_mut();
+        inner.read_rate = if timeout.is_zero() {
+            Some(None)
+        } else {
+            Some(Some(ReadRate {
+                rate,
+                timeout,
+                max_timeout,
+            }))
+        };
+        inner.read_rate_updated = true;
+    }
+
     #[inline]
     pub fn readany(
         &mut self,
@@ -115,6 +133,26 @@ impl PayloadSender {
         }
     }
 
+    /// Read rate override, if set for payload
+    pub(super) fn read_rate(&self) -> Option<Option<ReadRate>> {
+        self.inner
+            .upgrade()
+            .and_then(|shared| shared.borrow().read_rate)
+    }
+
+    /// Read rate override, if it is updated since last call
+    pub(super) fn take_read_rate_update(&self) -> Option<Option<ReadRate>> {
+        self.inner.upgrade().and_then(|shared| {
+            let mut inner = shared.borrow_mut();
+            if inner.read_rate_updated {
+                inner.read_rate_updated = false;
+                inner.read_rate
+            } else {
+                None
+            }
+        })
+    }
+
     pub(super) fn poll_data_required(&self, cx: &mut Context<'_>) -> PayloadStatus {
         // we check only if Payload (other side) is alive,
         // otherwise always return true (consume payload)
@@ -137,6 +175,8 @@ struct Inner {
     eof: bool,
     err: Option<PayloadError>,
     need_read: bool,
+    read_rate: Option<Option<ReadRate>>,
+    read_rate_updated: bool,
     items: VecDeque<Bytes>,
     task: LocalWaker,
     io_task: LocalWaker,
@@ -150,6 +190,8 @@ impl Inner {
             err: None,
             items: VecDeque::new(),
             need_read: true,
+            read_rate: None,
+            read_rate_updated: false,
             task: LocalWaker::new(),
             io_task: LocalWaker::new(),
         }
@@ -221,4 +263,27 @@ mod tests {
             poll_fn(|cx| payload.readany(cx)).await.unwrap().unwrap()
         );
     }
+
+    #[crate::rt_test]
+    async fn test_read_rate() {
+        let (sender, payload) = Payload::create(false);
+        assert_eq!(sender.read_rate(), None);
+
+        payload.set_read_rate(Seconds(1), Seconds(10), 128);
+        assert_eq!(
+            sender.read_rate(),
+            Some(Some(ReadRate {
+                rate: 128,
+                timeout: Seconds(1),
+                max_timeout: Seconds(10),
+            }))
+        );
+
+        assert!(sender.take_read_rate_update().is_some());
+        assert_eq!(sender.take_read_rate_update(), None);
+
+        payload.set_read_rate(Seconds::ZERO, Seconds::ZERO, 0);
+        assert_eq!(sender.read_rate(), Some(None));
+        assert_eq!(sender.take_read_rate_update(), Some(None));
+    }
 }
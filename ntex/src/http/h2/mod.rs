@@ -2,6 +2,7 @@
 mod default;
 pub(super) mod payload;
 mod service;
+mod shutdown;
 
 pub use ntex_h2::{Config, Control, ControlAck};
 
@@ -13,3 +14,4 @@ pub use self::payload::Payload;
 pub use self::service::H2Service;
 
 pub(in crate::http) use self::service::handle;
+pub(in crate::http) use self::shutdown::Connections;
@@ -1,7 +1,8 @@
 use std::{cell::RefCell, io, task::Context, task::Poll};
 use std::{error::Error, fmt, future::poll_fn, marker, mem, rc::Rc};
 
-use ntex_h2::{self as h2, frame::StreamId, server};
+use ntex_h2::frame::{Reason, StreamId};
+use ntex_h2::{self as h2, server};
 
 use crate::http::body::{BodySize, MessageBody};
 use crate::http::config::{DispatcherConfig, ServiceConfig};
@@ -11,9 +12,10 @@ use crate::http::message::{CurrentIo, ResponseHead};
 use crate::http::{DateService, Method, Request, Response, StatusCode, Uri, Version};
 use crate::io::{types, Filter, Io, IoBoxed, IoRef};
 use crate::service::{IntoServiceFactory, Service, ServiceCtx, ServiceFactory};
-use crate::util::{Bytes, BytesMut, HashMap};
+use crate::util::{ready, Bytes, BytesMut, HashMap};
 
 use super::payload::{Payload, PayloadSender};
+use super::shutdown::Connection;
 use super::DefaultControlService;
 
 /// `ServiceFactory` implementation for HTTP2 transport
@@ -217,6 +219,7 @@ where
     }
 
     fn poll_shutdown(&self, cx: &mut Context<'_>) -> Poll<()> {
+        ready!(self.config.h2_conns.poll_shutdown(cx));
         self.config.service.poll_shutdown(cx)
     }
 
@@ -254,12 +257,13 @@ where
 {
     io.set_disconnect_timeout(config.client_disconnect);
     let ioref = io.get_ref();
+    let conn = config.h2_conns.register(ioref.clone());
 
     let _ = server::handle_one(
         io,
         config.h2config.clone(),
         control,
-        PublishService::new(ioref, config),
+        PublishService::new(ioref, conn, config),
     )
     .await;
 
@@ -268,6 +272,7 @@ where
 
 struct PublishService<S: Service<Request>, B, C> {
     io: IoRef,
+    conn: Connection,
     config: Rc<DispatcherConfig<S, C>>,
     streams: RefCell<HashMap<StreamId, PayloadSender>>,
     _t: marker::PhantomData<B>,
@@ -280,9 +285,10 @@ where
     S::Response: Into<Response<B>>,
     B: MessageBody,
 {
-    fn new(io: IoRef, config: Rc<DispatcherConfig<S, C>>) -> Self {
+    fn new(io: IoRef, conn: Connection, config: Rc<DispatcherConfig<S, C>>) -> Self {
         Self {
             io,
+            conn,
             config,
             streams: RefCell::new(HashMap::default()),
             _t: marker::PhantomData,
@@ -307,12 +313,21 @@ where
         _: ServiceCtx<'_, Self>,
     ) -> Result<Self::Response, Self::Error> {
         let h2::Message { stream, kind } = msg;
-        let (io, pseudo, headers, eof, payload) = match kind {
+        let (io, pseudo, headers, eof, payload, _guard) = match kind {
             h2::MessageKind::Headers {
                 pseudo,
                 headers,
                 eof,
             } => {
+                // refuse streams opened after final GOAWAY frame
+                let guard = if let Some(guard) = self.conn.start_stream(stream.id()) {
+                    guard
+                } else {
+                    log::debug!("Refusing stream {:?} on shutdown", stream.id());
+                    stream.reset(Reason::REFUSED_STREAM);
+                    return Ok(());
+                };
+
                 let pl = if !eof {
                     log::debug!("Creating local payload stream for {:?}", stream.id());
                     let (sender, payload) = Payload::create(stream.empty_capacity());
@@ -321,7 +336,7 @@ where
                 } else {
                     None
                 };
-                (self.io.clone(), pseudo, headers, eof, pl)
+                (self.io.clone(), pseudo, headers, eof, pl, guard)
             }
             h2::MessageKind::Data(data, cap) => {
                 log::debug!("Got data chunk for {:?}: {:?}", stream.id(), data.len());
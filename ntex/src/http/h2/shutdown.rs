@@ -0,0 +1,245 @@
+use std::{cell::Cell, cell::RefCell, rc::Rc, task::Context, task::Poll};
+
+use ntex_h2::frame::{GoAway, Reason, StreamId};
+use ntex_h2::Codec;
+
+use crate::io::IoRef;
+use crate::task::LocalWaker;
+use crate::time::{sleep, Millis, Sleep};
+use crate::util::HashMap;
+
+/// Registry of active http/2 connections
+///
+/// On graceful shutdown every connection receives GOAWAY frame with max
+/// stream id, so clients stop opening new streams but requests in transit
+/// still get processed. After drain window final GOAWAY with last accepted
+/// stream id gets sent, newer streams are refused and connection is closed
+/// as soon as in-flight streams complete.
+///
+/// `ntex_h2` does not provide api for server initiated GOAWAY, frames are
+/// encoded with h2 codec directly to connection's write buffer.
+#[derive(Clone)]
+pub(in crate::http) struct Connections(Rc<Shared>);
+
+struct Shared {
+    drain: Millis,
+    codec: Codec,
+    inner: RefCell<Inner>,
+    waker: LocalWaker,
+}
+
+struct Inner {
+    next: usize,
+    conns: HashMap<usize, Rc<ConnState>>,
+    state: State,
+}
+
+enum State {
+    Running,
+    Draining(Sleep),
+    Closing(Sleep),
+    Done,
+}
+
+impl Connections {
+    pub(in crate::http) fn new(drain: Millis) -> Self {
+        Connections(Rc::new(Shared {
+            drain,
+            codec: Codec::default(),
+            waker: LocalWaker::new(),
+            inner: RefCell::new(Inner {
+                next: 0,
+                conns: HashMap::default(),
+                state: State::Running,
+            }),
+        }))
+    }
+
+    /// Register new connection
+    pub(super) fn register(&self, io: IoRef) -> Connection {
+        let state = Rc::new(ConnState {
+            io,
+            last_stream: Cell::new(StreamId::zero()),
+            streams: Cell::new(0),
+            closing: Cell::new(false),
+        });
+
+        let mut inner = self.0.inner.borrow_mut();
+        match inner.state {
+            State::Running => (),
+            State::Draining(_) => state.goaway(StreamId::MAX, &self.0.codec),
+            State::Closing(_) | State::Done => state.close(&self.0.codec),
+        }
+
+        let id = inner.next;
+        inner.next = inner.next.wrapping_add(1);
+        inner.conns.insert(id, state.clone());
+
+        Connection {
+            id,
+            state,
+            shared: self.0.clone(),
+        }
+    }
+
+    /// Gracefully shutdown registered connections
+    pub(in crate::http) fn poll_shutdown(&self, cx: &mut Context<'_>) -> Poll<()> {
+        let inner = &mut *self.0.inner.borrow_mut();
+
+        loop {
+            match inner.state {
+                State::Running => {
+                    if self.0.drain.is_zero() || inner.conns.is_empty() {
+                        inner.state = State::Done;
+                    } else {
+                        log::trace!(
+                            "Sending GOAWAY to {} http/2 connections",
+                            inner.conns.len()
+                        );
+                        for conn in inner.conns.values() {
+                            conn.goaway(StreamId::MAX, &self.0.codec);
+                        }
+                        inner.state = State::Draining(sleep(self.0.drain));
+                    }
+                }
+                State::Draining(ref timer) => {
+                    if timer.poll_elapsed(cx).is_pending() {
+                        return Poll::Pending;
+                    }
+                    for conn in inner.conns.values() {
+                        conn.close(&self.0.codec);
+                    }
+                    inner.state = State::Closing(sleep(self.0.drain));
+                }
+                State::Closing(ref timer) => {
+                    if inner.conns.is_empty() || timer.poll_elapsed(cx).is_ready() {
+                        inner.state = State::Done;
+                    } else {
+                        self.0.waker.register(cx.waker());
+                        return Poll::Pending;
+                    }
+                }
+                State::Done => return Poll::Ready(()),
+            }
+        }
+    }
+}
+
+/// Registered http/2 connection
+pub(super) struct Connection {
+    id: usize,
+    state: Rc<ConnState>,
+    shared: Rc<Shared>,
+}
+
+struct ConnState {
+    io: IoRef,
+    last_stream: Cell<StreamId>,
+    streams: Cell<usize>,
+    closing: Cell<bool>,
+}
+
+impl Connection {
+    /// Start processing of new stream
+    ///
+    /// Returns `None` if stream is opened after final GOAWAY frame.
+    pub(super) fn start_stream(&self, id: StreamId) -> Option<StreamGuard<'_>> {
+        let st = &self.state;
+        if id > st.last_stream.get() {
+            if st.closing.get() {
+                return None;
+            }
+            st.last_stream.set(id);
+        }
+        st.streams.set(st.streams.get() + 1);
+        Some(StreamGuard(st))
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        let mut inner = self.shared.inner.borrow_mut();
+        inner.conns.remove(&self.id);
+        if inner.conns.is_empty() {
+            self.shared.waker.wake();
+        }
+    }
+}
+
+impl ConnState {
+    fn goaway(&self, last: StreamId, codec: &Codec) {
+        let frm = GoAway::new(Reason::NO_ERROR).set_last_stream_id(last);
+        let _ = self.io.encode(frm.into(), codec);
+    }
+
+    /// Send final GOAWAY frame
+    fn close(&self, codec: &Codec) {
+        if !self.closing.get() {
+            self.closing.set(true);
+            self.goaway(self.last_stream.get(), codec);
+            if self.streams.get() == 0 {
+                self.io.close();
+            }
+        }
+    }
+}
+
+/// In-flight stream guard
+pub(super) struct StreamGuard<'a>(&'a ConnState);
+
+impl Drop for StreamGuard<'_> {
+    fn drop(&mut self) {
+        let streams = self.0.streams.get() - 1;
+        self.0.streams.set(streams);
+        if streams == 0 && self.0.closing.get() {
+            self.0.io.close();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::{poll_fn, Future};
+
+    use super::*;
+    use crate::{io::Io, testing::IoTest};
+
+    fn last_stream_id(buf: &[u8]) -> StreamId {
+        GoAway::load(&buf[9..]).unwrap().last_stream_id()
+    }
+
+    #[crate::rt_test]
+    async fn test_graceful_shutdown() {
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+        let io = Io::new(server);
+
+        let conns = Connections::new(Millis(100));
+        let conn = conns.register(io.get_ref());
+        let guard = conn.start_stream(StreamId::from(1)).unwrap();
+
+        // first goaway, new streams are still accepted
+        let fut = poll_fn(|cx| conns.poll_shutdown(cx));
+        let mut fut = std::pin::pin!(fut);
+        assert!(poll_fn(|cx| Poll::Ready(fut.as_mut().poll(cx).is_pending())).await);
+        crate::time::sleep(Millis(50)).await;
+        assert_eq!(last_stream_id(&client.read_any()), StreamId::MAX);
+        let guard2 = conn.start_stream(StreamId::from(3)).unwrap();
+        drop(guard2);
+
+        // final goaway
+        crate::time::sleep(Millis(100)).await;
+        assert!(poll_fn(|cx| Poll::Ready(fut.as_mut().poll(cx).is_pending())).await);
+        crate::time::sleep(Millis(50)).await;
+        assert_eq!(last_stream_id(&client.read_any()), StreamId::from(3));
+        assert!(conn.start_stream(StreamId::from(5)).is_none());
+        assert!(!io.is_closed());
+
+        // connection get closed after last stream
+        drop(guard);
+        crate::time::sleep(Millis(50)).await;
+        assert!(io.is_closed());
+        drop(conn);
+        fut.await;
+    }
+}
@@ -18,6 +18,8 @@ pub enum ContentEncoding {
     Deflate,
     /// Gzip algorithm
     Gzip,
+    /// A format using the Zstandard algorithm
+    Zstd,
     /// Indicates the identity function (i.e. no compression, nor modification)
     Identity,
 }
@@ -36,6 +38,7 @@ impl ContentEncoding {
             ContentEncoding::Br => "br",
             ContentEncoding::Gzip => "gzip",
             ContentEncoding::Deflate => "deflate",
+            ContentEncoding::Zstd => "zstd",
             ContentEncoding::Identity | ContentEncoding::Auto => "identity",
         }
     }
@@ -47,6 +50,7 @@ impl ContentEncoding {
             ContentEncoding::Br => 1.1,
             ContentEncoding::Gzip => 1.0,
             ContentEncoding::Deflate => 0.9,
+            ContentEncoding::Zstd => 0.8,
             ContentEncoding::Identity | ContentEncoding::Auto => 0.1,
         }
     }
@@ -62,6 +66,8 @@ impl<'a> From<&'a str> for ContentEncoding {
             ContentEncoding::Gzip
         } else if s.eq_ignore_ascii_case("deflate") {
             ContentEncoding::Deflate
+        } else if s.eq_ignore_ascii_case("zstd") {
+            ContentEncoding::Zstd
         } else {
             ContentEncoding::Identity
         }
@@ -78,5 +84,7 @@ mod tests {
         assert!(!ContentEncoding::Identity.is_compressed());
         assert!(!ContentEncoding::Auto.is_compressed());
         assert_eq!(format!("{:?}", ContentEncoding::Identity), "Identity");
+        assert_eq!(ContentEncoding::from("zstd"), ContentEncoding::Zstd);
+        assert_eq!(ContentEncoding::Zstd.as_str(), "zstd");
     }
 }
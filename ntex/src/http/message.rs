@@ -26,6 +26,8 @@ bitflags! {
         const UPGRADE     = 0b0000_0100;
         const EXPECT      = 0b0000_1000;
         const NO_CHUNKING = 0b0001_0000;
+        const ABSOLUTE_FORM = 0b0010_0000;
+        const PRESERVE_CASE = 0b0100_0000;
     }
 }
 
@@ -182,6 +184,38 @@ impl RequestHead {
         }
     }
 
+    #[inline]
+    /// Request target is sent in absolute form
+    pub fn absolute_form(&self) -> bool {
+        self.flags.contains(Flags::ABSOLUTE_FORM)
+    }
+
+    #[inline]
+    /// Send request target in absolute form, `GET http://host/path HTTP/1.1`
+    pub fn set_absolute_form(&mut self, val: bool) {
+        if val {
+            self.flags.insert(Flags::ABSOLUTE_FORM);
+        } else {
+            self.flags.remove(Flags::ABSOLUTE_FORM);
+        }
+    }
+
+    #[inline]
+    /// Response header names casing is preserved
+    pub fn preserve_header_case(&self) -> bool {
+        self.flags.contains(Flags::PRESERVE_CASE)
+    }
+
+    #[inline]
+    /// Record original casing of response header names, http/1 only
+    pub fn set_preserve_header_case(&mut self, val: bool) {
+        if val {
+            self.flags.insert(Flags::PRESERVE_CASE);
+        } else {
+            self.flags.remove(Flags::PRESERVE_CASE);
+        }
+    }
+
     #[inline]
     pub(crate) fn set_expect(&mut self) {
         self.flags.insert(Flags::EXPECT);
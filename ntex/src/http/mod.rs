@@ -18,13 +18,14 @@ pub mod error;
 pub mod h1;
 pub mod h2;
 pub mod header;
+pub mod range;
 pub mod test;
 
 pub(crate) use self::message::Message;
 
 pub use self::builder::HttpServiceBuilder;
 pub use self::client::Client;
-pub use self::config::{DateService, KeepAlive, ServiceConfig};
+pub use self::config::{DateService, FlushStrategy, KeepAlive, ServiceConfig};
 pub use self::error::ResponseError;
 pub use self::httpmessage::HttpMessage;
 pub use self::message::{ConnectionType, RequestHead, RequestHeadType, ResponseHead};
@@ -1,7 +1,7 @@
 use std::{fmt, future::poll_fn, mem, pin::Pin, task::Context, task::Poll};
 
 use super::{error::PayloadError, h1, h2};
-use crate::util::{Bytes, Stream};
+use crate::{time::Seconds, util::Bytes, util::Stream};
 
 /// Type represent boxed payload
 pub type PayloadStream = Pin<Box<dyn Stream<Item = Result<Bytes, PayloadError>>>>;
@@ -59,6 +59,17 @@ impl Payload {
         Payload::Stream(Box::pin(stream))
     }
 
+    /// Override read rate parameters for this payload.
+    ///
+    /// Overrides server-wide `ServiceConfig::payload_read_rate()` setting,
+    /// to disable read rate check set `timeout` to 0. Only http/1 payloads
+    /// are affected, http/2 streams rely on flow control.
+    pub fn set_read_rate(&self, timeout: Seconds, max_timeout: Seconds, rate: u16) {
+        if let Payload::H1(ref pl) = self {
+            pl.set_read_rate(timeout, max_timeout, rate)
+        }
+    }
+
     #[inline]
     /// Attempt to pull out the next value of this payload.
     pub async fn recv(&mut self) -> Option<Result<Bytes, PayloadError>> {
@@ -0,0 +1,549 @@
+//! Byte range requests support
+use std::collections::VecDeque;
+use std::task::{Context, Poll};
+use std::{cmp, error::Error, fmt, pin::Pin};
+
+use httpdate::HttpDate;
+use nanorand::{Rng, WyRand};
+
+use crate::http::body::{Body, SizedStream};
+use crate::http::header::{self, HeaderValue};
+use crate::http::{Method, RequestHead, Response, ResponseBuilder, StatusCode};
+use crate::util::{Bytes, BytesMut, Stream};
+
+/// Default max number of ranges per request
+const MAX_RANGES: usize = 16;
+
+/// Requested byte ranges
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HttpRange {
+    /// Full representation, `Range` header is missing, invalid or ignored
+    Full,
+    /// Satisfiable ranges, sorted and coalesced `(offset, length)` pairs
+    Partial(Vec<(u64, u64)>),
+    /// None of requested ranges is satisfiable
+    Unsatisfiable,
+}
+
+impl HttpRange {
+    /// Parse `Range` header value for representation of `size` bytes
+    ///
+    /// Invalid headers and headers with more than `max` ranges are ignored.
+    pub fn parse(header: &str, size: u64, max: usize) -> HttpRange {
+        let spec = if let Some(spec) = header.trim().strip_prefix("bytes=") {
+            spec
+        } else {
+            return HttpRange::Full;
+        };
+
+        let mut ranges = Vec::new();
+        for item in spec.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+            match parse_range(item, size) {
+                Some(Some(range)) => ranges.push(range),
+                Some(None) => (),
+                None => return HttpRange::Full,
+            }
+        }
+
+        if ranges.is_empty() {
+            if spec.trim().is_empty() {
+                HttpRange::Full
+            } else {
+                HttpRange::Unsatisfiable
+            }
+        } else if ranges.len() > max {
+            HttpRange::Full
+        } else {
+            HttpRange::Partial(coalesce(ranges))
+        }
+    }
+}
+
+/// Parse single range, returns `Some(None)` for unsatisfiable range
+fn parse_range(item: &str, size: u64) -> Option<Option<(u64, u64)>> {
+    let (start, end) = item.split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+
+    if start.is_empty() {
+        // suffix range
+        let len = end.parse::<u64>().ok()?;
+        if len == 0 || size == 0 {
+            Some(None)
+        } else {
+            let len = cmp::min(len, size);
+            Some(Some((size - len, len)))
+        }
+    } else {
+        let start = start.parse::<u64>().ok()?;
+        let end = if end.is_empty() {
+            u64::MAX
+        } else {
+            end.parse::<u64>().ok()?
+        };
+
+        if end < start {
+            None
+        } else if start >= size {
+            Some(None)
+        } else {
+            let end = cmp::min(end, size - 1);
+            Some(Some((start, end - start + 1)))
+        }
+    }
+}
+
+/// Sort and merge overlapping and adjacent ranges
+fn coalesce(mut ranges: Vec<(u64, u64)>) -> Vec<(u64, u64)> {
+    ranges.sort_unstable();
+    let mut result: Vec<(u64, u64)> = Vec::with_capacity(ranges.len());
+    for (offset, len) in ranges {
+        if let Some(last) = result.last_mut() {
+            if offset <= last.0 + last.1 {
+                last.1 = cmp::max(last.0 + last.1, offset + len) - last.0;
+                continue;
+            }
+        }
+        result.push((offset, len));
+    }
+    result
+}
+
+/// Partial response builder
+///
+/// Builds `200 OK`, single range or `multipart/byteranges` `206 Partial Content`
+/// and `416 Range Not Satisfiable` responses for request's `Range` header.
+/// Response body is produced by `source` function, function is called for
+/// each requested range with offset and length and must return stream of
+/// exactly `length` bytes. Source is not called for `HEAD` requests.
+///
+/// ```rust
+/// use ntex::http::range::RangeResponse;
+/// use ntex::util::Bytes;
+/// use ntex::web::{HttpRequest, HttpResponse};
+///
+/// static DATA: &[u8] = b"Hello world!";
+///
+/// async fn blob(req: HttpRequest) -> HttpResponse {
+///     RangeResponse::new(req.head(), DATA.len() as u64, |offset, len| {
+///         let data = &DATA[offset as usize..(offset + len) as usize];
+///         futures_util::stream::iter(vec![Ok(Bytes::from_static(data))])
+///     })
+///     .content_type("text/plain")
+///     .finish()
+/// }
+/// ```
+pub struct RangeResponse<F> {
+    size: u64,
+    range: Option<String>,
+    if_range: Option<String>,
+    head: bool,
+    max_ranges: usize,
+    etag: Option<HeaderValue>,
+    last_modified: Option<HttpDate>,
+    content_type: Option<HeaderValue>,
+    source: F,
+}
+
+impl<F> fmt::Debug for RangeResponse<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RangeResponse")
+            .field("size", &self.size)
+            .field("range", &self.range)
+            .field("if_range", &self.if_range)
+            .field("max_ranges", &self.max_ranges)
+            .field("content_type", &self.content_type)
+            .finish()
+    }
+}
+
+impl<F, S> RangeResponse<F>
+where
+    F: FnMut(u64, u64) -> S + 'static,
+    S: Stream<Item = Result<Bytes, Box<dyn Error>>> + Unpin + 'static,
+{
+    /// Create partial response for the request and representation of `size` bytes
+    pub fn new(req: &RequestHead, size: u64, source: F) -> Self {
+        let header = |name| {
+            req.headers
+                .get(name)
+                .and_then(|val| val.to_str().ok())
+                .map(|val| val.to_string())
+        };
+
+        RangeResponse {
+            size,
+            source,
+            range: header(&header::RANGE),
+            if_range: header(&header::IF_RANGE),
+            head: req.method == Method::HEAD,
+            max_ranges: MAX_RANGES,
+            etag: None,
+            last_modified: None,
+            content_type: None,
+        }
+    }
+
+    /// Set content type of the representation
+    pub fn content_type<V>(mut self, value: V) -> Self
+    where
+        HeaderValue: TryFrom<V>,
+    {
+        self.content_type = HeaderValue::try_from(value).ok();
+        self
+    }
+
+    /// Set representation validators for `If-Range` header evaluation
+    ///
+    /// Ranges are ignored if request contains `If-Range` header
+    /// that does not match validators. Validators are not added
+    /// to the response.
+    pub fn validators(
+        mut self,
+        etag: Option<HeaderValue>,
+        modified: Option<HttpDate>,
+    ) -> Self {
+        self.etag = etag;
+        self.last_modified = modified;
+        self
+    }
+
+    /// Set max number of ranges per request, requests with more ranges
+    /// get full representation. By default 16 ranges are allowed.
+    pub fn max_ranges(mut self, max: usize) -> Self {
+        self.max_ranges = max;
+        self
+    }
+
+    /// Requested byte ranges
+    pub fn range(&self) -> HttpRange {
+        let header = if let Some(ref header) = self.range {
+            header
+        } else {
+            return HttpRange::Full;
+        };
+
+        // range applies only if representation is not changed
+        if let Some(ref val) = self.if_range {
+            let matches = if val.starts_with('"') {
+                self.etag
+                    .as_ref()
+                    .map(|etag| etag.as_bytes() == val.as_bytes())
+                    .unwrap_or(false)
+            } else {
+                self.last_modified.is_some()
+                    && val.parse::<HttpDate>().ok() == self.last_modified
+            };
+            if !matches {
+                return HttpRange::Full;
+            }
+        }
+
+        HttpRange::parse(header, self.size, self.max_ranges)
+    }
+
+    /// Build response with `200 OK` status for full representation
+    pub fn finish(self) -> Response {
+        self.build(&mut Response::Ok())
+    }
+
+    /// Build response with provided response builder
+    ///
+    /// Builder's status is used for full representation,
+    /// i.e. `ETag` and `Last-Modified` headers could be set.
+    pub fn build(mut self, res: &mut ResponseBuilder) -> Response {
+        res.header(header::ACCEPT_RANGES, "bytes");
+
+        let (mut ranges, partial) = match self.range() {
+            HttpRange::Full => (vec![(0, self.size)], false),
+            HttpRange::Partial(ranges) => {
+                res.status(StatusCode::PARTIAL_CONTENT);
+                (ranges, true)
+            }
+            HttpRange::Unsatisfiable => {
+                return res
+                    .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .header(header::CONTENT_RANGE, format!("bytes */{}", self.size))
+                    .finish();
+            }
+        };
+
+        if ranges.len() == 1 {
+            let (offset, len) = ranges[0];
+            if let Some(ct) = self.content_type.take() {
+                res.header(header::CONTENT_TYPE, ct);
+            }
+            if partial {
+                res.header(header::CONTENT_RANGE, content_range(offset, len, self.size));
+            }
+            let stream = if self.head {
+                None
+            } else {
+                Some((self.source)(offset, len))
+            };
+            return res.body(Body::from(SizedStream::new(len, OptionStream(stream))));
+        }
+
+        // multipart/byteranges
+        let mut rng = WyRand::new();
+        let boundary = (0..2)
+            .map(|_| format!("{:016x}", rng.generate::<u64>()))
+            .collect::<String>();
+        res.header(
+            header::CONTENT_TYPE,
+            format!("multipart/byteranges; boundary={}", boundary),
+        );
+
+        let mut size = 0;
+        let parts = ranges
+            .drain(..)
+            .map(|(offset, len)| {
+                let mut buf = BytesMut::new();
+                buf.extend_from_slice(b"\r\n--");
+                buf.extend_from_slice(boundary.as_bytes());
+                if let Some(ref ct) = self.content_type {
+                    buf.extend_from_slice(b"\r\ncontent-type: ");
+                    buf.extend_from_slice(ct.as_bytes());
+                }
+                buf.extend_from_slice(b"\r\ncontent-range: ");
+                buf.extend_from_slice(content_range(offset, len, self.size).as_bytes());
+                buf.extend_from_slice(b"\r\n\r\n");
+                size += buf.len() as u64 + len;
+                (buf.freeze(), offset, len)
+            })
+            .collect();
+        let trailer = Bytes::from(format!("\r\n--{}--\r\n", boundary));
+        size += trailer.len() as u64;
+
+        let stream = if self.head {
+            None
+        } else {
+            Some(MultipartRanges {
+                parts,
+                trailer: Some(trailer),
+                current: None,
+                source: Box::new(self.source),
+            })
+        };
+        res.body(Body::from(SizedStream::new(size, OptionStream(stream))))
+    }
+}
+
+fn content_range(offset: u64, len: u64, size: u64) -> String {
+    format!("bytes {}-{}/{}", offset, offset + len - 1, size)
+}
+
+/// Body stream, stream is not set for `HEAD` requests
+struct OptionStream<S>(Option<S>);
+
+impl<S> Stream for OptionStream<S>
+where
+    S: Stream<Item = Result<Bytes, Box<dyn Error>>> + Unpin,
+{
+    type Item = Result<Bytes, Box<dyn Error>>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        if let Some(ref mut stream) = self.0 {
+            Pin::new(stream).poll_next(cx)
+        } else {
+            Poll::Ready(None)
+        }
+    }
+}
+
+/// Stream of `multipart/byteranges` body parts
+struct MultipartRanges<F, S> {
+    parts: VecDeque<(Bytes, u64, u64)>,
+    trailer: Option<Bytes>,
+    current: Option<S>,
+    source: Box<F>,
+}
+
+impl<F, S> Stream for MultipartRanges<F, S>
+where
+    F: FnMut(u64, u64) -> S,
+    S: Stream<Item = Result<Bytes, Box<dyn Error>>> + Unpin,
+{
+    type Item = Result<Bytes, Box<dyn Error>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if let Some(ref mut stream) = this.current {
+            match Pin::new(stream).poll_next(cx) {
+                Poll::Ready(None) => this.current = None,
+                res => return res,
+            }
+        }
+
+        if let Some((header, offset, len)) = this.parts.pop_front() {
+            this.current = Some((this.source)(offset, len));
+            Poll::Ready(Some(Ok(header)))
+        } else {
+            Poll::Ready(this.trailer.take().map(Ok))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{body::MessageBody, test::TestRequest};
+
+    static DATA: &[u8] = b"0123456789";
+
+    fn response(req: &mut TestRequest) -> RangeResponse<impl FnMut(u64, u64) -> Chunks> {
+        let req = req.finish();
+        RangeResponse::new(req.head(), DATA.len() as u64, |offset, len| {
+            Chunks(Some(Bytes::from_static(
+                &DATA[offset as usize..(offset + len) as usize],
+            )))
+        })
+        .content_type("text/plain")
+    }
+
+    struct Chunks(Option<Bytes>);
+
+    impl Stream for Chunks {
+        type Item = Result<Bytes, Box<dyn Error>>;
+
+        fn poll_next(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Option<Self::Item>> {
+            Poll::Ready(self.0.take().map(Ok))
+        }
+    }
+
+    async fn body(mut res: Response) -> Bytes {
+        let mut body = res.take_body();
+        let mut buf = BytesMut::new();
+        while let Some(chunk) = std::future::poll_fn(|cx| body.poll_next_chunk(cx)).await {
+            buf.extend_from_slice(&chunk.unwrap());
+        }
+        buf.freeze()
+    }
+
+    #[test]
+    fn test_parse() {
+        let parse = |val: &str| HttpRange::parse(val, 10, 16);
+        assert_eq!(parse("bytes=0-4"), HttpRange::Partial(vec![(0, 5)]));
+        assert_eq!(parse("bytes=5-"), HttpRange::Partial(vec![(5, 5)]));
+        assert_eq!(parse("bytes=-3"), HttpRange::Partial(vec![(7, 3)]));
+        assert_eq!(parse("bytes=-30"), HttpRange::Partial(vec![(0, 10)]));
+        assert_eq!(parse("bytes=5-100"), HttpRange::Partial(vec![(5, 5)]));
+        assert_eq!(parse("bytes=10-"), HttpRange::Unsatisfiable);
+        assert_eq!(parse("bytes=-0"), HttpRange::Unsatisfiable);
+        assert_eq!(
+            parse("bytes=0-1, 3-4"),
+            HttpRange::Partial(vec![(0, 2), (3, 2)])
+        );
+        assert_eq!(
+            parse("bytes=6-7,0-1,20-30"),
+            HttpRange::Partial(vec![(0, 2), (6, 2)])
+        );
+        // overlapping and adjacent ranges are merged
+        assert_eq!(parse("bytes=0-3,2-5"), HttpRange::Partial(vec![(0, 6)]));
+        assert_eq!(parse("bytes=0-1,2-3"), HttpRange::Partial(vec![(0, 4)]));
+        assert_eq!(parse("bytes=5-2"), HttpRange::Full);
+        assert_eq!(parse("bytes=0-1,x"), HttpRange::Full);
+        assert_eq!(parse("items=0-1"), HttpRange::Full);
+        assert_eq!(parse("bytes="), HttpRange::Full);
+        assert_eq!(HttpRange::parse("bytes=0-1,3-4", 10, 1), HttpRange::Full);
+    }
+
+    #[crate::rt_test]
+    async fn test_range_response() {
+        let res = response(&mut TestRequest::default()).finish();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get(header::ACCEPT_RANGES).unwrap(), "bytes");
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/plain"
+        );
+        assert_eq!(body(res).await, DATA);
+
+        let res =
+            response(TestRequest::default().header(header::RANGE, "bytes=2-4")).finish();
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            res.headers().get(header::CONTENT_RANGE).unwrap(),
+            "bytes 2-4/10"
+        );
+        assert_eq!(body(res).await, "234");
+
+        let res =
+            response(TestRequest::default().header(header::RANGE, "bytes=20-")).finish();
+        assert_eq!(res.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(
+            res.headers().get(header::CONTENT_RANGE).unwrap(),
+            "bytes */10"
+        );
+
+        // head request
+        let res = response(
+            TestRequest::default()
+                .method(Method::HEAD)
+                .header(header::RANGE, "bytes=2-4"),
+        )
+        .finish();
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(res.body().size(), crate::http::body::BodySize::Sized(3));
+    }
+
+    #[crate::rt_test]
+    async fn test_multipart() {
+        let res =
+            response(TestRequest::default().header(header::RANGE, "bytes=0-1,-2")).finish();
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        let ct = res
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .unwrap()
+            .to_str()
+            .unwrap();
+        let boundary = ct
+            .strip_prefix("multipart/byteranges; boundary=")
+            .unwrap()
+            .to_string();
+
+        let size = res.body().size();
+        let body = body(res).await;
+        assert_eq!(size, crate::http::body::BodySize::Sized(body.len() as u64));
+        assert_eq!(
+            body,
+            format!(
+                "\r\n--{0}\r\ncontent-type: text/plain\r\ncontent-range: bytes 0-1/10\r\n\r\n01\
+                 \r\n--{0}\r\ncontent-type: text/plain\r\ncontent-range: bytes 8-9/10\r\n\r\n89\
+                 \r\n--{0}--\r\n",
+                boundary
+            )
+            .as_str()
+        );
+    }
+
+    #[crate::rt_test]
+    async fn test_if_range() {
+        let etag = HeaderValue::from_static("\"tag\"");
+        let res = response(
+            TestRequest::default()
+                .header(header::RANGE, "bytes=2-4")
+                .header(header::IF_RANGE, "\"other\""),
+        )
+        .validators(Some(etag.clone()), None)
+        .finish();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = response(
+            TestRequest::default()
+                .header(header::RANGE, "bytes=2-4")
+                .header(header::IF_RANGE, "\"tag\""),
+        )
+        .validators(Some(etag), None)
+        .build(Response::Ok().header(header::ETAG, "\"tag\""));
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(res.headers().get(header::ETAG).unwrap(), "\"tag\"");
+    }
+}
@@ -2,6 +2,7 @@ use std::{error, fmt, marker, rc::Rc, task::Context, task::Poll};
 
 use crate::io::{types, Filter, Io};
 use crate::service::{IntoServiceFactory, Service, ServiceCtx, ServiceFactory};
+use crate::util::ready;
 
 use super::body::MessageBody;
 use super::builder::HttpServiceBuilder;
@@ -318,6 +319,9 @@ where
     }
 
     fn poll_shutdown(&self, cx: &mut Context<'_>) -> Poll<()> {
+        // drain http/2 connections first
+        ready!(self.config.h2_conns.poll_shutdown(cx));
+
         let ready1 = self.config.control.poll_shutdown(cx).is_ready();
         let ready2 = self.config.service.poll_shutdown(cx).is_ready();
 
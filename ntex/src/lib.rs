@@ -4,8 +4,14 @@
 //!
 //! * `openssl` - enables ssl support via `openssl` crate
 //! * `rustls` - enables ssl support via `rustls` crate
+//! * `native-tls` - enables ssl support via `native-tls` crate, uses platform trust store
 //! * `compress` - enables compression support in http and web modules
 //! * `cookie` - enables cookie support in http and web modules
+//! * `session` - enables session middleware
+//! * `identity` - enables identity middleware
+//! * `tracing` - enables request tracing middleware
+//! * `msgpack` - enables msgpack extractor and responder in web module
+//! * `cbor` - enables cbor extractor and responder in web module
 #![warn(
     rust_2018_idioms,
     unreachable_pub,
@@ -49,6 +55,9 @@ pub mod connect {
     //! Tcp connector service
     pub use ntex_net::connect::*;
 
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    pub use ntex_tls::ClientCert;
+
     #[cfg(feature = "openssl")]
     pub mod openssl {
         pub use ntex_tls::openssl::{SslConnector, SslFilter};
@@ -66,6 +75,11 @@ pub mod connect {
         #[deprecated]
         pub use ntex_tls::rustls::TlsConnector as Connector;
     }
+
+    #[cfg(feature = "native-tls")]
+    pub mod native_tls {
+        pub use ntex_tls::native_tls::{TlsConnector, TlsFilter};
+    }
 }
 
 pub mod router {
@@ -78,6 +92,11 @@ pub mod rt {
     pub use ntex_rt::*;
 
     pub use ntex_net::*;
+
+    pub mod signal {
+        //! Process signals handling
+        pub use ntex_server::signal::*;
+    }
 }
 
 pub mod service {
@@ -121,5 +140,6 @@ pub mod util {
     pub use ntex_bytes::{
         Buf, BufMut, ByteString, Bytes, BytesMut, BytesVec, Pool, PoolId, PoolRef,
     };
+    pub use ntex_util::time::{backoff, delay_queue};
     pub use ntex_util::{future::*, ready, services::*, HashMap, HashSet};
 }
@@ -1,7 +1,7 @@
 use std::{cell::RefCell, fmt, future::Future, marker::PhantomData, rc::Rc};
 
 use crate::http::Request;
-use crate::router::ResourceDef;
+use crate::router::{IntoPattern, ResourceDef};
 use crate::service::boxed::{self, BoxServiceFactory};
 use crate::service::{
     chain_factory, dev::ServiceChainFactory, map_config, IntoServiceFactory,
@@ -11,10 +11,12 @@ use crate::util::{BoxFuture, Extensions};
 
 use super::app_service::{AppFactory, AppService};
 use super::config::{AppConfig, ServiceConfig};
+use super::error_handler::ErrorHandlers;
 use super::request::WebRequest;
 use super::resource::Resource;
 use super::response::WebResponse;
 use super::route::Route;
+use super::scope::{Scope, ScopeService};
 use super::service::{AppServiceFactory, ServiceFactoryWrapper, WebServiceFactory};
 use super::{DefaultError, ErrorRenderer};
 
@@ -25,15 +27,15 @@ type FnStateFactory = Box<dyn Fn(Extensions) -> BoxFuture<'static, Result<Extens
 /// Application builder - structure that follows the builder pattern
 /// for building application instances.
 pub struct App<M, F, Err: ErrorRenderer = DefaultError> {
-    middleware: M,
-    filter: ServiceChainFactory<F, WebRequest<Err>>,
-    services: Vec<Box<dyn AppServiceFactory<Err>>>,
-    default: Option<Rc<HttpNewService<Err>>>,
-    external: Vec<ResourceDef>,
-    extensions: Extensions,
-    state_factories: Vec<FnStateFactory>,
+    pub(super) middleware: M,
+    pub(super) filter: ServiceChainFactory<F, WebRequest<Err>>,
+    pub(super) services: Vec<Box<dyn AppServiceFactory<Err>>>,
+    pub(super) default: Option<Rc<HttpNewService<Err>>>,
+    pub(super) external: Vec<ResourceDef>,
+    pub(super) extensions: Extensions,
+    pub(super) state_factories: Vec<FnStateFactory>,
     error_renderer: Err,
-    case_insensitive: bool,
+    pub(super) case_insensitive: bool,
 }
 
 impl App<Identity, Filter<DefaultError>, DefaultError> {
@@ -227,6 +229,44 @@ where
         self
     }
 
+    /// Mount application under the path prefix.
+    ///
+    /// Mounted application keeps its own state, state factories, default service,
+    /// filters and middlewares. Filters and middlewares of mounted application
+    /// are applied only to requests that match the prefix, if mounted application
+    /// does not define default service, the default service of parent application
+    /// is used. Parent application's state is accessible from mounted application.
+    ///
+    /// ```rust
+    /// use ntex::web::{self, middleware, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let admin = App::new()
+    ///         .wrap(middleware::DefaultHeaders::new().header("x-admin", "1"))
+    ///         .route("/users", web::get().to(|| async { HttpResponse::Ok() }))
+    ///         .default_service(web::to(|| async { HttpResponse::Forbidden() }));
+    ///
+    ///     let app = App::new()
+    ///         .route("/index.html", web::get().to(|| async { HttpResponse::Ok() }))
+    ///         .mount("/admin", admin);
+    /// }
+    /// ```
+    pub fn mount<P, M2, F2>(self, path: P, app: App<M2, F2, Err>) -> Self
+    where
+        P: IntoPattern,
+        M2: Middleware<ScopeService<F2::Service, Err>> + 'static,
+        M2::Service:
+            Service<WebRequest<Err>, Response = WebResponse, Error = Err::Container>,
+        F2: ServiceFactory<
+                WebRequest<Err>,
+                Response = WebRequest<Err>,
+                Error = Err::Container,
+                InitError = (),
+            > + 'static,
+    {
+        self.service(Scope::from_app(path, app))
+    }
+
     /// Default service to be used if no matching resource could be found.
     ///
     /// It is possible to use services like `Resource`, `Route`.
@@ -417,6 +457,32 @@ where
         self.case_insensitive = true;
         self
     }
+
+    /// Register application error handlers.
+    ///
+    /// Handlers map status codes and error types to custom responses,
+    /// for all resources and scopes of application. See [`ErrorHandlers`]
+    /// for details.
+    ///
+    /// ```rust
+    /// use ntex::http::StatusCode;
+    /// use ntex::web::{self, App, ErrorHandlers, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .default_error_handler(
+    ///             ErrorHandlers::new()
+    ///                 .status(StatusCode::METHOD_NOT_ALLOWED, |_, _| {
+    ///                     HttpResponse::MethodNotAllowed().json(&"method not allowed")
+    ///                 }),
+    ///         )
+    ///         .route("/index.html", web::get().to(|| async { HttpResponse::Ok() }));
+    /// }
+    /// ```
+    pub fn default_error_handler(mut self, handlers: ErrorHandlers<Err>) -> Self {
+        self.extensions.insert(handlers);
+        self
+    }
 }
 
 impl<M, F, Err> App<M, F, Err>
@@ -793,4 +859,63 @@ mod tests {
         let body = read_body(resp).await;
         assert_eq!(body, Bytes::from_static(b"https://youtube.com/watch/12345"));
     }
+
+    #[crate::rt_test]
+    async fn test_mount() {
+        let admin = App::new()
+            .state(10u32)
+            .state_factory(|| async { Ok::<_, ()>(20u64) })
+            .wrap(
+                DefaultHeaders::new()
+                    .header(header::CONTENT_TYPE, HeaderValue::from_static("0001")),
+            )
+            .route(
+                "/users",
+                web::get().to(
+                    |st: web::types::State<u32>,
+                     st2: web::types::State<u64>,
+                     st3: web::types::State<usize>| async move {
+                        HttpResponse::Ok().body(format!("{}-{}-{}", *st, *st2, *st3))
+                    },
+                ),
+            )
+            .default_service(web::to(|| async { HttpResponse::Forbidden() }));
+
+        let srv = init_service(
+            App::new()
+                .state(1usize)
+                .route(
+                    "/test",
+                    web::get().to(|st: web::types::State<usize>| async move {
+                        HttpResponse::Ok().body(format!("{}", *st))
+                    }),
+                )
+                .mount("/admin", admin),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/admin/users").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            HeaderValue::from_static("0001")
+        );
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"10-20-1"));
+
+        let req = TestRequest::with_uri("/admin/unknown").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        // middleware and state of mounted app are isolated
+        let req = TestRequest::with_uri("/test").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().get(header::CONTENT_TYPE).is_none());
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"1"));
+
+        let req = TestRequest::with_uri("/unknown").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
 }
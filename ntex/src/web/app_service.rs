@@ -1,6 +1,6 @@
-use std::{cell::RefCell, marker, rc::Rc, task::Context, task::Poll};
+use std::{cell::RefCell, collections::HashMap, marker, rc::Rc, task::Context, task::Poll};
 
-use crate::http::{Request, Response};
+use crate::http::{Message, Payload, Request, RequestHead, Response};
 use crate::router::{Path, ResourceDef, Router};
 use crate::service::boxed::{self, BoxService, BoxServiceFactory};
 use crate::service::dev::ServiceChainFactory;
@@ -9,14 +9,17 @@ use crate::util::{BoxFuture, Extensions};
 
 use super::config::AppConfig;
 use super::error::ErrorRenderer;
-use super::guard::Guard;
+use super::error_handler::ErrorHandlers;
+use super::guard::{get_host_uri, Guard, HostPattern};
 use super::httprequest::{HttpRequest, HttpRequestPool};
+use super::info::RouteInfo;
 use super::request::WebRequest;
 use super::response::WebResponse;
 use super::rmap::ResourceMap;
 use super::service::{AppServiceFactory, AppState, WebServiceConfig};
 
 type Guards = Vec<Box<dyn Guard>>;
+type HttpRouter<Err> = Router<(HttpService<Err>, Rc<RouteInfo>), Guards>;
 type HttpService<Err: ErrorRenderer> =
     BoxService<WebRequest<Err>, WebResponse, Err::Container>;
 type HttpNewService<Err: ErrorRenderer> =
@@ -114,6 +117,9 @@ where
                 .await
                 .map_err(|_| log::error!("Cannot initialize state factory"))?
         }
+        if let Some(handlers) = extensions.remove::<ErrorHandlers<Err>>() {
+            extensions.insert(handlers.finish(None));
+        }
         let state = AppState::new(extensions, None, config.clone());
 
         // App config
@@ -123,6 +129,7 @@ where
         services
             .into_iter()
             .for_each(|mut srv| srv.register(&mut config));
+        let hosts = config.take_hosts();
         let services = config.into_services();
 
         // resource map
@@ -135,8 +142,18 @@ where
         let services: Vec<_> = services
             .into_iter()
             .map(|(mut rdef, srv, guards, nested)| {
+                let info = RouteInfo::new(&rdef, nested.is_some());
+                rmap.add(&mut rdef, nested);
+                (rdef, srv, RefCell::new(guards), info)
+            })
+            .collect();
+
+        let hosts: Vec<_> = hosts
+            .into_iter()
+            .map(|(host, mut rdef, srv, guards, nested, routes)| {
+                let info = RouteInfo::new(&rdef, nested.is_some());
                 rmap.add(&mut rdef, nested);
-                (rdef, srv, RefCell::new(guards))
+                (host, rdef, srv, guards, info, routes)
             })
             .collect();
 
@@ -145,15 +162,43 @@ where
         rmap.finish(rmap.clone());
 
         // create http services
-        for (path, factory, guards) in &mut services.iter() {
+        for (path, factory, guards, info) in &mut services.iter() {
+            let service = factory
+                .create(())
+                .await
+                .map_err(|_| log::error!("Cannot construct app service"))?;
+            router.rdef(path.clone(), (service, info.clone())).2 =
+                guards.borrow_mut().take();
+        }
+
+        // create host services, each host scope gets its own router
+        let mut exact: HashMap<String, Vec<HostScope<Err>>> = HashMap::new();
+        let mut patterns = Vec::new();
+        for (host, path, factory, guards, info, routes) in hosts {
             let service = factory
                 .create(())
                 .await
                 .map_err(|_| log::error!("Cannot construct app service"))?;
-            router.rdef(path.clone(), service).2 = guards.borrow_mut().take();
+
+            let mut builder = Router::build();
+            if self.case_insensitive {
+                builder.case_insensitive();
+            }
+            builder.rdef(path, (service, info)).2 = guards;
+            let scope = HostScope {
+                routes,
+                router: builder.finish(),
+            };
+
+            if let Some(name) = host.static_host() {
+                exact.entry(name).or_default().push(scope);
+            } else {
+                patterns.push((host, scope));
+            }
         }
 
         let routing = AppRouting {
+            hosts: HostRouting { exact, patterns },
             router: router.finish(),
             default: Some(
                 default
@@ -215,6 +260,7 @@ where
         let req = if let Some(mut req) = self.pool.get_request() {
             let inner = Rc::get_mut(&mut req.0).unwrap();
             inner.path.set(head.uri.clone());
+            inner.routes.clear();
             inner.head = head;
             inner.payload = payload;
             inner.app_state = self.state.clone();
@@ -229,7 +275,31 @@ where
                 self.pool,
             )
         };
-        ctx.call(&self.service, WebRequest::new(req)).await
+        // request is consumed by middlewares, keep request line
+        // for rendering middleware errors with error handlers
+        let (uri, method, version) =
+            (req.uri().clone(), req.method().clone(), req.version());
+
+        let res = match ctx.call(&self.service, WebRequest::new(req)).await {
+            Ok(res) => res,
+            Err(err) => {
+                let mut head = Message::<RequestHead>::new();
+                head.uri = uri.clone();
+                head.method = method;
+                head.version = version;
+                let req = HttpRequest::new(
+                    Path::new(uri),
+                    head,
+                    Payload::None,
+                    self.rmap.clone(),
+                    self.state.clone(),
+                    self.pool,
+                );
+                let res = ErrorHandlers::<Err>::render_error(&err, &req);
+                WebResponse::new(res, req)
+            }
+        };
+        Ok(ErrorHandlers::<Err>::map_response(res))
     }
 }
 
@@ -243,8 +313,88 @@ where
     }
 }
 
+/// Routing table for host scopes
+///
+/// Hosts without wildcards are looked up by name, host patterns
+/// are checked in registration order.
+struct HostRouting<Err: ErrorRenderer> {
+    exact: HashMap<String, Vec<HostScope<Err>>>,
+    patterns: Vec<(HostPattern, HostScope<Err>)>,
+}
+
+/// Application level host scope
+struct HostScope<Err: ErrorRenderer> {
+    /// Scope routes, `None` matches any path
+    routes: Option<Router<()>>,
+    router: HttpRouter<Err>,
+}
+
+impl<Err: ErrorRenderer> HostRouting<Err> {
+    fn recognize<'a>(
+        &'a self,
+        req: &mut WebRequest<Err>,
+    ) -> Option<&'a (HttpService<Err>, Rc<RouteInfo>)> {
+        if self.exact.is_empty() && self.patterns.is_empty() {
+            return None;
+        }
+
+        let uri = get_host_uri(req.head())?;
+        let host = uri.host()?;
+        let host = host.strip_suffix('.').unwrap_or(host).to_ascii_lowercase();
+
+        if let Some(scopes) = self.exact.get(&host) {
+            for scope in scopes {
+                if let Some(item) = scope.recognize(req) {
+                    return Some(item);
+                }
+            }
+        }
+        for (pattern, scope) in &self.patterns {
+            if pattern.match_host(&host).is_some() {
+                if let Some(item) = scope.recognize(req) {
+                    return Some(item);
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<Err: ErrorRenderer> HostScope<Err> {
+    fn recognize<'a>(
+        &'a self,
+        req: &mut WebRequest<Err>,
+    ) -> Option<&'a (HttpService<Err>, Rc<RouteInfo>)> {
+        // scope prefix matches any path, check scope routes first
+        if let Some(ref routes) = self.routes {
+            let mut path = req.match_info().clone();
+            self.router.recognize(&mut path)?;
+            routes.recognize(&mut path)?;
+        }
+        self.router
+            .recognize_checked(req, check_guards)
+            .map(|(item, _)| item)
+    }
+}
+
+fn check_guards<Err: ErrorRenderer>(
+    req: &WebRequest<Err>,
+    guards: Option<&Guards>,
+) -> bool {
+    if let Some(guards) = guards {
+        let ctx = req.guard_ctx();
+        for f in guards {
+            if !f.check_ctx(&ctx) {
+                return false;
+            }
+        }
+    }
+    true
+}
+
 struct AppRouting<Err: ErrorRenderer> {
-    router: Router<HttpService<Err>, Guards>,
+    hosts: HostRouting<Err>,
+    router: HttpRouter<Err>,
     default: Option<HttpService<Err>>,
 }
 
@@ -257,18 +407,14 @@ impl<Err: ErrorRenderer> Service<WebRequest<Err>> for AppRouting<Err> {
         mut req: WebRequest<Err>,
         ctx: ServiceCtx<'_, Self>,
     ) -> Result<WebResponse, Err::Container> {
-        let res = self.router.recognize_checked(&mut req, |req, guards| {
-            if let Some(guards) = guards {
-                for f in guards {
-                    if !f.check(req.head()) {
-                        return false;
-                    }
-                }
-            }
-            true
+        let res = self.hosts.recognize(&mut req).or_else(|| {
+            self.router
+                .recognize_checked(&mut req, check_guards)
+                .map(|(item, _)| item)
         });
 
-        if let Some((srv, _info)) = res {
+        if let Some((srv, info)) = res {
+            req.add_route(info.clone());
             ctx.call(srv, req).await
         } else if let Some(ref default) = self.default {
             ctx.call(default, req).await
@@ -1,9 +1,11 @@
 //! Web error
-use std::{cell::RefCell, fmt, io::Write, marker::PhantomData};
+use std::{cell::RefCell, fmt, io::Write, marker::PhantomData, rc::Rc};
 
 use thiserror::Error;
 
 pub use ntex_http::error::Error as HttpError;
+#[cfg(feature = "msgpack")]
+pub use rmp_serde::encode::Error as MsgPackError;
 pub use serde_json::error::Error as JsonError;
 #[cfg(feature = "url")]
 pub use url_pkg::ParseError as UrlParseError;
@@ -14,6 +16,7 @@ use crate::http::{error, header, StatusCode};
 use crate::util::{BytesMut, Either};
 
 pub use super::error_default::{DefaultError, Error};
+pub use super::problem::ProblemDetails;
 pub use crate::http::error::BlockingError;
 
 use super::{HttpRequest, HttpResponse};
@@ -25,6 +28,13 @@ pub trait ErrorRenderer: Sized + 'static {
 pub trait ErrorContainer: error::ResponseError + Sized {
     /// Generate response for error container
     fn error_response(&self, req: &HttpRequest) -> HttpResponse;
+
+    /// Returns reference to the underlying error if it is of type `T`
+    ///
+    /// Used by [`ErrorHandlers`](super::ErrorHandlers), returns `None` by default.
+    fn downcast_ref<T: 'static>(&self) -> Option<&T> {
+        None
+    }
 }
 
 /// Error that can be rendered to a `Response`
@@ -79,10 +89,20 @@ where
 }
 
 /// Errors which can occur when attempting to work with `State` extractor
-#[derive(Error, Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
 pub enum StateExtractorError {
-    #[error("App state is not configured, to configure use App::state()")]
-    NotConfigured,
+    /// State of requested type is not configured for any of searched scopes
+    #[error(
+        "State of type `{type_name}` is not configured, searched: {}, \
+         to configure use App::state() or Scope::state()",
+        .scopes.join(", ")
+    )]
+    NotFound {
+        /// Requested state type
+        type_name: &'static str,
+        /// Searched scopes, innermost first
+        scopes: Vec<String>,
+    },
 }
 
 /// Errors which can occur when attempting to generate resource uri.
@@ -111,6 +131,13 @@ pub enum UrlencodedError {
         "Urlencoded payload size is bigger ({size} bytes) than allowed (default: {limit} bytes)",
     )]
     Overflow { size: usize, limit: usize },
+    /// Field value size is bigger than allowed
+    #[error("Urlencoded field {name:?} size is bigger ({size} bytes) than allowed ({limit} bytes)")]
+    FieldOverflow {
+        name: String,
+        size: usize,
+        limit: usize,
+    },
     /// Payload size is unknown
     #[error("Payload size is unknown")]
     UnknownLength,
@@ -123,6 +150,12 @@ pub enum UrlencodedError {
     /// Payload error
     #[error("Error that occur during reading payload: {0}")]
     Payload(#[from] error::PayloadError),
+    /// Error with response generated by `FormConfig::error_handler()`
+    #[error("{cause}")]
+    Response {
+        cause: Rc<UrlencodedError>,
+        response: ErrorResponseFactory,
+    },
 }
 
 /// A set of errors that can occur during parsing json payloads
@@ -140,6 +173,124 @@ pub enum JsonPayloadError {
     /// Payload error
     #[error("Error that occur during reading payload: {0}")]
     Payload(#[from] error::PayloadError),
+    /// Error with response generated by `JsonConfig::error_handler()`
+    #[error("{cause}")]
+    Response {
+        cause: Rc<JsonPayloadError>,
+        response: ErrorResponseFactory,
+    },
+}
+
+/// Response factory of extractor's error handler
+#[derive(Clone)]
+pub struct ErrorResponseFactory {
+    status: StatusCode,
+    factory: Rc<dyn Fn(&HttpRequest) -> HttpResponse>,
+}
+
+impl ErrorResponseFactory {
+    /// Create response factory
+    ///
+    /// Factory is called once to determine response status.
+    pub fn new<F>(req: &HttpRequest, factory: F) -> Self
+    where
+        F: Fn(&HttpRequest) -> HttpResponse + 'static,
+    {
+        ErrorResponseFactory {
+            status: factory(req).status(),
+            factory: Rc::new(factory),
+        }
+    }
+
+    /// Response status
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Generate response
+    pub fn response(&self, req: &HttpRequest) -> HttpResponse {
+        (self.factory)(req)
+    }
+}
+
+impl fmt::Debug for ErrorResponseFactory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ErrorResponseFactory")
+            .field("status", &self.status)
+            .finish()
+    }
+}
+
+/// A set of errors that can occur during parsing multipart payloads
+#[derive(Error, Debug)]
+pub enum MultipartError {
+    /// Content type error
+    #[error("Content type error")]
+    ContentType,
+    /// Multipart boundary is not found
+    #[error("Multipart boundary is not found")]
+    Boundary,
+    /// Payload size is bigger than allowed
+    #[error("Multipart payload size is bigger than allowed ({limit} bytes)")]
+    Overflow { limit: usize },
+    /// Field size is bigger than allowed
+    #[error("Multipart field {name:?} size is bigger than allowed ({limit} bytes)")]
+    FieldOverflow { name: String, limit: usize },
+    /// Field content type is not allowed
+    #[error("Multipart field {name:?} content type is not allowed")]
+    FieldContentType { name: String },
+    /// Parse error
+    #[error("Multipart parse error")]
+    Parse,
+    /// Multipart stream is incomplete
+    #[error("Multipart stream is incomplete")]
+    Incomplete,
+    /// Temporary file io error
+    #[error("Multipart io error: {0}")]
+    Io(#[from] std::io::Error),
+    /// Payload error
+    #[error("Error that occur during reading payload: {0}")]
+    Payload(#[from] error::PayloadError),
+}
+
+#[cfg(feature = "cbor")]
+/// Cbor serialization error
+pub type CborError = ciborium::ser::Error<std::io::Error>;
+
+#[cfg(feature = "msgpack")]
+/// A set of errors that can occur during parsing msgpack payloads
+#[derive(Error, Debug)]
+pub enum MsgPackPayloadError {
+    /// Payload size is bigger than allowed. (default: 32kB)
+    #[error("MsgPack payload size is bigger than allowed")]
+    Overflow,
+    /// Content type error
+    #[error("Content type error")]
+    ContentType,
+    /// Deserialize error
+    #[error("MsgPack deserialize error: {0}")]
+    Deserialize(#[from] rmp_serde::decode::Error),
+    /// Payload error
+    #[error("Error that occur during reading payload: {0}")]
+    Payload(#[from] PayloadError),
+}
+
+#[cfg(feature = "cbor")]
+/// A set of errors that can occur during parsing cbor payloads
+#[derive(Error, Debug)]
+pub enum CborPayloadError {
+    /// Payload size is bigger than allowed. (default: 32kB)
+    #[error("Cbor payload size is bigger than allowed")]
+    Overflow,
+    /// Content type error
+    #[error("Content type error")]
+    ContentType,
+    /// Deserialize error
+    #[error("Cbor deserialize error: {0}")]
+    Deserialize(#[from] ciborium::de::Error<std::io::Error>),
+    /// Payload error
+    #[error("Error that occur during reading payload: {0}")]
+    Payload(#[from] PayloadError),
 }
 
 /// A set of errors that can occur during parsing request paths
@@ -158,6 +309,31 @@ pub enum QueryPayloadError {
     Deserialize(#[from] serde::de::value::Error),
 }
 
+/// A set of errors that can occur during `Authorization` header processing
+///
+/// Each variant carries `WWW-Authenticate` challenge for the response.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum AuthError {
+    /// Authorization header is missing
+    #[error("Authorization header is missing")]
+    Missing(header::HeaderValue),
+    /// Authorization header is malformed or uses other scheme
+    #[error("Authorization header is invalid")]
+    Invalid(header::HeaderValue),
+    /// Credentials are rejected
+    #[error("Credentials are rejected")]
+    Rejected(header::HeaderValue),
+}
+
+impl AuthError {
+    /// `WWW-Authenticate` challenge
+    pub fn challenge(&self) -> &header::HeaderValue {
+        match self {
+            AuthError::Missing(ch) | AuthError::Invalid(ch) | AuthError::Rejected(ch) => ch,
+        }
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum PayloadError {
     /// Http error.
@@ -701,6 +877,24 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_downcast_error() {
+        let e: Error = UrlencodedError::UnknownLength.into();
+        assert!(e.is::<UrlencodedError>());
+        assert!(!e.is::<PayloadError>());
+        assert!(matches!(
+            e.downcast_ref::<UrlencodedError>(),
+            Some(UrlencodedError::UnknownLength)
+        ));
+        assert!(e.downcast_ref::<PayloadError>().is_none());
+
+        let e = e.downcast::<PayloadError>().unwrap_err();
+        assert!(matches!(
+            e.downcast::<UrlencodedError>(),
+            Ok(UrlencodedError::UnknownLength)
+        ));
+    }
+
     #[test]
     fn test_other_errors() {
         let req = TestRequest::default().to_http_request();
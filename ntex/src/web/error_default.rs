@@ -1,5 +1,5 @@
 //! Web error
-use std::{fmt, io, io::Write, str::Utf8Error};
+use std::{any::Any, fmt, io, io::Write, str::Utf8Error};
 
 use serde::de::value::Error as DeError;
 use serde_json::error::Error as JsonError;
@@ -9,7 +9,7 @@ use crate::http::body::Body;
 use crate::http::helpers::Writer;
 use crate::http::{self, header, StatusCode};
 use crate::util::{timeout::TimeoutError, BytesMut};
-use crate::ws::error::HandshakeError;
+use crate::ws::error::{HandshakeError, ProtocolError};
 
 use super::error::{self, ErrorContainer, ErrorRenderer, WebResponseError};
 use super::{HttpRequest, HttpResponse};
@@ -25,7 +25,30 @@ impl ErrorRenderer for DefaultError {
 /// Generic error container for errors that supports `DefaultError` renderer.
 #[derive(thiserror::Error)]
 pub struct Error {
-    cause: Box<dyn WebResponseError<DefaultError>>,
+    cause: Box<dyn Cause>,
+}
+
+/// Error cause with access to concrete error type
+trait Cause: WebResponseError<DefaultError> {
+    fn as_response_error(&self) -> &dyn WebResponseError<DefaultError>;
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<T: WebResponseError<DefaultError>> Cause for T {
+    fn as_response_error(&self) -> &dyn WebResponseError<DefaultError> {
+        self
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
 }
 
 impl Error {
@@ -37,7 +60,43 @@ impl Error {
 
     /// Returns the reference to the underlying `WebResponseError`.
     pub fn as_response_error(&self) -> &dyn WebResponseError<DefaultError> {
-        self.cause.as_ref()
+        self.cause.as_response_error()
+    }
+
+    /// Returns true if the underlying error is of type `T`.
+    pub fn is<T: WebResponseError<DefaultError>>(&self) -> bool {
+        self.cause.as_any().is::<T>()
+    }
+
+    /// Returns reference to the underlying error if it is of type `T`.
+    ///
+    /// ```rust
+    /// use ntex::web::{self, error::WebResponseError, DefaultError};
+    ///
+    /// #[derive(Debug, thiserror::Error)]
+    /// enum MyError {
+    ///     #[error("Not allowed")]
+    ///     NotAllowed,
+    /// }
+    ///
+    /// impl WebResponseError<DefaultError> for MyError {}
+    ///
+    /// let err = web::Error::from(MyError::NotAllowed);
+    /// assert!(matches!(err.downcast_ref::<MyError>(), Some(MyError::NotAllowed)));
+    /// ```
+    pub fn downcast_ref<T: WebResponseError<DefaultError>>(&self) -> Option<&T> {
+        self.cause.as_any().downcast_ref()
+    }
+
+    /// Attempts to downcast error to the concrete error type `T`.
+    ///
+    /// Returns original error if the underlying error is not of type `T`.
+    pub fn downcast<T: WebResponseError<DefaultError>>(self) -> Result<T, Error> {
+        if self.is::<T>() {
+            Ok(*self.cause.into_any().downcast().unwrap())
+        } else {
+            Err(self)
+        }
     }
 }
 
@@ -54,6 +113,10 @@ impl ErrorContainer for Error {
     fn error_response(&self, req: &HttpRequest) -> HttpResponse {
         self.cause.error_response(req)
     }
+
+    fn downcast_ref<T: 'static>(&self) -> Option<&T> {
+        self.cause.as_any().downcast_ref()
+    }
 }
 
 impl crate::http::error::ResponseError for Error {
@@ -156,11 +219,34 @@ impl WebResponseError<DefaultError> for error::UrlGenerationError {}
 impl WebResponseError<DefaultError> for error::UrlencodedError {
     fn status_code(&self) -> StatusCode {
         match *self {
-            error::UrlencodedError::Overflow { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            error::UrlencodedError::Overflow { .. }
+            | error::UrlencodedError::FieldOverflow { .. } => StatusCode::PAYLOAD_TOO_LARGE,
             error::UrlencodedError::UnknownLength => StatusCode::LENGTH_REQUIRED,
+            error::UrlencodedError::Response { ref response, .. } => response.status(),
             _ => StatusCode::BAD_REQUEST,
         }
     }
+
+    fn error_response(&self, req: &HttpRequest) -> HttpResponse {
+        match *self {
+            error::UrlencodedError::Response { ref response, .. } => response.response(req),
+            _ => text_response(self),
+        }
+    }
+}
+
+/// Return `Unauthorized` with `WWW-Authenticate` challenge for `AuthError`
+impl WebResponseError<DefaultError> for error::AuthError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::UNAUTHORIZED
+    }
+
+    fn error_response(&self, _: &HttpRequest) -> HttpResponse {
+        let mut resp = text_response(self);
+        resp.headers_mut()
+            .insert(header::WWW_AUTHENTICATE, self.challenge().clone());
+        resp
+    }
 }
 
 /// Return `BadRequest` for `JsonPayloadError`
@@ -168,6 +254,73 @@ impl WebResponseError<DefaultError> for error::JsonPayloadError {
     fn status_code(&self) -> StatusCode {
         match *self {
             error::JsonPayloadError::Overflow => StatusCode::PAYLOAD_TOO_LARGE,
+            error::JsonPayloadError::Response { ref response, .. } => response.status(),
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn error_response(&self, req: &HttpRequest) -> HttpResponse {
+        match *self {
+            error::JsonPayloadError::Response { ref response, .. } => {
+                response.response(req)
+            }
+            _ => text_response(self),
+        }
+    }
+}
+
+/// Plain text response with error's status code
+fn text_response<T: WebResponseError<DefaultError>>(err: &T) -> HttpResponse {
+    let mut resp = HttpResponse::new(err.status_code());
+    let mut buf = BytesMut::new();
+    let _ = write!(Writer(&mut buf), "{}", err);
+    resp.headers_mut().insert(
+        header::CONTENT_TYPE,
+        header::HeaderValue::from_static("text/plain; charset=utf-8"),
+    );
+    resp.set_body(Body::from(buf))
+}
+
+/// Response renderer for `MultipartError`
+impl WebResponseError<DefaultError> for error::MultipartError {
+    fn status_code(&self) -> StatusCode {
+        match *self {
+            error::MultipartError::Overflow { .. }
+            | error::MultipartError::FieldOverflow { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            error::MultipartError::FieldContentType { .. } => {
+                StatusCode::UNSUPPORTED_MEDIA_TYPE
+            }
+            error::MultipartError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+#[cfg(feature = "msgpack")]
+/// `InternalServerError` for `MsgPackError`
+impl WebResponseError<DefaultError> for error::MsgPackError {}
+
+#[cfg(feature = "msgpack")]
+/// Return `BadRequest` for `MsgPackPayloadError`
+impl WebResponseError<DefaultError> for error::MsgPackPayloadError {
+    fn status_code(&self) -> StatusCode {
+        match *self {
+            error::MsgPackPayloadError::Overflow => StatusCode::PAYLOAD_TOO_LARGE,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+#[cfg(feature = "cbor")]
+/// `InternalServerError` for `CborError`
+impl WebResponseError<DefaultError> for error::CborError {}
+
+#[cfg(feature = "cbor")]
+/// Return `BadRequest` for `CborPayloadError`
+impl WebResponseError<DefaultError> for error::CborPayloadError {
+    fn status_code(&self) -> StatusCode {
+        match *self {
+            error::CborPayloadError::Overflow => StatusCode::PAYLOAD_TOO_LARGE,
             _ => StatusCode::BAD_REQUEST,
         }
     }
@@ -206,6 +359,21 @@ impl WebResponseError<DefaultError> for http::error::PayloadError {
     }
 }
 
+#[cfg(feature = "session")]
+/// `InternalServerError` for `SessionError`
+impl WebResponseError<DefaultError> for super::middleware::SessionError {}
+
+#[cfg(feature = "identity")]
+/// Return `Unauthorized` for `IdentityError::Unauthorized`
+impl WebResponseError<DefaultError> for super::middleware::IdentityError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            super::middleware::IdentityError::Unauthorized => StatusCode::UNAUTHORIZED,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
 #[cfg(feature = "cookie")]
 /// Return `BadRequest` for `cookie::ParseError`
 impl WebResponseError<DefaultError> for coo_kie::ParseError {
@@ -259,3 +427,6 @@ impl WebResponseError<DefaultError> for HandshakeError {
         }
     }
 }
+
+/// `InternalServerError` for `ws::ProtocolError`
+impl WebResponseError<DefaultError> for ProtocolError {}
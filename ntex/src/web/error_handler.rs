@@ -0,0 +1,390 @@
+use std::{fmt, mem, rc::Rc};
+
+use crate::http::body::{BodySize, MessageBody};
+use crate::http::{Response, StatusCode};
+use crate::util::HashMap;
+
+use super::error::{ErrorContainer, ErrorRenderer};
+use super::{problem::ProblemDetails, DefaultError, HttpRequest, WebResponse};
+
+type StatusHandler = Box<dyn Fn(&HttpRequest, Response) -> Response>;
+type ErrorHandler<Err> =
+    Box<dyn Fn(&<Err as ErrorRenderer>::Container, &HttpRequest) -> Option<Response>>;
+
+/// Error handlers
+///
+/// Maps response status codes and error types to custom responses.
+/// Handlers are registered with `App::default_error_handler()` or
+/// `Scope::error_handler()`, scope handlers take precedence over
+/// handlers of parent scopes and application.
+///
+/// Error type handlers are applied when error is rendered, this includes
+/// errors of extractors, handlers and middlewares. Status handlers are
+/// applied to final responses, after all middlewares.
+///
+/// With `problem_details()` enabled, unhandled errors and empty error
+/// responses, like `404 Not Found` or `405 Method Not Allowed`, are
+/// rendered as `application/problem+json`, see [`ProblemDetails`].
+///
+/// ```rust
+/// use ntex::http::StatusCode;
+/// use ntex::web::{self, error, App, ErrorHandlers, HttpResponse};
+///
+/// fn problem(status: StatusCode, detail: &str) -> HttpResponse {
+///     HttpResponse::build(status)
+///         .content_type("application/problem+json")
+///         .json(&serde_json::json!({"status": status.as_u16(), "detail": detail}))
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .default_error_handler(
+///             ErrorHandlers::new()
+///                 .status(StatusCode::NOT_FOUND, |_, _| {
+///                     problem(StatusCode::NOT_FOUND, "Resource not found")
+///                 })
+///                 .error(|err: &error::QueryPayloadError, _| {
+///                     problem(StatusCode::BAD_REQUEST, &err.to_string())
+///                 }),
+///         )
+///         .route("/", web::get().to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+pub struct ErrorHandlers<Err: ErrorRenderer = DefaultError> {
+    status: HashMap<StatusCode, StatusHandler>,
+    errors: Vec<ErrorHandler<Err>>,
+    problem: bool,
+    parent: Option<Rc<ErrorHandlers<Err>>>,
+}
+
+impl<Err: ErrorRenderer> ErrorHandlers<Err> {
+    /// Construct empty error handlers
+    pub fn new() -> Self {
+        ErrorHandlers {
+            status: HashMap::default(),
+            errors: Vec::new(),
+            problem: false,
+            parent: None,
+        }
+    }
+
+    /// Register handler for response status code
+    ///
+    /// Handler receives original response and returns new one.
+    pub fn status<F>(mut self, status: StatusCode, f: F) -> Self
+    where
+        F: Fn(&HttpRequest, Response) -> Response + 'static,
+    {
+        self.status.insert(status, Box::new(f));
+        self
+    }
+
+    /// Register handler for error type
+    ///
+    /// Error is matched by error container, see
+    /// [`ErrorContainer::downcast_ref()`]. Handlers are checked in
+    /// registration order.
+    pub fn error<E, F>(mut self, f: F) -> Self
+    where
+        E: 'static,
+        F: Fn(&E, &HttpRequest) -> Response + 'static,
+    {
+        self.errors.push(Box::new(move |err: &Err::Container, req| {
+            err.downcast_ref::<E>().map(|err| f(err, req))
+        }));
+        self
+    }
+
+    /// Render framework errors as problem details
+    ///
+    /// Errors that are not matched by error type handlers and error
+    /// responses without body are rendered as `application/problem+json`.
+    /// Error message is used as problem detail, except for server errors.
+    /// Setting is inherited by nested scopes.
+    pub fn problem_details(mut self) -> Self {
+        self.problem = true;
+        self
+    }
+
+    /// Link handlers with parent scope handlers
+    pub(super) fn finish(mut self, parent: Option<&Rc<ErrorHandlers<Err>>>) -> Rc<Self> {
+        self.parent = parent.cloned();
+        Rc::new(self)
+    }
+
+    /// Render error with registered error type handlers
+    pub(super) fn render_error(err: &Err::Container, req: &HttpRequest) -> Response {
+        let mut problem = false;
+        let mut handlers = req.app_state::<Rc<ErrorHandlers<Err>>>();
+        while let Some(hnd) = handlers {
+            for f in &hnd.errors {
+                if let Some(res) = f(err, req) {
+                    return res;
+                }
+            }
+            problem |= hnd.problem;
+            handlers = hnd.parent.as_ref();
+        }
+
+        let res = err.error_response(req);
+        if problem && err.downcast_ref::<ProblemDetails>().is_none() {
+            // server error messages could expose internal details
+            let detail = if res.status().is_server_error() {
+                None
+            } else {
+                Some(err.to_string())
+            };
+            ProblemDetails::from_response(&res, detail, req)
+        } else {
+            res
+        }
+    }
+
+    /// Apply status handlers to response
+    pub(super) fn map_response(mut res: WebResponse) -> WebResponse {
+        let status = res.status();
+        let mut problem = false;
+        let mut handlers = res.request().app_state::<Rc<ErrorHandlers<Err>>>().cloned();
+        while let Some(hnd) = handlers {
+            if let Some(f) = hnd.status.get(&status) {
+                let response = mem::replace(res.response_mut(), Response::new(status));
+                let response = f(res.request(), response);
+                return res.into_response(response);
+            }
+            problem |= hnd.problem;
+            handlers = hnd.parent.clone();
+        }
+
+        if problem
+            && (status.is_client_error() || status.is_server_error())
+            && matches!(
+                res.response().body().size(),
+                BodySize::None | BodySize::Empty
+            )
+        {
+            let response =
+                ProblemDetails::from_response(res.response(), None, res.request());
+            res.into_response(response)
+        } else {
+            res
+        }
+    }
+}
+
+impl<Err: ErrorRenderer> Default for ErrorHandlers<Err> {
+    fn default() -> Self {
+        ErrorHandlers::new()
+    }
+}
+
+impl<Err: ErrorRenderer> fmt::Debug for ErrorHandlers<Err> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ErrorHandlers")
+            .field("status", &self.status.keys().collect::<Vec<_>>())
+            .field("errors", &self.errors.len())
+            .field("problem", &self.problem)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{header, Method};
+    use crate::service::fn_service;
+    use crate::web::error::QueryPayloadError;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::types::{BasicAuth, Query};
+    use crate::web::{self, error, middleware::HttpAuth, App, HttpResponse, WebRequest};
+
+    #[derive(serde::Deserialize)]
+    struct Params {
+        #[allow(dead_code)]
+        id: u32,
+    }
+
+    #[crate::rt_test]
+    async fn test_error_handlers() {
+        let srv = init_service(
+            App::new()
+                .default_error_handler(
+                    ErrorHandlers::new()
+                        .status(StatusCode::NOT_FOUND, |req, _| {
+                            HttpResponse::NotFound().body(format!("app {}", req.path()))
+                        })
+                        .status(StatusCode::UNAUTHORIZED, |_, mut res| {
+                            *res.status_mut() = StatusCode::FORBIDDEN;
+                            res
+                        })
+                        .error(|err: &QueryPayloadError, _| {
+                            HttpResponse::BadRequest().body(format!("query: {}", err))
+                        }),
+                )
+                .route("/", web::get().to(|_: Query<Params>| async { "" }))
+                .service(
+                    web::scope("/scope")
+                        .error_handler(
+                            ErrorHandlers::new().status(StatusCode::NOT_FOUND, |_, _| {
+                                HttpResponse::NotFound().body("scope")
+                            }),
+                        )
+                        .route("/", web::get().to(|_: Query<Params>| async { "" })),
+                )
+                .service(
+                    web::scope("/admin")
+                        .wrap(HttpAuth::basic(|_: BasicAuth| async { false }))
+                        .route("/", web::get().to(|| async { "" })),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/unknown").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(read_body(res).await, "app /unknown");
+
+        let req = TestRequest::with_uri("/scope/unknown").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(read_body(res).await, "scope");
+
+        // extractor error
+        let req = TestRequest::with_uri("/?id=test").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert!(read_body(res).await.starts_with(b"query: "));
+
+        // parent handler for scope
+        let req = TestRequest::with_uri("/scope/?id=test").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert!(read_body(res).await.starts_with(b"query: "));
+
+        // middleware response
+        let req = TestRequest::with_uri("/admin/").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let req = TestRequest::with_uri("/?id=1").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[crate::rt_test]
+    async fn test_app_middleware_error() {
+        let srv = init_service(
+            App::new()
+                .default_error_handler(
+                    ErrorHandlers::new().status(StatusCode::FORBIDDEN, |_, _| {
+                        HttpResponse::Forbidden().body("forbidden")
+                    }),
+                )
+                .filter(fn_service(|req: WebRequest<DefaultError>| async move {
+                    if req.path() == "/" {
+                        Ok(req)
+                    } else {
+                        Err(error::ErrorForbidden("filter").into())
+                    }
+                }))
+                .route("/", web::get().to(|| async { "" }))
+                .route("/admin", web::get().to(|| async { "" })),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/admin").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        assert_eq!(read_body(res).await, "forbidden");
+    }
+
+    #[crate::rt_test]
+    async fn test_problem_details() {
+        let srv = init_service(
+            App::new()
+                .default_error_handler(ErrorHandlers::new().problem_details())
+                .service(
+                    web::resource("/")
+                        .route(web::get().to(|_: Query<Params>| async { "" })),
+                )
+                .route(
+                    "/internal",
+                    web::get().to(|| async {
+                        Err::<String, _>(error::ErrorInternalServerError("db failure"))
+                    }),
+                )
+                .route(
+                    "/problem",
+                    web::get().to(|| async {
+                        Err::<String, _>(
+                            ProblemDetails::new(StatusCode::CONFLICT).detail("conflict"),
+                        )
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/unknown").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/problem+json"
+        );
+        let body: serde_json::Value =
+            serde_json::from_slice(&read_body(res).await).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({"title": "Not Found", "status": 404, "instance": "/unknown"})
+        );
+
+        let req = TestRequest::with_uri("/").method(Method::POST).to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/problem+json"
+        );
+
+        // extractor error
+        let req = TestRequest::with_uri("/?id=test").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/problem+json"
+        );
+        let body: serde_json::Value =
+            serde_json::from_slice(&read_body(res).await).unwrap();
+        assert_eq!(body["status"], 400);
+        assert!(body["detail"]
+            .as_str()
+            .unwrap()
+            .starts_with("Query deserialize error"));
+
+        // handler problem is rendered as is
+        let req = TestRequest::with_uri("/problem").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        let body: serde_json::Value =
+            serde_json::from_slice(&read_body(res).await).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({"title": "Conflict", "status": 409, "detail": "conflict"})
+        );
+
+        // server error message is not exposed
+        let req = TestRequest::with_uri("/internal").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body: serde_json::Value =
+            serde_json::from_slice(&read_body(res).await).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({"title": "Internal Server Error", "status": 500, "instance": "/internal"})
+        );
+    }
+}
@@ -3,6 +3,7 @@ use std::future::Future;
 
 use super::error::ErrorRenderer;
 use super::httprequest::HttpRequest;
+use super::types::OptionalConfig;
 use crate::http::Payload;
 
 /// Trait implemented by types that can be extracted from request.
@@ -21,7 +22,8 @@ pub trait FromRequest<Err>: Sized {
 
 /// Optionally extract a field from the request
 ///
-/// If the FromRequest for T fails, return None rather than returning an error response
+/// If the FromRequest for T fails, return None rather than returning an error response.
+/// Tolerated errors could be configured with [`OptionalConfig`].
 ///
 /// ## Example
 ///
@@ -67,7 +69,7 @@ impl<T, Err> FromRequest<Err> for Option<T>
 where
     T: FromRequest<Err>,
     Err: ErrorRenderer,
-    <T as FromRequest<Err>>::Error: Into<Err::Container>,
+    <T as FromRequest<Err>>::Error: Into<Err::Container> + 'static,
 {
     type Error = Err::Container;
 
@@ -79,6 +81,11 @@ where
         match T::from_request(req, payload).await {
             Ok(v) => Ok(Some(v)),
             Err(e) => {
+                if let Some(cfg) = req.app_state::<OptionalConfig>() {
+                    if !cfg.is_tolerated(&e) {
+                        return Err(e.into());
+                    }
+                }
                 log::debug!("Error for Option<T> extractor: {}", e.into());
                 Ok(None)
             }
@@ -88,7 +95,8 @@ where
 
 /// Optionally extract a field from the request or extract the Error if unsuccessful
 ///
-/// If the `FromRequest` for T fails, inject Err into handler rather than returning an error response
+/// If the `FromRequest` for T fails, inject Err into handler rather than returning an error response.
+/// Errors that are passed to handler could be configured with [`OptionalConfig`].
 ///
 /// ## Example
 ///
@@ -131,6 +139,7 @@ where
 impl<T, E> FromRequest<E> for Result<T, T::Error>
 where
     T: FromRequest<E>,
+    T::Error: 'static,
     E: ErrorRenderer,
 {
     type Error = T::Error;
@@ -142,7 +151,10 @@ where
     ) -> Result<Self, Self::Error> {
         match T::from_request(req, payload).await {
             Ok(v) => Ok(Ok(v)),
-            Err(e) => Ok(Err(e)),
+            Err(e) => match req.app_state::<OptionalConfig>() {
+                Some(cfg) if !cfg.is_tolerated(&e) => Err(e),
+                _ => Ok(Err(e)),
+            },
         }
     }
 }
@@ -197,7 +209,7 @@ mod tests {
     use crate::util::Bytes;
     use crate::web::error::UrlencodedError;
     use crate::web::test::{from_request, TestRequest};
-    use crate::web::types::{Form, FormConfig};
+    use crate::web::types::{Form, FormConfig, OptionalConfig};
 
     #[derive(serde::Deserialize, Debug, PartialEq)]
     struct Info {
@@ -283,4 +295,41 @@ mod tests {
             .unwrap();
         assert!(r.is_err());
     }
+
+    #[crate::rt_test]
+    async fn test_optional_config() {
+        let cfg = OptionalConfig::default()
+            .strict()
+            .tolerate(|err: &UrlencodedError| matches!(err, UrlencodedError::ContentType));
+
+        // no form data
+        let (req, mut pl) = TestRequest::default().state(cfg.clone()).to_http_parts();
+        let r = from_request::<Option<Form<Info>>>(&req, &mut pl)
+            .await
+            .unwrap();
+        assert_eq!(r, None);
+
+        // invalid form data
+        let (req, mut pl) = TestRequest::with_header(
+            header::CONTENT_TYPE,
+            "application/x-www-form-urlencoded",
+        )
+        .header(header::CONTENT_LENGTH, "9")
+        .set_payload(Bytes::from_static(b"bye=world"))
+        .state(cfg.clone())
+        .to_http_parts();
+        let r = from_request::<Option<Form<Info>>>(&req, &mut pl).await;
+        assert!(r.is_err());
+
+        let (req, mut pl) = TestRequest::with_header(
+            header::CONTENT_TYPE,
+            "application/x-www-form-urlencoded",
+        )
+        .header(header::CONTENT_LENGTH, "9")
+        .set_payload(Bytes::from_static(b"bye=world"))
+        .state(cfg)
+        .to_http_parts();
+        let r = from_request::<Result<Form<Info>, UrlencodedError>>(&req, &mut pl).await;
+        assert!(r.is_err());
+    }
 }
@@ -0,0 +1,1522 @@
+//! Static files support
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::task::{Context, Poll};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{cell::RefCell, cmp, error::Error, fmt, fs, pin::Pin, rc::Rc};
+
+use httpdate::HttpDate;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+
+use sha1::{Digest as _, Sha1};
+
+use crate::http::header::{self, HeaderValue};
+use crate::http::range::{HttpRange, RangeResponse};
+use crate::http::{Method, StatusCode};
+use crate::router::ResourceDef;
+use crate::rt::spawn_blocking;
+use crate::service::{fn_service, Service, ServiceCtx, ServiceFactory};
+use crate::util::{BoxFuture, Bytes, HashMap, Stream};
+
+use super::dev::{WebServiceConfig, WebServiceFactory};
+use super::{ErrorRenderer, HttpResponse, WebRequest, WebResponse};
+
+/// Construct [`EmbeddedFiles`] service from files embedded at compile time.
+///
+/// File paths are resolved relative to the current source file,
+/// the same way as for `include_bytes!()` macro.
+///
+/// ```rust,ignore
+/// use ntex::web::{self, App};
+///
+/// let app = App::new().service(ntex::embed_files!("/static";
+///     "index.html" => "../assets/index.html",
+///     "app.js" => "../assets/app.js",
+///     "app.js.br" => "../assets/app.js.br",
+/// ).index_file("index.html"));
+/// ```
+#[macro_export]
+macro_rules! embed_files {
+    ($path:expr; $($name:literal => $file:literal),* $(,)?) => {
+        $crate::web::fs::EmbeddedFiles::new($path)
+            $(.file($name, include_bytes!($file)))*
+    };
+}
+
+/// Static files service for assets embedded into the binary
+///
+/// ETags are computed once, when service is registered. If files map
+/// contains precompressed variant of the file, like `app.js.br` or
+/// `app.js.gz` for `app.js`, variant is selected according to request's
+/// `Accept-Encoding` header.
+///
+/// ```rust
+/// use ntex::web::{self, fs::EmbeddedFiles, App};
+///
+/// let app = App::new().service(
+///     EmbeddedFiles::new("/static")
+///         .file("index.html", b"<html></html>")
+///         .file("app.js", b"console.log('app')")
+///         .index_file("index.html"),
+/// );
+/// ```
+pub struct EmbeddedFiles {
+    path: String,
+    files: Vec<(String, &'static [u8])>,
+    index: Option<String>,
+}
+
+impl EmbeddedFiles {
+    /// Create embedded files service mounted at the specified path.
+    pub fn new(path: &str) -> Self {
+        EmbeddedFiles {
+            path: path.trim_end_matches('/').to_string(),
+            files: Vec::new(),
+            index: None,
+        }
+    }
+
+    /// Add file to the service.
+    ///
+    /// Name is a path relative to the mount path.
+    pub fn file(mut self, name: &str, data: &'static [u8]) -> Self {
+        self.files
+            .push((name.trim_start_matches('/').to_string(), data));
+        self
+    }
+
+    /// Add files from iterator of name and content pairs.
+    pub fn files<I, N>(mut self, files: I) -> Self
+    where
+        I: IntoIterator<Item = (N, &'static [u8])>,
+        N: AsRef<str>,
+    {
+        for (name, data) in files {
+            self = self.file(name.as_ref(), data);
+        }
+        self
+    }
+
+    /// Set file to serve for the mount path and directory requests.
+    pub fn index_file(mut self, name: &str) -> Self {
+        self.index = Some(name.trim_start_matches('/').to_string());
+        self
+    }
+
+    fn build(self) -> Embedded {
+        let raw: HashMap<_, _> = self.files.into_iter().collect();
+
+        let mut files = HashMap::default();
+        for (name, data) in &raw {
+            // precompressed variants are served with original file
+            let orig = name
+                .strip_suffix(".br")
+                .or_else(|| name.strip_suffix(".gz"));
+            if orig.map(|n| raw.contains_key(n)).unwrap_or(false) {
+                continue;
+            }
+
+            let variant = |ext: &str, enc: &'static str| {
+                raw.get(&format!("{}.{}", name, ext))
+                    .map(|data| Variant::new(data, Some(enc)))
+            };
+            files.insert(
+                name.clone(),
+                File {
+                    content_type: content_type(name, Some("utf-8")),
+                    identity: Variant::new(data, None),
+                    br: variant("br", "br"),
+                    gzip: variant("gz", "gzip"),
+                },
+            );
+        }
+
+        Embedded {
+            files,
+            index: self.index,
+        }
+    }
+}
+
+impl fmt::Debug for EmbeddedFiles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EmbeddedFiles")
+            .field("path", &self.path)
+            .field("files", &self.files.len())
+            .field("index", &self.index)
+            .finish()
+    }
+}
+
+impl<Err: ErrorRenderer> WebServiceFactory<Err> for EmbeddedFiles {
+    fn register(self, config: &mut WebServiceConfig<Err>) {
+        let rdef = if config.is_root() || !self.path.is_empty() {
+            ResourceDef::root_prefix(self.path.as_str())
+        } else {
+            ResourceDef::prefix(self.path.as_str())
+        };
+        let files = Rc::new(self.build());
+
+        config.register_service(
+            rdef,
+            None,
+            fn_service(move |req: WebRequest<Err>| {
+                let res = files.handle(&req);
+                async move { Ok(req.into_response(res)) }
+            }),
+            None,
+        )
+    }
+}
+
+struct Embedded {
+    files: HashMap<String, File>,
+    index: Option<String>,
+}
+
+struct File {
+    content_type: HeaderValue,
+    identity: Variant,
+    br: Option<Variant>,
+    gzip: Option<Variant>,
+}
+
+struct Variant {
+    data: &'static [u8],
+    etag: HeaderValue,
+    encoding: Option<HeaderValue>,
+}
+
+impl Variant {
+    fn new(data: &'static [u8], encoding: Option<&'static str>) -> Self {
+        let hash = Sha1::digest(data);
+        let mut etag = String::with_capacity(36);
+        etag.push('"');
+        for b in &hash[..16] {
+            etag.push_str(&format!("{:02x}", b));
+        }
+        if let Some(enc) = encoding {
+            etag.push('-');
+            etag.push_str(enc);
+        }
+        etag.push('"');
+
+        Variant {
+            data,
+            etag: HeaderValue::try_from(etag).unwrap(),
+            encoding: encoding.map(HeaderValue::from_static),
+        }
+    }
+}
+
+impl Embedded {
+    fn handle<Err>(&self, req: &WebRequest<Err>) -> HttpResponse {
+        if req.method() != Method::GET && req.method() != Method::HEAD {
+            return HttpResponse::MethodNotAllowed()
+                .header(header::ALLOW, "GET, HEAD")
+                .finish();
+        }
+
+        let name = req.match_info().unprocessed().trim_start_matches('/');
+        let file = if name.is_empty() || name.ends_with('/') {
+            self.index
+                .as_ref()
+                .and_then(|index| self.files.get(&format!("{}{}", name, index)))
+        } else {
+            self.files.get(name)
+        };
+        let file = if let Some(file) = file {
+            file
+        } else {
+            return HttpResponse::NotFound().finish();
+        };
+
+        let accept = req
+            .headers()
+            .get(&header::ACCEPT_ENCODING)
+            .and_then(|val| val.to_str().ok())
+            .unwrap_or("");
+        let variant = file
+            .br
+            .as_ref()
+            .filter(|_| accepts(accept, "br"))
+            .or_else(|| file.gzip.as_ref().filter(|_| accepts(accept, "gzip")))
+            .unwrap_or(&file.identity);
+
+        let modified = !not_modified(req, &variant.etag);
+        let mut res = if modified {
+            HttpResponse::Ok()
+        } else {
+            HttpResponse::build(StatusCode::NOT_MODIFIED)
+        };
+        res.header(header::ETAG, variant.etag.clone());
+        if file.br.is_some() || file.gzip.is_some() {
+            res.header(header::VARY, "accept-encoding");
+        }
+        if !modified {
+            return res.finish();
+        }
+
+        res.header(header::CONTENT_TYPE, file.content_type.clone());
+        if let Some(ref enc) = variant.encoding {
+            res.header(header::CONTENT_ENCODING, enc.clone());
+        }
+        res.body(Bytes::from_static(variant.data))
+    }
+}
+
+/// Default size of file read chunk
+const CHUNK_SIZE: usize = 65_536;
+
+/// Characters to escape in directory listing links
+const SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'&')
+    .add(b'\'')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'\\')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// Static files service for serving files from the directory
+///
+/// Files are read in chunks on blocking threads pool. Service supports
+/// conditional requests with `ETag` and `Last-Modified` headers and
+/// byte range requests. Request paths with `..` segments are
+/// rejected, resolved files must be located inside of the served
+/// directory, symlinks pointing outside of the directory are not served.
+/// Directory requests without trailing slash are redirected to the path
+/// with trailing slash.
+///
+/// ```rust,no_run
+/// use ntex::web::{self, App};
+///
+/// let app = App::new()
+///     .service(
+///         web::Files::new("/static", "./static")
+///             .index_file("index.html")
+///             .show_files_listing(),
+///     )
+///     // single page application
+///     .service(
+///         web::Files::new("/app", "./app")
+///             .index_file("index.html")
+///             .fallback_file("index.html")
+///             .use_precompressed(),
+///     );
+/// ```
+///
+/// Files service could be used as scope default service, in that case
+/// files are resolved relative to the scope path and mount path is ignored.
+///
+/// ```rust
+/// use ntex::web::{self, App};
+///
+/// let app = App::new().service(
+///     web::scope("/docs")
+///         .route("/search", web::get().to(|| async { "search" }))
+///         .default_service(web::Files::new("", "./docs").index_file("index.html")),
+/// );
+/// ```
+#[derive(Clone)]
+pub struct Files {
+    path: String,
+    directory: PathBuf,
+    index: Option<String>,
+    listing: bool,
+    chunk_size: usize,
+    precompressed: bool,
+    languages: bool,
+    charset: Option<String>,
+    fallback: Option<String>,
+    rewrite: Option<Rc<dyn Fn(&str) -> Option<String>>>,
+}
+
+impl Files {
+    /// Create files service for the directory mounted at the specified path.
+    pub fn new<T: Into<PathBuf>>(path: &str, dir: T) -> Self {
+        Files {
+            path: path.trim_end_matches('/').to_string(),
+            directory: dir.into(),
+            index: None,
+            listing: false,
+            chunk_size: CHUNK_SIZE,
+            precompressed: false,
+            languages: false,
+            charset: Some("utf-8".to_string()),
+            fallback: None,
+            rewrite: None,
+        }
+    }
+
+    /// Set file to serve for directory requests.
+    pub fn index_file(mut self, name: &str) -> Self {
+        self.index = Some(name.to_string());
+        self
+    }
+
+    /// Show directory listing for directories without index file.
+    ///
+    /// By default listing is disabled.
+    pub fn show_files_listing(mut self) -> Self {
+        self.listing = true;
+        self
+    }
+
+    /// Set size of file read chunk.
+    ///
+    /// By default chunk size is 64Kb.
+    pub fn chunk_size(mut self, size: usize) -> Self {
+        self.chunk_size = cmp::max(size, 1);
+        self
+    }
+
+    /// Serve precompressed variants of files.
+    ///
+    /// If client accepts `br` or `gzip` encoding and file has `.br` or
+    /// `.gz` sibling, sibling is served with `Content-Encoding` header.
+    /// By default precompressed variants are not used.
+    pub fn use_precompressed(mut self) -> Self {
+        self.precompressed = true;
+        self
+    }
+
+    /// Serve language variants of files.
+    ///
+    /// If file has sibling with language tag extension, for example
+    /// `index.html.fr`, and client accepts the language, sibling is served
+    /// with `Content-Language` header. Language preference is taken from
+    /// `Accept-Language` header, `fr-CA` falls back to `fr` variant.
+    /// Precompressed variant is looked up for selected file. By default
+    /// language variants are not used.
+    pub fn use_languages(mut self) -> Self {
+        self.languages = true;
+        self
+    }
+
+    /// Set charset for text files.
+    ///
+    /// Charset is added to `Content-Type` header of `text/*` and javascript
+    /// files, `None` disables charset. By default charset is `utf-8`.
+    pub fn charset(mut self, charset: Option<&str>) -> Self {
+        self.charset = charset.map(|cs| cs.to_string());
+        self
+    }
+
+    /// Set file to serve if requested file is not found.
+    ///
+    /// Name is a path relative to the served directory. Could be used
+    /// for single page applications with client side routing.
+    pub fn fallback_file(mut self, name: &str) -> Self {
+        self.fallback = Some(name.trim_start_matches('/').to_string());
+        self
+    }
+
+    /// Set request path rewrite function.
+    ///
+    /// Function receives decoded request path relative to the mount path
+    /// and returns path to look up, `None` produces `404 Not Found`.
+    pub fn path_rewrite<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&str) -> Option<String> + 'static,
+    {
+        self.rewrite = Some(Rc::new(f));
+        self
+    }
+
+    async fn handle<Err>(&self, req: &WebRequest<Err>) -> HttpResponse {
+        if req.method() != Method::GET && req.method() != Method::HEAD {
+            return HttpResponse::MethodNotAllowed()
+                .header(header::ALLOW, "GET, HEAD")
+                .finish();
+        }
+
+        let path = match percent_decode_str(req.match_info().unprocessed()).decode_utf8() {
+            Ok(path) => path.into_owned(),
+            Err(_) => return HttpResponse::BadRequest().finish(),
+        };
+        let path = if let Some(ref rewrite) = self.rewrite {
+            if let Some(path) = rewrite(&path) {
+                path
+            } else {
+                return HttpResponse::NotFound().finish();
+            }
+        } else {
+            path
+        };
+        let segments = if let Some(segments) = split_path(&path) {
+            segments
+        } else {
+            return HttpResponse::NotFound().finish();
+        };
+        let fallback = self.fallback.as_deref().and_then(split_path);
+
+        // acceptable precompressed variants
+        let mut encodings = Vec::new();
+        if self.precompressed {
+            let accept = req
+                .headers()
+                .get(&header::ACCEPT_ENCODING)
+                .and_then(|val| val.to_str().ok())
+                .unwrap_or("");
+            for (enc, ext) in [("br", "br"), ("gzip", "gz")] {
+                if accepts(accept, enc) {
+                    encodings.push((enc, ext));
+                }
+            }
+        }
+
+        // acceptable language variants
+        let languages = if self.languages {
+            accept_languages(
+                req.headers()
+                    .get(&header::ACCEPT_LANGUAGE)
+                    .and_then(|val| val.to_str().ok())
+                    .unwrap_or(""),
+            )
+        } else {
+            Vec::new()
+        };
+
+        let root = self.directory.clone();
+        let index = self.index.clone();
+        let listing = self.listing;
+        let slash = req.path().ends_with('/');
+        let result = blocking(move || {
+            let res = lookup(
+                &root,
+                segments,
+                index.as_deref(),
+                listing,
+                slash,
+                &languages,
+                &encodings,
+            );
+            match (res, fallback) {
+                (Ok(Lookup::NotFound), Some(fallback)) => {
+                    lookup(&root, fallback, None, false, true, &languages, &encodings)
+                }
+                (Err(e), Some(fallback)) if e.kind() == io::ErrorKind::NotFound => {
+                    lookup(&root, fallback, None, false, true, &languages, &encodings)
+                }
+                (res, _) => res,
+            }
+        });
+
+        match result.await {
+            Ok(Lookup::File(file, md, name, enc, lang)) => {
+                self.serve(req, file, *md, &name, enc, lang.as_deref())
+            }
+            Ok(Lookup::Listing(entries)) => dir_listing(req.path(), entries),
+            Ok(Lookup::Redirect) => {
+                let location = if let Some(query) = req.uri().query() {
+                    format!("{}/?{}", req.path(), query)
+                } else {
+                    format!("{}/", req.path())
+                };
+                HttpResponse::MovedPermanently()
+                    .header(header::LOCATION, location)
+                    .finish()
+            }
+            Ok(Lookup::NotFound) => HttpResponse::NotFound().finish(),
+            Err(e) => match e.kind() {
+                io::ErrorKind::NotFound => HttpResponse::NotFound().finish(),
+                io::ErrorKind::PermissionDenied => HttpResponse::Forbidden().finish(),
+                _ => {
+                    log::error!("Cannot open file {:?}: {}", path, e);
+                    HttpResponse::InternalServerError().finish()
+                }
+            },
+        }
+    }
+
+    fn serve<Err>(
+        &self,
+        req: &WebRequest<Err>,
+        file: fs::File,
+        md: fs::Metadata,
+        name: &str,
+        encoding: Option<&'static str>,
+        language: Option<&str>,
+    ) -> HttpResponse {
+        let size = md.len();
+        let modified = md.modified().ok();
+        let etag = file_etag(size, modified, encoding, language);
+        let last_modified = modified.map(HttpDate::from);
+
+        // preconditions
+        let precondition_failed = if req.headers().contains_key(header::IF_MATCH) {
+            !if_match(req, &etag)
+        } else if let Some(since) = header_date(req, &header::IF_UNMODIFIED_SINCE) {
+            last_modified.map(|lm| lm > since).unwrap_or(true)
+        } else {
+            false
+        };
+        if precondition_failed {
+            return HttpResponse::PreconditionFailed().finish();
+        }
+
+        let not_modified = if req.headers().contains_key(header::IF_NONE_MATCH) {
+            not_modified(req, &etag)
+        } else if let Some(since) = header_date(req, &header::IF_MODIFIED_SINCE) {
+            last_modified.map(|lm| lm <= since).unwrap_or(false)
+        } else {
+            false
+        };
+
+        let mut res = if not_modified {
+            HttpResponse::build(StatusCode::NOT_MODIFIED)
+        } else {
+            HttpResponse::Ok()
+        };
+        res.header(header::ETAG, etag.clone());
+        if let Some(lm) = last_modified {
+            res.header(header::LAST_MODIFIED, lm.to_string());
+        }
+        let vary = match (self.precompressed, self.languages) {
+            (true, true) => Some("accept-encoding, accept-language"),
+            (true, false) => Some("accept-encoding"),
+            (false, true) => Some("accept-language"),
+            (false, false) => None,
+        };
+        if let Some(vary) = vary {
+            res.header(header::VARY, vary);
+        }
+        if not_modified {
+            return res.finish();
+        }
+        if let Some(enc) = encoding {
+            res.header(header::CONTENT_ENCODING, enc);
+        }
+        if let Some(lang) = language {
+            res.header(header::CONTENT_LANGUAGE, lang);
+        }
+
+        let chunk_size = self.chunk_size;
+        let files = Rc::new(RefCell::new(Vec::new()));
+        let files2 = files.clone();
+        let range =
+            RangeResponse::new(req.head(), size, move |offset, len| ChunkedReadFile {
+                file: files2.borrow_mut().pop(),
+                offset,
+                size: len,
+                chunk_size,
+                fut: None,
+            })
+            .content_type(content_type(name, self.charset.as_deref()))
+            .validators(Some(etag), last_modified);
+
+        // every range reads its own file handle
+        let count = match range.range() {
+            HttpRange::Partial(ranges) => ranges.len(),
+            _ => 1,
+        };
+        for _ in 1..count {
+            match file.try_clone() {
+                Ok(file) => files.borrow_mut().push(file),
+                Err(e) => {
+                    log::error!("Cannot clone file handle: {}", e);
+                    return HttpResponse::InternalServerError().finish();
+                }
+            }
+        }
+        files.borrow_mut().push(file);
+
+        range.build(&mut res)
+    }
+}
+
+impl fmt::Debug for Files {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Files")
+            .field("path", &self.path)
+            .field("directory", &self.directory)
+            .field("index", &self.index)
+            .field("listing", &self.listing)
+            .field("precompressed", &self.precompressed)
+            .field("languages", &self.languages)
+            .field("charset", &self.charset)
+            .field("fallback", &self.fallback)
+            .finish()
+    }
+}
+
+impl<Err: ErrorRenderer> WebServiceFactory<Err> for Files {
+    fn register(self, config: &mut WebServiceConfig<Err>) {
+        let rdef = if config.is_root() || !self.path.is_empty() {
+            ResourceDef::root_prefix(self.path.as_str())
+        } else {
+            ResourceDef::prefix(self.path.as_str())
+        };
+        let files = Rc::new(self);
+
+        config.register_service(
+            rdef,
+            None,
+            fn_service(move |req: WebRequest<Err>| {
+                let files = files.clone();
+                async move {
+                    let res = files.handle(&req).await;
+                    Ok(req.into_response(res))
+                }
+            }),
+            None,
+        )
+    }
+}
+
+impl<Err: ErrorRenderer> ServiceFactory<WebRequest<Err>> for Files {
+    type Response = WebResponse;
+    type Error = Err::Container;
+    type Service = FilesService;
+    type InitError = ();
+
+    async fn create(&self, _: ()) -> Result<Self::Service, Self::InitError> {
+        Ok(FilesService(Rc::new(self.clone())))
+    }
+}
+
+/// Files service, resolves files by unmatched request path
+#[derive(Debug)]
+pub struct FilesService(Rc<Files>);
+
+impl<Err: ErrorRenderer> Service<WebRequest<Err>> for FilesService {
+    type Response = WebResponse;
+    type Error = Err::Container;
+
+    async fn call(
+        &self,
+        req: WebRequest<Err>,
+        _: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        let res = self.0.handle(&req).await;
+        Ok(req.into_response(res))
+    }
+}
+
+enum Lookup {
+    File(
+        fs::File,
+        Box<fs::Metadata>,
+        String,
+        Option<&'static str>,
+        Option<String>,
+    ),
+    Listing(Vec<(String, bool)>),
+    Redirect,
+    NotFound,
+}
+
+/// Split path to segments, `None` if path is not allowed
+fn split_path(path: &str) -> Option<Vec<String>> {
+    let mut segments = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => continue,
+            ".." => return None,
+            s if s.contains('\\') || s.contains('\0') => return None,
+            s => segments.push(s.to_string()),
+        }
+    }
+    Some(segments)
+}
+
+/// Resolve request path, runs on blocking threads pool
+fn lookup(
+    root: &Path,
+    segments: Vec<String>,
+    index: Option<&str>,
+    listing: bool,
+    slash: bool,
+    languages: &[String],
+    encodings: &[(&'static str, &'static str)],
+) -> io::Result<Lookup> {
+    let root = root.canonicalize()?;
+    let resolve = |path: PathBuf| -> io::Result<Option<PathBuf>> {
+        match path.canonicalize() {
+            Ok(path) if path.starts_with(&root) => Ok(Some(path)),
+            Ok(_) => Ok(None),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    };
+
+    let mut path =
+        if let Some(path) = resolve(root.join(segments.iter().collect::<PathBuf>()))? {
+            path
+        } else {
+            return Ok(Lookup::NotFound);
+        };
+
+    if path.is_dir() {
+        if let Some(index) = index.and_then(|index| resolve(path.join(index)).transpose()) {
+            let index = index?;
+            if index.is_file() {
+                path = index;
+            }
+        }
+
+        if path.is_dir() && !listing {
+            return Ok(Lookup::NotFound);
+        }
+        // relative links of directory content require trailing slash
+        if !slash {
+            return Ok(Lookup::Redirect);
+        }
+
+        if path.is_dir() {
+            let mut entries = Vec::new();
+            for entry in fs::read_dir(&path)? {
+                let entry = entry?;
+                entries.push((
+                    entry.file_name().to_string_lossy().into_owned(),
+                    entry.path().is_dir(),
+                ));
+            }
+            entries.sort();
+            return Ok(Lookup::Listing(entries));
+        }
+    }
+
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    // language sibling
+    let mut language = None;
+    for lang in languages {
+        let sibling = path.with_file_name(format!("{}.{}", name, lang));
+        if let Some(sibling) = resolve(sibling)? {
+            if sibling.is_file() {
+                path = sibling;
+                language = Some(lang.clone());
+                break;
+            }
+        }
+    }
+
+    // precompressed sibling
+    for &(enc, ext) in encodings {
+        let mut sibling = path.clone().into_os_string();
+        sibling.push(".");
+        sibling.push(ext);
+        if let Some(sibling) = resolve(sibling.into())? {
+            if sibling.is_file() {
+                let file = fs::File::open(&sibling)?;
+                let md = file.metadata()?;
+                return Ok(Lookup::File(file, Box::new(md), name, Some(enc), language));
+            }
+        }
+    }
+
+    let file = fs::File::open(&path)?;
+    let md = file.metadata()?;
+    Ok(Lookup::File(file, Box::new(md), name, None, language))
+}
+
+/// Run io operation on blocking threads pool
+async fn blocking<F, T>(f: F) -> io::Result<T>
+where
+    F: FnOnce() -> io::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    spawn_blocking(f)
+        .await
+        .map_err(|_| io::Error::other("Blocking task failed"))?
+}
+
+/// Render directory listing
+fn dir_listing(base: &str, entries: Vec<(String, bool)>) -> HttpResponse {
+    let base = html_escape(base.trim_end_matches('/'));
+    let title = if base.is_empty() { "/" } else { &base };
+
+    let mut body = format!(
+        "<html><head><title>Index of {0}</title></head>\
+         <body><h1>Index of {0}</h1><ul>",
+        title
+    );
+    for (name, is_dir) in entries {
+        let slash = if is_dir { "/" } else { "" };
+        body.push_str(&format!(
+            "<li><a href=\"{}/{}{}\">{}{}</a></li>",
+            base,
+            utf8_percent_encode(&name, SEGMENT),
+            slash,
+            html_escape(&name),
+            slash
+        ));
+    }
+    body.push_str("</ul></body></html>");
+
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(body)
+}
+
+fn html_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Stream of file chunks, chunks are read on blocking threads pool
+struct ChunkedReadFile {
+    file: Option<fs::File>,
+    offset: u64,
+    size: u64,
+    chunk_size: usize,
+    fut: Option<BoxFuture<'static, io::Result<(fs::File, Bytes)>>>,
+}
+
+impl Stream for ChunkedReadFile {
+    type Item = Result<Bytes, Box<dyn Error>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if let Some(ref mut fut) = this.fut {
+            return match fut.as_mut().poll(cx) {
+                Poll::Ready(Ok((file, chunk))) => {
+                    this.fut = None;
+                    this.file = Some(file);
+                    this.offset += chunk.len() as u64;
+                    this.size -= chunk.len() as u64;
+                    Poll::Ready(Some(Ok(chunk)))
+                }
+                Poll::Ready(Err(e)) => {
+                    this.fut = None;
+                    Poll::Ready(Some(Err(Box::new(e))))
+                }
+                Poll::Pending => Poll::Pending,
+            };
+        }
+
+        if this.size == 0 {
+            return Poll::Ready(None);
+        }
+        let mut file = if let Some(file) = this.file.take() {
+            file
+        } else {
+            return Poll::Ready(None);
+        };
+
+        let offset = this.offset;
+        let max = cmp::min(this.size, this.chunk_size as u64);
+        this.fut = Some(Box::pin(blocking(move || {
+            let mut buf = Vec::with_capacity(max as usize);
+            file.seek(SeekFrom::Start(offset))?;
+            (&mut file).take(max).read_to_end(&mut buf)?;
+            if buf.is_empty() {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            Ok((file, Bytes::from(buf)))
+        })));
+        Pin::new(this).poll_next(cx)
+    }
+}
+
+/// Generate etag from file size and modification time
+fn file_etag(
+    size: u64,
+    modified: Option<SystemTime>,
+    enc: Option<&str>,
+    lang: Option<&str>,
+) -> HeaderValue {
+    let mtime = modified
+        .and_then(|mtime| mtime.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default();
+    let enc = enc.map(|enc| format!("-{}", enc)).unwrap_or_default();
+    let lang = lang.map(|lang| format!("-{}", lang)).unwrap_or_default();
+    HeaderValue::try_from(format!(
+        "\"{:x}-{:x}.{:x}{}{}\"",
+        size,
+        mtime.as_secs(),
+        mtime.subsec_nanos(),
+        enc,
+        lang
+    ))
+    .unwrap()
+}
+
+/// Parse http date header
+fn header_date<Err>(req: &WebRequest<Err>, name: &header::HeaderName) -> Option<HttpDate> {
+    req.headers()
+        .get(name)
+        .and_then(|val| val.to_str().ok())
+        .and_then(|val| val.parse().ok())
+}
+
+/// Check if `If-Match` header matches etag
+fn if_match<Err>(req: &WebRequest<Err>, etag: &HeaderValue) -> bool {
+    req.headers()
+        .get_all(&header::IF_MATCH)
+        .filter_map(|val| val.to_str().ok())
+        .flat_map(|val| val.split(','))
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.as_bytes() == etag.as_bytes())
+}
+
+/// Check if `If-None-Match` header matches etag
+fn not_modified<Err>(req: &WebRequest<Err>, etag: &HeaderValue) -> bool {
+    req.headers()
+        .get_all(&header::IF_NONE_MATCH)
+        .filter_map(|val| val.to_str().ok())
+        .flat_map(|val| val.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == "*" || tag.as_bytes() == etag.as_bytes())
+}
+
+/// Check if encoding is acceptable, encodings with zero quality are rejected
+fn accepts(header: &str, encoding: &str) -> bool {
+    header.split(',').any(|item| {
+        let mut parts = item.split(';');
+        if !parts
+            .next()
+            .map(|enc| enc.trim().eq_ignore_ascii_case(encoding))
+            .unwrap_or(false)
+        {
+            return false;
+        }
+        parts
+            .filter_map(|p| p.trim().strip_prefix("q="))
+            .all(|q| q.parse::<f32>().map(|q| q > 0.0).unwrap_or(false))
+    })
+}
+
+/// Parse `Accept-Language` header
+///
+/// Returns lowercased language tags ordered by quality, primary subtag
+/// follows full tag. Tags with zero quality and wildcard are skipped.
+fn accept_languages(header: &str) -> Vec<String> {
+    let mut tags = Vec::new();
+    for item in header.split(',') {
+        let mut parts = item.split(';');
+        let tag = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+        if tag.is_empty()
+            || tag == "*"
+            || !tag.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+        {
+            continue;
+        }
+        let q = parts
+            .find_map(|p| p.trim().strip_prefix("q="))
+            .map(|q| q.parse::<f32>().unwrap_or(0.0))
+            .unwrap_or(1.0);
+        if q > 0.0 {
+            tags.push((tag, q));
+        }
+    }
+    tags.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(cmp::Ordering::Equal));
+
+    let mut languages = Vec::new();
+    for (tag, _) in tags {
+        let primary = tag.split('-').next().unwrap_or_default().to_string();
+        for lang in [tag, primary] {
+            if !lang.is_empty() && !languages.contains(&lang) {
+                languages.push(lang);
+            }
+        }
+    }
+    languages
+}
+
+/// Guess content type by file extension
+///
+/// Charset is added to text types.
+fn content_type(name: &str, charset: Option<&str>) -> HeaderValue {
+    let ext = name.rsplit_once('.').map(|(_, ext)| ext).unwrap_or("");
+    let mime = match ext.to_ascii_lowercase().as_str() {
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" | "mjs" => "text/javascript",
+        "json" | "map" => "application/json",
+        "txt" => "text/plain",
+        "csv" => "text/csv",
+        "md" => "text/markdown",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "wasm" => "application/wasm",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "pdf" => "application/pdf",
+        _ => "application/octet-stream",
+    };
+
+    match charset {
+        Some(charset) if mime.starts_with("text/") => {
+            HeaderValue::try_from(format!("{}; charset={}", mime, charset))
+                .unwrap_or_else(|_| HeaderValue::from_static(mime))
+        }
+        _ => HeaderValue::from_static(mime),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App};
+
+    #[crate::rt_test]
+    async fn test_embedded_files() {
+        let srv = init_service(
+            App::new().service(
+                EmbeddedFiles::new("/static")
+                    .file("index.html", b"<html></html>")
+                    .file("app.js", b"app")
+                    .file("app.js.br", b"app-br")
+                    .file("app.js.gz", b"app-gz")
+                    .file("data.tar.gz", b"data")
+                    .index_file("index.html"),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/static/").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/html; charset=utf-8"
+        );
+        assert_eq!(read_body(res).await, Bytes::from_static(b"<html></html>"));
+
+        let req = TestRequest::with_uri("/static/app.js").to_request();
+        let res = call_service(&srv, req).await;
+        assert!(res.headers().get(header::CONTENT_ENCODING).is_none());
+        let etag = res.headers().get(header::ETAG).unwrap().clone();
+        assert_eq!(read_body(res).await, Bytes::from_static(b"app"));
+
+        let req = TestRequest::with_uri("/static/app.js")
+            .header(header::ACCEPT_ENCODING, "gzip, br;q=0.8")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.headers().get(header::CONTENT_ENCODING).unwrap(), "br");
+        assert_eq!(res.headers().get(header::VARY).unwrap(), "accept-encoding");
+        assert_ne!(res.headers().get(header::ETAG).unwrap(), &etag);
+        assert_eq!(read_body(res).await, Bytes::from_static(b"app-br"));
+
+        let req = TestRequest::with_uri("/static/app.js")
+            .header(header::ACCEPT_ENCODING, "gzip, br;q=0")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.headers().get(header::CONTENT_ENCODING).unwrap(), "gzip");
+        assert_eq!(read_body(res).await, Bytes::from_static(b"app-gz"));
+
+        let req = TestRequest::with_uri("/static/app.js")
+            .header(header::IF_NONE_MATCH, etag.clone())
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(res.headers().get(header::ETAG).unwrap(), &etag);
+
+        let req = TestRequest::with_uri("/static/data.tar.gz").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.headers().get(header::CONTENT_ENCODING).is_none());
+
+        let req = TestRequest::with_uri("/static/app.js.br").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let req = TestRequest::with_uri("/static/app.js")
+            .method(Method::POST)
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    fn files_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "ntex-files-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("index.html"), b"<html></html>").unwrap();
+        fs::write(dir.join("data.txt"), b"0123456789").unwrap();
+        fs::write(dir.join("sub").join("a b.js"), b"app").unwrap();
+        dir
+    }
+
+    #[crate::rt_test]
+    async fn test_files() {
+        let dir = files_dir("files");
+        let srv = init_service(
+            App::new().service(
+                Files::new("/static", &dir)
+                    .index_file("index.html")
+                    .chunk_size(4),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/static/data.txt").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/plain; charset=utf-8"
+        );
+        assert_eq!(res.headers().get(header::ACCEPT_RANGES).unwrap(), "bytes");
+        let etag = res.headers().get(header::ETAG).unwrap().clone();
+        let lm = res.headers().get(header::LAST_MODIFIED).unwrap().clone();
+        assert_eq!(read_body(res).await, Bytes::from_static(b"0123456789"));
+
+        let req = TestRequest::with_uri("/static/").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(read_body(res).await, Bytes::from_static(b"<html></html>"));
+
+        let req = TestRequest::with_uri("/static/sub/a%20b.js").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(read_body(res).await, Bytes::from_static(b"app"));
+
+        // conditional requests
+        let req = TestRequest::with_uri("/static/data.txt")
+            .header(header::IF_NONE_MATCH, etag.clone())
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+
+        let req = TestRequest::with_uri("/static/data.txt")
+            .header(header::IF_MODIFIED_SINCE, lm.clone())
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+
+        let req = TestRequest::with_uri("/static/data.txt")
+            .header(header::IF_MATCH, "\"other\"")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+
+        // ranges
+        let req = TestRequest::with_uri("/static/data.txt")
+            .header(header::RANGE, "bytes=2-6")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            res.headers().get(header::CONTENT_RANGE).unwrap(),
+            "bytes 2-6/10"
+        );
+        assert_eq!(read_body(res).await, Bytes::from_static(b"23456"));
+
+        let req = TestRequest::with_uri("/static/data.txt")
+            .header(header::RANGE, "bytes=0-1,8-")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        let ct = res.headers().get(header::CONTENT_TYPE).unwrap();
+        assert!(ct
+            .to_str()
+            .unwrap()
+            .starts_with("multipart/byteranges; boundary="));
+        let body = read_body(res).await;
+        assert!(body.ends_with(b"--\r\n"));
+
+        let req = TestRequest::with_uri("/static/data.txt")
+            .header(header::RANGE, "bytes=2-6")
+            .header(header::IF_RANGE, "\"other\"")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/static/data.txt")
+            .header(header::RANGE, "bytes=20-")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(
+            res.headers().get(header::CONTENT_RANGE).unwrap(),
+            "bytes */10"
+        );
+
+        // path traversal and missing files
+        let req =
+            TestRequest::with_uri("/static/sub/%2e%2e/%2e%2e/etc/passwd").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let req = TestRequest::with_uri("/static/missing.txt").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let req = TestRequest::with_uri("/static/sub/").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let req = TestRequest::with_uri("/static/data.txt")
+            .method(Method::POST)
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[crate::rt_test]
+    async fn test_files_default_service() {
+        let dir = files_dir("files-default");
+        let srv = init_service(
+            App::new().service(
+                web::scope("/docs")
+                    .route("/search", web::get().to(|| async { "search" }))
+                    .default_service(Files::new("", &dir).index_file("index.html")),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/docs/search").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, Bytes::from_static(b"search"));
+
+        let req = TestRequest::with_uri("/docs/sub/a%20b.js").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(read_body(res).await, Bytes::from_static(b"app"));
+
+        let req = TestRequest::with_uri("/docs/").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, Bytes::from_static(b"<html></html>"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[crate::rt_test]
+    async fn test_files_listing() {
+        let dir = files_dir("listing");
+        let srv = init_service(
+            App::new().service(Files::new("/static", &dir).show_files_listing()),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/static/sub?a=1").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(
+            res.headers().get(header::LOCATION).unwrap(),
+            "/static/sub/?a=1"
+        );
+
+        let req = TestRequest::with_uri("/static/sub/").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            read_body(res).await,
+            Bytes::from_static(
+                b"<html><head><title>Index of /static/sub</title></head>\
+                  <body><h1>Index of /static/sub</h1><ul>\
+                  <li><a href=\"/static/sub/a%20b.js\">a b.js</a></li>\
+                  </ul></body></html>"
+            )
+        );
+
+        // request path is escaped
+        fs::create_dir(dir.join("q\"x")).unwrap();
+        fs::write(dir.join("q\"x").join("f"), b"").unwrap();
+        let req = TestRequest::with_uri("/static/q\"x/").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = String::from_utf8(read_body(res).await.to_vec()).unwrap();
+        assert!(body.contains("<a href=\"/static/q&quot;x/f\">"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[crate::rt_test]
+    async fn test_files_precompressed() {
+        let dir = files_dir("precompressed");
+        fs::write(dir.join("data.txt.gz"), b"data-gz").unwrap();
+        let srv = init_service(
+            App::new()
+                .service(
+                    Files::new("/static", &dir)
+                        .use_precompressed()
+                        .charset(Some("iso-8859-1")),
+                )
+                .service(
+                    Files::new("/app", &dir)
+                        .fallback_file("index.html")
+                        .charset(None)
+                        .path_rewrite(|path| {
+                            if path.starts_with("/private") {
+                                None
+                            } else {
+                                Some(path.replace("/v1/", "/"))
+                            }
+                        }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/static/data.txt")
+            .header(header::ACCEPT_ENCODING, "gzip, deflate")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get(header::CONTENT_ENCODING).unwrap(), "gzip");
+        assert_eq!(res.headers().get(header::VARY).unwrap(), "accept-encoding");
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/plain; charset=iso-8859-1"
+        );
+        let etag = res.headers().get(header::ETAG).unwrap().clone();
+        assert_eq!(read_body(res).await, Bytes::from_static(b"data-gz"));
+
+        let req = TestRequest::with_uri("/static/data.txt")
+            .header(header::ACCEPT_ENCODING, "br")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert!(res.headers().get(header::CONTENT_ENCODING).is_none());
+        assert_ne!(res.headers().get(header::ETAG).unwrap(), &etag);
+        assert_eq!(read_body(res).await, Bytes::from_static(b"0123456789"));
+
+        // fallback file
+        let req = TestRequest::with_uri("/app/users/1").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/html"
+        );
+        assert_eq!(read_body(res).await, Bytes::from_static(b"<html></html>"));
+
+        // path rewrite
+        let req = TestRequest::with_uri("/app/v1/data.txt").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, Bytes::from_static(b"0123456789"));
+
+        let req = TestRequest::with_uri("/app/private/data.txt").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[crate::rt_test]
+    async fn test_files_languages() {
+        let dir = files_dir("languages");
+        fs::write(dir.join("index.html.fr"), b"<html>fr</html>").unwrap();
+        fs::write(dir.join("index.html.de-at"), b"<html>de-at</html>").unwrap();
+        fs::write(dir.join("index.html.fr.gz"), b"fr-gz").unwrap();
+        let srv = init_service(
+            App::new()
+                .service(Files::new("/static", &dir).use_languages())
+                .service(Files::new("/gz", &dir).use_languages().use_precompressed()),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/static/index.html")
+            .header(header::ACCEPT_LANGUAGE, "fr-CA, en;q=0.8")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get(header::CONTENT_LANGUAGE).unwrap(), "fr");
+        assert_eq!(res.headers().get(header::VARY).unwrap(), "accept-language");
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/html; charset=utf-8"
+        );
+        let etag = res.headers().get(header::ETAG).unwrap().clone();
+        assert_eq!(read_body(res).await, Bytes::from_static(b"<html>fr</html>"));
+
+        let req = TestRequest::with_uri("/static/index.html")
+            .header(header::ACCEPT_LANGUAGE, "fr;q=0.5, de-AT")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(
+            res.headers().get(header::CONTENT_LANGUAGE).unwrap(),
+            "de-at"
+        );
+        assert_ne!(res.headers().get(header::ETAG).unwrap(), &etag);
+        assert_eq!(
+            read_body(res).await,
+            Bytes::from_static(b"<html>de-at</html>")
+        );
+
+        // no acceptable variant
+        let req = TestRequest::with_uri("/static/index.html")
+            .header(header::ACCEPT_LANGUAGE, "en, fr;q=0")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert!(res.headers().get(header::CONTENT_LANGUAGE).is_none());
+        assert_eq!(read_body(res).await, Bytes::from_static(b"<html></html>"));
+
+        // precompressed language variant
+        let req = TestRequest::with_uri("/gz/index.html")
+            .header(header::ACCEPT_LANGUAGE, "fr")
+            .header(header::ACCEPT_ENCODING, "gzip")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.headers().get(header::CONTENT_LANGUAGE).unwrap(), "fr");
+        assert_eq!(res.headers().get(header::CONTENT_ENCODING).unwrap(), "gzip");
+        assert_eq!(
+            res.headers().get(header::VARY).unwrap(),
+            "accept-encoding, accept-language"
+        );
+        assert_eq!(read_body(res).await, Bytes::from_static(b"fr-gz"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_content_type() {
+        assert_eq!(
+            content_type("index.HTML", Some("utf-8")),
+            "text/html; charset=utf-8"
+        );
+        assert_eq!(content_type("app.js", None), "text/javascript");
+        assert_eq!(content_type("data.json", Some("utf-8")), "application/json");
+        assert_eq!(
+            content_type("file", Some("utf-8")),
+            "application/octet-stream"
+        );
+    }
+
+    #[test]
+    fn test_accepts() {
+        assert!(accepts("gzip, br", "br"));
+        assert!(accepts("gzip;q=0.5, BR;q=0.1", "br"));
+        assert!(!accepts("gzip, br;q=0", "br"));
+        assert!(!accepts("gzip", "br"));
+        assert!(!accepts("", "gzip"));
+    }
+
+    #[test]
+    fn test_accept_languages() {
+        assert_eq!(
+            accept_languages("fr-CA, en;q=0.8, fr;q=0.5"),
+            vec!["fr-ca", "fr", "en"]
+        );
+        assert_eq!(accept_languages("en;q=0.1, de;q=0.9"), vec!["de", "en"]);
+        assert_eq!(accept_languages("*, en;q=0, ../x, ru"), vec!["ru"]);
+        assert!(accept_languages("").is_empty());
+    }
+}
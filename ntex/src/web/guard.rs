@@ -11,6 +11,8 @@
 //! Guards can not modify the request object. But it is possible
 //! to store extra attributes on a request by using the `Extensions` container.
 //! Extensions containers are available via the `RequestHead::extensions()` method.
+//! Guards could also access application state with [`GuardCtx`], see
+//! [`fn_guard_ctx`]. Route could use async guards, see [`fn_async`].
 //!
 //! ```rust
 //! use ntex::http::Method;
@@ -27,9 +29,12 @@
 //! ```
 #![allow(non_snake_case)]
 
-use std::fmt;
+use std::{cell::Ref, fmt, future::Future};
 
 use crate::http::{header, Method, RequestHead, Uri};
+use crate::util::{BoxFuture, Extensions};
+
+use super::{httprequest::HttpRequest, service::AppState, types::Flags};
 
 /// Trait defines resource guards. Guards are used for route selection.
 ///
@@ -40,6 +45,15 @@ pub trait Guard {
     /// Check if request matches predicate
     fn check(&self, request: &RequestHead) -> bool;
 
+    /// Check if request matches predicate, guard context provides
+    /// access to application state.
+    ///
+    /// Router uses this method for route selection, by default
+    /// it calls `Guard::check()`.
+    fn check_ctx(&self, ctx: &GuardCtx<'_>) -> bool {
+        self.check(ctx.head())
+    }
+
     /// Debug format
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_tuple("Guard").finish()
@@ -96,6 +110,183 @@ where
     }
 }
 
+/// Guard context
+///
+/// Provides access to request head, request extensions and
+/// application state during route resolution.
+pub struct GuardCtx<'a> {
+    head: &'a RequestHead,
+    state: Option<&'a AppState>,
+}
+
+impl<'a> GuardCtx<'a> {
+    pub(super) fn new(head: &'a RequestHead, state: &'a AppState) -> Self {
+        GuardCtx {
+            head,
+            state: Some(state),
+        }
+    }
+
+    /// Request head
+    pub fn head(&self) -> &'a RequestHead {
+        self.head
+    }
+
+    /// Request extensions
+    pub fn extensions(&self) -> Ref<'a, Extensions> {
+        self.head.extensions()
+    }
+
+    /// Get application state stored with `App::state()` or
+    /// `Scope::state()` methods.
+    pub fn app_state<T: 'static>(&self) -> Option<&'a T> {
+        self.state.and_then(|state| state.get::<T>())
+    }
+}
+
+impl<'a> From<&'a RequestHead> for GuardCtx<'a> {
+    /// Create guard context without application state
+    fn from(head: &'a RequestHead) -> Self {
+        GuardCtx { head, state: None }
+    }
+}
+
+impl<'a> fmt::Debug for GuardCtx<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GuardCtx")
+            .field("head", &self.head)
+            .finish()
+    }
+}
+
+/// Create guard object for supplied function, function has access
+/// to the guard context.
+///
+/// Guard always fails if it is checked without context.
+///
+/// ```rust
+/// use ntex::web::{self, guard, App, HttpResponse};
+///
+/// struct Features {
+///     beta: bool,
+/// }
+///
+/// fn main() {
+///     App::new()
+///         .state(Features { beta: true })
+///         .service(web::resource("/beta").route(
+///             web::get()
+///                 .guard(guard::fn_guard_ctx(|ctx| {
+///                     ctx.app_state::<Features>().map(|f| f.beta).unwrap_or(false)
+///                 }))
+///                 .to(|| async { HttpResponse::Ok() }))
+///         );
+/// }
+/// ```
+pub fn fn_guard_ctx<F>(f: F) -> impl Guard
+where
+    F: Fn(&GuardCtx<'_>) -> bool,
+{
+    FnCtxGuard(f)
+}
+
+struct FnCtxGuard<F: Fn(&GuardCtx<'_>) -> bool>(F);
+
+impl<F> Guard for FnCtxGuard<F>
+where
+    F: Fn(&GuardCtx<'_>) -> bool,
+{
+    fn check(&self, _: &RequestHead) -> bool {
+        false
+    }
+
+    fn check_ctx(&self, ctx: &GuardCtx<'_>) -> bool {
+        (self.0)(ctx)
+    }
+
+    /// Debug format
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("FnCtxGuard")
+            .field(&std::any::type_name::<F>())
+            .finish()
+    }
+}
+
+/// Trait defines async route guards.
+///
+/// Async guards are checked after all sync guards of the route have
+/// matched. Guards are checked one by one in order of registration,
+/// first failed guard stops the evaluation and router tries next route.
+pub trait AsyncGuard {
+    /// Check if request matches predicate
+    fn check(&self, req: HttpRequest) -> BoxFuture<'static, bool>;
+
+    /// Debug format
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AsyncGuard").finish()
+    }
+}
+
+/// Create async guard object for supplied function.
+///
+/// Async guard could be added to a route with `Route::async_guard()` method.
+/// Guard must not keep request object after future is completed.
+///
+/// ```rust
+/// use ntex::web::{self, guard, App, HttpRequest, HttpResponse};
+///
+/// async fn introspect(token: Option<String>) -> bool {
+///     token.as_deref() == Some("secret")
+/// }
+///
+/// fn main() {
+///     App::new().service(web::resource("/index.html").route(
+///         web::get()
+///             .async_guard(guard::fn_async(|req: HttpRequest| async move {
+///                 let token = req
+///                     .headers()
+///                     .get("x-token")
+///                     .and_then(|v| v.to_str().ok())
+///                     .map(|v| v.to_string());
+///                 introspect(token).await
+///             }))
+///             .to(|| async { HttpResponse::Ok() }))
+///     );
+/// }
+/// ```
+pub fn fn_async<F, R>(f: F) -> impl AsyncGuard
+where
+    F: Fn(HttpRequest) -> R,
+    R: Future<Output = bool> + 'static,
+{
+    FnAsyncGuard(f)
+}
+
+struct FnAsyncGuard<F>(F);
+
+impl<F, R> AsyncGuard for FnAsyncGuard<F>
+where
+    F: Fn(HttpRequest) -> R,
+    R: Future<Output = bool> + 'static,
+{
+    fn check(&self, req: HttpRequest) -> BoxFuture<'static, bool> {
+        Box::pin((self.0)(req))
+    }
+
+    /// Debug format
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("FnAsyncGuard")
+            .field(&std::any::type_name::<F>())
+            .finish()
+    }
+}
+
+impl fmt::Debug for dyn AsyncGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        AsyncGuard::fmt(self, f)
+    }
+}
+
 impl<F> Guard for F
 where
     F: Fn(&RequestHead) -> bool,
@@ -144,6 +335,15 @@ impl Guard for AnyGuard {
         false
     }
 
+    fn check_ctx(&self, ctx: &GuardCtx<'_>) -> bool {
+        for p in &self.0 {
+            if p.check_ctx(ctx) {
+                return true;
+            }
+        }
+        false
+    }
+
     /// Debug format
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "AnyGuard(")?;
@@ -205,6 +405,15 @@ impl Guard for AllGuard {
         true
     }
 
+    fn check_ctx(&self, ctx: &GuardCtx<'_>) -> bool {
+        for p in &self.0 {
+            if !p.check_ctx(ctx) {
+                return false;
+            }
+        }
+        true
+    }
+
     /// Debug format
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "AllGuard(")?;
@@ -234,6 +443,10 @@ impl Guard for NotGuard {
         !self.0.check(request)
     }
 
+    fn check_ctx(&self, ctx: &GuardCtx<'_>) -> bool {
+        !self.0.check_ctx(ctx)
+    }
+
     /// Debug format
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "NotGuard(")?;
@@ -343,22 +556,108 @@ impl Guard for HeaderGuard {
 
 /// Return predicate that matches if request contains specified Host name.
 ///
+/// Host labels could be `*` or `{name}` wildcards, each wildcard matches
+/// exactly one label. Host names are compared case-insensitively.
+///
 /// ```rust
 /// use ntex::web::{self, guard::Host, App, HttpResponse};
 ///
 /// fn main() {
-///     App::new().service(
-///         web::resource("/index.html")
-///             .guard(Host("www.rust-lang.org"))
-///             .to(|| async { HttpResponse::MethodNotAllowed() })
-///     );
+///     App::new()
+///         .service(
+///             web::resource("/index.html")
+///                 .guard(Host("www.rust-lang.org"))
+///                 .to(|| async { HttpResponse::MethodNotAllowed() })
+///         )
+///         .service(
+///             web::resource("/index.html")
+///                 .guard(Host("*.rust-lang.org"))
+///                 .to(|| async { HttpResponse::Ok() })
+///         );
 /// }
 /// ```
 pub fn Host<H: AsRef<str>>(host: H) -> HostGuard {
-    HostGuard(host.as_ref().to_string(), None)
+    HostGuard(HostPattern::new(host.as_ref()), None)
+}
+
+/// Host name pattern
+#[derive(Clone, Debug)]
+pub(super) struct HostPattern(Vec<HostLabel>);
+
+#[derive(Clone, Debug)]
+enum HostLabel {
+    Static(String),
+    Param(&'static str),
+    Wildcard,
+}
+
+impl HostPattern {
+    pub(super) fn new(pattern: &str) -> Self {
+        HostPattern(
+            pattern
+                .split('.')
+                .map(|label| {
+                    if label == "*" {
+                        HostLabel::Wildcard
+                    } else if let Some(name) =
+                        label.strip_prefix('{').and_then(|l| l.strip_suffix('}'))
+                    {
+                        HostLabel::Param(Box::leak(Box::new(name.to_owned())).as_str())
+                    } else {
+                        HostLabel::Static(label.to_string())
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    /// Lower-cased host name, if pattern does not contain wildcards
+    pub(super) fn static_host(&self) -> Option<String> {
+        let mut host = String::new();
+        for item in &self.0 {
+            if let HostLabel::Static(s) = item {
+                if !host.is_empty() {
+                    host.push('.');
+                }
+                host.push_str(&s.to_ascii_lowercase());
+            } else {
+                return None;
+            }
+        }
+        Some(host)
+    }
+
+    /// Match host name, returns values of named labels
+    pub(super) fn match_host<'a>(
+        &self,
+        host: &'a str,
+    ) -> Option<Vec<(&'static str, &'a str)>> {
+        let host = host.strip_suffix('.').unwrap_or(host);
+        let mut labels = host.split('.');
+        let mut params = Vec::new();
+
+        for item in &self.0 {
+            let label = labels.next().filter(|l| !l.is_empty())?;
+            match item {
+                HostLabel::Static(s) => {
+                    if !s.eq_ignore_ascii_case(label) {
+                        return None;
+                    }
+                }
+                HostLabel::Param(name) => params.push((*name, label)),
+                HostLabel::Wildcard => (),
+            }
+        }
+
+        if labels.next().is_some() {
+            None
+        } else {
+            Some(params)
+        }
+    }
 }
 
-fn get_host_uri(req: &RequestHead) -> Option<Uri> {
+pub(super) fn get_host_uri(req: &RequestHead) -> Option<Uri> {
     use core::str::FromStr;
     req.headers
         .get(header::HOST)
@@ -370,7 +669,7 @@ fn get_host_uri(req: &RequestHead) -> Option<Uri> {
 
 #[doc(hidden)]
 #[derive(Debug)]
-pub struct HostGuard(String, Option<String>);
+pub struct HostGuard(pub(super) HostPattern, pub(super) Option<String>);
 
 impl HostGuard {
     /// Set request scheme to match
@@ -389,7 +688,7 @@ impl Guard for HostGuard {
         };
 
         if let Some(uri_host) = req_host_uri.host() {
-            if self.0 != uri_host {
+            if self.0.match_host(uri_host).is_none() {
                 return false;
             }
         } else {
@@ -411,6 +710,46 @@ impl Guard for HostGuard {
     }
 }
 
+/// Return predicate that matches if feature flag is enabled.
+///
+/// Flags are populated by `middleware::FeatureFlags` middleware,
+/// guard fails if middleware is not configured.
+///
+/// ```rust
+/// use ntex::web::{self, guard, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     App::new()
+///         .wrap(middleware::FeatureFlags::new(middleware::Flags::from_env("FEATURE_")))
+///         .service(
+///             web::resource("/beta")
+///                 .guard(guard::Flag("beta"))
+///                 .to(|| async { HttpResponse::Ok() })
+///         );
+/// }
+/// ```
+pub fn Flag(name: &'static str) -> FlagGuard {
+    FlagGuard(name)
+}
+
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct FlagGuard(&'static str);
+
+impl Guard for FlagGuard {
+    fn check(&self, req: &RequestHead) -> bool {
+        req.extensions()
+            .get::<Flags>()
+            .map(|flags| flags.is_enabled(self.0))
+            .unwrap_or(false)
+    }
+
+    /// Debug format
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -461,6 +800,32 @@ mod tests {
         assert!(!pred.check(req.head()));
     }
 
+    #[test]
+    fn test_host_pattern() {
+        let req = TestRequest::default()
+            .header(
+                header::HOST,
+                header::HeaderValue::from_static("Blog.Rust-Lang.org:8080"),
+            )
+            .to_http_request();
+
+        assert!(Host("blog.rust-lang.org").check(req.head()));
+        assert!(Host("*.rust-lang.org").check(req.head()));
+        assert!(Host("{sub}.rust-lang.org").check(req.head()));
+        assert!(Host("blog.*.org").check(req.head()));
+        assert!(!Host("*.org").check(req.head()));
+        assert!(!Host("*.blog.rust-lang.org").check(req.head()));
+        assert!(!Host("*.crates.io").check(req.head()));
+
+        let pattern = HostPattern::new("{tenant}.{region}.example.com");
+        assert_eq!(
+            pattern.match_host("acme.eu.example.com."),
+            Some(vec![("tenant", "acme"), ("region", "eu")])
+        );
+        assert_eq!(pattern.match_host("acme.example.com"), None);
+        assert_eq!(pattern.match_host(".eu.example.com"), None);
+    }
+
     #[test]
     fn test_host_scheme() {
         let req = TestRequest::default()
@@ -605,4 +970,31 @@ mod tests {
         let g = |req: &RequestHead| req.headers().contains_key("content-type");
         assert!(g.check(req.head()));
     }
+
+    #[test]
+    fn test_fn_guard_ctx() {
+        struct Tenants(Vec<&'static str>);
+
+        let req = TestRequest::with_header("x-tenant", "t1")
+            .state(Tenants(vec!["t1"]))
+            .to_srv_request();
+        let g = fn_guard_ctx(|ctx| {
+            let tenant = ctx.head().headers().get("x-tenant").unwrap();
+            ctx.app_state::<Tenants>()
+                .map(|t| t.0.iter().any(|t| tenant.to_str().ok() == Some(*t)))
+                .unwrap_or(false)
+        });
+        assert!(g.check_ctx(&req.guard_ctx()));
+        assert!(!g.check(req.head()));
+        assert!(!g.check_ctx(&GuardCtx::from(req.head())));
+        assert!(Not(Trace()).check_ctx(&req.guard_ctx()));
+        assert!(format!("{:?}", req.guard_ctx()).contains("GuardCtx"));
+
+        let req = TestRequest::with_header("x-tenant", "t2")
+            .state(Tenants(vec!["t1"]))
+            .to_srv_request();
+        assert!(!g.check_ctx(&req.guard_ctx()));
+        assert!(!Any(Post()).or(g).check_ctx(&req.guard_ctx()));
+        assert!(req.guard_ctx().extensions().get::<usize>().is_none());
+    }
 }
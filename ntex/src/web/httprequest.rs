@@ -10,7 +10,7 @@ use crate::util::Extensions;
 use super::config::AppConfig;
 use super::error::ErrorRenderer;
 use super::extract::FromRequest;
-use super::info::ConnectionInfo;
+use super::info::{ConnectionInfo, MatchInfo, RouteInfo};
 use super::rmap::ResourceMap;
 use super::service::AppState;
 
@@ -23,6 +23,7 @@ pub(crate) struct HttpRequestInner {
     pub(crate) path: Path<Uri>,
     pub(crate) payload: Payload,
     pub(crate) app_state: AppState,
+    pub(crate) routes: Vec<Rc<RouteInfo>>,
     rmap: Rc<ResourceMap>,
     pool: &'static HttpRequestPool,
 }
@@ -44,6 +45,7 @@ impl HttpRequest {
             app_state,
             rmap,
             pool,
+            routes: Vec::new(),
         }))
     }
 }
@@ -137,6 +139,25 @@ impl HttpRequest {
         &mut Rc::get_mut(&mut self.0).unwrap().path
     }
 
+    /// Pattern of matched route
+    ///
+    /// Returns full route pattern including scope prefixes, i.e.
+    /// `/api/users/{id}`. Returns `None` if request is not routed yet
+    /// or is handled by default service.
+    pub fn match_pattern(&self) -> Option<String> {
+        self.match_route().pattern()
+    }
+
+    /// Matched route metadata
+    pub fn match_route(&self) -> MatchInfo {
+        MatchInfo::new(&self.0.routes)
+    }
+
+    #[inline]
+    pub(crate) fn add_route(&mut self, route: Rc<RouteInfo>) {
+        Rc::get_mut(&mut self.0).unwrap().routes.push(route)
+    }
+
     /// Request extensions
     #[inline]
     pub fn extensions(&self) -> Ref<'_, Extensions> {
@@ -180,6 +201,41 @@ impl HttpRequest {
         self.0.rmap.url_for(self, name, elements)
     }
 
+    #[cfg(feature = "url")]
+    /// Generate url for named resource from named parameters
+    ///
+    /// This method is similar to `HttpRequest::url_for()` but pattern
+    /// variables, including variables of parent scopes, are filled by name.
+    ///
+    /// ```rust
+    /// # use std::collections::HashMap;
+    /// # use ntex::web::{self, App, HttpRequest, HttpResponse};
+    /// #
+    /// async fn index(req: HttpRequest) -> HttpResponse {
+    ///     let params = HashMap::from([("user", "john"), ("id", "1")]);
+    ///     let url = req.url_for_map("item", &params).unwrap();
+    ///     HttpResponse::Found().header("location", url.as_str()).finish()
+    /// }
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .service(web::resource("/{user}/items/{id}").name("item"))
+    ///         .route("/", web::get().to(index));
+    /// }
+    /// ```
+    pub fn url_for_map<K, V, S>(
+        &self,
+        name: &str,
+        elements: &std::collections::HashMap<K, V, S>,
+    ) -> Result<url_pkg::Url, super::error::UrlGenerationError>
+    where
+        K: std::borrow::Borrow<str> + Eq + std::hash::Hash,
+        V: AsRef<str>,
+        S: std::hash::BuildHasher,
+    {
+        self.0.rmap.url_for_map(self, name, elements)
+    }
+
     #[cfg(feature = "url")]
     /// Generate url for named resource
     ///
@@ -421,6 +477,36 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "url")]
+    #[test]
+    fn test_url_for_map() {
+        let mut res = ResourceDef::new("/user/{name}.{ext}");
+        *res.name_mut() = "index".to_string();
+
+        let mut rmap = ResourceMap::new(ResourceDef::new(""));
+        rmap.add(&mut res, None);
+
+        let req = TestRequest::with_header(header::HOST, "www.rust-lang.org")
+            .rmap(rmap)
+            .to_http_request();
+
+        let params = std::collections::HashMap::from([("name", "test")]);
+        assert_eq!(
+            req.url_for_map("unknown", &params),
+            Err(crate::web::error::UrlGenerationError::ResourceNotFound)
+        );
+        assert_eq!(
+            req.url_for_map("index", &params),
+            Err(crate::web::error::UrlGenerationError::NotEnoughElements)
+        );
+        let params = std::collections::HashMap::from([("ext", "html"), ("name", "test")]);
+        let url = req.url_for_map("index", &params);
+        assert_eq!(
+            url.ok().unwrap().as_str(),
+            "http://www.rust-lang.org/user/test.html"
+        );
+    }
+
     #[cfg(feature = "url")]
     #[test]
     fn test_url_for_static() {
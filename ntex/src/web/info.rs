@@ -1,8 +1,10 @@
-use std::cell::Ref;
+use std::{cell::Ref, rc::Rc};
 
 use crate::http::header::{self, HeaderName};
-use crate::http::RequestHead;
+use crate::http::{Payload, RequestHead};
+use crate::router::ResourceDef;
 use crate::web::config::AppConfig;
+use crate::web::{ErrorRenderer, FromRequest, HttpRequest};
 
 const X_FORWARDED_FOR: &[u8] = b"x-forwarded-for";
 const X_FORWARDED_HOST: &[u8] = b"x-forwarded-host";
@@ -181,6 +183,104 @@ impl ConnectionInfo {
     }
 }
 
+/// Matched routing entry
+#[derive(Debug)]
+pub(crate) struct RouteInfo {
+    pattern: String,
+    name: String,
+    scope: bool,
+}
+
+impl RouteInfo {
+    pub(crate) fn new(rdef: &ResourceDef, scope: bool) -> Rc<Self> {
+        Rc::new(RouteInfo {
+            scope,
+            pattern: rdef.pattern().to_string(),
+            name: rdef.name().to_string(),
+        })
+    }
+}
+
+/// Matched route metadata
+///
+/// Contains route pattern, route name and patterns of matched scopes.
+/// Route pattern could be used for labeling metrics instead of request
+/// path. `MatchInfo` could be used as request extractor, in middlewares
+/// it is available via `HttpRequest::match_pattern()` after request
+/// is handled.
+///
+/// ```rust
+/// use ntex::web::{self, App, MatchInfo};
+///
+/// async fn index(info: MatchInfo) -> String {
+///     // "/api/users/{id}"
+///     info.pattern().unwrap_or_default()
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::scope("/api").route("/users/{id}", web::get().to(index)),
+///     );
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MatchInfo {
+    routes: Vec<Rc<RouteInfo>>,
+}
+
+impl MatchInfo {
+    pub(crate) fn new(routes: &[Rc<RouteInfo>]) -> Self {
+        MatchInfo {
+            routes: routes.to_vec(),
+        }
+    }
+
+    /// Full pattern of matched route
+    ///
+    /// Returns `None` if request is handled by default service.
+    pub fn pattern(&self) -> Option<String> {
+        match self.routes.last() {
+            Some(route) if !route.scope => {
+                let mut pattern = String::new();
+                for route in &self.routes {
+                    if pattern.ends_with('/') && route.pattern.starts_with('/') {
+                        pattern.push_str(&route.pattern[1..]);
+                    } else {
+                        pattern.push_str(&route.pattern);
+                    }
+                }
+                Some(pattern)
+            }
+            _ => None,
+        }
+    }
+
+    /// Name of matched route
+    pub fn name(&self) -> Option<&str> {
+        match self.routes.last() {
+            Some(route) if !route.scope && !route.name.is_empty() => Some(&route.name),
+            _ => None,
+        }
+    }
+
+    /// Patterns of matched scopes, outermost first
+    pub fn scopes(&self) -> impl Iterator<Item = &str> {
+        self.routes
+            .iter()
+            .filter(|route| route.scope)
+            .map(|route| route.pattern.as_str())
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for MatchInfo {
+    type Error = Err::Container;
+
+    #[inline]
+    async fn from_request(req: &HttpRequest, _: &mut Payload) -> Result<Self, Self::Error> {
+        Ok(req.match_route())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -233,4 +333,56 @@ mod tests {
         let info = req.connection_info();
         assert_eq!(info.scheme(), "https");
     }
+
+    #[crate::rt_test]
+    async fn test_match_info() {
+        use crate::util::Bytes;
+        use crate::web::test::{call_service, init_service, read_body};
+        use crate::web::{self, App};
+
+        async fn pattern(info: MatchInfo) -> String {
+            format!(
+                "{}|{}|{}",
+                info.pattern().unwrap_or_default(),
+                info.name().unwrap_or_default(),
+                info.scopes().collect::<Vec<_>>().join(",")
+            )
+        }
+
+        let srv = init_service(
+            App::new()
+                .service(
+                    web::scope("/api").service(
+                        web::scope("/v1").service(
+                            web::resource("/users/{id}")
+                                .name("user")
+                                .route(web::get().to(pattern)),
+                        ),
+                    ),
+                )
+                .route("/index.html", web::get().to(pattern)),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/api/v1/users/10").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(
+            res.request().match_pattern().as_deref(),
+            Some("/api/v1/users/{id}")
+        );
+        assert_eq!(
+            read_body(res).await,
+            Bytes::from_static(b"/api/v1/users/{id}|user|/api,/v1")
+        );
+
+        let req = TestRequest::with_uri("/index.html").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, Bytes::from_static(b"/index.html||"));
+
+        // default service
+        let req = TestRequest::with_uri("/api/unknown").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.request().match_pattern(), None);
+        assert_eq!(res.request().match_route().scopes().count(), 1);
+    }
 }
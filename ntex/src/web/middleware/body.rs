@@ -0,0 +1,306 @@
+//! Middleware for response body transformation
+use std::{error::Error, fmt, rc::Rc, task::Context, task::Poll};
+
+use crate::http::body::{Body, BodySize, MessageBody, ResponseBody};
+use crate::http::header::{CONTENT_ENCODING, CONTENT_LENGTH, ETAG};
+use crate::http::ResponseHead;
+use crate::service::{Middleware, Service, ServiceCtx};
+use crate::util::Bytes;
+use crate::web::{HttpRequest, WebRequest, WebResponse};
+
+/// Response body transformer
+///
+/// Transformer is created for each response and receives body chunks
+/// as they are produced by the response stream.
+pub trait BodyTransform: 'static {
+    /// Transform body chunk
+    ///
+    /// Empty result is skipped, so transformer could buffer data
+    /// and return it later.
+    fn chunk(&mut self, chunk: Bytes) -> Bytes;
+
+    /// Response body is complete, returned data is appended to the body
+    fn finish(&mut self) -> Option<Bytes> {
+        None
+    }
+}
+
+impl<F> BodyTransform for F
+where
+    F: FnMut(Bytes) -> Bytes + 'static,
+{
+    fn chunk(&mut self, chunk: Bytes) -> Bytes {
+        (self)(chunk)
+    }
+}
+
+/// Create middleware for streaming response body transformation.
+///
+/// Function is called for each response, if it returns transformer
+/// response body chunks are passed through it. `Content-Length`
+/// header is removed and body is sent as a stream, so transfer encoding
+/// is selected by the http layer. `ETag` header does not match transformed
+/// body and is removed as well. Responses without body and responses
+/// with `Content-Encoding` header are not transformed.
+///
+/// ```rust
+/// use ntex::util::{Bytes, BytesMut};
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// /// Inject script before closing `</body>` tag
+/// #[derive(Default)]
+/// struct Inject(BytesMut);
+///
+/// impl middleware::BodyTransform for Inject {
+///     fn chunk(&mut self, chunk: Bytes) -> Bytes {
+///         // buffer html document
+///         self.0.extend_from_slice(&chunk);
+///         Bytes::new()
+///     }
+///
+///     fn finish(&mut self) -> Option<Bytes> {
+///         let html = String::from_utf8_lossy(&self.0);
+///         Some(Bytes::from(html.replace("</body>", "<script>app()</script></body>")))
+///     }
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::wrap_body_fn(|_, head| {
+///             let html = head
+///                 .headers
+///                 .get("content-type")
+///                 .map(|ct| ct.as_bytes().starts_with(b"text/html"))
+///                 .unwrap_or(false);
+///             if html { Some(Inject::default()) } else { None }
+///         }))
+///         .route("/", web::get().to(|| async {
+///             HttpResponse::Ok()
+///                 .content_type("text/html")
+///                 .body("<html><body></body></html>")
+///         }));
+/// }
+/// ```
+pub fn wrap_body_fn<F, T>(f: F) -> BodyFn<F>
+where
+    F: Fn(&HttpRequest, &ResponseHead) -> Option<T> + 'static,
+    T: BodyTransform,
+{
+    BodyFn { f: Rc::new(f) }
+}
+
+/// `Middleware` for response body transformation, see [`wrap_body_fn`]
+pub struct BodyFn<F> {
+    f: Rc<F>,
+}
+
+impl<F> Clone for BodyFn<F> {
+    fn clone(&self) -> Self {
+        BodyFn { f: self.f.clone() }
+    }
+}
+
+impl<F> fmt::Debug for BodyFn<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BodyFn").finish()
+    }
+}
+
+impl<S, F> Middleware<S> for BodyFn<F> {
+    type Service = BodyFnMiddleware<S, F>;
+
+    fn create(&self, service: S) -> Self::Service {
+        BodyFnMiddleware {
+            service,
+            f: self.f.clone(),
+        }
+    }
+}
+
+pub struct BodyFnMiddleware<S, F> {
+    service: S,
+    f: Rc<F>,
+}
+
+impl<S, F> fmt::Debug for BodyFnMiddleware<S, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BodyFnMiddleware").finish()
+    }
+}
+
+impl<S, F, T, E> Service<WebRequest<E>> for BodyFnMiddleware<S, F>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+    F: Fn(&HttpRequest, &ResponseHead) -> Option<T> + 'static,
+    T: BodyTransform,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    crate::forward_poll_ready!(service);
+    crate::forward_poll_shutdown!(service);
+
+    async fn call(
+        &self,
+        req: WebRequest<E>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        let res = ctx.call(&self.service, req).await?;
+
+        if res.response().body().size() == BodySize::None
+            || res.headers().contains_key(CONTENT_ENCODING)
+        {
+            return Ok(res);
+        }
+
+        if let Some(transform) = (self.f)(res.request(), res.response().head()) {
+            Ok(res.map_body(move |head, body| {
+                head.headers.remove(CONTENT_LENGTH);
+                head.headers.remove(ETAG);
+                ResponseBody::Other(Body::from_message(TransformBody {
+                    body,
+                    transform,
+                    eof: false,
+                }))
+            }))
+        } else {
+            Ok(res)
+        }
+    }
+}
+
+struct TransformBody<T> {
+    body: ResponseBody<Body>,
+    transform: T,
+    eof: bool,
+}
+
+impl<T: BodyTransform> MessageBody for TransformBody<T> {
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        loop {
+            if self.eof {
+                return Poll::Ready(None);
+            }
+
+            return match self.body.poll_next_chunk(cx) {
+                Poll::Ready(Some(Ok(chunk))) => {
+                    let chunk = self.transform.chunk(chunk);
+                    if chunk.is_empty() {
+                        continue;
+                    }
+                    Poll::Ready(Some(Ok(chunk)))
+                }
+                Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => {
+                    self.eof = true;
+                    match self.transform.finish() {
+                        Some(tail) if !tail.is_empty() => Poll::Ready(Some(Ok(tail))),
+                        _ => Poll::Ready(None),
+                    }
+                }
+                Poll::Pending => Poll::Pending,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{header, StatusCode};
+    use crate::util::BytesMut;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, HttpResponse};
+
+    #[derive(Default)]
+    struct Upper(BytesMut);
+
+    impl BodyTransform for Upper {
+        fn chunk(&mut self, chunk: Bytes) -> Bytes {
+            self.0.extend_from_slice(&chunk);
+            Bytes::new()
+        }
+
+        fn finish(&mut self) -> Option<Bytes> {
+            Some(Bytes::from(self.0.to_ascii_uppercase()))
+        }
+    }
+
+    #[crate::rt_test]
+    async fn test_wrap_body_fn() {
+        let srv = init_service(
+            App::new()
+                .wrap(wrap_body_fn(|req, _| {
+                    if req.path() == "/upper" {
+                        Some(Upper::default())
+                    } else {
+                        None
+                    }
+                }))
+                .route(
+                    "/upper",
+                    web::get().to(|| async {
+                        HttpResponse::Ok()
+                            .header(header::CONTENT_LENGTH, "5")
+                            .header(header::ETAG, "\"hello\"")
+                            .body("hello")
+                    }),
+                )
+                .route("/plain", web::get().to(|| async { "hello" }))
+                .route(
+                    "/encoded",
+                    web::get().to(|| async {
+                        HttpResponse::Ok()
+                            .header(header::CONTENT_ENCODING, "identity")
+                            .body("hello")
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/upper").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.headers().get(header::CONTENT_LENGTH).is_none());
+        assert!(res.headers().get(header::ETAG).is_none());
+        assert_eq!(res.response().body().size(), BodySize::Stream);
+        assert_eq!(read_body(res).await, Bytes::from_static(b"HELLO"));
+
+        let req = TestRequest::with_uri("/plain").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, Bytes::from_static(b"hello"));
+
+        let req = TestRequest::with_uri("/encoded").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, Bytes::from_static(b"hello"));
+    }
+
+    #[crate::rt_test]
+    async fn test_closure_transform() {
+        let srv = init_service(
+            App::new()
+                .wrap(wrap_body_fn(|_, _| {
+                    Some(|chunk: Bytes| {
+                        if chunk.is_empty() {
+                            chunk
+                        } else {
+                            Bytes::from(format!("<{}>", String::from_utf8_lossy(&chunk)))
+                        }
+                    })
+                }))
+                .route("/", web::get().to(|| async { "hello" })),
+        )
+        .await;
+
+        let req = TestRequest::default().to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, Bytes::from_static(b"<hello>"));
+    }
+}
@@ -1,6 +1,7 @@
 //! `Middleware` for compressing response body.
 use std::{cmp, str::FromStr};
 
+use crate::http::body::{BodySize, MessageBody};
 use crate::http::encoding::Encoder;
 use crate::http::header::{ContentEncoding, ACCEPT_ENCODING};
 use crate::service::{Middleware, Service, ServiceCtx};
@@ -9,6 +10,10 @@ use crate::web::{BodyEncoding, ErrorRenderer, WebRequest, WebResponse};
 #[derive(Debug, Clone)]
 /// `Middleware` for compressing response body.
 ///
+/// Encoding is negotiated with `Accept-Encoding` request header, gzip, deflate,
+/// br and zstd encodings are supported. Response body is compressed as
+/// a stream. Responses with `Content-Encoding` header are not compressed.
+///
 /// Use `BodyEncoding` trait for overriding response compression.
 /// To disable compression set encoding to `ContentEncoding::Identity` value.
 ///
@@ -27,12 +32,26 @@ use crate::web::{BodyEncoding, ErrorRenderer, WebRequest, WebResponse};
 /// ```
 pub struct Compress {
     enc: ContentEncoding,
+    min_size: u64,
 }
 
 impl Compress {
     /// Create new `Compress` middleware with default encoding.
     pub fn new(encoding: ContentEncoding) -> Self {
-        Compress { enc: encoding }
+        Compress {
+            enc: encoding,
+            min_size: 0,
+        }
+    }
+
+    /// Set minimum size of response body for compression.
+    ///
+    /// Responses with known body size less than `size` are sent
+    /// uncompressed, streaming responses are always compressed.
+    /// By default all responses are compressed.
+    pub fn min_size(mut self, size: u64) -> Self {
+        self.min_size = size;
+        self
     }
 }
 
@@ -49,6 +68,7 @@ impl<S> Middleware<S> for Compress {
         CompressMiddleware {
             service,
             encoding: self.enc,
+            min_size: self.min_size,
         }
     }
 }
@@ -57,6 +77,7 @@ impl<S> Middleware<S> for Compress {
 pub struct CompressMiddleware<S> {
     service: S,
     encoding: ContentEncoding,
+    min_size: u64,
 }
 
 impl<S, E> Service<WebRequest<E>> for CompressMiddleware<S>
@@ -88,6 +109,12 @@ where
 
         let resp = ctx.call(&self.service, req).await?;
 
+        if let BodySize::Sized(size) = resp.response().body().size() {
+            if size < self.min_size {
+                return Ok(resp);
+            }
+        }
+
         let enc = if let Some(enc) = resp.response().get_encoding() {
             enc
         } else {
@@ -132,16 +159,23 @@ impl PartialEq for AcceptEncoding {
 
 impl AcceptEncoding {
     fn new(tag: &str) -> Option<AcceptEncoding> {
-        let parts: Vec<&str> = tag.split(';').collect();
-        let encoding = match parts.len() {
-            0 => return None,
-            _ => ContentEncoding::from(parts[0]),
-        };
-        let quality = match parts.len() {
-            1 => encoding.quality(),
-            _ => f64::from_str(parts[1]).unwrap_or(0.0),
+        let mut parts = tag.split(';');
+        let encoding = ContentEncoding::from(parts.next()?);
+        let quality = match parts.next() {
+            None => encoding.quality(),
+            Some(q) => q
+                .strip_prefix("q=")
+                .or_else(|| q.strip_prefix("Q="))
+                .and_then(|q| f64::from_str(q).ok())
+                .unwrap_or(0.0),
         };
-        Some(AcceptEncoding { encoding, quality })
+
+        // zero quality means "not acceptable"
+        if quality > 0.0 {
+            Some(AcceptEncoding { encoding, quality })
+        } else {
+            None
+        }
     }
 
     /// Parse a raw Accept-Encoding header value into an ordered list.
@@ -163,3 +197,92 @@ impl AcceptEncoding {
         ContentEncoding::Identity
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header::CONTENT_ENCODING;
+    use crate::web::test::{call_service, init_service, TestRequest};
+    use crate::web::{self, App, HttpResponse};
+
+    #[test]
+    fn test_accept_encoding() {
+        let auto = ContentEncoding::Auto;
+        assert_eq!(AcceptEncoding::parse("gzip", auto), ContentEncoding::Gzip);
+        assert_eq!(AcceptEncoding::parse("gzip, br", auto), ContentEncoding::Br);
+        assert_eq!(
+            AcceptEncoding::parse("br;q=0.5, gzip;q=0.8, zstd", auto),
+            ContentEncoding::Gzip
+        );
+        assert_eq!(
+            AcceptEncoding::parse("br;q=0, deflate", auto),
+            ContentEncoding::Deflate
+        );
+        assert_eq!(
+            AcceptEncoding::parse("gzip;q=0", ContentEncoding::Gzip),
+            ContentEncoding::Identity
+        );
+        assert_eq!(
+            AcceptEncoding::parse("gzip, br", ContentEncoding::Zstd),
+            ContentEncoding::Identity
+        );
+        assert_eq!(
+            AcceptEncoding::parse("deflate;q=0.1, zstd;q=0.2", ContentEncoding::Deflate),
+            ContentEncoding::Deflate
+        );
+    }
+
+    #[crate::rt_test]
+    async fn test_compress() {
+        let srv = init_service(
+            App::new()
+                .wrap(Compress::default().min_size(16))
+                .route("/", web::get().to(|| async { "0123456789abcdef" }))
+                .route("/small", web::get().to(|| async { "0123456789" }))
+                .route(
+                    "/opt-out",
+                    web::get().to(|| async {
+                        HttpResponse::Ok()
+                            .encoding(ContentEncoding::Identity)
+                            .body("0123456789abcdef")
+                    }),
+                )
+                .route(
+                    "/encoded",
+                    web::get().to(|| async {
+                        HttpResponse::Ok()
+                            .header(CONTENT_ENCODING, "br")
+                            .body("0123456789abcdef")
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/")
+            .header(ACCEPT_ENCODING, "br;q=0.5, gzip")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+        assert_eq!(res.response().body().size(), BodySize::Stream);
+
+        let req = TestRequest::with_uri("/").to_request();
+        let res = call_service(&srv, req).await;
+        assert!(res.headers().get(CONTENT_ENCODING).is_none());
+
+        for path in ["/small", "/opt-out"] {
+            let req = TestRequest::with_uri(path)
+                .header(ACCEPT_ENCODING, "gzip")
+                .to_request();
+            let res = call_service(&srv, req).await;
+            assert!(res.headers().get(CONTENT_ENCODING).is_none());
+            assert!(matches!(res.response().body().size(), BodySize::Sized(_)));
+        }
+
+        let req = TestRequest::with_uri("/encoded")
+            .header(ACCEPT_ENCODING, "gzip")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.headers().get(CONTENT_ENCODING).unwrap(), "br");
+        assert_eq!(res.response().body().size(), BodySize::Sized(16));
+    }
+}
@@ -0,0 +1,554 @@
+//! Middleware for cross-origin resource sharing (CORS)
+use std::{fmt, rc::Rc};
+
+use crate::http::error::HttpError;
+use crate::http::header::{self, HeaderName, HeaderValue};
+use crate::http::{Method, RequestHead, Response};
+use crate::service::{Middleware, Service, ServiceCtx};
+use crate::time::Seconds;
+use crate::web::{WebRequest, WebResponse};
+
+/// `Middleware` for cross-origin resource sharing (CORS).
+///
+/// Middleware answers preflight `OPTIONS` requests and adds CORS headers
+/// to responses for allowed origins. Preflight requests from not allowed
+/// origins, or with not allowed method or headers, get `403 Forbidden`
+/// response. Other requests are passed to the service, but responses
+/// do not get CORS headers, so browser blocks access to the response.
+/// Responses get `Vary: Origin` header, unless any origin is allowed without
+/// credentials.
+///
+/// By default no origins are allowed, allowed methods are `GET`, `HEAD`
+/// and `POST`.
+///
+/// ```rust
+/// use ntex::http::{header, Method};
+/// use ntex::time::Seconds;
+/// use ntex::web::{self, middleware::Cors, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(
+///             Cors::new()
+///                 .allowed_origin("https://www.rust-lang.org")
+///                 .allowed_origin("https://*.ntex.rs")
+///                 .allowed_methods([Method::GET, Method::POST])
+///                 .allowed_headers([header::AUTHORIZATION, header::CONTENT_TYPE])
+///                 .expose_headers(["x-request-id"])
+///                 .supports_credentials()
+///                 .max_age(Seconds(3600)),
+///         )
+///         .route("/", web::get().to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Cors {
+    inner: Rc<Inner>,
+}
+
+enum Origin {
+    Exact(String),
+    Wildcard(String, String),
+    Fn(Box<dyn Fn(&HeaderValue, &RequestHead) -> bool>),
+}
+
+struct Inner {
+    any_origin: bool,
+    origins: Vec<Origin>,
+    any_method: bool,
+    methods: Vec<Method>,
+    methods_hdr: HeaderValue,
+    any_header: bool,
+    headers: Vec<HeaderName>,
+    expose_hdr: Option<HeaderValue>,
+    credentials: bool,
+    max_age: Option<HeaderValue>,
+}
+
+impl Default for Cors {
+    fn default() -> Self {
+        let methods = vec![Method::GET, Method::HEAD, Method::POST];
+        Cors {
+            inner: Rc::new(Inner {
+                any_origin: false,
+                origins: Vec::new(),
+                any_method: false,
+                methods_hdr: join(methods.iter().map(|m| m.as_str())),
+                methods,
+                any_header: false,
+                headers: Vec::new(),
+                expose_hdr: None,
+                credentials: false,
+                max_age: None,
+            }),
+        }
+    }
+}
+
+impl Cors {
+    /// Construct `Cors` middleware.
+    pub fn new() -> Self {
+        Cors::default()
+    }
+
+    fn inner(&mut self) -> &mut Inner {
+        Rc::get_mut(&mut self.inner).expect("Multiple copies exist")
+    }
+
+    /// Add allowed origin.
+    ///
+    /// Origin could contain one `*` wildcard, i.e. `https://*.example.com`,
+    /// single `*` allows any origin.
+    pub fn allowed_origin(mut self, origin: &str) -> Self {
+        let inner = self.inner();
+        if origin == "*" {
+            inner.any_origin = true;
+        } else if let Some((prefix, suffix)) = origin.split_once('*') {
+            inner
+                .origins
+                .push(Origin::Wildcard(prefix.to_string(), suffix.to_string()));
+        } else {
+            inner.origins.push(Origin::Exact(origin.to_string()));
+        }
+        self
+    }
+
+    /// Add function that checks if origin is allowed.
+    pub fn allowed_origin_fn<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&HeaderValue, &RequestHead) -> bool + 'static,
+    {
+        self.inner().origins.push(Origin::Fn(Box::new(f)));
+        self
+    }
+
+    /// Allow any origin.
+    pub fn allow_any_origin(mut self) -> Self {
+        self.inner().any_origin = true;
+        self
+    }
+
+    /// Set allowed methods.
+    pub fn allowed_methods<I>(mut self, methods: I) -> Self
+    where
+        I: IntoIterator<Item = Method>,
+    {
+        let inner = self.inner();
+        inner.methods = methods.into_iter().collect();
+        inner.methods_hdr = join(inner.methods.iter().map(|m| m.as_str()));
+        self
+    }
+
+    /// Allow any method.
+    pub fn allow_any_method(mut self) -> Self {
+        self.inner().any_method = true;
+        self
+    }
+
+    /// Add allowed request headers.
+    pub fn allowed_headers<I, H>(mut self, headers: I) -> Self
+    where
+        I: IntoIterator<Item = H>,
+        HeaderName: TryFrom<H>,
+        <HeaderName as TryFrom<H>>::Error: Into<HttpError>,
+    {
+        let inner = self.inner();
+        for hdr in headers {
+            match HeaderName::try_from(hdr) {
+                Ok(hdr) => inner.headers.push(hdr),
+                Err(_) => panic!("Cannot create header name"),
+            }
+        }
+        self
+    }
+
+    /// Allow any request header.
+    pub fn allow_any_header(mut self) -> Self {
+        self.inner().any_header = true;
+        self
+    }
+
+    /// Set response headers exposed to the client.
+    pub fn expose_headers<I, H>(mut self, headers: I) -> Self
+    where
+        I: IntoIterator<Item = H>,
+        HeaderName: TryFrom<H>,
+        <HeaderName as TryFrom<H>>::Error: Into<HttpError>,
+    {
+        let headers: Vec<_> = headers
+            .into_iter()
+            .map(|hdr| match HeaderName::try_from(hdr) {
+                Ok(hdr) => hdr,
+                Err(_) => panic!("Cannot create header name"),
+            })
+            .collect();
+        self.inner().expose_hdr = if headers.is_empty() {
+            None
+        } else {
+            Some(join(headers.iter().map(|h| h.as_str())))
+        };
+        self
+    }
+
+    /// Allow requests with credentials.
+    ///
+    /// Request origin is used instead of `*` if any origin is allowed.
+    pub fn supports_credentials(mut self) -> Self {
+        self.inner().credentials = true;
+        self
+    }
+
+    /// Set period for caching preflight responses.
+    pub fn max_age(mut self, age: Seconds) -> Self {
+        self.inner().max_age = Some(HeaderValue::from(age.0));
+        self
+    }
+}
+
+fn join<'a, I: Iterator<Item = &'a str>>(items: I) -> HeaderValue {
+    HeaderValue::try_from(items.collect::<Vec<_>>().join(", ")).unwrap()
+}
+
+impl Inner {
+    fn is_origin_allowed(&self, origin: &HeaderValue, head: &RequestHead) -> bool {
+        if self.any_origin {
+            return true;
+        }
+        let s = origin.to_str().unwrap_or_default();
+        self.origins.iter().any(|item| match item {
+            Origin::Exact(val) => val == s,
+            Origin::Wildcard(prefix, suffix) => {
+                s.len() > prefix.len() + suffix.len()
+                    && s.starts_with(prefix.as_str())
+                    && s.ends_with(suffix.as_str())
+            }
+            Origin::Fn(f) => f(origin, head),
+        })
+    }
+
+    fn origin_value(&self, origin: &HeaderValue) -> HeaderValue {
+        if self.any_origin && !self.credentials {
+            HeaderValue::from_static("*")
+        } else {
+            origin.clone()
+        }
+    }
+
+    fn preflight(&self, head: &RequestHead, origin: &HeaderValue) -> Response {
+        let method = head
+            .headers
+            .get(&header::ACCESS_CONTROL_REQUEST_METHOD)
+            .and_then(|val| Method::from_bytes(val.as_bytes()).ok());
+        let method_allowed = match method {
+            Some(ref method) => self.any_method || self.methods.contains(method),
+            None => false,
+        };
+        let req_headers = head.headers.get(&header::ACCESS_CONTROL_REQUEST_HEADERS);
+        let headers_allowed = self.any_header
+            || req_headers
+                .and_then(|val| val.to_str().ok())
+                .map(|val| {
+                    val.split(',')
+                        .map(str::trim)
+                        .filter(|s| !s.is_empty())
+                        .all(|s| {
+                            HeaderName::try_from(s)
+                                .map(|name| self.headers.contains(&name))
+                                .unwrap_or(false)
+                        })
+                })
+                .unwrap_or(req_headers.is_none());
+
+        if !method_allowed || !headers_allowed || !self.is_origin_allowed(origin, head) {
+            let mut res = Response::Forbidden();
+            if self.vary_origin() {
+                res.header(header::VARY, "Origin");
+            }
+            return res.finish();
+        }
+
+        let mut res = Response::Ok();
+        res.header(
+            header::ACCESS_CONTROL_ALLOW_ORIGIN,
+            self.origin_value(origin),
+        );
+        if self.any_method {
+            res.header(
+                header::ACCESS_CONTROL_ALLOW_METHODS,
+                method.unwrap().as_str(),
+            );
+        } else {
+            res.header(
+                header::ACCESS_CONTROL_ALLOW_METHODS,
+                self.methods_hdr.clone(),
+            );
+        }
+        if let Some(val) = req_headers {
+            res.header(header::ACCESS_CONTROL_ALLOW_HEADERS, val.clone());
+        }
+        if self.credentials {
+            res.header(header::ACCESS_CONTROL_ALLOW_CREDENTIALS, "true");
+        }
+        if let Some(ref val) = self.max_age {
+            res.header(header::ACCESS_CONTROL_MAX_AGE, val.clone());
+        }
+        if self.vary_origin() {
+            res.header(header::VARY, "Origin");
+        }
+        res.finish()
+    }
+
+    /// Responses depend on request origin
+    fn vary_origin(&self) -> bool {
+        !self.any_origin || self.credentials
+    }
+}
+
+impl fmt::Debug for Cors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cors")
+            .field("any_origin", &self.inner.any_origin)
+            .field("origins", &self.inner.origins.len())
+            .field("methods", &self.inner.methods_hdr)
+            .field("credentials", &self.inner.credentials)
+            .finish()
+    }
+}
+
+impl<S> Middleware<S> for Cors {
+    type Service = CorsMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        CorsMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct CorsMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S> fmt::Debug for CorsMiddleware<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CorsMiddleware").finish()
+    }
+}
+
+impl<S, E> Service<WebRequest<E>> for CorsMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    crate::forward_poll_ready!(service);
+    crate::forward_poll_shutdown!(service);
+
+    async fn call(
+        &self,
+        req: WebRequest<E>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        let origin = if let Some(origin) = req.headers().get(&header::ORIGIN) {
+            origin.clone()
+        } else {
+            let mut res = ctx.call(&self.service, req).await?;
+            if self.inner.vary_origin() {
+                res.headers_mut()
+                    .append(header::VARY, HeaderValue::from_static("Origin"));
+            }
+            return Ok(res);
+        };
+
+        // preflight request
+        if *req.method() == Method::OPTIONS
+            && req
+                .headers()
+                .contains_key(&header::ACCESS_CONTROL_REQUEST_METHOD)
+        {
+            let res = self.inner.preflight(req.head(), &origin);
+            return Ok(req.into_response(res));
+        }
+
+        let allowed = self.inner.is_origin_allowed(&origin, req.head());
+        let mut res = ctx.call(&self.service, req).await?;
+
+        if allowed {
+            let inner = &self.inner;
+            let headers = res.headers_mut();
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_ORIGIN,
+                inner.origin_value(&origin),
+            );
+            if inner.credentials {
+                headers.insert(
+                    header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                    HeaderValue::from_static("true"),
+                );
+            }
+            if let Some(ref val) = inner.expose_hdr {
+                headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, val.clone());
+            }
+        }
+        // response for not allowed origin differs too
+        if self.inner.vary_origin() {
+            res.headers_mut()
+                .append(header::VARY, HeaderValue::from_static("Origin"));
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{header::*, StatusCode};
+    use crate::web::test::{call_service, init_service, TestRequest};
+    use crate::web::{self, App, HttpResponse};
+
+    #[crate::rt_test]
+    async fn test_preflight() {
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    Cors::new()
+                        .allowed_origin("https://www.rust-lang.org")
+                        .allowed_origin("https://*.ntex.rs")
+                        .allowed_methods([Method::GET, Method::PUT])
+                        .allowed_headers([AUTHORIZATION, CONTENT_TYPE])
+                        .max_age(Seconds(3600)),
+                )
+                .route("/", web::get().to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/")
+            .method(Method::OPTIONS)
+            .header(ORIGIN, "https://docs.ntex.rs")
+            .header(ACCESS_CONTROL_REQUEST_METHOD, "PUT")
+            .header(
+                ACCESS_CONTROL_REQUEST_HEADERS,
+                "authorization, content-type",
+            )
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let hdrs = res.headers();
+        assert_eq!(
+            hdrs.get(ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            "https://docs.ntex.rs"
+        );
+        assert_eq!(hdrs.get(ACCESS_CONTROL_ALLOW_METHODS).unwrap(), "GET, PUT");
+        assert_eq!(
+            hdrs.get(ACCESS_CONTROL_ALLOW_HEADERS).unwrap(),
+            "authorization, content-type"
+        );
+        assert_eq!(hdrs.get(ACCESS_CONTROL_MAX_AGE).unwrap(), "3600");
+        assert_eq!(hdrs.get(VARY).unwrap(), "Origin");
+        assert!(hdrs.get(ACCESS_CONTROL_ALLOW_CREDENTIALS).is_none());
+
+        for (origin, method, headers) in [
+            ("https://ntex.rs", "GET", "authorization"),
+            ("https://www.rust-lang.org", "DELETE", "authorization"),
+            ("https://www.rust-lang.org", "GET", "x-custom"),
+        ] {
+            let req = TestRequest::with_uri("/")
+                .method(Method::OPTIONS)
+                .header(ORIGIN, origin)
+                .header(ACCESS_CONTROL_REQUEST_METHOD, method)
+                .header(ACCESS_CONTROL_REQUEST_HEADERS, headers)
+                .to_request();
+            let res = call_service(&srv, req).await;
+            assert_eq!(res.status(), StatusCode::FORBIDDEN);
+            assert!(res.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+            assert_eq!(res.headers().get(VARY).unwrap(), "Origin");
+        }
+    }
+
+    #[crate::rt_test]
+    async fn test_response_headers() {
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    Cors::new()
+                        .allowed_origin_fn(|origin, _| origin.as_bytes().ends_with(b".org"))
+                        .expose_headers(["x-request-id"])
+                        .supports_credentials(),
+                )
+                .route("/", web::get().to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/")
+            .header(ORIGIN, "https://www.rust-lang.org")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        let hdrs = res.headers();
+        assert_eq!(
+            hdrs.get(ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            "https://www.rust-lang.org"
+        );
+        assert_eq!(hdrs.get(ACCESS_CONTROL_ALLOW_CREDENTIALS).unwrap(), "true");
+        assert_eq!(
+            hdrs.get(ACCESS_CONTROL_EXPOSE_HEADERS).unwrap(),
+            "x-request-id"
+        );
+
+        // not allowed origin
+        let req = TestRequest::with_uri("/")
+            .header(ORIGIN, "https://ntex.rs")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+        assert_eq!(res.headers().get(VARY).unwrap(), "Origin");
+
+        // request without origin
+        let req = TestRequest::with_uri("/").to_request();
+        let res = call_service(&srv, req).await;
+        assert!(res.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+        assert_eq!(res.headers().get(VARY).unwrap(), "Origin");
+    }
+
+    #[crate::rt_test]
+    async fn test_any_origin() {
+        let srv = init_service(
+            App::new()
+                .service(
+                    web::scope("/api")
+                        .wrap(Cors::new().allowed_origin("*").allow_any_method())
+                        .route("/", web::get().to(|| async { HttpResponse::Ok() })),
+                )
+                .route("/", web::get().to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/api/")
+            .method(Method::OPTIONS)
+            .header(ORIGIN, "https://ntex.rs")
+            .header(ACCESS_CONTROL_REQUEST_METHOD, "PATCH")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let hdrs = res.headers();
+        assert_eq!(hdrs.get(ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(), "*");
+        assert_eq!(hdrs.get(ACCESS_CONTROL_ALLOW_METHODS).unwrap(), "PATCH");
+        assert!(hdrs.get(VARY).is_none());
+
+        let req = TestRequest::with_uri("/api/")
+            .header(ORIGIN, "https://ntex.rs")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(), "*");
+
+        // outside of scope
+        let req = TestRequest::with_uri("/")
+            .header(ORIGIN, "https://ntex.rs")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert!(res.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+    }
+}
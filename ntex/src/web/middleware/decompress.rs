@@ -0,0 +1,172 @@
+//! `Middleware` for request payload decompression.
+use crate::http::encoding::Decoder;
+use crate::http::header::{ContentEncoding, CONTENT_ENCODING, CONTENT_LENGTH};
+use crate::http::Payload;
+use crate::service::{Middleware, Service, ServiceCtx};
+use crate::web::{WebRequest, WebResponse};
+
+#[derive(Debug, Clone, Default)]
+/// `Middleware` for request payload decompression.
+///
+/// Payload of requests with gzip, deflate, br or zstd `Content-Encoding`
+/// is decompressed before extractors run, `Content-Encoding` and
+/// `Content-Length` headers are removed from the request. Payload stream
+/// returns `PayloadError::Overflow` error if decompressed payload exceeds
+/// limit. Requests with other encodings are passed as is.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::Decompress::new().limit(1024 * 1024))
+///         .route("/", web::post().to(|body: String| async move { body }));
+/// }
+/// ```
+pub struct Decompress {
+    limit: usize,
+}
+
+impl Decompress {
+    /// Create new `Decompress` middleware.
+    pub fn new() -> Self {
+        Decompress { limit: 0 }
+    }
+
+    /// Set max size of decompressed payload.
+    ///
+    /// By default limit is not set, extractors apply their own limits.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+}
+
+impl<S> Middleware<S> for Decompress {
+    type Service = DecompressMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        DecompressMiddleware {
+            service,
+            limit: self.limit,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DecompressMiddleware<S> {
+    service: S,
+    limit: usize,
+}
+
+impl<S, E> Service<WebRequest<E>> for DecompressMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    crate::forward_poll_ready!(service);
+    crate::forward_poll_shutdown!(service);
+
+    async fn call(
+        &self,
+        mut req: WebRequest<E>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<WebResponse, S::Error> {
+        let encoding = req
+            .headers()
+            .get(&CONTENT_ENCODING)
+            .and_then(|val| val.to_str().ok())
+            .map(ContentEncoding::from)
+            .unwrap_or(ContentEncoding::Identity);
+
+        if matches!(
+            encoding,
+            ContentEncoding::Br
+                | ContentEncoding::Gzip
+                | ContentEncoding::Deflate
+                | ContentEncoding::Zstd
+        ) {
+            let decoder = Decoder::new(req.take_payload(), encoding).limit(self.limit);
+            req.set_payload(Payload::Stream(Box::pin(decoder)));
+            req.headers_mut().remove(&CONTENT_ENCODING);
+            req.headers_mut().remove(&CONTENT_LENGTH);
+        }
+
+        ctx.call(&self.service, req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::*;
+    use crate::http::{error::PayloadError, StatusCode};
+    use crate::util::{stream_recv, Bytes, BytesMut};
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, HttpRequest};
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut enc = GzEncoder::new(Vec::new(), Compression::default());
+        enc.write_all(data).unwrap();
+        enc.finish().unwrap()
+    }
+
+    #[crate::rt_test]
+    async fn test_decompress() {
+        let srv = init_service(
+            App::new()
+                .wrap(Decompress::new().limit(64))
+                .route(
+                    "/",
+                    web::post().to(|req: HttpRequest, body: Bytes| async move {
+                        assert!(!req.headers().contains_key(CONTENT_ENCODING));
+                        body
+                    }),
+                )
+                .route(
+                    "/stream",
+                    web::post().to(|mut pl: web::types::Payload| async move {
+                        let mut buf = BytesMut::new();
+                        while let Some(item) = stream_recv(&mut pl).await {
+                            match item {
+                                Ok(chunk) => buf.extend_from_slice(&chunk),
+                                Err(PayloadError::Overflow) => return "overflow".into(),
+                                Err(e) => return format!("{:?}", e),
+                            }
+                        }
+                        format!("{}", buf.len())
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .header(CONTENT_ENCODING, "gzip")
+            .set_payload(gzip(b"hello world"))
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(read_body(res).await, Bytes::from_static(b"hello world"));
+
+        let req = TestRequest::post()
+            .uri("/stream")
+            .header(CONTENT_ENCODING, "gzip")
+            .set_payload(gzip(&[b'x'; 1024]))
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, Bytes::from_static(b"overflow"));
+
+        // identity payload is not changed
+        let req = TestRequest::post()
+            .uri("/stream")
+            .set_payload(vec![b'x'; 1024])
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, Bytes::from_static(b"1024"));
+    }
+}
@@ -0,0 +1,595 @@
+//! Middleware for verifying and generating body digests
+use std::task::{Context, Poll};
+use std::{cell::RefCell, error::Error, future::poll_fn, pin::Pin, rc::Rc};
+
+use base64::{engine::general_purpose::STANDARD as base64, Engine};
+use md5::Md5;
+use sha2::{Digest as _, Sha256, Sha512};
+
+use crate::http::body::{Body, BodySize, MessageBody, ResponseBody};
+use crate::http::error::PayloadError;
+use crate::http::header::{HeaderName, HeaderValue};
+use crate::http::Payload;
+use crate::service::{Middleware, Service, ServiceCtx};
+use crate::util::{Bytes, BytesMut, Stream};
+use crate::web::{ErrorRenderer, HttpResponse, WebRequest, WebResponse};
+
+/// `Content-Digest` header name, rfc9530
+pub const CONTENT_DIGEST: HeaderName = HeaderName::from_static("content-digest");
+
+/// `Content-MD5` header name
+pub const CONTENT_MD5: HeaderName = HeaderName::from_static("content-md5");
+
+/// Default max size of buffered response body, 1Mb
+const MAX_SIZE: usize = 1_048_576;
+
+/// Digest algorithm for `Content-Digest` header
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DigestAlgorithm {
+    Sha256,
+    Sha512,
+}
+
+impl DigestAlgorithm {
+    fn as_str(&self) -> &'static str {
+        match self {
+            DigestAlgorithm::Sha256 => "sha-256",
+            DigestAlgorithm::Sha512 => "sha-512",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        if s.eq_ignore_ascii_case("sha-256") {
+            Some(DigestAlgorithm::Sha256)
+        } else if s.eq_ignore_ascii_case("sha-512") {
+            Some(DigestAlgorithm::Sha512)
+        } else {
+            None
+        }
+    }
+}
+
+/// `Middleware` for request body verification and response digests.
+///
+/// Request body is verified against `Content-Digest` and `Content-MD5`
+/// headers. Digest is computed incrementally while body is read, on
+/// mismatch reading body fails with `PayloadError::DigestMismatch` error
+/// which is rendered as `400 Bad Request`. If handler does not read whole
+/// body, the rest of the body is read and verified after handler completes.
+/// Unknown digest algorithms are ignored.
+///
+/// Response digest is generated with [`Digest::response()`], this requires
+/// buffering of response bodies, bodies larger than [`Digest::max_size()`]
+/// are sent without digest.
+///
+/// Middleware requires `digest` feature.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+/// use ntex::web::middleware::DigestAlgorithm;
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::Digest::new())
+///         .service(
+///             web::resource("/objects")
+///                 .wrap(middleware::Digest::new().response(DigestAlgorithm::Sha256))
+///                 .route(web::get().to(|| async { HttpResponse::Ok().body("data") }))
+///         );
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Digest {
+    inner: Rc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    verify: bool,
+    response: Option<DigestAlgorithm>,
+    max_size: usize,
+}
+
+impl Default for Digest {
+    fn default() -> Self {
+        Digest {
+            inner: Rc::new(Inner {
+                verify: true,
+                response: None,
+                max_size: MAX_SIZE,
+            }),
+        }
+    }
+}
+
+impl Digest {
+    /// Construct `Digest` middleware.
+    pub fn new() -> Digest {
+        Digest::default()
+    }
+
+    /// Verify request body digests.
+    ///
+    /// By default verification is enabled.
+    pub fn verify(mut self, enabled: bool) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .verify = enabled;
+        self
+    }
+
+    /// Generate `Content-Digest` header for responses.
+    ///
+    /// Existing `Content-Digest` header is not replaced.
+    pub fn response(mut self, alg: DigestAlgorithm) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .response = Some(alg);
+        self
+    }
+
+    /// Set max size of response body that could be buffered.
+    ///
+    /// By default max size is 1Mb.
+    pub fn max_size(mut self, size: usize) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .max_size = size;
+        self
+    }
+}
+
+impl<S> Middleware<S> for Digest {
+    type Service = DigestMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        DigestMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DigestMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for DigestMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+    E: ErrorRenderer,
+    E::Container: From<PayloadError>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    crate::forward_poll_ready!(service);
+    crate::forward_poll_shutdown!(service);
+
+    async fn call(
+        &self,
+        mut req: WebRequest<E>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        let mut state = None;
+        if self.inner.verify {
+            match expected_digests(&req) {
+                Ok(checks) if checks.is_empty() => (),
+                Ok(checks) => {
+                    let st = Rc::new(RefCell::new(DigestState {
+                        payload: req.take_payload(),
+                        checks,
+                        done: false,
+                    }));
+                    req.set_payload(Payload::from_stream(DigestPayload(st.clone())));
+                    state = Some(st);
+                }
+                Err(err) => return Ok(req.error_response(err)),
+            }
+        }
+
+        let mut res = ctx.call(&self.service, req).await?;
+
+        // verify rest of the body if handler did not read it,
+        // payload could still be in use by response body
+        if state.is_some() {
+            res.drop_payload();
+        }
+        if let Some(state) = state.filter(|st| Rc::strong_count(st) == 1) {
+            while let Some(item) = poll_fn(|cx| state.borrow_mut().poll_next(cx)).await {
+                match item {
+                    Ok(_) => (),
+                    Err(PayloadError::DigestMismatch) => {
+                        return Ok(res.error_response::<E, _>(PayloadError::DigestMismatch))
+                    }
+                    Err(_) => break,
+                }
+            }
+        }
+
+        if let Some(alg) = self.inner.response {
+            if res.headers().contains_key(&CONTENT_DIGEST) {
+                return Ok(res);
+            }
+
+            let body = match res.take_body() {
+                ResponseBody::Body(Body::None) | ResponseBody::Other(Body::None) => {
+                    return Ok(res.map_body(|_, _| ResponseBody::Other(Body::None)));
+                }
+                ResponseBody::Body(Body::Empty) | ResponseBody::Other(Body::Empty) => {
+                    Bytes::new()
+                }
+                ResponseBody::Body(Body::Bytes(b))
+                | ResponseBody::Other(Body::Bytes(b)) => b,
+                body => {
+                    // bodies above the limit are sent without digest
+                    if let BodySize::Sized(size) = body.size() {
+                        if size as usize > self.inner.max_size {
+                            return Ok(res.map_body(move |_, _| body));
+                        }
+                    }
+                    match read_body(body, self.inner.max_size).await {
+                        Ok(Ok(body)) => body,
+                        Ok(Err(body)) => {
+                            return Ok(res.map_body(move |_, _| {
+                                ResponseBody::Other(Body::from_message(body))
+                            }));
+                        }
+                        Err(e) => {
+                            log::error!("Cannot read response body: {}", e);
+                            let response = HttpResponse::InternalServerError().finish();
+                            return Ok(res.into_response(response));
+                        }
+                    }
+                }
+            };
+
+            let value = format!(
+                "{}=:{}:",
+                alg.as_str(),
+                base64.encode(Hasher::new(alg).chain(&body).finish())
+            );
+            if let Ok(value) = HeaderValue::try_from(value) {
+                res.headers_mut().insert(CONTENT_DIGEST, value);
+            }
+            res = res.map_body(move |_, _| ResponseBody::Other(Body::Bytes(body)));
+        }
+        Ok(res)
+    }
+}
+
+/// Read response body, if body is larger than `max_size`
+/// returns read part and the rest of the body
+async fn read_body(
+    mut body: ResponseBody<Body>,
+    max_size: usize,
+) -> Result<Result<Bytes, PrefixedBody>, Box<dyn Error>> {
+    let mut buf = BytesMut::new();
+    loop {
+        match poll_fn(|cx| body.poll_next_chunk(cx)).await {
+            Some(Ok(chunk)) => {
+                buf.extend_from_slice(&chunk);
+                if buf.len() > max_size {
+                    return Ok(Err(PrefixedBody {
+                        buf: Some(buf.freeze()),
+                        body,
+                    }));
+                }
+            }
+            Some(Err(e)) => return Err(e),
+            None => return Ok(Ok(buf.freeze())),
+        }
+    }
+}
+
+/// Response body with already read part
+struct PrefixedBody {
+    buf: Option<Bytes>,
+    body: ResponseBody<Body>,
+}
+
+impl MessageBody for PrefixedBody {
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        if let Some(buf) = self.buf.take() {
+            Poll::Ready(Some(Ok(buf)))
+        } else {
+            self.body.poll_next_chunk(cx)
+        }
+    }
+}
+
+/// Parse request digest headers
+fn expected_digests<E>(req: &WebRequest<E>) -> Result<Vec<Check>, PayloadError> {
+    let mut checks = Vec::new();
+
+    for value in req.headers().get_all(&CONTENT_DIGEST) {
+        let value = value.to_str().map_err(|_| PayloadError::DigestMismatch)?;
+        for item in value.split(',') {
+            let (alg, digest) = item.split_once('=').ok_or(PayloadError::DigestMismatch)?;
+            if let Some(alg) = DigestAlgorithm::parse(alg.trim()) {
+                checks.push(Check {
+                    hasher: Hasher::new(alg),
+                    expected: decode(
+                        digest
+                            .trim()
+                            .strip_prefix(':')
+                            .and_then(|d| d.strip_suffix(':')),
+                    )?,
+                });
+            }
+        }
+    }
+
+    if let Some(value) = req.headers().get(&CONTENT_MD5) {
+        checks.push(Check {
+            hasher: Hasher::Md5(Md5::new()),
+            expected: decode(value.to_str().ok().map(|s| s.trim()))?,
+        });
+    }
+    Ok(checks)
+}
+
+fn decode(value: Option<&str>) -> Result<Vec<u8>, PayloadError> {
+    value
+        .and_then(|val| base64.decode(val).ok())
+        .ok_or(PayloadError::DigestMismatch)
+}
+
+enum Hasher {
+    Sha256(Sha256),
+    Sha512(Sha512),
+    Md5(Md5),
+}
+
+impl Hasher {
+    fn new(alg: DigestAlgorithm) -> Self {
+        match alg {
+            DigestAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            DigestAlgorithm::Sha512 => Hasher::Sha512(Sha512::new()),
+        }
+    }
+
+    fn chain(mut self, data: &[u8]) -> Self {
+        self.update(data);
+        self
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(h) => h.update(data),
+            Hasher::Sha512(h) => h.update(data),
+            Hasher::Md5(h) => h.update(data),
+        }
+    }
+
+    fn finish(self) -> Vec<u8> {
+        match self {
+            Hasher::Sha256(h) => h.finalize().to_vec(),
+            Hasher::Sha512(h) => h.finalize().to_vec(),
+            Hasher::Md5(h) => h.finalize().to_vec(),
+        }
+    }
+}
+
+struct Check {
+    hasher: Hasher,
+    expected: Vec<u8>,
+}
+
+/// Request payload, computes digests while payload is read
+struct DigestPayload(Rc<RefCell<DigestState>>);
+
+struct DigestState {
+    payload: Payload,
+    checks: Vec<Check>,
+    done: bool,
+}
+
+impl DigestState {
+    fn poll_next(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, PayloadError>>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+
+        match Pin::new(&mut self.payload).poll_next(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                for check in &mut self.checks {
+                    check.hasher.update(&chunk);
+                }
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            Poll::Ready(None) => {
+                self.done = true;
+                for check in self.checks.drain(..) {
+                    if check.hasher.finish() != check.expected {
+                        return Poll::Ready(Some(Err(PayloadError::DigestMismatch)));
+                    }
+                }
+                Poll::Ready(None)
+            }
+            Poll::Ready(Some(Err(e))) => {
+                self.done = true;
+                Poll::Ready(Some(Err(e)))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl Stream for DigestPayload {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0.borrow_mut().poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sha2::Digest as _;
+
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::service::{IntoService, Pipeline};
+    use crate::web::test::{read_body, TestRequest};
+    use crate::web::{DefaultError, Error, HttpResponse};
+
+    fn sha256(data: &[u8]) -> String {
+        format!("sha-256=:{}:", base64.encode(Sha256::digest(data)))
+    }
+
+    #[crate::rt_test]
+    async fn test_verify() {
+        let srv = |mut req: WebRequest<DefaultError>| async move {
+            let mut pl = req.take_payload();
+            let mut body = BytesMut::new();
+            while let Some(chunk) = pl.recv().await {
+                match chunk {
+                    Ok(chunk) => body.extend_from_slice(&chunk),
+                    Err(e) => return Ok(req.error_response(e)),
+                }
+            }
+            Ok::<_, Error>(req.into_response(HttpResponse::Ok().body(body.freeze())))
+        };
+        let mw = Pipeline::new(Digest::new().create(srv.into_service()));
+
+        let req = TestRequest::default()
+            .header(CONTENT_DIGEST, sha256(b"data"))
+            .header(CONTENT_MD5, base64.encode(Md5::digest(b"data")))
+            .set_payload("data")
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"data"));
+
+        let req = TestRequest::default()
+            .header(CONTENT_DIGEST, sha256(b"other"))
+            .set_payload("data")
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let req = TestRequest::default()
+            .header(CONTENT_MD5, base64.encode(Md5::digest(b"other")))
+            .set_payload("data")
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        // malformed digest
+        let req = TestRequest::default()
+            .header(CONTENT_DIGEST, "sha-256=abc")
+            .set_payload("data")
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        // unknown algorithms are ignored
+        let req = TestRequest::default()
+            .header(CONTENT_DIGEST, "unixsum=:MTIz:")
+            .set_payload("data")
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[crate::rt_test]
+    async fn test_verify_unread() {
+        let srv = |req: WebRequest<DefaultError>| async move {
+            Ok::<_, Error>(req.into_response(HttpResponse::Ok().finish()))
+        };
+        let mw = Pipeline::new(Digest::new().create(srv.into_service()));
+
+        let req = TestRequest::default()
+            .header(CONTENT_DIGEST, sha256(b"data"))
+            .set_payload("data")
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::default()
+            .header(CONTENT_DIGEST, sha256(b"other"))
+            .set_payload("data")
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[crate::rt_test]
+    async fn test_response() {
+        let srv = |req: WebRequest<DefaultError>| async move {
+            Ok::<_, Error>(req.into_response(HttpResponse::Ok().body("data")))
+        };
+        let mw = Pipeline::new(
+            Digest::new()
+                .response(DigestAlgorithm::Sha256)
+                .create(srv.into_service()),
+        );
+
+        let req = TestRequest::default().to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(
+            resp.headers()
+                .get(CONTENT_DIGEST)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            sha256(b"data")
+        );
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"data"));
+    }
+
+    #[crate::rt_test]
+    async fn test_response_max_size() {
+        let srv = |req: WebRequest<DefaultError>| async move {
+            Ok::<_, Error>(req.into_response(HttpResponse::Ok().streaming(
+                futures_util::stream::iter(vec![
+                    Ok::<_, Error>(Bytes::from_static(b"data")),
+                    Ok(Bytes::from_static(b"more")),
+                ]),
+            )))
+        };
+        let mw = Pipeline::new(
+            Digest::new()
+                .response(DigestAlgorithm::Sha256)
+                .create(srv.into_service()),
+        );
+        let resp = mw
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+        assert_eq!(
+            resp.headers()
+                .get(CONTENT_DIGEST)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            sha256(b"datamore")
+        );
+
+        let mw = Pipeline::new(
+            Digest::new()
+                .response(DigestAlgorithm::Sha256)
+                .max_size(5)
+                .create(srv.into_service()),
+        );
+        let resp = mw
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+        assert!(resp.headers().get(CONTENT_DIGEST).is_none());
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"datamore"));
+    }
+}
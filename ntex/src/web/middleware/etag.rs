@@ -0,0 +1,360 @@
+//! Middleware for `ETag` generation and conditional requests
+use std::{future::poll_fn, rc::Rc};
+
+use httpdate::HttpDate;
+use sha1::{Digest as _, Sha1};
+
+use crate::http::body::{Body, BodySize, MessageBody, ResponseBody};
+use crate::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use crate::http::{Method, StatusCode};
+use crate::service::{Middleware, Service, ServiceCtx};
+use crate::util::{Bytes, BytesMut};
+use crate::web::{HttpResponse, WebRequest, WebResponse};
+
+/// Default max size of buffered response body, 1Mb
+const MAX_SIZE: usize = 1_048_576;
+
+/// Headers that are passed to `304 Not Modified` response
+const NOT_MODIFIED_HEADERS: [HeaderName; 7] = [
+    header::CACHE_CONTROL,
+    header::CONTENT_LOCATION,
+    header::DATE,
+    header::ETAG,
+    header::EXPIRES,
+    header::LAST_MODIFIED,
+    header::VARY,
+];
+
+/// `Middleware` for `ETag` generation and conditional requests.
+///
+/// Middleware computes `ETag` for successful responses to `GET` and `HEAD`
+/// requests, as sha-256 digest of response body. If handler sets `ETag`
+/// header, it is used as is. Then `If-None-Match` and `If-Modified-Since`
+/// request headers are evaluated and if representation is not modified
+/// response is converted to `304 Not Modified`.
+///
+/// Streaming response bodies and bodies larger than max size are not
+/// buffered, for such responses only handler provided `ETag` and
+/// `Last-Modified` headers are checked.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::ETag::new().weak(true))
+///         .route("/", web::get().to(|| async { HttpResponse::Ok().body("data") }));
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ETag {
+    inner: Rc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    weak: bool,
+    max_size: usize,
+}
+
+impl Default for ETag {
+    fn default() -> Self {
+        ETag {
+            inner: Rc::new(Inner {
+                weak: false,
+                max_size: MAX_SIZE,
+            }),
+        }
+    }
+}
+
+impl ETag {
+    /// Construct `ETag` middleware.
+    pub fn new() -> Self {
+        ETag::default()
+    }
+
+    /// Generate weak etags, i.e. `W/"..."`.
+    ///
+    /// Weak etags should be used if response body could be modified
+    /// by other middlewares, for example compressed. By default strong
+    /// etags are generated.
+    pub fn weak(mut self, weak: bool) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .weak = weak;
+        self
+    }
+
+    /// Set max size of response body that could be buffered.
+    ///
+    /// By default max size is 1Mb.
+    pub fn max_size(mut self, size: usize) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .max_size = size;
+        self
+    }
+}
+
+impl<S> Middleware<S> for ETag {
+    type Service = ETagMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        ETagMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ETagMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for ETagMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    crate::forward_poll_ready!(service);
+    crate::forward_poll_shutdown!(service);
+
+    async fn call(
+        &self,
+        req: WebRequest<E>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        let method = req.method().clone();
+        let mut res = ctx.call(&self.service, req).await?;
+
+        if (method != Method::GET && method != Method::HEAD)
+            || res.status() != StatusCode::OK
+        {
+            return Ok(res);
+        }
+
+        if !res.headers().contains_key(&header::ETAG) {
+            let size = match res.response().body().size() {
+                BodySize::None => return Ok(res),
+                BodySize::Empty => 0,
+                BodySize::Sized(size) => size as usize,
+                BodySize::Stream => usize::MAX,
+            };
+            if size > self.inner.max_size {
+                return Ok(not_modified(res));
+            }
+
+            let body = match res.take_body() {
+                ResponseBody::Body(Body::Bytes(b))
+                | ResponseBody::Other(Body::Bytes(b)) => b,
+                ResponseBody::Body(Body::Empty) | ResponseBody::Other(Body::Empty) => {
+                    Bytes::new()
+                }
+                mut body => {
+                    let mut buf = BytesMut::new();
+                    loop {
+                        match poll_fn(|cx| body.poll_next_chunk(cx)).await {
+                            Some(Ok(chunk)) => buf.extend_from_slice(&chunk),
+                            Some(Err(e)) => {
+                                log::error!("Cannot read response body: {}", e);
+                                let response = HttpResponse::InternalServerError().finish();
+                                return Ok(res.into_response(response));
+                            }
+                            None => break,
+                        }
+                    }
+                    buf.freeze()
+                }
+            };
+
+            let etag = etag_value(&body, self.inner.weak);
+            res.headers_mut().insert(header::ETAG, etag);
+            res = res.map_body(move |_, _| ResponseBody::Other(Body::Bytes(body)));
+        }
+        Ok(not_modified(res))
+    }
+}
+
+/// Compute etag from response body
+fn etag_value(body: &[u8], weak: bool) -> HeaderValue {
+    let digest = Sha1::digest(body);
+    let mut value = String::with_capacity(36);
+    if weak {
+        value.push_str("W/");
+    }
+    value.push('"');
+    for b in &digest[..16] {
+        value.push_str(&format!("{:02x}", b));
+    }
+    value.push('"');
+    HeaderValue::try_from(value).unwrap()
+}
+
+/// Convert response to `304 Not Modified` if request conditions match
+///
+/// `If-None-Match` takes precedence over `If-Modified-Since`.
+fn not_modified(res: WebResponse) -> WebResponse {
+    let headers = res.request().headers();
+    let matched = if headers.contains_key(&header::IF_NONE_MATCH) {
+        res.headers()
+            .get(&header::ETAG)
+            .map(|etag| if_none_match(headers, etag))
+            .unwrap_or(false)
+    } else {
+        match (
+            header_date(headers, &header::IF_MODIFIED_SINCE),
+            header_date(res.headers(), &header::LAST_MODIFIED),
+        ) {
+            (Some(since), Some(modified)) => modified <= since,
+            _ => false,
+        }
+    };
+    if !matched {
+        return res;
+    }
+
+    let mut response = HttpResponse::NotModified().finish();
+    for name in NOT_MODIFIED_HEADERS.iter() {
+        for value in res.headers().get_all(name) {
+            response.headers_mut().append(name.clone(), value.clone());
+        }
+    }
+    res.into_response(response)
+}
+
+/// Check if `If-None-Match` header matches etag, using weak comparison
+fn if_none_match(headers: &HeaderMap, etag: &HeaderValue) -> bool {
+    let etag = match etag.to_str() {
+        Ok(etag) => etag.trim_start_matches("W/"),
+        Err(_) => return false,
+    };
+    headers
+        .get_all(&header::IF_NONE_MATCH)
+        .filter_map(|val| val.to_str().ok())
+        .flat_map(|val| val.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == "*" || tag == etag)
+}
+
+/// Parse http date header
+fn header_date(headers: &HeaderMap, name: &HeaderName) -> Option<HttpDate> {
+    headers
+        .get(name)
+        .and_then(|val| val.to_str().ok())
+        .and_then(|val| val.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App};
+
+    #[crate::rt_test]
+    async fn test_etag() {
+        let srv = init_service(
+            App::new()
+                .wrap(ETag::new())
+                .route("/", web::get().to(|| async { "data" }))
+                .route("/", web::post().to(|| async { "data" })),
+        )
+        .await;
+
+        let res = call_service(&srv, TestRequest::default().to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let etag = res.headers().get(header::ETAG).unwrap().clone();
+        assert_eq!(etag, etag_value(b"data", false));
+        assert_eq!(read_body(res).await, "data");
+
+        let req = TestRequest::default()
+            .header(
+                header::IF_NONE_MATCH,
+                "\"other\", W/".to_string() + etag.to_str().unwrap(),
+            )
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(res.headers().get(header::ETAG).unwrap(), &etag);
+        assert!(res.headers().get(header::CONTENT_TYPE).is_none());
+        assert!(read_body(res).await.is_empty());
+
+        let req = TestRequest::default()
+            .header(header::IF_NONE_MATCH, "\"other\"")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // unsafe methods are not affected
+        let req = TestRequest::default()
+            .method(Method::POST)
+            .header(header::IF_NONE_MATCH, "*")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.headers().get(header::ETAG).is_none());
+    }
+
+    #[crate::rt_test]
+    async fn test_handler_headers() {
+        let modified = HttpDate::from(std::time::UNIX_EPOCH).to_string();
+        let srv = init_service(
+            App::new()
+                .wrap(ETag::new().weak(true).max_size(2))
+                .route(
+                    "/etag",
+                    web::get().to(|| async {
+                        HttpResponse::Ok()
+                            .header(header::ETAG, "\"custom\"")
+                            .header(header::CACHE_CONTROL, "no-cache")
+                            .body("data")
+                    }),
+                )
+                .route(
+                    "/modified",
+                    web::get().to(move || {
+                        let modified = modified.clone();
+                        async move {
+                            HttpResponse::Ok()
+                                .header(header::LAST_MODIFIED, modified)
+                                .body("data")
+                        }
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/etag")
+            .header(header::IF_NONE_MATCH, "W/\"custom\"")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(res.headers().get(header::ETAG).unwrap(), "\"custom\"");
+        assert_eq!(
+            res.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-cache"
+        );
+
+        // body is larger than max size
+        let since = HttpDate::from(std::time::SystemTime::now()).to_string();
+        let req = TestRequest::with_uri("/modified")
+            .header(header::IF_MODIFIED_SINCE, since.clone())
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert!(res.headers().get(header::ETAG).is_none());
+        assert!(res.headers().contains_key(header::LAST_MODIFIED));
+
+        let req = TestRequest::with_uri("/modified")
+            .header(header::IF_MODIFIED_SINCE, since)
+            .header(header::IF_NONE_MATCH, "\"other\"")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(read_body(res).await, "data");
+    }
+}
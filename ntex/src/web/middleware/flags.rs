@@ -0,0 +1,258 @@
+//! Middleware for feature flags
+use std::{cell::RefCell, fmt, future::Future, rc::Rc, rc::Weak};
+
+use crate::http::RequestHead;
+use crate::service::{Middleware, Service, ServiceCtx};
+use crate::time::{sleep, Millis};
+use crate::web::{WebRequest, WebResponse};
+
+pub use crate::web::types::Flags;
+
+/// Feature flags provider
+pub trait FlagsProvider {
+    /// Get feature flags for the request
+    fn flags(&self, head: &RequestHead) -> Flags;
+}
+
+/// Static flags provider
+impl FlagsProvider for Flags {
+    fn flags(&self, _: &RequestHead) -> Flags {
+        self.clone()
+    }
+}
+
+/// Flags provider which periodically refreshes flags in background
+///
+/// Refresh function is called immediately and then every `interval`.
+/// Until first refresh completes all flags are disabled. If refresh fails
+/// previous flags are kept. Background task stops after provider and all
+/// its clones get dropped.
+///
+/// Provider spawns background task on current worker, so it must be
+/// constructed within the application factory.
+///
+/// ```rust
+/// use ntex::time::Seconds;
+/// use ntex::web::{self, middleware::{FeatureFlags, Flags, RefreshFlags}, App};
+///
+/// async fn fetch_flags() -> Result<Flags, std::io::Error> {
+///     // request flags from remote service
+///     Ok(Flags::from_iter([("beta", true)]))
+/// }
+///
+/// #[ntex::main]
+/// async fn main() {
+///     let app = App::new()
+///         .wrap(FeatureFlags::new(RefreshFlags::new(Seconds(30), fetch_flags)));
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct RefreshFlags(Rc<RefCell<Flags>>);
+
+impl RefreshFlags {
+    /// Create refreshing flags provider
+    pub fn new<T, F, R, E>(interval: T, f: F) -> Self
+    where
+        T: Into<Millis>,
+        F: Fn() -> R + 'static,
+        R: Future<Output = Result<Flags, E>> + 'static,
+        E: fmt::Debug + 'static,
+    {
+        let flags = Rc::new(RefCell::new(Flags::default()));
+        let weak = Rc::downgrade(&flags);
+        crate::rt::spawn(refresh(weak, interval.into(), f));
+        RefreshFlags(flags)
+    }
+}
+
+async fn refresh<F, R, E>(flags: Weak<RefCell<Flags>>, interval: Millis, f: F)
+where
+    F: Fn() -> R,
+    R: Future<Output = Result<Flags, E>>,
+    E: fmt::Debug,
+{
+    loop {
+        let result = f().await;
+        if let Some(flags) = flags.upgrade() {
+            match result {
+                Ok(new_flags) => *flags.borrow_mut() = new_flags,
+                Err(e) => log::error!("Cannot refresh feature flags: {:?}", e),
+            }
+        } else {
+            return;
+        }
+        sleep(interval).await;
+        if flags.strong_count() == 0 {
+            return;
+        }
+    }
+}
+
+impl FlagsProvider for RefreshFlags {
+    fn flags(&self, _: &RequestHead) -> Flags {
+        self.0.borrow().clone()
+    }
+}
+
+/// `Middleware` for populating feature flags.
+///
+/// Middleware gets flags from provider for each request and stores them
+/// in request extensions. Flags are not changed during request processing,
+/// so handlers and guards observe consistent set of flags.
+///
+/// ```rust
+/// use ntex::web::{self, guard, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::FeatureFlags::new(middleware::Flags::from_env("FEATURE_")))
+///         .service(
+///             web::resource("/beta")
+///                 .guard(guard::Flag("beta"))
+///                 .to(|| async { HttpResponse::Ok() }),
+///         );
+/// }
+/// ```
+pub struct FeatureFlags<P> {
+    provider: Rc<P>,
+}
+
+impl<P: FlagsProvider> FeatureFlags<P> {
+    /// Construct `FeatureFlags` middleware
+    pub fn new(provider: P) -> Self {
+        FeatureFlags {
+            provider: Rc::new(provider),
+        }
+    }
+}
+
+impl<P> Clone for FeatureFlags<P> {
+    fn clone(&self) -> Self {
+        FeatureFlags {
+            provider: self.provider.clone(),
+        }
+    }
+}
+
+impl<P> fmt::Debug for FeatureFlags<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FeatureFlags").finish()
+    }
+}
+
+impl<S, P> Middleware<S> for FeatureFlags<P> {
+    type Service = FeatureFlagsMiddleware<S, P>;
+
+    fn create(&self, service: S) -> Self::Service {
+        FeatureFlagsMiddleware {
+            service,
+            provider: self.provider.clone(),
+        }
+    }
+}
+
+pub struct FeatureFlagsMiddleware<S, P> {
+    service: S,
+    provider: Rc<P>,
+}
+
+impl<S, P> fmt::Debug for FeatureFlagsMiddleware<S, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FeatureFlagsMiddleware").finish()
+    }
+}
+
+impl<S, P, E> Service<WebRequest<E>> for FeatureFlagsMiddleware<S, P>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+    P: FlagsProvider,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    crate::forward_poll_ready!(service);
+    crate::forward_poll_shutdown!(service);
+
+    async fn call(
+        &self,
+        req: WebRequest<E>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        let flags = self.provider.flags(req.head());
+        req.extensions_mut().insert(flags);
+        ctx.call(&self.service, req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::util::Bytes;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, guard, App, HttpResponse};
+
+    #[crate::rt_test]
+    async fn test_feature_flags() {
+        let srv = init_service(
+            App::new()
+                .wrap(FeatureFlags::new(Flags::from_iter([
+                    ("beta", true),
+                    ("legacy", false),
+                ])))
+                .service(web::resource("/beta").guard(guard::Flag("beta")).to(
+                    |flags: Flags| async move { format!("{}", flags.is_enabled("legacy")) },
+                ))
+                .service(
+                    web::resource("/legacy")
+                        .guard(guard::Flag("legacy"))
+                        .to(|| async { HttpResponse::Ok() }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/beta").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"false"));
+
+        let req = TestRequest::with_uri("/legacy").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[crate::rt_test]
+    async fn test_refresh_flags() {
+        let counter = Rc::new(Cell::new(0));
+        let counter2 = counter.clone();
+        let provider = RefreshFlags::new(Millis(100), move || {
+            let counter = counter2.clone();
+            async move {
+                counter.set(counter.get() + 1);
+                if counter.get() == 1 {
+                    Ok(Flags::from_iter([("beta", true)]))
+                } else {
+                    Err("failed")
+                }
+            }
+        });
+        let req = TestRequest::default().to_http_request();
+        assert!(!provider.flags(req.head()).is_enabled("beta"));
+
+        sleep(Millis(50)).await;
+        assert_eq!(counter.get(), 1);
+        assert!(provider.flags(req.head()).is_enabled("beta"));
+
+        // failed refresh keeps previous flags
+        sleep(Millis(100)).await;
+        assert_eq!(counter.get(), 2);
+        assert!(provider.flags(req.head()).is_enabled("beta"));
+
+        // refresh stops after provider is dropped
+        drop(provider);
+        sleep(Millis(200)).await;
+        assert_eq!(counter.get(), 2);
+    }
+}
@@ -0,0 +1,239 @@
+//! Middleware for HTTP authentication
+use std::{fmt, future::Future, marker::PhantomData, rc::Rc};
+
+use crate::http::{header, Response};
+use crate::service::{Middleware, Service, ServiceCtx};
+use crate::web::types::{auth, AuthScheme, BasicAuth, BearerAuth};
+use crate::web::{WebRequest, WebResponse};
+
+/// `Middleware` for HTTP authentication.
+///
+/// Middleware parses `Authorization` header and validates credentials
+/// with provided async function. Requests without credentials or with
+/// rejected credentials get `401 Unauthorized` response with
+/// `WWW-Authenticate` challenge.
+///
+/// ```rust
+/// use ntex::web::{self, middleware::HttpAuth, types::BasicAuth, App};
+///
+/// async fn validate(auth: BasicAuth) -> bool {
+///     auth.user_id() == "admin" && auth.password() == Some("secret")
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::scope("/admin")
+///             .wrap(HttpAuth::basic(validate).realm("Admin area"))
+///             .route("/", web::get().to(|| async { "admin" })),
+///     );
+/// }
+/// ```
+pub struct HttpAuth<T, F> {
+    inner: Rc<Inner<F>>,
+    _t: PhantomData<T>,
+}
+
+struct Inner<F> {
+    f: F,
+    realm: Option<String>,
+}
+
+impl<F, R> HttpAuth<BasicAuth, F>
+where
+    F: Fn(BasicAuth) -> R + 'static,
+    R: Future<Output = bool>,
+{
+    /// Construct middleware for HTTP Basic authentication
+    pub fn basic(f: F) -> Self {
+        HttpAuth::new(f)
+    }
+}
+
+impl<F, R> HttpAuth<BearerAuth, F>
+where
+    F: Fn(BearerAuth) -> R + 'static,
+    R: Future<Output = bool>,
+{
+    /// Construct middleware for HTTP Bearer authentication
+    pub fn bearer(f: F) -> Self {
+        HttpAuth::new(f)
+    }
+}
+
+impl<T, F, R> HttpAuth<T, F>
+where
+    T: AuthScheme,
+    F: Fn(T) -> R + 'static,
+    R: Future<Output = bool>,
+{
+    /// Construct middleware for custom authentication scheme
+    pub fn new(f: F) -> Self {
+        HttpAuth {
+            inner: Rc::new(Inner { f, realm: None }),
+            _t: PhantomData,
+        }
+    }
+
+    /// Set realm for `WWW-Authenticate` challenge
+    pub fn realm(mut self, realm: &str) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .realm = Some(realm.to_string());
+        self
+    }
+}
+
+impl<T, F> Clone for HttpAuth<T, F> {
+    fn clone(&self) -> Self {
+        HttpAuth {
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<T, F> fmt::Debug for HttpAuth<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpAuth")
+            .field("realm", &self.inner.realm)
+            .finish()
+    }
+}
+
+impl<S, T, F> Middleware<S> for HttpAuth<T, F> {
+    type Service = HttpAuthMiddleware<S, T, F>;
+
+    fn create(&self, service: S) -> Self::Service {
+        HttpAuthMiddleware {
+            service,
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+pub struct HttpAuthMiddleware<S, T, F> {
+    service: S,
+    inner: Rc<Inner<F>>,
+    _t: PhantomData<T>,
+}
+
+impl<S, T, F> fmt::Debug for HttpAuthMiddleware<S, T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpAuthMiddleware").finish()
+    }
+}
+
+impl<S, T, F, R, E> Service<WebRequest<E>> for HttpAuthMiddleware<S, T, F>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+    T: AuthScheme,
+    F: Fn(T) -> R + 'static,
+    R: Future<Output = bool>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    crate::forward_poll_ready!(service);
+    crate::forward_poll_shutdown!(service);
+
+    async fn call(
+        &self,
+        req: WebRequest<E>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        let realm = self.inner.realm.as_deref();
+        let challenge = match auth::extract::<T>(req.headers(), realm) {
+            Ok(credentials) => {
+                if (self.inner.f)(credentials).await {
+                    return ctx.call(&self.service, req).await;
+                }
+                T::challenge(realm)
+            }
+            Err(e) => e.challenge().clone(),
+        };
+
+        Ok(req.into_response(
+            Response::Unauthorized()
+                .header(header::WWW_AUTHENTICATE, challenge)
+                .finish(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{call_service, init_service, TestRequest};
+    use crate::web::{self, App};
+
+    #[crate::rt_test]
+    async fn test_basic() {
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    HttpAuth::basic(|auth: BasicAuth| async move {
+                        auth.user_id() == "user" && auth.password() == Some("pass")
+                    })
+                    .realm("test"),
+                )
+                .route(
+                    "/",
+                    web::get()
+                        .to(|auth: BasicAuth| async move { auth.user_id().to_string() }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::default().to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            res.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+            "Basic realm=\"test\""
+        );
+
+        // user:wrong
+        let req = TestRequest::default()
+            .header(header::AUTHORIZATION, "Basic dXNlcjp3cm9uZw==")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        // user:pass
+        let req = TestRequest::default()
+            .header(header::AUTHORIZATION, "Basic dXNlcjpwYXNz")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[crate::rt_test]
+    async fn test_bearer() {
+        let srv = init_service(
+            App::new()
+                .wrap(HttpAuth::bearer(|auth: BearerAuth| async move {
+                    auth.token() == "secret"
+                }))
+                .route("/", web::get().to(|| async { "ok" })),
+        )
+        .await;
+
+        let req = TestRequest::default()
+            .header(header::AUTHORIZATION, "Bearer wrong")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            res.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+            "Bearer"
+        );
+
+        let req = TestRequest::default()
+            .header(header::AUTHORIZATION, "Bearer secret")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,755 @@
+//! Middleware for request authentication
+use std::{cell::RefCell, fmt, rc::Rc, str::FromStr, time::UNIX_EPOCH};
+
+use coo_kie::{time, Cookie, CookieJar, Key, SameSite};
+use thiserror::Error;
+
+use crate::http::header::{self, HeaderValue};
+use crate::http::{HttpMessage, Payload, Response};
+use crate::service::{Middleware, Service, ServiceCtx};
+use crate::time::{system_time, Millis};
+use crate::web::types::{AuthScheme, BearerAuth};
+use crate::web::{ErrorRenderer, FromRequest, HttpRequest, WebRequest, WebResponse};
+
+/// Identity error
+#[derive(Error, Debug)]
+pub enum IdentityError {
+    /// Request is not authenticated
+    #[error("Request is not authenticated")]
+    Unauthorized,
+    /// Identity policy error
+    #[error("Identity policy error: {0}")]
+    Policy(Box<dyn std::error::Error>),
+}
+
+#[allow(async_fn_in_trait)]
+/// Identity policy
+///
+/// Policy extracts identity from the request and stores identity
+/// changes made by `Identity::login()` and `Identity::logout()`.
+pub trait IdentityPolicy: 'static {
+    /// Load identity from request
+    ///
+    /// Returns `None` for anonymous requests.
+    async fn load<E>(&self, req: &WebRequest<E>) -> Result<Option<String>, IdentityError>;
+
+    /// Store identity changes to response
+    ///
+    /// `id` is `None` if identity is logged out.
+    async fn store(
+        &self,
+        id: Option<&str>,
+        res: &mut WebResponse,
+    ) -> Result<(), IdentityError>;
+}
+
+/// Identity policy which keeps identity in encrypted cookie
+///
+/// By default cookie name is `identity`, cookie expires when browser
+/// session ends. Cookie keeps login time, identity expires in 24 hours
+/// after login regardless of cookie expiration.
+pub struct CookieIdentityPolicy {
+    key: Key,
+    name: String,
+    path: String,
+    domain: Option<String>,
+    secure: bool,
+    http_only: bool,
+    same_site: SameSite,
+    max_age: Option<Millis>,
+    lifetime: Millis,
+}
+
+impl CookieIdentityPolicy {
+    /// Construct new policy with cookie key
+    pub fn new(key: Key) -> Self {
+        CookieIdentityPolicy {
+            key,
+            name: "identity".to_string(),
+            path: "/".to_string(),
+            domain: None,
+            secure: true,
+            http_only: true,
+            same_site: SameSite::Lax,
+            max_age: None,
+            lifetime: Millis(86_400_000),
+        }
+    }
+
+    /// Set identity cookie name
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Set identity cookie path, by default it is `/`
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = path.to_string();
+        self
+    }
+
+    /// Set identity cookie domain
+    pub fn domain(mut self, domain: &str) -> Self {
+        self.domain = Some(domain.to_string());
+        self
+    }
+
+    /// Set `Secure` cookie attribute, enabled by default
+    pub fn secure(mut self, value: bool) -> Self {
+        self.secure = value;
+        self
+    }
+
+    /// Set `HttpOnly` cookie attribute, enabled by default
+    pub fn http_only(mut self, value: bool) -> Self {
+        self.http_only = value;
+        self
+    }
+
+    /// Set `SameSite` cookie attribute, by default it is `Lax`
+    pub fn same_site(mut self, value: SameSite) -> Self {
+        self.same_site = value;
+        self
+    }
+
+    /// Set identity cookie max-age
+    pub fn max_age(mut self, value: Millis) -> Self {
+        self.max_age = Some(value);
+        self
+    }
+
+    /// Set identity lifetime, by default it is 24 hours
+    ///
+    /// Identity is rejected if it is older than lifetime, even if
+    /// client keeps the cookie.
+    pub fn lifetime(mut self, value: Millis) -> Self {
+        self.lifetime = value;
+        self
+    }
+
+    fn cookie(&self, value: String) -> Cookie<'static> {
+        let mut cookie = Cookie::build((self.name.clone(), value))
+            .path(self.path.clone())
+            .secure(self.secure)
+            .http_only(self.http_only)
+            .same_site(self.same_site)
+            .build();
+        if let Some(ref domain) = self.domain {
+            cookie.set_domain(domain.clone());
+        }
+        if let Some(max_age) = self.max_age {
+            cookie.set_max_age(time::Duration::milliseconds(max_age.0 as i64));
+        }
+        cookie
+    }
+}
+
+impl fmt::Debug for CookieIdentityPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CookieIdentityPolicy")
+            .field("name", &self.name)
+            .field("path", &self.path)
+            .field("domain", &self.domain)
+            .field("max_age", &self.max_age)
+            .field("lifetime", &self.lifetime)
+            .finish()
+    }
+}
+
+/// Seconds since unix epoch
+fn timestamp() -> u64 {
+    system_time()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+impl IdentityPolicy for CookieIdentityPolicy {
+    async fn load<E>(&self, req: &WebRequest<E>) -> Result<Option<String>, IdentityError> {
+        if let Some(cookie) = req.cookie(&self.name) {
+            let mut jar = CookieJar::new();
+            jar.add_original(cookie);
+            Ok(jar.private(&self.key).get(&self.name).and_then(|c| {
+                // cookie value is `issued-at:identity`
+                let (issued, id) = c.value().split_once(':')?;
+                let issued = issued.parse::<u64>().ok()?;
+                if timestamp().saturating_sub(issued) * 1000 < self.lifetime.0 as u64 {
+                    Some(id.to_string())
+                } else {
+                    None
+                }
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn store(
+        &self,
+        id: Option<&str>,
+        res: &mut WebResponse,
+    ) -> Result<(), IdentityError> {
+        if let Some(id) = id {
+            let mut jar = CookieJar::new();
+            jar.private_mut(&self.key)
+                .add(self.cookie(format!("{}:{}", timestamp(), id)));
+            for cookie in jar.delta() {
+                let _ = res.response_mut().add_cookie(cookie.clone());
+            }
+        } else {
+            let mut cookie = self.cookie(String::new());
+            cookie.make_removal();
+            let _ = res.response_mut().add_cookie(cookie);
+        }
+        Ok(())
+    }
+}
+
+/// Identity policy for `Authorization: Bearer` tokens
+///
+/// Token is validated by provided function, function returns identity
+/// for valid tokens. Tokens are issued by the application, so
+/// `Identity::login()` and `Identity::logout()` do not affect responses.
+pub struct BearerIdentityPolicy<F> {
+    f: F,
+}
+
+impl<F> BearerIdentityPolicy<F>
+where
+    F: Fn(&str) -> Option<String> + 'static,
+{
+    /// Construct new policy with token validation function
+    pub fn new(f: F) -> Self {
+        BearerIdentityPolicy { f }
+    }
+}
+
+impl<F> fmt::Debug for BearerIdentityPolicy<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BearerIdentityPolicy").finish()
+    }
+}
+
+impl<F> IdentityPolicy for BearerIdentityPolicy<F>
+where
+    F: Fn(&str) -> Option<String> + 'static,
+{
+    async fn load<E>(&self, req: &WebRequest<E>) -> Result<Option<String>, IdentityError> {
+        Ok(req
+            .headers()
+            .get(&header::AUTHORIZATION)
+            .and_then(|val| val.to_str().ok())
+            .and_then(BearerAuth::parse)
+            .and_then(|auth| (self.f)(auth.token())))
+    }
+
+    async fn store(
+        &self,
+        _: Option<&str>,
+        _: &mut WebResponse,
+    ) -> Result<(), IdentityError> {
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct IdentityInner {
+    id: Option<String>,
+    changed: bool,
+}
+
+/// Request identity
+///
+/// `Identity` could be used as request extractor, extractor returns
+/// identity loaded by `Identities` middleware. If middleware is not
+/// configured, request is anonymous and changes are not stored.
+///
+/// ```rust
+/// use ntex::web::{self, middleware::Identity, HttpResponse};
+///
+/// async fn login(id: Identity) -> HttpResponse {
+///     id.login("user1");
+///     HttpResponse::Ok().finish()
+/// }
+///
+/// async fn logout(id: Identity) -> HttpResponse {
+///     id.logout();
+///     HttpResponse::Ok().finish()
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Identity(Rc<RefCell<IdentityInner>>);
+
+impl Identity {
+    fn new(id: Option<String>) -> Self {
+        Identity(Rc::new(RefCell::new(IdentityInner { id, changed: false })))
+    }
+
+    /// Identity of the request, `None` for anonymous requests
+    pub fn id(&self) -> Option<String> {
+        self.0.borrow().id.clone()
+    }
+
+    /// Check if request is authenticated
+    pub fn is_authenticated(&self) -> bool {
+        self.0.borrow().id.is_some()
+    }
+
+    /// Remember identity
+    pub fn login<T: Into<String>>(&self, id: T) {
+        let mut inner = self.0.borrow_mut();
+        inner.id = Some(id.into());
+        inner.changed = true;
+    }
+
+    /// Forget identity
+    pub fn logout(&self) {
+        let mut inner = self.0.borrow_mut();
+        inner.id = None;
+        inner.changed = true;
+    }
+
+    fn changed(&self) -> bool {
+        self.0.borrow().changed
+    }
+}
+
+impl fmt::Debug for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.0.borrow();
+        f.debug_struct("Identity")
+            .field("id", &inner.id)
+            .field("changed", &inner.changed)
+            .finish()
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for Identity {
+    type Error = Err::Container;
+
+    #[inline]
+    async fn from_request(req: &HttpRequest, _: &mut Payload) -> Result<Self, Self::Error> {
+        Ok(req
+            .extensions()
+            .get::<Identity>()
+            .cloned()
+            .unwrap_or_else(|| Identity::new(None)))
+    }
+}
+
+/// Authenticated user extractor
+///
+/// Extractor parses request identity into `T`, anonymous requests
+/// and identities that cannot be parsed are rejected with
+/// `401 Unauthorized` response.
+///
+/// ```rust
+/// use ntex::web::{self, middleware::AuthUser};
+///
+/// async fn index(user: AuthUser<u64>) -> String {
+///     format!("User id: {}", user.into_inner())
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthUser<T>(pub T);
+
+impl<T> AuthUser<T> {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> std::ops::Deref for AuthUser<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: FromStr, Err: ErrorRenderer> FromRequest<Err> for AuthUser<T> {
+    type Error = IdentityError;
+
+    async fn from_request(req: &HttpRequest, _: &mut Payload) -> Result<Self, Self::Error> {
+        req.extensions()
+            .get::<Identity>()
+            .and_then(|id| id.0.borrow().id.as_deref().and_then(|id| id.parse().ok()))
+            .map(AuthUser)
+            .ok_or(IdentityError::Unauthorized)
+    }
+}
+
+/// `Middleware` for request authentication.
+///
+/// Middleware loads request identity with configured policy and makes
+/// it available via `Identity` and `AuthUser` extractors. Identity
+/// changes are stored with the policy after request is handled. Policy
+/// errors produce `500 Internal Server Error` response.
+///
+/// ```rust
+/// use coo_kie::Key;
+/// use ntex::web::{self, middleware, App};
+///
+/// fn main() {
+///     let key = Key::generate();
+///
+///     let app = App::new()
+///         .wrap(middleware::Identities::new(
+///             middleware::CookieIdentityPolicy::new(key.clone()).name("auth"),
+///         ))
+///         .service(
+///             web::scope("/admin")
+///                 .wrap(middleware::RequireIdentity::redirect("/login"))
+///                 .route("/", web::get().to(|| async { "admin" })),
+///         );
+/// }
+/// ```
+pub struct Identities<P> {
+    policy: Rc<P>,
+}
+
+impl<P: IdentityPolicy> Identities<P> {
+    /// Construct `Identities` middleware with identity policy
+    pub fn new(policy: P) -> Self {
+        Identities {
+            policy: Rc::new(policy),
+        }
+    }
+}
+
+impl<P> Clone for Identities<P> {
+    fn clone(&self) -> Self {
+        Identities {
+            policy: self.policy.clone(),
+        }
+    }
+}
+
+impl<P> fmt::Debug for Identities<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Identities").finish()
+    }
+}
+
+impl<S, P> Middleware<S> for Identities<P> {
+    type Service = IdentitiesMiddleware<S, P>;
+
+    fn create(&self, service: S) -> Self::Service {
+        IdentitiesMiddleware {
+            service,
+            policy: self.policy.clone(),
+        }
+    }
+}
+
+pub struct IdentitiesMiddleware<S, P> {
+    service: S,
+    policy: Rc<P>,
+}
+
+impl<S, P> fmt::Debug for IdentitiesMiddleware<S, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IdentitiesMiddleware").finish()
+    }
+}
+
+impl<S, P, E> Service<WebRequest<E>> for IdentitiesMiddleware<S, P>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+    P: IdentityPolicy,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    crate::forward_poll_ready!(service);
+    crate::forward_poll_shutdown!(service);
+
+    async fn call(
+        &self,
+        req: WebRequest<E>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        let id = match self.policy.load(&req).await {
+            Ok(id) => id,
+            Err(e) => {
+                log::error!("Cannot load identity: {}", e);
+                return Ok(req.into_response(Response::InternalServerError().finish()));
+            }
+        };
+
+        let identity = Identity::new(id);
+        req.extensions_mut().insert(identity.clone());
+
+        let mut res = ctx.call(&self.service, req).await?;
+        if identity.changed() {
+            let id = identity.id();
+            if let Err(e) = self.policy.store(id.as_deref(), &mut res).await {
+                log::error!("Cannot store identity: {}", e);
+                return Ok(res.into_response(Response::InternalServerError().finish()));
+            }
+        }
+        Ok(res)
+    }
+}
+
+/// `Middleware` which rejects anonymous requests.
+///
+/// Identity is loaded by `Identities` middleware, which must be
+/// registered on the outer level. Anonymous requests get
+/// `401 Unauthorized` response or are redirected to login page.
+/// Middleware could be registered per scope or per resource.
+#[derive(Debug, Clone, Default)]
+pub struct RequireIdentity {
+    redirect: Option<HeaderValue>,
+}
+
+impl RequireIdentity {
+    /// Reject anonymous requests with `401 Unauthorized` response
+    pub fn new() -> Self {
+        RequireIdentity { redirect: None }
+    }
+
+    /// Redirect anonymous requests to specified location
+    ///
+    /// ## Panics
+    ///
+    /// Panics if location is not valid header value.
+    pub fn redirect(location: &str) -> Self {
+        RequireIdentity {
+            redirect: Some(HeaderValue::from_str(location).expect("Invalid location")),
+        }
+    }
+}
+
+impl<S> Middleware<S> for RequireIdentity {
+    type Service = RequireIdentityMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        RequireIdentityMiddleware {
+            service,
+            redirect: self.redirect.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RequireIdentityMiddleware<S> {
+    service: S,
+    redirect: Option<HeaderValue>,
+}
+
+impl<S, E> Service<WebRequest<E>> for RequireIdentityMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    crate::forward_poll_ready!(service);
+    crate::forward_poll_shutdown!(service);
+
+    async fn call(
+        &self,
+        req: WebRequest<E>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        let authenticated = req
+            .extensions()
+            .get::<Identity>()
+            .map(|id| id.is_authenticated())
+            .unwrap_or(false);
+
+        if authenticated {
+            ctx.call(&self.service, req).await
+        } else if let Some(ref location) = self.redirect {
+            Ok(req.into_response(
+                Response::Found()
+                    .header(header::LOCATION, location.clone())
+                    .finish(),
+            ))
+        } else {
+            Ok(req.into_response(Response::Unauthorized().finish()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::util::Bytes;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, HttpResponse};
+
+    fn identity_cookie(res: &WebResponse) -> Option<Cookie<'static>> {
+        res.response()
+            .cookies()
+            .find(|c| c.name() == "identity")
+            .map(|c| c.into_owned())
+    }
+
+    #[crate::rt_test]
+    async fn test_cookie_policy() {
+        let srv = init_service(
+            App::new()
+                .wrap(Identities::new(CookieIdentityPolicy::new(Key::generate())))
+                .route(
+                    "/login",
+                    web::get().to(|id: Identity| async move {
+                        id.login("10");
+                        HttpResponse::Ok()
+                    }),
+                )
+                .route(
+                    "/logout",
+                    web::get().to(|id: Identity| async move {
+                        id.logout();
+                        HttpResponse::Ok()
+                    }),
+                )
+                .route(
+                    "/",
+                    web::get()
+                        .to(|id: Identity| async move { id.id().unwrap_or_default() }),
+                )
+                .service(
+                    web::scope("/user")
+                        .wrap(RequireIdentity::redirect("/login"))
+                        .route(
+                            "/",
+                            web::get().to(|user: AuthUser<u64>| async move {
+                                format!("{}", user.into_inner())
+                            }),
+                        ),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/user/").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::FOUND);
+        assert_eq!(res.headers().get(header::LOCATION).unwrap(), "/login");
+
+        let req = TestRequest::with_uri("/login").to_request();
+        let res = call_service(&srv, req).await;
+        let cookie = identity_cookie(&res).unwrap();
+        assert!(cookie.http_only().unwrap());
+        assert_ne!(cookie.value(), "10");
+
+        let req = TestRequest::default().cookie(cookie.clone()).to_request();
+        let res = call_service(&srv, req).await;
+        assert!(identity_cookie(&res).is_none());
+        assert_eq!(read_body(res).await, Bytes::from_static(b"10"));
+
+        let req = TestRequest::with_uri("/user/")
+            .cookie(cookie.clone())
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, Bytes::from_static(b"10"));
+
+        // tampered cookie
+        let req = TestRequest::default()
+            .cookie(Cookie::new("identity", "10"))
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, Bytes::new());
+
+        let req = TestRequest::with_uri("/logout").cookie(cookie).to_request();
+        let res = call_service(&srv, req).await;
+        let cookie = identity_cookie(&res).unwrap();
+        assert_eq!(cookie.value(), "");
+    }
+
+    #[crate::rt_test]
+    async fn test_cookie_lifetime() {
+        let key = Key::generate();
+        let app = |lifetime| {
+            App::new()
+                .wrap(Identities::new(
+                    CookieIdentityPolicy::new(key.clone()).lifetime(lifetime),
+                ))
+                .route(
+                    "/login",
+                    web::get().to(|id: Identity| async move {
+                        id.login("10");
+                        HttpResponse::Ok()
+                    }),
+                )
+                .route(
+                    "/",
+                    web::get()
+                        .to(|id: Identity| async move { id.id().unwrap_or_default() }),
+                )
+        };
+
+        let srv = init_service(app(Millis(60_000))).await;
+        let req = TestRequest::with_uri("/login").to_request();
+        let cookie = identity_cookie(&call_service(&srv, req).await).unwrap();
+        let req = TestRequest::default().cookie(cookie.clone()).to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, Bytes::from_static(b"10"));
+
+        // expired identity
+        let srv = init_service(app(Millis(0))).await;
+        let req = TestRequest::default().cookie(cookie).to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, Bytes::new());
+
+        // identity without login time
+        let mut jar = CookieJar::new();
+        jar.private_mut(&key).add(Cookie::new("identity", "10"));
+        let cookie = jar.delta().next().unwrap().clone();
+        let srv = init_service(app(Millis(60_000))).await;
+        let req = TestRequest::default().cookie(cookie).to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, Bytes::new());
+    }
+
+    #[crate::rt_test]
+    async fn test_bearer_policy() {
+        let srv = init_service(
+            App::new()
+                .wrap(Identities::new(BearerIdentityPolicy::new(|token| {
+                    if token == "secret" {
+                        Some("user1".to_string())
+                    } else {
+                        None
+                    }
+                })))
+                .service(web::scope("/api").wrap(RequireIdentity::new()).route(
+                    "/",
+                    web::get().to(|user: AuthUser<String>| async move { user.0 }),
+                ))
+                .route(
+                    "/num",
+                    web::get().to(|user: AuthUser<u64>| async move { user.to_string() }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/api/").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let req = TestRequest::with_uri("/api/")
+            .header(header::AUTHORIZATION, "Bearer wrong")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let req = TestRequest::with_uri("/api/")
+            .header(header::AUTHORIZATION, "Bearer secret")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(read_body(res).await, Bytes::from_static(b"user1"));
+
+        // identity cannot be parsed
+        let req = TestRequest::with_uri("/num")
+            .header(header::AUTHORIZATION, "bearer secret")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+}
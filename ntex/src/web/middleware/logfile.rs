@@ -0,0 +1,263 @@
+//! Access log file sink
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::{fmt, fs, mem, time::Duration, time::Instant};
+
+use crate::rt::spawn_blocking;
+use crate::time::Seconds;
+
+/// Default size of pending log records buffer
+const MAX_BUFFER: usize = 1_048_576;
+
+/// Access log file with rotation support.
+///
+/// Log records are buffered in memory and written to the file on blocking
+/// threads pool, so request processing never waits for disk io. Records
+/// are dropped if buffer overflows.
+///
+/// File could be rotated by size or by time. On rotation current file is
+/// renamed to `<path>.1`, previously rotated files get shifted and the
+/// oldest one is removed.
+///
+/// `LogFile` could be cloned and shared between workers.
+///
+/// ```rust,no_run
+/// use ntex::web::{self, App, middleware::{Logger, LogFile}};
+///
+/// #[ntex::main]
+/// async fn main() -> std::io::Result<()> {
+///     let log = LogFile::new("/var/log/app/access.log")
+///         .max_size(64 * 1024 * 1024)
+///         .max_files(10);
+///
+///     web::server(move || {
+///         App::new().wrap(Logger::default().file(log.clone()))
+///     })
+///     .bind("127.0.0.1:8080")?
+///     .run()
+///     .await
+/// }
+/// ```
+#[derive(Clone)]
+pub struct LogFile {
+    inner: Arc<Mutex<Inner>>,
+}
+
+struct Inner {
+    buf: Vec<u8>,
+    max_buffer: usize,
+    in_flight: bool,
+    writer: Option<Writer>,
+}
+
+struct Writer {
+    path: PathBuf,
+    file: Option<fs::File>,
+    size: u64,
+    opened: Instant,
+    max_size: u64,
+    interval: Seconds,
+    max_files: usize,
+}
+
+impl LogFile {
+    /// Create log file sink for the path.
+    ///
+    /// File is opened in append mode on first write. By default file
+    /// is not rotated.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        LogFile {
+            inner: Arc::new(Mutex::new(Inner {
+                buf: Vec::new(),
+                max_buffer: MAX_BUFFER,
+                in_flight: false,
+                writer: Some(Writer {
+                    path: path.as_ref().to_path_buf(),
+                    file: None,
+                    size: 0,
+                    opened: Instant::now(),
+                    max_size: 0,
+                    interval: Seconds::ZERO,
+                    max_files: 5,
+                }),
+            })),
+        }
+    }
+
+    /// Rotate file when its size exceeds specified number of bytes.
+    ///
+    /// Zero value disables size based rotation. Disabled by default.
+    pub fn max_size(self, size: u64) -> Self {
+        self.with_writer(|w| w.max_size = size);
+        self
+    }
+
+    /// Rotate file when it is older than specified interval.
+    ///
+    /// Zero value disables time based rotation. Disabled by default.
+    pub fn rotate_interval(self, interval: Seconds) -> Self {
+        self.with_writer(|w| w.interval = interval);
+        self
+    }
+
+    /// Number of rotated files to keep.
+    ///
+    /// By default 5 files are kept.
+    pub fn max_files(self, num: usize) -> Self {
+        self.with_writer(|w| w.max_files = num.max(1));
+        self
+    }
+
+    /// Max size of pending records buffer.
+    ///
+    /// New records are dropped if buffer is full. By default buffer size is 1Mb.
+    pub fn max_buffer(self, size: usize) -> Self {
+        self.inner.lock().unwrap().max_buffer = size;
+        self
+    }
+
+    fn with_writer<F: FnOnce(&mut Writer)>(&self, f: F) {
+        if let Some(ref mut w) = self.inner.lock().unwrap().writer {
+            f(w)
+        }
+    }
+
+    /// Write log record, new line is appended to the record.
+    pub(super) fn write(&self, record: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.buf.len() + record.len() > inner.max_buffer {
+            log::warn!("Access log buffer is full, dropping record");
+            return;
+        }
+        inner.buf.extend_from_slice(record.as_bytes());
+        inner.buf.push(b'\n');
+
+        if !inner.in_flight {
+            inner.in_flight = true;
+            let buf = mem::take(&mut inner.buf);
+            let writer = inner.writer.take().unwrap();
+            drop(inner);
+
+            let state = self.inner.clone();
+            drop(spawn_blocking(move || flush(state, writer, buf)));
+        }
+    }
+}
+
+/// Write pending records until buffer is empty
+fn flush(state: Arc<Mutex<Inner>>, mut writer: Writer, mut buf: Vec<u8>) {
+    loop {
+        if let Err(e) = writer.write(&buf) {
+            log::error!("Cannot write access log {:?}: {}", writer.path, e);
+            writer.file = None;
+        }
+
+        let mut inner = state.lock().unwrap();
+        if inner.buf.is_empty() {
+            inner.in_flight = false;
+            inner.writer = Some(writer);
+            return;
+        }
+        buf.clear();
+        mem::swap(&mut buf, &mut inner.buf);
+    }
+}
+
+impl Writer {
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        if self.file.is_some() && self.need_rotation(data.len()) {
+            self.file = None;
+            self.rotate()?;
+        }
+
+        let file = if let Some(ref mut file) = self.file {
+            file
+        } else {
+            let file = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+            self.size = file.metadata()?.len();
+            self.opened = Instant::now();
+            self.file.insert(file)
+        };
+        file.write_all(data)?;
+        self.size += data.len() as u64;
+        Ok(())
+    }
+
+    fn need_rotation(&self, len: usize) -> bool {
+        (self.max_size != 0 && self.size != 0 && self.size + len as u64 > self.max_size)
+            || (!self.interval.is_zero()
+                && self.opened.elapsed() >= Duration::from(self.interval))
+    }
+
+    fn rotate(&self) -> io::Result<()> {
+        let _ = fs::remove_file(self.rotated(self.max_files));
+        for idx in (1..self.max_files).rev() {
+            let from = self.rotated(idx);
+            if from.exists() {
+                fs::rename(from, self.rotated(idx + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated(1))
+    }
+
+    fn rotated(&self, idx: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", idx));
+        path.into()
+    }
+}
+
+impl fmt::Debug for LogFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LogFile").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::{sleep, Millis};
+
+    async fn wait(path: &Path, expected: &str) -> bool {
+        for _ in 0..100 {
+            if fs::read_to_string(path)
+                .map(|s| s == expected)
+                .unwrap_or(false)
+            {
+                return true;
+            }
+            sleep(Millis(10)).await;
+        }
+        false
+    }
+
+    #[crate::rt_test]
+    async fn test_log_file() {
+        let dir = std::env::temp_dir().join(format!("ntex-log-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("access.log");
+
+        let log = LogFile::new(&path).max_size(10).max_files(2);
+        log.write("record1");
+        assert!(wait(&path, "record1\n").await);
+
+        log.write("record2");
+        assert!(wait(&path, "record2\n").await);
+        assert!(wait(&path.with_extension("log.1"), "record1\n").await);
+
+        log.write("record3");
+        assert!(wait(&path, "record3\n").await);
+        log.write("record4");
+        assert!(wait(&path, "record4\n").await);
+        assert!(wait(&path.with_extension("log.1"), "record3\n").await);
+        assert!(wait(&path.with_extension("log.2"), "record2\n").await);
+        assert!(!path.with_extension("log.3").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
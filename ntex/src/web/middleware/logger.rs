@@ -1,15 +1,19 @@
 //! Request logging middleware
 use std::task::{Context, Poll};
-use std::{env, error::Error, fmt, fmt::Display, rc::Rc, time};
+use std::{cell::RefCell, env, error::Error, fmt, fmt::Display, fmt::Write, rc::Rc, time};
 
+use nanorand::{Rng, WyRand};
 use regex::Regex;
 
+use crate::channel::mpsc;
 use crate::http::body::{Body, BodySize, MessageBody, ResponseBody};
 use crate::http::header::HeaderName;
 use crate::service::{Middleware, Service, ServiceCtx};
-use crate::util::{Bytes, HashSet};
+use crate::util::{Bytes, Extensions, HashMap, HashSet};
 use crate::web::{HttpResponse, WebRequest, WebResponse};
 
+use super::LogFile;
+
 /// `Middleware` for logging request and response info to the terminal.
 ///
 /// `Logger` middleware uses standard log crate to log information. You should
@@ -58,6 +62,8 @@ use crate::web::{HttpResponse, WebRequest, WebResponse};
 ///
 /// `%D`  Time taken to serve the request, in milliseconds
 ///
+/// `%F`  Time to first byte of response body, in milliseconds
+///
 /// `%U`  Request URL
 ///
 /// `%{FOO}i`  request.headers['FOO']
@@ -66,24 +72,95 @@ use crate::web::{HttpResponse, WebRequest, WebResponse};
 ///
 /// `%{FOO}e`  os.environ['FOO']
 ///
+/// `%{FOO}x`  Custom field, see [`Logger::field()`]
+///
+/// Request time (`%T` and `%D`) is measured until response body is
+/// completely sent, time to first byte (`%F`) is measured until first
+/// chunk of the body is ready.
+///
+/// ## JSON
+///
+/// In json mode ([`Logger::json()`]) each record is a json object, literal
+/// text of the format is ignored and every field is rendered as a key:
+///
+/// | Field     | Key            |
+/// |-----------|----------------|
+/// | `%a`      | `remote_addr`  |
+/// | `%t`      | `request_time` |
+/// | `%r`      | `request`      |
+/// | `%s`      | `status`       |
+/// | `%b`      | `size`         |
+/// | `%T`      | `time`         |
+/// | `%D`      | `time_ms`      |
+/// | `%F`      | `ttfb_ms`      |
+/// | `%U`      | `path`         |
+/// | `%{FOO}i` | `req_foo`      |
+/// | `%{FOO}o` | `resp_foo`     |
+/// | `%{FOO}e` | `env_FOO`      |
+/// | `%{FOO}x` | `FOO`          |
+///
+/// Missing values are rendered as `null`.
+///
+/// ## Sink
+///
+/// By default records are written to the log crate. Access log could be
+/// written directly to the file with rotation support, see [`Logger::file()`]
+/// and [`LogFile`], or to any other [`LogSink`], see [`Logger::sink()`].
+///
 #[derive(Debug)]
 pub struct Logger {
     inner: Rc<Inner>,
 }
 
-#[derive(Debug)]
+/// Destination for access log records
+///
+/// Sink is created per worker, if records from all workers should go to
+/// the same destination sink must be cloneable.
+pub trait LogSink: 'static {
+    /// Write rendered log record
+    fn write(&self, record: &str);
+}
+
+impl LogSink for LogFile {
+    fn write(&self, record: &str) {
+        LogFile::write(self, record)
+    }
+}
+
+impl LogSink for mpsc::Sender<String> {
+    fn write(&self, record: &str) {
+        if self.send(record.to_string()).is_err() {
+            log::trace!("Access log receiver is gone, dropping record");
+        }
+    }
+}
+
+type FieldFn = Box<dyn Fn(&Extensions) -> Option<String>>;
+
 struct Inner {
     format: Format,
     exclude: HashSet<String>,
+    fields: HashMap<String, FieldFn>,
+    sink: Option<Rc<dyn LogSink>>,
+    json: bool,
+    sample: f64,
 }
 
 impl Logger {
     /// Create `Logger` middleware with the specified `format`.
     pub fn new(format: &str) -> Logger {
+        Logger::with_format(Format::new(format))
+    }
+
+    fn with_format(format: Format) -> Logger {
         Logger {
             inner: Rc::new(Inner {
-                format: Format::new(format),
+                format,
                 exclude: HashSet::default(),
+                fields: HashMap::default(),
+                sink: None,
+                json: false,
+                sample: 1.0,
             }),
         }
     }
@@ -96,6 +173,68 @@ impl Logger {
             .insert(path.into());
         self
     }
+
+    /// Register custom field for `%{name}x` format.
+    ///
+    /// Field value is computed from request extensions after response
+    /// is ready, so values set by handlers are available. If closure
+    /// returns `None` or field is not registered, `-` is logged.
+    ///
+    /// ```rust
+    /// use ntex::web::middleware::Logger;
+    ///
+    /// struct UserId(u64);
+    ///
+    /// let logger = Logger::new("%a %r %s user=%{user}x")
+    ///     .field("user", |ext| ext.get::<UserId>().map(|id| id.0.to_string()));
+    /// ```
+    pub fn field<F>(mut self, name: &str, f: F) -> Self
+    where
+        F: Fn(&Extensions) -> Option<String> + 'static,
+    {
+        Rc::get_mut(&mut self.inner)
+            .unwrap()
+            .fields
+            .insert(name.to_string(), Box::new(f));
+        self
+    }
+
+    /// Render records as json objects.
+    ///
+    /// See [json keys](Logger#json) for details.
+    pub fn json(mut self) -> Self {
+        Rc::get_mut(&mut self.inner).unwrap().json = true;
+        self
+    }
+
+    /// Log only a fraction of requests.
+    ///
+    /// `rate` is a value between `0.0` and `1.0`. Responses with server
+    /// error status are always logged. By default all requests are logged.
+    pub fn sample(mut self, rate: f64) -> Self {
+        Rc::get_mut(&mut self.inner).unwrap().sample = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Write access log to the file instead of log crate.
+    ///
+    /// See [`LogFile`] for rotation options.
+    pub fn file(self, file: LogFile) -> Self {
+        self.sink(file)
+    }
+
+    /// Write access log to the custom sink instead of log crate.
+    ///
+    /// ```rust
+    /// use ntex::{channel::mpsc, web::middleware::Logger};
+    ///
+    /// let (tx, rx) = mpsc::channel::<String>();
+    /// let logger = Logger::default().json().sink(tx);
+    /// ```
+    pub fn sink<T: LogSink>(mut self, sink: T) -> Self {
+        Rc::get_mut(&mut self.inner).unwrap().sink = Some(Rc::new(sink));
+        self
+    }
 }
 
 impl Default for Logger {
@@ -105,12 +244,19 @@ impl Default for Logger {
     /// %a "%r" %s %b "%{Referer}i" "%{User-Agent}i" %T
     /// ```
     fn default() -> Self {
-        Logger {
-            inner: Rc::new(Inner {
-                format: Format::default(),
-                exclude: HashSet::default(),
-            }),
-        }
+        Logger::with_format(Format::default())
+    }
+}
+
+impl fmt::Debug for Inner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Logger")
+            .field("format", &self.format)
+            .field("exclude", &self.exclude)
+            .field("fields", &self.fields.keys())
+            .field("json", &self.json)
+            .field("sample", &self.sample)
+            .finish()
     }
 }
 
@@ -121,17 +267,37 @@ impl<S> Middleware<S> for Logger {
         LoggerMiddleware {
             service,
             inner: self.inner.clone(),
+            rng: RefCell::new(WyRand::new()),
         }
     }
 }
 
-#[derive(Debug)]
 /// Logger middleware
 pub struct LoggerMiddleware<S> {
     inner: Rc<Inner>,
+    rng: RefCell<WyRand>,
     service: S,
 }
 
+impl<S: fmt::Debug> fmt::Debug for LoggerMiddleware<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoggerMiddleware")
+            .field("inner", &self.inner)
+            .field("service", &self.service)
+            .finish()
+    }
+}
+
+impl<S> LoggerMiddleware<S> {
+    fn sampled(&self) -> bool {
+        let rate = self.inner.sample;
+        rate >= 1.0
+            || (rate > 0.0
+                && (self.rng.borrow_mut().generate::<u32>() as f64)
+                    < rate * u32::MAX as f64)
+    }
+}
+
 impl<S, E> Service<WebRequest<E>> for LoggerMiddleware<S>
 where
     S: Service<WebRequest<E>, Response = WebResponse>,
@@ -158,16 +324,26 @@ where
             }
 
             let res = ctx.call(&self.service, req).await?;
+            if !res.status().is_server_error() && !self.sampled() {
+                return Ok(res);
+            }
+
+            let extensions = res.request().extensions();
             for unit in &mut format.0 {
                 unit.render_response(res.response());
+                unit.render_field(&self.inner.fields, &extensions);
             }
+            drop(extensions);
 
             Ok(res.map_body(move |_, body| {
                 ResponseBody::Other(Body::from_message(StreamLog {
                     body,
                     time,
                     format: Some(format),
+                    json: self.inner.json,
+                    sink: self.inner.sink.clone(),
                     size: 0,
+                    ttfb: None,
                 }))
             }))
         }
@@ -177,20 +353,31 @@ where
 struct StreamLog {
     body: ResponseBody<Body>,
     format: Option<Format>,
+    json: bool,
+    sink: Option<Rc<dyn LogSink>>,
     size: usize,
     time: time::SystemTime,
+    ttfb: Option<time::Duration>,
 }
 
 impl Drop for StreamLog {
     fn drop(&mut self) {
         if let Some(ref format) = self.format {
             let render = |fmt: &mut fmt::Formatter<'_>| {
-                for unit in &format.0 {
-                    unit.render(fmt, self.size, self.time)?;
+                if self.json {
+                    format.render_json(fmt, self.size, self.time, self.ttfb)
+                } else {
+                    for unit in &format.0 {
+                        unit.render(fmt, self.size, self.time, self.ttfb)?;
+                    }
+                    Ok(())
                 }
-                Ok(())
             };
-            log::info!("{}", FormatDisplay(&render));
+            if let Some(ref sink) = self.sink {
+                sink.write(&FormatDisplay(&render).to_string());
+            } else {
+                log::info!("{}", FormatDisplay(&render));
+            }
         }
     }
 }
@@ -204,7 +391,11 @@ impl MessageBody for StreamLog {
         &mut self,
         cx: &mut Context<'_>,
     ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
-        match self.body.poll_next_chunk(cx) {
+        let result = self.body.poll_next_chunk(cx);
+        if result.is_ready() && self.ttfb.is_none() {
+            self.ttfb = self.time.elapsed().ok();
+        }
+        match result {
             Poll::Ready(Some(Ok(chunk))) => {
                 self.size += chunk.len();
                 Poll::Ready(Some(Ok(chunk)))
@@ -218,7 +409,14 @@ impl MessageBody for StreamLog {
 /// `FormatText`s concatenated into one line.
 #[derive(Clone, Debug)]
 #[doc(hidden)]
-struct Format(Vec<FormatText>);
+struct Format(Vec<FormatText>, Rc<[Option<JsonKey>]>);
+
+/// Json key of the format unit
+#[derive(Debug)]
+struct JsonKey {
+    name: String,
+    number: bool,
+}
 
 impl Default for Format {
     /// Return the default formatting style for the `Logger`:
@@ -233,7 +431,7 @@ impl Format {
     /// Returns `None` if the format string syntax is incorrect.
     fn new(s: &str) -> Format {
         log::trace!("Access log format: {}", s);
-        let fmt = Regex::new(r"%(\{([A-Za-z0-9\-_]+)\}([ioe])|[atPrUsbTD]?)").unwrap();
+        let fmt = Regex::new(r"%(\{([A-Za-z0-9\-_]+)\}([ioex])|[atPrUsbTDF]?)").unwrap();
 
         let mut idx = 0;
         let mut results = Vec::new();
@@ -254,6 +452,7 @@ impl Format {
                         HeaderName::try_from(key.as_str()).unwrap(),
                     ),
                     "e" => FormatText::EnvironHeader(key.as_str().to_owned()),
+                    "x" => FormatText::Field(key.as_str().to_owned()),
                     _ => unreachable!(),
                 })
             } else {
@@ -268,6 +467,7 @@ impl Format {
                     "U" => FormatText::UrlPath,
                     "T" => FormatText::Time,
                     "D" => FormatText::TimeMillis,
+                    "F" => FormatText::FirstByteMillis,
                     _ => FormatText::Str(m.as_str().to_owned()),
                 });
             }
@@ -276,8 +476,62 @@ impl Format {
             results.push(FormatText::Str(s[idx..].to_owned()));
         }
 
-        Format(results)
+        let keys = results.iter().map(|unit| unit.json_key()).collect();
+        Format(results, keys)
     }
+
+    /// Render units as json object
+    fn render_json(
+        &self,
+        fmt: &mut fmt::Formatter<'_>,
+        size: usize,
+        entry_time: time::SystemTime,
+        ttfb: Option<time::Duration>,
+    ) -> Result<(), fmt::Error> {
+        fmt.write_char('{')?;
+        let mut first = true;
+        let mut buf = String::new();
+        for (unit, key) in self.0.iter().zip(self.1.iter()) {
+            if let Some(key) = key {
+                buf.clear();
+                let render =
+                    |f: &mut fmt::Formatter<'_>| unit.render(f, size, entry_time, ttfb);
+                write!(&mut buf, "{}", FormatDisplay(&render))?;
+
+                if !first {
+                    fmt.write_char(',')?;
+                }
+                first = false;
+                write_json_str(fmt, &key.name)?;
+                fmt.write_char(':')?;
+                if buf == "-" {
+                    fmt.write_str("null")?;
+                } else if key.number {
+                    fmt.write_str(&buf)?;
+                } else {
+                    write_json_str(fmt, &buf)?;
+                }
+            }
+        }
+        fmt.write_char('}')
+    }
+}
+
+/// Write escaped json string
+fn write_json_str(fmt: &mut fmt::Formatter<'_>, s: &str) -> Result<(), fmt::Error> {
+    fmt.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => fmt.write_str("\\\"")?,
+            '\\' => fmt.write_str("\\\\")?,
+            '\n' => fmt.write_str("\\n")?,
+            '\r' => fmt.write_str("\\r")?,
+            '\t' => fmt.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(fmt, "\\u{:04x}", c as u32)?,
+            c => fmt.write_char(c)?,
+        }
+    }
+    fmt.write_char('"')
 }
 
 /// A string of text to be logged. This is either one of the data
@@ -293,19 +547,42 @@ enum FormatText {
     ResponseSize,
     Time,
     TimeMillis,
+    FirstByteMillis,
     RemoteAddr,
     UrlPath,
     RequestHeader(HeaderName),
     ResponseHeader(HeaderName),
     EnvironHeader(String),
+    Field(String),
 }
 
 impl FormatText {
+    fn json_key(&self) -> Option<JsonKey> {
+        let (name, number) = match *self {
+            FormatText::Str(_) | FormatText::Percent => return None,
+            FormatText::RequestLine => ("request".to_string(), false),
+            FormatText::RequestTime => ("request_time".to_string(), false),
+            FormatText::ResponseStatus => ("status".to_string(), true),
+            FormatText::ResponseSize => ("size".to_string(), true),
+            FormatText::Time => ("time".to_string(), true),
+            FormatText::TimeMillis => ("time_ms".to_string(), true),
+            FormatText::FirstByteMillis => ("ttfb_ms".to_string(), true),
+            FormatText::RemoteAddr => ("remote_addr".to_string(), false),
+            FormatText::UrlPath => ("path".to_string(), false),
+            FormatText::RequestHeader(ref name) => (format!("req_{}", name), false),
+            FormatText::ResponseHeader(ref name) => (format!("resp_{}", name), false),
+            FormatText::EnvironHeader(ref name) => (format!("env_{}", name), false),
+            FormatText::Field(ref name) => (name.clone(), false),
+        };
+        Some(JsonKey { name, number })
+    }
+
     fn render(
         &self,
         fmt: &mut fmt::Formatter<'_>,
         size: usize,
         entry_time: time::SystemTime,
+        ttfb: Option<time::Duration>,
     ) -> Result<(), fmt::Error> {
         match *self {
             FormatText::Str(ref string) => fmt.write_str(string),
@@ -321,6 +598,14 @@ impl FormatText {
                 let rt = (rt.as_nanos() as f64) / 1_000_000.0;
                 fmt.write_fmt(format_args!("{:.6}", rt))
             }
+            FormatText::FirstByteMillis => {
+                if let Some(rt) = ttfb {
+                    let rt = (rt.as_nanos() as f64) / 1_000_000.0;
+                    fmt.write_fmt(format_args!("{:.6}", rt))
+                } else {
+                    "-".fmt(fmt)
+                }
+            }
             FormatText::EnvironHeader(ref name) => {
                 if let Ok(val) = env::var(name) {
                     fmt.write_fmt(format_args!("{}", val))
@@ -332,6 +617,13 @@ impl FormatText {
         }
     }
 
+    fn render_field(&mut self, fields: &HashMap<String, FieldFn>, ext: &Extensions) {
+        if let FormatText::Field(ref name) = *self {
+            let val = fields.get(name).and_then(|f| f(ext));
+            *self = FormatText::Str(val.unwrap_or_else(|| "-".to_string()));
+        }
+    }
+
     fn render_response<B>(&mut self, res: &HttpResponse<B>) {
         match *self {
             FormatText::ResponseStatus => {
@@ -480,7 +772,7 @@ mod tests {
 
         let render = |fmt: &mut fmt::Formatter<'_>| {
             for unit in &format.0 {
-                unit.render(fmt, 1024, now)?;
+                unit.render(fmt, 1024, now, None)?;
             }
             Ok(())
         };
@@ -510,7 +802,7 @@ mod tests {
 
         let render = |fmt: &mut fmt::Formatter<'_>| {
             for unit in &format.0 {
-                unit.render(fmt, 1024, now)?;
+                unit.render(fmt, 1024, now, None)?;
             }
             Ok(())
         };
@@ -541,7 +833,7 @@ mod tests {
         let entry_time = time::SystemTime::now();
         let render = |fmt: &mut fmt::Formatter<'_>| {
             for unit in &format.0 {
-                unit.render(fmt, 1024, entry_time)?;
+                unit.render(fmt, 1024, entry_time, None)?;
             }
             Ok(())
         };
@@ -568,11 +860,74 @@ mod tests {
 
         let render = |fmt: &mut fmt::Formatter<'_>| {
             for unit in &format.0 {
-                unit.render(fmt, 1024, now)?;
+                unit.render(fmt, 1024, now, None)?;
             }
             Ok(())
         };
         let s = format!("{}", FormatDisplay(&render));
         assert!(s.contains(&httpdate::HttpDate::from(now).to_string()));
     }
+
+    #[crate::rt_test]
+    async fn test_json_sink() {
+        struct UserId(u64);
+
+        let srv = |req: WebRequest<DefaultError>| async move {
+            req.extensions_mut().insert(UserId(10));
+            Ok::<_, Error>(
+                req.into_response(
+                    HttpResponse::build(StatusCode::OK)
+                        .header("X-Test", "\"ttt\"")
+                        .body("TEST"),
+                ),
+            )
+        };
+        let (tx, rx) = mpsc::channel::<String>();
+        let logger = Logger::new("%r %s %b %{X-Test}o %{Referer}i %{user}x %{none}x %F")
+            .field("user", |ext| ext.get::<UserId>().map(|id| id.0.to_string()))
+            .json()
+            .sink(tx);
+
+        let srv = Pipeline::new(Middleware::create(&logger, srv.into_service()));
+        let req = TestRequest::with_uri("/test?q=1").to_srv_request();
+        let res = srv.call(req).await.unwrap();
+        let body = test::read_body(res).await;
+        assert_eq!(body, Bytes::from_static(b"TEST"));
+
+        let record = rx.recv().await.unwrap();
+        let val: serde_json::Value = serde_json::from_str(&record).unwrap();
+        assert_eq!(val["request"], "GET /test?q=1 HTTP/1.1");
+        assert_eq!(val["status"], 200);
+        assert_eq!(val["size"], 4);
+        assert_eq!(val["resp_x-test"], "\"ttt\"");
+        assert!(val["req_referer"].is_null());
+        assert_eq!(val["user"], "10");
+        assert!(val["none"].is_null());
+        assert!(val["ttfb_ms"].is_number());
+    }
+
+    #[crate::rt_test]
+    async fn test_sample() {
+        let srv = |req: WebRequest<DefaultError>| async move {
+            let status = if req.path() == "/error" {
+                StatusCode::INTERNAL_SERVER_ERROR
+            } else {
+                StatusCode::OK
+            };
+            Ok::<_, Error>(req.into_response(HttpResponse::build(status).finish()))
+        };
+        let (tx, rx) = mpsc::channel::<String>();
+        let logger = Logger::new("%U %s").sample(0.0).sink(tx);
+        let srv = Pipeline::new(Middleware::create(&logger, srv.into_service()));
+
+        let res = srv.call(TestRequest::default().to_srv_request()).await;
+        let _ = test::read_body(res.unwrap()).await;
+        let req = TestRequest::with_uri("/error").to_srv_request();
+        let _ = test::read_body(srv.call(req).await.unwrap()).await;
+
+        drop(srv);
+        drop(logger);
+        assert_eq!(rx.recv().await.unwrap(), "/error 500");
+        assert!(rx.recv().await.is_none());
+    }
 }
@@ -0,0 +1,621 @@
+//! Request metrics middleware
+use std::collections::{BTreeMap, HashSet};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::{fmt, fmt::Write};
+
+use crate::http::body::{BodySize, MessageBody};
+use crate::http::header::{self, HeaderValue};
+use crate::http::Method;
+use crate::router::ResourceDef;
+use crate::service::{fn_service, Middleware, Service, ServiceCtx};
+use crate::time::now;
+use crate::web::dev::{WebServiceConfig, WebServiceFactory};
+use crate::web::{ErrorRenderer, HttpResponse, WebRequest, WebResponse};
+
+/// Default request duration buckets, in seconds
+const DURATION_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Default request and response size buckets, in bytes
+const SIZE_BUCKETS: &[f64] = &[
+    100.0,
+    1_000.0,
+    10_000.0,
+    100_000.0,
+    1_000_000.0,
+    10_000_000.0,
+];
+
+/// Route label for requests without matched route
+const UNMATCHED: &str = "unmatched";
+
+/// Method label for non-standard methods
+const OTHER: &str = "OTHER";
+
+/// `Middleware` for collecting request metrics.
+///
+/// Middleware collects request counts, durations, request and response
+/// sizes labeled by method, route pattern and response status, and number
+/// of requests in flight. Metrics are rendered in Prometheus text exposition
+/// format by [`Metrics::render()`] or by [`Metrics::endpoint()`] service.
+///
+/// Duration is measured until response head is ready, response
+/// size is recorded only for responses with known body size. Requests
+/// without matched route are labeled with `unmatched` route, non-standard
+/// methods are labeled with `OTHER` method.
+///
+/// Metrics could be shared between workers, it must be created outside
+/// of the application factory and cloned into each application. Each
+/// worker records metrics to its own storage, storages are merged on render.
+///
+/// ```rust
+/// use ntex::web::{self, middleware::Metrics, App};
+///
+/// fn main() {
+///     let metrics = Metrics::new().prefix("app").exclude("/metrics");
+///
+///     let app = App::new()
+///         .wrap(metrics.clone())
+///         .service(metrics.endpoint("/metrics"))
+///         .route("/users/{id}", web::get().to(|| async { "user" }));
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Metrics(Arc<Inner>);
+
+struct Inner {
+    prefix: String,
+    duration_buckets: Vec<f64>,
+    size_buckets: Vec<f64>,
+    exclude: HashSet<String>,
+    in_flight: AtomicI64,
+    shards: Mutex<Vec<Arc<Mutex<Shard>>>>,
+}
+
+type Shard = BTreeMap<Labels, Series>;
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Labels {
+    method: &'static str,
+    route: String,
+    status: u16,
+}
+
+#[derive(Clone)]
+struct Series {
+    count: u64,
+    duration: Histogram,
+    request_size: Histogram,
+    response_size: Histogram,
+}
+
+#[derive(Clone)]
+struct Histogram {
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(size: usize) -> Self {
+        Histogram {
+            buckets: vec![0; size],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, bounds: &[f64], val: f64) {
+        for (idx, bound) in bounds.iter().enumerate() {
+            if val <= *bound {
+                self.buckets[idx] += 1;
+            }
+        }
+        self.sum += val;
+        self.count += 1;
+    }
+
+    fn merge(&mut self, other: &Histogram) {
+        for (b, o) in self.buckets.iter_mut().zip(other.buckets.iter()) {
+            *b += *o;
+        }
+        self.sum += other.sum;
+        self.count += other.count;
+    }
+}
+
+impl Series {
+    fn merge(&mut self, other: &Series) {
+        self.count += other.count;
+        self.duration.merge(&other.duration);
+        self.request_size.merge(&other.request_size);
+        self.response_size.merge(&other.response_size);
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Metrics::new()
+    }
+}
+
+impl Metrics {
+    /// Create new metrics registry
+    pub fn new() -> Self {
+        Metrics(Arc::new(Inner {
+            prefix: "ntex".to_string(),
+            duration_buckets: DURATION_BUCKETS.to_vec(),
+            size_buckets: SIZE_BUCKETS.to_vec(),
+            exclude: HashSet::new(),
+            in_flight: AtomicI64::new(0),
+            shards: Mutex::new(Vec::new()),
+        }))
+    }
+
+    fn inner(&mut self) -> &mut Inner {
+        Arc::get_mut(&mut self.0).expect("Multiple copies exist")
+    }
+
+    /// Set metric names prefix, `ntex` by default
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.inner().prefix = prefix.to_string();
+        self
+    }
+
+    /// Set request duration histogram buckets, in seconds
+    pub fn duration_buckets(mut self, buckets: &[f64]) -> Self {
+        let mut buckets = buckets.to_vec();
+        buckets.sort_by(|a, b| a.total_cmp(b));
+        self.inner().duration_buckets = buckets;
+        self
+    }
+
+    /// Set request and response size histogram buckets, in bytes
+    pub fn size_buckets(mut self, buckets: &[f64]) -> Self {
+        let mut buckets = buckets.to_vec();
+        buckets.sort_by(|a, b| a.total_cmp(b));
+        self.inner().size_buckets = buckets;
+        self
+    }
+
+    /// Do not collect metrics for requests with specified path
+    pub fn exclude<T: Into<String>>(mut self, path: T) -> Self {
+        self.inner().exclude.insert(path.into());
+        self
+    }
+
+    /// Create service that renders metrics
+    pub fn endpoint(&self, path: &str) -> MetricsEndpoint {
+        MetricsEndpoint {
+            path: path.to_string(),
+            metrics: self.clone(),
+        }
+    }
+
+    /// Number of requests in flight
+    pub fn in_flight(&self) -> i64 {
+        self.0.in_flight.load(Ordering::Relaxed)
+    }
+
+    fn shard(&self) -> Arc<Mutex<Shard>> {
+        let shard = Arc::new(Mutex::new(BTreeMap::new()));
+        self.0.shards.lock().unwrap().push(shard.clone());
+        shard
+    }
+
+    fn record(
+        &self,
+        shard: &Mutex<Shard>,
+        labels: Labels,
+        duration: f64,
+        req_size: Option<u64>,
+        res_size: Option<u64>,
+    ) {
+        let inner = &self.0;
+        let mut series = shard.lock().unwrap();
+        let series = series.entry(labels).or_insert_with(|| Series {
+            count: 0,
+            duration: Histogram::new(inner.duration_buckets.len()),
+            request_size: Histogram::new(inner.size_buckets.len()),
+            response_size: Histogram::new(inner.size_buckets.len()),
+        });
+        series.count += 1;
+        series.duration.observe(&inner.duration_buckets, duration);
+        if let Some(size) = req_size {
+            series
+                .request_size
+                .observe(&inner.size_buckets, size as f64);
+        }
+        if let Some(size) = res_size {
+            series
+                .response_size
+                .observe(&inner.size_buckets, size as f64);
+        }
+    }
+
+    /// Render metrics in Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let inner = &self.0;
+        let mut series = Shard::new();
+        for shard in inner.shards.lock().unwrap().iter() {
+            for (labels, s) in shard.lock().unwrap().iter() {
+                if let Some(item) = series.get_mut(labels) {
+                    item.merge(s);
+                } else {
+                    series.insert(labels.clone(), s.clone());
+                }
+            }
+        }
+        let prefix = &inner.prefix;
+        let mut buf = String::new();
+
+        let _ = writeln!(
+            buf,
+            "# HELP {0}_http_requests_total Total number of HTTP requests\n\
+             # TYPE {0}_http_requests_total counter",
+            prefix
+        );
+        for (labels, s) in series.iter() {
+            let _ = writeln!(
+                buf,
+                "{}_http_requests_total{{{}}} {}",
+                prefix, labels, s.count
+            );
+        }
+
+        let histograms: [(&str, &str, &[f64], fn(&Series) -> &Histogram); 3] = [
+            (
+                "http_request_duration_seconds",
+                "HTTP request duration in seconds",
+                &inner.duration_buckets,
+                |s| &s.duration,
+            ),
+            (
+                "http_request_size_bytes",
+                "HTTP request body size in bytes",
+                &inner.size_buckets,
+                |s| &s.request_size,
+            ),
+            (
+                "http_response_size_bytes",
+                "HTTP response body size in bytes",
+                &inner.size_buckets,
+                |s| &s.response_size,
+            ),
+        ];
+        for (name, help, bounds, get) in histograms {
+            let _ = writeln!(
+                buf,
+                "# HELP {0}_{1} {2}\n# TYPE {0}_{1} histogram",
+                prefix, name, help
+            );
+            for (labels, s) in series.iter() {
+                let h = get(s);
+                if h.count == 0 {
+                    continue;
+                }
+                for (bound, count) in bounds.iter().zip(h.buckets.iter()) {
+                    let _ = writeln!(
+                        buf,
+                        "{}_{}_bucket{{{},le=\"{}\"}} {}",
+                        prefix, name, labels, bound, count
+                    );
+                }
+                let _ = writeln!(
+                    buf,
+                    "{0}_{1}_bucket{{{2},le=\"+Inf\"}} {3}\n\
+                     {0}_{1}_sum{{{2}}} {4}\n\
+                     {0}_{1}_count{{{2}}} {3}",
+                    prefix, name, labels, h.count, h.sum
+                );
+            }
+        }
+
+        let _ = writeln!(
+            buf,
+            "# HELP {0}_http_requests_in_flight Number of HTTP requests in flight\n\
+             # TYPE {0}_http_requests_in_flight gauge\n\
+             {0}_http_requests_in_flight {1}",
+            prefix,
+            self.in_flight()
+        );
+        buf
+    }
+}
+
+impl fmt::Display for Labels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "method=\"{}\",route=\"{}\",status=\"{}\"",
+            self.method,
+            escape(&self.route),
+            self.status
+        )
+    }
+}
+
+/// Method label, non-standard methods are clamped to `OTHER`
+fn method_label(method: &Method) -> &'static str {
+    match *method {
+        Method::GET => "GET",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::DELETE => "DELETE",
+        Method::HEAD => "HEAD",
+        Method::OPTIONS => "OPTIONS",
+        Method::CONNECT => "CONNECT",
+        Method::PATCH => "PATCH",
+        Method::TRACE => "TRACE",
+        _ => OTHER,
+    }
+}
+
+/// Escape label value
+fn escape(val: &str) -> String {
+    val.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+impl fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metrics")
+            .field("prefix", &self.0.prefix)
+            .field("exclude", &self.0.exclude)
+            .finish()
+    }
+}
+
+impl<S> Middleware<S> for Metrics {
+    type Service = MetricsMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        MetricsMiddleware {
+            service,
+            shard: self.shard(),
+            metrics: self.clone(),
+        }
+    }
+}
+
+pub struct MetricsMiddleware<S> {
+    service: S,
+    metrics: Metrics,
+    shard: Arc<Mutex<Shard>>,
+}
+
+impl<S> fmt::Debug for MetricsMiddleware<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MetricsMiddleware")
+            .field("metrics", &self.metrics)
+            .finish()
+    }
+}
+
+/// Decrements in-flight gauge on drop
+struct InFlight<'a>(&'a AtomicI64);
+
+impl<'a> InFlight<'a> {
+    fn new(val: &'a AtomicI64) -> Self {
+        val.fetch_add(1, Ordering::Relaxed);
+        InFlight(val)
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl<S, E> Service<WebRequest<E>> for MetricsMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    crate::forward_poll_ready!(service);
+    crate::forward_poll_shutdown!(service);
+
+    async fn call(
+        &self,
+        req: WebRequest<E>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        if self.metrics.0.exclude.contains(req.path()) {
+            return ctx.call(&self.service, req).await;
+        }
+
+        let method = method_label(req.method());
+        let req_size = req
+            .headers()
+            .get(&header::CONTENT_LENGTH)
+            .and_then(|val| val.to_str().ok())
+            .and_then(|val| val.parse::<u64>().ok());
+
+        let start = now();
+        let result = {
+            let _guard = InFlight::new(&self.metrics.0.in_flight);
+            ctx.call(&self.service, req).await
+        };
+        let duration = start.elapsed().as_secs_f64();
+
+        let (route, status, res_size) = match result {
+            Ok(ref res) => {
+                let size = match res.response().body().size() {
+                    BodySize::Sized(size) => Some(size),
+                    BodySize::None | BodySize::Empty => Some(0),
+                    BodySize::Stream => None,
+                };
+                (res.request().match_pattern(), res.status().as_u16(), size)
+            }
+            Err(_) => (None, 500, None),
+        };
+        let labels = Labels {
+            method,
+            route: route.unwrap_or_else(|| UNMATCHED.to_string()),
+            status,
+        };
+        self.metrics
+            .record(&self.shard, labels, duration, req_size, res_size);
+
+        result
+    }
+}
+
+/// Service that renders metrics in Prometheus text exposition format
+#[derive(Debug)]
+pub struct MetricsEndpoint {
+    path: String,
+    metrics: Metrics,
+}
+
+impl<Err: ErrorRenderer> WebServiceFactory<Err> for MetricsEndpoint {
+    fn register(self, config: &mut WebServiceConfig<Err>) {
+        let metrics = self.metrics;
+
+        config.register_service(
+            ResourceDef::new(self.path.as_str()),
+            None,
+            fn_service(move |req: WebRequest<Err>| {
+                let res = HttpResponse::Ok()
+                    .header(
+                        header::CONTENT_TYPE,
+                        HeaderValue::from_static(
+                            "text/plain; version=0.0.4; charset=utf-8",
+                        ),
+                    )
+                    .body(metrics.render());
+                async move { Ok(req.into_response(res)) }
+            }),
+            None,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App};
+
+    #[crate::rt_test]
+    async fn test_metrics() {
+        let metrics = Metrics::new()
+            .prefix("test")
+            .duration_buckets(&[10.0, 0.5])
+            .size_buckets(&[10.0])
+            .exclude("/metrics");
+        let srv = init_service(
+            App::new()
+                .wrap(metrics.clone())
+                .service(metrics.endpoint("/metrics"))
+                .route(
+                    "/users/{id}",
+                    web::post().to(|| async { HttpResponse::Ok().body("user data") }),
+                ),
+        )
+        .await;
+
+        for id in ["1", "2"] {
+            let req = TestRequest::post()
+                .uri(&format!("/users/{}", id))
+                .header(header::CONTENT_LENGTH, "100")
+                .to_request();
+            let res = call_service(&srv, req).await;
+            assert_eq!(res.status(), StatusCode::OK);
+        }
+        let req = TestRequest::with_uri("/missing").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        let req = TestRequest::with_uri("/missing")
+            .method(Method::from_bytes(b"PURGE").unwrap())
+            .to_request();
+        let _ = call_service(&srv, req).await;
+
+        let req = TestRequest::with_uri("/metrics").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/plain; version=0.0.4; charset=utf-8"
+        );
+        let body = read_body(res).await;
+        let body = std::str::from_utf8(&body).unwrap();
+
+        let labels = "method=\"POST\",route=\"/users/{id}\",status=\"200\"";
+        assert!(body.contains("# TYPE test_http_requests_total counter"));
+        assert!(body.contains(&format!("test_http_requests_total{{{}}} 2", labels)));
+        assert!(body.contains(
+            "test_http_requests_total{method=\"GET\",route=\"unmatched\",status=\"404\"} 1"
+        ));
+        assert!(body.contains(&format!(
+            "test_http_request_duration_seconds_bucket{{{},le=\"10\"}} 2",
+            labels
+        )));
+        assert!(body.contains(&format!(
+            "test_http_request_duration_seconds_count{{{}}} 2",
+            labels
+        )));
+        assert!(body.contains(&format!(
+            "test_http_request_size_bytes_bucket{{{},le=\"10\"}} 0",
+            labels
+        )));
+        assert!(body.contains(&format!(
+            "test_http_request_size_bytes_sum{{{}}} 200",
+            labels
+        )));
+        assert!(body.contains(&format!(
+            "test_http_response_size_bytes_bucket{{{},le=\"10\"}} 2",
+            labels
+        )));
+        assert!(body.contains(
+            "test_http_requests_total{method=\"OTHER\",route=\"unmatched\",status=\"404\"} 1"
+        ));
+        assert!(!body.contains("PURGE"));
+        assert!(body.contains("test_http_requests_in_flight 0"));
+        assert!(!body.contains("route=\"/metrics\""));
+        assert_eq!(metrics.in_flight(), 0);
+    }
+
+    #[crate::rt_test]
+    async fn test_metrics_shards() {
+        let metrics = Metrics::new();
+        let srv1 = init_service(
+            App::new()
+                .wrap(metrics.clone())
+                .route("/", web::get().to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+        let srv2 = init_service(
+            App::new()
+                .wrap(metrics.clone())
+                .route("/", web::get().to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+        let _ = call_service(&srv1, TestRequest::default().to_request()).await;
+        let _ = call_service(&srv2, TestRequest::default().to_request()).await;
+
+        assert!(metrics.render().contains(
+            "ntex_http_requests_total{method=\"GET\",route=\"/\",status=\"200\"} 2"
+        ));
+    }
+
+    #[test]
+    fn test_escape() {
+        let labels = Labels {
+            method: "GET",
+            route: "/a\"b\\c".to_string(),
+            status: 200,
+        };
+        assert_eq!(
+            labels.to_string(),
+            "method=\"GET\",route=\"/a\\\"b\\\\c\",status=\"200\""
+        );
+    }
+}
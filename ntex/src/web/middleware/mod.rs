@@ -4,9 +4,80 @@
 mod compress;
 #[cfg(feature = "compress")]
 pub use self::compress::Compress;
+#[cfg(feature = "compress")]
+mod decompress;
+#[cfg(feature = "compress")]
+pub use self::decompress::Decompress;
 
+mod logfile;
 mod logger;
-pub use self::logger::Logger;
+pub use self::logfile::LogFile;
+pub use self::logger::{LogSink, Logger};
+
+mod body;
+pub use self::body::{wrap_body_fn, BodyFn, BodyTransform};
 
 mod defaultheaders;
 pub use self::defaultheaders::DefaultHeaders;
+
+mod security;
+pub use self::security::{
+    DefaultSecurityHeaders, CROSS_ORIGIN_OPENER_POLICY, PERMISSIONS_POLICY,
+};
+
+mod flags;
+pub use self::flags::{FeatureFlags, Flags, FlagsProvider, RefreshFlags};
+
+#[cfg(feature = "digest")]
+mod digest;
+#[cfg(feature = "digest")]
+pub use self::digest::{Digest, DigestAlgorithm, CONTENT_DIGEST, CONTENT_MD5};
+
+mod etag;
+pub use self::etag::ETag;
+
+mod normalize;
+pub use self::normalize::{NormalizePath, TrailingSlash};
+
+mod overload;
+pub use self::overload::Overload;
+
+mod metrics;
+pub use self::metrics::{Metrics, MetricsEndpoint};
+
+mod ratelimit;
+pub use self::ratelimit::{
+    MemoryRateLimitBackend, RateLimitAlgorithm, RateLimitBackend, RateLimitQuota,
+    RateLimitStatus, RateLimiter, X_RATELIMIT_LIMIT, X_RATELIMIT_REMAINING,
+    X_RATELIMIT_RESET,
+};
+
+mod requestid;
+pub use self::requestid::{RequestId, RequestIdFormat, RequestIds, X_REQUEST_ID};
+
+mod cors;
+pub use self::cors::Cors;
+
+mod httpauth;
+pub use self::httpauth::HttpAuth;
+
+#[cfg(feature = "session")]
+mod session;
+#[cfg(feature = "session")]
+pub use self::session::{
+    CookieSessionStore, MemorySessionStore, Session, SessionError, SessionState,
+    SessionStatus, SessionStore, Sessions,
+};
+
+#[cfg(feature = "identity")]
+mod identity;
+#[cfg(feature = "identity")]
+pub use self::identity::{
+    AuthUser, BearerIdentityPolicy, CookieIdentityPolicy, Identities, Identity,
+    IdentityError, IdentityPolicy, RequireIdentity,
+};
+
+#[cfg(feature = "tracing")]
+mod trace;
+#[cfg(feature = "tracing")]
+pub use self::trace::{TraceContext, Tracing, TRACEPARENT};
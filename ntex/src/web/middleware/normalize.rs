@@ -0,0 +1,286 @@
+//! Middleware for path normalization
+use std::rc::Rc;
+
+use crate::http::{header, StatusCode, Uri};
+use crate::service::{Middleware, Service, ServiceCtx};
+use crate::web::{HttpResponse, WebRequest, WebResponse};
+
+/// Trailing slash handling mode
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TrailingSlash {
+    /// Remove trailing slash, root path is not changed
+    Trim,
+    /// Append trailing slash if it is missing
+    Always,
+    /// Keep trailing slash as is
+    Ignore,
+}
+
+/// `Middleware` for path normalization.
+///
+/// Middleware merges repeated slashes and adds or removes trailing slash
+/// before request get routed, so `//resource//` could match `/resource`
+/// route. Query string is preserved. By default path is rewritten, if
+/// redirect is enabled, client is redirected to normalized path instead.
+///
+/// Middleware should be registered on application level, with `App::wrap()`.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::NormalizePath::new(middleware::TrailingSlash::Trim))
+///         .route("/test", web::get().to(|| async { "test" }));
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct NormalizePath {
+    inner: Rc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    trailing_slash: TrailingSlash,
+    merge_slashes: bool,
+    redirect: Option<StatusCode>,
+}
+
+impl Default for NormalizePath {
+    fn default() -> Self {
+        NormalizePath::new(TrailingSlash::Trim)
+    }
+}
+
+impl NormalizePath {
+    /// Construct `NormalizePath` middleware with trailing slash mode.
+    pub fn new(trailing_slash: TrailingSlash) -> Self {
+        NormalizePath {
+            inner: Rc::new(Inner {
+                trailing_slash,
+                merge_slashes: true,
+                redirect: None,
+            }),
+        }
+    }
+
+    /// Merge repeated slashes.
+    ///
+    /// By default repeated slashes are merged.
+    pub fn merge_slashes(mut self, merge: bool) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .merge_slashes = merge;
+        self
+    }
+
+    /// Redirect to normalized path instead of rewriting it.
+    ///
+    /// Status code should be one of `301 Moved Permanently`, `302 Found`,
+    /// `307 Temporary Redirect` or `308 Permanent Redirect`, panics otherwise.
+    pub fn redirect(mut self, status: StatusCode) -> Self {
+        assert!(
+            matches!(
+                status,
+                StatusCode::MOVED_PERMANENTLY
+                    | StatusCode::FOUND
+                    | StatusCode::TEMPORARY_REDIRECT
+                    | StatusCode::PERMANENT_REDIRECT
+            ),
+            "Redirection status is expected"
+        );
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .redirect = Some(status);
+        self
+    }
+}
+
+impl<S> Middleware<S> for NormalizePath {
+    type Service = NormalizePathMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        NormalizePathMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct NormalizePathMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for NormalizePathMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    crate::forward_poll_ready!(service);
+    crate::forward_poll_shutdown!(service);
+
+    async fn call(
+        &self,
+        mut req: WebRequest<E>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        if let Some(path) = self.inner.normalize(req.path()) {
+            let path = match req.uri().query() {
+                Some(query) => format!("{}?{}", path, query),
+                None => path,
+            };
+
+            if let Some(status) = self.inner.redirect {
+                let res = HttpResponse::build(status)
+                    .header(header::LOCATION, path)
+                    .finish();
+                return Ok(req.into_response(res));
+            }
+
+            let mut parts = req.uri().clone().into_parts();
+            parts.path_and_query = path.parse().ok();
+            if let Ok(uri) = Uri::from_parts(parts) {
+                req.match_info_mut().set(uri.clone());
+                req.head_mut().uri = uri;
+            }
+        }
+        ctx.call(&self.service, req).await
+    }
+}
+
+impl Inner {
+    /// Returns normalized path, if it differs from original one
+    ///
+    /// Leading slashes are always merged, path starting with `//`
+    /// is a network-path reference and could not be used as location.
+    fn normalize(&self, path: &str) -> Option<String> {
+        let mut result = String::with_capacity(path.len() + 1);
+        for ch in path.chars() {
+            let leading = result.len() == 1;
+            let merge =
+                (ch == '/' && (self.merge_slashes || leading)) || (ch == '\\' && leading);
+            if !(merge && result.ends_with('/')) {
+                result.push(ch);
+            }
+        }
+
+        match self.trailing_slash {
+            TrailingSlash::Trim => {
+                while result.len() > 1 && result.ends_with('/') {
+                    result.pop();
+                }
+            }
+            TrailingSlash::Always => {
+                if !result.ends_with('/') {
+                    result.push('/');
+                }
+            }
+            TrailingSlash::Ignore => (),
+        }
+
+        if result == path {
+            None
+        } else {
+            Some(result)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, HttpRequest};
+
+    #[test]
+    fn test_normalize() {
+        let inner = NormalizePath::new(TrailingSlash::Trim).inner;
+        assert_eq!(inner.normalize("/"), None);
+        assert_eq!(inner.normalize("/test"), None);
+        assert_eq!(inner.normalize("//"), Some("/".to_string()));
+        assert_eq!(
+            inner.normalize("//test//v1//"),
+            Some("/test/v1".to_string())
+        );
+
+        let inner = NormalizePath::new(TrailingSlash::Always).inner;
+        assert_eq!(inner.normalize("/"), None);
+        assert_eq!(inner.normalize("/test"), Some("/test/".to_string()));
+        assert_eq!(inner.normalize("/test//"), Some("/test/".to_string()));
+
+        let inner = NormalizePath::new(TrailingSlash::Ignore)
+            .merge_slashes(false)
+            .inner;
+        assert_eq!(inner.normalize("/test//"), None);
+        assert_eq!(inner.normalize("//test//"), Some("/test//".to_string()));
+        assert_eq!(inner.normalize("/\\test"), Some("/test".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Redirection status is expected")]
+    fn test_redirect_status() {
+        let _ = NormalizePath::new(TrailingSlash::Trim).redirect(StatusCode::NOT_MODIFIED);
+    }
+
+    #[crate::rt_test]
+    async fn test_rewrite() {
+        let srv = init_service(
+            App::new()
+                .wrap(NormalizePath::new(TrailingSlash::Trim))
+                .route(
+                    "/test/{id}",
+                    web::get().to(|req: HttpRequest| async move {
+                        format!("{} {}", req.match_info().query("id"), req.uri())
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("//test//1/?q=1").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(read_body(res).await, "1 /test/1?q=1");
+    }
+
+    #[crate::rt_test]
+    async fn test_redirect() {
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    NormalizePath::new(TrailingSlash::Always)
+                        .redirect(StatusCode::PERMANENT_REDIRECT),
+                )
+                .route("/test/", web::get().to(|| async { "test" })),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/test?q=1").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(res.headers().get(header::LOCATION).unwrap(), "/test/?q=1");
+
+        let req = TestRequest::with_uri("/test/").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    NormalizePath::new(TrailingSlash::Trim)
+                        .merge_slashes(false)
+                        .redirect(StatusCode::FOUND),
+                )
+                .route("/test", web::get().to(|| async { "test" })),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("//example.com/").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::FOUND);
+        assert_eq!(res.headers().get(header::LOCATION).unwrap(), "/example.com");
+    }
+}
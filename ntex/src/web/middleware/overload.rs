@@ -0,0 +1,304 @@
+//! Middleware for rejecting requests on overload
+use std::{fmt, rc::Rc};
+
+use crate::http::header::{HeaderValue, CONTENT_LENGTH, RETRY_AFTER};
+use crate::service::{Middleware, Service, ServiceCtx};
+use crate::time::Seconds;
+use crate::util::counter::Counter;
+use crate::web::{HttpResponse, WebRequest, WebResponse};
+
+/// `Middleware` for rejecting requests while server is overloaded.
+///
+/// Server is overloaded if number of in-flight requests reaches the limit
+/// or custom load check returns `true`. Rejected requests receive
+/// `503 Service Unavailable` response with `Retry-After` header. Requests
+/// with `Content-Length` above configured limit are rejected with
+/// `413 Payload Too Large` before request body is read.
+///
+/// In-flight requests are counted per worker, request is in-flight until
+/// wrapped service returns response.
+///
+/// ```rust
+/// use ntex::time::Seconds;
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(
+///             middleware::Overload::new()
+///                 .max_inflight(256)
+///                 .max_content_length(1_048_576)
+///                 .retry_after(Seconds(5))
+///                 .exempt("/health"),
+///         )
+///         .service(web::resource("/health").to(|| async { HttpResponse::Ok() }))
+///         .service(web::resource("/test").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Overload {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    max_inflight: usize,
+    max_content_length: Option<u64>,
+    retry_after: Seconds,
+    check: Option<Box<dyn Fn() -> bool>>,
+    exempt: Vec<String>,
+}
+
+impl Default for Overload {
+    fn default() -> Self {
+        Overload {
+            inner: Rc::new(Inner {
+                max_inflight: 0,
+                max_content_length: None,
+                retry_after: Seconds(1),
+                check: None,
+                exempt: Vec::new(),
+            }),
+        }
+    }
+}
+
+impl Overload {
+    /// Construct `Overload` middleware.
+    pub fn new() -> Overload {
+        Overload::default()
+    }
+
+    /// Set max number of in-flight requests.
+    ///
+    /// Zero value disables the limit. By default limit is disabled.
+    pub fn max_inflight(mut self, max: usize) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .max_inflight = max;
+        self
+    }
+
+    /// Reject requests with `Content-Length` above the limit.
+    pub fn max_content_length(mut self, max: u64) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .max_content_length = Some(max);
+        self
+    }
+
+    /// Set `Retry-After` value for rejected requests.
+    ///
+    /// Zero value disables header. By default is set to 1 second.
+    pub fn retry_after(mut self, secs: Seconds) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .retry_after = secs;
+        self
+    }
+
+    /// Set custom load check.
+    ///
+    /// New requests are rejected while check returns `true`.
+    pub fn check<F>(mut self, f: F) -> Self
+    where
+        F: Fn() -> bool + 'static,
+    {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .check = Some(Box::new(f));
+        self
+    }
+
+    /// Do not reject requests for specified path, i.e. health checks.
+    pub fn exempt<T: Into<String>>(mut self, path: T) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .exempt
+            .push(path.into());
+        self
+    }
+}
+
+impl Inner {
+    fn too_large<E>(&self, req: &WebRequest<E>) -> bool {
+        if let Some(max) = self.max_content_length {
+            req.headers()
+                .get(&CONTENT_LENGTH)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<u64>().ok())
+                .map(|len| len > max)
+                .unwrap_or(false)
+        } else {
+            false
+        }
+    }
+
+    fn overloaded(&self, inflight: usize) -> bool {
+        (self.max_inflight != 0 && inflight >= self.max_inflight)
+            || self.check.as_ref().map(|f| f()).unwrap_or(false)
+    }
+}
+
+impl fmt::Debug for Inner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Overload")
+            .field("max_inflight", &self.max_inflight)
+            .field("max_content_length", &self.max_content_length)
+            .field("retry_after", &self.retry_after)
+            .field("exempt", &self.exempt)
+            .finish()
+    }
+}
+
+impl<S> Middleware<S> for Overload {
+    type Service = OverloadMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        OverloadMiddleware {
+            service,
+            inner: self.inner.clone(),
+            count: Counter::new(self.inner.max_inflight),
+        }
+    }
+}
+
+/// Service created by `Overload` middleware
+#[derive(Debug)]
+pub struct OverloadMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+    count: Counter,
+}
+
+impl<S, E> Service<WebRequest<E>> for OverloadMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    crate::forward_poll_ready!(service);
+    crate::forward_poll_shutdown!(service);
+
+    async fn call(
+        &self,
+        req: WebRequest<E>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        if !self.inner.exempt.iter().any(|p| p == req.path()) {
+            if self.inner.too_large(&req) {
+                log::trace!("Request payload is too large, rejecting");
+                return Ok(req.into_response(HttpResponse::PayloadTooLarge().finish()));
+            }
+            if self.inner.overloaded(self.count.total()) {
+                log::trace!("Server is overloaded, rejecting request");
+                let mut res = HttpResponse::ServiceUnavailable();
+                if !self.inner.retry_after.is_zero() {
+                    res.header(
+                        RETRY_AFTER,
+                        HeaderValue::from(self.inner.retry_after.seconds()),
+                    );
+                }
+                return Ok(req.into_response(res.finish()));
+            }
+        }
+
+        let _guard = self.count.get();
+        ctx.call(&self.service, req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::service::{IntoService, Pipeline};
+    use crate::time::{sleep, Millis};
+    use crate::web::test::{ok_service, TestRequest};
+    use crate::web::{DefaultError, Error};
+
+    #[crate::rt_test]
+    async fn test_inflight() {
+        let srv = |req: WebRequest<DefaultError>| async move {
+            sleep(Millis(100)).await;
+            Ok::<_, Error>(req.into_response(HttpResponse::Ok().finish()))
+        };
+        let mw = Pipeline::new(
+            Overload::new()
+                .max_inflight(1)
+                .retry_after(Seconds(5))
+                .exempt("/health")
+                .create(srv.into_service()),
+        );
+
+        let mw2 = mw.clone();
+        let fut = crate::rt::spawn(async move {
+            mw2.call(TestRequest::default().to_srv_request()).await
+        });
+        sleep(Millis(25)).await;
+
+        let req = TestRequest::default().to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(resp.headers().get(RETRY_AFTER).unwrap(), "5");
+
+        let req = TestRequest::with_uri("/health").to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = fut.await.unwrap().unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::default().to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[crate::rt_test]
+    async fn test_check() {
+        let overloaded = Rc::new(Cell::new(true));
+        let o = overloaded.clone();
+        let mw = Pipeline::new(
+            Overload::new()
+                .check(move || o.get())
+                .create(ok_service::<DefaultError>()),
+        );
+
+        let resp = mw
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(resp.headers().get(RETRY_AFTER).unwrap(), "1");
+
+        overloaded.set(false);
+        let resp = mw
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[crate::rt_test]
+    async fn test_content_length() {
+        let mw = Pipeline::new(
+            Overload::new()
+                .max_content_length(10)
+                .create(ok_service::<DefaultError>()),
+        );
+
+        let req = TestRequest::default()
+            .header(CONTENT_LENGTH, "11")
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let req = TestRequest::default()
+            .header(CONTENT_LENGTH, "10")
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
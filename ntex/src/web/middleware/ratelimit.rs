@@ -0,0 +1,614 @@
+//! Middleware for request rate limiting
+use std::collections::{HashMap, VecDeque};
+use std::{fmt, rc::Rc, sync::Arc, sync::Mutex};
+use std::{time::Duration, time::Instant};
+
+use crate::http::header::{HeaderName, HeaderValue, RETRY_AFTER};
+use crate::http::RequestHead;
+use crate::service::{Middleware, Service, ServiceCtx};
+use crate::time::{now, Millis};
+use crate::web::{HttpResponse, WebRequest, WebResponse};
+
+/// `X-RateLimit-Limit` header
+pub const X_RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("x-ratelimit-limit");
+/// `X-RateLimit-Remaining` header
+pub const X_RATELIMIT_REMAINING: HeaderName =
+    HeaderName::from_static("x-ratelimit-remaining");
+/// `X-RateLimit-Reset` header
+pub const X_RATELIMIT_RESET: HeaderName = HeaderName::from_static("x-ratelimit-reset");
+
+/// Rate limiting algorithm
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum RateLimitAlgorithm {
+    /// Token bucket, allows bursts up to the limit
+    #[default]
+    TokenBucket,
+    /// Sliding window counter
+    SlidingWindow,
+}
+
+/// Rate limit quota, `limit` requests per `period`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RateLimitQuota {
+    /// Max number of requests per period
+    pub limit: u32,
+    /// Quota period
+    pub period: Millis,
+    /// Rate limiting algorithm
+    pub algorithm: RateLimitAlgorithm,
+}
+
+/// Result of rate limit check
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    /// Request is allowed
+    pub allowed: bool,
+    /// Max number of requests per period
+    pub limit: u32,
+    /// Number of remaining requests
+    pub remaining: u32,
+    /// Time until next request is allowed if request is rejected,
+    /// otherwise time until quota is fully restored
+    pub reset: Millis,
+}
+
+#[allow(async_fn_in_trait)]
+/// Rate limiter storage backend
+///
+/// Backend keeps rate limiter state per key, distributed backend
+/// could be used to share limits between multiple servers.
+pub trait RateLimitBackend: 'static {
+    /// Check quota for the key and consume one request if it is allowed
+    async fn acquire(
+        &self,
+        key: &str,
+        quota: &RateLimitQuota,
+    ) -> Result<RateLimitStatus, Box<dyn std::error::Error>>;
+}
+
+#[derive(Debug)]
+enum State {
+    Bucket {
+        tokens: f64,
+        updated: Instant,
+    },
+    Window {
+        start: Instant,
+        prev: u32,
+        current: u32,
+    },
+}
+
+/// In-memory rate limiter backend
+///
+/// Backend could be shared between workers, it must be created outside
+/// of the application factory and cloned into each application.
+/// Idle keys expire incrementally, each request checks a few oldest keys.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryRateLimitBackend(Arc<Mutex<Storage>>);
+
+/// Max number of keys checked for expiration per request
+const EXPIRE_BATCH: usize = 8;
+
+#[derive(Debug, Default)]
+struct Storage {
+    states: HashMap<String, Entry>,
+    queue: VecDeque<(Instant, String)>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    state: State,
+    expires: Instant,
+}
+
+impl MemoryRateLimitBackend {
+    /// Create new in-memory backend
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Storage {
+    fn acquire(
+        &mut self,
+        key: &str,
+        quota: &RateLimitQuota,
+        now: Instant,
+    ) -> RateLimitStatus {
+        self.expire(now);
+
+        let period = Duration::from(quota.period);
+        let expires = match quota.algorithm {
+            RateLimitAlgorithm::TokenBucket => now + period,
+            RateLimitAlgorithm::SlidingWindow => now + period * 2,
+        };
+
+        let entry = if let Some(entry) = self.states.get_mut(key) {
+            entry
+        } else {
+            self.queue.push_back((expires, key.to_string()));
+            self.states.entry(key.to_string()).or_insert(Entry {
+                expires,
+                state: match quota.algorithm {
+                    RateLimitAlgorithm::TokenBucket => State::Bucket {
+                        tokens: quota.limit as f64,
+                        updated: now,
+                    },
+                    RateLimitAlgorithm::SlidingWindow => State::Window {
+                        start: now,
+                        prev: 0,
+                        current: 0,
+                    },
+                },
+            })
+        };
+        entry.expires = expires;
+        check(&mut entry.state, quota, now)
+    }
+
+    /// Remove expired keys, keys that were used since queued are re-queued
+    fn expire(&mut self, now: Instant) {
+        for _ in 0..EXPIRE_BATCH {
+            match self.queue.front() {
+                Some((deadline, _)) if *deadline <= now => {
+                    let (_, key) = self.queue.pop_front().unwrap();
+                    match self.states.get(&key) {
+                        Some(entry) if entry.expires > now => {
+                            self.queue.push_back((entry.expires, key));
+                        }
+                        _ => {
+                            self.states.remove(&key);
+                        }
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+}
+
+impl RateLimitBackend for MemoryRateLimitBackend {
+    async fn acquire(
+        &self,
+        key: &str,
+        quota: &RateLimitQuota,
+    ) -> Result<RateLimitStatus, Box<dyn std::error::Error>> {
+        Ok(self.0.lock().unwrap().acquire(key, quota, now()))
+    }
+}
+
+fn check(state: &mut State, quota: &RateLimitQuota, now: Instant) -> RateLimitStatus {
+    let limit = quota.limit as f64;
+    let period = quota.period.0.max(1) as f64;
+
+    match state {
+        State::Bucket { tokens, updated } => {
+            // refill rate per millisecond
+            let rate = limit / period;
+            let elapsed = now.duration_since(*updated).as_millis() as f64;
+            *tokens = (*tokens + elapsed * rate).min(limit);
+            *updated = now;
+
+            if *tokens >= 1.0 {
+                *tokens -= 1.0;
+                RateLimitStatus {
+                    allowed: true,
+                    limit: quota.limit,
+                    remaining: *tokens as u32,
+                    reset: Millis(((limit - *tokens) / rate).ceil() as u32),
+                }
+            } else {
+                RateLimitStatus {
+                    allowed: false,
+                    limit: quota.limit,
+                    remaining: 0,
+                    reset: Millis(((1.0 - *tokens) / rate).ceil() as u32),
+                }
+            }
+        }
+        State::Window {
+            start,
+            prev,
+            current,
+        } => {
+            let mut elapsed = now.duration_since(*start).as_millis() as f64;
+            if elapsed >= period {
+                let windows = (elapsed / period) as u32;
+                *prev = if windows == 1 { *current } else { 0 };
+                *current = 0;
+                *start += Duration::from_millis((windows as f64 * period) as u64);
+                elapsed -= windows as f64 * period;
+            }
+
+            // weighted count of previous and current windows
+            let count = *prev as f64 * (1.0 - elapsed / period) + *current as f64;
+            let reset = Millis((period - elapsed).ceil() as u32);
+            if count + 1.0 <= limit {
+                *current += 1;
+                RateLimitStatus {
+                    allowed: true,
+                    limit: quota.limit,
+                    remaining: (limit - count - 1.0) as u32,
+                    reset,
+                }
+            } else {
+                RateLimitStatus {
+                    allowed: false,
+                    limit: quota.limit,
+                    remaining: 0,
+                    reset,
+                }
+            }
+        }
+    }
+}
+
+type KeyFn = Box<dyn Fn(&RequestHead) -> Option<String>>;
+
+struct Inner<B> {
+    backend: B,
+    quota: RateLimitQuota,
+    prefix: String,
+    key: KeyFn,
+    headers: bool,
+}
+
+/// `Middleware` for request rate limiting.
+///
+/// Requests are grouped by key, by default key is peer ip address.
+/// Requests above the quota are rejected with `429 Too Many Requests`
+/// response and `Retry-After` header. `X-RateLimit-Limit`,
+/// `X-RateLimit-Remaining` and `X-RateLimit-Reset` headers are added
+/// to all responses. Requests without key are not limited. If backend
+/// fails, request is allowed.
+///
+/// Middleware could be registered for app, scope or resource. By default
+/// each instance uses own in-memory backend, backend must be shared
+/// to apply same limits on all workers.
+///
+/// ```rust
+/// use ntex::time::Millis;
+/// use ntex::web::{self, middleware::{MemoryRateLimitBackend, RateLimiter}, App};
+///
+/// fn main() {
+///     let backend = MemoryRateLimitBackend::new();
+///
+///     let app = App::new().service(
+///         web::scope("/api")
+///             .wrap(
+///                 RateLimiter::with_backend(backend.clone(), 100, Millis::from_secs(60))
+///                     .sliding_window()
+///                     .key_header("x-api-key"),
+///             )
+///             .route("/", web::get().to(|| async { "api" })),
+///     );
+/// }
+/// ```
+pub struct RateLimiter<B = MemoryRateLimitBackend> {
+    inner: Rc<Inner<B>>,
+}
+
+impl RateLimiter<MemoryRateLimitBackend> {
+    /// Construct rate limiter with in-memory backend,
+    /// `limit` requests per `period` are allowed
+    pub fn new(limit: u32, period: Millis) -> Self {
+        RateLimiter::with_backend(MemoryRateLimitBackend::new(), limit, period)
+    }
+}
+
+impl<B: RateLimitBackend> RateLimiter<B> {
+    /// Construct rate limiter with custom backend
+    pub fn with_backend(backend: B, limit: u32, period: Millis) -> Self {
+        RateLimiter {
+            inner: Rc::new(Inner {
+                backend,
+                quota: RateLimitQuota {
+                    limit,
+                    period,
+                    algorithm: RateLimitAlgorithm::TokenBucket,
+                },
+                prefix: String::new(),
+                key: Box::new(|head| head.peer_addr().map(|addr| addr.ip().to_string())),
+                headers: true,
+            }),
+        }
+    }
+
+    fn inner(&mut self) -> &mut Inner<B> {
+        Rc::get_mut(&mut self.inner).expect("Multiple copies exist")
+    }
+
+    /// Use sliding window algorithm
+    ///
+    /// By default token bucket algorithm is used.
+    pub fn sliding_window(mut self) -> Self {
+        self.inner().quota.algorithm = RateLimitAlgorithm::SlidingWindow;
+        self
+    }
+
+    /// Set key prefix
+    ///
+    /// Prefix separates limits of rate limiters with shared backend,
+    /// backend key is `{prefix}:{key}`. Prefix must not contain `:`.
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        assert!(!prefix.contains(':'), "Prefix must not contain ':'");
+        self.inner().prefix = prefix.to_string();
+        self
+    }
+
+    /// Set request key function
+    ///
+    /// Requests are not limited if function returns `None`.
+    pub fn key<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&RequestHead) -> Option<String> + 'static,
+    {
+        self.inner().key = Box::new(f);
+        self
+    }
+
+    /// Use request header value as key, i.e. api key
+    pub fn key_header(self, name: &str) -> Self {
+        let name = HeaderName::try_from(name).expect("Invalid header name");
+        self.key(move |head| {
+            head.headers
+                .get(&name)
+                .and_then(|val| val.to_str().ok())
+                .map(|val| val.to_string())
+        })
+    }
+
+    /// Add `X-RateLimit-*` headers to responses, enabled by default
+    pub fn headers(mut self, value: bool) -> Self {
+        self.inner().headers = value;
+        self
+    }
+}
+
+impl<B> Clone for RateLimiter<B> {
+    fn clone(&self) -> Self {
+        RateLimiter {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<B> fmt::Debug for RateLimiter<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RateLimiter")
+            .field("quota", &self.inner.quota)
+            .field("prefix", &self.inner.prefix)
+            .field("headers", &self.inner.headers)
+            .finish()
+    }
+}
+
+impl<S, B> Middleware<S> for RateLimiter<B> {
+    type Service = RateLimiterMiddleware<S, B>;
+
+    fn create(&self, service: S) -> Self::Service {
+        RateLimiterMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct RateLimiterMiddleware<S, B> {
+    service: S,
+    inner: Rc<Inner<B>>,
+}
+
+impl<S, B> fmt::Debug for RateLimiterMiddleware<S, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RateLimiterMiddleware").finish()
+    }
+}
+
+impl<B> Inner<B> {
+    fn set_headers(&self, res: &mut HttpResponse, st: &RateLimitStatus) {
+        if self.headers {
+            let hdrs = res.headers_mut();
+            hdrs.insert(X_RATELIMIT_LIMIT, HeaderValue::from(st.limit));
+            hdrs.insert(X_RATELIMIT_REMAINING, HeaderValue::from(st.remaining));
+            hdrs.insert(X_RATELIMIT_RESET, HeaderValue::from(seconds(st.reset)));
+        }
+    }
+}
+
+/// Round up to seconds
+fn seconds(val: Millis) -> u32 {
+    val.0.div_ceil(1000)
+}
+
+impl<S, B, E> Service<WebRequest<E>> for RateLimiterMiddleware<S, B>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+    B: RateLimitBackend,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    crate::forward_poll_ready!(service);
+    crate::forward_poll_shutdown!(service);
+
+    async fn call(
+        &self,
+        req: WebRequest<E>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        let key = if let Some(key) = (self.inner.key)(req.head()) {
+            format!("{}:{}", self.inner.prefix, key)
+        } else {
+            return ctx.call(&self.service, req).await;
+        };
+
+        let status = match self.inner.backend.acquire(&key, &self.inner.quota).await {
+            Ok(status) => status,
+            Err(e) => {
+                log::error!("Rate limiter backend failed: {}", e);
+                return ctx.call(&self.service, req).await;
+            }
+        };
+
+        if status.allowed {
+            let mut res = ctx.call(&self.service, req).await?;
+            self.inner.set_headers(res.response_mut(), &status);
+            Ok(res)
+        } else {
+            log::trace!("Rate limit exceeded for {:?}", key);
+            let mut res = HttpResponse::TooManyRequests()
+                .header(RETRY_AFTER, HeaderValue::from(seconds(status.reset)))
+                .finish();
+            self.inner.set_headers(&mut res, &status);
+            Ok(req.into_response(res))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{call_service, init_service, TestRequest};
+    use crate::web::{self, App};
+
+    #[crate::rt_test]
+    async fn test_token_bucket() {
+        let srv = init_service(
+            App::new()
+                .service(
+                    web::scope("/api")
+                        .wrap(
+                            RateLimiter::new(2, Millis::from_secs(60)).key_header("x-key"),
+                        )
+                        .route("/", web::get().to(|| async { "api" })),
+                )
+                .route("/", web::get().to(|| async { "index" })),
+        )
+        .await;
+
+        for remaining in ["1", "0"] {
+            let req = TestRequest::with_uri("/api/")
+                .header("x-key", "a")
+                .to_request();
+            let res = call_service(&srv, req).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(res.headers().get(X_RATELIMIT_LIMIT).unwrap(), "2");
+            assert_eq!(res.headers().get(X_RATELIMIT_REMAINING).unwrap(), remaining);
+        }
+
+        let req = TestRequest::with_uri("/api/")
+            .header("x-key", "a")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(res.headers().get(RETRY_AFTER).unwrap(), "30");
+
+        // other key
+        let req = TestRequest::with_uri("/api/")
+            .header("x-key", "b")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // no key
+        let req = TestRequest::with_uri("/api/").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.headers().get(X_RATELIMIT_LIMIT).is_none());
+
+        // not limited route
+        let req = TestRequest::with_uri("/").header("x-key", "a").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_sliding_window() {
+        let quota = RateLimitQuota {
+            limit: 2,
+            period: Millis(1000),
+            algorithm: RateLimitAlgorithm::SlidingWindow,
+        };
+        let start = Instant::now();
+        let mut state = State::Window {
+            start,
+            prev: 0,
+            current: 0,
+        };
+
+        assert!(check(&mut state, &quota, start).allowed);
+        assert!(check(&mut state, &quota, start).allowed);
+        let st = check(&mut state, &quota, start + Duration::from_millis(200));
+        assert!(!st.allowed);
+        assert_eq!(st.reset, Millis(800));
+
+        // half of previous window is counted
+        let st = check(&mut state, &quota, start + Duration::from_millis(1500));
+        assert!(st.allowed);
+        assert_eq!(st.remaining, 0);
+        assert!(!check(&mut state, &quota, start + Duration::from_millis(1500)).allowed);
+
+        // previous window is expired
+        let st = check(&mut state, &quota, start + Duration::from_millis(3100));
+        assert!(st.allowed);
+        assert_eq!(st.remaining, 1);
+    }
+
+    #[test]
+    fn test_expire() {
+        let quota = RateLimitQuota {
+            limit: 2,
+            period: Millis(1000),
+            algorithm: RateLimitAlgorithm::TokenBucket,
+        };
+        let start = Instant::now();
+        let mut storage = Storage::default();
+
+        for idx in 0..20 {
+            storage.acquire(&idx.to_string(), &quota, start);
+        }
+        assert_eq!(storage.states.len(), 20);
+
+        // used key is re-queued
+        storage.acquire("0", &quota, start + Duration::from_millis(500));
+        storage.acquire("x", &quota, start + Duration::from_millis(1000));
+        assert_eq!(storage.states.len(), 21 - EXPIRE_BATCH + 1);
+        assert!(storage.states.contains_key("0"));
+
+        storage.acquire("x", &quota, start + Duration::from_millis(1000));
+        storage.acquire("x", &quota, start + Duration::from_millis(1000));
+        assert_eq!(storage.states.len(), 2);
+        assert!(storage.states.contains_key("0"));
+
+        storage.acquire("x", &quota, start + Duration::from_millis(1600));
+        assert_eq!(storage.states.len(), 1);
+        assert_eq!(storage.queue.len(), 1);
+    }
+
+    #[test]
+    fn test_bucket_refill() {
+        let quota = RateLimitQuota {
+            limit: 2,
+            period: Millis(1000),
+            algorithm: RateLimitAlgorithm::TokenBucket,
+        };
+        let start = Instant::now();
+        let mut state = State::Bucket {
+            tokens: 2.0,
+            updated: start,
+        };
+
+        assert!(check(&mut state, &quota, start).allowed);
+        assert!(check(&mut state, &quota, start).allowed);
+        let st = check(&mut state, &quota, start);
+        assert!(!st.allowed);
+        assert_eq!(st.reset, Millis(500));
+
+        let st = check(&mut state, &quota, start + Duration::from_millis(500));
+        assert!(st.allowed);
+        assert_eq!(st.remaining, 0);
+    }
+}
@@ -0,0 +1,363 @@
+//! Request id middleware
+use std::{fmt, rc::Rc, time::SystemTime};
+
+use nanorand::{Rng, WyRand};
+
+use crate::http::header::{HeaderName, HeaderValue};
+use crate::http::Payload;
+use crate::service::{Middleware, Service, ServiceCtx};
+use crate::util::{ByteString, Extensions};
+use crate::web::error::ErrorRenderer;
+use crate::web::{FromRequest, HttpRequest, WebRequest, WebResponse};
+
+/// `X-Request-Id` header
+pub const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Max length of accepted incoming request id
+const MAX_LENGTH: usize = 128;
+
+/// Crockford's base32 alphabet
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Unique id of the request
+///
+/// Id is assigned by [`RequestIds`] middleware and is available to handlers
+/// as extractor. If middleware is not registered, new id is generated.
+///
+/// ```rust
+/// use ntex::web::middleware::RequestId;
+///
+/// async fn index(id: RequestId) -> String {
+///     format!("Request: {}", id)
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RequestId(ByteString);
+
+impl RequestId {
+    /// Generate new ULID request id
+    pub fn ulid() -> Self {
+        let ts = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        let mut val = ((ts & 0xffff_ffff_ffff) << 80) | (random() & ((1 << 80) - 1));
+
+        let mut buf = [0u8; 26];
+        for b in buf.iter_mut().rev() {
+            *b = ALPHABET[(val & 0x1f) as usize];
+            val >>= 5;
+        }
+        RequestId(ByteString::from(std::str::from_utf8(&buf).unwrap()))
+    }
+
+    /// Generate new random UUID (version 4) request id
+    pub fn uuid() -> Self {
+        // set version and variant bits
+        let val = (random() & !(0xf << 76) & !(0x3 << 62)) | (0x4 << 76) | (0x2 << 62);
+        RequestId(ByteString::from(format!(
+            "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            val >> 96,
+            (val >> 80) & 0xffff,
+            (val >> 64) & 0xffff,
+            (val >> 48) & 0xffff,
+            val & 0xffff_ffff_ffff
+        )))
+    }
+
+    /// Request id as string
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Request id from request extensions
+    ///
+    /// Could be used as custom field for `Logger` middleware.
+    ///
+    /// ```rust
+    /// use ntex::web::middleware::{Logger, RequestId};
+    ///
+    /// let logger = Logger::new("%{request_id}x %r %s")
+    ///     .field("request_id", RequestId::log_field);
+    /// ```
+    pub fn log_field(ext: &Extensions) -> Option<String> {
+        ext.get::<RequestId>().map(|id| id.0.to_string())
+    }
+
+    /// Accept incoming id if it is not empty, not too long and contains
+    /// only visible ascii characters
+    fn from_header(val: &HeaderValue) -> Option<Self> {
+        let val = val.to_str().ok()?;
+        if !val.is_empty()
+            && val.len() <= MAX_LENGTH
+            && val.bytes().all(|b| b.is_ascii_graphic())
+        {
+            Some(RequestId(ByteString::from(val)))
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for RequestId {
+    fn default() -> Self {
+        RequestId::ulid()
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for RequestId {
+    type Error = Err::Container;
+
+    #[inline]
+    async fn from_request(req: &HttpRequest, _: &mut Payload) -> Result<Self, Self::Error> {
+        Ok(req
+            .extensions()
+            .get::<RequestId>()
+            .cloned()
+            .unwrap_or_default())
+    }
+}
+
+fn random() -> u128 {
+    let mut rng = WyRand::new();
+    ((rng.generate::<u64>() as u128) << 64) | rng.generate::<u64>() as u128
+}
+
+/// Format of generated request ids
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RequestIdFormat {
+    /// Lexicographically sortable id, 26 characters
+    Ulid,
+    /// Random UUID, version 4
+    Uuid,
+}
+
+/// `Middleware` for assigning unique id to each request.
+///
+/// Incoming `X-Request-Id` header is honored, otherwise new id is generated.
+/// Id is stored in request extensions, available to handlers via
+/// [`RequestId`] extractor and set on the response header.
+///
+/// ```rust
+/// use ntex::web::{self, middleware::{Logger, RequestId, RequestIds}, App};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(
+///             Logger::new("%{request_id}x %r %s")
+///                 .field("request_id", RequestId::log_field),
+///         )
+///         .wrap(RequestIds::new())
+///         .route("/", web::get().to(|id: RequestId| async move { id.to_string() }));
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct RequestIds {
+    inner: Rc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    header: HeaderName,
+    format: RequestIdFormat,
+    trust_incoming: bool,
+}
+
+impl RequestIds {
+    /// Construct request id middleware
+    pub fn new() -> Self {
+        RequestIds {
+            inner: Rc::new(Inner {
+                header: X_REQUEST_ID,
+                format: RequestIdFormat::Ulid,
+                trust_incoming: true,
+            }),
+        }
+    }
+
+    /// Set header name, `X-Request-Id` is used by default
+    pub fn header(mut self, name: HeaderName) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .header = name;
+        self
+    }
+
+    /// Set format of generated ids, ULID is used by default
+    pub fn format(mut self, format: RequestIdFormat) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .format = format;
+        self
+    }
+
+    /// Use id from request header, enabled by default
+    ///
+    /// Disable for public facing services, new id is generated
+    /// for each request.
+    pub fn trust_incoming(mut self, value: bool) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .trust_incoming = value;
+        self
+    }
+}
+
+impl Default for RequestIds {
+    fn default() -> Self {
+        RequestIds::new()
+    }
+}
+
+impl<S> Middleware<S> for RequestIds {
+    type Service = RequestIdsMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        RequestIdsMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct RequestIdsMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S> fmt::Debug for RequestIdsMiddleware<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestIdsMiddleware")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<S, E> Service<WebRequest<E>> for RequestIdsMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    crate::forward_poll_ready!(service);
+    crate::forward_poll_shutdown!(service);
+
+    async fn call(
+        &self,
+        req: WebRequest<E>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        let incoming = if self.inner.trust_incoming {
+            req.headers()
+                .get(&self.inner.header)
+                .and_then(RequestId::from_header)
+        } else {
+            None
+        };
+        let id = incoming.unwrap_or_else(|| match self.inner.format {
+            RequestIdFormat::Ulid => RequestId::ulid(),
+            RequestIdFormat::Uuid => RequestId::uuid(),
+        });
+        req.extensions_mut().insert(id.clone());
+
+        let mut res = ctx.call(&self.service, req).await?;
+        if let Ok(val) = HeaderValue::from_str(id.as_str()) {
+            res.headers_mut().insert(self.inner.header.clone(), val);
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::mpsc;
+    use crate::http::StatusCode;
+    use crate::web::middleware::Logger;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App};
+
+    #[test]
+    fn test_generate() {
+        let id = RequestId::ulid();
+        assert_eq!(id.as_str().len(), 26);
+        assert!(id.as_str().bytes().all(|b| ALPHABET.contains(&b)));
+        assert_ne!(id, RequestId::ulid());
+
+        let id = RequestId::uuid();
+        let parts: Vec<_> = id.as_str().split('-').collect();
+        assert_eq!(
+            parts.iter().map(|p| p.len()).collect::<Vec<_>>(),
+            vec![8, 4, 4, 4, 12]
+        );
+        assert!(parts[2].starts_with('4'));
+        assert!(matches!(parts[3].as_bytes()[0], b'8' | b'9' | b'a' | b'b'));
+    }
+
+    #[crate::rt_test]
+    async fn test_request_id() {
+        let srv = init_service(
+            App::new()
+                .wrap(RequestIds::new().format(RequestIdFormat::Uuid))
+                .route(
+                    "/",
+                    web::get().to(|id: RequestId| async move { id.to_string() }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::default()
+            .header(X_REQUEST_ID, "incoming-id")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get(X_REQUEST_ID).unwrap(), "incoming-id");
+        assert_eq!(read_body(res).await, "incoming-id");
+
+        // invalid incoming id
+        let req = TestRequest::default()
+            .header(X_REQUEST_ID, "bad id")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        let header = res.headers().get(X_REQUEST_ID).unwrap().clone();
+        let body = read_body(res).await;
+        assert_eq!(body.len(), 36);
+        assert_eq!(body, header.to_str().unwrap());
+    }
+
+    #[crate::rt_test]
+    async fn test_logger_field() {
+        let (tx, rx) = mpsc::channel::<String>();
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    Logger::new("%{request_id}x")
+                        .field("request_id", RequestId::log_field)
+                        .sink(tx),
+                )
+                .wrap(
+                    RequestIds::new()
+                        .header(HeaderName::from_static("x-correlation-id"))
+                        .trust_incoming(false),
+                )
+                .route("/", web::get().to(|| async { "" })),
+        )
+        .await;
+
+        let req = TestRequest::default()
+            .header("x-correlation-id", "incoming-id")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        let header = res.headers().get("x-correlation-id").unwrap().clone();
+        assert_ne!(header, "incoming-id");
+        let _ = read_body(res).await;
+        assert_eq!(rx.recv().await.unwrap(), header.to_str().unwrap());
+    }
+}
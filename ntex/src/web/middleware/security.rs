@@ -0,0 +1,279 @@
+//! Middleware for setting security response headers
+use std::rc::Rc;
+
+use crate::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use crate::service::{Middleware, Service, ServiceCtx};
+use crate::web::{WebRequest, WebResponse};
+
+/// `Permissions-Policy` header
+pub const PERMISSIONS_POLICY: HeaderName = HeaderName::from_static("permissions-policy");
+
+/// `Cross-Origin-Opener-Policy` header
+pub const CROSS_ORIGIN_OPENER_POLICY: HeaderName =
+    HeaderName::from_static("cross-origin-opener-policy");
+
+/// Default `max-age` of `Strict-Transport-Security` header, one year
+const HSTS_MAX_AGE: u32 = 31_536_000;
+
+/// `Middleware` for setting security response headers.
+///
+/// By default middleware sets following headers:
+///
+/// * `Strict-Transport-Security: max-age=31536000; includeSubDomains`
+/// * `X-Content-Type-Options: nosniff`
+/// * `X-Frame-Options: DENY`
+/// * `Referrer-Policy: strict-origin-when-cross-origin`
+///
+/// `Content-Security-Policy` is not set by default, it depends on
+/// application content. Headers are not set if response already contains
+/// them, so handlers could override any of them.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(
+///             middleware::DefaultSecurityHeaders::new()
+///                 .content_security_policy("default-src 'self'")
+///                 .frame_options("SAMEORIGIN")
+///         )
+///         .route("/", web::get().to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct DefaultSecurityHeaders {
+    inner: Rc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    headers: HeaderMap,
+}
+
+impl Default for DefaultSecurityHeaders {
+    fn default() -> Self {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::STRICT_TRANSPORT_SECURITY,
+            hsts_value(HSTS_MAX_AGE, true, false),
+        );
+        headers.insert(
+            header::X_CONTENT_TYPE_OPTIONS,
+            HeaderValue::from_static("nosniff"),
+        );
+        headers.insert(header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY"));
+        headers.insert(
+            header::REFERRER_POLICY,
+            HeaderValue::from_static("strict-origin-when-cross-origin"),
+        );
+
+        DefaultSecurityHeaders {
+            inner: Rc::new(Inner { headers }),
+        }
+    }
+}
+
+impl DefaultSecurityHeaders {
+    /// Construct `DefaultSecurityHeaders` middleware with default headers.
+    pub fn new() -> Self {
+        DefaultSecurityHeaders::default()
+    }
+
+    /// Set `Strict-Transport-Security` header.
+    ///
+    /// `max_age` is in seconds.
+    pub fn hsts(self, max_age: u32, include_subdomains: bool, preload: bool) -> Self {
+        self.insert(
+            header::STRICT_TRANSPORT_SECURITY,
+            hsts_value(max_age, include_subdomains, preload),
+        )
+    }
+
+    /// Set `Content-Security-Policy` header.
+    pub fn content_security_policy(self, policy: &str) -> Self {
+        self.header(header::CONTENT_SECURITY_POLICY, policy)
+    }
+
+    /// Set `Content-Security-Policy-Report-Only` header.
+    pub fn content_security_policy_report_only(self, policy: &str) -> Self {
+        self.header(header::CONTENT_SECURITY_POLICY_REPORT_ONLY, policy)
+    }
+
+    /// Set `X-Frame-Options` header, i.e. `DENY` or `SAMEORIGIN`.
+    pub fn frame_options(self, value: &str) -> Self {
+        self.header(header::X_FRAME_OPTIONS, value)
+    }
+
+    /// Set `Referrer-Policy` header.
+    pub fn referrer_policy(self, policy: &str) -> Self {
+        self.header(header::REFERRER_POLICY, policy)
+    }
+
+    /// Set `Permissions-Policy` header.
+    pub fn permissions_policy(self, policy: &str) -> Self {
+        self.header(PERMISSIONS_POLICY, policy)
+    }
+
+    /// Set `Cross-Origin-Opener-Policy` header.
+    pub fn cross_origin_opener_policy(self, policy: &str) -> Self {
+        self.header(CROSS_ORIGIN_OPENER_POLICY, policy)
+    }
+
+    /// Set any other header.
+    ///
+    /// Panics if value is not valid header value.
+    pub fn header(self, name: HeaderName, value: &str) -> Self {
+        match HeaderValue::try_from(value) {
+            Ok(value) => self.insert(name, value),
+            Err(_) => panic!("Cannot create header value"),
+        }
+    }
+
+    /// Do not set header, i.e. one of the defaults.
+    pub fn disable(mut self, name: HeaderName) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .headers
+            .remove(name);
+        self
+    }
+
+    fn insert(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .headers
+            .insert(name, value);
+        self
+    }
+}
+
+fn hsts_value(max_age: u32, include_subdomains: bool, preload: bool) -> HeaderValue {
+    let mut value = format!("max-age={}", max_age);
+    if include_subdomains {
+        value.push_str("; includeSubDomains");
+    }
+    if preload {
+        value.push_str("; preload");
+    }
+    HeaderValue::try_from(value).unwrap()
+}
+
+impl<S> Middleware<S> for DefaultSecurityHeaders {
+    type Service = DefaultSecurityHeadersMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        DefaultSecurityHeadersMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DefaultSecurityHeadersMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for DefaultSecurityHeadersMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    crate::forward_poll_ready!(service);
+    crate::forward_poll_shutdown!(service);
+
+    async fn call(
+        &self,
+        req: WebRequest<E>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        let mut res = ctx.call(&self.service, req).await?;
+
+        for (key, value) in self.inner.headers.iter() {
+            if !res.headers().contains_key(key) {
+                res.headers_mut().insert(key.clone(), value.clone());
+            }
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{call_service, init_service, TestRequest};
+    use crate::web::{self, App, HttpResponse};
+
+    #[crate::rt_test]
+    async fn test_security_headers() {
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    DefaultSecurityHeaders::new()
+                        .hsts(600, false, true)
+                        .content_security_policy("default-src 'self'")
+                        .permissions_policy("geolocation=()")
+                        .disable(header::REFERRER_POLICY),
+                )
+                .route("/", web::get().to(|| async { HttpResponse::Ok() }))
+                .route(
+                    "/frame",
+                    web::get().to(|| async {
+                        HttpResponse::Ok()
+                            .header(header::X_FRAME_OPTIONS, "SAMEORIGIN")
+                            .finish()
+                    }),
+                ),
+        )
+        .await;
+
+        let res = call_service(&srv, TestRequest::default().to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let headers = res.headers();
+        assert_eq!(
+            headers.get(header::STRICT_TRANSPORT_SECURITY).unwrap(),
+            "max-age=600; preload"
+        );
+        assert_eq!(
+            headers.get(header::CONTENT_SECURITY_POLICY).unwrap(),
+            "default-src 'self'"
+        );
+        assert_eq!(headers.get(PERMISSIONS_POLICY).unwrap(), "geolocation=()");
+        assert_eq!(
+            headers.get(header::X_CONTENT_TYPE_OPTIONS).unwrap(),
+            "nosniff"
+        );
+        assert_eq!(headers.get(header::X_FRAME_OPTIONS).unwrap(), "DENY");
+        assert!(headers.get(header::REFERRER_POLICY).is_none());
+
+        // handler overrides header
+        let req = TestRequest::with_uri("/frame").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(
+            res.headers().get(header::X_FRAME_OPTIONS).unwrap(),
+            "SAMEORIGIN"
+        );
+    }
+
+    #[test]
+    fn test_defaults() {
+        let mw = DefaultSecurityHeaders::new();
+        assert_eq!(
+            mw.inner
+                .headers
+                .get(header::STRICT_TRANSPORT_SECURITY)
+                .unwrap(),
+            "max-age=31536000; includeSubDomains"
+        );
+        assert!(mw
+            .inner
+            .headers
+            .get(header::CONTENT_SECURITY_POLICY)
+            .is_none());
+    }
+}
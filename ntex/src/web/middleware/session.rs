@@ -0,0 +1,778 @@
+//! Middleware for cookie based sessions
+use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc, sync::Arc, sync::Mutex};
+use std::{time::Duration, time::Instant};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD as base64, Engine};
+use coo_kie::{time, Cookie, CookieJar, Key, SameSite};
+use nanorand::{Rng, WyRand};
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+
+use crate::http::{HttpMessage, Payload, Response};
+use crate::service::{Middleware, Service, ServiceCtx};
+use crate::time::{now, Millis};
+use crate::web::{ErrorRenderer, FromRequest, HttpRequest, WebRequest, WebResponse};
+
+/// Session state, values are stored as json strings
+pub type SessionState = HashMap<String, String>;
+
+/// Session store error
+#[derive(Error, Debug)]
+pub enum SessionError {
+    /// Session value cannot be serialized or deserialized
+    #[error("Session serialization error: {0}")]
+    Serialize(#[from] serde_json::Error),
+    /// Session state is too large for the store
+    #[error("Session state is too large")]
+    Overflow,
+    /// Store backend error
+    #[error("Session store error: {0}")]
+    Store(Box<dyn std::error::Error>),
+}
+
+#[allow(async_fn_in_trait)]
+/// Session storage backend
+///
+/// Store keeps session state by session key, session key is sent
+/// to the client in signed or encrypted cookie.
+pub trait SessionStore: 'static {
+    /// Load session state
+    ///
+    /// Returns `None` if session does not exist or is expired.
+    async fn load(&self, key: &str) -> Result<Option<SessionState>, SessionError>;
+
+    /// Save session state
+    ///
+    /// New session must be created if `key` is `None`. Returns session key.
+    async fn save(
+        &self,
+        key: Option<&str>,
+        state: &SessionState,
+        ttl: Millis,
+    ) -> Result<String, SessionError>;
+
+    /// Extend session expiry
+    async fn update_ttl(&self, key: &str, ttl: Millis) -> Result<(), SessionError>;
+
+    /// Delete session
+    async fn delete(&self, key: &str) -> Result<(), SessionError>;
+}
+
+/// Session store which keeps state in the session cookie
+///
+/// Session state is serialized into the cookie value, so state size
+/// is limited by cookie size. Use encrypted cookie if session contains
+/// sensitive data.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CookieSessionStore;
+
+const MAX_COOKIE_STATE: usize = 3072;
+
+impl SessionStore for CookieSessionStore {
+    async fn load(&self, key: &str) -> Result<Option<SessionState>, SessionError> {
+        Ok(base64
+            .decode(key)
+            .ok()
+            .and_then(|val| serde_json::from_slice(&val).ok()))
+    }
+
+    async fn save(
+        &self,
+        _: Option<&str>,
+        state: &SessionState,
+        _: Millis,
+    ) -> Result<String, SessionError> {
+        let value = base64.encode(serde_json::to_vec(state)?);
+        if value.len() > MAX_COOKIE_STATE {
+            Err(SessionError::Overflow)
+        } else {
+            Ok(value)
+        }
+    }
+
+    async fn update_ttl(&self, _: &str, _: Millis) -> Result<(), SessionError> {
+        Ok(())
+    }
+
+    async fn delete(&self, _: &str) -> Result<(), SessionError> {
+        Ok(())
+    }
+}
+
+/// In-memory session store
+///
+/// Store could be shared between workers, it must be created outside
+/// of the application factory and cloned into each application.
+/// Sessions are lost on restart.
+#[derive(Clone, Debug, Default)]
+pub struct MemorySessionStore(Arc<Mutex<HashMap<String, (SessionState, Instant)>>>);
+
+impl MemorySessionStore {
+    /// Create new in-memory store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SessionStore for MemorySessionStore {
+    async fn load(&self, key: &str) -> Result<Option<SessionState>, SessionError> {
+        let now = now();
+        Ok(self
+            .0
+            .lock()
+            .unwrap()
+            .get(key)
+            .and_then(|(state, expires)| (*expires > now).then(|| state.clone())))
+    }
+
+    async fn save(
+        &self,
+        key: Option<&str>,
+        state: &SessionState,
+        ttl: Millis,
+    ) -> Result<String, SessionError> {
+        let now = now();
+        let key = key.map(|k| k.to_string()).unwrap_or_else(new_key);
+        let mut sessions = self.0.lock().unwrap();
+        sessions.retain(|_, (_, expires)| *expires > now);
+        sessions.insert(key.clone(), (state.clone(), now + Duration::from(ttl)));
+        Ok(key)
+    }
+
+    async fn update_ttl(&self, key: &str, ttl: Millis) -> Result<(), SessionError> {
+        if let Some(item) = self.0.lock().unwrap().get_mut(key) {
+            item.1 = now() + Duration::from(ttl);
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), SessionError> {
+        self.0.lock().unwrap().remove(key);
+        Ok(())
+    }
+}
+
+/// Generate new session key
+///
+/// Key is not required to be cryptographically random, client cannot
+/// use other keys because cookie is signed or encrypted.
+fn new_key() -> String {
+    let mut rng = WyRand::new();
+    format!(
+        "{:016x}{:016x}",
+        rng.generate::<u64>(),
+        rng.generate::<u64>()
+    )
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// Session status
+pub enum SessionStatus {
+    /// Session is not changed
+    Unchanged,
+    /// Session state is changed
+    Changed,
+    /// Session key must be renewed
+    Renewed,
+    /// Session must be deleted
+    Purged,
+}
+
+#[derive(Debug)]
+struct SessionInner {
+    state: SessionState,
+    status: SessionStatus,
+}
+
+/// Request session
+///
+/// `Session` could be used as request extractor, extractor returns session
+/// loaded by `Sessions` middleware. If middleware is not configured,
+/// session is empty and changes are not stored.
+///
+/// ```rust
+/// use ntex::web::{self, middleware::Session};
+///
+/// async fn index(session: Session) -> Result<String, web::Error> {
+///     let counter = session.get::<usize>("counter")?.unwrap_or(0) + 1;
+///     session.insert("counter", counter)?;
+///     Ok(format!("Visits: {}", counter))
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Session(Rc<RefCell<SessionInner>>);
+
+impl Session {
+    fn new(state: SessionState) -> Self {
+        Session(Rc::new(RefCell::new(SessionInner {
+            state,
+            status: SessionStatus::Unchanged,
+        })))
+    }
+
+    /// Get session value
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, SessionError> {
+        if let Some(val) = self.0.borrow().state.get(key) {
+            Ok(Some(serde_json::from_str(val)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Set session value
+    pub fn insert<T: Serialize>(&self, key: &str, value: T) -> Result<(), SessionError> {
+        let val = serde_json::to_string(&value)?;
+        let mut inner = self.0.borrow_mut();
+        inner.state.insert(key.to_string(), val);
+        inner.changed();
+        Ok(())
+    }
+
+    /// Remove session value
+    pub fn remove(&self, key: &str) -> Option<String> {
+        let mut inner = self.0.borrow_mut();
+        let val = inner.state.remove(key);
+        if val.is_some() {
+            inner.changed();
+        }
+        val
+    }
+
+    /// Remove all session values
+    pub fn clear(&self) {
+        let mut inner = self.0.borrow_mut();
+        inner.state.clear();
+        inner.changed();
+    }
+
+    /// Renew session key
+    ///
+    /// Session state is kept, but it gets stored under new session key.
+    /// Key should be renewed on privilege changes, i.e. after login.
+    pub fn renew(&self) {
+        let mut inner = self.0.borrow_mut();
+        if inner.status != SessionStatus::Purged {
+            inner.status = SessionStatus::Renewed;
+        }
+    }
+
+    /// Invalidate session
+    ///
+    /// Session gets deleted from the store and session cookie is removed.
+    pub fn purge(&self) {
+        let mut inner = self.0.borrow_mut();
+        inner.state.clear();
+        inner.status = SessionStatus::Purged;
+    }
+
+    /// Session status
+    pub fn status(&self) -> SessionStatus {
+        self.0.borrow().status
+    }
+
+    /// Session values
+    pub fn entries(&self) -> SessionState {
+        self.0.borrow().state.clone()
+    }
+}
+
+impl SessionInner {
+    fn changed(&mut self) {
+        match self.status {
+            SessionStatus::Unchanged => self.status = SessionStatus::Changed,
+            // purged session is stored under new key
+            SessionStatus::Purged => self.status = SessionStatus::Renewed,
+            _ => (),
+        }
+    }
+}
+
+impl fmt::Debug for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.0.borrow();
+        f.debug_struct("Session")
+            .field("state", &inner.state)
+            .field("status", &inner.status)
+            .finish()
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for Session {
+    type Error = Err::Container;
+
+    #[inline]
+    async fn from_request(req: &HttpRequest, _: &mut Payload) -> Result<Self, Self::Error> {
+        Ok(req
+            .extensions()
+            .get::<Session>()
+            .cloned()
+            .unwrap_or_else(|| Session::new(SessionState::default())))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Security {
+    Signed,
+    Private,
+}
+
+struct Inner<St> {
+    store: St,
+    key: Key,
+    security: Security,
+    name: String,
+    path: String,
+    domain: Option<String>,
+    secure: bool,
+    http_only: bool,
+    same_site: SameSite,
+    ttl: Millis,
+    rolling: bool,
+}
+
+/// `Middleware` for cookie based sessions.
+///
+/// Middleware loads session from the store and makes it available via
+/// `Session` extractor. Session key is stored in encrypted cookie,
+/// signed cookie could be used instead. If session is changed, it gets
+/// saved to the store after the request is handled. Store errors produce
+/// `500 Internal Server Error` response.
+///
+/// By default cookie name is `session`, session ttl is one day.
+///
+/// ```rust
+/// use coo_kie::Key;
+/// use ntex::time::Millis;
+/// use ntex::web::{self, middleware::{MemorySessionStore, Session, Sessions}, App};
+///
+/// async fn login(session: Session) -> Result<&'static str, web::Error> {
+///     session.renew();
+///     session.insert("user_id", 1)?;
+///     Ok("Welcome")
+/// }
+///
+/// fn main() {
+///     let key = Key::generate();
+///     let store = MemorySessionStore::new();
+///
+///     let app = App::new()
+///         .wrap(
+///             Sessions::new(store.clone(), key.clone())
+///                 .ttl(Millis::from_secs(3600))
+///                 .rolling_ttl(true),
+///         )
+///         .route("/login", web::post().to(login));
+/// }
+/// ```
+pub struct Sessions<St> {
+    inner: Rc<Inner<St>>,
+}
+
+impl<St: SessionStore> Sessions<St> {
+    /// Construct `Sessions` middleware with store and cookie key
+    pub fn new(store: St, key: Key) -> Self {
+        Sessions {
+            inner: Rc::new(Inner {
+                store,
+                key,
+                security: Security::Private,
+                name: "session".to_string(),
+                path: "/".to_string(),
+                domain: None,
+                secure: true,
+                http_only: true,
+                same_site: SameSite::Lax,
+                ttl: Millis::from_secs(86400),
+                rolling: false,
+            }),
+        }
+    }
+
+    fn inner(&mut self) -> &mut Inner<St> {
+        Rc::get_mut(&mut self.inner).expect("Multiple copies exist")
+    }
+
+    /// Use signed cookie instead of encrypted
+    ///
+    /// Client could read session key, but cannot change it.
+    pub fn signed(mut self) -> Self {
+        self.inner().security = Security::Signed;
+        self
+    }
+
+    /// Set session cookie name
+    pub fn cookie_name(mut self, name: &str) -> Self {
+        self.inner().name = name.to_string();
+        self
+    }
+
+    /// Set session cookie path, by default it is `/`
+    pub fn cookie_path(mut self, path: &str) -> Self {
+        self.inner().path = path.to_string();
+        self
+    }
+
+    /// Set session cookie domain
+    pub fn cookie_domain(mut self, domain: &str) -> Self {
+        self.inner().domain = Some(domain.to_string());
+        self
+    }
+
+    /// Set `Secure` cookie attribute, enabled by default
+    pub fn cookie_secure(mut self, value: bool) -> Self {
+        self.inner().secure = value;
+        self
+    }
+
+    /// Set `HttpOnly` cookie attribute, enabled by default
+    pub fn cookie_http_only(mut self, value: bool) -> Self {
+        self.inner().http_only = value;
+        self
+    }
+
+    /// Set `SameSite` cookie attribute, by default it is `Lax`
+    pub fn cookie_same_site(mut self, value: SameSite) -> Self {
+        self.inner().same_site = value;
+        self
+    }
+
+    /// Set session ttl
+    ///
+    /// Ttl is used for store expiry and for cookie max-age.
+    pub fn ttl(mut self, ttl: Millis) -> Self {
+        self.inner().ttl = ttl;
+        self
+    }
+
+    /// Extend session expiry on each request
+    ///
+    /// By default session expires after ttl since last change.
+    pub fn rolling_ttl(mut self, value: bool) -> Self {
+        self.inner().rolling = value;
+        self
+    }
+}
+
+impl<St> Inner<St> {
+    /// Get session key from request cookie
+    fn session_key<E>(&self, req: &WebRequest<E>) -> Option<String> {
+        let cookie = req.cookie(&self.name)?;
+        let mut jar = CookieJar::new();
+        jar.add_original(cookie);
+        let cookie = match self.security {
+            Security::Signed => jar.signed(&self.key).get(&self.name),
+            Security::Private => jar.private(&self.key).get(&self.name),
+        };
+        cookie.map(|c| c.value().to_string())
+    }
+
+    fn set_cookie(&self, res: &mut WebResponse, value: String) {
+        let mut cookie = Cookie::build((self.name.clone(), value))
+            .path(self.path.clone())
+            .secure(self.secure)
+            .http_only(self.http_only)
+            .same_site(self.same_site)
+            .max_age(time::Duration::milliseconds(self.ttl.0 as i64))
+            .build();
+        if let Some(ref domain) = self.domain {
+            cookie.set_domain(domain.clone());
+        }
+
+        let mut jar = CookieJar::new();
+        match self.security {
+            Security::Signed => jar.signed_mut(&self.key).add(cookie),
+            Security::Private => jar.private_mut(&self.key).add(cookie),
+        }
+        for cookie in jar.delta() {
+            let _ = res.response_mut().add_cookie(cookie.clone());
+        }
+    }
+
+    fn remove_cookie(&self, res: &mut WebResponse) {
+        let mut cookie = Cookie::build((self.name.clone(), ""))
+            .path(self.path.clone())
+            .build();
+        if let Some(ref domain) = self.domain {
+            cookie.set_domain(domain.clone());
+        }
+        cookie.make_removal();
+        let _ = res.response_mut().add_cookie(cookie);
+    }
+}
+
+impl<St: SessionStore> Inner<St> {
+    /// Store session changes and update session cookie
+    async fn store(
+        &self,
+        key: Option<String>,
+        session: &Session,
+        res: &mut WebResponse,
+    ) -> Result<(), SessionError> {
+        let (status, state) = {
+            let inner = session.0.borrow();
+            (inner.status, inner.state.clone())
+        };
+
+        match status {
+            SessionStatus::Unchanged => {
+                if let Some(key) = key {
+                    if self.rolling {
+                        self.store.update_ttl(&key, self.ttl).await?;
+                        self.set_cookie(res, key);
+                    }
+                }
+            }
+            SessionStatus::Changed => {
+                let key = self.store.save(key.as_deref(), &state, self.ttl).await?;
+                self.set_cookie(res, key);
+            }
+            SessionStatus::Renewed => {
+                if let Some(key) = key {
+                    self.store.delete(&key).await?;
+                }
+                let key = self.store.save(None, &state, self.ttl).await?;
+                self.set_cookie(res, key);
+            }
+            SessionStatus::Purged => {
+                if let Some(key) = key {
+                    self.store.delete(&key).await?;
+                    self.remove_cookie(res);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<St> fmt::Debug for Sessions<St> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sessions")
+            .field("name", &self.inner.name)
+            .field("security", &self.inner.security)
+            .field("ttl", &self.inner.ttl)
+            .field("rolling", &self.inner.rolling)
+            .finish()
+    }
+}
+
+impl<St> Clone for Sessions<St> {
+    fn clone(&self) -> Self {
+        Sessions {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<S, St> Middleware<S> for Sessions<St> {
+    type Service = SessionsMiddleware<S, St>;
+
+    fn create(&self, service: S) -> Self::Service {
+        SessionsMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct SessionsMiddleware<S, St> {
+    service: S,
+    inner: Rc<Inner<St>>,
+}
+
+impl<S, St> fmt::Debug for SessionsMiddleware<S, St> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionsMiddleware").finish()
+    }
+}
+
+impl<S, St, E> Service<WebRequest<E>> for SessionsMiddleware<S, St>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+    St: SessionStore,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    crate::forward_poll_ready!(service);
+    crate::forward_poll_shutdown!(service);
+
+    async fn call(
+        &self,
+        req: WebRequest<E>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        let mut key = self.inner.session_key(&req);
+        let state = if let Some(ref k) = key {
+            match self.inner.store.load(k).await {
+                Ok(Some(state)) => state,
+                Ok(None) => {
+                    key = None;
+                    SessionState::default()
+                }
+                Err(e) => {
+                    log::error!("Cannot load session: {}", e);
+                    return Ok(req.into_response(Response::InternalServerError().finish()));
+                }
+            }
+        } else {
+            SessionState::default()
+        };
+
+        let session = Session::new(state);
+        req.extensions_mut().insert(session.clone());
+
+        let mut res = ctx.call(&self.service, req).await?;
+        if let Err(e) = self.inner.store(key, &session, &mut res).await {
+            log::error!("Cannot store session: {}", e);
+            Ok(res.into_response(Response::InternalServerError().finish()))
+        } else {
+            Ok(res)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{header, StatusCode};
+    use crate::util::Bytes;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, DefaultError, HttpResponse};
+
+    fn session_cookie(res: &WebResponse) -> Option<Cookie<'static>> {
+        res.response()
+            .cookies()
+            .find(|c| c.name() == "session")
+            .map(|c| c.into_owned())
+    }
+
+    async fn counter(session: Session) -> Result<String, web::Error> {
+        let counter = session.get::<usize>("counter")?.unwrap_or(0) + 1;
+        session.insert("counter", counter)?;
+        Ok(counter.to_string())
+    }
+
+    #[crate::rt_test]
+    async fn test_memory_store() {
+        let key = Key::generate();
+        let store = MemorySessionStore::new();
+        let srv = init_service(
+            App::new()
+                .wrap(Sessions::new(store.clone(), key))
+                .route("/", web::get().to(counter))
+                .route(
+                    "/renew",
+                    web::get().to(|session: Session| async move {
+                        session.renew();
+                        HttpResponse::Ok()
+                    }),
+                )
+                .route(
+                    "/purge",
+                    web::get().to(|session: Session| async move {
+                        session.purge();
+                        HttpResponse::Ok()
+                    }),
+                )
+                .route("/none", web::get().to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+
+        let res = call_service(&srv, TestRequest::default().to_request()).await;
+        let cookie = session_cookie(&res).unwrap();
+        assert!(cookie.http_only().unwrap());
+        assert!(cookie.secure().unwrap());
+        assert_eq!(read_body(res).await, Bytes::from_static(b"1"));
+        assert_eq!(store.0.lock().unwrap().len(), 1);
+
+        let req = TestRequest::default().cookie(cookie.clone()).to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, Bytes::from_static(b"2"));
+
+        // unchanged session does not set cookie
+        let req = TestRequest::with_uri("/none")
+            .cookie(cookie.clone())
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert!(session_cookie(&res).is_none());
+
+        // tampered cookie
+        let req = TestRequest::default()
+            .cookie(Cookie::new("session", "0000"))
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, Bytes::from_static(b"1"));
+
+        // renew key
+        let req = TestRequest::with_uri("/renew")
+            .cookie(cookie.clone())
+            .to_request();
+        let res = call_service(&srv, req).await;
+        let cookie2 = session_cookie(&res).unwrap();
+        assert_ne!(cookie.value(), cookie2.value());
+
+        let req = TestRequest::default().cookie(cookie).to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, Bytes::from_static(b"1"));
+
+        let req = TestRequest::default().cookie(cookie2.clone()).to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, Bytes::from_static(b"3"));
+
+        // purge
+        let req = TestRequest::with_uri("/purge")
+            .cookie(cookie2.clone())
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(session_cookie(&res).unwrap().value(), "");
+
+        let req = TestRequest::default().cookie(cookie2).to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, Bytes::from_static(b"1"));
+    }
+
+    #[crate::rt_test]
+    async fn test_cookie_store() {
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    Sessions::new(CookieSessionStore, Key::generate())
+                        .signed()
+                        .cookie_secure(false)
+                        .rolling_ttl(true),
+                )
+                .route("/", web::get().to(counter))
+                .route("/none", web::get().to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+
+        let res = call_service(&srv, TestRequest::default().to_request()).await;
+        let cookie = session_cookie(&res).unwrap();
+        assert!(!cookie.value().is_empty());
+        assert!(cookie.secure().is_none());
+
+        let req = TestRequest::default().cookie(cookie.clone()).to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(read_body(res).await, Bytes::from_static(b"2"));
+
+        // rolling ttl updates cookie
+        let req = TestRequest::with_uri("/none").cookie(cookie).to_request();
+        let res = call_service(&srv, req).await;
+        assert!(res.headers().contains_key(header::SET_COOKIE));
+    }
+
+    #[crate::rt_test]
+    async fn test_session_without_middleware() {
+        let (req, mut pl) = TestRequest::default().to_http_parts();
+        let session = <Session as FromRequest<DefaultError>>::from_request(&req, &mut pl)
+            .await
+            .unwrap();
+        assert_eq!(session.get::<usize>("counter").unwrap(), None);
+        session.insert("counter", 1).unwrap();
+        assert_eq!(session.status(), SessionStatus::Changed);
+        session.purge();
+        assert_eq!(session.status(), SessionStatus::Purged);
+        assert!(session.entries().is_empty());
+    }
+}
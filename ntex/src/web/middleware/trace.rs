@@ -0,0 +1,430 @@
+//! Request tracing middleware
+use std::{fmt, rc::Rc};
+
+use nanorand::{Rng, WyRand};
+use tracing::{field::Empty, Instrument};
+
+use crate::http::header::{HeaderName, HeaderValue};
+use crate::http::Payload;
+use crate::service::{Middleware, Service, ServiceCtx};
+use crate::time::now;
+use crate::web::error::ErrorRenderer;
+use crate::web::{FromRequest, HttpRequest, WebRequest, WebResponse};
+
+/// W3C `traceparent` header
+pub const TRACEPARENT: HeaderName = HeaderName::from_static("traceparent");
+
+const FLAG_SAMPLED: u8 = 0x01;
+
+/// W3C trace context of the request
+///
+/// Context is extracted from `traceparent` header by `Tracing` middleware,
+/// new trace is started if request has no valid header. Use
+/// [`TraceContext::traceparent()`] to propagate trace to outgoing requests.
+///
+/// ```rust
+/// use ntex::web::{self, middleware::TraceContext};
+///
+/// async fn index(ctx: TraceContext) -> String {
+///     format!("Trace: {}", ctx.trace_id())
+/// }
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TraceContext {
+    trace_id: u128,
+    span_id: u64,
+    parent_id: Option<u64>,
+    flags: u8,
+}
+
+impl TraceContext {
+    /// Start new trace
+    pub fn new() -> Self {
+        let mut rng = WyRand::new();
+        let trace_id = loop {
+            let id = (rng.generate::<u64>() as u128) << 64 | rng.generate::<u64>() as u128;
+            if id != 0 {
+                break id;
+            }
+        };
+        TraceContext {
+            trace_id,
+            span_id: span_id(&mut rng),
+            parent_id: None,
+            flags: FLAG_SAMPLED,
+        }
+    }
+
+    /// Continue trace from `traceparent` header value
+    ///
+    /// Returns `None` if value is not valid.
+    pub fn from_traceparent(value: &str) -> Option<Self> {
+        let mut parts = value.trim().split('-');
+        let version = parts.next().filter(|v| hex(v, 2).is_some() && *v != "ff")?;
+        let trace_id = parts.next().and_then(|v| hex(v, 32)).filter(|v| *v != 0)?;
+        let parent_id = parts.next().and_then(|v| hex(v, 16)).filter(|v| *v != 0)?;
+        let flags = parts.next().and_then(|v| hex(v, 2))?;
+
+        // version 00 does not define additional fields
+        if version == "00" && parts.next().is_some() {
+            return None;
+        }
+
+        Some(TraceContext {
+            trace_id,
+            span_id: span_id(&mut WyRand::new()),
+            parent_id: Some(parent_id as u64),
+            flags: flags as u8,
+        })
+    }
+
+    /// Trace id, 32 hex digits
+    pub fn trace_id(&self) -> String {
+        format!("{:032x}", self.trace_id)
+    }
+
+    /// Span id of the request, 16 hex digits
+    pub fn span_id(&self) -> String {
+        format!("{:016x}", self.span_id)
+    }
+
+    /// Span id of the remote parent
+    pub fn parent_id(&self) -> Option<String> {
+        self.parent_id.map(|id| format!("{:016x}", id))
+    }
+
+    /// Check if trace is sampled by the caller
+    pub fn is_sampled(&self) -> bool {
+        self.flags & FLAG_SAMPLED != 0
+    }
+
+    /// `traceparent` header value for outgoing requests
+    pub fn traceparent(&self) -> String {
+        format!(
+            "00-{:032x}-{:016x}-{:02x}",
+            self.trace_id, self.span_id, self.flags
+        )
+    }
+}
+
+impl Default for TraceContext {
+    fn default() -> Self {
+        TraceContext::new()
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for TraceContext {
+    type Error = Err::Container;
+
+    #[inline]
+    async fn from_request(req: &HttpRequest, _: &mut Payload) -> Result<Self, Self::Error> {
+        Ok(req
+            .extensions()
+            .get::<TraceContext>()
+            .copied()
+            .unwrap_or_default())
+    }
+}
+
+fn span_id(rng: &mut WyRand) -> u64 {
+    loop {
+        let id = rng.generate::<u64>();
+        if id != 0 {
+            return id;
+        }
+    }
+}
+
+/// Parse lower case hex value of specified length
+fn hex(val: &str, len: usize) -> Option<u128> {
+    if val.len() == len && val.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+        u128::from_str_radix(val, 16).ok()
+    } else {
+        None
+    }
+}
+
+/// `Middleware` for request tracing.
+///
+/// Middleware creates `tracing` span for each request and instruments
+/// request handling with it. Span records request method, path, matched
+/// route pattern, response status, latency and W3C trace context ids,
+/// fields follow OpenTelemetry http semantic conventions. Service errors
+/// and server errors set `otel.status_code` field to `ERROR`.
+///
+/// Trace context is extracted from `traceparent` request header and is
+/// available to handlers via [`TraceContext`] extractor.
+///
+/// ```rust
+/// use ntex::web::{self, middleware::Tracing, App};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(Tracing::new())
+///         .route("/users/{id}", web::get().to(|| async { "user" }));
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Tracing {
+    inner: Rc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    accept_traceparent: bool,
+    response_traceparent: bool,
+}
+
+impl Tracing {
+    /// Construct tracing middleware
+    pub fn new() -> Self {
+        Tracing {
+            inner: Rc::new(Inner {
+                accept_traceparent: true,
+                response_traceparent: false,
+            }),
+        }
+    }
+
+    /// Continue trace from `traceparent` request header, enabled by default
+    ///
+    /// Disable for public facing services, new trace is started
+    /// for each request.
+    pub fn accept_traceparent(mut self, value: bool) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .accept_traceparent = value;
+        self
+    }
+
+    /// Add `traceparent` header with request's trace context to responses,
+    /// disabled by default
+    pub fn response_traceparent(mut self, value: bool) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .response_traceparent = value;
+        self
+    }
+}
+
+impl Default for Tracing {
+    fn default() -> Self {
+        Tracing::new()
+    }
+}
+
+impl<S> Middleware<S> for Tracing {
+    type Service = TracingMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        TracingMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct TracingMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S> fmt::Debug for TracingMiddleware<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TracingMiddleware")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<S, E> Service<WebRequest<E>> for TracingMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+    S::Error: fmt::Display,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    crate::forward_poll_ready!(service);
+    crate::forward_poll_shutdown!(service);
+
+    async fn call(
+        &self,
+        req: WebRequest<E>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        let trace = if self.inner.accept_traceparent {
+            req.headers()
+                .get(&TRACEPARENT)
+                .and_then(|val| val.to_str().ok())
+                .and_then(TraceContext::from_traceparent)
+                .unwrap_or_default()
+        } else {
+            TraceContext::new()
+        };
+        req.extensions_mut().insert(trace);
+
+        let span = tracing::info_span!(
+            "HTTP request",
+            http.request.method = %req.method(),
+            url.path = %req.path(),
+            http.route = Empty,
+            http.response.status_code = Empty,
+            latency_ms = Empty,
+            trace_id = %trace.trace_id(),
+            span_id = %trace.span_id(),
+            parent_span_id = Empty,
+            otel.kind = "server",
+            otel.status_code = Empty,
+            error = Empty,
+        );
+        if let Some(parent) = trace.parent_id() {
+            span.record("parent_span_id", parent.as_str());
+        }
+
+        let start = now();
+        let result = ctx.call(&self.service, req).instrument(span.clone()).await;
+        span.record("latency_ms", start.elapsed().as_millis() as u64);
+
+        match result {
+            Ok(mut res) => {
+                let status = res.status();
+                span.record("http.response.status_code", status.as_u16());
+                if let Some(pattern) = res.request().match_pattern() {
+                    span.record("http.route", pattern.as_str());
+                }
+                if status.is_server_error() {
+                    span.record("otel.status_code", "ERROR");
+                }
+                if self.inner.response_traceparent {
+                    if let Ok(val) = HeaderValue::try_from(trace.traceparent()) {
+                        res.headers_mut().insert(TRACEPARENT, val);
+                    }
+                }
+                Ok(res)
+            }
+            Err(e) => {
+                span.record("otel.status_code", "ERROR");
+                span.record("error", tracing::field::display(&e));
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, DefaultError};
+
+    #[test]
+    fn test_traceparent() {
+        let ctx = TraceContext::from_traceparent(
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        )
+        .unwrap();
+        assert_eq!(ctx.trace_id(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(ctx.parent_id().unwrap(), "00f067aa0ba902b7");
+        assert_ne!(ctx.span_id(), "00f067aa0ba902b7");
+        assert!(ctx.is_sampled());
+        assert_eq!(
+            ctx.traceparent(),
+            format!("00-4bf92f3577b34da6a3ce929d0e0e4736-{}-01", ctx.span_id())
+        );
+
+        for val in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-00",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        ] {
+            assert!(TraceContext::from_traceparent(val).is_none(), "{}", val);
+        }
+
+        // future versions could define additional fields
+        let ctx = TraceContext::from_traceparent(
+            "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00-extra",
+        )
+        .unwrap();
+        assert!(!ctx.is_sampled());
+
+        let ctx = TraceContext::new();
+        assert_eq!(ctx.trace_id().len(), 32);
+        assert_eq!(ctx.parent_id(), None);
+    }
+
+    #[crate::rt_test]
+    async fn test_tracing() {
+        let srv = init_service(
+            App::new()
+                .wrap(Tracing::new().response_traceparent(true))
+                .route(
+                    "/{id}",
+                    web::get().to(|ctx: TraceContext| async move { ctx.trace_id() }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/1")
+            .header(
+                TRACEPARENT,
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            )
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let header = res.headers().get(TRACEPARENT).unwrap().to_str().unwrap();
+        assert!(header.starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+        assert!(!header.contains("00f067aa0ba902b7"));
+        let body = read_body(res).await;
+        assert_eq!(body, "4bf92f3577b34da6a3ce929d0e0e4736");
+
+        // new trace
+        let req = TestRequest::with_uri("/1")
+            .header(TRACEPARENT, "invalid")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        let body = read_body(res).await;
+        assert_eq!(body.len(), 32);
+        assert_ne!(body, "4bf92f3577b34da6a3ce929d0e0e4736");
+    }
+
+    #[crate::rt_test]
+    async fn test_ignore_traceparent() {
+        let srv = init_service(
+            App::new()
+                .wrap(Tracing::new().accept_traceparent(false))
+                .route(
+                    "/",
+                    web::get().to(|ctx: TraceContext| async move {
+                        ctx.parent_id().unwrap_or_default()
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::default()
+            .header(
+                TRACEPARENT,
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            )
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert!(res.headers().get(TRACEPARENT).is_none());
+        assert_eq!(read_body(res).await, "");
+    }
+
+    #[crate::rt_test]
+    async fn test_extractor_without_middleware() {
+        let (req, mut pl) = TestRequest::default().to_http_parts();
+        let ctx = <TraceContext as FromRequest<DefaultError>>::from_request(&req, &mut pl)
+            .await
+            .unwrap();
+        assert_eq!(ctx.parent_id(), None);
+    }
+}
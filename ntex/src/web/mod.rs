@@ -51,28 +51,35 @@
 //! * *WebSockets* server/client
 //! * Transparent content compression/decompression (br, gzip, deflate)
 //! * Configurable request routing
-//! * SSL support with OpenSSL or `rustls`
-//! * Middlewares
+//! * SSL support with OpenSSL, `rustls` or `native-tls`
+//! * Middlewares, including load shedding
 //! * Supported Rust version: 1.41 or later
 //!
 //! ## Package feature
 //!
 //! * `cookie` - enables http cookie support
+//! * `session` - enables cookie based session middleware
+//! * `identity` - enables authentication middleware with cookie and bearer token policies
+//! * `tracing` - enables request tracing middleware with W3C trace context
 //! * `compress` - enables content encoding compression support
 //! * `openssl` - enables ssl support via `openssl` crate
 //! * `rustls` - enables ssl support via `rustls` crate
+//! * `native-tls` - enables ssl support via `native-tls` crate
 
 mod app;
 mod app_service;
 mod config;
 pub mod error;
 mod error_default;
+mod error_handler;
 mod extract;
+pub mod fs;
 pub mod guard;
 mod handler;
 mod httprequest;
 mod info;
 pub mod middleware;
+mod problem;
 mod request;
 mod resource;
 mod responder;
@@ -82,8 +89,10 @@ mod route;
 mod scope;
 mod server;
 mod service;
+pub mod sse;
 pub mod test;
 pub mod types;
+pub mod upload;
 mod util;
 pub mod ws;
 
@@ -106,9 +115,12 @@ pub use self::config::ServiceConfig;
 pub use self::error::{
     DefaultError, Error, ErrorContainer, ErrorRenderer, WebResponseError,
 };
+pub use self::error_handler::ErrorHandlers;
 pub use self::extract::FromRequest;
+pub use self::fs::Files;
 pub use self::handler::Handler;
 pub use self::httprequest::HttpRequest;
+pub use self::info::MatchInfo;
 pub use self::request::WebRequest;
 pub use self::resource::Resource;
 pub use self::responder::Responder;
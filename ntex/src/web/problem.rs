@@ -0,0 +1,226 @@
+//! Problem details for http apis, rfc7807
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::http::header::{self, HeaderMap};
+use crate::http::{Response, StatusCode};
+
+use super::error::{ErrorRenderer, WebResponseError};
+use super::HttpRequest;
+
+/// `application/problem+json` content type
+const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Standard problem details members
+const RESERVED: [&str; 5] = ["type", "title", "status", "detail", "instance"];
+
+/// Problem details error, rfc7807
+///
+/// Error is rendered as `application/problem+json` response.
+///
+/// ```rust
+/// use ntex::http::StatusCode;
+/// use ntex::web::{self, error::ProblemDetails, App};
+///
+/// async fn index() -> Result<String, ProblemDetails> {
+///     Err(ProblemDetails::new(StatusCode::FORBIDDEN)
+///         .problem_type("https://example.com/probs/out-of-credit")
+///         .title("You do not have enough credit.")
+///         .detail("Your current balance is 30, but that costs 50.")
+///         .extension("balance", 30))
+/// }
+///
+/// fn main() {
+///     let app = App::new().route("/", web::get().to(index));
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ProblemDetails {
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    problem_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(serialize_with = "serialize_status")]
+    status: StatusCode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instance: Option<String>,
+    #[serde(flatten)]
+    extensions: Map<String, Value>,
+}
+
+impl ProblemDetails {
+    /// Create problem details for status code
+    ///
+    /// Title is set to canonical reason of status code.
+    pub fn new(status: StatusCode) -> Self {
+        ProblemDetails {
+            status,
+            problem_type: None,
+            title: status.canonical_reason().map(|s| s.to_string()),
+            detail: None,
+            instance: None,
+            extensions: Map::new(),
+        }
+    }
+
+    /// Set problem type uri
+    ///
+    /// If type is not set, it is assumed to be `about:blank`.
+    pub fn problem_type<T: Into<String>>(mut self, uri: T) -> Self {
+        self.problem_type = Some(uri.into());
+        self
+    }
+
+    /// Set short summary of the problem type
+    pub fn title<T: Into<String>>(mut self, title: T) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Set status code
+    pub fn status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Set explanation specific to this occurrence of the problem
+    pub fn detail<T: Into<String>>(mut self, detail: T) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// Set uri reference that identifies this occurrence of the problem
+    pub fn instance<T: Into<String>>(mut self, instance: T) -> Self {
+        self.instance = Some(instance.into());
+        self
+    }
+
+    /// Add extension member
+    ///
+    /// Panics if name is one of standard members (`type`, `title`, `status`,
+    /// `detail`, `instance`) or if value cannot be serialized to json.
+    pub fn extension<T: Serialize>(mut self, name: &str, value: T) -> Self {
+        assert!(
+            !RESERVED.contains(&name),
+            "Extension name is reserved: {}",
+            name
+        );
+        let value = serde_json::to_value(value).expect("Cannot serialize extension");
+        self.extensions.insert(name.to_string(), value);
+        self
+    }
+
+    /// Get status code
+    pub fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Render problem details response
+    pub fn to_response(&self) -> Response {
+        Response::build(self.status)
+            .content_type(PROBLEM_JSON)
+            .json(self)
+    }
+
+    /// Render problem details response for error response
+    ///
+    /// Headers of original response are preserved, except content headers.
+    pub(super) fn from_response(
+        res: &Response,
+        detail: Option<String>,
+        req: &HttpRequest,
+    ) -> Response {
+        let mut problem = ProblemDetails::new(res.status()).instance(req.path());
+        problem.detail = detail;
+
+        let mut response = problem.to_response();
+        copy_headers(res.headers(), response.headers_mut());
+        response
+    }
+}
+
+fn copy_headers(from: &HeaderMap, to: &mut HeaderMap) {
+    for (name, value) in from.iter() {
+        if name != header::CONTENT_TYPE && name != header::CONTENT_LENGTH {
+            to.append(name.clone(), value.clone());
+        }
+    }
+}
+
+fn serialize_status<S: serde::Serializer>(
+    status: &StatusCode,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u16(status.as_u16())
+}
+
+impl fmt::Display for ProblemDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.title, &self.detail) {
+            (Some(title), Some(detail)) => write!(f, "{}: {}", title, detail),
+            (Some(msg), None) | (None, Some(msg)) => f.write_str(msg),
+            (None, None) => write!(f, "{}", self.status),
+        }
+    }
+}
+
+impl std::error::Error for ProblemDetails {}
+
+impl<Err: ErrorRenderer> WebResponseError<Err> for ProblemDetails {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self, _: &HttpRequest) -> Response {
+        self.to_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::test::TestRequest;
+    use crate::web::DefaultError;
+
+    #[test]
+    fn test_problem_details() {
+        let problem = ProblemDetails::new(StatusCode::FORBIDDEN)
+            .problem_type("https://example.com/probs/out-of-credit")
+            .detail("Your current balance is 30")
+            .instance("/account/12345")
+            .extension("balance", 30);
+        assert_eq!(problem.status_code(), StatusCode::FORBIDDEN);
+        assert_eq!(problem.to_string(), "Forbidden: Your current balance is 30");
+
+        let req = TestRequest::default().to_http_request();
+        let res = WebResponseError::<DefaultError>::error_response(&problem, &req);
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            PROBLEM_JSON
+        );
+
+        let body: Value = serde_json::from_slice(res.body().get_ref()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "type": "https://example.com/probs/out-of-credit",
+                "title": "Forbidden",
+                "status": 403,
+                "detail": "Your current balance is 30",
+                "instance": "/account/12345",
+                "balance": 30,
+            })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Extension name is reserved: status")]
+    fn test_reserved_extension() {
+        let _ = ProblemDetails::new(StatusCode::FORBIDDEN).extension("status", 200);
+    }
+}
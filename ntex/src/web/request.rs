@@ -9,8 +9,9 @@ use crate::util::Extensions;
 
 use super::config::AppConfig;
 use super::error::{ErrorRenderer, WebResponseError};
+use super::guard::GuardCtx;
 use super::httprequest::HttpRequest;
-use super::info::ConnectionInfo;
+use super::info::{ConnectionInfo, RouteInfo};
 use super::response::WebResponse;
 use super::rmap::ResourceMap;
 use super::service::AppState;
@@ -190,6 +191,17 @@ impl<Err> WebRequest<Err> {
         self.req.match_info_mut()
     }
 
+    #[inline]
+    /// Pattern of matched route, see `HttpRequest::match_pattern()`
+    pub fn match_pattern(&self) -> Option<String> {
+        self.req.match_pattern()
+    }
+
+    #[inline]
+    pub(crate) fn add_route(&mut self, route: Rc<RouteInfo>) {
+        self.req.add_route(route)
+    }
+
     #[inline]
     /// Get a reference to a `ResourceMap` of current application.
     pub fn resource_map(&self) -> &ResourceMap {
@@ -235,6 +247,16 @@ impl<Err> WebRequest<Err> {
         self.req.extensions()
     }
 
+    /// Guard context for current request
+    pub(super) fn guard_ctx(&self) -> GuardCtx<'_> {
+        GuardCtx::new(self.head(), &(self.req).0.app_state)
+    }
+
+    /// Http request for current web request
+    pub(super) fn http_request(&self) -> &HttpRequest {
+        &self.req
+    }
+
     /// Mutable reference to a the request's extensions
     #[inline]
     pub fn extensions_mut(&self) -> RefMut<'_, Extensions> {
@@ -8,14 +8,14 @@ use crate::service::{chain, chain_factory, ServiceCtx};
 use crate::service::{
     Identity, IntoServiceFactory, Middleware, Service, ServiceFactory, Stack,
 };
-use crate::util::Extensions;
+use crate::{time::Seconds, util::Extensions};
 
 use super::dev::{insert_slash, WebServiceConfig, WebServiceFactory};
 use super::extract::FromRequest;
 use super::handler::Handler;
 use super::request::WebRequest;
 use super::response::WebResponse;
-use super::route::{IntoRoutes, Route, RouteService};
+use super::route::{IntoRoutes, PayloadLimits, Route, RouteService};
 use super::{app::Filter, error::ErrorRenderer, guard::Guard, service::AppState};
 
 type HttpService<Err: ErrorRenderer> =
@@ -55,6 +55,7 @@ pub struct Resource<Err: ErrorRenderer, M = Identity, T = Filter<Err>> {
     routes: Vec<Route<Err>>,
     state: Option<Extensions>,
     guards: Vec<Box<dyn Guard>>,
+    limits: PayloadLimits,
     default: Rc<RefCell<Option<Rc<HttpNewService<Err>>>>>,
 }
 
@@ -68,6 +69,7 @@ impl<Err: ErrorRenderer> Resource<Err> {
             middleware: Identity,
             filter: chain_factory(Filter::new()),
             guards: Vec::new(),
+            limits: PayloadLimits::default(),
             default: Rc::new(RefCell::new(None)),
         }
     }
@@ -124,6 +126,15 @@ where
         self
     }
 
+    /// Take routes, routes inherit resource payload limits
+    fn take_routes(&mut self) -> Vec<Route<Err>> {
+        let mut routes = std::mem::take(&mut self.routes);
+        for route in &mut routes {
+            route.inherit_limits(&self.limits);
+        }
+        routes
+    }
+
     /// Provide resource specific state. This method allows to add extractor
     /// configuration or specific state available via `State<T>` extractor.
     /// Provided state is available for all routes registered for the current resource.
@@ -156,6 +167,46 @@ where
         self
     }
 
+    /// Set maximum size of request's payload for all resource routes.
+    ///
+    /// Requests with larger `Content-Length` get rejected with *413* response,
+    /// streamed payload fails with `PayloadError::Overflow` once limit is reached.
+    /// Route could override limit with `Route::payload_limit()` method.
+    ///
+    /// ```rust
+    /// use ntex::web::{self, App, HttpResponse};
+    ///
+    /// async fn upload(pl: web::types::Payload) -> HttpResponse {
+    ///     HttpResponse::Ok().finish()
+    /// }
+    ///
+    /// fn main() {
+    ///     let app = App::new().service(
+    ///         web::resource("/upload")
+    ///             .payload_limit(1024 * 1024 * 1024)
+    ///             .route(web::put().to(upload))
+    ///     );
+    /// }
+    /// ```
+    pub fn payload_limit(mut self, size: usize) -> Self {
+        self.limits.max_size(size);
+        self
+    }
+
+    /// Set read rate parameters for request's payload for all resource routes.
+    ///
+    /// Overrides server-wide `ServiceConfig::payload_read_rate()` setting.
+    /// Route could override read rate with `Route::payload_read_rate()` method.
+    pub fn payload_read_rate(
+        mut self,
+        timeout: Seconds,
+        max_timeout: Seconds,
+        rate: u16,
+    ) -> Self {
+        self.limits.read_rate(timeout, max_timeout, rate);
+        self
+    }
+
     /// Register a new route.
     ///
     /// ```rust
@@ -264,6 +315,7 @@ where
             name: self.name,
             state: self.state,
             guards: self.guards,
+            limits: self.limits,
             routes: self.routes,
             default: self.default,
         }
@@ -284,6 +336,7 @@ where
             name: self.name,
             state: self.state,
             guards: self.guards,
+            limits: self.limits,
             routes: self.routes,
             default: self.default,
         }
@@ -337,16 +390,16 @@ where
         }
 
         let state = self.state.take().map(|state| {
-            AppState::new(
+            AppState::nested(
                 state,
-                Some(config.state().clone()),
-                config.state().config().clone(),
+                config.state(),
+                format!("resource \"{}\"", self.rdef.join("|")),
             )
         });
 
         let router_factory = ResourceRouterFactory {
             state,
-            routes: self.routes,
+            routes: self.take_routes(),
             default: self.default.borrow_mut().take(),
         };
 
@@ -380,11 +433,11 @@ where
     Err: ErrorRenderer,
 {
     fn into_factory(
-        self,
+        mut self,
     ) -> ResourceServiceFactory<Err, M, ServiceChainFactory<F, WebRequest<Err>>> {
         let router_factory = ResourceRouterFactory {
             state: None,
-            routes: self.routes,
+            routes: self.take_routes(),
             default: self.default.borrow_mut().take(),
         };
 
@@ -469,7 +522,7 @@ impl<Err: ErrorRenderer> Service<WebRequest<Err>> for ResourceRouter<Err> {
         ctx: ServiceCtx<'_, Self>,
     ) -> Result<Self::Response, Self::Error> {
         for route in self.routes.iter() {
-            if route.check(&mut req) {
+            if route.check(&mut req) && route.check_async(&req).await {
                 if let Some(ref state) = self.state {
                     req.set_state_container(state.clone());
                 }
@@ -493,9 +546,9 @@ mod tests {
     use crate::http::{Method, StatusCode};
     use crate::time::{sleep, Millis};
     use crate::web::middleware::DefaultHeaders;
-    use crate::web::test::{call_service, init_service, TestRequest};
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
     use crate::web::{self, guard, request::WebRequest, App, DefaultError, HttpResponse};
-    use crate::{service::fn_service, util::Ready};
+    use crate::{service::fn_service, util::Bytes, util::Ready};
 
     #[crate::rt_test]
     async fn test_filter() {
@@ -567,6 +620,43 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::OK);
     }
 
+    #[crate::rt_test]
+    async fn test_pattern_constraints() {
+        let srv =
+            init_service(
+                App::new()
+                    .service(web::resource("/users/{id:uint}").to(
+                        |id: web::types::Path<u64>| async move { format!("id: {}", id) },
+                    ))
+                    .service(web::resource("/users/{name}").to(
+                        |name: web::types::Path<String>| async move {
+                            format!("name: {}", name)
+                        },
+                    ))
+                    .service(
+                        web::resource("/items/{key:uuid}")
+                            .to(|| async { HttpResponse::Ok() }),
+                    ),
+            )
+            .await;
+        let req = TestRequest::with_uri("/users/10").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"id: 10"));
+
+        let req = TestRequest::with_uri("/users/ntex").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"name: ntex"));
+
+        let req = TestRequest::with_uri("/items/67e55044-10b1-426f-9247-bb680e5fe0c8")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/items/10").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
     #[crate::rt_test]
     async fn test_default_resource() {
         let srv = init_service(
@@ -5,9 +5,8 @@ use crate::http::header::{HeaderMap, HeaderName, HeaderValue};
 use crate::http::{Response, ResponseBuilder, StatusCode};
 use crate::util::{Bytes, BytesMut, Either};
 
-use super::error::{
-    DefaultError, ErrorContainer, ErrorRenderer, InternalError, WebResponseError,
-};
+use super::error::{DefaultError, ErrorRenderer, InternalError, WebResponseError};
+use super::error_handler::ErrorHandlers;
 use super::httprequest::HttpRequest;
 
 /// Trait implemented by types that can be converted to a http response.
@@ -102,7 +101,7 @@ where
     async fn respond_to(self, req: &HttpRequest) -> Response {
         match self {
             Ok(val) => val.respond_to(req).await,
-            Err(e) => e.into().error_response(req),
+            Err(e) => ErrorHandlers::<Err>::render_error(&e.into(), req),
         }
     }
 }
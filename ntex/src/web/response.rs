@@ -3,7 +3,8 @@ use std::fmt;
 use crate::http::body::{Body, MessageBody, ResponseBody};
 use crate::http::{HeaderMap, Response, ResponseHead, StatusCode};
 
-use super::error::{ErrorContainer, ErrorRenderer};
+use super::error::ErrorRenderer;
+use super::error_handler::ErrorHandlers;
 use super::httprequest::HttpRequest;
 
 /// An service http response
@@ -24,7 +25,7 @@ impl WebResponse {
         request: HttpRequest,
     ) -> Self {
         let err = err.into();
-        let res: Response = err.error_response(&request);
+        let res: Response = ErrorHandlers::<Err>::render_error(&err, &request);
 
         if res.head().status == StatusCode::INTERNAL_SERVER_ERROR {
             log::error!("Internal Server Error: {:?}", err);
@@ -109,6 +110,14 @@ impl WebResponse {
         self.response.take_body()
     }
 
+    /// Drop request payload, if request is not shared
+    #[cfg(feature = "digest")]
+    pub(crate) fn drop_payload(&mut self) {
+        if let Some(inner) = std::rc::Rc::get_mut(&mut self.request.0) {
+            inner.payload = crate::http::Payload::None;
+        }
+    }
+
     /// Set a new body
     pub fn map_body<F>(self, f: F) -> WebResponse
     where
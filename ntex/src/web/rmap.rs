@@ -1,3 +1,5 @@
+#[cfg(feature = "url")]
+use std::{borrow::Borrow, collections, hash::BuildHasher, hash::Hash};
 use std::{cell::RefCell, rc::Rc};
 
 #[cfg(feature = "url")]
@@ -8,6 +10,10 @@ use crate::util::HashMap;
 #[cfg(feature = "url")]
 use crate::web::httprequest::HttpRequest;
 
+/// Fills path with resource pattern elements
+#[cfg(feature = "url")]
+type Fill<'a> = dyn FnMut(&ResourceDef, &mut String) -> bool + 'a;
+
 #[derive(Clone, Debug)]
 pub struct ResourceMap {
     #[allow(dead_code)]
@@ -62,10 +68,41 @@ impl ResourceMap {
         U: IntoIterator<Item = I>,
         I: AsRef<str>,
     {
-        let mut path = String::new();
         let mut elements = elements.into_iter();
+        self.build_url(req, name, &mut |pattern, path| {
+            pattern.resource_path(path, &mut elements)
+        })
+    }
+
+    /// Generate url for named resource from named parameters
+    ///
+    /// Check [`HttpRequest::url_for_map()`](../struct.HttpRequest.html#method.
+    /// url_for_map) for detailed information.
+    pub fn url_for_map<K, V, S>(
+        &self,
+        req: &HttpRequest,
+        name: &str,
+        elements: &collections::HashMap<K, V, S>,
+    ) -> Result<Url, super::error::UrlGenerationError>
+    where
+        K: Borrow<str> + Eq + Hash,
+        V: AsRef<str>,
+        S: BuildHasher,
+    {
+        self.build_url(req, name, &mut |pattern, path| {
+            pattern.resource_path_named(path, elements)
+        })
+    }
 
-        if self.patterns_for(name, &mut path, &mut elements)?.is_some() {
+    fn build_url(
+        &self,
+        req: &HttpRequest,
+        name: &str,
+        fill: &mut Fill<'_>,
+    ) -> Result<Url, super::error::UrlGenerationError> {
+        let mut path = String::new();
+
+        if self.patterns_for(name, &mut path, fill)?.is_some() {
             if path.starts_with('/') {
                 let conn = req.connection_info();
                 Ok(Url::parse(&format!(
@@ -97,38 +134,30 @@ impl ResourceMap {
     // false
     // }
 
-    fn patterns_for<U, I>(
+    fn patterns_for(
         &self,
         name: &str,
         path: &mut String,
-        elements: &mut U,
-    ) -> Result<Option<()>, super::error::UrlGenerationError>
-    where
-        U: Iterator<Item = I>,
-        I: AsRef<str>,
-    {
-        if self.pattern_for(name, path, elements)?.is_some() {
+        fill: &mut Fill<'_>,
+    ) -> Result<Option<()>, super::error::UrlGenerationError> {
+        if self.pattern_for(name, path, fill)?.is_some() {
             Ok(Some(()))
         } else {
-            self.parent_pattern_for(name, path, elements)
+            self.parent_pattern_for(name, path, fill)
         }
     }
 
-    fn pattern_for<U, I>(
+    fn pattern_for(
         &self,
         name: &str,
         path: &mut String,
-        elements: &mut U,
-    ) -> Result<Option<()>, super::error::UrlGenerationError>
-    where
-        U: Iterator<Item = I>,
-        I: AsRef<str>,
-    {
+        fill: &mut Fill<'_>,
+    ) -> Result<Option<()>, super::error::UrlGenerationError> {
         if let Some(pattern) = self.named.get(name) {
             if pattern.pattern().starts_with('/') {
-                self.fill_root(path, elements)?;
+                self.fill_root(path, fill)?;
             }
-            if pattern.resource_path(path, elements) {
+            if fill(pattern, path) {
                 Ok(Some(()))
             } else {
                 Err(super::error::UrlGenerationError::NotEnoughElements)
@@ -136,7 +165,7 @@ impl ResourceMap {
         } else {
             for (_, rmap) in &self.patterns {
                 if let Some(ref rmap) = rmap {
-                    if rmap.pattern_for(name, path, elements)?.is_some() {
+                    if rmap.pattern_for(name, path, fill)?.is_some() {
                         return Ok(Some(()));
                     }
                 }
@@ -145,45 +174,37 @@ impl ResourceMap {
         }
     }
 
-    fn fill_root<U, I>(
+    fn fill_root(
         &self,
         path: &mut String,
-        elements: &mut U,
-    ) -> Result<(), super::error::UrlGenerationError>
-    where
-        U: Iterator<Item = I>,
-        I: AsRef<str>,
-    {
+        fill: &mut Fill<'_>,
+    ) -> Result<(), super::error::UrlGenerationError> {
         if let Some(ref parent) = *self.parent.borrow() {
-            parent.fill_root(path, elements)?;
+            parent.fill_root(path, fill)?;
         }
-        if self.root.resource_path(path, elements) {
+        if fill(&self.root, path) {
             Ok(())
         } else {
             Err(super::error::UrlGenerationError::NotEnoughElements)
         }
     }
 
-    fn parent_pattern_for<U, I>(
+    fn parent_pattern_for(
         &self,
         name: &str,
         path: &mut String,
-        elements: &mut U,
-    ) -> Result<Option<()>, super::error::UrlGenerationError>
-    where
-        U: Iterator<Item = I>,
-        I: AsRef<str>,
-    {
+        fill: &mut Fill<'_>,
+    ) -> Result<Option<()>, super::error::UrlGenerationError> {
         if let Some(ref parent) = *self.parent.borrow() {
             if let Some(pattern) = parent.named.get(name) {
-                self.fill_root(path, elements)?;
-                if pattern.resource_path(path, elements) {
+                self.fill_root(path, fill)?;
+                if fill(pattern, path) {
                     Ok(Some(()))
                 } else {
                     Err(super::error::UrlGenerationError::NotEnoughElements)
                 }
             } else {
-                parent.parent_pattern_for(name, path, elements)
+                parent.parent_pattern_for(name, path, fill)
             }
         } else {
             Ok(None)
@@ -1,11 +1,13 @@
-use std::{fmt, mem, rc::Rc};
+use std::{fmt, mem, pin::Pin, rc::Rc, task::Context, task::Poll};
 
-use crate::{http::Method, service::Service, service::ServiceCtx, service::ServiceFactory};
+use crate::http::{error::PayloadError, header, Method, Payload};
+use crate::service::{Service, ServiceCtx, ServiceFactory};
+use crate::{time::Seconds, util::ready, util::Bytes, util::Stream};
 
 use super::error::ErrorRenderer;
 use super::error_default::DefaultError;
 use super::extract::FromRequest;
-use super::guard::{self, AllGuard, Guard};
+use super::guard::{self, AllGuard, AsyncGuard, Guard};
 use super::handler::{Handler, HandlerFn, HandlerWrapper};
 use super::request::WebRequest;
 use super::response::WebResponse;
@@ -19,6 +21,8 @@ pub struct Route<Err: ErrorRenderer = DefaultError> {
     handler: Rc<dyn HandlerFn<Err>>,
     methods: Vec<Method>,
     guards: Rc<AllGuard>,
+    async_guards: Rc<Vec<Box<dyn AsyncGuard>>>,
+    limits: PayloadLimits,
 }
 
 impl<Err: ErrorRenderer> Route<Err> {
@@ -28,9 +32,16 @@ impl<Err: ErrorRenderer> Route<Err> {
             handler: Rc::new(HandlerWrapper::new(|| async { HttpResponse::NotFound() })),
             methods: Vec::new(),
             guards: Default::default(),
+            async_guards: Default::default(),
+            limits: PayloadLimits::default(),
         }
     }
 
+    /// Use resource payload limits that are not set for the route
+    pub(super) fn inherit_limits(&mut self, limits: &PayloadLimits) {
+        self.limits.inherit(limits);
+    }
+
     pub(super) fn take_guards(&mut self) -> Vec<Box<dyn Guard>> {
         for m in &self.methods {
             Rc::get_mut(&mut self.guards)
@@ -45,7 +56,9 @@ impl<Err: ErrorRenderer> Route<Err> {
         RouteService {
             handler: self.handler.clone(),
             guards: self.guards.clone(),
+            async_guards: self.async_guards.clone(),
             methods: self.methods.clone(),
+            limits: self.limits,
         }
     }
 }
@@ -56,6 +69,8 @@ impl<Err: ErrorRenderer> fmt::Debug for Route<Err> {
             .field("handler", &self.handler)
             .field("methods", &self.methods)
             .field("guards", &self.guards)
+            .field("async_guards", &self.async_guards)
+            .field("limits", &self.limits)
             .finish()
     }
 }
@@ -75,6 +90,8 @@ pub struct RouteService<Err: ErrorRenderer> {
     handler: Rc<dyn HandlerFn<Err>>,
     methods: Vec<Method>,
     guards: Rc<AllGuard>,
+    async_guards: Rc<Vec<Box<dyn AsyncGuard>>>,
+    limits: PayloadLimits,
 }
 
 impl<Err: ErrorRenderer> RouteService<Err> {
@@ -83,7 +100,17 @@ impl<Err: ErrorRenderer> RouteService<Err> {
             return false;
         }
 
-        self.guards.check(req.head())
+        self.guards.check_ctx(&req.guard_ctx())
+    }
+
+    /// Check async guards, guards are checked in order of registration
+    pub(super) async fn check_async(&self, req: &WebRequest<Err>) -> bool {
+        for guard in self.async_guards.iter() {
+            if !guard.check(req.http_request().clone()).await {
+                return false;
+            }
+        }
+        true
     }
 }
 
@@ -93,6 +120,8 @@ impl<Err: ErrorRenderer> fmt::Debug for RouteService<Err> {
             .field("handler", &self.handler)
             .field("methods", &self.methods)
             .field("guards", &self.guards)
+            .field("async_guards", &self.async_guards)
+            .field("limits", &self.limits)
             .finish()
     }
 }
@@ -103,13 +132,104 @@ impl<Err: ErrorRenderer> Service<WebRequest<Err>> for RouteService<Err> {
 
     async fn call(
         &self,
-        req: WebRequest<Err>,
+        mut req: WebRequest<Err>,
         _: ServiceCtx<'_, Self>,
     ) -> Result<Self::Response, Self::Error> {
+        if !self.limits.apply(&mut req) {
+            return Ok(WebResponse::new(
+                HttpResponse::PayloadTooLarge().finish(),
+                req.into_parts().0,
+            ));
+        }
         self.handler.call(req).await
     }
 }
 
+#[derive(Copy, Clone, Debug, Default)]
+/// Route payload limits, override server-wide http settings
+pub(super) struct PayloadLimits {
+    max_size: Option<usize>,
+    read_rate: Option<(Seconds, Seconds, u16)>,
+}
+
+impl PayloadLimits {
+    pub(super) fn max_size(&mut self, size: usize) {
+        self.max_size = Some(size);
+    }
+
+    pub(super) fn read_rate(&mut self, timeout: Seconds, max_timeout: Seconds, rate: u16) {
+        self.read_rate = Some((timeout, max_timeout, rate));
+    }
+
+    fn inherit(&mut self, other: &PayloadLimits) {
+        if self.max_size.is_none() {
+            self.max_size = other.max_size;
+        }
+        if self.read_rate.is_none() {
+            self.read_rate = other.read_rate;
+        }
+    }
+
+    /// Apply limits to request payload, returns false if payload is too large
+    fn apply<Err>(&self, req: &mut WebRequest<Err>) -> bool {
+        if let Some((timeout, max_timeout, rate)) = self.read_rate {
+            let pl = req.take_payload();
+            pl.set_read_rate(timeout, max_timeout, rate);
+            req.set_payload(pl);
+        }
+
+        if let Some(max_size) = self.max_size {
+            let len = req
+                .headers()
+                .get(&header::CONTENT_LENGTH)
+                .and_then(|val| val.to_str().ok())
+                .and_then(|val| val.parse::<u64>().ok());
+            if let Some(len) = len {
+                if len > max_size as u64 {
+                    return false;
+                }
+            }
+
+            let pl = req.take_payload();
+            if !matches!(pl, Payload::None) {
+                req.set_payload(Payload::from_stream(LimitedPayload {
+                    pl,
+                    remaining: max_size,
+                }));
+            }
+        }
+        true
+    }
+}
+
+/// Payload stream that fails once size limit is reached
+struct LimitedPayload {
+    pl: Payload,
+    remaining: usize,
+}
+
+impl Stream for LimitedPayload {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        match ready!(this.pl.poll_recv(cx)) {
+            Some(Ok(chunk)) => {
+                if chunk.len() > this.remaining {
+                    // drop payload, rest of the data is not needed
+                    this.pl = Payload::None;
+                    Poll::Ready(Some(Err(PayloadError::Overflow)))
+                } else {
+                    this.remaining -= chunk.len();
+                    Poll::Ready(Some(Ok(chunk)))
+                }
+            }
+            item => Poll::Ready(item),
+        }
+    }
+}
+
 impl<Err: ErrorRenderer> Route<Err> {
     /// Add method guard to the route.
     ///
@@ -147,6 +267,78 @@ impl<Err: ErrorRenderer> Route<Err> {
         self
     }
 
+    /// Add async guard to the route.
+    ///
+    /// Async guards are checked after method and sync guards have matched,
+    /// in order of registration. Evaluation stops on first failed guard.
+    ///
+    /// ```rust
+    /// # use ntex::web::{self, *};
+    /// # fn main() {
+    /// App::new().service(web::resource("/path").route(
+    ///     web::get()
+    ///         .async_guard(guard::fn_async(|req: HttpRequest| async move {
+    ///             req.headers().contains_key("x-token")
+    ///         }))
+    ///         .to(|| async { HttpResponse::Ok() }))
+    /// );
+    /// # }
+    /// ```
+    pub fn async_guard<F: AsyncGuard + 'static>(mut self, f: F) -> Self {
+        Rc::get_mut(&mut self.async_guards)
+            .unwrap()
+            .push(Box::new(f));
+        self
+    }
+
+    /// Set maximum size of request's payload for the route.
+    ///
+    /// Requests with larger `Content-Length` get rejected with *413* response,
+    /// streamed payload fails with `PayloadError::Overflow` once limit is reached.
+    /// Payload extractors still use own limits, like `PayloadConfig`.
+    ///
+    /// Route limit overrides limit set by `Resource::payload_limit()` method.
+    ///
+    /// ```rust
+    /// use ntex::{time::Seconds, web::{self, App, HttpResponse}};
+    ///
+    /// async fn upload(pl: web::types::Payload) -> HttpResponse {
+    ///     HttpResponse::Ok().finish()
+    /// }
+    ///
+    /// fn main() {
+    ///     let app = App::new().service(
+    ///         web::resource("/upload").route(
+    ///             web::put()
+    ///                 .payload_limit(1024 * 1024 * 1024)
+    ///                 .payload_read_rate(Seconds(5), Seconds(3600), 1024)
+    ///                 .to(upload))
+    ///     );
+    /// }
+    /// ```
+    pub fn payload_limit(mut self, size: usize) -> Self {
+        self.limits.max_size(size);
+        self
+    }
+
+    /// Set read rate parameters for request's payload.
+    ///
+    /// Overrides server-wide `ServiceConfig::payload_read_rate()` setting
+    /// for the route. If the client sends `rate` amount of data within `timeout`
+    /// period of time, extend timeout by `timeout` seconds. But no more than
+    /// `max_timeout` timeout. To disable read rate check set `timeout` to 0.
+    ///
+    /// Read rate is applied to http/1 connections only.
+    pub fn payload_read_rate(
+        mut self,
+        timeout: Seconds,
+        max_timeout: Seconds,
+        rate: u16,
+    ) -> Self {
+        self.limits.read_rate(timeout, max_timeout, rate);
+        self
+    }
+
     /// Set handler function, use request extractors for parameters.
     ///
     /// ```rust
@@ -278,11 +470,13 @@ array_routes!(12, a, b, c, d, e, f, g, h, i, j, k, l);
 
 #[cfg(test)]
 mod tests {
+    use std::{cell::Cell, rc::Rc};
+
     use crate::http::{header, Method, StatusCode};
-    use crate::time::{sleep, Millis};
+    use crate::time::{sleep, Millis, Seconds};
     use crate::util::Bytes;
     use crate::web::test::{call_service, init_service, read_body, TestRequest};
-    use crate::web::{self, error, guard, App, DefaultError, HttpResponse};
+    use crate::web::{self, error, guard, App, DefaultError, HttpRequest, HttpResponse};
 
     #[derive(serde::Serialize, PartialEq, Debug)]
     struct MyObject {
@@ -373,4 +567,100 @@ mod tests {
         let body = read_body(resp).await;
         assert_eq!(body, Bytes::from_static(b"{\"name\":\"test\"}"));
     }
+
+    #[crate::rt_test]
+    async fn test_async_guard() {
+        let checked = Rc::new(Cell::new(0));
+        let checked2 = checked.clone();
+
+        let srv = init_service(App::new().service(web::resource("/test").route(vec![
+                web::get()
+                    .async_guard(guard::fn_async(|req: HttpRequest| async move {
+                        sleep(Millis(10)).await;
+                        req.headers().contains_key("x-token")
+                    }))
+                    .async_guard(guard::fn_async(move |_| {
+                        let checked = checked2.clone();
+                        async move {
+                            checked.set(checked.get() + 1);
+                            true
+                        }
+                    }))
+                    .to(|| async { HttpResponse::Ok() }),
+                web::get().to(|| async { HttpResponse::Forbidden() }),
+            ])))
+        .await;
+
+        let req = TestRequest::with_uri("/test").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(checked.get(), 0);
+
+        let req = TestRequest::with_uri("/test")
+            .header("x-token", "secret")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(checked.get(), 1);
+
+        // sync guards are checked first
+        let req = TestRequest::with_uri("/test")
+            .method(Method::POST)
+            .header("x-token", "secret")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(checked.get(), 1);
+    }
+
+    #[crate::rt_test]
+    async fn test_payload_limits() {
+        let srv = init_service(
+            App::new().service(
+                web::resource("/upload")
+                    .payload_limit(8)
+                    .payload_read_rate(Seconds(1), Seconds(5), 128)
+                    .route([
+                        web::put()
+                            .to(|body: Bytes| async move { HttpResponse::Ok().body(body) }),
+                        web::post()
+                            .payload_limit(16)
+                            .to(|body: Bytes| async move { HttpResponse::Ok().body(body) }),
+                    ]),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/upload")
+            .method(Method::PUT)
+            .header(header::CONTENT_LENGTH, "10")
+            .set_payload("0123456789")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let req = TestRequest::with_uri("/upload")
+            .method(Method::PUT)
+            .set_payload("0123456789")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let req = TestRequest::with_uri("/upload")
+            .method(Method::PUT)
+            .set_payload("0123")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"0123"));
+
+        let req = TestRequest::with_uri("/upload")
+            .method(Method::POST)
+            .header(header::CONTENT_LENGTH, "10")
+            .set_payload("0123456789")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"0123456789"));
+    }
 }
@@ -5,13 +5,15 @@ use crate::router::{IntoPattern, ResourceDef, Router};
 use crate::service::boxed::{self, BoxService, BoxServiceFactory};
 use crate::service::{chain_factory, dev::ServiceChainFactory, IntoServiceFactory};
 use crate::service::{Identity, Middleware, Service, ServiceCtx, ServiceFactory, Stack};
-use crate::util::Extensions;
+use crate::util::{BoxFuture, Extensions};
 
-use super::app::Filter;
+use super::app::{App, Filter};
 use super::config::ServiceConfig;
 use super::dev::{WebServiceConfig, WebServiceFactory};
 use super::error::ErrorRenderer;
-use super::guard::Guard;
+use super::error_handler::ErrorHandlers;
+use super::guard::{get_host_uri, Guard, HostGuard, HostPattern};
+use super::info::RouteInfo;
 use super::request::WebRequest;
 use super::resource::Resource;
 use super::response::WebResponse;
@@ -24,6 +26,7 @@ type HttpService<Err: ErrorRenderer> =
     BoxService<WebRequest<Err>, WebResponse, Err::Container>;
 type HttpNewService<Err: ErrorRenderer> =
     BoxServiceFactory<(), WebRequest<Err>, WebResponse, Err::Container, ()>;
+type FnStateFactory = Box<dyn Fn(Extensions) -> BoxFuture<'static, Result<Extensions, ()>>>;
 
 /// Resources scope.
 ///
@@ -59,8 +62,10 @@ pub struct Scope<Err: ErrorRenderer, M = Identity, T = Filter<Err>> {
     filter: ServiceChainFactory<T, WebRequest<Err>>,
     rdef: Vec<String>,
     state: Option<Extensions>,
+    state_factories: Vec<FnStateFactory>,
     services: Vec<Box<dyn AppServiceFactory<Err>>>,
     guards: Vec<Box<dyn Guard>>,
+    host: Option<HostPattern>,
     default: Rc<RefCell<Option<Rc<HttpNewService<Err>>>>>,
     external: Vec<ResourceDef>,
     case_insensitive: bool,
@@ -74,7 +79,9 @@ impl<Err: ErrorRenderer> Scope<Err> {
             filter: chain_factory(Filter::new()),
             rdef: path.patterns(),
             state: None,
+            state_factories: Vec::new(),
             guards: Vec::new(),
+            host: None,
             services: Vec::new(),
             default: Rc::new(RefCell::new(None)),
             external: Vec::new(),
@@ -83,6 +90,31 @@ impl<Err: ErrorRenderer> Scope<Err> {
     }
 }
 
+impl<Err: ErrorRenderer, M, T> Scope<Err, M, T> {
+    /// Create scope from application
+    pub(super) fn from_app<P: IntoPattern>(path: P, app: App<M, T, Err>) -> Self {
+        let state = if app.extensions.is_empty() && app.state_factories.is_empty() {
+            None
+        } else {
+            Some(app.extensions)
+        };
+
+        Scope {
+            middleware: app.middleware,
+            filter: app.filter,
+            rdef: path.patterns(),
+            state,
+            state_factories: app.state_factories,
+            guards: Vec::new(),
+            host: None,
+            services: app.services,
+            default: Rc::new(RefCell::new(app.default)),
+            external: app.external,
+            case_insensitive: app.case_insensitive,
+        }
+    }
+}
+
 impl<Err, M, T> Scope<Err, M, T>
 where
     T: ServiceFactory<
@@ -118,6 +150,36 @@ where
         self
     }
 
+    /// Match scope by request host.
+    ///
+    /// Host labels could be `*` or `{name}` wildcards, each wildcard matches
+    /// exactly one label. Values of named labels are available as path
+    /// parameters, i.e. via `HttpRequest::match_info()` or `Path` extractor.
+    /// Values are converted to lower case.
+    ///
+    /// Application level host scopes are indexed by host name and take
+    /// precedence over other services, if request path does not match any
+    /// host scope, request is routed to the rest of application services.
+    /// Scopes nested into other scopes use host guard.
+    ///
+    /// ```rust
+    /// use ntex::web::{self, types::Path, App};
+    ///
+    /// fn main() {
+    ///     let app = App::new().service(
+    ///         web::scope("/api")
+    ///             .host("{tenant}.example.com")
+    ///             .route("/{id}", web::get().to(|p: Path<(String, u32)>| async move {
+    ///                 format!("tenant: {}, id: {}", p.0, p.1)
+    ///             }))
+    ///     );
+    /// }
+    /// ```
+    pub fn host(mut self, pattern: &str) -> Self {
+        self.host = Some(HostPattern::new(pattern));
+        self
+    }
+
     /// Set or override application state. Application state could be accessed
     /// by using `State<T>` extractor where `T` is state type.
     ///
@@ -152,6 +214,31 @@ where
         self
     }
 
+    /// Register error handlers for scope.
+    ///
+    /// Scope handlers take precedence over handlers of parent scopes
+    /// and application, unmatched errors and status codes are passed
+    /// to parent handlers.
+    ///
+    /// ```rust
+    /// use ntex::http::StatusCode;
+    /// use ntex::web::{self, App, ErrorHandlers, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new().service(
+    ///         web::scope("/api")
+    ///             .error_handler(ErrorHandlers::new().status(
+    ///                 StatusCode::NOT_FOUND,
+    ///                 |_, _| HttpResponse::NotFound().json(&"not found"),
+    ///             ))
+    ///             .route("/index.html", web::get().to(|| async { HttpResponse::Ok() }))
+    ///     );
+    /// }
+    /// ```
+    pub fn error_handler(self, handlers: ErrorHandlers<Err>) -> Self {
+        self.state(handlers)
+    }
+
     /// Use ascii case-insensitive routing.
     ///
     /// Only static segments could be case-insensitive.
@@ -273,6 +360,24 @@ where
     /// Default service to be used if no matching route could be found.
     ///
     /// If default resource is not registered, app's default resource is being used.
+    /// Unmatched sub-path, request path relative to the scope path, is available
+    /// via `req.match_info().unprocessed()`, so default service could be used
+    /// for mounting sub-routers or file servers.
+    ///
+    /// ```rust
+    /// use ntex::service::fn_service;
+    /// use ntex::web::{self, App, DefaultError, HttpResponse, WebRequest};
+    ///
+    /// let app = App::new().service(
+    ///     web::scope("/mount").default_service(fn_service(
+    ///         |req: WebRequest<DefaultError>| async move {
+    ///             // "/a/b" for "/mount/a/b" request
+    ///             let path = req.match_info().unprocessed().to_string();
+    ///             Ok(req.into_response(HttpResponse::Ok().body(path)))
+    ///         },
+    ///     )),
+    /// );
+    /// ```
     pub fn default_service<F, S>(mut self, f: F) -> Self
     where
         F: IntoServiceFactory<S, WebRequest<Err>>,
@@ -324,7 +429,9 @@ where
             middleware: self.middleware,
             rdef: self.rdef,
             state: self.state,
+            state_factories: self.state_factories,
             guards: self.guards,
+            host: self.host,
             services: self.services,
             default: self.default,
             external: self.external,
@@ -348,7 +455,9 @@ where
             filter: self.filter,
             rdef: self.rdef,
             state: self.state,
+            state_factories: self.state_factories,
             guards: self.guards,
+            host: self.host,
             services: self.services,
             default: self.default,
             external: self.external,
@@ -371,15 +480,19 @@ where
 {
     fn register(mut self, config: &mut WebServiceConfig<Err>) {
         // update default resource if needed
-        if self.default.borrow().is_none() {
+        let has_default = self.default.borrow().is_some();
+        if !has_default {
             *self.default.borrow_mut() = Some(config.default_service());
         }
 
-        let state = self.state.take().map(|state| {
-            AppState::new(
+        let state = self.state.take().map(|mut state| {
+            if let Some(handlers) = state.remove::<ErrorHandlers<Err>>() {
+                state.insert(handlers.finish(config.state().get()));
+            }
+            AppState::nested(
                 state,
-                Some(config.state().clone()),
-                config.state().config().clone(),
+                config.state(),
+                format!("scope \"{}\"", self.rdef.join("|")),
             )
         });
 
@@ -397,9 +510,17 @@ where
             rmap.add(&mut rdef, None);
         }
 
+        // routes of application level host scope
+        let mut routes = Router::build();
+        if self.case_insensitive {
+            routes.case_insensitive();
+        }
+
         // complete scope pipeline creation
         let router_factory = ScopeRouterFactory {
             state,
+            host: self.host.clone(),
+            state_factories: Rc::new(self.state_factories),
             default: self.default.borrow_mut().take(),
             case_insensitive: self.case_insensitive,
             services: cfg
@@ -413,13 +534,29 @@ where
                     } else {
                         rdef
                     };
+                    let info = RouteInfo::new(&rdef, nested.is_some());
                     rmap.add(&mut rdef, nested);
-                    (rdef, srv, RefCell::new(guards))
+                    routes.rdef(rdef.clone(), ());
+                    (rdef, srv, RefCell::new(guards), info)
                 })
                 .collect(),
         };
 
-        // get guards
+        let factory = ScopeServiceFactory {
+            middleware: self.middleware,
+            filter: self.filter,
+            routing: router_factory,
+        };
+
+        // application level host scopes are indexed by host
+        let host = match self.host {
+            Some(host) if config.is_root() => Some(host),
+            Some(host) => {
+                self.guards.push(Box::new(HostGuard(host, None)));
+                None
+            }
+            None => None,
+        };
         let guards = if self.guards.is_empty() {
             None
         } else {
@@ -427,16 +564,25 @@ where
         };
 
         // register final service
-        config.register_service(
-            ResourceDef::root_prefix(self.rdef),
-            guards,
-            ScopeServiceFactory {
-                middleware: self.middleware,
-                filter: self.filter,
-                routing: router_factory,
-            },
-            Some(Rc::new(rmap)),
-        )
+        let rdef = ResourceDef::root_prefix(self.rdef);
+        if let Some(host) = host {
+            // scope with custom default service handles any path
+            let routes = if has_default {
+                None
+            } else {
+                Some(routes.finish())
+            };
+            config.register_host_service(
+                host,
+                rdef,
+                guards,
+                factory,
+                Some(Rc::new(rmap)),
+                routes,
+            )
+        } else {
+            config.register_service(rdef, guards, factory, Some(Rc::new(rmap)))
+        }
     }
 }
 
@@ -508,7 +654,14 @@ where
 
 struct ScopeRouterFactory<Err: ErrorRenderer> {
     state: Option<AppState>,
-    services: Vec<(ResourceDef, HttpNewService<Err>, RefCell<Option<Guards>>)>,
+    host: Option<HostPattern>,
+    state_factories: Rc<Vec<FnStateFactory>>,
+    services: Vec<(
+        ResourceDef,
+        HttpNewService<Err>,
+        RefCell<Option<Guards>>,
+        Rc<RouteInfo>,
+    )>,
     default: Option<Rc<HttpNewService<Err>>>,
     case_insensitive: bool,
 }
@@ -520,14 +673,28 @@ impl<Err: ErrorRenderer> ServiceFactory<WebRequest<Err>> for ScopeRouterFactory<
     type Service = ScopeRouter<Err>;
 
     async fn create(&self, _: ()) -> Result<Self::Service, Self::InitError> {
+        // mounted app state factories
+        if let Some(ref state) = self.state {
+            if !self.state_factories.is_empty() {
+                let mut extensions = Extensions::new();
+                for fut in self.state_factories.iter() {
+                    extensions = fut(extensions)
+                        .await
+                        .map_err(|_| log::error!("Cannot initialize state factory"))?
+                }
+                state.set_deferred(extensions);
+            }
+        }
+
         // create http services
         let mut router = Router::build();
         if self.case_insensitive {
             router.case_insensitive();
         }
-        for (path, factory, guards) in &mut self.services.iter() {
+        for (path, factory, guards, info) in &mut self.services.iter() {
             let service = factory.create(()).await?;
-            router.rdef(path.clone(), service).2 = guards.borrow_mut().take();
+            router.rdef(path.clone(), (service, info.clone())).2 =
+                guards.borrow_mut().take();
         }
 
         let default = if let Some(ref default) = self.default {
@@ -540,13 +707,15 @@ impl<Err: ErrorRenderer> ServiceFactory<WebRequest<Err>> for ScopeRouterFactory<
             default,
             router: router.finish(),
             state: self.state.clone(),
+            host: self.host.clone(),
         })
     }
 }
 
 struct ScopeRouter<Err: ErrorRenderer> {
     state: Option<AppState>,
-    router: Router<HttpService<Err>, Vec<Box<dyn Guard>>>,
+    host: Option<HostPattern>,
+    router: Router<(HttpService<Err>, Rc<RouteInfo>), Vec<Box<dyn Guard>>>,
     default: Option<HttpService<Err>>,
 }
 
@@ -559,10 +728,22 @@ impl<Err: ErrorRenderer> Service<WebRequest<Err>> for ScopeRouter<Err> {
         mut req: WebRequest<Err>,
         ctx: ServiceCtx<'_, Self>,
     ) -> Result<Self::Response, Self::Error> {
+        // host labels
+        if let Some(ref host) = self.host {
+            if let Some(uri) = get_host_uri(req.head()) {
+                if let Some(params) = uri.host().and_then(|h| host.match_host(h)) {
+                    for (name, value) in params {
+                        req.match_info_mut().add(name, value.to_ascii_lowercase());
+                    }
+                }
+            }
+        }
+
         let res = self.router.recognize_checked(&mut req, |req, guards| {
             if let Some(guards) = guards {
+                let ctx = req.guard_ctx();
                 for f in guards {
-                    if !f.check(req.head()) {
+                    if !f.check_ctx(&ctx) {
                         return false;
                     }
                 }
@@ -570,12 +751,16 @@ impl<Err: ErrorRenderer> Service<WebRequest<Err>> for ScopeRouter<Err> {
             true
         });
 
-        if let Some((srv, _info)) = res {
+        if let Some(((srv, info), _)) = res {
+            req.add_route(info.clone());
             if let Some(ref state) = self.state {
                 req.set_state_container(state.clone());
             }
             ctx.call(srv, req).await
         } else if let Some(ref default) = self.default {
+            if let Some(ref state) = self.state {
+                req.set_state_container(state.clone());
+            }
             ctx.call(default, req).await
         } else {
             let req = req.into_parts().0;
@@ -854,6 +1039,120 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::NO_CONTENT);
     }
 
+    #[crate::rt_test]
+    async fn test_scope_host() {
+        let srv =
+            init_service(
+                App::new()
+                    .service(web::scope("/app").host("{tenant}.example.com").service(
+                        web::resource("/{id}").to(|r: HttpRequest| async move {
+                            HttpResponse::Ok().body(format!(
+                                "{}:{}",
+                                &r.match_info()["tenant"],
+                                &r.match_info()["id"]
+                            ))
+                        }),
+                    ))
+                    .service(web::scope_host("*.example.org").service(
+                        web::resource("/").to(|| async { HttpResponse::NoContent() }),
+                    ))
+                    .service(
+                        web::resource("/app/{id}").to(|| async { HttpResponse::Created() }),
+                    ),
+            )
+            .await;
+
+        let req = TestRequest::with_uri("/app/1")
+            .header("host", "Acme.example.com:8080")
+            .to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"acme:1"));
+
+        let req = TestRequest::with_uri("/app/1")
+            .header("host", "example.com")
+            .to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+
+        let req = TestRequest::with_uri("/")
+            .header("host", "www.example.org")
+            .to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+        let req = TestRequest::with_uri("/")
+            .header("host", "example.org")
+            .to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[crate::rt_test]
+    async fn test_virtual_hosts() {
+        let srv = init_service(
+            App::new()
+                .service(web::scope_host("api.example.com").service(
+                    web::resource("/").to(|| async { HttpResponse::Ok().body("api") }),
+                ))
+                .service(web::scope_host("*.example.com").service(
+                    web::resource("/").to(|| async { HttpResponse::Ok().body("any") }),
+                ))
+                .service(web::scope_host("api.example.com").service(
+                    web::resource("/v1").to(|| async { HttpResponse::Ok().body("v1") }),
+                ))
+                .service(
+                    web::scope("/nested").service(
+                        web::scope_host("www.example.com").service(
+                            web::resource("/").to(|| async { HttpResponse::Ok() }),
+                        ),
+                    ),
+                )
+                .service(web::resource("/").to(|| async { HttpResponse::Created() }))
+                .service(web::resource("/other").to(|| async { HttpResponse::Accepted() })),
+        )
+        .await;
+
+        for (host, path, body) in [
+            ("API.example.com.", "/", "api"),
+            ("api.example.com:8080", "/v1", "v1"),
+            ("www.example.com", "/", "any"),
+        ] {
+            let req = TestRequest::with_uri(path)
+                .header("host", host)
+                .to_request();
+            let resp = srv.call(req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert_eq!(read_body(resp).await, Bytes::from(body));
+        }
+
+        let req = TestRequest::with_uri("/")
+            .header("host", "example.com")
+            .to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+
+        // paths unknown to host scopes are routed to application services
+        let req = TestRequest::with_uri("/other")
+            .header("host", "api.example.com")
+            .to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+
+        // nested host scopes are checked with guards
+        let req = TestRequest::with_uri("/nested/")
+            .header("host", "www.example.com")
+            .to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/nested/")
+            .header("host", "api.example.com")
+            .to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
     #[crate::rt_test]
     async fn test_scope_variable_segment() {
         let srv = init_service(App::new().service(web::scope("/ab-{project}").service(
@@ -1061,6 +1360,53 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::NOT_FOUND);
     }
 
+    #[crate::rt_test]
+    async fn test_default_resource_sub_path() {
+        let default = || {
+            fn_service(|r: WebRequest<DefaultError>| async move {
+                let path = r.match_info().unprocessed().to_string();
+                Ok(r.into_response(HttpResponse::Ok().body(path)))
+            })
+        };
+        let srv = init_service(
+            App::new().service(
+                web::scope("/mount")
+                    .service(
+                        web::resource("/known").to(|| async { HttpResponse::Created() }),
+                    )
+                    .service(web::scope("/{name}/nested").default_service(default()))
+                    .default_service(default()),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/mount/known").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+
+        let req = TestRequest::with_uri("/mount/a/b").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"/a/b"));
+
+        let req = TestRequest::with_uri("/mount/x/nested/a/b").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"/a/b"));
+    }
+
+    #[crate::rt_test]
+    async fn test_tail_pattern() {
+        let srv = init_service(App::new().service(web::scope("/app").route(
+            "/assets/{path:.*}",
+            web::get().to(|p: web::types::Path<String>| async move { p.into_inner() }),
+        )))
+        .await;
+
+        let req = TestRequest::with_uri("/app/assets/css/main.css").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"css/main.css"));
+    }
+
     #[crate::rt_test]
     async fn test_default_resource_propagation() {
         let srv = init_service(
@@ -1233,4 +1579,31 @@ mod tests {
             Bytes::from_static(b"http://localhost:8080/a/b/c/12345")
         );
     }
+
+    #[cfg(feature = "url")]
+    #[crate::rt_test]
+    async fn test_url_for_map_nested() {
+        let srv = init_service(App::new().service(web::scope("/{project}").service(
+            web::scope("/b").service(web::resource("/c/{stuff}").name("c").route(
+                web::get().to(|req: HttpRequest| async move {
+                    let params = std::collections::HashMap::from([
+                        ("project", "ntex"),
+                        ("stuff", "12345"),
+                    ]);
+                    HttpResponse::Ok()
+                        .body(format!("{}", req.url_for_map("c", &params).unwrap()))
+                }),
+            )),
+        )))
+        .await;
+
+        let req = TestRequest::with_uri("/a/b/c/test").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = read_body(resp).await;
+        assert_eq!(
+            body,
+            Bytes::from_static(b"http://localhost:8080/ntex/b/c/12345")
+        );
+    }
 }
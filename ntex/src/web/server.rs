@@ -21,6 +21,8 @@ struct Config {
     ssl_handshake_timeout: Seconds,
     headers_read_rate: Option<ReadRate>,
     payload_read_rate: Option<ReadRate>,
+    preserve_header_case: bool,
+    flush_strategy: http::FlushStrategy,
     pool: PoolId,
 }
 
@@ -44,6 +46,8 @@ impl Config {
         if let Some(hdrs) = self.payload_read_rate {
             svc_cfg.payload_read_rate(hdrs.timeout, hdrs.max_timeout, hdrs.rate);
         }
+        svc_cfg.preserve_header_case(self.preserve_header_case);
+        svc_cfg.flush_strategy(self.flush_strategy);
         svc_cfg
     }
 }
@@ -107,6 +111,8 @@ where
                     max_timeout: Seconds(13),
                 }),
                 payload_read_rate: None,
+                preserve_header_case: false,
+                flush_strategy: http::FlushStrategy::Immediate,
                 pool: PoolId::P0,
             })),
             backlog: 1024,
@@ -218,6 +224,28 @@ where
         self
     }
 
+    /// Preserve original casing of http/1 header names.
+    ///
+    /// Original casing of request header names is stored in request's
+    /// `HeaderMap`, header names with recorded casing are written verbatim.
+    ///
+    /// By default header case preservation is disabled.
+    pub fn preserve_header_case(self, enabled: bool) -> Self {
+        self.config.lock().unwrap().preserve_header_case = enabled;
+        self
+    }
+
+    /// Set http/1 response flushing strategy.
+    ///
+    /// Response data could be passed to the io immediately or coalesced
+    /// to reduce number of socket writes, see [`FlushStrategy`](http::FlushStrategy).
+    ///
+    /// By default response data is flushed immediately.
+    pub fn flush_strategy(self, strategy: http::FlushStrategy) -> Self {
+        self.config.lock().unwrap().flush_strategy = strategy;
+        self
+    }
+
     /// Set read rate parameters for request headers.
     ///
     /// Set max timeout for reading request headers. If the client
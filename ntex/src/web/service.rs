@@ -1,13 +1,13 @@
-use std::rc::Rc;
+use std::{cell::OnceCell, rc::Rc};
 
-use crate::router::{IntoPattern, ResourceDef};
+use crate::router::{IntoPattern, ResourceDef, Router};
 use crate::service::{boxed, IntoServiceFactory, ServiceFactory};
 use crate::util::Extensions;
 
 use super::config::AppConfig;
 use super::dev::insert_slash;
 use super::error::ErrorRenderer;
-use super::guard::{AllGuard, Guard};
+use super::guard::{AllGuard, Guard, HostPattern};
 use super::{request::WebRequest, response::WebResponse, rmap::ResourceMap};
 
 pub trait WebServiceFactory<Err: ErrorRenderer> {
@@ -45,6 +45,14 @@ where
 type Guards = Vec<Box<dyn Guard>>;
 type HttpServiceFactory<Err: ErrorRenderer> =
     boxed::BoxServiceFactory<(), WebRequest<Err>, WebResponse, Err::Container, ()>;
+type HostService<Err: ErrorRenderer> = (
+    HostPattern,
+    ResourceDef,
+    HttpServiceFactory<Err>,
+    Option<Guards>,
+    Option<Rc<ResourceMap>>,
+    Option<Router<()>>,
+);
 
 #[derive(Debug, Clone)]
 pub(crate) struct AppState(Rc<AppStateInner>);
@@ -52,8 +60,10 @@ pub(crate) struct AppState(Rc<AppStateInner>);
 #[derive(Debug)]
 struct AppStateInner {
     ext: Extensions,
+    deferred: OnceCell<Extensions>,
     parent: Option<AppState>,
     config: AppConfig,
+    scope: String,
 }
 
 impl AppState {
@@ -65,7 +75,20 @@ impl AppState {
         AppState(Rc::new(AppStateInner {
             ext,
             parent,
+            deferred: OnceCell::new(),
             config,
+            scope: "app".to_string(),
+        }))
+    }
+
+    /// Create state for nested scope or resource
+    pub(crate) fn nested(ext: Extensions, parent: &AppState, scope: String) -> Self {
+        AppState(Rc::new(AppStateInner {
+            ext,
+            scope,
+            parent: Some(parent.clone()),
+            deferred: OnceCell::new(),
+            config: parent.config().clone(),
         }))
     }
 
@@ -73,8 +96,19 @@ impl AppState {
         &self.0.config
     }
 
+    /// Set state constructed by async state factories
+    pub(crate) fn set_deferred(&self, ext: Extensions) {
+        if self.0.deferred.set(ext).is_err() {
+            log::error!("State is already initialized");
+        }
+    }
+
     pub(crate) fn get<T: 'static>(&self) -> Option<&T> {
-        let result = self.0.ext.get::<T>();
+        let result = self
+            .0
+            .ext
+            .get::<T>()
+            .or_else(|| self.0.deferred.get().and_then(|ext| ext.get::<T>()));
         if result.is_some() {
             result
         } else if let Some(parent) = self.0.parent.as_ref() {
@@ -84,8 +118,26 @@ impl AppState {
         }
     }
 
+    /// Names of scopes searched during state lookup, innermost first
+    pub(crate) fn scopes(&self) -> Vec<String> {
+        let mut scopes = vec![self.0.scope.clone()];
+        let mut state = self;
+        while let Some(parent) = state.0.parent.as_ref() {
+            scopes.push(parent.0.scope.clone());
+            state = parent;
+        }
+        scopes
+    }
+
     pub(crate) fn contains<T: 'static>(&self) -> bool {
-        if self.0.ext.contains::<T>() {
+        if self.0.ext.contains::<T>()
+            || self
+                .0
+                .deferred
+                .get()
+                .map(|ext| ext.contains::<T>())
+                .unwrap_or(false)
+        {
             true
         } else if let Some(parent) = self.0.parent.as_ref() {
             parent.contains::<T>()
@@ -106,6 +158,7 @@ pub struct WebServiceConfig<Err: ErrorRenderer> {
         Option<Guards>,
         Option<Rc<ResourceMap>>,
     )>,
+    hosts: Vec<HostService<Err>>,
 }
 
 impl<Err: ErrorRenderer> WebServiceConfig<Err> {
@@ -116,6 +169,7 @@ impl<Err: ErrorRenderer> WebServiceConfig<Err> {
             default,
             root: true,
             services: Vec::new(),
+            hosts: Vec::new(),
         }
     }
 
@@ -139,11 +193,17 @@ impl<Err: ErrorRenderer> WebServiceConfig<Err> {
         self.services
     }
 
+    /// Take services registered for host patterns
+    pub(super) fn take_hosts(&mut self) -> Vec<HostService<Err>> {
+        std::mem::take(&mut self.hosts)
+    }
+
     pub(crate) fn clone_config(&self, state: Option<AppState>) -> Self {
         WebServiceConfig {
             state: state.unwrap_or_else(|| self.state.clone()),
             default: self.default.clone(),
             services: Vec::new(),
+            hosts: Vec::new(),
             root: false,
         }
     }
@@ -177,6 +237,38 @@ impl<Err: ErrorRenderer> WebServiceConfig<Err> {
         self.services
             .push((rdef, boxed::factory(factory.into_factory()), guards, nested));
     }
+
+    /// Register http service for requests with matching host
+    ///
+    /// Host services are indexed by host name and are checked before
+    /// other services. Service is selected only if request path matches
+    /// one of `routes`, `None` matches any path.
+    pub(super) fn register_host_service<F, S>(
+        &mut self,
+        host: HostPattern,
+        rdef: ResourceDef,
+        guards: Option<Vec<Box<dyn Guard>>>,
+        factory: F,
+        nested: Option<Rc<ResourceMap>>,
+        routes: Option<Router<()>>,
+    ) where
+        F: IntoServiceFactory<S, WebRequest<Err>>,
+        S: ServiceFactory<
+                WebRequest<Err>,
+                Response = WebResponse,
+                Error = Err::Container,
+                InitError = (),
+            > + 'static,
+    {
+        self.hosts.push((
+            host,
+            rdef,
+            boxed::factory(factory.into_factory()),
+            guards,
+            nested,
+            routes,
+        ));
+    }
 }
 
 /// Create service adapter for a specific path.
@@ -0,0 +1,368 @@
+//! Server-Sent Events support
+//!
+//! ```rust
+//! use ntex::{channel::mpsc, rt, time::Seconds};
+//! use ntex::web::{self, sse, App};
+//!
+//! async fn events(last_id: sse::LastEventId) -> impl web::Responder {
+//!     let start: u64 = last_id.get().and_then(|id| id.parse().ok()).unwrap_or(0);
+//!
+//!     let (tx, rx) = mpsc::channel::<Result<sse::Event, std::io::Error>>();
+//!     rt::spawn(async move {
+//!         for id in start + 1..start + 10 {
+//!             let ev = sse::Event::new(format!("event #{}", id)).id(id.to_string());
+//!             if tx.send(Ok(ev)).is_err() {
+//!                 break;
+//!             }
+//!         }
+//!     });
+//!     sse::EventStream::new(rx).keep_alive(Seconds(30))
+//! }
+//!
+//! fn main() {
+//!     let app = App::new().route("/events", web::get().to(events));
+//! }
+//! ```
+use std::{error::Error, fmt::Write, pin::Pin, task::Context, task::Poll};
+
+use crate::http::header::{self, HeaderName};
+use crate::http::{Payload, Response, StatusCode};
+use crate::time::{sleep, Millis, Seconds, Sleep};
+use crate::util::{Bytes, BytesMut, Stream};
+
+use super::{ErrorRenderer, FromRequest, HttpRequest, Responder};
+
+/// `Last-Event-ID` header name
+pub const LAST_EVENT_ID: HeaderName = HeaderName::from_static("last-event-id");
+
+/// Server-sent event
+///
+/// Multi-line data and comments are sent as multiple fields. Line breaks
+/// are removed from event name and id.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Event {
+    id: Option<String>,
+    event: Option<String>,
+    data: Option<String>,
+    retry: Option<Millis>,
+    comment: Option<String>,
+}
+
+impl Event {
+    /// Create event with data
+    pub fn new<T: Into<String>>(data: T) -> Self {
+        Event {
+            data: Some(data.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Create comment
+    ///
+    /// Comments are ignored by clients.
+    pub fn comment<T: Into<String>>(text: T) -> Self {
+        Event {
+            comment: Some(text.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Set event id
+    ///
+    /// Client sends last received id in `Last-Event-ID` header on reconnect.
+    pub fn id<T: Into<String>>(mut self, id: T) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Set event name
+    pub fn event<T: Into<String>>(mut self, event: T) -> Self {
+        self.event = Some(event.into());
+        self
+    }
+
+    /// Set client reconnection time
+    pub fn retry<T: Into<Millis>>(mut self, retry: T) -> Self {
+        self.retry = Some(retry.into());
+        self
+    }
+
+    fn encode(&self, dst: &mut BytesMut) {
+        if let Some(ref comment) = self.comment {
+            for line in lines(comment) {
+                let _ = writeln!(dst, ":{}", line);
+            }
+        }
+        if let Some(ref event) = self.event {
+            let _ = writeln!(dst, "event: {}", single_line(event));
+        }
+        if let Some(ref data) = self.data {
+            for line in lines(data) {
+                if line.is_empty() {
+                    dst.extend_from_slice(b"data:\n");
+                } else {
+                    let _ = writeln!(dst, "data: {}", line);
+                }
+            }
+        }
+        if let Some(ref id) = self.id {
+            let _ = writeln!(dst, "id: {}", single_line(id));
+        }
+        if let Some(retry) = self.retry {
+            let _ = writeln!(dst, "retry: {}", retry.0);
+        }
+        dst.extend_from_slice(b"\n");
+    }
+}
+
+/// Split text on `\n`, `\r\n` and `\r`, empty lines are preserved
+fn lines(text: &str) -> impl Iterator<Item = &str> {
+    text.split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .flat_map(|line| line.split('\r'))
+}
+
+/// Remove line breaks from field value
+fn single_line(value: &str) -> std::borrow::Cow<'_, str> {
+    if value.contains(['\r', '\n']) {
+        value.replace(['\r', '\n'], "").into()
+    } else {
+        value.into()
+    }
+}
+
+/// Server-sent events response
+///
+/// Converts stream of events to `text/event-stream` response. Keep-alive
+/// comments are sent if stream does not produce events for keep-alive
+/// interval, by default interval is set to 15 seconds.
+pub struct EventStream<S> {
+    stream: S,
+    keep_alive: Seconds,
+}
+
+impl<S, E> EventStream<S>
+where
+    S: Stream<Item = Result<Event, E>> + Unpin + 'static,
+    E: Error + 'static,
+{
+    /// Create events response from stream of events
+    pub fn new(stream: S) -> Self {
+        EventStream {
+            stream,
+            keep_alive: Seconds(15),
+        }
+    }
+
+    /// Set keep-alive interval
+    ///
+    /// Zero value disables keep-alive comments.
+    pub fn keep_alive(mut self, interval: Seconds) -> Self {
+        self.keep_alive = interval;
+        self
+    }
+}
+
+impl<S, E, Err> Responder<Err> for EventStream<S>
+where
+    S: Stream<Item = Result<Event, E>> + Unpin + 'static,
+    E: Error + 'static,
+    Err: ErrorRenderer,
+{
+    async fn respond_to(self, _: &HttpRequest) -> Response {
+        let timer = if self.keep_alive.is_zero() {
+            None
+        } else {
+            Some(sleep(self.keep_alive))
+        };
+
+        Response::build(StatusCode::OK)
+            .content_type("text/event-stream")
+            .header(header::CACHE_CONTROL, "no-cache")
+            .streaming(EventsBody {
+                timer,
+                stream: self.stream,
+                keep_alive: self.keep_alive,
+            })
+    }
+}
+
+struct EventsBody<S> {
+    stream: S,
+    timer: Option<Sleep>,
+    keep_alive: Seconds,
+}
+
+impl<S, E> Stream for EventsBody<S>
+where
+    S: Stream<Item = Result<Event, E>> + Unpin,
+{
+    type Item = Result<Bytes, E>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.as_mut().get_mut();
+
+        match Pin::new(&mut this.stream).poll_next(cx) {
+            Poll::Ready(Some(Ok(ev))) => {
+                if let Some(ref timer) = this.timer {
+                    timer.reset(this.keep_alive);
+                }
+                let mut buf = BytesMut::new();
+                ev.encode(&mut buf);
+                Poll::Ready(Some(Ok(buf.freeze())))
+            }
+            Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(e))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => match this.timer {
+                Some(ref timer) if timer.poll_elapsed(cx).is_ready() => {
+                    timer.reset(this.keep_alive);
+                    Poll::Ready(Some(Ok(Bytes::from_static(b":\n\n"))))
+                }
+                _ => Poll::Pending,
+            },
+        }
+    }
+}
+
+/// Extract `Last-Event-ID` header value
+///
+/// Client sends id of last received event on reconnect, so event stream
+/// could be resumed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LastEventId(Option<String>);
+
+impl LastEventId {
+    /// Get last event id
+    pub fn get(&self) -> Option<&str> {
+        self.0.as_deref()
+    }
+
+    /// Unwrap into inner value
+    pub fn into_inner(self) -> Option<String> {
+        self.0
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for LastEventId {
+    type Error = Err::Container;
+
+    #[inline]
+    async fn from_request(req: &HttpRequest, _: &mut Payload) -> Result<Self, Self::Error> {
+        Ok(LastEventId(
+            req.headers()
+                .get(&LAST_EVENT_ID)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string()),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::*;
+    use crate::util::stream_recv;
+    use crate::web::test::{read_body, TestRequest};
+    use crate::web::{self, DefaultError};
+
+    #[test]
+    fn test_encode() {
+        let mut buf = BytesMut::new();
+        Event::new("line1\nline2")
+            .id("1")
+            .event("update")
+            .retry(Millis(1000))
+            .encode(&mut buf);
+        assert_eq!(
+            &buf[..],
+            &b"event: update\ndata: line1\ndata: line2\nid: 1\nretry: 1000\n\n"[..]
+        );
+
+        let mut buf = BytesMut::new();
+        Event::comment("hello").encode(&mut buf);
+        assert_eq!(&buf[..], &b":hello\n\n"[..]);
+
+        // empty lines are preserved
+        let mut buf = BytesMut::new();
+        Event::new("line1\r\n\nline3\rline4\n").encode(&mut buf);
+        assert_eq!(
+            &buf[..],
+            &b"data: line1\ndata:\ndata: line3\ndata: line4\ndata:\n\n"[..]
+        );
+
+        let mut buf = BytesMut::new();
+        Event::new("").encode(&mut buf);
+        assert_eq!(&buf[..], &b"data:\n\n"[..]);
+
+        // line breaks could not inject fields
+        let mut buf = BytesMut::new();
+        Event::new("1")
+            .id("1\ndata: injected")
+            .event("update\r\nretry: 1")
+            .encode(&mut buf);
+        assert_eq!(
+            &buf[..],
+            &b"event: updateretry: 1\ndata: 1\nid: 1data: injected\n\n"[..]
+        );
+    }
+
+    #[crate::rt_test]
+    async fn test_last_event_id() {
+        let (req, mut pl) = TestRequest::default()
+            .header(LAST_EVENT_ID, "10")
+            .to_http_parts();
+        let id = <LastEventId as FromRequest<DefaultError>>::from_request(&req, &mut pl)
+            .await
+            .unwrap();
+        assert_eq!(id.get(), Some("10"));
+
+        let (req, mut pl) = TestRequest::default().to_http_parts();
+        let id = <LastEventId as FromRequest<DefaultError>>::from_request(&req, &mut pl)
+            .await
+            .unwrap();
+        assert_eq!(id.into_inner(), None);
+    }
+
+    #[crate::rt_test]
+    async fn test_event_stream() {
+        let req = TestRequest::default().to_http_request();
+        let events = futures_util::stream::iter(vec![
+            Ok::<_, io::Error>(Event::new("1").id("1")),
+            Ok(Event::new("2").id("2")),
+        ]);
+        let resp =
+            <_ as Responder<DefaultError>>::respond_to(EventStream::new(events), &req)
+                .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/event-stream"
+        );
+        let body = read_body(web::WebResponse::new(resp, req)).await;
+        assert_eq!(&body[..], &b"data: 1\nid: 1\n\ndata: 2\nid: 2\n\n"[..]);
+    }
+
+    #[crate::rt_test]
+    async fn test_keep_alive() {
+        let (tx, rx) = crate::channel::mpsc::channel::<Result<Event, io::Error>>();
+        let mut body = EventsBody {
+            stream: rx,
+            timer: Some(sleep(Seconds(1))),
+            keep_alive: Seconds(1),
+        };
+
+        let chunk = stream_recv(&mut body).await.unwrap().unwrap();
+        assert_eq!(&chunk[..], &b":\n\n"[..]);
+
+        tx.send(Ok(Event::new("1"))).unwrap();
+        let chunk = stream_recv(&mut body).await.unwrap().unwrap();
+        assert_eq!(&chunk[..], &b"data: 1\n\n"[..]);
+
+        drop(tx);
+        assert!(stream_recv(&mut body).await.is_none());
+    }
+}
@@ -0,0 +1,264 @@
+//! Authorization header extractors
+use base64::{engine::general_purpose::STANDARD as base64, Engine};
+
+use crate::http::header::{self, HeaderMap, HeaderValue};
+use crate::http::Payload;
+use crate::web::error::{AuthError, ErrorRenderer};
+use crate::web::{FromRequest, HttpRequest};
+
+/// Authentication scheme
+///
+/// Scheme parses credentials from `Authorization` header and builds
+/// `WWW-Authenticate` challenge for unauthorized responses.
+pub trait AuthScheme: Sized + 'static {
+    /// Parse credentials from `Authorization` header value
+    fn parse(value: &str) -> Option<Self>;
+
+    /// Challenge for `WWW-Authenticate` header
+    fn challenge(realm: Option<&str>) -> HeaderValue;
+}
+
+/// Authorization extractors configuration
+///
+/// ```rust
+/// use ntex::web::{self, types::{AuthConfig, BasicAuth}, App};
+///
+/// async fn index(auth: BasicAuth) -> String {
+///     format!("Hello, {}!", auth.user_id())
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .state(AuthConfig::default().realm("Restricted area"))
+///         .route("/", web::get().to(index));
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct AuthConfig {
+    realm: Option<String>,
+}
+
+impl AuthConfig {
+    /// Set realm for `WWW-Authenticate` challenge
+    pub fn realm(mut self, realm: &str) -> Self {
+        self.realm = Some(realm.to_string());
+        self
+    }
+}
+
+/// Extract credentials of `T` scheme from request headers
+pub(crate) fn extract<T: AuthScheme>(
+    headers: &HeaderMap,
+    realm: Option<&str>,
+) -> Result<T, AuthError> {
+    let value = headers
+        .get(&header::AUTHORIZATION)
+        .ok_or_else(|| AuthError::Missing(T::challenge(realm)))?;
+    value
+        .to_str()
+        .ok()
+        .and_then(T::parse)
+        .ok_or_else(|| AuthError::Invalid(T::challenge(realm)))
+}
+
+fn challenge(scheme: &'static str, realm: Option<&str>) -> HeaderValue {
+    if let Some(realm) = realm {
+        let realm = realm.replace('\\', "\\\\").replace('"', "\\\"");
+        HeaderValue::from_str(&format!("{} realm=\"{}\"", scheme, realm))
+            .unwrap_or_else(|_| HeaderValue::from_static(scheme))
+    } else {
+        HeaderValue::from_static(scheme)
+    }
+}
+
+/// Split header value to scheme and credentials
+fn credentials<'a>(value: &'a str, scheme: &str) -> Option<&'a str> {
+    let (name, credentials) = value.trim().split_once(' ')?;
+    if name.eq_ignore_ascii_case(scheme) {
+        Some(credentials.trim())
+    } else {
+        None
+    }
+}
+
+/// Extractor for HTTP Basic authentication credentials
+///
+/// Requests without valid `Authorization: Basic` header are rejected
+/// with `401 Unauthorized` response and `WWW-Authenticate` challenge.
+/// Extractor does not validate credentials, use `HttpAuth` middleware
+/// or check credentials in the handler.
+///
+/// ```rust
+/// use ntex::web::{self, types::BasicAuth};
+///
+/// async fn index(auth: BasicAuth) -> String {
+///     format!("Hello, {}!", auth.user_id())
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BasicAuth {
+    user_id: String,
+    password: Option<String>,
+}
+
+impl BasicAuth {
+    /// User id
+    pub fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    /// Password, `None` if password is empty
+    pub fn password(&self) -> Option<&str> {
+        self.password.as_deref()
+    }
+}
+
+impl AuthScheme for BasicAuth {
+    fn parse(value: &str) -> Option<Self> {
+        let decoded = base64.decode(credentials(value, "Basic")?).ok()?;
+        let decoded = String::from_utf8(decoded).ok()?;
+        let (user_id, password) = decoded.split_once(':')?;
+
+        Some(BasicAuth {
+            user_id: user_id.to_string(),
+            password: if password.is_empty() {
+                None
+            } else {
+                Some(password.to_string())
+            },
+        })
+    }
+
+    fn challenge(realm: Option<&str>) -> HeaderValue {
+        challenge("Basic", realm)
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for BasicAuth {
+    type Error = AuthError;
+
+    #[inline]
+    async fn from_request(req: &HttpRequest, _: &mut Payload) -> Result<Self, Self::Error> {
+        let realm = req
+            .app_state::<AuthConfig>()
+            .and_then(|c| c.realm.as_deref());
+        extract(req.headers(), realm)
+    }
+}
+
+/// Extractor for HTTP Bearer token
+///
+/// Requests without valid `Authorization: Bearer` header are rejected
+/// with `401 Unauthorized` response and `WWW-Authenticate` challenge.
+///
+/// ```rust
+/// use ntex::web::{self, types::BearerAuth};
+///
+/// async fn index(auth: BearerAuth) -> String {
+///     format!("Token: {}", auth.token())
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BearerAuth {
+    token: String,
+}
+
+impl BearerAuth {
+    /// Bearer token
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+}
+
+impl AuthScheme for BearerAuth {
+    fn parse(value: &str) -> Option<Self> {
+        let token = credentials(value, "Bearer")?;
+        if token.is_empty() {
+            None
+        } else {
+            Some(BearerAuth {
+                token: token.to_string(),
+            })
+        }
+    }
+
+    fn challenge(realm: Option<&str>) -> HeaderValue {
+        challenge("Bearer", realm)
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for BearerAuth {
+    type Error = AuthError;
+
+    #[inline]
+    async fn from_request(req: &HttpRequest, _: &mut Payload) -> Result<Self, Self::Error> {
+        let realm = req
+            .app_state::<AuthConfig>()
+            .and_then(|c| c.realm.as_deref());
+        extract(req.headers(), realm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{from_request, TestRequest};
+    use crate::web::{DefaultError, WebResponseError};
+
+    #[crate::rt_test]
+    async fn test_basic_auth() {
+        let (req, mut pl) = TestRequest::default()
+            .header(header::AUTHORIZATION, "Basic dXNlcjpwYXNz")
+            .to_http_parts();
+        let auth = from_request::<BasicAuth>(&req, &mut pl).await.unwrap();
+        assert_eq!(auth.user_id(), "user");
+        assert_eq!(auth.password(), Some("pass"));
+
+        // empty password
+        let (req, mut pl) = TestRequest::default()
+            .header(header::AUTHORIZATION, "basic dXNlcjo=")
+            .to_http_parts();
+        let auth = from_request::<BasicAuth>(&req, &mut pl).await.unwrap();
+        assert_eq!(auth.user_id(), "user");
+        assert_eq!(auth.password(), None);
+
+        let (req, mut pl) = TestRequest::default()
+            .header(header::AUTHORIZATION, "Bearer dXNlcjpwYXNz")
+            .to_http_parts();
+        let err = from_request::<BasicAuth>(&req, &mut pl).await.unwrap_err();
+        assert_eq!(err, AuthError::Invalid(HeaderValue::from_static("Basic")));
+
+        let (req, mut pl) = TestRequest::default()
+            .state(AuthConfig::default().realm("test"))
+            .to_http_parts();
+        let err = from_request::<BasicAuth>(&req, &mut pl).await.unwrap_err();
+        assert_eq!(err.challenge(), "Basic realm=\"test\"");
+
+        let res = WebResponseError::<DefaultError>::error_response(&err, &req);
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            res.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+            "Basic realm=\"test\""
+        );
+    }
+
+    #[crate::rt_test]
+    async fn test_bearer_auth() {
+        let (req, mut pl) = TestRequest::default()
+            .header(header::AUTHORIZATION, "Bearer mF_9.B5f-4.1JqM")
+            .to_http_parts();
+        let auth = from_request::<BearerAuth>(&req, &mut pl).await.unwrap();
+        assert_eq!(auth.token(), "mF_9.B5f-4.1JqM");
+
+        let (req, mut pl) = TestRequest::default()
+            .header(header::AUTHORIZATION, "Bearer ")
+            .to_http_parts();
+        let err = from_request::<BearerAuth>(&req, &mut pl).await.unwrap_err();
+        assert_eq!(err, AuthError::Invalid(HeaderValue::from_static("Bearer")));
+
+        let (req, mut pl) = TestRequest::default().to_http_parts();
+        let err = from_request::<BearerAuth>(&req, &mut pl).await.unwrap_err();
+        assert_eq!(err, AuthError::Missing(HeaderValue::from_static("Bearer")));
+    }
+}
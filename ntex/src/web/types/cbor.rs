@@ -0,0 +1,341 @@
+//! Cbor extractor/responder
+use std::{fmt, ops, sync::Arc};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::http::{error, HttpMessage, Payload, Response, StatusCode};
+use crate::web::error::{CborError, CborPayloadError};
+use crate::web::error::{ErrorRenderer, PayloadError, WebResponseError};
+use crate::web::{FromRequest, HttpRequest, Responder};
+
+use super::payload::HttpMessageBody;
+
+/// Cbor helper
+///
+/// Cbor can be used for extracting typed information from request's
+/// payload and for cbor response generation.
+///
+/// To extract typed information from request's body, the type `T` must
+/// implement the `Deserialize` trait from *serde*. Request's content type
+/// must be `application/cbor` or any type with `+cbor` suffix.
+///
+/// [**CborConfig**](struct.CborConfig.html) allows to configure extraction
+/// process.
+///
+/// ## Example
+///
+/// ```rust
+/// use ntex::web;
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Info {
+///     username: String,
+/// }
+///
+/// /// deserialize `Info` from request's body and send it back
+/// async fn index(info: web::types::Cbor<Info>) -> web::types::Cbor<Info> {
+///     info
+/// }
+///
+/// fn main() {
+///     let app = web::App::new().service(
+///        web::resource("/index.html").route(
+///            web::post().to(index))
+///     );
+/// }
+/// ```
+pub struct Cbor<T>(pub T);
+
+impl<T> Cbor<T> {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> ops::Deref for Cbor<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> ops::DerefMut for Cbor<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> fmt::Debug for Cbor<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Cbor").field(&self.0).finish()
+    }
+}
+
+impl<T> fmt::Display for Cbor<T>
+where
+    T: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl<T: Serialize, Err: ErrorRenderer> Responder<Err> for Cbor<T>
+where
+    Err::Container: From<CborError>,
+{
+    async fn respond_to(self, req: &HttpRequest) -> Response {
+        let mut body = Vec::new();
+        if let Err(e) = ciborium::into_writer(&self.0, &mut body) {
+            return e.error_response(req);
+        }
+
+        Response::build(StatusCode::OK)
+            .content_type("application/cbor")
+            .body(body)
+    }
+}
+
+impl<T, Err: ErrorRenderer> FromRequest<Err> for Cbor<T>
+where
+    T: DeserializeOwned + 'static,
+{
+    type Error = CborPayloadError;
+
+    async fn from_request(
+        req: &HttpRequest,
+        payload: &mut Payload,
+    ) -> Result<Self, Self::Error> {
+        let (limit, ctype) = req
+            .app_state::<CborConfig>()
+            .map(|c| (c.limit, c.content_type.clone()))
+            .unwrap_or((32768, None));
+
+        // check content-type
+        let cbor = if let Ok(Some(mime)) = req.mime_type() {
+            mime.subtype().as_str() == "cbor"
+                || mime.suffix().map(|s| s.as_str()) == Some("cbor")
+                || ctype.as_ref().is_some_and(|predicate| predicate(mime))
+        } else {
+            false
+        };
+        if !cbor {
+            return Err(CborPayloadError::ContentType);
+        }
+
+        let body = HttpMessageBody::new(req, payload)
+            .limit(limit)
+            .await
+            .map_err(|e| match e {
+                PayloadError::Payload(error::PayloadError::Overflow) => {
+                    CborPayloadError::Overflow
+                }
+                e => e.into(),
+            })?;
+
+        match ciborium::from_reader(&body[..]) {
+            Ok(data) => Ok(Cbor(data)),
+            Err(e) => {
+                log::debug!(
+                    "Failed to deserialize Cbor from payload. \
+                     Request path: {}",
+                    req.path()
+                );
+                Err(e.into())
+            }
+        }
+    }
+}
+
+/// Cbor extractor configuration
+///
+/// ```rust
+/// use ntex::web::{self, App};
+///
+/// #[derive(serde::Deserialize)]
+/// struct Info {
+///     username: String,
+/// }
+///
+/// /// deserialize `Info` from request's body, max payload size is 4kb
+/// async fn index(info: web::types::Cbor<Info>) -> String {
+///     format!("Welcome {}!", info.username)
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/index.html")
+///             .state(
+///                 // change cbor extractor configuration
+///                 web::types::CborConfig::default()
+///                    .limit(4096)
+///                    .content_type(|mime| {  // <- accept application/octet-stream
+///                        mime == mime::APPLICATION_OCTET_STREAM
+///                    })
+///             )
+///             .route(web::post().to(index))
+///     );
+/// }
+/// ```
+#[derive(Clone)]
+pub struct CborConfig {
+    limit: usize,
+    content_type: Option<Arc<dyn Fn(mime::Mime) -> bool + Send + Sync>>,
+}
+
+impl CborConfig {
+    /// Change max size of payload. By default max size is 32Kb
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Set predicate for allowed content types
+    pub fn content_type<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(mime::Mime) -> bool + Send + Sync + 'static,
+    {
+        self.content_type = Some(Arc::new(predicate));
+        self
+    }
+}
+
+impl Default for CborConfig {
+    fn default() -> Self {
+        CborConfig {
+            limit: 32768,
+            content_type: None,
+        }
+    }
+}
+
+impl fmt::Debug for CborConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CborConfig")
+            .field("limit", &self.limit)
+            .field(
+                "content_type",
+                &self
+                    .content_type
+                    .as_ref()
+                    .map(|_| "Arc<dyn Fn(mime::Mime) -> bool + Send + Sync>"),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header;
+    use crate::util::Bytes;
+    use crate::web::test::{from_request, respond_to, TestRequest};
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct MyObject {
+        name: String,
+    }
+
+    fn payload() -> Bytes {
+        let mut body = Vec::new();
+        ciborium::into_writer(
+            &MyObject {
+                name: "test".to_string(),
+            },
+            &mut body,
+        )
+        .unwrap();
+        Bytes::from(body)
+    }
+
+    #[crate::rt_test]
+    async fn test_responder() {
+        let mut m = Cbor(MyObject {
+            name: "test2".to_string(),
+        });
+        m.name = "test".to_string();
+        assert!(format!("{:?}", m).contains("Cbor"));
+        assert!(format!("{:?}", CborConfig::default()).contains("CborConfig"));
+
+        let req = TestRequest::default().to_http_request();
+        let resp = respond_to(m, &req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            header::HeaderValue::from_static("application/cbor")
+        );
+        assert_eq!(resp.body().get_ref(), payload().as_ref());
+    }
+
+    #[crate::rt_test]
+    async fn test_extract() {
+        let (req, mut pl) =
+            TestRequest::with_header(header::CONTENT_TYPE, "application/cbor")
+                .set_payload(payload())
+                .to_http_parts();
+        let s = from_request::<Cbor<MyObject>>(&req, &mut pl).await.unwrap();
+        assert_eq!(
+            s.into_inner(),
+            MyObject {
+                name: "test".to_string()
+            }
+        );
+
+        let (req, mut pl) =
+            TestRequest::with_header(header::CONTENT_TYPE, "application/vnd.api+cbor")
+                .set_payload(payload())
+                .to_http_parts();
+        let s = from_request::<Cbor<MyObject>>(&req, &mut pl).await;
+        assert_eq!(s.unwrap().name, "test");
+
+        // overflow
+        let (req, mut pl) =
+            TestRequest::with_header(header::CONTENT_TYPE, "application/cbor")
+                .set_payload(payload())
+                .state(CborConfig::default().limit(2))
+                .to_http_parts();
+        let s = from_request::<Cbor<MyObject>>(&req, &mut pl).await;
+        let err = s.err().unwrap();
+        assert!(matches!(err, CborPayloadError::Overflow));
+        assert_eq!(
+            WebResponseError::<crate::web::DefaultError>::status_code(&err),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+
+        // content type
+        let (req, mut pl) = TestRequest::with_header(header::CONTENT_TYPE, "text/plain")
+            .set_payload(payload())
+            .to_http_parts();
+        let s = from_request::<Cbor<MyObject>>(&req, &mut pl).await;
+        assert!(matches!(s.err().unwrap(), CborPayloadError::ContentType));
+
+        let (req, mut pl) =
+            TestRequest::with_header(header::CONTENT_TYPE, "application/octet-stream")
+                .set_payload(payload())
+                .state(
+                    CborConfig::default()
+                        .content_type(|mime| mime == mime::APPLICATION_OCTET_STREAM),
+                )
+                .to_http_parts();
+        let s = from_request::<Cbor<MyObject>>(&req, &mut pl).await;
+        assert!(s.is_ok());
+
+        // invalid payload
+        let (req, mut pl) =
+            TestRequest::with_header(header::CONTENT_TYPE, "application/cbor")
+                .set_payload(Bytes::from_static(b"\x01"))
+                .to_http_parts();
+        let s = from_request::<Cbor<MyObject>>(&req, &mut pl).await;
+        let err = s.err().unwrap();
+        assert!(matches!(err, CborPayloadError::Deserialize(_)));
+        assert_eq!(
+            WebResponseError::<crate::web::DefaultError>::status_code(&err),
+            StatusCode::BAD_REQUEST
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use std::{fmt, rc::Rc};
+
+use crate::http::Payload;
+use crate::util::HashMap;
+use crate::web::{ErrorRenderer, FromRequest, HttpRequest};
+
+/// Set of feature flags
+///
+/// `Flags` could be used as request extractor, extractor returns flags
+/// populated by `FeatureFlags` middleware. If middleware is not
+/// configured all flags are disabled. Flags are also available for
+/// guards via request extensions, see `guard::Flag()`.
+///
+/// ```rust
+/// use ntex::web::{self, types::Flags, App, HttpResponse};
+///
+/// async fn index(flags: Flags) -> HttpResponse {
+///     if flags.is_enabled("new-ui") {
+///         HttpResponse::Ok().body("new ui")
+///     } else {
+///         HttpResponse::Ok().body("old ui")
+///     }
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(web::middleware::FeatureFlags::new(
+///             Flags::from_iter([("new-ui", true)])
+///         ))
+///         .route("/", web::get().to(index));
+/// }
+/// ```
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Flags(Rc<HashMap<String, bool>>);
+
+impl Flags {
+    /// Load flags from environment variables with specified prefix
+    ///
+    /// Prefix is stripped and the rest of variable name is lowercased,
+    /// i.e. `FEATURE_NEW_UI=1` with `FEATURE_` prefix gets loaded as
+    /// `new_ui` flag. Values `1`, `true`, `on` and `yes` enable the flag.
+    pub fn from_env(prefix: &str) -> Self {
+        Flags::from_vars(std::env::vars(), prefix)
+    }
+
+    fn from_vars<I: Iterator<Item = (String, String)>>(vars: I, prefix: &str) -> Self {
+        vars.filter_map(|(name, val)| {
+            name.strip_prefix(prefix).map(|name| {
+                let val = val.trim().to_ascii_lowercase();
+                (
+                    name.to_ascii_lowercase(),
+                    matches!(val.as_str(), "1" | "true" | "on" | "yes"),
+                )
+            })
+        })
+        .collect()
+    }
+
+    /// Check if flag is enabled
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.0.get(name).copied().unwrap_or(false)
+    }
+
+    /// Get flag value
+    pub fn get(&self, name: &str) -> Option<bool> {
+        self.0.get(name).copied()
+    }
+
+    /// Iterate over all flags
+    pub fn iter(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.0.iter().map(|(name, val)| (name.as_str(), *val))
+    }
+}
+
+impl<K: Into<String>> FromIterator<(K, bool)> for Flags {
+    fn from_iter<T: IntoIterator<Item = (K, bool)>>(iter: T) -> Self {
+        Flags(Rc::new(
+            iter.into_iter().map(|(k, v)| (k.into(), v)).collect(),
+        ))
+    }
+}
+
+impl fmt::Debug for Flags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for Flags {
+    type Error = Err::Container;
+
+    #[inline]
+    async fn from_request(req: &HttpRequest, _: &mut Payload) -> Result<Self, Self::Error> {
+        Ok(req.extensions().get::<Flags>().cloned().unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::{test::TestRequest, DefaultError};
+
+    #[test]
+    fn test_from_vars() {
+        let flags = Flags::from_vars(
+            vec![
+                ("FEATURE_NEW_UI".to_string(), "1".to_string()),
+                ("FEATURE_BETA".to_string(), "off".to_string()),
+                ("FEATURE_V2".to_string(), " True".to_string()),
+                ("PATH".to_string(), "/bin".to_string()),
+            ]
+            .into_iter(),
+            "FEATURE_",
+        );
+        assert!(flags.is_enabled("new_ui"));
+        assert!(flags.is_enabled("v2"));
+        assert!(!flags.is_enabled("beta"));
+        assert_eq!(flags.get("beta"), Some(false));
+        assert_eq!(flags.get("path"), None);
+        assert_eq!(flags.iter().count(), 3);
+    }
+
+    #[crate::rt_test]
+    async fn test_flags_extractor_without_middleware() {
+        let (req, mut pl) = TestRequest::default().to_http_parts();
+        let flags = <Flags as FromRequest<DefaultError>>::from_request(&req, &mut pl)
+            .await
+            .unwrap();
+        assert!(!flags.is_enabled("beta"));
+    }
+}
@@ -1,5 +1,6 @@
 //! Form extractor
-use std::{fmt, future::Future, ops, pin::Pin, task::Context, task::Poll};
+use std::task::{Context, Poll};
+use std::{fmt, future::Future, ops, pin::Pin, rc::Rc};
 
 use encoding_rs::{Encoding, UTF_8};
 use serde::{de::DeserializeOwned, Serialize};
@@ -8,9 +9,12 @@ use serde::{de::DeserializeOwned, Serialize};
 use crate::http::encoding::Decoder;
 use crate::http::header::{CONTENT_LENGTH, CONTENT_TYPE};
 use crate::http::{HttpMessage, Payload, Response, StatusCode};
-use crate::util::{stream_recv, BoxFuture, BytesMut};
-use crate::web::error::{ErrorRenderer, UrlencodedError, WebResponseError};
-use crate::web::{FromRequest, HttpRequest, Responder};
+use crate::util::{stream_recv, BoxFuture, Bytes, BytesMut, HashMap};
+use crate::web::error::WebResponseError;
+use crate::web::error::{ErrorRenderer, ErrorResponseFactory, UrlencodedError};
+use crate::web::{FromRequest, HttpRequest, HttpResponse, Responder};
+
+use super::urlencoded;
 
 /// Form data helper (`application/x-www-form-urlencoded`)
 ///
@@ -105,13 +109,31 @@ where
         req: &HttpRequest,
         payload: &mut Payload,
     ) -> Result<Self, Self::Error> {
-        let limit = req
-            .app_state::<FormConfig>()
-            .map(|c| c.limit)
-            .unwrap_or(16384);
+        let cfg = req.app_state::<FormConfig>();
+        let fut = if let Some(cfg) = cfg {
+            UrlEncoded::new(req, payload, cfg.content_type.clone())
+                .limit(cfg.limit)
+                .nested(cfg.nested)
+                .field_limits(cfg.field_limits.clone())
+        } else {
+            UrlEncoded::new(req, payload, None).limit(16384)
+        };
 
-        match UrlEncoded::new(req, payload).limit(limit).await {
-            Err(e) => Err(e),
+        match fut.await {
+            Err(e) => {
+                if let Some(handler) = cfg.and_then(|c| c.err_handler.clone()) {
+                    let cause = Rc::new(e);
+                    let err = cause.clone();
+                    Err(UrlencodedError::Response {
+                        cause,
+                        response: ErrorResponseFactory::new(req, move |req| {
+                            handler(&err, req)
+                        }),
+                    })
+                } else {
+                    Err(e)
+                }
+            }
             Ok(item) => Ok(Form(item)),
         }
     }
@@ -172,9 +194,75 @@ where
 ///     );
 /// }
 /// ```
-#[derive(Clone, Debug)]
+///
+/// Nested form encoding, as produced by html forms and js clients, could be
+/// enabled with [`FormConfig::nested`]:
+///
+/// ```rust
+/// use ntex::web::{self, App};
+///
+/// #[derive(serde::Deserialize)]
+/// struct FormData {
+///     tags: Vec<String>,
+///     user: std::collections::HashMap<String, String>,
+/// }
+///
+/// /// Accepts `tags[]=a&tags[]=b&user[name]=ntex`
+/// async fn index(form: web::types::Form<FormData>) -> String {
+///     format!("Tags {:?}", form.tags)
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/index.html")
+///             .state(
+///                 web::types::FormConfig::default()
+///                     .nested(true)
+///                     .field_limit("tags", 64)
+///             )
+///             .route(web::post().to(index))
+///     );
+/// }
+/// ```
+///
+/// Extraction errors could be converted to custom responses with
+/// [`FormConfig::error_handler`]:
+///
+/// ```rust
+/// use ntex::web::{self, App, HttpResponse};
+///
+/// #[derive(serde::Deserialize)]
+/// struct FormData {
+///     username: String,
+/// }
+///
+/// async fn index(form: web::types::Form<FormData>) -> String {
+///     format!("Welcome {}!", form.username)
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/index.html")
+///             .state(
+///                 web::types::FormConfig::default()
+///                     // accept `text/plain` content type
+///                     .content_type(|mime| mime == mime::TEXT_PLAIN)
+///                     .error_handler(|err, _| {
+///                         HttpResponse::UnprocessableEntity()
+///                             .json(&serde_json::json!({"error": err.to_string()}))
+///                     })
+///             )
+///             .route(web::post().to(index))
+///     );
+/// }
+/// ```
+#[derive(Clone)]
 pub struct FormConfig {
     limit: usize,
+    nested: bool,
+    field_limits: Rc<HashMap<String, usize>>,
+    content_type: Option<Rc<dyn Fn(mime::Mime) -> bool>>,
+    err_handler: Option<Rc<dyn Fn(&UrlencodedError, &HttpRequest) -> HttpResponse>>,
 }
 
 impl FormConfig {
@@ -183,11 +271,85 @@ impl FormConfig {
         self.limit = limit;
         self
     }
+
+    /// Enable support for arrays and nested maps.
+    ///
+    /// Repeated keys (`tag=a&tag=b`), bracketed arrays (`tag[]=a&tag[]=b`),
+    /// indexed arrays (`tag[0]=a`) and nested maps (`user[name]=a`) get
+    /// deserialized to sequences and maps.
+    ///
+    /// By default nested encoding is disabled.
+    pub fn nested(mut self, enabled: bool) -> Self {
+        self.nested = enabled;
+        self
+    }
+
+    /// Set max size of a field value.
+    ///
+    /// Limit applies to each value of the field, for nested fields
+    /// the top level name is used, so `tags` limits `tags[]` values.
+    pub fn field_limit<N: Into<String>>(mut self, name: N, limit: usize) -> Self {
+        Rc::make_mut(&mut self.field_limits).insert(name.into(), limit);
+        self
+    }
+
+    /// Set predicate for additional allowed content types.
+    ///
+    /// `application/x-www-form-urlencoded` is always allowed.
+    pub fn content_type<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(mime::Mime) -> bool + 'static,
+    {
+        self.content_type = Some(Rc::new(predicate));
+        self
+    }
+
+    /// Set custom error handler.
+    ///
+    /// Handler generates response for extraction errors, response is used
+    /// instead of error renderer's default response.
+    pub fn error_handler<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&UrlencodedError, &HttpRequest) -> HttpResponse + 'static,
+    {
+        self.err_handler = Some(Rc::new(f));
+        self
+    }
 }
 
 impl Default for FormConfig {
     fn default() -> Self {
-        FormConfig { limit: 16384 }
+        FormConfig {
+            limit: 16384,
+            nested: false,
+            field_limits: Rc::new(HashMap::default()),
+            content_type: None,
+            err_handler: None,
+        }
+    }
+}
+
+impl fmt::Debug for FormConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FormConfig")
+            .field("limit", &self.limit)
+            .field("nested", &self.nested)
+            .field("field_limits", &self.field_limits)
+            .field(
+                "content_type",
+                &self
+                    .content_type
+                    .as_ref()
+                    .map(|_| "Rc<dyn Fn(mime::Mime) -> bool>"),
+            )
+            .field(
+                "error_handler",
+                &self
+                    .err_handler
+                    .as_ref()
+                    .map(|_| "Rc<dyn Fn(&UrlencodedError, &HttpRequest) -> HttpResponse>"),
+            )
+            .finish()
     }
 }
 
@@ -209,6 +371,8 @@ struct UrlEncoded<U> {
     stream: Option<Payload>,
     limit: usize,
     length: Option<usize>,
+    nested: bool,
+    field_limits: Rc<HashMap<String, usize>>,
     encoding: &'static Encoding,
     err: Option<UrlencodedError>,
     fut: Option<BoxFuture<'static, Result<U, UrlencodedError>>>,
@@ -216,10 +380,20 @@ struct UrlEncoded<U> {
 
 impl<U> UrlEncoded<U> {
     /// Create a new future to URL encode a request
-    fn new(req: &HttpRequest, payload: &mut Payload) -> UrlEncoded<U> {
+    fn new(
+        req: &HttpRequest,
+        payload: &mut Payload,
+        ctype: Option<Rc<dyn Fn(mime::Mime) -> bool>>,
+    ) -> UrlEncoded<U> {
         // check content type
         if req.content_type().to_lowercase() != "application/x-www-form-urlencoded" {
-            return Self::err(UrlencodedError::ContentType);
+            let allowed = match (ctype, req.mime_type()) {
+                (Some(predicate), Ok(Some(mime))) => predicate(mime),
+                _ => false,
+            };
+            if !allowed {
+                return Self::err(UrlencodedError::ContentType);
+            }
         }
         let encoding = match req.encoding() {
             Ok(enc) => enc,
@@ -249,6 +423,8 @@ impl<U> UrlEncoded<U> {
             stream: Some(payload),
             limit: 32_768,
             length: len,
+            nested: false,
+            field_limits: Rc::new(HashMap::default()),
             fut: None,
             err: None,
         }
@@ -261,6 +437,8 @@ impl<U> UrlEncoded<U> {
             fut: None,
             err: Some(e),
             length: None,
+            nested: false,
+            field_limits: Rc::new(HashMap::default()),
             encoding: UTF_8,
         }
     }
@@ -270,6 +448,18 @@ impl<U> UrlEncoded<U> {
         self.limit = limit;
         self
     }
+
+    /// Enable arrays and nested maps support
+    fn nested(mut self, nested: bool) -> Self {
+        self.nested = nested;
+        self
+    }
+
+    /// Set per-field size limits
+    fn field_limits(mut self, limits: Rc<HashMap<String, usize>>) -> Self {
+        self.field_limits = limits;
+        self
+    }
 }
 
 impl<U> Future for UrlEncoded<U>
@@ -297,6 +487,8 @@ where
 
         // future
         let encoding = self.encoding;
+        let nested = self.nested;
+        let field_limits = self.field_limits.clone();
         let mut stream = self.stream.take().unwrap();
 
         self.fut = Some(Box::pin(async move {
@@ -314,14 +506,22 @@ where
                 }
             }
 
-            if encoding == UTF_8 {
-                serde_urlencoded::from_bytes::<U>(&body).map_err(|_| UrlencodedError::Parse)
+            let body = if encoding == UTF_8 {
+                body.freeze()
             } else {
-                let body = encoding
+                encoding
                     .decode_without_bom_handling_and_without_replacement(&body)
-                    .map(|s| s.into_owned())
-                    .ok_or(UrlencodedError::Parse)?;
-                serde_urlencoded::from_str::<U>(&body).map_err(|_| UrlencodedError::Parse)
+                    .map(|s| Bytes::from(s.into_owned()))
+                    .ok_or(UrlencodedError::Parse)?
+            };
+
+            if nested {
+                urlencoded::from_bytes::<U>(&body, &field_limits)
+            } else {
+                if !field_limits.is_empty() {
+                    urlencoded::parse_pairs(&body, &field_limits)?;
+                }
+                serde_urlencoded::from_bytes::<U>(&body).map_err(|_| UrlencodedError::Parse)
             }
         }));
         self.poll(cx)
@@ -336,6 +536,7 @@ mod tests {
     use crate::http::header::HeaderValue;
     use crate::util::Bytes;
     use crate::web::test::{from_request, respond_to, TestRequest};
+    use crate::web::DefaultError;
 
     #[derive(Deserialize, Serialize, Debug, PartialEq, thiserror::Error)]
     #[error("Info({hello})")]
@@ -400,20 +601,96 @@ mod tests {
         assert!(eq(res.err().unwrap(), UrlencodedError::UnknownLength));
     }
 
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Tags {
+        name: String,
+        tags: Vec<String>,
+    }
+
+    #[crate::rt_test]
+    async fn test_form_nested() {
+        let (req, mut pl) =
+            TestRequest::with_header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .state(FormConfig::default().nested(true))
+                .set_payload(Bytes::from_static(b"name=ntex&tags[]=a&tags[]=b"))
+                .to_http_parts();
+        let Form(s) = from_request::<Form<Tags>>(&req, &mut pl).await.unwrap();
+        assert_eq!(s.name, "ntex");
+        assert_eq!(s.tags, vec!["a".to_string(), "b".to_string()]);
+
+        // nested encoding is disabled by default
+        let (req, mut pl) =
+            TestRequest::with_header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .set_payload(Bytes::from_static(b"name=ntex&tags[]=a&tags[]=b"))
+                .to_http_parts();
+        let res = from_request::<Form<Tags>>(&req, &mut pl).await;
+        assert!(matches!(res.err().unwrap(), UrlencodedError::Parse));
+
+        let (req, mut pl) =
+            TestRequest::with_header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .state(FormConfig::default().field_limit("hello", 4))
+                .set_payload(Bytes::from_static(b"hello=world&counter=123"))
+                .to_http_parts();
+        let res = from_request::<Form<Info>>(&req, &mut pl).await;
+        assert!(matches!(
+            res.err().unwrap(),
+            UrlencodedError::FieldOverflow {
+                size: 5,
+                limit: 4,
+                ..
+            }
+        ));
+    }
+
+    #[crate::rt_test]
+    async fn test_form_config() {
+        let (req, mut pl) = TestRequest::with_header(CONTENT_TYPE, "text/plain")
+            .state(FormConfig::default().content_type(|mime| mime == mime::TEXT_PLAIN))
+            .set_payload(Bytes::from_static(b"hello=world&counter=123"))
+            .to_http_parts();
+        let Form(s) = from_request::<Form<Info>>(&req, &mut pl).await.unwrap();
+        assert_eq!(s.hello, "world");
+
+        let (req, mut pl) = TestRequest::with_header(CONTENT_TYPE, "text/plain")
+            .state(FormConfig::default().error_handler(|err, _| {
+                HttpResponse::UnprocessableEntity().body(err.to_string())
+            }))
+            .set_payload(Bytes::from_static(b"hello=world&counter=123"))
+            .to_http_parts();
+        let err = from_request::<Form<Info>>(&req, &mut pl)
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err,
+            UrlencodedError::Response { ref cause, .. }
+                if matches!(**cause, UrlencodedError::ContentType)
+        ));
+        assert_eq!(
+            WebResponseError::<DefaultError>::status_code(&err),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+        for _ in 0..2 {
+            let resp = WebResponseError::<DefaultError>::error_response(&err, &req);
+            assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+            assert_eq!(resp.body().get_ref(), b"Content type error");
+        }
+    }
+
     #[crate::rt_test]
     async fn test_urlencoded_error() {
         let (req, mut pl) =
             TestRequest::with_header(CONTENT_TYPE, "application/x-www-form-urlencoded")
                 .header(CONTENT_LENGTH, "xxxx")
                 .to_http_parts();
-        let info = UrlEncoded::<Info>::new(&req, &mut pl).await;
+        let info = UrlEncoded::<Info>::new(&req, &mut pl, None).await;
         assert!(eq(info.err().unwrap(), UrlencodedError::UnknownLength));
 
         let (req, mut pl) =
             TestRequest::with_header(CONTENT_TYPE, "application/x-www-form-urlencoded")
                 .header(CONTENT_LENGTH, "1000000")
                 .to_http_parts();
-        let info = UrlEncoded::<Info>::new(&req, &mut pl).await;
+        let info = UrlEncoded::<Info>::new(&req, &mut pl, None).await;
         assert!(eq(
             info.err().unwrap(),
             UrlencodedError::Overflow { size: 0, limit: 0 }
@@ -422,7 +699,7 @@ mod tests {
         let (req, mut pl) = TestRequest::with_header(CONTENT_TYPE, "text/plain")
             .header(CONTENT_LENGTH, "10")
             .to_http_parts();
-        let info = UrlEncoded::<Info>::new(&req, &mut pl).await;
+        let info = UrlEncoded::<Info>::new(&req, &mut pl, None).await;
         assert!(eq(info.err().unwrap(), UrlencodedError::ContentType));
     }
 
@@ -434,7 +711,7 @@ mod tests {
                 .set_payload(Bytes::from_static(b"hello=world&counter=123"))
                 .to_http_parts();
 
-        let info = UrlEncoded::<Info>::new(&req, &mut pl).await.unwrap();
+        let info = UrlEncoded::<Info>::new(&req, &mut pl, None).await.unwrap();
         assert_eq!(
             info,
             Info {
@@ -451,7 +728,7 @@ mod tests {
         .set_payload(Bytes::from_static(b"hello=world&counter=123"))
         .to_http_parts();
 
-        let info = UrlEncoded::<Info>::new(&req, &mut pl).await.unwrap();
+        let info = UrlEncoded::<Info>::new(&req, &mut pl, None).await.unwrap();
         assert_eq!(
             info,
             Info {
@@ -468,7 +745,7 @@ mod tests {
         .set_payload(Bytes::from_static(b"hello=world&counter=123"))
         .to_http_parts();
 
-        let info = UrlEncoded::<Info>::new(&req, &mut pl).await.unwrap();
+        let info = UrlEncoded::<Info>::new(&req, &mut pl, None).await.unwrap();
         assert_eq!(
             info,
             Info {
@@ -1,5 +1,6 @@
 //! Json extractor/responder
-use std::{fmt, future::Future, ops, pin::Pin, sync::Arc, task::Context, task::Poll};
+use std::task::{Context, Poll};
+use std::{fmt, future::Future, ops, pin::Pin, rc::Rc, sync::Arc};
 
 use serde::{de::DeserializeOwned, Serialize};
 
@@ -8,8 +9,9 @@ use crate::http::encoding::Decoder;
 use crate::http::header::CONTENT_LENGTH;
 use crate::http::{HttpMessage, Payload, Response, StatusCode};
 use crate::util::{stream_recv, BoxFuture, BytesMut};
-use crate::web::error::{ErrorRenderer, JsonError, JsonPayloadError, WebResponseError};
-use crate::web::{FromRequest, HttpRequest, Responder};
+use crate::web::error::{ErrorRenderer, ErrorResponseFactory, JsonError};
+use crate::web::error::{JsonPayloadError, WebResponseError};
+use crate::web::{FromRequest, HttpRequest, HttpResponse, Responder};
 
 /// Json helper
 ///
@@ -165,8 +167,8 @@ where
         payload: &mut Payload,
     ) -> Result<Self, Self::Error> {
         let req2 = req.clone();
-        let (limit, ctype) = req
-            .app_state::<JsonConfig>()
+        let cfg = req.app_state::<JsonConfig>();
+        let (limit, ctype) = cfg
             .map(|c| (c.limit, c.content_type.clone()))
             .unwrap_or((32768, None));
 
@@ -177,7 +179,18 @@ where
                      Request path: {}",
                     req2.path()
                 );
-                Err(e)
+                if let Some(handler) = cfg.and_then(|c| c.err_handler.clone()) {
+                    let cause = Rc::new(e);
+                    let err = cause.clone();
+                    Err(JsonPayloadError::Response {
+                        cause,
+                        response: ErrorResponseFactory::new(&req2, move |req| {
+                            handler(&err, req)
+                        }),
+                    })
+                } else {
+                    Err(e)
+                }
             }
             Ok(data) => Ok(Json(data)),
         }
@@ -215,12 +228,49 @@ where
 ///     );
 /// }
 /// ```
+///
+/// Extraction errors could be converted to custom responses with
+/// [`JsonConfig::error_handler`]:
+///
+/// ```rust
+/// use ntex::web::{self, error::JsonPayloadError, App, HttpResponse};
+///
+/// #[derive(serde::Deserialize)]
+/// struct Info {
+///     username: String,
+/// }
+///
+/// async fn index(info: web::types::Json<Info>) -> String {
+///     format!("Welcome {}!", info.username)
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/index.html")
+///             .state(web::types::JsonConfig::default().error_handler(|err, _| {
+///                 let status = match err {
+///                     JsonPayloadError::Deserialize(_) => {
+///                         ntex::http::StatusCode::UNPROCESSABLE_ENTITY
+///                     }
+///                     _ => ntex::http::StatusCode::BAD_REQUEST,
+///                 };
+///                 HttpResponse::build(status)
+///                     .json(&serde_json::json!({"error": err.to_string()}))
+///             }))
+///             .route(web::post().to(index))
+///     );
+/// }
+/// ```
 #[derive(Clone)]
 pub struct JsonConfig {
     limit: usize,
     content_type: Option<Arc<dyn Fn(mime::Mime) -> bool + Send + Sync>>,
+    err_handler: Option<Arc<JsonErrorHandler>>,
 }
 
+type JsonErrorHandler =
+    dyn Fn(&JsonPayloadError, &HttpRequest) -> HttpResponse + Send + Sync;
+
 impl JsonConfig {
     /// Change max size of payload. By default max size is 32Kb
     pub fn limit(mut self, limit: usize) -> Self {
@@ -236,6 +286,18 @@ impl JsonConfig {
         self.content_type = Some(Arc::new(predicate));
         self
     }
+
+    /// Set custom error handler.
+    ///
+    /// Handler generates response for extraction errors, response is used
+    /// instead of error renderer's default response.
+    pub fn error_handler<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&JsonPayloadError, &HttpRequest) -> HttpResponse + Send + Sync + 'static,
+    {
+        self.err_handler = Some(Arc::new(f));
+        self
+    }
 }
 
 impl Default for JsonConfig {
@@ -243,6 +305,7 @@ impl Default for JsonConfig {
         JsonConfig {
             limit: 32768,
             content_type: None,
+            err_handler: None,
         }
     }
 }
@@ -258,6 +321,10 @@ impl fmt::Debug for JsonConfig {
                     .as_ref()
                     .map(|_| "Arc<dyn Fn(mime::Mime) -> bool + Send + Sync>"),
             )
+            .field(
+                "error_handler",
+                &self.err_handler.as_ref().map(|_| "Arc<JsonErrorHandler>"),
+            )
             .finish()
     }
 }
@@ -383,6 +450,7 @@ mod tests {
     use crate::http::header;
     use crate::util::Bytes;
     use crate::web::test::{from_request, respond_to, TestRequest};
+    use crate::web::DefaultError;
 
     #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug, thiserror::Error)]
     #[error("MyObject({name})")]
@@ -577,6 +645,54 @@ mod tests {
         assert!(s.is_ok())
     }
 
+    #[crate::rt_test]
+    async fn test_error_handler() {
+        let (req, mut pl) = TestRequest::with_header(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static("application/json"),
+        )
+        .set_payload(Bytes::from_static(b"{\"name\": 1}"))
+        .state(JsonConfig::default().error_handler(|err, _| {
+            let status = match err {
+                JsonPayloadError::Deserialize(_) => StatusCode::UNPROCESSABLE_ENTITY,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            HttpResponse::build(status).json(&serde_json::json!({"error": "invalid"}))
+        }))
+        .to_http_parts();
+
+        let err = from_request::<Json<MyObject>>(&req, &mut pl)
+            .await
+            .err()
+            .unwrap();
+        assert!(format!("{}", err).contains("Json deserialize error"));
+        assert_eq!(
+            WebResponseError::<DefaultError>::status_code(&err),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+        let resp = WebResponseError::<DefaultError>::error_response(&err, &req);
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        assert_eq!(resp.body().get_ref(), b"{\"error\":\"invalid\"}");
+
+        // default response
+        let (req, mut pl) = TestRequest::with_header(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static("text/plain"),
+        )
+        .to_http_parts();
+        let err = from_request::<Json<MyObject>>(&req, &mut pl)
+            .await
+            .err()
+            .unwrap();
+        let resp = WebResponseError::<DefaultError>::error_response(&err, &req);
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(resp.body().get_ref(), b"Content type error");
+    }
+
     #[crate::rt_test]
     async fn test_with_json_and_bad_custom_content_type() {
         let (req, mut pl) = TestRequest::with_header(
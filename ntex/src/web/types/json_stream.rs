@@ -0,0 +1,278 @@
+//! Json streaming responder
+use std::{error::Error, pin::Pin, task::Context, task::Poll};
+
+use serde::Serialize;
+
+use crate::http::body::{Body, BodySize, MessageBody};
+use crate::http::helpers::Writer;
+use crate::http::{Response, StatusCode};
+use crate::util::{Bytes, BytesMut, Stream};
+use crate::web::{ErrorRenderer, HttpRequest, Responder};
+
+/// Default size of buffered data, 8Kb
+const FLUSH_SIZE: usize = 8192;
+
+/// Json stream responder
+///
+/// Serializes items of async stream into chunked json array or into
+/// newline delimited json (`application/x-ndjson`). Items are serialized
+/// as response body is written to the peer, so whole collection is never
+/// buffered in memory. Serialized items are buffered until buffer reaches
+/// flush size or stream is not ready to produce next item.
+///
+/// If stream returns an error, response body is terminated and connection
+/// get closed.
+///
+/// ```rust
+/// use ntex::web::{self, types::JsonStream, App};
+///
+/// #[derive(serde::Serialize)]
+/// struct Item {
+///     id: u64,
+/// }
+///
+/// async fn items() -> impl web::Responder {
+///     let items = futures_util::stream::iter(
+///         (0..1000).map(|id| Ok::<_, std::io::Error>(Item { id })),
+///     );
+///     JsonStream::new(items).flush_size(16384)
+/// }
+///
+/// fn main() {
+///     let app = App::new().route("/items", web::get().to(items));
+/// }
+/// ```
+#[derive(Debug)]
+pub struct JsonStream<S> {
+    stream: S,
+    ndjson: bool,
+    flush_size: usize,
+}
+
+impl<S, T, E> JsonStream<S>
+where
+    S: Stream<Item = Result<T, E>> + Unpin,
+    T: Serialize,
+{
+    /// Create json array stream responder
+    pub fn new(stream: S) -> Self {
+        JsonStream {
+            stream,
+            ndjson: false,
+            flush_size: FLUSH_SIZE,
+        }
+    }
+
+    /// Serialize items as newline delimited json
+    ///
+    /// Response content type is set to `application/x-ndjson`.
+    pub fn ndjson(mut self) -> Self {
+        self.ndjson = true;
+        self
+    }
+
+    /// Set size of buffered data that triggers chunk flush
+    ///
+    /// With zero flush size each item is sent as separate chunk.
+    /// By default flush size is 8Kb.
+    pub fn flush_size(mut self, size: usize) -> Self {
+        self.flush_size = size;
+        self
+    }
+}
+
+impl<S, T, E, Err> Responder<Err> for JsonStream<S>
+where
+    S: Stream<Item = Result<T, E>> + Unpin + 'static,
+    T: Serialize,
+    E: Error + 'static,
+    Err: ErrorRenderer,
+{
+    async fn respond_to(self, _: &HttpRequest) -> Response {
+        let content_type = if self.ndjson {
+            "application/x-ndjson"
+        } else {
+            "application/json"
+        };
+
+        Response::build(StatusCode::OK)
+            .content_type(content_type)
+            .body(Body::from_message(JsonStreamBody {
+                stream: self.stream,
+                ndjson: self.ndjson,
+                flush_size: self.flush_size,
+                buf: BytesMut::new(),
+                count: 0,
+                done: false,
+            }))
+    }
+}
+
+struct JsonStreamBody<S> {
+    stream: S,
+    ndjson: bool,
+    flush_size: usize,
+    buf: BytesMut,
+    count: usize,
+    done: bool,
+}
+
+impl<S> JsonStreamBody<S> {
+    fn flush(&mut self) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        if self.buf.is_empty() {
+            Poll::Ready(None)
+        } else {
+            Poll::Ready(Some(Ok(self.buf.split().freeze())))
+        }
+    }
+}
+
+impl<S, T, E> MessageBody for JsonStreamBody<S>
+where
+    S: Stream<Item = Result<T, E>> + Unpin + 'static,
+    T: Serialize,
+    E: Error + 'static,
+{
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        loop {
+            if self.done {
+                return Poll::Ready(None);
+            }
+
+            match Pin::new(&mut self.stream).poll_next(cx) {
+                Poll::Ready(Some(Ok(item))) => {
+                    if !self.ndjson {
+                        let sep = if self.count == 0 { b'[' } else { b',' };
+                        self.buf.extend_from_slice(&[sep]);
+                    }
+                    if let Err(e) = serde_json::to_writer(Writer(&mut self.buf), &item) {
+                        self.done = true;
+                        return Poll::Ready(Some(Err(Box::new(e))));
+                    }
+                    if self.ndjson {
+                        self.buf.extend_from_slice(b"\n");
+                    }
+                    self.count += 1;
+
+                    if self.buf.len() >= self.flush_size {
+                        return self.flush();
+                    }
+                }
+                Poll::Ready(Some(Err(e))) => {
+                    self.done = true;
+                    return Poll::Ready(Some(Err(Box::new(e))));
+                }
+                Poll::Ready(None) => {
+                    if !self.ndjson {
+                        if self.count == 0 {
+                            self.buf.extend_from_slice(b"[");
+                        }
+                        self.buf.extend_from_slice(b"]");
+                    }
+                    let result = self.flush();
+                    self.done = true;
+                    return result;
+                }
+                Poll::Pending => {
+                    return if self.buf.is_empty() {
+                        Poll::Pending
+                    } else {
+                        self.flush()
+                    };
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{future::poll_fn, io};
+
+    use super::*;
+    use crate::http::header;
+    use crate::web::test::{read_body, TestRequest};
+    use crate::web::{DefaultError, WebResponse};
+
+    fn items(num: u32) -> impl Stream<Item = Result<u32, io::Error>> + Unpin {
+        futures_util::stream::iter((0..num).map(Ok))
+    }
+
+    #[crate::rt_test]
+    async fn test_json_stream() {
+        let req = TestRequest::default().to_http_request();
+        let resp =
+            <_ as Responder<DefaultError>>::respond_to(JsonStream::new(items(3)), &req)
+                .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        let body = read_body(WebResponse::new(resp, req.clone())).await;
+        assert_eq!(&body[..], b"[0,1,2]");
+
+        let resp =
+            <_ as Responder<DefaultError>>::respond_to(JsonStream::new(items(0)), &req)
+                .await;
+        let body = read_body(WebResponse::new(resp, req.clone())).await;
+        assert_eq!(&body[..], b"[]");
+
+        let resp = <_ as Responder<DefaultError>>::respond_to(
+            JsonStream::new(items(2)).ndjson(),
+            &req,
+        )
+        .await;
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/x-ndjson"
+        );
+        let body = read_body(WebResponse::new(resp, req)).await;
+        assert_eq!(&body[..], b"0\n1\n");
+    }
+
+    #[crate::rt_test]
+    async fn test_flush_size() {
+        let mut body = JsonStreamBody {
+            stream: items(3),
+            ndjson: false,
+            flush_size: 0,
+            buf: BytesMut::new(),
+            count: 0,
+            done: false,
+        };
+        let mut chunks = Vec::new();
+        while let Some(chunk) = poll_fn(|cx| body.poll_next_chunk(cx)).await {
+            chunks.push(chunk.unwrap());
+        }
+        assert_eq!(chunks, vec!["[0", ",1", ",2", "]"]);
+
+        let (tx, rx) = crate::channel::mpsc::channel::<Result<u32, io::Error>>();
+        let mut body = JsonStreamBody {
+            stream: rx,
+            ndjson: true,
+            flush_size: FLUSH_SIZE,
+            buf: BytesMut::new(),
+            count: 0,
+            done: false,
+        };
+        tx.send(Ok(1)).unwrap();
+        tx.send(Ok(2)).unwrap();
+        let chunk = poll_fn(|cx| body.poll_next_chunk(cx)).await;
+        assert_eq!(chunk.unwrap().unwrap(), "1\n2\n");
+
+        tx.send(Err(io::Error::other("error"))).unwrap();
+        assert!(poll_fn(|cx| body.poll_next_chunk(cx))
+            .await
+            .unwrap()
+            .is_err());
+        assert!(poll_fn(|cx| body.poll_next_chunk(cx)).await.is_none());
+    }
+}
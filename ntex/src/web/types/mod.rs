@@ -1,14 +1,35 @@
 //! Extractor types
 
+pub(in crate::web) mod auth;
+#[cfg(feature = "cbor")]
+mod cbor;
+mod flags;
 pub(in crate::web) mod form;
 pub(in crate::web) mod json;
+mod json_stream;
+#[cfg(feature = "msgpack")]
+mod msgpack;
+mod multipart;
+mod optional;
+mod or;
 mod path;
 pub(in crate::web) mod payload;
 mod query;
 pub(in crate::web) mod state;
+mod urlencoded;
 
+pub use self::auth::{AuthConfig, AuthScheme, BasicAuth, BearerAuth};
+#[cfg(feature = "cbor")]
+pub use self::cbor::{Cbor, CborConfig};
+pub use self::flags::Flags;
 pub use self::form::{Form, FormConfig};
 pub use self::json::{Json, JsonConfig};
+pub use self::json_stream::JsonStream;
+#[cfg(feature = "msgpack")]
+pub use self::msgpack::{MsgPack, MsgPackConfig};
+pub use self::multipart::{Field, FieldData, Multipart, MultipartConfig, TempFile};
+pub use self::optional::OptionalConfig;
+pub use self::or::Or;
 pub use self::path::Path;
 pub use self::payload::{Payload, PayloadConfig};
 pub use self::query::Query;
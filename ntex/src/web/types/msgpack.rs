@@ -0,0 +1,341 @@
+//! MsgPack extractor/responder
+use std::{fmt, ops, sync::Arc};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::http::{error, HttpMessage, Payload, Response, StatusCode};
+use crate::web::error::{ErrorRenderer, PayloadError, WebResponseError};
+use crate::web::error::{MsgPackError, MsgPackPayloadError};
+use crate::web::{FromRequest, HttpRequest, Responder};
+
+use super::payload::HttpMessageBody;
+
+/// MsgPack helper
+///
+/// MsgPack can be used for extracting typed information from request's
+/// payload and for msgpack response generation.
+///
+/// To extract typed information from request's body, the type `T` must
+/// implement the `Deserialize` trait from *serde*. Request's content type
+/// must be `application/msgpack`, `application/x-msgpack` or any type
+/// with `+msgpack` suffix.
+///
+/// [**MsgPackConfig**](struct.MsgPackConfig.html) allows to configure extraction
+/// process.
+///
+/// ## Example
+///
+/// ```rust
+/// use ntex::web;
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Info {
+///     username: String,
+/// }
+///
+/// /// deserialize `Info` from request's body and send it back
+/// async fn index(info: web::types::MsgPack<Info>) -> web::types::MsgPack<Info> {
+///     info
+/// }
+///
+/// fn main() {
+///     let app = web::App::new().service(
+///        web::resource("/index.html").route(
+///            web::post().to(index))
+///     );
+/// }
+/// ```
+pub struct MsgPack<T>(pub T);
+
+impl<T> MsgPack<T> {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> ops::Deref for MsgPack<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> ops::DerefMut for MsgPack<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> fmt::Debug for MsgPack<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("MsgPack").field(&self.0).finish()
+    }
+}
+
+impl<T> fmt::Display for MsgPack<T>
+where
+    T: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl<T: Serialize, Err: ErrorRenderer> Responder<Err> for MsgPack<T>
+where
+    Err::Container: From<MsgPackError>,
+{
+    async fn respond_to(self, req: &HttpRequest) -> Response {
+        let body = match rmp_serde::to_vec_named(&self.0) {
+            Ok(body) => body,
+            Err(e) => return e.error_response(req),
+        };
+
+        Response::build(StatusCode::OK)
+            .content_type("application/msgpack")
+            .body(body)
+    }
+}
+
+impl<T, Err: ErrorRenderer> FromRequest<Err> for MsgPack<T>
+where
+    T: DeserializeOwned + 'static,
+{
+    type Error = MsgPackPayloadError;
+
+    async fn from_request(
+        req: &HttpRequest,
+        payload: &mut Payload,
+    ) -> Result<Self, Self::Error> {
+        let (limit, ctype) = req
+            .app_state::<MsgPackConfig>()
+            .map(|c| (c.limit, c.content_type.clone()))
+            .unwrap_or((32768, None));
+
+        // check content-type
+        let msgpack = if let Ok(Some(mime)) = req.mime_type() {
+            matches!(mime.subtype().as_str(), "msgpack" | "x-msgpack")
+                || mime.suffix().map(|s| s.as_str()) == Some("msgpack")
+                || ctype.as_ref().is_some_and(|predicate| predicate(mime))
+        } else {
+            false
+        };
+        if !msgpack {
+            return Err(MsgPackPayloadError::ContentType);
+        }
+
+        let body = HttpMessageBody::new(req, payload)
+            .limit(limit)
+            .await
+            .map_err(|e| match e {
+                PayloadError::Payload(error::PayloadError::Overflow) => {
+                    MsgPackPayloadError::Overflow
+                }
+                e => e.into(),
+            })?;
+
+        match rmp_serde::from_slice(&body) {
+            Ok(data) => Ok(MsgPack(data)),
+            Err(e) => {
+                log::debug!(
+                    "Failed to deserialize MsgPack from payload. \
+                     Request path: {}",
+                    req.path()
+                );
+                Err(e.into())
+            }
+        }
+    }
+}
+
+/// MsgPack extractor configuration
+///
+/// ```rust
+/// use ntex::web::{self, App};
+///
+/// #[derive(serde::Deserialize)]
+/// struct Info {
+///     username: String,
+/// }
+///
+/// /// deserialize `Info` from request's body, max payload size is 4kb
+/// async fn index(info: web::types::MsgPack<Info>) -> String {
+///     format!("Welcome {}!", info.username)
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/index.html")
+///             .state(
+///                 // change msgpack extractor configuration
+///                 web::types::MsgPackConfig::default()
+///                    .limit(4096)
+///                    .content_type(|mime| {  // <- accept application/octet-stream
+///                        mime == mime::APPLICATION_OCTET_STREAM
+///                    })
+///             )
+///             .route(web::post().to(index))
+///     );
+/// }
+/// ```
+#[derive(Clone)]
+pub struct MsgPackConfig {
+    limit: usize,
+    content_type: Option<Arc<dyn Fn(mime::Mime) -> bool + Send + Sync>>,
+}
+
+impl MsgPackConfig {
+    /// Change max size of payload. By default max size is 32Kb
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Set predicate for allowed content types
+    pub fn content_type<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(mime::Mime) -> bool + Send + Sync + 'static,
+    {
+        self.content_type = Some(Arc::new(predicate));
+        self
+    }
+}
+
+impl Default for MsgPackConfig {
+    fn default() -> Self {
+        MsgPackConfig {
+            limit: 32768,
+            content_type: None,
+        }
+    }
+}
+
+impl fmt::Debug for MsgPackConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MsgPackConfig")
+            .field("limit", &self.limit)
+            .field(
+                "content_type",
+                &self
+                    .content_type
+                    .as_ref()
+                    .map(|_| "Arc<dyn Fn(mime::Mime) -> bool + Send + Sync>"),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header;
+    use crate::util::Bytes;
+    use crate::web::test::{from_request, respond_to, TestRequest};
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct MyObject {
+        name: String,
+    }
+
+    fn payload() -> Bytes {
+        Bytes::from(
+            rmp_serde::to_vec_named(&MyObject {
+                name: "test".to_string(),
+            })
+            .unwrap(),
+        )
+    }
+
+    #[crate::rt_test]
+    async fn test_responder() {
+        let mut m = MsgPack(MyObject {
+            name: "test2".to_string(),
+        });
+        m.name = "test".to_string();
+        assert!(format!("{:?}", m).contains("MsgPack"));
+        assert!(format!("{:?}", MsgPackConfig::default()).contains("MsgPackConfig"));
+
+        let req = TestRequest::default().to_http_request();
+        let resp = respond_to(m, &req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            header::HeaderValue::from_static("application/msgpack")
+        );
+        assert_eq!(resp.body().get_ref(), payload().as_ref());
+    }
+
+    #[crate::rt_test]
+    async fn test_extract() {
+        let (req, mut pl) =
+            TestRequest::with_header(header::CONTENT_TYPE, "application/msgpack")
+                .set_payload(payload())
+                .to_http_parts();
+        let s = from_request::<MsgPack<MyObject>>(&req, &mut pl)
+            .await
+            .unwrap();
+        assert_eq!(
+            s.into_inner(),
+            MyObject {
+                name: "test".to_string()
+            }
+        );
+
+        let (req, mut pl) =
+            TestRequest::with_header(header::CONTENT_TYPE, "application/vnd.api+msgpack")
+                .set_payload(payload())
+                .to_http_parts();
+        let s = from_request::<MsgPack<MyObject>>(&req, &mut pl).await;
+        assert_eq!(s.unwrap().name, "test");
+
+        // overflow
+        let (req, mut pl) =
+            TestRequest::with_header(header::CONTENT_TYPE, "application/x-msgpack")
+                .set_payload(payload())
+                .state(MsgPackConfig::default().limit(2))
+                .to_http_parts();
+        let s = from_request::<MsgPack<MyObject>>(&req, &mut pl).await;
+        let err = s.err().unwrap();
+        assert!(matches!(err, MsgPackPayloadError::Overflow));
+        assert_eq!(
+            WebResponseError::<crate::web::DefaultError>::status_code(&err),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+
+        // content type
+        let (req, mut pl) = TestRequest::with_header(header::CONTENT_TYPE, "text/plain")
+            .set_payload(payload())
+            .to_http_parts();
+        let s = from_request::<MsgPack<MyObject>>(&req, &mut pl).await;
+        assert!(matches!(s.err().unwrap(), MsgPackPayloadError::ContentType));
+
+        let (req, mut pl) =
+            TestRequest::with_header(header::CONTENT_TYPE, "application/octet-stream")
+                .set_payload(payload())
+                .state(
+                    MsgPackConfig::default()
+                        .content_type(|mime| mime == mime::APPLICATION_OCTET_STREAM),
+                )
+                .to_http_parts();
+        let s = from_request::<MsgPack<MyObject>>(&req, &mut pl).await;
+        assert!(s.is_ok());
+
+        // invalid payload
+        let (req, mut pl) =
+            TestRequest::with_header(header::CONTENT_TYPE, "application/msgpack")
+                .set_payload(Bytes::from_static(b"\xc1"))
+                .to_http_parts();
+        let s = from_request::<MsgPack<MyObject>>(&req, &mut pl).await;
+        let err = s.err().unwrap();
+        assert!(matches!(err, MsgPackPayloadError::Deserialize(_)));
+        assert_eq!(
+            WebResponseError::<crate::web::DefaultError>::status_code(&err),
+            StatusCode::BAD_REQUEST
+        );
+    }
+}
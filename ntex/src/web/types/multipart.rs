@@ -0,0 +1,787 @@
+//! Multipart payload extractor
+use std::{fmt, fs, io, io::Write, path::Path, path::PathBuf, rc::Rc};
+
+use nanorand::{Rng, WyRand};
+
+use crate::http::header::{HeaderMap, HeaderName, HeaderValue};
+use crate::http::header::{CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_TYPE};
+use crate::http::{HttpMessage, Payload};
+use crate::rt::spawn_blocking;
+use crate::util::{stream_recv, Bytes, BytesMut};
+use crate::web::error::{ErrorRenderer, MultipartError};
+use crate::web::{FromRequest, HttpRequest};
+
+const MAX_HEADERS: usize = 32;
+const MAX_HEADERS_SIZE: usize = 8192;
+const SPOOL_BUF_SIZE: usize = 65_536;
+
+/// Multipart payload extractor (`multipart/form-data`)
+///
+/// Payload is parsed lazily as a sequence of fields, each field's content
+/// is read in chunks. Unread content of a field is skipped on request for
+/// next field.
+///
+/// [**MultipartConfig**](struct.MultipartConfig.html) allows to configure
+/// size limits, allowed content types and temp-file spooling.
+///
+/// ```rust
+/// use ntex::web::{self, error::MultipartError, types::Multipart};
+///
+/// async fn upload(mut mp: Multipart) -> Result<String, MultipartError> {
+///     let mut size = 0;
+///     while let Some(mut field) = mp.next_field().await? {
+///         println!("Field: {:?}, file: {:?}", field.name(), field.filename());
+///         while let Some(chunk) = field.chunk().await? {
+///             size += chunk.len();
+///         }
+///     }
+///     Ok(format!("Received {} bytes", size))
+/// }
+/// # fn main() {}
+/// ```
+pub struct Multipart {
+    payload: Payload,
+    boundary: Bytes,
+    buf: BytesMut,
+    state: State,
+    size: usize,
+    cfg: MultipartConfig,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum State {
+    Preamble,
+    Delimiter,
+    Headers,
+    Body,
+    Eof,
+}
+
+impl Multipart {
+    /// Create multipart stream for request
+    pub fn new(
+        req: &HttpRequest,
+        payload: Payload,
+        cfg: MultipartConfig,
+    ) -> Result<Self, MultipartError> {
+        let mime = req
+            .mime_type()
+            .ok()
+            .flatten()
+            .ok_or(MultipartError::ContentType)?;
+        if mime.type_() != mime::MULTIPART || mime.subtype() != mime::FORM_DATA {
+            return Err(MultipartError::ContentType);
+        }
+        let boundary = match mime.get_param(mime::BOUNDARY) {
+            Some(b) if !b.as_str().is_empty() && b.as_str().len() <= 70 => b,
+            _ => return Err(MultipartError::Boundary),
+        };
+
+        // reject large payloads before reading body
+        let len = req
+            .headers()
+            .get(&CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<usize>().ok());
+        if let Some(len) = len {
+            if len > cfg.limit {
+                return Err(MultipartError::Overflow { limit: cfg.limit });
+            }
+        }
+
+        let mut buf = BytesMut::with_capacity(boundary.as_str().len() + 4);
+        buf.extend_from_slice(b"\r\n--");
+        buf.extend_from_slice(boundary.as_str().as_bytes());
+
+        Ok(Multipart {
+            payload,
+            cfg,
+            boundary: buf.freeze(),
+            buf: BytesMut::new(),
+            state: State::Preamble,
+            size: 0,
+        })
+    }
+
+    /// Get next field
+    ///
+    /// Returns `None` if multipart stream is completed.
+    pub async fn next_field(&mut self) -> Result<Option<Field<'_>>, MultipartError> {
+        // skip unread content of previous field
+        while self.state == State::Body {
+            self.read_chunk().await?;
+        }
+
+        loop {
+            match self.state {
+                State::Preamble => {
+                    let boundary = &self.boundary[2..];
+                    if let Some(pos) = find(&self.buf, boundary) {
+                        let _ = self.buf.split_to(pos + boundary.len());
+                        self.state = State::Delimiter;
+                    } else {
+                        // keep possible partial boundary
+                        if self.buf.len() > boundary.len() {
+                            let _ = self.buf.split_to(self.buf.len() - boundary.len());
+                        }
+                        self.fill().await?;
+                    }
+                }
+                State::Delimiter => {
+                    if self.buf.len() < 2 {
+                        self.fill().await?;
+                    } else if self.buf.starts_with(b"--") {
+                        self.state = State::Eof;
+                    } else if self.buf.starts_with(b"\r\n") {
+                        let _ = self.buf.split_to(2);
+                        self.state = State::Headers;
+                    } else {
+                        return Err(MultipartError::Parse);
+                    }
+                }
+                State::Headers => {
+                    let headers = if self.buf.starts_with(b"\r\n") {
+                        let _ = self.buf.split_to(2);
+                        HeaderMap::new()
+                    } else if let Some(pos) = find(&self.buf, b"\r\n\r\n") {
+                        let headers = parse_headers(&self.buf[..pos + 4])?;
+                        let _ = self.buf.split_to(pos + 4);
+                        headers
+                    } else if self.buf.len() > MAX_HEADERS_SIZE {
+                        return Err(MultipartError::Parse);
+                    } else {
+                        self.fill().await?;
+                        continue;
+                    };
+                    return self.field(headers).map(Some);
+                }
+                State::Body => unreachable!(),
+                State::Eof => return Ok(None),
+            }
+        }
+    }
+
+    fn field(&mut self, headers: HeaderMap) -> Result<Field<'_>, MultipartError> {
+        let (name, filename) = headers
+            .get(&CONTENT_DISPOSITION)
+            .and_then(|v| v.to_str().ok())
+            .map(parse_disposition)
+            .unwrap_or_default();
+        let allowed = &self.cfg.content_types;
+        let content_type = if let Some(val) = headers.get(&CONTENT_TYPE) {
+            match val.to_str().ok().and_then(|v| v.parse::<mime::Mime>().ok()) {
+                Some(ct) => Some(ct),
+                // malformed content type could not match allowed types
+                None if !allowed.is_empty() => {
+                    return Err(MultipartError::FieldContentType {
+                        name: name.unwrap_or_default(),
+                    });
+                }
+                None => None,
+            }
+        } else {
+            None
+        };
+
+        if let Some(ref ct) = content_type {
+            if !allowed.is_empty()
+                && !allowed.iter().any(|m| {
+                    m.type_() == ct.type_()
+                        && (m.subtype() == mime::STAR || m.subtype() == ct.subtype())
+                })
+            {
+                return Err(MultipartError::FieldContentType {
+                    name: name.unwrap_or_default(),
+                });
+            }
+        }
+
+        self.state = State::Body;
+        Ok(Field {
+            name,
+            filename,
+            content_type,
+            headers,
+            mp: self,
+            size: 0,
+        })
+    }
+
+    async fn read_chunk(&mut self) -> Result<Option<Bytes>, MultipartError> {
+        while self.state == State::Body {
+            if let Some(pos) = find(&self.buf, &self.boundary) {
+                let chunk = self.buf.split_to(pos).freeze();
+                let _ = self.buf.split_to(self.boundary.len());
+                self.state = State::Delimiter;
+                if !chunk.is_empty() {
+                    return Ok(Some(chunk));
+                }
+            } else if self.buf.len() > self.boundary.len() {
+                let len = self.buf.len() - self.boundary.len();
+                return Ok(Some(self.buf.split_to(len).freeze()));
+            } else {
+                self.fill().await?;
+            }
+        }
+        Ok(None)
+    }
+
+    async fn fill(&mut self) -> Result<(), MultipartError> {
+        match stream_recv(&mut self.payload).await {
+            Some(Ok(chunk)) => {
+                self.size += chunk.len();
+                if self.size > self.cfg.limit {
+                    Err(MultipartError::Overflow {
+                        limit: self.cfg.limit,
+                    })
+                } else {
+                    self.buf.extend_from_slice(&chunk);
+                    Ok(())
+                }
+            }
+            Some(Err(e)) => Err(e.into()),
+            None => Err(MultipartError::Incomplete),
+        }
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for Multipart {
+    type Error = MultipartError;
+
+    async fn from_request(
+        req: &HttpRequest,
+        payload: &mut Payload,
+    ) -> Result<Self, Self::Error> {
+        let cfg = req
+            .app_state::<MultipartConfig>()
+            .cloned()
+            .unwrap_or_default();
+        Multipart::new(req, payload.take(), cfg)
+    }
+}
+
+impl fmt::Debug for Multipart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Multipart")
+            .field("state", &self.state)
+            .field("size", &self.size)
+            .field("cfg", &self.cfg)
+            .finish()
+    }
+}
+
+/// Multipart field
+pub struct Field<'a> {
+    mp: &'a mut Multipart,
+    name: Option<String>,
+    filename: Option<String>,
+    content_type: Option<mime::Mime>,
+    headers: HeaderMap,
+    size: usize,
+}
+
+impl Field<'_> {
+    /// Field name from `Content-Disposition` header
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// File name from `Content-Disposition` header
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    /// Field content type
+    pub fn content_type(&self) -> Option<&mime::Mime> {
+        self.content_type.as_ref()
+    }
+
+    /// Field headers
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Read next chunk of field content
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>, MultipartError> {
+        let chunk = self.mp.read_chunk().await?;
+        if let Some(ref chunk) = chunk {
+            self.size += chunk.len();
+            if self.size > self.mp.cfg.field_limit {
+                return Err(MultipartError::FieldOverflow {
+                    name: self.name.clone().unwrap_or_default(),
+                    limit: self.mp.cfg.field_limit,
+                });
+            }
+        }
+        Ok(chunk)
+    }
+
+    /// Read field content to memory
+    pub async fn bytes(&mut self) -> Result<Bytes, MultipartError> {
+        let mut buf = BytesMut::new();
+        while let Some(chunk) = self.chunk().await? {
+            buf.extend_from_slice(&chunk);
+        }
+        Ok(buf.freeze())
+    }
+
+    /// Read field content
+    ///
+    /// Content larger than spool threshold is written to temporary file,
+    /// file io runs on blocking threads pool.
+    pub async fn data(&mut self) -> Result<FieldData, MultipartError> {
+        let mut buf = BytesMut::new();
+        while let Some(chunk) = self.chunk().await? {
+            buf.extend_from_slice(&chunk);
+
+            if let Some(threshold) = self.mp.cfg.spool_threshold {
+                if buf.len() > threshold {
+                    let dir = self.mp.cfg.temp_dir.as_ref().map(|p| p.as_path());
+                    let mut file = TempFile::new(dir).await?;
+                    while let Some(chunk) = self.chunk().await? {
+                        buf.extend_from_slice(&chunk);
+                        if buf.len() >= SPOOL_BUF_SIZE {
+                            file.write(buf.split().freeze()).await?;
+                        }
+                    }
+                    file.write(buf.freeze()).await?;
+                    return Ok(FieldData::File(file));
+                }
+            }
+        }
+        Ok(FieldData::Memory(buf.freeze()))
+    }
+}
+
+impl fmt::Debug for Field<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Field")
+            .field("name", &self.name)
+            .field("filename", &self.filename)
+            .field("content_type", &self.content_type)
+            .field("headers", &self.headers)
+            .finish()
+    }
+}
+
+/// Field content
+#[derive(Debug)]
+pub enum FieldData {
+    /// Content is stored in memory
+    Memory(Bytes),
+    /// Content is spooled to temporary file
+    File(TempFile),
+}
+
+/// Temporary file with spooled field content
+///
+/// File is removed on drop, unless it is persisted.
+#[derive(Debug)]
+pub struct TempFile {
+    path: PathBuf,
+    file: Option<fs::File>,
+    size: u64,
+}
+
+impl TempFile {
+    async fn new(dir: Option<&Path>) -> io::Result<Self> {
+        let dir = dir
+            .map(|d| d.to_path_buf())
+            .unwrap_or_else(std::env::temp_dir);
+        blocking(move || TempFile::create(dir)).await
+    }
+
+    fn create(dir: PathBuf) -> io::Result<Self> {
+        let mut rng = WyRand::new();
+        loop {
+            let path = dir.join(format!("ntex-upload-{:016x}", rng.generate::<u64>()));
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(file) => {
+                    return Ok(TempFile {
+                        path,
+                        file: Some(file),
+                        size: 0,
+                    })
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    async fn write(&mut self, data: Bytes) -> io::Result<()> {
+        if let Some(mut file) = self.file.take() {
+            let len = data.len() as u64;
+            let file = blocking(move || {
+                file.write_all(&data)?;
+                file.flush()?;
+                Ok(file)
+            })
+            .await?;
+            self.file = Some(file);
+            self.size += len;
+        }
+        Ok(())
+    }
+
+    /// Temporary file path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Content size
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Open temporary file for reading
+    pub fn open(&self) -> io::Result<fs::File> {
+        fs::File::open(&self.path)
+    }
+
+    /// Move temporary file to new location
+    ///
+    /// File is renamed, so new location must be on the same filesystem.
+    pub fn persist<P: AsRef<Path>>(mut self, path: P) -> io::Result<()> {
+        self.file.take();
+        fs::rename(&self.path, path)?;
+        self.path = PathBuf::new();
+        Ok(())
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if !self.path.as_os_str().is_empty() {
+            self.file.take();
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Run io operation on blocking threads pool
+async fn blocking<F, T>(f: F) -> io::Result<T>
+where
+    F: FnOnce() -> io::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    spawn_blocking(f)
+        .await
+        .map_err(|_| io::Error::other("Blocking task failed"))?
+}
+
+/// Multipart extractor configuration
+///
+/// ```rust
+/// use ntex::web::{self, types::Multipart, types::MultipartConfig, App};
+///
+/// async fn upload(mp: Multipart) -> &'static str {
+///     "Uploaded"
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/upload")
+///             .state(
+///                 MultipartConfig::default()
+///                     .limit(64 * 1024 * 1024)
+///                     .field_limit(32 * 1024 * 1024)
+///                     .spool_threshold(256 * 1024)
+///                     .allow_content_type(mime::IMAGE_STAR)
+///             )
+///             .route(web::post().to(upload))
+///     );
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct MultipartConfig {
+    limit: usize,
+    field_limit: usize,
+    spool_threshold: Option<usize>,
+    temp_dir: Option<Rc<PathBuf>>,
+    content_types: Rc<Vec<mime::Mime>>,
+}
+
+impl MultipartConfig {
+    /// Change max size of payload. By default max size is 16Mb
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Change max size of single field. By default max size is 2Mb
+    pub fn field_limit(mut self, limit: usize) -> Self {
+        self.field_limit = limit;
+        self
+    }
+
+    /// Spool fields larger than threshold to temporary files.
+    ///
+    /// Applies to [`Field::data()`] only. By default spooling is disabled.
+    pub fn spool_threshold(mut self, threshold: usize) -> Self {
+        self.spool_threshold = Some(threshold);
+        self
+    }
+
+    /// Set directory for temporary files.
+    ///
+    /// By default system temporary directory is used.
+    pub fn temp_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.temp_dir = Some(Rc::new(dir.into()));
+        self
+    }
+
+    /// Allow field content type.
+    ///
+    /// Fields with not allowed content type are rejected, fields without
+    /// content type are always allowed. Wildcard subtypes (`image/*`) are
+    /// supported. By default any content type is allowed.
+    pub fn allow_content_type(mut self, ct: mime::Mime) -> Self {
+        Rc::make_mut(&mut self.content_types).push(ct);
+        self
+    }
+}
+
+impl Default for MultipartConfig {
+    fn default() -> Self {
+        MultipartConfig {
+            limit: 16_777_216,
+            field_limit: 2_097_152,
+            spool_threshold: None,
+            temp_dir: None,
+            content_types: Rc::new(Vec::new()),
+        }
+    }
+}
+
+fn find(buf: &[u8], pat: &[u8]) -> Option<usize> {
+    buf.windows(pat.len()).position(|w| w == pat)
+}
+
+fn parse_headers(src: &[u8]) -> Result<HeaderMap, MultipartError> {
+    let mut hdrs = [httparse::EMPTY_HEADER; MAX_HEADERS];
+    match httparse::parse_headers(src, &mut hdrs) {
+        Ok(httparse::Status::Complete((_, hdrs))) => {
+            let mut headers = HeaderMap::new();
+            for h in hdrs {
+                let name = HeaderName::from_bytes(h.name.as_bytes())
+                    .map_err(|_| MultipartError::Parse)?;
+                let value =
+                    HeaderValue::from_bytes(h.value).map_err(|_| MultipartError::Parse)?;
+                headers.append(name, value);
+            }
+            Ok(headers)
+        }
+        _ => Err(MultipartError::Parse),
+    }
+}
+
+/// Parse `name` and `filename` params of `Content-Disposition` header
+fn parse_disposition(val: &str) -> (Option<String>, Option<String>) {
+    let (mut name, mut filename) = (None, None);
+    let mut rest = val.split_once(';').map(|(_, rest)| rest).unwrap_or("");
+
+    while let Some((key, tail)) = rest.split_once('=') {
+        let tail = tail.trim_start();
+        let value = if let Some(quoted) = tail.strip_prefix('"') {
+            let (value, tail) = quoted.split_once('"').unwrap_or((quoted, ""));
+            rest = tail.split_once(';').map(|(_, rest)| rest).unwrap_or("");
+            value
+        } else {
+            let (value, tail) = tail.split_once(';').unwrap_or((tail, ""));
+            rest = tail;
+            value.trim()
+        };
+
+        match key.trim().to_ascii_lowercase().as_str() {
+            "name" => name = Some(value.to_string()),
+            "filename" => filename = Some(value.to_string()),
+            _ => (),
+        }
+    }
+    (name, filename)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::{test::TestRequest, DefaultError};
+    use crate::{channel::mpsc, http::error::PayloadError};
+
+    const BODY: &[u8] = b"preamble\r\n--abc\r\n\
+        Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+        hello\r\n\
+        --abc\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"a;b.txt\"\r\n\
+        Content-Type: text/plain\r\n\r\n\
+        file\r\ncontent\r\n\
+        --abc--\r\n";
+
+    async fn multipart(cfg: Option<MultipartConfig>, chunk: usize) -> Multipart {
+        let (tx, rx) = mpsc::channel::<Result<Bytes, PayloadError>>();
+        for chunk in BODY.chunks(chunk) {
+            tx.send(Ok(Bytes::copy_from_slice(chunk))).unwrap();
+        }
+        drop(tx);
+
+        let mut req = TestRequest::default()
+            .header(CONTENT_TYPE, "multipart/form-data; boundary=abc");
+        if let Some(cfg) = cfg {
+            req = req.state(cfg);
+        }
+        let (req, _) = req.to_http_parts();
+        let mut pl = Payload::from_stream(rx);
+        <Multipart as FromRequest<DefaultError>>::from_request(&req, &mut pl)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_disposition() {
+        assert_eq!(
+            parse_disposition("form-data; name=\"a\"; filename=\"b;c.txt\""),
+            (Some("a".to_string()), Some("b;c.txt".to_string()))
+        );
+        assert_eq!(
+            parse_disposition("form-data; filename=b.txt ; name=a"),
+            (Some("a".to_string()), Some("b.txt".to_string()))
+        );
+        assert_eq!(parse_disposition("form-data"), (None, None));
+    }
+
+    #[crate::rt_test]
+    async fn test_fields() {
+        for size in [1, 3, 7, BODY.len()] {
+            let mut mp = multipart(None, size).await;
+
+            let mut field = mp.next_field().await.unwrap().unwrap();
+            assert_eq!(field.name(), Some("text"));
+            assert_eq!(field.filename(), None);
+            assert_eq!(field.bytes().await.unwrap(), Bytes::from_static(b"hello"));
+
+            let mut field = mp.next_field().await.unwrap().unwrap();
+            assert_eq!(field.name(), Some("file"));
+            assert_eq!(field.filename(), Some("a;b.txt"));
+            assert_eq!(field.content_type(), Some(&mime::TEXT_PLAIN));
+            assert_eq!(
+                field.bytes().await.unwrap(),
+                Bytes::from_static(b"file\r\ncontent")
+            );
+            assert!(mp.next_field().await.unwrap().is_none());
+        }
+
+        // skip unread fields
+        let mut mp = multipart(None, 5).await;
+        mp.next_field().await.unwrap().unwrap();
+        let field = mp.next_field().await.unwrap().unwrap();
+        assert_eq!(field.name(), Some("file"));
+        assert!(mp.next_field().await.unwrap().is_none());
+    }
+
+    #[crate::rt_test]
+    async fn test_limits() {
+        let mut mp = multipart(Some(MultipartConfig::default().limit(32)), 8).await;
+        assert!(matches!(
+            mp.next_field().await,
+            Err(MultipartError::Overflow { limit: 32 })
+        ));
+
+        let mut mp = multipart(Some(MultipartConfig::default().field_limit(5)), 8).await;
+        assert!(mp
+            .next_field()
+            .await
+            .unwrap()
+            .unwrap()
+            .bytes()
+            .await
+            .is_ok());
+        let mut field = mp.next_field().await.unwrap().unwrap();
+        assert!(matches!(
+            field.bytes().await,
+            Err(MultipartError::FieldOverflow { limit: 5, .. })
+        ));
+
+        let cfg = MultipartConfig::default().allow_content_type(mime::IMAGE_STAR);
+        let mut mp = multipart(Some(cfg), 8).await;
+        assert!(mp.next_field().await.is_ok());
+        assert!(matches!(
+            mp.next_field().await,
+            Err(MultipartError::FieldContentType { .. })
+        ));
+
+        // malformed content type is not allowed
+        let body = b"--abc\r\n\
+            Content-Disposition: form-data; name=\"file\"\r\n\
+            Content-Type: image\r\n\r\n\
+            data\r\n\
+            --abc--\r\n";
+        let (req, mut pl) = TestRequest::default()
+            .header(CONTENT_TYPE, "multipart/form-data; boundary=abc")
+            .state(MultipartConfig::default().allow_content_type(mime::IMAGE_STAR))
+            .set_payload(&body[..])
+            .to_http_parts();
+        let mut mp = <Multipart as FromRequest<DefaultError>>::from_request(&req, &mut pl)
+            .await
+            .unwrap();
+        assert!(matches!(
+            mp.next_field().await,
+            Err(MultipartError::FieldContentType { .. })
+        ));
+    }
+
+    #[crate::rt_test]
+    async fn test_spool() {
+        let cfg = MultipartConfig::default().spool_threshold(8);
+        let mut mp = multipart(Some(cfg), 4).await;
+
+        let mut field = mp.next_field().await.unwrap().unwrap();
+        assert!(matches!(field.data().await.unwrap(), FieldData::Memory(_)));
+
+        let mut field = mp.next_field().await.unwrap().unwrap();
+        let file = if let FieldData::File(file) = field.data().await.unwrap() {
+            file
+        } else {
+            panic!()
+        };
+        assert_eq!(file.size(), 13);
+        assert_eq!(fs::read(file.path()).unwrap(), b"file\r\ncontent");
+
+        let path = file.path().to_path_buf();
+        drop(file);
+        assert!(!path.exists());
+    }
+
+    #[crate::rt_test]
+    async fn test_errors() {
+        let (req, mut pl) = TestRequest::default()
+            .header(CONTENT_TYPE, "text/plain")
+            .to_http_parts();
+        let res =
+            <Multipart as FromRequest<DefaultError>>::from_request(&req, &mut pl).await;
+        assert!(matches!(res, Err(MultipartError::ContentType)));
+
+        let (req, mut pl) = TestRequest::default()
+            .header(CONTENT_TYPE, "multipart/form-data")
+            .to_http_parts();
+        let res =
+            <Multipart as FromRequest<DefaultError>>::from_request(&req, &mut pl).await;
+        assert!(matches!(res, Err(MultipartError::Boundary)));
+
+        let (req, mut pl) = TestRequest::default()
+            .header(CONTENT_TYPE, "multipart/form-data; boundary=abc")
+            .set_payload(&BODY[..66])
+            .to_http_parts();
+        let mut mp = <Multipart as FromRequest<DefaultError>>::from_request(&req, &mut pl)
+            .await
+            .unwrap();
+        let mut field = mp.next_field().await.unwrap().unwrap();
+        assert!(matches!(
+            field.bytes().await,
+            Err(MultipartError::Incomplete)
+        ));
+    }
+}
@@ -0,0 +1,86 @@
+//! Configuration for optional extractors
+use std::{any::Any, fmt, sync::Arc};
+
+type Check = dyn Fn(&dyn Any) -> Option<bool> + Send + Sync;
+
+/// Configuration for `Option<T>` and `Result<T, E>` extractors.
+///
+/// By default `Option<T>` extractor converts any error of inner extractor
+/// to `None` and `Result<T, E>` extractor passes any error to handler.
+/// Config selects errors that are tolerated, other errors fail request
+/// as if inner extractor is used directly.
+///
+/// ```rust
+/// use ntex::web::{self, error::JsonPayloadError, types::{Json, OptionalConfig}, App};
+///
+/// #[derive(serde::Deserialize)]
+/// struct Info {
+///     username: String,
+/// }
+///
+/// // request without json body is accepted,
+/// // but invalid json is rejected with error
+/// async fn index(info: Option<Json<Info>>) -> String {
+///     match info {
+///         Some(info) => format!("Welcome {}!", info.username),
+///         None => "Welcome!".to_string(),
+///     }
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/index.html")
+///             .state(OptionalConfig::default().strict().tolerate(
+///                 |err: &JsonPayloadError| matches!(err, JsonPayloadError::ContentType),
+///             ))
+///             .route(web::post().to(index))
+///     );
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct OptionalConfig {
+    checks: Vec<Arc<Check>>,
+    strict: bool,
+}
+
+impl OptionalConfig {
+    /// Tolerate errors of type `E` for which predicate returns `true`.
+    ///
+    /// Errors of type `E` for which predicate returns `false` fail request.
+    /// Predicates are checked in registration order.
+    pub fn tolerate<E, F>(mut self, f: F) -> Self
+    where
+        E: 'static,
+        F: Fn(&E) -> bool + Send + Sync + 'static,
+    {
+        self.checks.push(Arc::new(move |err: &dyn Any| {
+            err.downcast_ref::<E>().map(&f)
+        }));
+        self
+    }
+
+    /// Fail request for errors that are not matched by any predicate.
+    ///
+    /// By default all unmatched errors are tolerated.
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// Check if extractor error is tolerated
+    pub(in crate::web) fn is_tolerated(&self, err: &dyn Any) -> bool {
+        self.checks
+            .iter()
+            .find_map(|f| f(err))
+            .unwrap_or(!self.strict)
+    }
+}
+
+impl fmt::Debug for OptionalConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OptionalConfig")
+            .field("checks", &self.checks.len())
+            .field("strict", &self.strict)
+            .finish()
+    }
+}
@@ -0,0 +1,139 @@
+//! Extractor composition
+use crate::http::Payload;
+use crate::web::error::ErrorRenderer;
+use crate::web::{FromRequest, HttpRequest};
+
+/// Extract one of two types from the request.
+///
+/// Extractor `A` is tried first, if it fails extractor `B` is used.
+/// If both extractors fail, error of the first extractor is returned.
+///
+/// **Note**: body extractors check request content type before reading
+/// payload, so `Or<Json<T>, Form<T>>` works as expected. But if first
+/// extractor reads payload and fails, payload is not available for
+/// second extractor.
+///
+/// ```rust
+/// use ntex::web::{self, types::{Form, Json, Or}, App};
+///
+/// #[derive(serde::Deserialize)]
+/// struct Info {
+///     username: String,
+/// }
+///
+/// async fn index(info: Or<Json<Info>, Form<Info>>) -> String {
+///     let info = match info {
+///         Or::Left(Json(info)) => info,
+///         Or::Right(Form(info)) => info,
+///     };
+///     format!("Welcome {}!", info.username)
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/index.html").route(web::post().to(index))
+///     );
+/// }
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Or<A, B> {
+    /// Value of the first extractor
+    Left(A),
+    /// Value of the second extractor
+    Right(B),
+}
+
+impl<A, B> Or<A, B> {
+    /// Return true if value is extracted by the first extractor
+    pub fn is_left(&self) -> bool {
+        matches!(self, Or::Left(_))
+    }
+
+    /// Return true if value is extracted by the second extractor
+    pub fn is_right(&self) -> bool {
+        matches!(self, Or::Right(_))
+    }
+}
+
+impl<T> Or<T, T> {
+    /// Extract inner value if both extractors produce the same type
+    pub fn into_inner(self) -> T {
+        match self {
+            Or::Left(val) | Or::Right(val) => val,
+        }
+    }
+}
+
+impl<A, B, Err> FromRequest<Err> for Or<A, B>
+where
+    A: FromRequest<Err>,
+    B: FromRequest<Err>,
+    A::Error: Into<Err::Container>,
+    B::Error: Into<Err::Container>,
+    Err: ErrorRenderer,
+{
+    type Error = Err::Container;
+
+    async fn from_request(
+        req: &HttpRequest,
+        payload: &mut Payload,
+    ) -> Result<Self, Self::Error> {
+        match A::from_request(req, payload).await {
+            Ok(val) => Ok(Or::Left(val)),
+            Err(err) => match B::from_request(req, payload).await {
+                Ok(val) => Ok(Or::Right(val)),
+                Err(e) => {
+                    log::debug!("Error for Or<A, B> extractor: {}", e.into());
+                    Err(err.into())
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header;
+    use crate::util::Bytes;
+    use crate::web::test::{from_request, TestRequest};
+    use crate::web::types::{Form, Json};
+
+    #[derive(serde::Deserialize, Debug)]
+    struct Info {
+        hello: String,
+    }
+
+    #[crate::rt_test]
+    async fn test_or() {
+        let (req, mut pl) =
+            TestRequest::with_header(header::CONTENT_TYPE, "application/json")
+                .header(header::CONTENT_LENGTH, "16")
+                .set_payload(Bytes::from_static(b"{\"hello\":\"json\"}"))
+                .to_http_parts();
+        let r = from_request::<Or<Json<Info>, Form<Info>>>(&req, &mut pl)
+            .await
+            .unwrap();
+        assert!(r.is_left());
+
+        let (req, mut pl) = TestRequest::with_header(
+            header::CONTENT_TYPE,
+            "application/x-www-form-urlencoded",
+        )
+        .header(header::CONTENT_LENGTH, "10")
+        .set_payload(Bytes::from_static(b"hello=form"))
+        .to_http_parts();
+        let r = from_request::<Or<Json<Info>, Form<Info>>>(&req, &mut pl)
+            .await
+            .unwrap();
+        match r {
+            Or::Right(Form(info)) => assert_eq!(info.hello, "form"),
+            Or::Left(_) => panic!("form is expected"),
+        }
+
+        let (req, mut pl) =
+            TestRequest::with_header(header::CONTENT_TYPE, "text/plain").to_http_parts();
+        let r = from_request::<Or<Json<Info>, Form<Info>>>(&req, &mut pl).await;
+        assert!(r.is_err());
+    }
+}
@@ -299,7 +299,7 @@ impl Default for PayloadConfig {
 /// By default only 256Kb payload reads to a memory, then
 /// `PayloadError::Overflow` get returned. Use `MessageBody::limit()`
 /// method to change upper limit.
-struct HttpMessageBody {
+pub(super) struct HttpMessageBody {
     limit: usize,
     length: Option<usize>,
     #[cfg(feature = "compress")]
@@ -312,7 +312,10 @@ struct HttpMessageBody {
 
 impl HttpMessageBody {
     /// Create `MessageBody` for request.
-    fn new(req: &HttpRequest, payload: &mut crate::http::Payload) -> HttpMessageBody {
+    pub(super) fn new(
+        req: &HttpRequest,
+        payload: &mut crate::http::Payload,
+    ) -> HttpMessageBody {
         let mut len = None;
         if let Some(l) = req.headers().get(&header::CONTENT_LENGTH) {
             if let Ok(s) = l.to_str() {
@@ -348,7 +351,7 @@ impl HttpMessageBody {
     }
 
     /// Change max size of payload. By default max size is 256Kb
-    fn limit(mut self, limit: usize) -> Self {
+    pub(super) fn limit(mut self, limit: usize) -> Self {
         self.limit = limit;
         self
     }
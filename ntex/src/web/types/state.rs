@@ -23,8 +23,14 @@ use crate::web::service::AppState;
 /// threads, a shareable object should be used, e.g. `Send + Sync`. Application
 /// state does not need to be `Send` or `Sync`.
 ///
+/// State could also be attached to scopes and resources with `Scope::state()`
+/// and `Resource::state()` methods. State is resolved hierarchically, lookup
+/// starts from the innermost resource or scope and goes up to the application,
+/// innermost state wins.
+///
 /// If state is not set for a handler, using `State<T>` extractor would
-/// cause *Internal Server Error* response.
+/// cause *Internal Server Error* response. Error contains requested type name
+/// and list of searched scopes.
 ///
 /// ```rust
 /// use std::sync::{Arc, Mutex};
@@ -84,12 +90,16 @@ impl<T: 'static, E: ErrorRenderer> FromRequest<E> for State<T> {
         if req.0.app_state.contains::<T>() {
             Ok(Self(req.0.app_state.clone(), PhantomData))
         } else {
+            let err = StateExtractorError::NotFound {
+                type_name: std::any::type_name::<T>(),
+                scopes: req.0.app_state.scopes(),
+            };
             log::debug!(
-                "Failed to construct App-level State extractor. \
-                 Request path: {:?}",
+                "Failed to construct State extractor: {}. Request path: {:?}",
+                err,
                 req.path()
             );
-            Err(StateExtractorError::NotConfigured)
+            Err(err)
         }
     }
 }
@@ -101,7 +111,7 @@ mod tests {
     use super::*;
     use crate::http::StatusCode;
     use crate::web::test::{self, init_service, TestRequest};
-    use crate::web::{self, App, HttpResponse};
+    use crate::web::{self, App, DefaultError, HttpResponse};
 
     #[crate::rt_test]
     async fn test_state_extractor() {
@@ -198,6 +208,72 @@ mod tests {
         assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
     }
 
+    #[crate::rt_test]
+    async fn test_nested_scope_state() {
+        let srv = init_service(
+            App::new().state(1usize).state(1u8).service(
+                web::scope("/a").state(10usize).state(10u16).service(
+                    web::scope("/b")
+                        .state(100usize)
+                        .service(web::resource("/c").route(web::get().to(
+                            |a: State<usize>, b: State<u16>, c: State<u8>| async move {
+                                assert_eq!(*a, 100);
+                                assert_eq!(*b, 10);
+                                assert_eq!(*c, 1);
+                                HttpResponse::Ok()
+                            },
+                        ))),
+                ),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/a/b/c").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[crate::rt_test]
+    async fn test_state_not_found() {
+        let app =
+            App::new()
+                .state(1usize)
+                .service(web::scope("/a").state(10u16).service(
+                    web::scope("/b").service(web::resource("/c").state(1u8).to(
+                        |req: HttpRequest| async move {
+                            let err =
+                                <State<u32> as FromRequest<DefaultError>>::from_request(
+                                    &req,
+                                    &mut Payload::None,
+                                )
+                                .await
+                                .err()
+                                .unwrap();
+                            assert_eq!(
+                                err,
+                                StateExtractorError::NotFound {
+                                    type_name: "u32",
+                                    scopes: vec![
+                                        "resource \"/c\"".to_string(),
+                                        "scope \"/a\"".to_string(),
+                                        "app".to_string()
+                                    ]
+                                }
+                            );
+                            assert!(err
+                                .to_string()
+                                .contains("searched: resource \"/c\", scope \"/a\", app"));
+                            HttpResponse::Ok()
+                        },
+                    )),
+                ));
+        let srv = init_service(app).await;
+
+        let req = TestRequest::with_uri("/a/b/c").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
     #[crate::rt_test]
     async fn test_override_state() {
         let srv = init_service(App::new().state(1usize).service(
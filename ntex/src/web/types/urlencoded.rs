@@ -0,0 +1,439 @@
+//! Nested urlencoded deserializer
+//!
+//! Supports repeated keys (`tag=a&tag=b`), bracketed arrays (`tag[]=a&tag[]=b`),
+//! indexed arrays (`tag[0]=a&tag[1]=b`) and nested maps (`user[name]=a`).
+use serde::de::value::{Error, MapDeserializer, SeqDeserializer};
+use serde::de::{self, DeserializeOwned, Error as _};
+use serde::de::{IntoDeserializer, Visitor};
+
+use crate::util::HashMap;
+use crate::web::error::UrlencodedError;
+
+/// Max nesting level for keys
+const MAX_DEPTH: usize = 16;
+
+#[derive(Debug, PartialEq)]
+enum Value {
+    Str(String),
+    Seq(Vec<Value>),
+    Map(Vec<(String, Value)>),
+}
+
+/// Deserialize urlencoded body with support for arrays and nested maps.
+pub(super) fn from_bytes<T: DeserializeOwned>(
+    body: &[u8],
+    limits: &HashMap<String, usize>,
+) -> Result<T, UrlencodedError> {
+    let pairs = parse_pairs(body, limits)?;
+
+    let mut root = Vec::new();
+    for (key, val) in pairs {
+        let path = parse_key(&key);
+        if path.len() > MAX_DEPTH {
+            return Err(UrlencodedError::Parse);
+        }
+        insert(&mut root, &path, val)?;
+    }
+    T::deserialize(Value::Map(root)).map_err(|_| UrlencodedError::Parse)
+}
+
+/// Split urlencoded body to key/value pairs and check per-field limits.
+pub(super) fn parse_pairs(
+    body: &[u8],
+    limits: &HashMap<String, usize>,
+) -> Result<Vec<(String, String)>, UrlencodedError> {
+    let pairs = serde_urlencoded::from_bytes::<Vec<(String, String)>>(body)
+        .map_err(|_| UrlencodedError::Parse)?;
+
+    if !limits.is_empty() {
+        for (key, val) in &pairs {
+            let name = key.split('[').next().unwrap_or(key);
+            if let Some(limit) = limits.get(name) {
+                if val.len() > *limit {
+                    return Err(UrlencodedError::FieldOverflow {
+                        name: name.to_string(),
+                        size: val.len(),
+                        limit: *limit,
+                    });
+                }
+            }
+        }
+    }
+    Ok(pairs)
+}
+
+/// Split key to path segments, `a[b][]` is parsed to `["a", "b", ""]`.
+///
+/// Malformed keys are used as is.
+fn parse_key(key: &str) -> Vec<&str> {
+    let start = match key.find('[') {
+        Some(0) | None => return vec![key],
+        Some(idx) => idx,
+    };
+
+    let mut path = vec![&key[..start]];
+    let mut rest = &key[start..];
+    while !rest.is_empty() {
+        match (rest.strip_prefix('['), rest.find(']')) {
+            (Some(seg), Some(end)) => {
+                let seg = &seg[..end - 1];
+                if seg.contains('[') {
+                    return vec![key];
+                }
+                path.push(seg);
+                rest = &rest[end + 1..];
+            }
+            _ => return vec![key],
+        }
+    }
+    path
+}
+
+fn insert(
+    map: &mut Vec<(String, Value)>,
+    path: &[&str],
+    val: String,
+) -> Result<(), UrlencodedError> {
+    let (name, rest) = path.split_first().ok_or(UrlencodedError::Parse)?;
+    let idx = if let Some(idx) = map.iter().position(|(k, _)| k == name) {
+        idx
+    } else {
+        let item = match rest.first() {
+            None => {
+                map.push((name.to_string(), Value::Str(val)));
+                return Ok(());
+            }
+            Some(&"") => Value::Seq(Vec::new()),
+            Some(_) => Value::Map(Vec::new()),
+        };
+        map.push((name.to_string(), item));
+        map.len() - 1
+    };
+
+    let entry = &mut map[idx].1;
+    match rest.split_first() {
+        // repeated key, `a=1&a=2`
+        None => match entry {
+            Value::Str(_) => {
+                let prev = std::mem::replace(entry, Value::Seq(Vec::new()));
+                if let Value::Seq(ref mut items) = entry {
+                    items.push(prev);
+                    items.push(Value::Str(val));
+                }
+                Ok(())
+            }
+            Value::Seq(ref mut items) => {
+                items.push(Value::Str(val));
+                Ok(())
+            }
+            Value::Map(_) => Err(UrlencodedError::Parse),
+        },
+        // array item, `a[]=1` or `a[][b]=1`
+        Some((&"", tail)) => {
+            if let Value::Str(_) = entry {
+                let prev = std::mem::replace(entry, Value::Seq(Vec::new()));
+                if let Value::Seq(ref mut items) = entry {
+                    items.push(prev);
+                }
+            }
+            let items = if let Value::Seq(ref mut items) = entry {
+                items
+            } else {
+                return Err(UrlencodedError::Parse);
+            };
+
+            if tail.is_empty() {
+                items.push(Value::Str(val));
+                return Ok(());
+            }
+
+            // continue last map item unless it already contains the key
+            let reuse = matches!(
+                items.last(),
+                Some(Value::Map(m)) if !m.iter().any(|(k, _)| k == tail[0])
+            );
+            if !reuse {
+                items.push(Value::Map(Vec::new()));
+            }
+            if let Some(Value::Map(ref mut m)) = items.last_mut() {
+                insert(m, tail, val)
+            } else {
+                Err(UrlencodedError::Parse)
+            }
+        }
+        // nested map, `a[b]=1`
+        Some(_) => {
+            if let Value::Map(ref mut m) = entry {
+                insert(m, rest, val)
+            } else {
+                Err(UrlencodedError::Parse)
+            }
+        }
+    }
+}
+
+impl Value {
+    fn unexpected(&self) -> de::Unexpected<'_> {
+        match self {
+            Value::Str(s) => de::Unexpected::Str(s),
+            Value::Seq(_) => de::Unexpected::Seq,
+            Value::Map(_) => de::Unexpected::Map,
+        }
+    }
+
+    fn into_str(self, exp: &str) -> Result<String, Error> {
+        match self {
+            Value::Str(s) => Ok(s),
+            v => Err(Error::invalid_type(v.unexpected(), &exp)),
+        }
+    }
+
+    fn into_seq(self) -> Result<Vec<Value>, Error> {
+        match self {
+            Value::Str(_) => Ok(vec![self]),
+            Value::Seq(items) => Ok(items),
+            // indexed array, `a[0]=1&a[1]=2`
+            Value::Map(items) => {
+                let mut items = items
+                    .into_iter()
+                    .map(|(k, v)| k.parse::<usize>().map(|k| (k, v)))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| Error::invalid_type(de::Unexpected::Map, &"sequence"))?;
+                items.sort_by_key(|(k, _)| *k);
+                Ok(items.into_iter().map(|(_, v)| v).collect())
+            }
+        }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Value {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! forward_parsed_value {
+    ($($ty:ident => $method:ident,)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                let s = self.into_str(stringify!($ty))?;
+                match s.parse::<$ty>() {
+                    Ok(val) => IntoDeserializer::<'de, Error>::into_deserializer(val)
+                        .$method(visitor),
+                    Err(e) => Err(Error::custom(e)),
+                }
+            }
+        )*
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Str(s) => visitor.visit_string(s),
+            Value::Seq(items) => {
+                let mut de = SeqDeserializer::new(items.into_iter());
+                let val = visitor.visit_seq(&mut de)?;
+                de.end()?;
+                Ok(val)
+            }
+            Value::Map(items) => {
+                let mut de = MapDeserializer::new(items.into_iter());
+                let val = visitor.visit_map(&mut de)?;
+                de.end()?;
+                Ok(val)
+            }
+        }
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let mut de = SeqDeserializer::new(self.into_seq()?.into_iter());
+        let val = visitor.visit_seq(&mut de)?;
+        de.end()?;
+        Ok(val)
+    }
+
+    fn deserialize_tuple<V>(self, _: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _: &'static str,
+        _: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_enum(self.into_str("enum")?.into_deserializer())
+    }
+
+    forward_parsed_value! {
+        bool => deserialize_bool,
+        u8 => deserialize_u8,
+        u16 => deserialize_u16,
+        u32 => deserialize_u32,
+        u64 => deserialize_u64,
+        i8 => deserialize_i8,
+        i16 => deserialize_i16,
+        i32 => deserialize_i32,
+        i64 => deserialize_i64,
+        f32 => deserialize_f32,
+        f64 => deserialize_f64,
+        char => deserialize_char,
+    }
+
+    serde::forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct map struct identifier ignored_any
+        i128 u128
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Item {
+        name: String,
+        qty: u32,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Data {
+        id: u64,
+        tags: Vec<String>,
+        user: std::collections::HashMap<String, String>,
+        #[serde(default)]
+        items: Vec<Item>,
+        flag: Option<bool>,
+    }
+
+    #[test]
+    fn test_parse_key() {
+        assert_eq!(parse_key("a"), vec!["a"]);
+        assert_eq!(parse_key("a[]"), vec!["a", ""]);
+        assert_eq!(parse_key("a[b][]"), vec!["a", "b", ""]);
+        assert_eq!(parse_key("a[b]c"), vec!["a[b]c"]);
+        assert_eq!(parse_key("[a]"), vec!["[a]"]);
+        assert_eq!(parse_key("a[[b]]"), vec!["a[[b]]"]);
+    }
+
+    #[test]
+    fn test_nested() {
+        let limits = HashMap::default();
+        let data: Data = from_bytes(
+            b"id=1&tags[]=a&tags[]=b&user[name]=ntex&user[role]=admin&flag=true",
+            &limits,
+        )
+        .unwrap();
+        assert_eq!(data.id, 1);
+        assert_eq!(data.tags, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(data.user.get("name").unwrap(), "ntex");
+        assert_eq!(data.user.get("role").unwrap(), "admin");
+        assert_eq!(data.flag, Some(true));
+
+        // repeated and indexed keys
+        let data: Data =
+            from_bytes(b"id=1&tags=a&tags=b&user[name]=ntex", &limits).unwrap();
+        assert_eq!(data.tags, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(data.flag, None);
+        let data: Data =
+            from_bytes(b"id=1&tags[1]=b&tags[0]=a&user[name]=ntex", &limits).unwrap();
+        assert_eq!(data.tags, vec!["a".to_string(), "b".to_string()]);
+
+        // single value for sequence
+        let data: Data = from_bytes(b"id=1&tags=a&user[name]=ntex", &limits).unwrap();
+        assert_eq!(data.tags, vec!["a".to_string()]);
+
+        // array of maps
+        let data: Data = from_bytes(
+            b"id=1&tags[]=a&user[name]=n&items[][name]=x&items[][qty]=1&items[][name]=y&items[][qty]=2",
+            &limits,
+        )
+        .unwrap();
+        assert_eq!(
+            data.items,
+            vec![
+                Item {
+                    name: "x".to_string(),
+                    qty: 1
+                },
+                Item {
+                    name: "y".to_string(),
+                    qty: 2
+                }
+            ]
+        );
+
+        // type errors
+        assert!(from_bytes::<Data>(b"id=a&tags=a&user[name]=n", &limits).is_err());
+        assert!(from_bytes::<Data>(b"id=1&id=2&tags=a&user[name]=n", &limits).is_err());
+        assert!(from_bytes::<Data>(b"id=1&tags=a&user=n&user[name]=n", &limits).is_err());
+    }
+
+    #[test]
+    fn test_field_limits() {
+        let mut limits = HashMap::default();
+        limits.insert("tags".to_string(), 3);
+
+        assert!(parse_pairs(b"tags[]=abc&tags[]=def", &limits).is_ok());
+        match parse_pairs(b"tags[]=abc&tags[]=defg", &limits) {
+            Err(UrlencodedError::FieldOverflow { name, size, limit }) => {
+                assert_eq!(name, "tags");
+                assert_eq!(size, 4);
+                assert_eq!(limit, 3);
+            }
+            _ => panic!(),
+        }
+    }
+}
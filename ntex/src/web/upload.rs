@@ -0,0 +1,791 @@
+//! Resumable uploads, tus protocol v1.0.0
+//!
+//! [`Uploads`] service implements upload creation, offset tracking,
+//! `PATCH` based append, checksum verification and termination. Upload
+//! data and state are kept by [`UploadStore`] implementation.
+//!
+//! ```rust
+//! use ntex::web::{self, upload, App};
+//!
+//! fn main() {
+//!     // store must be shared between workers
+//!     let store = upload::MemoryUploadStore::new();
+//!
+//!     web::server(move || {
+//!         App::new().service(
+//!             upload::Uploads::new("/files", store.clone()).max_size(1024 * 1024 * 1024),
+//!         )
+//!     });
+//! }
+//! ```
+use std::{collections::HashMap, rc::Rc, sync::Arc, sync::Mutex};
+
+use base64::{engine::general_purpose::STANDARD as base64, Engine};
+#[cfg(feature = "digest")]
+use md5::Md5;
+use sha1::Digest as _;
+#[cfg(feature = "digest")]
+use sha2::Sha256;
+use thiserror::Error;
+
+use crate::http::error::PayloadError;
+use crate::http::header::{self, HeaderName, HeaderValue};
+use crate::http::{Method, Payload, StatusCode};
+use crate::router::ResourceDef;
+use crate::service::fn_service;
+use crate::util::{stream_recv, Bytes, BytesMut};
+
+use super::dev::{WebServiceConfig, WebServiceFactory};
+use super::error::{ErrorRenderer, WebResponseError};
+use super::{HttpRequest, HttpResponse, WebRequest, WebResponse};
+
+/// `Tus-Resumable` header name
+pub const TUS_RESUMABLE: HeaderName = HeaderName::from_static("tus-resumable");
+/// `Tus-Version` header name
+pub const TUS_VERSION: HeaderName = HeaderName::from_static("tus-version");
+/// `Tus-Extension` header name
+pub const TUS_EXTENSION: HeaderName = HeaderName::from_static("tus-extension");
+/// `Tus-Max-Size` header name
+pub const TUS_MAX_SIZE: HeaderName = HeaderName::from_static("tus-max-size");
+/// `Tus-Checksum-Algorithm` header name
+pub const TUS_CHECKSUM_ALGORITHM: HeaderName =
+    HeaderName::from_static("tus-checksum-algorithm");
+/// `Upload-Offset` header name
+pub const UPLOAD_OFFSET: HeaderName = HeaderName::from_static("upload-offset");
+/// `Upload-Length` header name
+pub const UPLOAD_LENGTH: HeaderName = HeaderName::from_static("upload-length");
+/// `Upload-Defer-Length` header name
+pub const UPLOAD_DEFER_LENGTH: HeaderName = HeaderName::from_static("upload-defer-length");
+/// `Upload-Metadata` header name
+pub const UPLOAD_METADATA: HeaderName = HeaderName::from_static("upload-metadata");
+/// `Upload-Checksum` header name
+pub const UPLOAD_CHECKSUM: HeaderName = HeaderName::from_static("upload-checksum");
+
+/// Supported protocol version
+const VERSION: &str = "1.0.0";
+/// Supported protocol extensions
+const EXTENSIONS: &str = "creation,creation-defer-length,checksum,termination";
+/// Supported checksum algorithms
+#[cfg(feature = "digest")]
+const CHECKSUM_ALGORITHMS: &str = "md5,sha1,sha256";
+#[cfg(not(feature = "digest"))]
+const CHECKSUM_ALGORITHMS: &str = "sha1";
+/// Content type of `PATCH` requests
+const OFFSET_OCTET_STREAM: &str = "application/offset+octet-stream";
+/// Default max size of chunk with checksum, 4Mb
+const MAX_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// Upload metadata, decoded values of `Upload-Metadata` header
+pub type UploadMetadata = HashMap<String, String>;
+
+/// Upload state
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UploadInfo {
+    /// Upload size, `None` if size is deferred
+    pub length: Option<u64>,
+    /// Number of received bytes
+    pub offset: u64,
+    /// Upload metadata
+    pub metadata: UploadMetadata,
+}
+
+impl UploadInfo {
+    /// Check if all upload data is received
+    pub fn is_complete(&self) -> bool {
+        self.length == Some(self.offset)
+    }
+}
+
+/// Upload errors
+#[derive(Error, Debug)]
+pub enum UploadError {
+    /// Protocol version is not supported
+    #[error("Unsupported tus protocol version")]
+    Version,
+    /// Upload does not exist
+    #[error("Upload not found")]
+    NotFound,
+    /// Request header is missing or malformed
+    #[error("Invalid {0} header")]
+    Header(HeaderName),
+    /// `Upload-Offset` does not match upload offset
+    #[error("Upload offset mismatch, expected offset is {0}")]
+    Offset(u64),
+    /// Upload size is bigger than allowed
+    #[error("Upload size is bigger than allowed")]
+    Overflow,
+    /// Request content type is not `application/offset+octet-stream`
+    #[error("Content type is expected to be application/offset+octet-stream")]
+    ContentType,
+    /// Checksum algorithm is not supported
+    #[error("Unsupported checksum algorithm")]
+    ChecksumAlgorithm,
+    /// Chunk checksum does not match `Upload-Checksum` header
+    #[error("Checksum mismatch")]
+    Checksum,
+    /// Payload error
+    #[error("Error that occur during reading payload: {0}")]
+    Payload(#[from] PayloadError),
+    /// Store backend error
+    #[error("Upload store error: {0}")]
+    Store(Box<dyn std::error::Error>),
+}
+
+impl<Err: ErrorRenderer> WebResponseError<Err> for UploadError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            UploadError::Version => StatusCode::PRECONDITION_FAILED,
+            UploadError::NotFound => StatusCode::NOT_FOUND,
+            UploadError::Offset(_) => StatusCode::CONFLICT,
+            UploadError::Overflow => StatusCode::PAYLOAD_TOO_LARGE,
+            UploadError::ContentType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            UploadError::Checksum => StatusCode::from_u16(460).unwrap(),
+            UploadError::Store(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn error_response(&self, _: &HttpRequest) -> HttpResponse {
+        let mut res = HttpResponse::build(WebResponseError::<Err>::status_code(self));
+        res.header(TUS_RESUMABLE, VERSION);
+        if let UploadError::Version = self {
+            res.header(TUS_VERSION, VERSION);
+        }
+        res.content_type("text/plain; charset=utf-8")
+            .body(self.to_string())
+    }
+}
+
+#[allow(async_fn_in_trait)]
+/// Upload storage backend
+///
+/// Store keeps upload state and received data by upload id.
+pub trait UploadStore: 'static {
+    /// Create new upload, returns upload id
+    async fn create(
+        &self,
+        length: Option<u64>,
+        metadata: UploadMetadata,
+    ) -> Result<String, UploadError>;
+
+    /// Load upload state
+    ///
+    /// Returns `None` if upload does not exist.
+    async fn info(&self, id: &str) -> Result<Option<UploadInfo>, UploadError>;
+
+    /// Set size of upload created with deferred length
+    async fn set_length(&self, id: &str, length: u64) -> Result<(), UploadError>;
+
+    /// Append chunk to the upload
+    ///
+    /// Store must reject chunk with `UploadError::Offset` error if `offset`
+    /// does not match current offset of the upload.
+    async fn append(&self, id: &str, offset: u64, chunk: Bytes) -> Result<(), UploadError>;
+
+    /// Called once all upload data is received
+    async fn finish(&self, _: &str) -> Result<(), UploadError> {
+        Ok(())
+    }
+
+    /// Delete upload
+    async fn delete(&self, id: &str) -> Result<(), UploadError>;
+}
+
+/// In-memory upload store
+///
+/// Store could be shared between workers, it must be created outside
+/// of the application factory and cloned into each application.
+/// Uploads are lost on restart.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryUploadStore(Arc<Mutex<HashMap<String, (UploadInfo, BytesMut)>>>);
+
+impl MemoryUploadStore {
+    /// Create new in-memory store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Received upload data
+    pub fn data(&self, id: &str) -> Option<Bytes> {
+        self.0
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(|(_, data)| Bytes::copy_from_slice(data))
+    }
+}
+
+impl UploadStore for MemoryUploadStore {
+    async fn create(
+        &self,
+        length: Option<u64>,
+        metadata: UploadMetadata,
+    ) -> Result<String, UploadError> {
+        // upload id is the only access token, use os random source
+        let mut buf = [0u8; 16];
+        getrandom::getrandom(&mut buf).map_err(|e| UploadError::Store(Box::new(e)))?;
+        let id = format!("{:032x}", u128::from_be_bytes(buf));
+        let info = UploadInfo {
+            length,
+            metadata,
+            offset: 0,
+        };
+        self.0
+            .lock()
+            .unwrap()
+            .insert(id.clone(), (info, BytesMut::new()));
+        Ok(id)
+    }
+
+    async fn info(&self, id: &str) -> Result<Option<UploadInfo>, UploadError> {
+        Ok(self.0.lock().unwrap().get(id).map(|(info, _)| info.clone()))
+    }
+
+    async fn set_length(&self, id: &str, length: u64) -> Result<(), UploadError> {
+        let mut uploads = self.0.lock().unwrap();
+        let (info, _) = uploads.get_mut(id).ok_or(UploadError::NotFound)?;
+        info.length = Some(length);
+        Ok(())
+    }
+
+    async fn append(&self, id: &str, offset: u64, chunk: Bytes) -> Result<(), UploadError> {
+        let mut uploads = self.0.lock().unwrap();
+        let (info, data) = uploads.get_mut(id).ok_or(UploadError::NotFound)?;
+        if info.offset != offset {
+            return Err(UploadError::Offset(info.offset));
+        }
+        data.extend_from_slice(&chunk);
+        info.offset += chunk.len() as u64;
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), UploadError> {
+        self.0
+            .lock()
+            .unwrap()
+            .remove(id)
+            .map(|_| ())
+            .ok_or(UploadError::NotFound)
+    }
+}
+
+/// Resumable uploads service
+///
+/// Uploads are created with `POST` request to service path, upload url
+/// is returned in `Location` header. `HEAD` request to upload url returns
+/// current upload offset, client resumes upload with `PATCH` requests
+/// starting from that offset. Upload is deleted with `DELETE` request.
+///
+/// Chunks without `Upload-Checksum` header are appended to the store as
+/// they are received, so interrupted request does not lose received data.
+/// Chunks with checksum are buffered and appended only after successful
+/// verification.
+pub struct Uploads<S> {
+    path: String,
+    store: S,
+    max_size: Option<u64>,
+    max_chunk_size: usize,
+}
+
+impl<S: UploadStore> Uploads<S> {
+    /// Create uploads service for the path prefix
+    pub fn new(path: &str, store: S) -> Self {
+        Uploads {
+            path: path.trim_end_matches('/').to_string(),
+            store,
+            max_size: None,
+            max_chunk_size: MAX_CHUNK_SIZE,
+        }
+    }
+
+    /// Set max upload size
+    ///
+    /// By default upload size is not limited.
+    pub fn max_size(mut self, size: u64) -> Self {
+        self.max_size = Some(size);
+        self
+    }
+
+    /// Set max size of chunk with checksum
+    ///
+    /// Chunks with checksum are buffered in memory until checksum is
+    /// verified. By default max chunk size is 4Mb.
+    pub fn max_chunk_size(mut self, size: usize) -> Self {
+        self.max_chunk_size = size;
+        self
+    }
+
+    async fn handle(
+        &self,
+        req: &HttpRequest,
+        payload: Payload,
+    ) -> Result<HttpResponse, UploadError> {
+        if req.method() == Method::OPTIONS {
+            return Ok(self.options());
+        }
+        if req.headers().get(&TUS_RESUMABLE).map(|v| v.as_bytes())
+            != Some(VERSION.as_bytes())
+        {
+            return Err(UploadError::Version);
+        }
+
+        // unmatched part of the path is upload id
+        let id = req.match_info().path().trim_matches('/');
+        let res = match *req.method() {
+            Method::POST if id.is_empty() => self.create(req).await?,
+            Method::HEAD if !id.is_empty() => self.head(id).await?,
+            Method::PATCH if !id.is_empty() => self.patch(req, id, payload).await?,
+            Method::DELETE if !id.is_empty() => {
+                self.store.delete(id).await?;
+                HttpResponse::NoContent().finish()
+            }
+            _ => {
+                let allow = if id.is_empty() {
+                    "OPTIONS, POST"
+                } else {
+                    "OPTIONS, HEAD, PATCH, DELETE"
+                };
+                HttpResponse::MethodNotAllowed()
+                    .header(header::ALLOW, allow)
+                    .finish()
+            }
+        };
+        Ok(res)
+    }
+
+    fn options(&self) -> HttpResponse {
+        let mut res = HttpResponse::NoContent();
+        res.header(TUS_VERSION, VERSION)
+            .header(TUS_EXTENSION, EXTENSIONS)
+            .header(TUS_CHECKSUM_ALGORITHM, CHECKSUM_ALGORITHMS);
+        if let Some(size) = self.max_size {
+            res.header(TUS_MAX_SIZE, size);
+        }
+        res.finish()
+    }
+
+    async fn create(&self, req: &HttpRequest) -> Result<HttpResponse, UploadError> {
+        let length = if let Some(length) = parse_u64(req, &UPLOAD_LENGTH)? {
+            if self.max_size.is_some_and(|max| length > max) {
+                return Err(UploadError::Overflow);
+            }
+            Some(length)
+        } else if req
+            .headers()
+            .get(&UPLOAD_DEFER_LENGTH)
+            .map(|v| v.as_bytes())
+            == Some(b"1")
+        {
+            None
+        } else {
+            return Err(UploadError::Header(UPLOAD_LENGTH));
+        };
+        let metadata = match req.headers().get(&UPLOAD_METADATA) {
+            Some(value) => {
+                parse_metadata(value).ok_or(UploadError::Header(UPLOAD_METADATA))?
+            }
+            None => UploadMetadata::new(),
+        };
+
+        let id = self.store.create(length, metadata).await?;
+        if length == Some(0) {
+            self.store.finish(&id).await?;
+        }
+        // upload url includes prefixes of parent scopes
+        let path = req.path();
+        let prefix = &path[..path.len() - req.match_info().path().len()];
+        Ok(HttpResponse::Created()
+            .header(
+                header::LOCATION,
+                format!("{}/{}", prefix.trim_end_matches('/'), id),
+            )
+            .finish())
+    }
+
+    async fn head(&self, id: &str) -> Result<HttpResponse, UploadError> {
+        let info = self.store.info(id).await?.ok_or(UploadError::NotFound)?;
+
+        let mut res = HttpResponse::Ok();
+        res.header(UPLOAD_OFFSET, info.offset)
+            .header(header::CACHE_CONTROL, "no-store");
+        if let Some(length) = info.length {
+            res.header(UPLOAD_LENGTH, length);
+        } else {
+            res.header(UPLOAD_DEFER_LENGTH, "1");
+        }
+        Ok(res.finish())
+    }
+
+    async fn patch(
+        &self,
+        req: &HttpRequest,
+        id: &str,
+        mut payload: Payload,
+    ) -> Result<HttpResponse, UploadError> {
+        if req
+            .headers()
+            .get(&header::CONTENT_TYPE)
+            .map(|v| v.as_bytes())
+            != Some(OFFSET_OCTET_STREAM.as_bytes())
+        {
+            return Err(UploadError::ContentType);
+        }
+        let offset =
+            parse_u64(req, &UPLOAD_OFFSET)?.ok_or(UploadError::Header(UPLOAD_OFFSET))?;
+        let checksum = req
+            .headers()
+            .get(&UPLOAD_CHECKSUM)
+            .map(Checksum::parse)
+            .transpose()?;
+
+        let mut info = self.store.info(id).await?.ok_or(UploadError::NotFound)?;
+        if info.offset != offset {
+            return Err(UploadError::Offset(info.offset));
+        }
+
+        // deferred upload length
+        if let Some(length) = parse_u64(req, &UPLOAD_LENGTH)? {
+            if info.length.is_some_and(|l| l != length) || length < info.offset {
+                return Err(UploadError::Header(UPLOAD_LENGTH));
+            }
+            if self.max_size.is_some_and(|max| length > max) {
+                return Err(UploadError::Overflow);
+            }
+            if info.length.is_none() {
+                self.store.set_length(id, length).await?;
+                info.length = Some(length);
+            }
+        }
+        let limit = info.length.or(self.max_size).unwrap_or(u64::MAX);
+
+        if let Some(mut checksum) = checksum {
+            let mut buf = BytesMut::new();
+            while let Some(chunk) = stream_recv(&mut payload).await {
+                let chunk = chunk?;
+                if buf.len() + chunk.len() > self.max_chunk_size
+                    || info.offset + (buf.len() + chunk.len()) as u64 > limit
+                {
+                    return Err(UploadError::Overflow);
+                }
+                checksum.update(&chunk);
+                buf.extend_from_slice(&chunk);
+            }
+            if !checksum.verify() {
+                return Err(UploadError::Checksum);
+            }
+            if !buf.is_empty() {
+                let size = buf.len() as u64;
+                self.store.append(id, info.offset, buf.freeze()).await?;
+                info.offset += size;
+            }
+        } else {
+            while let Some(chunk) = stream_recv(&mut payload).await {
+                let chunk = chunk?;
+                if info.offset + chunk.len() as u64 > limit {
+                    return Err(UploadError::Overflow);
+                }
+                let size = chunk.len() as u64;
+                self.store.append(id, info.offset, chunk).await?;
+                info.offset += size;
+            }
+        }
+
+        if info.is_complete() {
+            self.store.finish(id).await?;
+        }
+        Ok(HttpResponse::NoContent()
+            .header(UPLOAD_OFFSET, info.offset)
+            .finish())
+    }
+}
+
+impl<S, Err> WebServiceFactory<Err> for Uploads<S>
+where
+    S: UploadStore,
+    Err: ErrorRenderer,
+{
+    fn register(self, config: &mut WebServiceConfig<Err>) {
+        let rdef = ResourceDef::root_prefix(self.path.as_str());
+        let uploads = Rc::new(self);
+
+        config.register_service(
+            rdef,
+            None,
+            fn_service(move |req: WebRequest<Err>| {
+                let uploads = uploads.clone();
+                async move {
+                    let (req, payload) = req.into_parts();
+                    let mut res = match uploads.handle(&req, payload).await {
+                        Ok(res) => res,
+                        Err(e) => WebResponseError::<Err>::error_response(&e, &req),
+                    };
+                    res.headers_mut()
+                        .insert(TUS_RESUMABLE, HeaderValue::from_static(VERSION));
+                    Ok(WebResponse::new(res, req))
+                }
+            }),
+            None,
+        )
+    }
+}
+
+/// Parse numeric header
+fn parse_u64(req: &HttpRequest, name: &HeaderName) -> Result<Option<u64>, UploadError> {
+    req.headers()
+        .get(name)
+        .map(|v| {
+            v.to_str()
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .ok_or_else(|| UploadError::Header(name.clone()))
+        })
+        .transpose()
+}
+
+/// Parse `Upload-Metadata` header, `key base64-value` pairs separated by comma
+fn parse_metadata(value: &HeaderValue) -> Option<UploadMetadata> {
+    let mut metadata = UploadMetadata::new();
+    for item in value.to_str().ok()?.split(',') {
+        let mut parts = item.trim().splitn(2, ' ');
+        let key = parts.next().filter(|k| !k.is_empty())?;
+        let value = match parts.next() {
+            Some(value) => String::from_utf8(base64.decode(value.trim()).ok()?).ok()?,
+            None => String::new(),
+        };
+        metadata.insert(key.to_string(), value);
+    }
+    Some(metadata)
+}
+
+/// Chunk checksum
+struct Checksum {
+    hasher: Hasher,
+    expected: Vec<u8>,
+}
+
+enum Hasher {
+    #[cfg(feature = "digest")]
+    Md5(Md5),
+    Sha1(sha1::Sha1),
+    #[cfg(feature = "digest")]
+    Sha256(Sha256),
+}
+
+impl Checksum {
+    /// Parse `Upload-Checksum` header, `algorithm base64-checksum`
+    fn parse(value: &HeaderValue) -> Result<Self, UploadError> {
+        let err = || UploadError::Header(UPLOAD_CHECKSUM);
+        let (alg, checksum) = value
+            .to_str()
+            .ok()
+            .and_then(|v| v.split_once(' '))
+            .ok_or_else(err)?;
+        let expected = base64.decode(checksum.trim()).map_err(|_| err())?;
+
+        let hasher = match alg {
+            #[cfg(feature = "digest")]
+            "md5" => Hasher::Md5(Md5::new()),
+            "sha1" => Hasher::Sha1(sha1::Sha1::new()),
+            #[cfg(feature = "digest")]
+            "sha256" => Hasher::Sha256(Sha256::new()),
+            _ => return Err(UploadError::ChecksumAlgorithm),
+        };
+        Ok(Checksum { hasher, expected })
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self.hasher {
+            #[cfg(feature = "digest")]
+            Hasher::Md5(ref mut h) => h.update(data),
+            Hasher::Sha1(ref mut h) => h.update(data),
+            #[cfg(feature = "digest")]
+            Hasher::Sha256(ref mut h) => h.update(data),
+        }
+    }
+
+    fn verify(self) -> bool {
+        let result = match self.hasher {
+            #[cfg(feature = "digest")]
+            Hasher::Md5(h) => h.finalize().to_vec(),
+            Hasher::Sha1(h) => h.finalize().to_vec(),
+            #[cfg(feature = "digest")]
+            Hasher::Sha256(h) => h.finalize().to_vec(),
+        };
+        result == self.expected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::test::{call_service, init_service, TestRequest};
+    use crate::web::App;
+
+    #[crate::rt_test]
+    async fn test_upload() {
+        let store = MemoryUploadStore::new();
+        let srv = init_service(
+            App::new().service(Uploads::new("/files", store.clone()).max_size(1024)),
+        )
+        .await;
+
+        // options
+        let req = TestRequest::with_uri("/files")
+            .method(Method::OPTIONS)
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        assert_eq!(res.headers().get(&TUS_MAX_SIZE).unwrap(), "1024");
+        assert_eq!(res.headers().get(&TUS_RESUMABLE).unwrap(), VERSION);
+
+        // version is required
+        let req = TestRequest::with_uri("/files")
+            .method(Method::POST)
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+        assert_eq!(res.headers().get(&TUS_VERSION).unwrap(), VERSION);
+
+        // create
+        let req = TestRequest::with_uri("/files")
+            .method(Method::POST)
+            .header(TUS_RESUMABLE, VERSION)
+            .header(UPLOAD_LENGTH, "11")
+            .header(UPLOAD_METADATA, "filename d29ybGQudHh0,is_confidential")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let location = res
+            .headers()
+            .get(header::LOCATION)
+            .unwrap()
+            .to_str()
+            .unwrap();
+        let id = location.strip_prefix("/files/").unwrap().to_string();
+        assert_eq!(id.len(), 32);
+
+        let info = store.info(&id).await.unwrap().unwrap();
+        assert_eq!(info.length, Some(11));
+        assert_eq!(info.metadata["filename"], "world.txt");
+        assert_eq!(info.metadata["is_confidential"], "");
+
+        // append
+        let patch = |offset: &str, body: &'static str| {
+            TestRequest::with_uri(&format!("/files/{}", id))
+                .method(Method::PATCH)
+                .header(TUS_RESUMABLE, VERSION)
+                .header(header::CONTENT_TYPE, OFFSET_OCTET_STREAM)
+                .header(UPLOAD_OFFSET, offset)
+                .set_payload(body)
+        };
+        let res = call_service(&srv, patch("0", "hello").to_request()).await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        assert_eq!(res.headers().get(&UPLOAD_OFFSET).unwrap(), "5");
+
+        let res = call_service(&srv, patch("0", "hello").to_request()).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+
+        // offset
+        let req = TestRequest::with_uri(&format!("/files/{}", id))
+            .method(Method::HEAD)
+            .header(TUS_RESUMABLE, VERSION)
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get(&UPLOAD_OFFSET).unwrap(), "5");
+        assert_eq!(res.headers().get(&UPLOAD_LENGTH).unwrap(), "11");
+
+        // checksum
+        let req = patch("5", " world")
+            .header(UPLOAD_CHECKSUM, "sha1 AAAAAAAAAAAAAAAAAAAAAAAAAAA=")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status().as_u16(), 460);
+
+        let req = patch("5", " world")
+            .header(UPLOAD_CHECKSUM, "crc32 AAAAAA==")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let req = patch("5", " world!")
+            .header(UPLOAD_CHECKSUM, "sha1 AAAAAA==")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let sum = base64.encode(sha1::Sha1::digest(b" world"));
+        let req = patch("5", " world")
+            .header(UPLOAD_CHECKSUM, format!("sha1 {}", sum))
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        assert_eq!(res.headers().get(&UPLOAD_OFFSET).unwrap(), "11");
+        assert_eq!(store.data(&id).unwrap(), "hello world");
+
+        // delete
+        let req = TestRequest::with_uri(&format!("/files/{}", id))
+            .method(Method::DELETE)
+            .header(TUS_RESUMABLE, VERSION)
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        assert!(store.data(&id).is_none());
+
+        let res = call_service(&srv, patch("11", "").to_request()).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[crate::rt_test]
+    async fn test_deferred_length() {
+        let store = MemoryUploadStore::new();
+        let srv = init_service(App::new().service(
+            crate::web::scope("/api").service(Uploads::new("/files/", store.clone())),
+        ))
+        .await;
+
+        let req = TestRequest::with_uri("/api/files/")
+            .method(Method::POST)
+            .header(TUS_RESUMABLE, VERSION)
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let req = TestRequest::with_uri("/api/files")
+            .method(Method::POST)
+            .header(TUS_RESUMABLE, VERSION)
+            .header(UPLOAD_DEFER_LENGTH, "1")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let location = res
+            .headers()
+            .get(header::LOCATION)
+            .unwrap()
+            .to_str()
+            .unwrap();
+        let id = location.strip_prefix("/api/files/").unwrap().to_string();
+        assert_eq!(store.info(&id).await.unwrap().unwrap().length, None);
+
+        let req = TestRequest::with_uri(location)
+            .method(Method::PATCH)
+            .header(TUS_RESUMABLE, VERSION)
+            .header(header::CONTENT_TYPE, OFFSET_OCTET_STREAM)
+            .header(UPLOAD_OFFSET, "0")
+            .header(UPLOAD_LENGTH, "4")
+            .set_payload("data")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        let info = store.info(&id).await.unwrap().unwrap();
+        assert!(info.is_complete());
+
+        let req = TestRequest::with_uri(location)
+            .method(Method::GET)
+            .header(TUS_RESUMABLE, VERSION)
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+}
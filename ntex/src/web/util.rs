@@ -34,7 +34,11 @@ use super::{HttpResponse, HttpResponseBuilder};
 ///
 /// By default, each segment matches the regular expression `[^{}/]+`.
 ///
-/// You can also specify a custom regex in the form `{identifier:regex}`:
+/// You can also specify a custom regex in the form `{identifier:regex}`
+/// or one of named constraints `int`, `uint`, `hex`, `alpha`, `alnum`
+/// and `uuid`, for example `/users/{id:uint}`. Resource does not match
+/// if segment does not satisfy constraint, so overlapping resources
+/// could be registered for different types of segments.
 ///
 /// For instance, to route `GET`-requests on any route matching
 /// `/users/{userid}/{friend}` and store `userid` and `friend` in
@@ -78,6 +82,27 @@ pub fn scope<T: IntoPattern, Err: ErrorRenderer>(path: T) -> Scope<Err> {
     Scope::new(path)
 }
 
+/// Configure scope for requests with matching host.
+///
+/// Host labels could be `*` or `{name}` wildcards, values of named labels
+/// are available as path parameters. Application level host scopes are
+/// indexed by host name, so one server could serve many domains without
+/// linear guard checks. See [`Scope::host()`].
+///
+/// ```rust
+/// use ntex::web::{self, types::Path};
+///
+/// let app = web::App::new().service(
+///     web::scope_host("{tenant}.example.com")
+///         .service(web::resource("/").to(|tenant: Path<String>| async move {
+///             format!("tenant: {}", tenant)
+///         }))
+/// );
+/// ```
+pub fn scope_host<Err: ErrorRenderer>(host: &str) -> Scope<Err> {
+    Scope::new("").host(host)
+}
+
 /// Create *route* without configuration.
 pub fn route<Err: ErrorRenderer>() -> Route<Err> {
     Route::new()
@@ -1,15 +1,19 @@
 //! WebSockets protocol support
-use std::{fmt, rc::Rc};
+use std::{cell::Cell, fmt, rc::Rc, time::Duration, time::Instant};
 
+pub use crate::ws::error::ProtocolError;
 pub use crate::ws::{CloseCode, CloseReason, Frame, Message, WsSink};
 
 use crate::http::{body::BodySize, h1, StatusCode};
 use crate::service::{
-    apply_fn, chain_factory, fn_factory_with_config, IntoServiceFactory, ServiceFactory,
+    apply_fn, chain_factory, fn_factory_with_config, IntoServiceFactory, Service,
+    ServiceCtx, ServiceFactory,
 };
+use crate::time::{now, sleep, Seconds};
+use crate::util::{select, Bytes, Either, Ready};
 use crate::web::{HttpRequest, HttpResponse};
 use crate::ws::{self, error::HandshakeError, error::WsError, handshake};
-use crate::{io::DispatchItem, rt, time::Seconds, util::Either, util::Ready};
+use crate::{io::DispatchItem, io::IoBoxed, io::IoError, rt};
 
 /// Do websocket handshake and start websockets service.
 pub async fn start<T, F, Err>(req: HttpRequest, factory: F) -> Result<HttpResponse, Err>
@@ -55,7 +59,7 @@ where
                     Either::Right(Ready::Err(WsError::Protocol(e)))
                 }
                 DispatchItem::Disconnect(e) => {
-                    Either::Right(Ready::Err(WsError::Disconnected(e)))
+                    Either::Right(Ready::Err(WsError::Disconnected(e.map(IoError::from))))
                 }
             }))
         }
@@ -65,6 +69,10 @@ where
 }
 
 /// Do websocket handshake and start websockets service.
+///
+/// If `compress` feature is enabled and application state contains
+/// [`ws::DeflateConfig`], permessage-deflate extension is negotiated
+/// with the client.
 pub async fn start_with<T, F, Err>(
     req: HttpRequest,
     factory: F,
@@ -76,25 +84,7 @@ where
     F: IntoServiceFactory<T, DispatchItem<ws::Codec>, WsSink>,
     Err: From<T::InitError> + From<HandshakeError>,
 {
-    log::trace!("Start ws handshake verification for {:?}", req.path());
-
-    // ws handshake
-    let res = handshake(req.head())?.finish().into_parts().0;
-
-    // extract io
-    let item = req
-        .head()
-        .take_io()
-        .ok_or(HandshakeError::NoWebsocketUpgrade)?;
-    let io = item.0;
-    let codec = item.1;
-
-    io.encode(h1::Message::Item((res, BodySize::Empty)), &codec)
-        .map_err(|_| HandshakeError::NoWebsocketUpgrade)?;
-    log::trace!("Ws handshake verification completed for {:?}", req.path());
-
-    // create sink
-    let codec = ws::Codec::new();
+    let (io, codec) = upgrade(&req)?;
     let sink = WsSink::new(io.get_ref(), codec.clone());
 
     // create ws service
@@ -111,3 +101,249 @@ where
 
     Ok(HttpResponse::new(StatusCode::OK))
 }
+
+/// Websockets connection handler
+///
+/// Handler is driven by [`start_handler()`]. Continuation frames are
+/// aggregated into complete messages and ping frames are answered
+/// automatically.
+#[allow(async_fn_in_trait)]
+pub trait WsHandler: 'static {
+    /// The type of errors produced by handler
+    type Error: fmt::Debug;
+
+    /// Connection is established.
+    async fn on_open(&self, session: &WsSession) -> Result<(), Self::Error> {
+        let _ = session;
+        Ok(())
+    }
+
+    /// Text or binary message is received.
+    async fn on_message(
+        &self,
+        msg: Message,
+        session: &WsSession,
+    ) -> Result<(), Self::Error>;
+
+    /// Connection is closed.
+    ///
+    /// Reason is `None` if connection is terminated without close frame.
+    async fn on_close(&self, reason: Option<CloseReason>, session: &WsSession) {
+        let _ = (reason, session);
+    }
+}
+
+/// Websockets session handle
+#[derive(Clone, Debug)]
+pub struct WsSession(Rc<SessionInner>);
+
+#[derive(Debug)]
+struct SessionInner {
+    sink: WsSink,
+    closed: Cell<bool>,
+    last_pong: Cell<Instant>,
+}
+
+impl WsSession {
+    fn new(sink: WsSink) -> Self {
+        WsSession(Rc::new(SessionInner {
+            sink,
+            closed: Cell::new(false),
+            last_pong: Cell::new(now()),
+        }))
+    }
+
+    /// Websockets sink
+    pub fn sink(&self) -> &WsSink {
+        &self.0.sink
+    }
+
+    /// Encode and send message to the peer.
+    pub async fn send(&self, msg: Message) -> Result<(), ProtocolError> {
+        self.0.sink.send(msg).await
+    }
+
+    /// Send close frame and close connection.
+    pub async fn close(&self, reason: Option<CloseReason>) -> Result<(), ProtocolError> {
+        self.0.sink.send(Message::Close(reason)).await?;
+        self.0.sink.io().close();
+        Ok(())
+    }
+
+    /// Send ping frames at specified interval.
+    ///
+    /// Connection is closed if peer does not respond with pong frame
+    /// within `timeout`. Heartbeat stops when connection get disconnected,
+    /// it should be started only once per session.
+    pub fn heartbeat(&self, interval: Seconds, timeout: Seconds) {
+        let session = self.clone();
+        session.0.last_pong.set(now());
+
+        rt::spawn(async move {
+            let hb = async {
+                loop {
+                    sleep(interval).await;
+                    if now() - session.0.last_pong.get() > Duration::from(timeout) {
+                        log::trace!("Ws peer did not respond to pings, closing connection");
+                        session.0.sink.io().close();
+                        return;
+                    }
+                    if session.send(Message::Ping(Bytes::new())).await.is_err() {
+                        return;
+                    }
+                }
+            };
+            let _ = select(session.0.sink.on_disconnect(), hb).await;
+        });
+    }
+}
+
+/// Do websocket handshake and start websockets handler.
+///
+/// ```rust
+/// use ntex::web::{self, ws, HttpRequest, HttpResponse};
+///
+/// struct Echo;
+///
+/// impl ws::WsHandler for Echo {
+///     type Error = ws::ProtocolError;
+///
+///     async fn on_message(
+///         &self,
+///         msg: ws::Message,
+///         session: &ws::WsSession,
+///     ) -> Result<(), Self::Error> {
+///         session.send(msg).await
+///     }
+/// }
+///
+/// async fn index(req: HttpRequest) -> Result<HttpResponse, web::Error> {
+///     ws::start_handler(req, Echo).await
+/// }
+/// ```
+pub async fn start_handler<H, Err>(
+    req: HttpRequest,
+    handler: H,
+) -> Result<HttpResponse, Err>
+where
+    H: WsHandler,
+    Err: From<H::Error> + From<HandshakeError>,
+{
+    let (io, codec) = upgrade(&req)?;
+    let session = WsSession::new(WsSink::new(io.get_ref(), codec.clone()));
+    handler.on_open(&session).await?;
+
+    let handler = Rc::new(handler);
+    let srv = HandlerService {
+        handler: handler.clone(),
+        session: session.clone(),
+    };
+    let cfg = crate::io::DispatcherConfig::default();
+    cfg.set_keepalive_timeout(Seconds::ZERO);
+
+    // start websockets handler dispatcher
+    rt::spawn(async move {
+        let codec = ws::MessageCodec::new(codec);
+        let res = crate::io::Dispatcher::new(io, codec, srv, &cfg).await;
+        log::trace!("Ws handler is terminated: {:?}", res);
+
+        if !session.0.closed.get() {
+            session.0.closed.set(true);
+            handler.on_close(None, &session).await;
+        }
+    });
+
+    Ok(HttpResponse::new(StatusCode::OK))
+}
+
+struct HandlerService<H> {
+    handler: Rc<H>,
+    session: WsSession,
+}
+
+impl<H: WsHandler> Service<DispatchItem<ws::MessageCodec, IoError>> for HandlerService<H> {
+    type Response = Option<Message>;
+    type Error = WsError<H::Error>;
+
+    async fn call(
+        &self,
+        item: DispatchItem<ws::MessageCodec, IoError>,
+        _: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        match item {
+            DispatchItem::Item(msg) => match msg {
+                Message::Text(_) | Message::Binary(_) => {
+                    self.handler
+                        .on_message(msg, &self.session)
+                        .await
+                        .map_err(WsError::Service)?;
+                    Ok(None)
+                }
+                Message::Ping(data) => Ok(Some(Message::Pong(data))),
+                Message::Pong(_) => {
+                    self.session.0.last_pong.set(now());
+                    Ok(None)
+                }
+                Message::Close(reason) => {
+                    if !self.session.0.closed.get() {
+                        self.session.0.closed.set(true);
+                        self.handler.on_close(reason.clone(), &self.session).await;
+                    }
+                    let io = self.session.0.sink.io().clone();
+                    rt::spawn(async move { io.close() });
+                    Ok(Some(Message::Close(reason)))
+                }
+                Message::Continuation(_) => Ok(None),
+            },
+            DispatchItem::WBackPressureEnabled | DispatchItem::WBackPressureDisabled => {
+                Ok(None)
+            }
+            DispatchItem::KeepAliveTimeout => Err(WsError::KeepAlive),
+            DispatchItem::ReadTimeout => Err(WsError::ReadTimeout),
+            DispatchItem::DecoderError(e) | DispatchItem::EncoderError(e) => {
+                Err(WsError::Protocol(e))
+            }
+            DispatchItem::Disconnect(e) => Err(WsError::Disconnected(e)),
+        }
+    }
+}
+
+/// Do websocket handshake and send handshake response.
+fn upgrade(req: &HttpRequest) -> Result<(IoBoxed, ws::Codec), HandshakeError> {
+    log::trace!("Start ws handshake verification for {:?}", req.path());
+
+    // ws handshake
+    let mut res = handshake(req.head())?;
+
+    // negotiate permessage-deflate extension
+    #[cfg(feature = "compress")]
+    let deflate = req
+        .app_state::<ws::DeflateConfig>()
+        .and_then(|cfg| cfg.negotiate(req.headers()));
+    #[cfg(feature = "compress")]
+    if let Some((_, ref hdr)) = deflate {
+        res.header(crate::http::header::SEC_WEBSOCKET_EXTENSIONS, hdr.clone());
+    }
+    let res = res.finish().into_parts().0;
+
+    // extract io
+    let (io, codec) = req
+        .head()
+        .take_io()
+        .ok_or(HandshakeError::NoWebsocketUpgrade)?;
+
+    io.encode(h1::Message::Item((res, BodySize::Empty)), &codec)
+        .map_err(|_| HandshakeError::NoWebsocketUpgrade)?;
+    log::trace!("Ws handshake verification completed for {:?}", req.path());
+
+    // create ws codec
+    let codec = ws::Codec::new();
+    #[cfg(feature = "compress")]
+    let codec = if let Some((cfg, _)) = deflate {
+        codec.deflate(cfg)
+    } else {
+        codec
+    };
+
+    Ok((io, codec))
+}
@@ -1,6 +1,8 @@
 //! Websockets client
 use std::{cell::RefCell, fmt, marker, net, rc::Rc, str};
 
+#[cfg(feature = "native-tls")]
+use crate::connect::native_tls;
 #[cfg(feature = "openssl")]
 use crate::connect::openssl;
 #[cfg(feature = "rustls")]
@@ -16,12 +18,14 @@ use crate::http::header::{self, HeaderMap, HeaderName, HeaderValue, AUTHORIZATIO
 use crate::http::{body::BodySize, client, client::ClientResponse, error::HttpError, h1};
 use crate::http::{ConnectionType, RequestHead, RequestHeadType, StatusCode, Uri};
 use crate::io::{
-    Base, DispatchItem, Dispatcher, DispatcherConfig, Filter, Io, Layer, Sealed,
+    Base, DispatchItem, Dispatcher, DispatcherConfig, Filter, Io, IoError, Layer, Sealed,
 };
 use crate::service::{apply_fn, into_service, IntoService, Pipeline, Service};
 use crate::time::{timeout, Millis, Seconds};
 use crate::{channel::mpsc, rt, util::Ready, ws};
 
+#[cfg(feature = "compress")]
+use super::deflate::DeflateConfig;
 use super::error::{WsClientBuilderError, WsClientError, WsError};
 use super::transport::WsTransport;
 
@@ -36,6 +40,8 @@ pub struct WsClient<F, T> {
     extra_headers: RefCell<Option<HeaderMap>>,
     config: DispatcherConfig,
     client_cfg: Rc<client::ClientConfig>,
+    #[cfg(feature = "compress")]
+    deflate: Option<DeflateConfig>,
     _t: marker::PhantomData<F>,
 }
 
@@ -47,6 +53,8 @@ pub struct WsClientBuilder<F, T> {
     origin: Option<HeaderValue>,
     #[cfg(feature = "cookie")]
     cookies: Option<CookieJar>,
+    #[cfg(feature = "compress")]
+    deflate: Option<DeflateConfig>,
 }
 
 struct Inner<F, T> {
@@ -153,6 +161,19 @@ where
             HeaderValue::try_from(key.as_str()).unwrap(),
         );
 
+        // protocols that are actually sent, extra headers override request headers
+        let protocols: Vec<String> =
+            if headers.contains_key(header::SEC_WEBSOCKET_PROTOCOL) {
+                &headers
+            } else {
+                &head.headers
+            }
+            .get_all(header::SEC_WEBSOCKET_PROTOCOL)
+            .filter_map(|hdr| hdr.to_str().ok())
+            .flat_map(|hdr| hdr.split(','))
+            .map(|proto| proto.trim().to_string())
+            .collect();
+
         let msg = Connect::new(head.uri.clone()).set_addr(self.addr);
         log::trace!("Open ws connection to {:?} addr: {:?}", head.uri, self.addr);
 
@@ -241,17 +262,48 @@ where
             log::trace!("{}: Missing SEC-WEBSOCKET-ACCEPT header", tag);
             return Err(WsClientError::MissingWebSocketAcceptHeader);
         };
+
+        // selected protocol must be one of requested protocols
+        if let Some(hdr) = response.headers.get(&header::SEC_WEBSOCKET_PROTOCOL) {
+            let requested = match hdr.to_str() {
+                Ok(proto) => protocols.iter().any(|p| p == proto),
+                Err(_) => false,
+            };
+            if !requested {
+                log::trace!("{}: Invalid protocol header: {:?}", tag, hdr);
+                return Err(WsClientError::InvalidProtocolHeader(hdr.clone()));
+            }
+        }
         log::trace!("{}: Ws handshake response verification is completed", tag);
 
+        let codec = if server_mode {
+            ws::Codec::new().max_size(max_size)
+        } else {
+            ws::Codec::new().max_size(max_size).client_mode()
+        };
+
+        // negotiate permessage-deflate extension
+        #[cfg(feature = "compress")]
+        let codec = if let Some(ref cfg) = self.deflate {
+            if let Some(hdr) = response.headers.get(&header::SEC_WEBSOCKET_EXTENSIONS) {
+                if let Some(cfg) = cfg.accept_response(&response.headers) {
+                    codec.deflate(cfg)
+                } else {
+                    log::trace!("{}: Invalid extensions header: {:?}", tag, hdr);
+                    return Err(WsClientError::InvalidExtensionsHeader(hdr.clone()));
+                }
+            } else {
+                codec
+            }
+        } else {
+            codec
+        };
+
         // response and ws io
         Ok(WsConnection::new(
             io,
             ClientResponse::with_empty_payload(response, self.client_cfg.clone()),
-            if server_mode {
-                ws::Codec::new().max_size(max_size)
-            } else {
-                ws::Codec::new().max_size(max_size).client_mode()
-            },
+            codec,
             self.config.clone(),
         ))
     }
@@ -306,6 +358,8 @@ impl WsClientBuilder<Base, ()> {
             }),
             #[cfg(feature = "cookie")]
             cookies: None,
+            #[cfg(feature = "compress")]
+            deflate: None,
         }
     }
 }
@@ -326,6 +380,10 @@ where
     }
 
     /// Set supported websocket protocols
+    ///
+    /// Protocol selected by the server is validated against requested
+    /// protocols, including protocols set with `Sec-WebSocket-Protocol`
+    /// header, and is available via [`WsConnection::protocol()`].
     pub fn protocols<U, V>(&mut self, protos: U) -> &mut Self
     where
         U: IntoIterator<Item = V>,
@@ -502,6 +560,15 @@ where
         self
     }
 
+    #[cfg(feature = "compress")]
+    /// Offer permessage-deflate extension.
+    ///
+    /// Messages get compressed if server accepts the offer.
+    pub fn deflate(&mut self, cfg: DeflateConfig) -> &mut Self {
+        self.deflate = Some(cfg);
+        self
+    }
+
     /// Use custom connector
     pub fn connector<F1, T1>(&mut self, connector: T1) -> WsClientBuilder<F1, T1>
     where
@@ -526,6 +593,8 @@ where
             origin: self.origin.take(),
             #[cfg(feature = "cookie")]
             cookies: self.cookies.take(),
+            #[cfg(feature = "compress")]
+            deflate: self.deflate.take(),
         }
     }
 
@@ -547,6 +616,15 @@ where
         self.connector(rustls::Connector::from(config))
     }
 
+    #[cfg(feature = "native-tls")]
+    /// Use native-tls connector.
+    pub fn native_tls(
+        &mut self,
+        connector: tls_native::TlsConnector,
+    ) -> WsClientBuilder<Layer<native_tls::TlsFilter>, native_tls::TlsConnector<Uri>> {
+        self.connector(native_tls::TlsConnector::new(connector))
+    }
+
     /// This method construct new `WsClientBuilder`
     pub fn take(&mut self) -> WsClientBuilder<F, T> {
         WsClientBuilder {
@@ -556,6 +634,8 @@ where
             protocols: self.protocols.take(),
             #[cfg(feature = "cookie")]
             cookies: self.cookies.take(),
+            #[cfg(feature = "compress")]
+            deflate: self.deflate.take(),
         }
     }
 
@@ -635,6 +715,14 @@ where
             );
         }
 
+        #[cfg(feature = "compress")]
+        if let Some(ref cfg) = self.deflate {
+            inner
+                .head
+                .headers
+                .insert(header::SEC_WEBSOCKET_EXTENSIONS, cfg.offer());
+        }
+
         Ok(WsClient {
             connector: inner.connector.into(),
             head: Rc::new(inner.head),
@@ -645,6 +733,8 @@ where
             config: inner.config,
             extra_headers: RefCell::new(None),
             client_cfg: Default::default(),
+            #[cfg(feature = "compress")]
+            deflate: self.deflate.take(),
             _t: marker::PhantomData,
         })
     }
@@ -711,6 +801,14 @@ impl<F> WsConnection<F> {
     pub fn response(&self) -> &ClientResponse {
         &self.res
     }
+
+    /// Get subprotocol selected by the server
+    pub fn protocol(&self) -> Option<&str> {
+        self.res
+            .headers()
+            .get(&header::SEC_WEBSOCKET_PROTOCOL)
+            .and_then(|hdr| hdr.to_str().ok())
+    }
 }
 
 impl<F> WsConnection<F> {
@@ -764,7 +862,7 @@ impl WsConnection<Sealed> {
             service.into_service().map_err(WsError::Service),
             |req, svc| async move {
                 match req {
-                    DispatchItem::<ws::Codec>::Item(item) => svc.call(item).await,
+                    DispatchItem::<ws::Codec, IoError>::Item(item) => svc.call(item).await,
                     DispatchItem::WBackPressureEnabled
                     | DispatchItem::WBackPressureDisabled => Ok(None),
                     DispatchItem::KeepAliveTimeout => Err(WsError::KeepAlive),
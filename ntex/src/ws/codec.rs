@@ -1,8 +1,12 @@
 use std::cell::Cell;
+#[cfg(feature = "compress")]
+use std::rc::Rc;
 
 use crate::codec::{Decoder, Encoder};
 use crate::util::{ByteString, Bytes, BytesMut};
 
+#[cfg(feature = "compress")]
+use super::deflate::{DeflateConfig, DeflateContext};
 use super::error::ProtocolError;
 use super::frame::Parser;
 use super::proto::{CloseReason, OpCode};
@@ -55,6 +59,8 @@ pub enum Item {
 pub struct Codec {
     flags: Cell<Flags>,
     max_size: usize,
+    #[cfg(feature = "compress")]
+    deflate: Option<Rc<DeflateContext>>,
 }
 
 bitflags::bitflags! {
@@ -64,6 +70,7 @@ bitflags::bitflags! {
         const R_CONTINUATION = 0b0000_0010;
         const W_CONTINUATION = 0b0000_0100;
         const CLOSED         = 0b0000_1000;
+        const R_COMPRESSED   = 0b0001_0000;
     }
 }
 
@@ -73,6 +80,8 @@ impl Codec {
         Codec {
             max_size: 65_536,
             flags: Cell::new(Flags::SERVER),
+            #[cfg(feature = "compress")]
+            deflate: None,
         }
     }
 
@@ -92,6 +101,17 @@ impl Codec {
         self
     }
 
+    #[cfg(feature = "compress")]
+    /// Enable permessage-deflate extension.
+    ///
+    /// Configuration must be negotiated with the peer, see
+    /// [`DeflateConfig::negotiate()`]. Text and binary messages get
+    /// compressed, continuation messages are sent uncompressed.
+    pub fn deflate(mut self, cfg: DeflateConfig) -> Self {
+        self.deflate = Some(Rc::new(DeflateContext::new(cfg)));
+        self
+    }
+
     /// Check if codec encoded `Close` message
     pub fn is_closed(&self) -> bool {
         self.flags.get().contains(Flags::CLOSED)
@@ -108,6 +128,72 @@ impl Codec {
         flags.remove(f);
         self.flags.set(flags);
     }
+
+    fn write_data(
+        &self,
+        dst: &mut BytesMut,
+        data: &[u8],
+        op: OpCode,
+    ) -> Result<(), ProtocolError> {
+        let mask = !self.flags.get().contains(Flags::SERVER);
+
+        #[cfg(feature = "compress")]
+        if let Some(ref deflate) = self.deflate {
+            let data = deflate.compress(data, !mask)?;
+            Parser::write_frame(dst, data, op, true, true, mask);
+            return Ok(());
+        }
+
+        Parser::write_message(dst, data, op, true, mask);
+        Ok(())
+    }
+
+    #[cfg(feature = "compress")]
+    /// Decompress payload of compressed message
+    fn inflate(
+        &self,
+        finished: bool,
+        rsv1: bool,
+        opcode: OpCode,
+        payload: Option<Bytes>,
+    ) -> Result<Option<Bytes>, ProtocolError> {
+        let deflate = if let Some(ref deflate) = self.deflate {
+            deflate
+        } else {
+            return Ok(payload);
+        };
+
+        let compressed = match opcode {
+            OpCode::Text | OpCode::Binary => {
+                if rsv1 && !finished {
+                    self.insert_flags(Flags::R_COMPRESSED);
+                }
+                rsv1
+            }
+            OpCode::Continue if !rsv1 => {
+                let compressed = self.flags.get().contains(Flags::R_COMPRESSED);
+                if finished {
+                    self.remove_flags(Flags::R_COMPRESSED);
+                }
+                compressed
+            }
+            _ if rsv1 => return Err(ProtocolError::UnexpectedRsv),
+            _ => false,
+        };
+
+        if compressed {
+            let data = payload.unwrap_or_default();
+            let server = self.flags.get().contains(Flags::SERVER);
+            Ok(Some(deflate.decompress(
+                &data,
+                finished,
+                server,
+                self.max_size,
+            )?))
+        } else {
+            Ok(payload)
+        }
+    }
 }
 
 impl Default for Codec {
@@ -122,20 +208,8 @@ impl Encoder for Codec {
 
     fn encode(&self, item: Message, dst: &mut BytesMut) -> Result<(), Self::Error> {
         match item {
-            Message::Text(txt) => Parser::write_message(
-                dst,
-                txt.as_ref(),
-                OpCode::Text,
-                true,
-                !self.flags.get().contains(Flags::SERVER),
-            ),
-            Message::Binary(bin) => Parser::write_message(
-                dst,
-                bin,
-                OpCode::Binary,
-                true,
-                !self.flags.get().contains(Flags::SERVER),
-            ),
+            Message::Text(txt) => self.write_data(dst, txt.as_bytes(), OpCode::Text)?,
+            Message::Binary(bin) => self.write_data(dst, &bin, OpCode::Binary)?,
             Message::Ping(txt) => Parser::write_message(
                 dst,
                 txt,
@@ -221,8 +295,15 @@ impl Decoder for Codec {
     type Error = ProtocolError;
 
     fn decode(&self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        match Parser::parse(src, self.flags.get().contains(Flags::SERVER), self.max_size) {
-            Ok(Some((finished, opcode, payload))) => {
+        match Parser::parse_frame(
+            src,
+            self.flags.get().contains(Flags::SERVER),
+            self.max_size,
+        ) {
+            Ok(Some((finished, _rsv1, opcode, payload))) => {
+                #[cfg(feature = "compress")]
+                let payload = self.inflate(finished, _rsv1, opcode, payload)?;
+
                 // handle continuation
                 if !finished {
                     match opcode {
@@ -0,0 +1,596 @@
+//! Permessage-deflate extension (RFC 7692)
+use std::{cell::Cell, cell::RefCell, cmp, fmt};
+
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+
+use crate::http::header::{self, HeaderMap, HeaderValue};
+use crate::util::Bytes;
+
+use super::error::ProtocolError;
+
+const EXTENSION: &str = "permessage-deflate";
+const TRAILER: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+const MAX_WINDOW_BITS: u8 = 15;
+const MIN_WINDOW_BITS: u8 = 9;
+
+/// Permessage-deflate extension configuration
+///
+/// Window sizes of both compressors are negotiated with the peer,
+/// negotiated configuration is used for connection's compression context.
+///
+/// Server uses configuration to negotiate extension with
+/// [`DeflateConfig::negotiate()`], client uses it with
+/// [`WsClientBuilder::deflate()`](super::WsClientBuilder::deflate).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeflateConfig {
+    server_no_context_takeover: bool,
+    client_no_context_takeover: bool,
+    max_window_bits: u8,
+    server_window_bits: u8,
+    client_window_bits: u8,
+    level: u32,
+    max_size: usize,
+}
+
+impl Default for DeflateConfig {
+    fn default() -> Self {
+        DeflateConfig {
+            server_no_context_takeover: false,
+            client_no_context_takeover: false,
+            max_window_bits: MAX_WINDOW_BITS,
+            server_window_bits: MAX_WINDOW_BITS,
+            client_window_bits: MAX_WINDOW_BITS,
+            level: Compression::default().level(),
+            max_size: 0,
+        }
+    }
+}
+
+impl DeflateConfig {
+    /// Create default configuration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reset server compression context after each message.
+    pub fn server_no_context_takeover(mut self, val: bool) -> Self {
+        self.server_no_context_takeover = val;
+        self
+    }
+
+    /// Reset client compression context after each message.
+    pub fn client_no_context_takeover(mut self, val: bool) -> Self {
+        self.client_no_context_takeover = val;
+        self
+    }
+
+    /// Max window bits for peer's compressor, from 9 to 15.
+    ///
+    /// Smaller window reduces memory usage of decompressor.
+    /// Server requests it only if client offer supports it.
+    /// Window of our compressor is requested by the peer.
+    /// By default is 15.
+    pub fn max_window_bits(mut self, bits: u8) -> Self {
+        self.max_window_bits = bits.clamp(MIN_WINDOW_BITS, MAX_WINDOW_BITS);
+        self
+    }
+
+    /// Set compression level, from 0 to 9.
+    pub fn level(mut self, level: u32) -> Self {
+        self.level = cmp::min(level, 9);
+        self
+    }
+
+    /// Max size of decompressed message.
+    ///
+    /// By default codec's max frame size is used.
+    pub fn max_size(mut self, size: usize) -> Self {
+        self.max_size = size;
+        self
+    }
+
+    /// Client extension offer for `Sec-WebSocket-Extensions` header
+    pub fn offer(&self) -> HeaderValue {
+        let mut offer = EXTENSION.to_string();
+        if self.server_no_context_takeover {
+            offer.push_str("; server_no_context_takeover");
+        }
+        if self.client_no_context_takeover {
+            offer.push_str("; client_no_context_takeover");
+        }
+        if self.max_window_bits < MAX_WINDOW_BITS {
+            offer.push_str(&format!(
+                "; server_max_window_bits={}",
+                self.max_window_bits
+            ));
+        }
+        offer.push_str("; client_max_window_bits");
+        HeaderValue::try_from(offer).unwrap()
+    }
+
+    /// Negotiate extension with client offers from request headers.
+    ///
+    /// Returns negotiated configuration and value for response's
+    /// `Sec-WebSocket-Extensions` header, or `None` if no offer is acceptable.
+    pub fn negotiate(&self, headers: &HeaderMap) -> Option<(DeflateConfig, HeaderValue)> {
+        extensions(headers)
+            .flatten()
+            .find_map(|params| self.accept_offer(&params))
+    }
+
+    fn accept_offer(&self, params: &[Param<'_>]) -> Option<(DeflateConfig, HeaderValue)> {
+        let mut cfg = self.clone();
+        let mut server_bits = None;
+        let mut client_bits = None;
+
+        for param in params {
+            match (param.name, param.value) {
+                ("server_no_context_takeover", None) => {
+                    cfg.server_no_context_takeover = true
+                }
+                ("client_no_context_takeover", None) => {
+                    cfg.client_no_context_takeover = true
+                }
+                ("server_max_window_bits", Some(val)) => {
+                    server_bits = Some(window_bits(val)?);
+                }
+                ("client_max_window_bits", None) => client_bits = Some(MAX_WINDOW_BITS),
+                ("client_max_window_bits", Some(val)) => {
+                    client_bits = Some(window_bits(val)?);
+                }
+                _ => return None,
+            }
+        }
+
+        let mut res = EXTENSION.to_string();
+        if cfg.server_no_context_takeover {
+            res.push_str("; server_no_context_takeover");
+        }
+        if cfg.client_no_context_takeover {
+            res.push_str("; client_no_context_takeover");
+        }
+        if let Some(bits) = server_bits {
+            cfg.server_window_bits = bits;
+            res.push_str(&format!("; server_max_window_bits={}", bits));
+        }
+        // client announced support, limit its window
+        if let Some(bits) = client_bits {
+            let bits = cmp::min(bits, self.max_window_bits);
+            if bits < MAX_WINDOW_BITS {
+                cfg.client_window_bits = bits;
+                res.push_str(&format!("; client_max_window_bits={}", bits));
+            }
+        }
+        Some((cfg, HeaderValue::try_from(res).unwrap()))
+    }
+
+    /// Verify server response for client offer.
+    ///
+    /// Returns `None` if response is not acceptable for the offer.
+    pub fn accept_response(&self, headers: &HeaderMap) -> Option<DeflateConfig> {
+        let mut offers = extensions(headers);
+        let params = offers.next()??;
+        if offers.next().is_some() {
+            return None;
+        }
+
+        let mut cfg = self.clone();
+        let mut server_nct = false;
+        let mut server_bits = false;
+        for param in &params {
+            match (param.name, param.value) {
+                ("server_no_context_takeover", None) => server_nct = true,
+                ("client_no_context_takeover", None) => {
+                    cfg.client_no_context_takeover = true
+                }
+                ("server_max_window_bits", Some(val)) => {
+                    let bits = window_bits(val)?;
+                    if bits > self.max_window_bits {
+                        return None;
+                    }
+                    cfg.server_window_bits = bits;
+                    server_bits = true;
+                }
+                ("client_max_window_bits", Some(val)) => {
+                    cfg.client_window_bits = window_bits(val)?;
+                }
+                _ => return None,
+            }
+        }
+
+        // server must accept requested parameters
+        if (self.server_no_context_takeover && !server_nct)
+            || (self.max_window_bits < MAX_WINDOW_BITS && !server_bits)
+        {
+            return None;
+        }
+        cfg.server_no_context_takeover = server_nct;
+        Some(cfg)
+    }
+}
+
+struct Param<'a> {
+    name: &'a str,
+    value: Option<&'a str>,
+}
+
+/// Iterate over permessage-deflate offers, `None` item is malformed offer
+fn extensions(headers: &HeaderMap) -> impl Iterator<Item = Option<Vec<Param<'_>>>> {
+    headers
+        .get_all(header::SEC_WEBSOCKET_EXTENSIONS)
+        .filter_map(|val| val.to_str().ok())
+        .flat_map(|val| val.split(','))
+        .filter_map(|ext| {
+            let mut parts = ext.split(';').map(|p| p.trim());
+            if parts.next()? != EXTENSION {
+                return None;
+            }
+            Some(
+                parts
+                    .map(|p| {
+                        if let Some((name, value)) = p.split_once('=') {
+                            let value = value.trim().trim_matches('"');
+                            (!value.is_empty()).then_some(Param {
+                                name: name.trim(),
+                                value: Some(value),
+                            })
+                        } else {
+                            Some(Param {
+                                name: p,
+                                value: None,
+                            })
+                        }
+                    })
+                    .collect(),
+            )
+        })
+}
+
+fn window_bits(val: &str) -> Option<u8> {
+    val.parse()
+        .ok()
+        .filter(|bits| (MIN_WINDOW_BITS..=MAX_WINDOW_BITS).contains(bits))
+}
+
+/// Compression context of the connection
+///
+/// Compressor and decompressor are created on first use, window size
+/// depends on connection side.
+pub(super) struct DeflateContext {
+    cfg: DeflateConfig,
+    compress: RefCell<Option<Compress>>,
+    decompress: RefCell<Option<Decompress>>,
+    size: Cell<usize>,
+}
+
+impl DeflateContext {
+    pub(super) fn new(cfg: DeflateConfig) -> Self {
+        DeflateContext {
+            compress: RefCell::new(None),
+            decompress: RefCell::new(None),
+            size: Cell::new(0),
+            cfg,
+        }
+    }
+
+    fn window_bits(&self, server: bool) -> u8 {
+        if server {
+            self.cfg.server_window_bits
+        } else {
+            self.cfg.client_window_bits
+        }
+    }
+
+    /// Compress message payload
+    pub(super) fn compress(
+        &self,
+        data: &[u8],
+        server: bool,
+    ) -> Result<Bytes, ProtocolError> {
+        let mut c = self.compress.borrow_mut();
+        let c = c.get_or_insert_with(|| {
+            Compress::new_with_window_bits(
+                Compression::new(self.cfg.level),
+                false,
+                self.window_bits(server),
+            )
+        });
+        let mut out = Vec::with_capacity(data.len() / 2 + 64);
+        let start = c.total_in();
+
+        loop {
+            let consumed = (c.total_in() - start) as usize;
+            if out.len() == out.capacity() {
+                out.reserve(cmp::max(out.capacity(), 64));
+            }
+            c.compress_vec(&data[consumed..], &mut out, FlushCompress::Sync)
+                .map_err(|e| ProtocolError::Compression(e.to_string()))?;
+
+            let consumed = (c.total_in() - start) as usize;
+            if consumed == data.len() && out.len() < out.capacity() {
+                break;
+            }
+        }
+        if out.ends_with(&TRAILER) {
+            out.truncate(out.len() - TRAILER.len());
+        }
+
+        let no_takeover = if server {
+            self.cfg.server_no_context_takeover
+        } else {
+            self.cfg.client_no_context_takeover
+        };
+        if no_takeover {
+            c.reset();
+        }
+        Ok(Bytes::from(out))
+    }
+
+    /// Decompress message fragment
+    pub(super) fn decompress(
+        &self,
+        data: &[u8],
+        fin: bool,
+        server: bool,
+        max_size: usize,
+    ) -> Result<Bytes, ProtocolError> {
+        let limit = if self.cfg.max_size != 0 {
+            self.cfg.max_size
+        } else {
+            max_size
+        };
+        let mut d = self.decompress.borrow_mut();
+        let d = d.get_or_insert_with(|| {
+            Decompress::new_with_window_bits(false, self.window_bits(!server))
+        });
+        let mut out = Vec::new();
+
+        let result = inflate(d, data, &mut out, limit - self.size.get()).and_then(|_| {
+            if fin {
+                inflate(d, &TRAILER, &mut out, limit - self.size.get())
+            } else {
+                Ok(())
+            }
+        });
+        if let Err(e) = result {
+            self.size.set(0);
+            return Err(e);
+        }
+
+        if fin {
+            self.size.set(0);
+            let no_takeover = if server {
+                self.cfg.client_no_context_takeover
+            } else {
+                self.cfg.server_no_context_takeover
+            };
+            if no_takeover {
+                d.reset(false);
+            }
+        } else {
+            self.size.set(self.size.get() + out.len());
+        }
+        Ok(Bytes::from(out))
+    }
+}
+
+fn inflate(
+    d: &mut Decompress,
+    data: &[u8],
+    out: &mut Vec<u8>,
+    limit: usize,
+) -> Result<(), ProtocolError> {
+    let start = d.total_in();
+
+    loop {
+        if out.len() == out.capacity() {
+            if out.len() > limit {
+                return Err(ProtocolError::Overflow);
+            }
+            out.reserve(cmp::min(
+                cmp::max(data.len() * 2, 1024),
+                limit + 1 - out.len(),
+            ));
+        }
+        let before = d.total_out();
+        let status = d
+            .decompress_vec(
+                &data[(d.total_in() - start) as usize..],
+                out,
+                FlushDecompress::Sync,
+            )
+            .map_err(|e| ProtocolError::Compression(e.to_string()))?;
+
+        let consumed = (d.total_in() - start) as usize;
+        if out.len() > limit {
+            return Err(ProtocolError::Overflow);
+        }
+        if status == Status::StreamEnd
+            || (consumed == data.len() && out.len() < out.capacity())
+        {
+            return Ok(());
+        }
+        if status == Status::BufError
+            && d.total_out() == before
+            && out.len() < out.capacity()
+        {
+            return Err(ProtocolError::Compression("Corrupted stream".into()));
+        }
+    }
+}
+
+impl fmt::Debug for DeflateContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeflateContext")
+            .field("cfg", &self.cfg)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(val: &'static str) -> HeaderMap {
+        let mut hdrs = HeaderMap::new();
+        hdrs.insert(
+            header::SEC_WEBSOCKET_EXTENSIONS,
+            HeaderValue::from_static(val),
+        );
+        hdrs
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let cfg = DeflateConfig::new();
+        let (neg, res) = cfg
+            .negotiate(&headers("permessage-deflate; client_max_window_bits"))
+            .unwrap();
+        assert_eq!(res, "permessage-deflate");
+        assert_eq!(neg, cfg);
+
+        // invalid first offer, second offer is accepted
+        let (neg, res) = cfg
+            .negotiate(&headers(
+                "permessage-deflate; server_max_window_bits=8, \
+                 permessage-deflate; server_no_context_takeover; server_max_window_bits=10",
+            ))
+            .unwrap();
+        assert_eq!(
+            res,
+            "permessage-deflate; server_no_context_takeover; server_max_window_bits=10"
+        );
+        assert!(neg.server_no_context_takeover);
+        assert_eq!(neg.server_window_bits, 10);
+        assert_eq!(neg.client_window_bits, 15);
+
+        let (neg, res) = cfg
+            .clone()
+            .max_window_bits(10)
+            .negotiate(&headers(
+                "permessage-deflate; client_max_window_bits; server_max_window_bits=15",
+            ))
+            .unwrap();
+        assert_eq!(
+            res,
+            "permessage-deflate; server_max_window_bits=15; client_max_window_bits=10"
+        );
+        assert_eq!(neg.client_window_bits, 10);
+
+        // client window hint
+        let (neg, res) = cfg
+            .negotiate(&headers("permessage-deflate; client_max_window_bits=12"))
+            .unwrap();
+        assert_eq!(res, "permessage-deflate; client_max_window_bits=12");
+        assert_eq!(neg.client_window_bits, 12);
+
+        // client does not support client window limit
+        let (neg, res) = cfg
+            .clone()
+            .max_window_bits(10)
+            .negotiate(&headers("permessage-deflate"))
+            .unwrap();
+        assert_eq!(res, "permessage-deflate");
+        assert_eq!(neg.client_window_bits, 15);
+
+        assert!(cfg
+            .negotiate(&headers("permessage-deflate; unknown"))
+            .is_none());
+        assert!(cfg
+            .negotiate(&headers("permessage-deflate; client_max_window_bits=20"))
+            .is_none());
+        assert!(cfg.negotiate(&headers("x-webkit-deflate-frame")).is_none());
+        assert!(cfg.negotiate(&HeaderMap::new()).is_none());
+    }
+
+    #[test]
+    fn test_accept_response() {
+        let cfg = DeflateConfig::new().server_no_context_takeover(true);
+        assert_eq!(
+            cfg.offer(),
+            "permessage-deflate; server_no_context_takeover; client_max_window_bits"
+        );
+        assert_eq!(
+            DeflateConfig::new().max_window_bits(10).offer(),
+            "permessage-deflate; server_max_window_bits=10; client_max_window_bits"
+        );
+
+        let neg = cfg
+            .accept_response(&headers(
+                "permessage-deflate; server_no_context_takeover; client_no_context_takeover",
+            ))
+            .unwrap();
+        assert!(neg.client_no_context_takeover);
+
+        assert!(cfg
+            .accept_response(&headers("permessage-deflate"))
+            .is_none());
+        let neg = cfg
+            .accept_response(&headers(
+                "permessage-deflate; server_no_context_takeover; client_max_window_bits=10",
+            ))
+            .unwrap();
+        assert_eq!(neg.client_window_bits, 10);
+        assert!(cfg
+            .accept_response(&headers(
+                "permessage-deflate; server_no_context_takeover; client_max_window_bits"
+            ))
+            .is_none());
+        assert!(DeflateConfig::new()
+            .max_window_bits(10)
+            .accept_response(&headers("permessage-deflate; server_max_window_bits=12"))
+            .is_none());
+        assert!(DeflateConfig::new()
+            .accept_response(&headers("permessage-deflate, permessage-deflate"))
+            .is_none());
+    }
+
+    #[test]
+    fn test_compress() {
+        let server = DeflateContext::new(DeflateConfig::new());
+        let client = DeflateContext::new(DeflateConfig::new());
+
+        let data = b"Hello, Hello, Hello, Hello, Hello";
+        for _ in 0..3 {
+            let compressed = server.compress(data, true).unwrap();
+            assert!(compressed.len() < data.len());
+            assert!(!compressed.ends_with(&TRAILER));
+            let decompressed = client.decompress(&compressed, true, false, 1024).unwrap();
+            assert_eq!(&decompressed[..], &data[..]);
+        }
+
+        // fragmented message
+        let compressed = server.compress(data, true).unwrap();
+        let (first, last) = compressed.split_at(compressed.len() / 2);
+        let mut result = client
+            .decompress(first, false, false, 1024)
+            .unwrap()
+            .to_vec();
+        result.extend_from_slice(&client.decompress(last, true, false, 1024).unwrap());
+        assert_eq!(&result[..], &data[..]);
+
+        // small windows
+        let cfg = DeflateConfig::new()
+            .max_window_bits(9)
+            .accept_response(&headers(
+                "permessage-deflate; server_max_window_bits=9; client_max_window_bits=9",
+            ))
+            .unwrap();
+        let server = DeflateContext::new(cfg.clone());
+        let client = DeflateContext::new(cfg);
+        let data: Vec<u8> = (0..8192u32).map(|i| (i % 251) as u8).collect();
+        let compressed = server.compress(&data, true).unwrap();
+        let decompressed = client.decompress(&compressed, true, false, 16384).unwrap();
+        assert_eq!(&decompressed[..], &data[..]);
+        let compressed = client.compress(&data, false).unwrap();
+        let decompressed = server.decompress(&compressed, true, true, 16384).unwrap();
+        assert_eq!(&decompressed[..], &data[..]);
+
+        // decompressed size limit
+        let compressed = server.compress(&[b'a'; 4096], true).unwrap();
+        assert!(matches!(
+            client.decompress(&compressed, true, false, 1024),
+            Err(ProtocolError::Overflow)
+        ));
+    }
+}
@@ -5,7 +5,7 @@ use thiserror::Error;
 
 use crate::http::error::{DecodeError, EncodeError, HttpError, ResponseError};
 use crate::http::{header::HeaderValue, header::ALLOW, Response, StatusCode};
-use crate::{connect::ConnectError, util::Either};
+use crate::{connect::ConnectError, io::IoError, util::Either};
 
 use super::OpCode;
 
@@ -25,7 +25,7 @@ pub enum WsError<E> {
     Protocol(ProtocolError),
     /// Peer has been disconnected
     #[error("Peer has been disconnected: {0:?}")]
-    Disconnected(Option<io::Error>),
+    Disconnected(Option<IoError>),
 }
 
 /// Websocket protocol errors
@@ -58,6 +58,15 @@ pub enum ProtocolError {
     /// Unknown continuation fragment
     #[error("Unknown continuation fragment {0}")]
     ContinuationFragment(OpCode),
+    /// Received frame with unexpected `RSV1` bit
+    #[error("Received frame with unexpected RSV1 bit")]
+    UnexpectedRsv,
+    /// Received text message with invalid utf8 payload
+    #[error("Received text message with invalid utf8 payload")]
+    InvalidUtf8,
+    /// Permessage-deflate compression error
+    #[error("Compression error: {0}")]
+    Compression(String),
 }
 
 /// Websocket client error
@@ -73,6 +82,20 @@ pub enum WsClientBuilderError {
     Http(#[from] HttpError),
 }
 
+/// Managed websocket client error
+#[derive(Error, Debug)]
+pub enum WsManagedError<E> {
+    /// Connect error
+    #[error("Connect error: {0}")]
+    Connect(WsClientError),
+    /// Websocket connection error
+    #[error("Websocket connection error: {0}")]
+    Ws(WsError<E>),
+    /// Peer did not respond to pings
+    #[error("Peer did not respond to pings")]
+    PongTimeout,
+}
+
 /// Websocket client error
 #[derive(Error, Debug)]
 pub enum WsClientError {
@@ -97,6 +120,12 @@ pub enum WsClientError {
     /// Missing SEC-WEBSOCKET-ACCEPT header
     #[error("Missing SEC-WEBSOCKET-ACCEPT header")]
     MissingWebSocketAcceptHeader,
+    /// Invalid SEC-WEBSOCKET-PROTOCOL header
+    #[error("Invalid SEC-WEBSOCKET-PROTOCOL header")]
+    InvalidProtocolHeader(HeaderValue),
+    /// Invalid SEC-WEBSOCKET-EXTENSIONS header
+    #[error("Invalid SEC-WEBSOCKET-EXTENSIONS header")]
+    InvalidExtensionsHeader(HeaderValue),
     /// Invalid challenge response
     #[error("Invalid challenge response")]
     InvalidChallengeResponse(String, HeaderValue),
@@ -5,18 +5,49 @@ use crate::http::{RequestHead, Response, ResponseBuilder};
 use super::error::HandshakeError;
 
 /// Verify `WebSocket` handshake request and create handshake reponse.
-// /// `protocols` is a sequence of known protocols. On successful handshake,
-// /// the returned response headers contain the first protocol in this list
-// /// which the server also knows.
 pub fn handshake(req: &RequestHead) -> Result<ResponseBuilder, HandshakeError> {
     verify_handshake(req)?;
     Ok(handshake_response(req))
 }
 
+/// Verify `WebSocket` handshake request and create handshake reponse
+/// with negotiated subprotocol.
+///
+/// `protocols` is a sequence of known protocols. On successful handshake,
+/// the returned response headers contain the first protocol in this list
+/// which the client also requested.
+pub fn handshake_with_protocols(
+    req: &RequestHead,
+    protocols: &[&str],
+) -> Result<ResponseBuilder, HandshakeError> {
+    verify_handshake(req)?;
+    let mut res = handshake_response(req);
+    if let Some(protocol) = select_protocol(req, protocols) {
+        res.header(header::SEC_WEBSOCKET_PROTOCOL, protocol);
+    }
+    Ok(res)
+}
+
+/// Select websocket subprotocol.
+///
+/// Returns first protocol from `protocols` which is requested by the client
+/// in `Sec-WebSocket-Protocol` header.
+pub fn select_protocol<'a>(req: &RequestHead, protocols: &[&'a str]) -> Option<&'a str> {
+    let requested: Vec<_> = req
+        .headers()
+        .get_all(header::SEC_WEBSOCKET_PROTOCOL)
+        .filter_map(|hdr| hdr.to_str().ok())
+        .flat_map(|hdr| hdr.split(','))
+        .map(|proto| proto.trim())
+        .collect();
+
+    protocols
+        .iter()
+        .find(|proto| requested.contains(proto))
+        .copied()
+}
+
 /// Verify `WebSocket` handshake request.
-// /// `protocols` is a sequence of known protocols. On successful handshake,
-// /// the returned response headers contain the first protocol in this list
-// /// which the server also knows.
 pub fn verify_handshake(req: &RequestHead) -> Result<(), HandshakeError> {
     // WebSocket accepts only GET
     if req.method != Method::GET {
@@ -195,6 +226,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_select_protocol() {
+        let req = TestRequest::default()
+            .header(
+                header::SEC_WEBSOCKET_PROTOCOL,
+                header::HeaderValue::from_static("v1, v2"),
+            )
+            .header(
+                header::SEC_WEBSOCKET_PROTOCOL,
+                header::HeaderValue::from_static("v3"),
+            )
+            .finish();
+        assert_eq!(select_protocol(req.head(), &["v3", "v2"]), Some("v3"));
+        assert_eq!(select_protocol(req.head(), &["v4", "v2"]), Some("v2"));
+        assert_eq!(select_protocol(req.head(), &["v4"]), None);
+
+        let req = TestRequest::default().finish();
+        assert_eq!(select_protocol(req.head(), &["v1"]), None);
+    }
+
     #[test]
     fn test_wserror_http_response() {
         let resp: Response = HandshakeError::GetMethodRequired.error_response();
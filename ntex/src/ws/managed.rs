@@ -0,0 +1,267 @@
+//! Managed websockets client connection
+use std::{cell::Cell, cmp, fmt, future::Future, rc::Rc};
+
+use crate::connect::{Connect, ConnectError};
+use crate::http::Uri;
+use crate::io::{Filter, Io};
+use crate::service::{apply_fn, IntoService, Service};
+use crate::time::backoff::{Backoff, Strategy};
+use crate::time::{sleep, Millis, Seconds};
+use crate::util::{select, Bytes, Either};
+use crate::ws;
+
+use super::error::{WsError, WsManagedError};
+use super::WsClient;
+
+/// Managed websockets client connection
+///
+/// Sends ping frames at configured interval and closes connection if peer
+/// does not respond with pong frames. Dropped connection gets re-established
+/// according to reconnect policy, user callback is called for each new
+/// connection so it could re-subscribe and create new frames handler.
+///
+/// ```rust,no_run
+/// use ntex::{time::Seconds, ws};
+///
+/// #[ntex::main]
+/// async fn main() {
+///     let res = ws::WsClient::build("http://127.0.0.1:8080/ws")
+///         .finish()
+///         .unwrap()
+///         .managed()
+///         .ping_interval(Seconds(10))
+///         .reconnect(ws::Reconnect::default())
+///         .start(|sink: ws::WsSink| async move {
+///             sink.send(ws::Message::Text("subscribe".into())).await.unwrap();
+///
+///             Ok::<_, ()>(|frame: ws::Frame| async move {
+///                 println!("Received frame: {:?}", frame);
+///                 Ok::<_, ()>(None)
+///             })
+///         })
+///         .await;
+/// }
+/// ```
+pub struct WsManagedClient<F, T> {
+    client: WsClient<F, T>,
+    ping_interval: Seconds,
+    max_missed_pongs: u16,
+    reconnect: Option<Reconnect>,
+}
+
+/// Reconnect policy for managed websockets connection
+///
+/// Delay between reconnect attempts doubles after each failed attempt,
+/// starting from min delay up to max delay.
+#[derive(Clone, Debug)]
+pub struct Reconnect {
+    min_delay: Millis,
+    max_delay: Millis,
+    max_attempts: usize,
+}
+
+impl Default for Reconnect {
+    /// Reconnect with delays from 100 millis to 30 seconds without
+    /// attempts limit.
+    fn default() -> Self {
+        Reconnect {
+            min_delay: Millis(100),
+            max_delay: Millis(30_000),
+            max_attempts: 0,
+        }
+    }
+}
+
+impl Reconnect {
+    /// Set min and max delay between reconnect attempts.
+    pub fn delay<U: Into<Millis>>(mut self, min: U, max: U) -> Self {
+        self.min_delay = min.into();
+        self.max_delay = cmp::max(self.min_delay, max.into());
+        self
+    }
+
+    /// Max number of consecutive failed reconnect attempts.
+    ///
+    /// Zero value means unlimited number of attempts. By default is unlimited.
+    pub fn max_attempts(mut self, attempts: usize) -> Self {
+        self.max_attempts = attempts;
+        self
+    }
+
+    fn backoff(&self) -> Backoff {
+        Backoff::new(Strategy::Exponential {
+            min: self.min_delay,
+            max: self.max_delay,
+        })
+        .max_attempts(self.max_attempts)
+    }
+}
+
+impl<F, T> WsClient<F, T> {
+    /// Convert client to managed connection client.
+    ///
+    /// By default pings are disabled and connection is not re-established.
+    pub fn managed(self) -> WsManagedClient<F, T> {
+        WsManagedClient {
+            client: self,
+            ping_interval: Seconds::ZERO,
+            max_missed_pongs: 3,
+            reconnect: None,
+        }
+    }
+}
+
+impl<F, T> WsManagedClient<F, T> {
+    /// Send ping frames at specified interval.
+    ///
+    /// Zero value disables pings. Disabled by default.
+    pub fn ping_interval(mut self, interval: Seconds) -> Self {
+        self.ping_interval = interval;
+        self
+    }
+
+    /// Close connection after specified number of unanswered pings.
+    ///
+    /// By default connection is closed after 3 missed pongs.
+    pub fn max_missed_pongs(mut self, num: u16) -> Self {
+        self.max_missed_pongs = cmp::max(num, 1);
+        self
+    }
+
+    /// Re-establish connection according to reconnect policy.
+    ///
+    /// Connection is re-established after failure and after it is closed
+    /// by peer. Connection is not re-established if frames handler returns error.
+    pub fn reconnect(mut self, policy: Reconnect) -> Self {
+        self.reconnect = Some(policy);
+        self
+    }
+}
+
+impl<F, T> WsManagedClient<F, T>
+where
+    F: Filter,
+    T: Service<Connect<Uri>, Response = Io<F>, Error = ConnectError>,
+{
+    /// Connect to websockets server and start processing frames.
+    ///
+    /// Callback is called for each established connection, it receives
+    /// connection's sink and returns frames handler. Future resolves
+    /// if connection is closed and reconnect policy is not set or exhausted,
+    /// or if frames handler returns error.
+    pub async fn start<C, R, U, S>(
+        &self,
+        callback: C,
+    ) -> Result<(), WsManagedError<S::Error>>
+    where
+        C: Fn(ws::WsSink) -> R,
+        R: Future<Output = Result<U, S::Error>>,
+        U: IntoService<S, ws::Frame>,
+        S: Service<ws::Frame, Response = Option<ws::Message>> + 'static,
+    {
+        let mut backoff = self.reconnect.as_ref().map(Reconnect::backoff);
+
+        loop {
+            let err = match self.client.connect().await {
+                Ok(con) => {
+                    if let Some(ref mut backoff) = backoff {
+                        backoff.reset();
+                    }
+                    let con = con.seal();
+                    let sink = con.sink();
+                    let service = callback(sink.clone())
+                        .await
+                        .map_err(|e| WsManagedError::Ws(WsError::Service(e)))?
+                        .into_service();
+
+                    // any pong frame resets missed pings counter
+                    let missed = Rc::new(Cell::new(0));
+                    let m = missed.clone();
+                    let service = apply_fn(service, move |frame, svc| {
+                        if let ws::Frame::Pong(_) = frame {
+                            m.set(0);
+                        }
+                        async move { svc.call(frame).await }
+                    });
+
+                    match select(con.start(service), self.heartbeat(&sink, &missed)).await {
+                        Either::Left(Ok(())) => None,
+                        Either::Left(Err(WsError::Service(e))) => {
+                            return Err(WsManagedError::Ws(WsError::Service(e)))
+                        }
+                        Either::Left(Err(e)) => Some(WsManagedError::Ws(e)),
+                        Either::Right(_) => Some(WsManagedError::PongTimeout),
+                    }
+                }
+                Err(e) => Some(WsManagedError::Connect(e)),
+            };
+
+            if let Some(ref err) = err {
+                log::trace!("Ws connection failed: {}", err);
+            }
+
+            let delay = match backoff {
+                Some(ref mut backoff) => backoff.next_delay().await,
+                None => None,
+            };
+            match (delay, err) {
+                (Some(delay), _) => log::trace!("Ws reconnecting after {:?}", delay),
+                (None, Some(err)) => return Err(err),
+                (None, None) => return Ok(()),
+            }
+        }
+    }
+
+    async fn heartbeat(&self, sink: &ws::WsSink, missed: &Cell<u16>) {
+        if self.ping_interval.is_zero() {
+            return std::future::pending().await;
+        }
+
+        loop {
+            sleep(self.ping_interval).await;
+            if missed.get() >= self.max_missed_pongs {
+                log::trace!("Ws peer did not respond to pings, closing connection");
+                sink.io().force_close();
+                return;
+            }
+            missed.set(missed.get() + 1);
+            let _ = sink.send(ws::Message::Ping(Bytes::new())).await;
+        }
+    }
+}
+
+impl<F, T> fmt::Debug for WsManagedClient<F, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WsManagedClient")
+            .field("client", &self.client)
+            .field("ping_interval", &self.ping_interval)
+            .field("max_missed_pongs", &self.max_missed_pongs)
+            .field("reconnect", &self.reconnect)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let policy = Reconnect::default().delay(Millis(100), Millis(1000));
+        let delays: Vec<_> = policy.backoff().take(6).collect();
+        assert_eq!(
+            delays,
+            vec![
+                Millis(100),
+                Millis(200),
+                Millis(400),
+                Millis(800),
+                Millis(1000),
+                Millis(1000)
+            ]
+        );
+
+        let policy = Reconnect::default().max_attempts(2);
+        assert_eq!(policy.backoff().count(), 2);
+    }
+}
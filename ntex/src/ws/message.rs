@@ -0,0 +1,310 @@
+use std::cell::RefCell;
+
+use crate::codec::{Decoder, Encoder};
+use crate::util::{ByteString, Bytes, BytesMut};
+
+use super::codec::{Codec, Frame, Item, Message};
+use super::error::ProtocolError;
+
+/// WebSockets messages codec
+///
+/// Decoder aggregates continuation frames into complete text and binary
+/// messages, control frames are passed through as is. Encoder splits
+/// large text and binary messages into continuation frames.
+///
+/// Decoder never produces `Message::Continuation`, encoder sends it to
+/// the peer as is.
+#[derive(Debug, Clone)]
+pub struct MessageCodec {
+    codec: Codec,
+    max_size: usize,
+    fragment_size: usize,
+    buf: RefCell<Option<(bool, BytesMut)>>,
+}
+
+impl MessageCodec {
+    /// Create new messages codec on top of frames codec
+    pub fn new(codec: Codec) -> MessageCodec {
+        MessageCodec {
+            codec,
+            max_size: 1_048_576,
+            fragment_size: 65_536,
+            buf: RefCell::new(None),
+        }
+    }
+
+    /// Set max message size
+    ///
+    /// Limits size of aggregated message, size of each frame is limited
+    /// by frames codec. By default max size is set to 1Mb
+    pub fn max_message_size(mut self, size: usize) -> Self {
+        self.max_size = size;
+        self
+    }
+
+    /// Set max size of outgoing frame
+    ///
+    /// Text and binary messages larger than this size are sent as
+    /// continuation frames. Zero value disables fragmentation.
+    /// By default fragment size is set to 64kb
+    pub fn fragment_size(mut self, size: usize) -> Self {
+        self.fragment_size = size;
+        self
+    }
+
+    /// Get reference to frames codec
+    pub fn codec(&self) -> &Codec {
+        &self.codec
+    }
+
+    /// Check if codec encoded `Close` message
+    pub fn is_closed(&self) -> bool {
+        self.codec.is_closed()
+    }
+
+    fn start(&self, text: bool, data: Bytes) -> Result<(), ProtocolError> {
+        if data.len() > self.max_size {
+            return Err(ProtocolError::Overflow);
+        }
+        *self.buf.borrow_mut() = Some((text, BytesMut::from(&data[..])));
+        Ok(())
+    }
+
+    fn append(&self, data: Bytes) -> Result<(), ProtocolError> {
+        let mut buf = self.buf.borrow_mut();
+        let (_, buf) = buf.as_mut().ok_or(ProtocolError::ContinuationNotStarted)?;
+        if buf.len() + data.len() > self.max_size {
+            return Err(ProtocolError::Overflow);
+        }
+        buf.extend_from_slice(&data);
+        Ok(())
+    }
+
+    fn fragment(
+        &self,
+        mut data: Bytes,
+        text: bool,
+        dst: &mut BytesMut,
+    ) -> Result<(), ProtocolError> {
+        let first = data.split_to(self.fragment_size);
+        let item = if text {
+            Item::FirstText(first)
+        } else {
+            Item::FirstBinary(first)
+        };
+        self.codec.encode(Message::Continuation(item), dst)?;
+
+        while data.len() > self.fragment_size {
+            let chunk = data.split_to(self.fragment_size);
+            self.codec
+                .encode(Message::Continuation(Item::Continue(chunk)), dst)?;
+        }
+        self.codec
+            .encode(Message::Continuation(Item::Last(data)), dst)
+    }
+}
+
+impl Default for MessageCodec {
+    fn default() -> Self {
+        Self::new(Codec::new())
+    }
+}
+
+impl From<Codec> for MessageCodec {
+    fn from(codec: Codec) -> Self {
+        Self::new(codec)
+    }
+}
+
+fn text(data: Bytes) -> Result<Message, ProtocolError> {
+    ByteString::try_from(data)
+        .map(Message::Text)
+        .map_err(|_| ProtocolError::InvalidUtf8)
+}
+
+impl Encoder for MessageCodec {
+    type Item = Message;
+    type Error = ProtocolError;
+
+    fn encode(&self, item: Message, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        match item {
+            Message::Text(txt)
+                if self.fragment_size != 0 && txt.len() > self.fragment_size =>
+            {
+                self.fragment(txt.into_bytes(), true, dst)
+            }
+            Message::Binary(bin)
+                if self.fragment_size != 0 && bin.len() > self.fragment_size =>
+            {
+                self.fragment(bin, false, dst)
+            }
+            item => self.codec.encode(item, dst),
+        }
+    }
+}
+
+impl Decoder for MessageCodec {
+    type Item = Message;
+    type Error = ProtocolError;
+
+    fn decode(&self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        loop {
+            let frame = if let Some(frame) = self.codec.decode(src)? {
+                frame
+            } else {
+                return Ok(None);
+            };
+
+            return match frame {
+                Frame::Text(data) => {
+                    if data.len() > self.max_size {
+                        return Err(ProtocolError::Overflow);
+                    }
+                    text(data).map(Some)
+                }
+                Frame::Binary(data) => {
+                    if data.len() > self.max_size {
+                        return Err(ProtocolError::Overflow);
+                    }
+                    Ok(Some(Message::Binary(data)))
+                }
+                Frame::Ping(data) => Ok(Some(Message::Ping(data))),
+                Frame::Pong(data) => Ok(Some(Message::Pong(data))),
+                Frame::Close(reason) => Ok(Some(Message::Close(reason))),
+                Frame::Continuation(Item::FirstText(data)) => {
+                    self.start(true, data)?;
+                    continue;
+                }
+                Frame::Continuation(Item::FirstBinary(data)) => {
+                    self.start(false, data)?;
+                    continue;
+                }
+                Frame::Continuation(Item::Continue(data)) => {
+                    self.append(data)?;
+                    continue;
+                }
+                Frame::Continuation(Item::Last(data)) => {
+                    self.append(data)?;
+                    let (is_text, buf) = self.buf.borrow_mut().take().unwrap();
+                    if is_text {
+                        text(buf.freeze()).map(Some)
+                    } else {
+                        Ok(Some(Message::Binary(buf.freeze())))
+                    }
+                }
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fragments() {
+        let client = MessageCodec::new(Codec::new().client_mode()).fragment_size(4);
+        let server = MessageCodec::default();
+
+        let mut buf = BytesMut::new();
+        client
+            .encode(Message::Text("hello world".into()), &mut buf)
+            .unwrap();
+        client
+            .encode(Message::Binary(Bytes::from_static(b"1234")), &mut buf)
+            .unwrap();
+
+        // frames are fragmented
+        let mut frames = buf.clone();
+        let codec = Codec::new();
+        assert_eq!(
+            codec.decode(&mut frames).unwrap(),
+            Some(Frame::Continuation(Item::FirstText(Bytes::from_static(
+                b"hell"
+            ))))
+        );
+        assert_eq!(
+            codec.decode(&mut frames).unwrap(),
+            Some(Frame::Continuation(Item::Continue(Bytes::from_static(
+                b"o wo"
+            ))))
+        );
+        assert_eq!(
+            codec.decode(&mut frames).unwrap(),
+            Some(Frame::Continuation(Item::Last(Bytes::from_static(b"rld"))))
+        );
+        assert_eq!(
+            codec.decode(&mut frames).unwrap(),
+            Some(Frame::Binary(Bytes::from_static(b"1234")))
+        );
+
+        assert_eq!(
+            server.decode(&mut buf).unwrap(),
+            Some(Message::Text("hello world".into()))
+        );
+        assert_eq!(
+            server.decode(&mut buf).unwrap(),
+            Some(Message::Binary(Bytes::from_static(b"1234")))
+        );
+        assert_eq!(server.decode(&mut buf).unwrap(), None);
+    }
+
+    #[test]
+    fn test_interleaved_control() {
+        let client = Codec::new().client_mode();
+        let server = MessageCodec::default();
+
+        let mut buf = BytesMut::new();
+        client
+            .encode(
+                Message::Continuation(Item::FirstBinary(Bytes::from_static(b"ab"))),
+                &mut buf,
+            )
+            .unwrap();
+        client
+            .encode(Message::Ping(Bytes::from_static(b"p")), &mut buf)
+            .unwrap();
+        client
+            .encode(
+                Message::Continuation(Item::Last(Bytes::from_static(b"cd"))),
+                &mut buf,
+            )
+            .unwrap();
+
+        assert_eq!(
+            server.decode(&mut buf).unwrap(),
+            Some(Message::Ping(Bytes::from_static(b"p")))
+        );
+        assert_eq!(
+            server.decode(&mut buf).unwrap(),
+            Some(Message::Binary(Bytes::from_static(b"abcd")))
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let client = MessageCodec::new(Codec::new().client_mode()).fragment_size(4);
+
+        let server = MessageCodec::default().max_message_size(8);
+        let mut buf = BytesMut::new();
+        client
+            .encode(Message::Binary(Bytes::from_static(b"123456789")), &mut buf)
+            .unwrap();
+        assert!(matches!(
+            server.decode(&mut buf),
+            Err(ProtocolError::Overflow)
+        ));
+
+        let server = MessageCodec::default();
+        let mut buf = BytesMut::new();
+        Codec::new()
+            .client_mode()
+            .encode(Message::Binary(Bytes::from_static(b"\xff\xfe")), &mut buf)
+            .unwrap();
+        buf[0] = (buf[0] & 0xf0) | 0x01; // change opcode to text
+        assert!(matches!(
+            server.decode(&mut buf),
+            Err(ProtocolError::InvalidUtf8)
+        ));
+    }
+}
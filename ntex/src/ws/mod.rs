@@ -5,9 +5,13 @@
 //! communicate with the peer.
 mod client;
 mod codec;
+#[cfg(feature = "compress")]
+mod deflate;
 mod frame;
 mod handshake;
+mod managed;
 mod mask;
+mod message;
 mod proto;
 mod sink;
 mod transport;
@@ -16,8 +20,15 @@ pub mod error;
 
 pub use self::client::{WsClient, WsClientBuilder, WsConnection};
 pub use self::codec::{Codec, Frame, Item, Message};
+#[cfg(feature = "compress")]
+pub use self::deflate::DeflateConfig;
 pub use self::frame::Parser;
-pub use self::handshake::{handshake, handshake_response, verify_handshake};
+pub use self::handshake::{
+    handshake, handshake_response, handshake_with_protocols, select_protocol,
+    verify_handshake,
+};
+pub use self::managed::{Reconnect, WsManagedClient};
+pub use self::message::MessageCodec;
 pub use self::proto::{hash_key, CloseCode, CloseReason, OpCode};
 pub use self::sink::WsSink;
 pub use self::transport::{WsTransport, WsTransportService};
@@ -128,6 +128,194 @@ async fn test_openssl_read_before_error() {
     assert!(io.recv(&BytesCodec).await.unwrap().is_none());
 }
 
+#[cfg(feature = "openssl")]
+fn mtls_server() -> ntex::server::TestServer {
+    use ntex::server::openssl;
+    use ntex_tls::openssl::PeerCert;
+    use tls_openssl::ssl::{SslAcceptor, SslFiletype, SslMethod, SslVerifyMode};
+
+    let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+    builder
+        .set_private_key_file("./tests/key.pem", SslFiletype::PEM)
+        .unwrap();
+    builder
+        .set_certificate_chain_file("./tests/cert.pem")
+        .unwrap();
+    builder.set_verify_callback(
+        SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT,
+        |_, _| true,
+    );
+    let acceptor = builder.build();
+
+    test_server(move || {
+        chain_factory(openssl::SslAcceptor::new(acceptor.clone())).and_then(
+            fn_service(|io: Io<_>| async move {
+                let cert = io.query::<PeerCert>();
+                let cert = cert.as_ref().unwrap().0.to_der().unwrap();
+                assert_eq!(cert, X509::from_pem(CERT).unwrap().to_der().unwrap());
+                io.send(Bytes::from_static(b"test"), &BytesCodec)
+                    .await
+                    .unwrap();
+                time::sleep(time::Millis(100)).await;
+                Ok::<_, Box<dyn std::error::Error>>(())
+            })
+            .map_init_err(|_| ()),
+        )
+    })
+}
+
+#[cfg(feature = "openssl")]
+use tls_openssl::x509::X509;
+
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+const CERT: &[u8] = include_bytes!("cert.pem");
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+const KEY: &[u8] = include_bytes!("key.pem");
+
+#[cfg(feature = "openssl")]
+#[ntex::test]
+async fn test_openssl_client_cert() {
+    use ntex::connect::ClientCert;
+    use tls_openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+
+    let srv = mtls_server();
+
+    let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+    builder.set_verify(SslVerifyMode::NONE);
+
+    let conn = ntex::connect::openssl::SslConnector::new(builder.build())
+        .client_cert(&ClientCert::from_pem(CERT, KEY))
+        .unwrap();
+    let conn = Pipeline::new(conn);
+    let addr = format!("127.0.0.1:{}", srv.addr().port());
+    let io = conn.call(addr.into()).await.unwrap();
+    let item = io.recv(&BytesCodec).await.unwrap().unwrap();
+    assert_eq!(item, Bytes::from_static(b"test"));
+
+    // invalid certificate
+    let builder = SslConnector::builder(SslMethod::tls()).unwrap();
+    assert!(
+        ntex::connect::openssl::SslConnector::<&str>::new(builder.build())
+            .client_cert(&ClientCert::from_pem(&b"cert"[..], KEY))
+            .is_err()
+    );
+}
+
+#[cfg(all(feature = "openssl", feature = "rustls"))]
+#[ntex::test]
+async fn test_rustls_client_cert() {
+    use ntex::connect::ClientCert;
+
+    let srv = mtls_server();
+
+    let config = rustls_utils::tls_connector();
+
+    let conn = ntex::connect::rustls::TlsConnector::new(config)
+        .client_cert_for("127.0.0.1", &ClientCert::from_pem(CERT, KEY))
+        .unwrap();
+    let conn = Pipeline::new(conn);
+    let addr = format!("127.0.0.1:{}", srv.addr().port());
+    let io = conn.call(addr.into()).await.unwrap();
+    let item = io.recv(&BytesCodec).await.unwrap().unwrap();
+    assert_eq!(item, Bytes::from_static(b"test"));
+
+    // pkcs12 is not supported
+    let config = rustls_utils::tls_connector();
+    assert!(ntex::connect::rustls::TlsConnector::<&str>::new(config)
+        .client_cert(&ClientCert::from_pkcs12(&b""[..], ""))
+        .is_err());
+}
+
+#[cfg(all(feature = "rustls", feature = "dangerous"))]
+#[ntex::test]
+async fn test_rustls_accept_invalid_certs() {
+    use ntex::server::rustls;
+    use tls_rustls::{ClientConfig, RootCertStore};
+
+    let srv = test_server(|| {
+        chain_factory(rustls::TlsAcceptor::new(rustls_utils::tls_acceptor_arc())).and_then(
+            fn_service(|io: Io<_>| async move {
+                io.send(Bytes::from_static(b"test"), &BytesCodec)
+                    .await
+                    .unwrap();
+                Ok::<_, io::Error>(())
+            })
+            .map_init_err(|_| ()),
+        )
+    });
+    let addr = format!("localhost:{}", srv.addr().port());
+    let config = || {
+        ClientConfig::builder()
+            .with_root_certificates(RootCertStore::empty())
+            .with_no_client_auth()
+    };
+
+    let conn = Pipeline::new(
+        ntex::connect::rustls::TlsConnector::new(config())
+            .dangerous_accept_invalid_certs(true),
+    );
+    let io = conn.call(addr.clone().into()).await.unwrap();
+    let item = io.recv(&BytesCodec).await.unwrap().unwrap();
+    assert_eq!(item, Bytes::from_static(b"test"));
+
+    // verification is enabled again
+    let conn = Pipeline::new(
+        ntex::connect::rustls::TlsConnector::new(config())
+            .dangerous_accept_invalid_certs(true)
+            .dangerous_accept_invalid_certs(false),
+    );
+    assert!(conn.call(addr.into()).await.is_err());
+}
+
+#[cfg(all(feature = "openssl", feature = "native-tls"))]
+#[ntex::test]
+async fn test_native_tls_string() {
+    use ntex::server::openssl;
+    use ntex_tls::native_tls::PeerCert;
+    use tls_openssl::x509::X509;
+
+    let srv = test_server(|| {
+        chain_factory(
+            fn_service(|io: Io<_>| async move {
+                let res = io.read_ready().await;
+                assert!(res.is_ok());
+                Ok(io)
+            })
+            .map_init_err(|_| ()),
+        )
+        .and_then(openssl::SslAcceptor::new(ssl_acceptor()))
+        .and_then(
+            fn_service(|io: Io<_>| async move {
+                io.send(Bytes::from_static(b"test"), &BytesCodec)
+                    .await
+                    .unwrap();
+                assert_eq!(io.recv(&BytesCodec).await.unwrap().unwrap(), "test");
+                Ok::<_, Box<dyn std::error::Error>>(())
+            })
+            .map_init_err(|_| ()),
+        )
+    });
+
+    let connector = tls_native::TlsConnector::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+    let conn = Pipeline::new(ntex::connect::native_tls::TlsConnector::new(connector));
+    let addr = format!("127.0.0.1:{}", srv.addr().port());
+    let io = conn.call(addr.into()).await.unwrap();
+    assert_eq!(io.query::<PeerAddr>().get().unwrap(), srv.addr().into());
+    let cert = X509::from_pem(include_bytes!("cert.pem")).unwrap();
+    assert_eq!(
+        io.query::<PeerCert>().as_ref().unwrap().0.to_der().unwrap(),
+        cert.to_der().unwrap()
+    );
+    let item = io.recv(&BytesCodec).await.unwrap().unwrap();
+    assert_eq!(item, Bytes::from_static(b"test"));
+    io.send(Bytes::from_static(b"test"), &BytesCodec)
+        .await
+        .unwrap();
+}
+
 #[cfg(feature = "rustls")]
 #[ignore]
 #[ntex::test]
@@ -194,6 +382,69 @@ async fn test_rustls_string() {
     assert!(io.recv(&BytesCodec).await.unwrap().is_none());
 }
 
+#[cfg(all(unix, feature = "tokio"))]
+async fn raw_parts_round_trip<P, F>(io: Io, peer: &mut P, f: F)
+where
+    P: std::io::Read + std::io::Write,
+    F: FnOnce(std::os::fd::OwnedFd) -> io::Result<Io>,
+{
+    // received data is not consumed
+    peer.write_all(b"hello").unwrap();
+    while io.with_read_buf(|buf| buf.len()) < 5 {
+        io.read_ready().await.unwrap();
+    }
+
+    let parts = io.into_raw_parts().unwrap();
+    assert_eq!(&parts.read_buf[..], b"hello");
+    assert!(parts.write_buf.is_empty());
+
+    // io tasks of detached io get terminated, io stream stays open
+    time::sleep(time::Millis(50)).await;
+
+    let io = Io::from_raw_parts(parts, f).unwrap();
+    assert_eq!(
+        io.recv(&BytesCodec).await.unwrap().unwrap(),
+        Bytes::from_static(b"hello")
+    );
+
+    io.send(Bytes::from_static(b"world"), &BytesCodec)
+        .await
+        .unwrap();
+    let mut buf = [0; 5];
+    peer.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"world");
+
+    peer.write_all(b"again").unwrap();
+    assert_eq!(
+        io.recv(&BytesCodec).await.unwrap().unwrap(),
+        Bytes::from_static(b"again")
+    );
+}
+
+#[cfg(all(unix, feature = "tokio"))]
+#[ntex::test]
+async fn test_raw_parts_tcp() {
+    let lst = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut peer = std::net::TcpStream::connect(lst.local_addr().unwrap()).unwrap();
+    peer.set_read_timeout(Some(std::time::Duration::from_secs(5)))
+        .unwrap();
+    let (stream, _) = lst.accept().unwrap();
+
+    let io = ntex::rt::from_tcp_stream(stream).unwrap();
+    raw_parts_round_trip(io, &mut peer, |fd| ntex::rt::from_tcp_stream(fd.into())).await;
+}
+
+#[cfg(all(unix, feature = "tokio"))]
+#[ntex::test]
+async fn test_raw_parts_uds() {
+    let (stream, mut peer) = std::os::unix::net::UnixStream::pair().unwrap();
+    peer.set_read_timeout(Some(std::time::Duration::from_secs(5)))
+        .unwrap();
+
+    let io = ntex::rt::from_unix_stream(stream).unwrap();
+    raw_parts_round_trip(io, &mut peer, |fd| ntex::rt::from_unix_stream(fd.into())).await;
+}
+
 #[ntex::test]
 async fn test_static_str() {
     let srv = test_server(|| {
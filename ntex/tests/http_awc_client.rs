@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use std::io::{Read, Write};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::{cell::RefCell, rc::Rc};
 
 use brotli2::write::BrotliEncoder;
 use coo_kie::Cookie;
@@ -9,11 +10,17 @@ use flate2::{read::GzDecoder, write::GzEncoder, write::ZlibEncoder, Compression}
 use futures_util::stream::once;
 use rand::Rng;
 
+use ntex::codec::BytesCodec;
 use ntex::http::client::error::{JsonPayloadError, SendRequestError};
-use ntex::http::client::{Client, Connector};
+use ntex::http::client::{
+    Cache, CacheStatus, Client, ClientResponse, ClientServiceRequest, Connector,
+    MemoryStorage, PoolEvent, ReaderStream,
+};
+use ntex::http::error::PayloadError;
 use ntex::http::test::server as test_server;
-use ntex::http::{header, HttpMessage, HttpService, Method};
-use ntex::service::{chain_factory, map_config};
+use ntex::http::{header, HttpMessage, HttpService, Method, StatusCode};
+use ntex::io::types::HttpProtocol;
+use ntex::service::{chain_factory, map_config, Middleware, Service, ServiceCtx};
 use ntex::web::dev::AppConfig;
 use ntex::web::middleware::Compress;
 use ntex::web::{self, test, App, BodyEncoding, Error, HttpRequest, HttpResponse};
@@ -201,6 +208,58 @@ async fn test_timeout_override() {
     }
 }
 
+#[ntex::test]
+async fn test_payload_timeout() {
+    let srv = test::server(|| {
+        App::new().service(web::resource("/").route(web::to(|| async {
+            HttpResponse::Ok().streaming(Box::pin(once(async {
+                sleep(Millis(2000)).await;
+                Ok::<_, std::io::Error>(Bytes::from_static(STR.as_bytes()))
+            })))
+        })))
+    });
+
+    let mut response = srv
+        .get("/")
+        .response_payload_timeout(Millis(250))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    match response.body().await {
+        Err(PayloadError::Timeout) => (),
+        _ => panic!(),
+    }
+}
+
+#[ntex::test]
+async fn test_pool_status() {
+    let srv = test::server(|| {
+        App::new()
+            .service(web::resource("/").route(web::to(|| async { HttpResponse::Ok() })))
+    });
+
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let events2 = events.clone();
+    let connector = Connector::default()
+        .limit_per_host(1)
+        .max_idle(1)
+        .on_pool_event(move |ev| events2.borrow_mut().push(ev.clone()))
+        .finish();
+    let client = Client::build().connector(connector).finish();
+
+    let response = client.get(srv.url("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+    let response = client.get(srv.url("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+
+    let status = client.pool_status();
+    assert_eq!(status.idle(), 1);
+    assert_eq!(status.active(), 0);
+    assert_eq!(events.borrow().len(), 1);
+    assert!(matches!(events.borrow()[0], PoolEvent::Connected { .. }));
+}
+
 #[ntex::test]
 async fn test_connection_reuse() {
     let num = Arc::new(AtomicUsize::new(0));
@@ -353,6 +412,93 @@ async fn test_connection_wait_queue() {
     assert_eq!(num.load(Ordering::Relaxed), 1);
 }
 
+#[ntex::test]
+async fn test_send_all() {
+    let inflight = Arc::new(AtomicUsize::new(0));
+    let max = Arc::new(AtomicUsize::new(0));
+    let max2 = max.clone();
+
+    let srv = test::server(move || {
+        let (inflight, max) = (inflight.clone(), max2.clone());
+        App::new().service(web::resource("/{id}").to(
+            move |path: web::types::Path<usize>| {
+                let (inflight, max) = (inflight.clone(), max.clone());
+                async move {
+                    let id = path.into_inner();
+                    let cur = inflight.fetch_add(1, Ordering::Relaxed) + 1;
+                    max.fetch_max(cur, Ordering::Relaxed);
+                    sleep(Millis(50 * (5 - id as u32 % 5))).await;
+                    inflight.fetch_sub(1, Ordering::Relaxed);
+                    HttpResponse::Ok().body(id.to_string())
+                }
+            },
+        ))
+    });
+
+    let client = Client::build().timeout(Seconds(30)).finish();
+    let reqs = (0..10).map(|id| client.get(srv.url(&format!("/{}", id))));
+    let mut results = ntex::http::client::batch::send_all(reqs, 3);
+
+    let mut done = Vec::new();
+    while let Some((idx, res)) = ntex::util::stream_recv(&mut results).await {
+        let mut res = res.unwrap();
+        assert!(res.status().is_success());
+        assert_eq!(res.body().await.unwrap(), Bytes::from(idx.to_string()));
+        done.push(idx);
+    }
+    done.sort();
+    assert_eq!(done, (0..10).collect::<Vec<_>>());
+    assert_eq!(max.load(Ordering::Relaxed), 3);
+}
+
+#[ntex::test]
+async fn test_send_all_limit_per_host() {
+    fn server(max: Arc<AtomicUsize>) -> test::TestServer {
+        let inflight = Arc::new(AtomicUsize::new(0));
+        test::server(move || {
+            let (inflight, max) = (inflight.clone(), max.clone());
+            App::new().service(web::resource("/{id}").to(
+                move |path: web::types::Path<usize>| {
+                    let (inflight, max) = (inflight.clone(), max.clone());
+                    async move {
+                        let cur = inflight.fetch_add(1, Ordering::Relaxed) + 1;
+                        max.fetch_max(cur, Ordering::Relaxed);
+                        sleep(Millis(50)).await;
+                        inflight.fetch_sub(1, Ordering::Relaxed);
+                        HttpResponse::Ok().body(path.into_inner().to_string())
+                    }
+                },
+            ))
+        })
+    }
+
+    let max1 = Arc::new(AtomicUsize::new(0));
+    let max2 = Arc::new(AtomicUsize::new(0));
+    let srv1 = server(max1.clone());
+    let srv2 = server(max2.clone());
+
+    // requests to first host over limit are deferred,
+    // requests to second host are started instead
+    let client = Client::build().timeout(Seconds(30)).finish();
+    let reqs = (0..8).map(|id| {
+        let srv = if matches!(id, 3 | 4) { &srv2 } else { &srv1 };
+        client.get(srv.url(&format!("/{}", id)))
+    });
+    let mut results = ntex::http::client::batch::send_all(reqs, 4).limit_per_host(2);
+
+    let mut done = Vec::new();
+    while let Some((idx, res)) = ntex::util::stream_recv(&mut results).await {
+        let mut res = res.unwrap();
+        assert!(res.status().is_success());
+        assert_eq!(res.body().await.unwrap(), Bytes::from(idx.to_string()));
+        done.push(idx);
+    }
+    done.sort();
+    assert_eq!(done, (0..8).collect::<Vec<_>>());
+    assert_eq!(max1.load(Ordering::Relaxed), 2);
+    assert_eq!(max2.load(Ordering::Relaxed), 2);
+}
+
 #[ntex::test]
 async fn test_connection_wait_queue_force_close() {
     let num = Arc::new(AtomicUsize::new(0));
@@ -654,6 +800,151 @@ async fn test_client_brotli_encoding_large_random() {
     assert_eq!(bytes, Bytes::from(data.clone()));
 }
 
+#[ntex::test]
+async fn test_client_cache() {
+    let num = Arc::new(AtomicUsize::new(0));
+    let num2 = num.clone();
+
+    let srv = test::server(move || {
+        let num = num2.clone();
+        let num2 = num2.clone();
+        App::new()
+            .service(web::resource("/fresh").route(web::to(move || {
+                num.fetch_add(1, Ordering::Relaxed);
+                async {
+                    HttpResponse::Ok()
+                        .header(header::CACHE_CONTROL, "max-age=60")
+                        .body(STR)
+                }
+            })))
+            .service(
+                web::resource("/validate").route(web::to(move |req: HttpRequest| {
+                    num2.fetch_add(1, Ordering::Relaxed);
+                    async move {
+                        if req.headers().get(header::IF_NONE_MATCH).is_some() {
+                            HttpResponse::NotModified()
+                                .header(header::ETAG, "\"1\"")
+                                .finish()
+                        } else {
+                            HttpResponse::Ok()
+                                .header(header::CACHE_CONTROL, "no-cache")
+                                .header(header::ETAG, "\"1\"")
+                                .body(STR)
+                        }
+                    }
+                })),
+            )
+    });
+
+    let client = Client::build()
+        .wrap(Cache::new(MemoryStorage::new(16)))
+        .finish();
+
+    let mut response = client.get(srv.url("/fresh")).send().await.unwrap();
+    assert_eq!(response.cache_status(), Some(CacheStatus::Miss));
+    assert_eq!(
+        response.body().await.unwrap(),
+        Bytes::from_static(STR.as_ref())
+    );
+
+    let mut response = client.get(srv.url("/fresh")).send().await.unwrap();
+    assert_eq!(response.cache_status(), Some(CacheStatus::Hit));
+    assert_eq!(
+        response.body().await.unwrap(),
+        Bytes::from_static(STR.as_ref())
+    );
+    assert_eq!(num.load(Ordering::Relaxed), 1);
+
+    // unsafe request invalidates stored response
+    let response = client.post(srv.url("/fresh")).send().await.unwrap();
+    assert!(response.status().is_success());
+    let response = client.get(srv.url("/fresh")).send().await.unwrap();
+    assert_eq!(response.cache_status(), Some(CacheStatus::Miss));
+
+    let mut response = client.get(srv.url("/validate")).send().await.unwrap();
+    assert_eq!(response.cache_status(), Some(CacheStatus::Miss));
+    assert_eq!(
+        response.body().await.unwrap(),
+        Bytes::from_static(STR.as_ref())
+    );
+
+    let mut response = client.get(srv.url("/validate")).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.cache_status(), Some(CacheStatus::Revalidated));
+    assert_eq!(
+        response.body().await.unwrap(),
+        Bytes::from_static(STR.as_ref())
+    );
+}
+
+#[ntex::test]
+async fn test_acquire_connection() {
+    let srv = test::server(|| {
+        App::new()
+            .service(web::resource("/").route(web::to(|| async { HttpResponse::Ok() })))
+    });
+
+    let client = Client::new();
+    let conn = client
+        .connector()
+        .acquire(srv.url("/").parse().unwrap())
+        .await
+        .unwrap();
+    assert_eq!(conn.protocol(), HttpProtocol::Http1);
+
+    let io = conn.io().unwrap();
+    io.encode(
+        Bytes::from_static(b"GET / HTTP/1.1\r\nhost: localhost\r\n\r\n"),
+        &BytesCodec,
+    )
+    .unwrap();
+    let data = io.recv(&BytesCodec).await.unwrap().unwrap();
+    assert!(data.starts_with(b"HTTP/1.1 200 OK"));
+
+    conn.release(false);
+    assert_eq!(client.pool_status().idle(), 1);
+}
+
+#[ntex::test]
+async fn test_client_reader() {
+    let data = rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(100_000)
+        .map(char::from)
+        .collect::<String>();
+
+    let srv = test::server(|| {
+        App::new().service(web::resource("/").route(web::to(
+            |req: HttpRequest, data: Bytes| async move {
+                assert_eq!(
+                    req.headers().get(header::TRANSFER_ENCODING).unwrap(),
+                    "chunked"
+                );
+                HttpResponse::Ok().body(data)
+            },
+        )))
+    });
+
+    let mut response = srv
+        .post("/")
+        .send_reader(std::io::Cursor::new(data.clone()))
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from(data.clone()));
+
+    // small chunks
+    let mut response = srv
+        .post("/")
+        .send_stream(ReaderStream::new(std::io::Cursor::new(data.clone())).chunk_size(1000))
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from(data));
+}
+
 #[ntex::test]
 async fn test_client_deflate_encoding() {
     let srv = test::server(|| {
@@ -706,6 +997,138 @@ async fn test_client_deflate_encoding_large_random() {
     assert_eq!(bytes, Bytes::from(data));
 }
 
+#[ntex::test]
+async fn test_client_zstd_encoding() {
+    let srv = test::server(|| {
+        App::new().service(web::resource("/").route(web::to(
+            |req: HttpRequest, data: Bytes| async move {
+                assert!(req
+                    .headers()
+                    .get(header::ACCEPT_ENCODING)
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .contains("zstd"));
+                let data = zstd::encode_all(data.as_ref(), 3).unwrap();
+                HttpResponse::Ok()
+                    .header("content-encoding", "zstd")
+                    .body(data)
+            },
+        )))
+    });
+
+    // client request
+    let mut response = srv.post("/").send_body(STR).await.unwrap();
+    assert!(response.status().is_success());
+
+    // read response
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+}
+
+#[ntex::test]
+async fn test_client_decompress_limit() {
+    let srv = test::server(|| {
+        App::new().service(web::resource("/").route(web::to(|| async {
+            let mut e = GzEncoder::new(Vec::new(), Compression::default());
+            e.write_all(&[b'x'; 100_000]).unwrap();
+            let data = e.finish().unwrap();
+
+            HttpResponse::Ok()
+                .header("content-encoding", "gzip")
+                .body(data)
+        })))
+    });
+
+    // decompressed payload exceeds limit
+    let client = Client::build()
+        .response_payload_limit(1_000_000)
+        .response_decompress_limit(10_000)
+        .finish();
+    let mut response = client.get(srv.url("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+    assert!(matches!(
+        response.body().await.err().unwrap(),
+        ntex::http::error::PayloadError::Overflow
+    ));
+
+    // decompression is disabled
+    let client = Client::build().disable_decompress().finish();
+    let mut response = client.get(srv.url("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+    let bytes = response.body().await.unwrap();
+    assert!(bytes.len() < 10_000);
+
+    let mut d = GzDecoder::new(bytes.as_ref());
+    let mut data = Vec::new();
+    d.read_to_end(&mut data).unwrap();
+    assert_eq!(data.len(), 100_000);
+}
+
+#[ntex::test]
+async fn test_client_request_compress() {
+    let srv = test::server(|| {
+        App::new().service(web::resource("/").route(web::to(
+            |req: HttpRequest, data: Bytes| async move {
+                let enc = req
+                    .headers()
+                    .get(header::CONTENT_ENCODING)
+                    .map(|v| v.to_str().unwrap().to_string())
+                    .unwrap_or_default();
+                HttpResponse::Ok().header("x-encoding", enc).body(data)
+            },
+        )))
+    });
+
+    for enc in [
+        header::ContentEncoding::Gzip,
+        header::ContentEncoding::Deflate,
+        header::ContentEncoding::Br,
+        header::ContentEncoding::Zstd,
+    ] {
+        let mut response = srv.post("/").compress(enc).send_body(STR).await.unwrap();
+        assert!(response.status().is_success());
+        assert_eq!(response.headers().get("x-encoding").unwrap(), enc.as_str());
+        let bytes = response.body().await.unwrap();
+        assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+
+        // streaming body
+        let mut response = srv
+            .post("/")
+            .compress(enc)
+            .send_stream(once(Ready::Ok::<_, Error>(Bytes::from_static(b"stream"))))
+            .await
+            .unwrap();
+        assert_eq!(response.headers().get("x-encoding").unwrap(), enc.as_str());
+        let bytes = response.body().await.unwrap();
+        assert_eq!(bytes, Bytes::from_static(b"stream"));
+    }
+
+    // frozen request
+    let req = srv
+        .post("/")
+        .compress(header::ContentEncoding::Gzip)
+        .freeze()
+        .unwrap();
+    for _ in 0..2 {
+        let mut response = req.send_body(STR).await.unwrap();
+        assert_eq!(response.headers().get("x-encoding").unwrap(), "gzip");
+        let bytes = response.body().await.unwrap();
+        assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+    }
+
+    // small bodies are not compressed
+    let mut response = srv
+        .post("/")
+        .compress(header::ContentEncoding::Br)
+        .send_body("small")
+        .await
+        .unwrap();
+    assert_eq!(response.headers().get("x-encoding").unwrap(), "");
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"small"));
+}
+
 // #[ntex::test]
 // async fn test_body_streaming_implicit() {
 //     let srv = test::TestServer::start(|app| {
@@ -726,6 +1149,161 @@ async fn test_client_deflate_encoding_large_random() {
 //     assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
 // }
 
+#[ntex::test]
+async fn test_client_redirects() {
+    fn redirect(status: u16, location: &str) -> HttpResponse {
+        HttpResponse::build(ntex::http::StatusCode::from_u16(status).unwrap())
+            .header(header::LOCATION, location)
+            .finish()
+    }
+
+    let srv = test::server(|| {
+        App::new()
+            .route("/start", web::get().to(|| async { redirect(302, "/next") }))
+            .route("/next", web::get().to(|| async { redirect(307, "end") }))
+            .route(
+                "/see-other",
+                web::post().to(|| async { redirect(303, "/end") }),
+            )
+            .route("/temp", web::post().to(|| async { redirect(307, "/end") }))
+            .route("/loop", web::get().to(|| async { redirect(302, "/loop") }))
+            .route(
+                "/end",
+                web::to(|req: HttpRequest, body: Bytes| async move {
+                    HttpResponse::Ok().body(format!(
+                        "{} {} {}",
+                        req.method(),
+                        req.headers().contains_key(header::AUTHORIZATION),
+                        String::from_utf8_lossy(&body)
+                    ))
+                }),
+            )
+    });
+
+    // redirect chain
+    let client = Client::new();
+    let mut response = client.get(srv.url("/start")).send().await.unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(
+        response.redirects(),
+        &[
+            srv.url("/start").parse::<ntex::http::Uri>().unwrap(),
+            srv.url("/next").parse::<ntex::http::Uri>().unwrap()
+        ]
+    );
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"GET false "));
+
+    // 303 changes method to GET and drops body
+    let mut response = client
+        .post(srv.url("/see-other"))
+        .send_body("data")
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"GET false "));
+
+    // 307 preserves method and body
+    let mut response = client
+        .post(srv.url("/temp"))
+        .bearer_auth("token")
+        .send_body("data")
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.redirects().len(), 1);
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"POST true data"));
+
+    // too many redirects
+    let res = client.get(srv.url("/loop")).send().await;
+    assert!(matches!(res, Err(SendRequestError::TooManyRedirects)));
+
+    let client = Client::build().max_redirects(1).finish();
+    let res = client.get(srv.url("/start")).send().await;
+    assert!(matches!(res, Err(SendRequestError::TooManyRedirects)));
+
+    // redirects are disabled
+    let client = Client::build().disable_redirects().finish();
+    let response = client.get(srv.url("/start")).send().await.unwrap();
+    assert_eq!(response.status(), ntex::http::StatusCode::FOUND);
+    assert!(response.redirects().is_empty());
+
+    // sensitive headers are dropped on cross-origin redirect
+    let url = srv.url("/end");
+    let srv2 = test::server(move || {
+        let url = url.clone();
+        App::new().route(
+            "/cross",
+            web::get().to(move || {
+                let url = url.clone();
+                async move { redirect(302, &url) }
+            }),
+        )
+    });
+    let mut response = Client::new()
+        .get(srv2.url("/cross"))
+        .bearer_auth("token")
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"GET false "));
+}
+
+#[ntex::test]
+async fn test_client_redirects_connection_and_timeout() {
+    let peers = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let peers2 = peers.clone();
+
+    let srv = test::server(move || {
+        let peers = peers2.clone();
+        let peers2 = peers2.clone();
+        App::new()
+            .route(
+                "/moved",
+                web::get().to(move |req: HttpRequest| {
+                    peers.lock().unwrap().push(req.peer_addr());
+                    async {
+                        HttpResponse::Found()
+                            .header(header::LOCATION, "/end")
+                            .body(STR)
+                    }
+                }),
+            )
+            .route(
+                "/end",
+                web::get().to(move |req: HttpRequest| {
+                    peers2.lock().unwrap().push(req.peer_addr());
+                    async { HttpResponse::Ok().finish() }
+                }),
+            )
+            .route(
+                "/slow",
+                web::get().to(|| async {
+                    sleep(Millis(300)).await;
+                    HttpResponse::Found()
+                        .header(header::LOCATION, "/slow")
+                        .finish()
+                }),
+            )
+    });
+
+    // redirect response body is drained and connection is re-used
+    let response = Client::new().get(srv.url("/moved")).send().await.unwrap();
+    assert!(response.status().is_success());
+    let peers = peers.lock().unwrap().clone();
+    assert_eq!(peers.len(), 2);
+    assert_eq!(peers[0], peers[1]);
+
+    // timeout applies to whole redirect chain
+    let client = Client::build().timeout(Millis(500)).finish();
+    let res = client.get(srv.url("/slow")).send().await;
+    assert!(matches!(res, Err(SendRequestError::Timeout)));
+}
+
 #[ntex::test]
 async fn test_client_cookie_handling() {
     use std::io::{Error as IoError, ErrorKind};
@@ -792,6 +1370,157 @@ async fn test_client_cookie_handling() {
     assert_eq!(c2, cookie2);
 }
 
+#[ntex::test]
+async fn test_client_cookie_store() {
+    let srv = test::server(|| {
+        App::new()
+            .route(
+                "/login",
+                web::get().to(|| async {
+                    HttpResponse::Found()
+                        .header(header::LOCATION, "/account")
+                        .header(header::SET_COOKIE, "session=abc; Path=/")
+                        .header(header::SET_COOKIE, "secret=1; Secure")
+                        .header(header::SET_COOKIE, "private=1; Path=/private")
+                        .finish()
+                }),
+            )
+            .route(
+                "/logout",
+                web::get().to(|| async {
+                    HttpResponse::Ok()
+                        .header(header::SET_COOKIE, "session=; Path=/; Max-Age=0")
+                        .finish()
+                }),
+            )
+            .default_service(web::to(|req: HttpRequest| async move {
+                HttpResponse::Ok().body(
+                    req.headers()
+                        .get(header::COOKIE)
+                        .map(|v| v.to_str().unwrap().to_string())
+                        .unwrap_or_default(),
+                )
+            }))
+    });
+
+    let store = ntex::http::client::CookieStore::new();
+    let client = Client::build().cookie_store(store.clone()).finish();
+    assert!(client.cookie_store().is_some());
+
+    // cookies from redirect response are sent on next hop
+    let mut response = client.get(srv.url("/login")).send().await.unwrap();
+    assert!(response.status().is_success());
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"session=abc"));
+    // secure cookie is not stored for plain http
+    assert_eq!(store.len(), 2);
+
+    // path aware, user cookies are preserved
+    let mut response = client
+        .get(srv.url("/private/data"))
+        .cookie(Cookie::new("user", "1"))
+        .send()
+        .await
+        .unwrap();
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"user=1; private=1; session=abc"));
+
+    // seeded cookie
+    let url = srv.url("/").parse::<ntex::http::Uri>().unwrap();
+    assert!(store.insert(Cookie::new("seed", "2"), &url));
+    let response = client.get(srv.url("/logout")).send().await.unwrap();
+    assert!(response.status().is_success());
+    let mut response = client.get(srv.url("/")).send().await.unwrap();
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"seed=2"));
+}
+
+struct Retry(Arc<AtomicUsize>);
+
+impl<S> Middleware<S> for Retry {
+    type Service = RetryService<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        RetryService {
+            service,
+            counter: self.0.clone(),
+        }
+    }
+}
+
+struct RetryService<S> {
+    service: S,
+    counter: Arc<AtomicUsize>,
+}
+
+impl<S> Service<ClientServiceRequest> for RetryService<S>
+where
+    S: Service<ClientServiceRequest, Response = ClientResponse, Error = SendRequestError>,
+{
+    type Response = ClientResponse;
+    type Error = SendRequestError;
+
+    async fn call(
+        &self,
+        mut req: ClientServiceRequest,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<ClientResponse, SendRequestError> {
+        req.set_header(header::AUTHORIZATION, "Bearer token");
+        loop {
+            self.counter.fetch_add(1, Ordering::Relaxed);
+            let retry = req.try_clone();
+            let res = ctx.call(&self.service, req).await?;
+            match retry {
+                Some(next) if res.status() == StatusCode::SERVICE_UNAVAILABLE => req = next,
+                _ => return Ok(res),
+            }
+        }
+    }
+}
+
+#[ntex::test]
+async fn test_client_middleware() {
+    let available = Arc::new(AtomicUsize::new(0));
+    let available2 = available.clone();
+    let srv = test::server(move || {
+        let available = available2.clone();
+        App::new().default_service(web::to(move |req: HttpRequest| {
+            let available = available.clone();
+            async move {
+                assert_eq!(
+                    req.headers().get(header::AUTHORIZATION).unwrap(),
+                    "Bearer token"
+                );
+                if available.fetch_add(1, Ordering::Relaxed) < 2 {
+                    HttpResponse::ServiceUnavailable().finish()
+                } else {
+                    HttpResponse::Ok().body("ok")
+                }
+            }
+        }))
+    });
+
+    let counter = Arc::new(AtomicUsize::new(0));
+    let client = Client::build().wrap(Retry(counter.clone())).finish();
+
+    let mut response = client.post(srv.url("/")).send_body("data").await.unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.body().await.unwrap(), Bytes::from_static(b"ok"));
+    assert_eq!(counter.load(Ordering::Relaxed), 3);
+
+    // streaming body could not be retried
+    available.store(0, Ordering::Relaxed);
+    let response = client
+        .post(srv.url("/"))
+        .send_stream(once(Ready::Ok::<_, JsonPayloadError>(Bytes::from_static(
+            b"data",
+        ))))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(counter.load(Ordering::Relaxed), 4);
+}
+
 #[ntex::test]
 async fn client_read_until_eof() {
     let addr = ntex::server::TestServer::unused_addr();
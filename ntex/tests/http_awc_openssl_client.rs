@@ -86,3 +86,156 @@ async fn test_connection_reuse_h2() {
     // one connection
     assert_eq!(num.load(Ordering::Relaxed), 1);
 }
+
+#[ntex::test]
+async fn test_client_cert() {
+    use ntex::connect::ClientCert;
+    use ntex::http::Request;
+    use ntex_tls::openssl::PeerCert;
+
+    let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+    builder
+        .set_private_key_file("./tests/key.pem", SslFiletype::PEM)
+        .unwrap();
+    builder
+        .set_certificate_chain_file("./tests/cert.pem")
+        .unwrap();
+    builder.set_verify_callback(
+        SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT,
+        |_, _| true,
+    );
+    let acceptor = builder.build();
+
+    let srv = test_server(move || {
+        HttpService::build()
+            .h1(|req: Request| async move {
+                let has_cert = req.io().unwrap().query::<PeerCert>().as_ref().is_some();
+                assert!(has_cert);
+                Ok::<_, std::io::Error>(HttpResponse::Ok().finish())
+            })
+            .openssl(acceptor.clone())
+    });
+
+    let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+    builder.set_verify(SslVerifyMode::NONE);
+
+    let cert = ClientCert::from_pem(
+        &include_bytes!("cert.pem")[..],
+        &include_bytes!("key.pem")[..],
+    );
+    let client = Client::build()
+        .connector(
+            Connector::default()
+                .openssl(builder.build())
+                .client_cert_for("localhost", cert)
+                .finish(),
+        )
+        .finish();
+
+    let response = client.get(srv.surl("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+}
+
+#[cfg(feature = "dangerous")]
+#[ntex::test]
+async fn test_dangerous_accept_invalid_certs() {
+    use ntex::http::client::error::{ConnectError, SendRequestError};
+    use ntex::http::Request;
+    use tls_openssl::x509::X509;
+
+    let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+    builder
+        .set_private_key_file("./tests/key.pem", SslFiletype::PEM)
+        .unwrap();
+    builder
+        .set_certificate_chain_file("./tests/cert.pem")
+        .unwrap();
+    let acceptor = builder.build();
+
+    let srv = test_server(move || {
+        HttpService::build()
+            .h1(|_: Request| async move {
+                Ok::<_, std::io::Error>(HttpResponse::Ok().finish())
+            })
+            .openssl(acceptor.clone())
+    });
+
+    // verify server certificates
+    let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+    builder.set_verify(SslVerifyMode::PEER);
+    let client = Client::build()
+        .connector(Connector::default().openssl(builder.build()).finish())
+        .finish();
+
+    // self-signed certificate
+    let res = client.get(srv.surl("/")).send().await;
+    assert!(matches!(res, Err(SendRequestError::Connect(_))));
+
+    let response = client
+        .get(srv.surl("/"))
+        .dangerous_accept_invalid_certs(true)
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+
+    // custom verifier
+    let der = X509::from_pem(include_bytes!("cert.pem"))
+        .unwrap()
+        .to_der()
+        .unwrap();
+    let response = client
+        .get(srv.surl("/"))
+        .dangerous_cert_verifier(move |cert| cert == der.as_slice())
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+
+    let res = client
+        .get(srv.surl("/"))
+        .dangerous_cert_verifier(|_| false)
+        .send()
+        .await;
+    assert!(matches!(
+        res,
+        Err(SendRequestError::Connect(ConnectError::CertificateRejected))
+    ));
+
+    // verification is not affected by previous requests
+    let res = client.get(srv.surl("/")).send().await;
+    assert!(matches!(res, Err(SendRequestError::Connect(_))));
+}
+
+#[ntex::test]
+async fn test_handshake_timeout() {
+    use ntex::http::client::error::{ConnectError, SendRequestError};
+    use ntex::{io::Io, service::fn_service, time::sleep, time::Millis};
+
+    // server accepts connections but never completes tls handshake
+    let srv = ntex::server::test_server(|| {
+        fn_service(|io: Io| async move {
+            sleep(Millis(1000)).await;
+            drop(io);
+            Ok::<_, ()>(())
+        })
+    });
+
+    let client = Client::build()
+        .connector(
+            Connector::default()
+                .openssl(SslConnector::builder(SslMethod::tls()).unwrap().build())
+                .handshake_timeout(Millis(100))
+                .finish(),
+        )
+        .finish();
+
+    let res = client
+        .get(format!("https://localhost:{}/", srv.addr().port()))
+        .send()
+        .await;
+    assert!(matches!(
+        res,
+        Err(SendRequestError::Connect(ConnectError::HandshakeTimeout))
+    ));
+}
@@ -80,3 +80,46 @@ async fn test_connection_reuse_h2() {
     // one connection
     //assert_eq!(num.load(Ordering::Relaxed), 1);
 }
+
+#[cfg(feature = "dangerous")]
+#[ntex::test]
+async fn test_dangerous_accept_invalid_certs() {
+    use ntex::http::client::error::SendRequestError;
+    use ntex::http::Request;
+    use tls_rustls::{ClientConfig, RootCertStore};
+
+    let srv = test_server(move || {
+        HttpService::build()
+            .h1(|_: Request| async move {
+                Ok::<_, std::io::Error>(HttpResponse::Ok().finish())
+            })
+            .rustls(rustls_utils::tls_acceptor())
+    });
+
+    // verify server certificates
+    let config = ClientConfig::builder()
+        .with_root_certificates(RootCertStore::empty())
+        .with_no_client_auth();
+    let client = Client::build()
+        .connector(Connector::default().rustls(config).finish())
+        .finish();
+
+    let res = client.get(srv.surl("/")).send().await;
+    assert!(matches!(res, Err(SendRequestError::Connect(_))));
+
+    let response = client
+        .get(srv.surl("/"))
+        .dangerous_accept_invalid_certs(true)
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+
+    let response = client
+        .get(srv.surl("/"))
+        .dangerous_cert_verifier(|cert| !cert.is_empty())
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+}
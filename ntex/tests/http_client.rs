@@ -1,9 +1,12 @@
 use std::io;
 
+use ntex::http::client::error::SendRequestError;
+use ntex::http::h1::Control;
 use ntex::http::test::server as test_server;
-use ntex::http::{HttpService, Method, Request, Response};
-use ntex::service::ServiceFactory;
-use ntex::util::{Bytes, Ready};
+use ntex::http::{header, HttpService, Method, Request, Response, StatusCode};
+use ntex::service::{fn_service, ServiceFactory};
+use ntex::util::{stream_recv, Bytes, BytesMut, Ready};
+use ntex::{time::Seconds, web::error};
 
 const STR: &str = "Hello World Hello World Hello World Hello World Hello World \
                    Hello World Hello World Hello World Hello World Hello World \
@@ -69,6 +72,67 @@ async fn test_connection_close() {
     assert!(response.status().is_success());
 }
 
+#[ntex::test]
+async fn test_http10() {
+    let srv = test_server(move || {
+        HttpService::build()
+            .finish(|mut req: Request| async move {
+                let close = req
+                    .headers()
+                    .get(header::CONNECTION)
+                    .map(|v| v.to_str().unwrap().to_string())
+                    .unwrap_or_default();
+                let mut pl = req.take_payload();
+                let mut body = BytesMut::new();
+                while let Some(chunk) = stream_recv(&mut pl).await {
+                    body.extend_from_slice(&chunk.unwrap());
+                }
+                Ok::<_, io::Error>(Response::Ok().body(format!(
+                    "{:?} {}{}",
+                    req.version(),
+                    close,
+                    String::from_utf8_lossy(&body)
+                )))
+            })
+            .map(|_| ())
+    });
+
+    let mut response = srv
+        .request(Method::POST, "/")
+        .http10()
+        .send_body("test")
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"HTTP/1.0 test"));
+
+    // streaming body requires content-length
+    let body = || {
+        futures_util::stream::iter(vec![
+            Ok::<_, io::Error>(Bytes::from_static(b"te")),
+            Ok(Bytes::from_static(b"st")),
+        ])
+    };
+    let result = srv
+        .request(Method::POST, "/")
+        .http10()
+        .send_stream(body())
+        .await;
+    assert!(matches!(result, Err(SendRequestError::Send(_))));
+
+    let mut response = srv
+        .request(Method::POST, "/")
+        .http10()
+        .header(header::CONTENT_LENGTH, "4")
+        .send_stream(body())
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"HTTP/1.0 test"));
+}
+
 #[ntex::test]
 async fn test_with_query_parameter() {
     let srv = test_server(move || {
@@ -87,3 +151,144 @@ async fn test_with_query_parameter() {
     let response = request.send().await.unwrap();
     assert!(response.status().is_success());
 }
+
+#[ntex::test]
+async fn test_expect_continue() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .h1_control(fn_service(|req: Control<_, _>| async move {
+                let ack = if let Control::Expect(exc) = req {
+                    if exc.get_ref().head().uri.query() == Some("yes=") {
+                        exc.ack()
+                    } else {
+                        exc.fail(error::InternalError::default(
+                            "error",
+                            StatusCode::PRECONDITION_FAILED,
+                        ))
+                    }
+                } else {
+                    req.ack()
+                };
+                Ok::<_, std::convert::Infallible>(ack)
+            }))
+            .h1(fn_service(|mut req: Request| async move {
+                assert!(req.headers().contains_key("expect"));
+                let mut pl = req.take_payload();
+                let mut body = BytesMut::new();
+                while let Some(chunk) = stream_recv(&mut pl).await {
+                    body.extend_from_slice(&chunk.unwrap());
+                }
+                Ok::<_, io::Error>(Response::Ok().body(body.freeze()))
+            }))
+    });
+
+    let mut response = srv
+        .request(Method::POST, "/?yes=")
+        .expect_continue(Seconds(5))
+        .send_body(STR)
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+
+    // server rejects request, body is not sent
+    let mut response = srv
+        .request(Method::POST, "/")
+        .expect_continue(Seconds(5))
+        .send_body(STR)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"error"));
+}
+
+#[ntex::test]
+async fn test_informational() {
+    use std::{cell::RefCell, rc::Rc};
+
+    use ntex::{codec::BytesCodec, io::Io};
+
+    let srv = ntex::server::test_server(|| {
+        fn_service(|io: Io<_>| async move {
+            let _ = io.recv(&BytesCodec).await;
+            io.send(
+                Bytes::from_static(
+                    b"HTTP/1.1 103 Early Hints\r\nlink: </style.css>; rel=preload\r\n\r\n\
+                      HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok",
+                ),
+                &BytesCodec,
+            )
+            .await
+            .unwrap();
+            ntex::time::sleep(ntex::time::Millis(100)).await;
+            Ok::<_, io::Error>(())
+        })
+    });
+
+    let hints = Rc::new(RefCell::new(Vec::new()));
+    let hints2 = hints.clone();
+    let mut response = ntex::http::client::Client::new()
+        .get(format!("http://{}/", srv.addr()))
+        .on_informational(move |head| {
+            hints2
+                .borrow_mut()
+                .push((head.status, head.headers.get(header::LINK).unwrap().clone()));
+        })
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.body().await.unwrap(), Bytes::from_static(b"ok"));
+    assert_eq!(
+        &*hints.borrow(),
+        &[(
+            StatusCode::EARLY_HINTS,
+            header::HeaderValue::from_static("</style.css>; rel=preload")
+        )]
+    );
+}
+
+#[ntex::test]
+async fn test_h2_prior_knowledge() {
+    let srv = test_server(move || {
+        HttpService::build().h2(|req: Request| async move {
+            assert_eq!(req.version(), ntex::http::Version::HTTP_2);
+            Ok::<_, io::Error>(Response::Ok().body(STR))
+        })
+    });
+
+    let client = ntex::http::client::Client::build()
+        .connector(
+            ntex::http::client::Connector::default()
+                .http2_prior_knowledge()
+                .finish(),
+        )
+        .finish();
+
+    let mut response = client.get(srv.url("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.version(), ntex::http::Version::HTTP_2);
+
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+
+    // requests are multiplexed over one connection
+    let response = client.get(srv.url("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+
+    // released h2 connection is not duplicated in the pool
+    let uri: ntex::http::Uri = srv.url("/").parse().unwrap();
+    let conn = client.connector().acquire(uri.clone()).await.unwrap();
+    assert_eq!(conn.protocol(), ntex::io::types::HttpProtocol::Http2);
+    assert!(conn.io().is_none());
+    conn.release(false);
+    let conn = client.connector().acquire(uri.clone()).await.unwrap();
+    conn.release(false);
+
+    let status = client.pool_status();
+    let host = status.get(uri.authority().unwrap()).unwrap();
+    assert_eq!(host.idle(), 1);
+    assert_eq!(host.active(), 0);
+}
@@ -2,7 +2,7 @@ use std::{cell::Cell, io, sync::Arc, sync::Mutex, task::Context, task::Poll};
 
 use ntex::codec::BytesCodec;
 use ntex::http::test::server as test_server;
-use ntex::http::{body, h1, test, HttpService, Request, Response, StatusCode};
+use ntex::http::{body, h1, header, test, HttpService, Request, Response, StatusCode};
 use ntex::io::{DispatchItem, Dispatcher, Io};
 use ntex::service::{Pipeline, Service, ServiceCtx};
 use ntex::time::Seconds;
@@ -307,3 +307,88 @@ async fn test_transport() {
         }))
     );
 }
+
+#[ntex::test]
+async fn test_protocols() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .h1_control(move |req: h1::Control<_, _>| {
+                let ack = if let h1::Control::Upgrade(upg) = req {
+                    upg.handle(|req, io, codec| async move {
+                        let res = if req.path() == "/bad" {
+                            ws::handshake(req.head())
+                                .unwrap()
+                                .header(header::SEC_WEBSOCKET_PROTOCOL, "v9")
+                                .finish()
+                        } else {
+                            ws::handshake_with_protocols(req.head(), &["v3", "v2"])
+                                .unwrap()
+                                .finish()
+                        };
+                        io.encode(
+                            h1::Message::Item((res.drop_body(), body::BodySize::None)),
+                            &codec,
+                        )
+                        .unwrap();
+                        Dispatcher::new(
+                            io.seal(),
+                            ws::Codec::new(),
+                            service,
+                            &Default::default(),
+                        )
+                        .await
+                    })
+                } else {
+                    req.ack()
+                };
+                async move { Ok::<_, io::Error>(ack) }
+            })
+            .finish(|_| Ready::Ok::<_, io::Error>(Response::NotFound()))
+    });
+
+    let con = ws::WsClient::build(srv.url("/"))
+        .address(srv.addr())
+        .protocols(["v1", "v2"])
+        .finish()
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(con.protocol(), Some("v2"));
+
+    let con = ws::WsClient::build(srv.url("/"))
+        .address(srv.addr())
+        .protocols(["v1"])
+        .finish()
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(con.protocol(), None);
+
+    // protocol requested with header
+    let con = ws::WsClient::build(srv.url("/"))
+        .address(srv.addr())
+        .header(header::SEC_WEBSOCKET_PROTOCOL, "v3")
+        .finish()
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(con.protocol(), Some("v3"));
+
+    // server selected protocol which is not requested
+    let err = ws::WsClient::build(srv.url("/bad"))
+        .address(srv.addr())
+        .protocols(["v1", "v2"])
+        .finish()
+        .unwrap()
+        .connect()
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(
+        err,
+        ws::error::WsClientError::InvalidProtocolHeader(_)
+    ));
+}
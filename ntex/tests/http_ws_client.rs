@@ -7,7 +7,7 @@ use ntex::io::{DispatchItem, Dispatcher, DispatcherConfig};
 use ntex::service::{fn_factory_with_config, fn_service};
 use ntex::web::{self, App, HttpRequest};
 use ntex::ws::{self, handshake_response};
-use ntex::{time::Seconds, util::ByteString, util::Bytes, util::Ready};
+use ntex::{time::Millis, time::Seconds, util::ByteString, util::Bytes, util::Ready};
 
 async fn ws_service(
     msg: DispatchItem<ws::Codec>,
@@ -221,3 +221,64 @@ async fn test_upgrade_handler_with_await() {
         .await
         .unwrap();
 }
+
+#[ntex::test]
+async fn test_managed_reconnect() {
+    use std::{cell::Cell, rc::Rc};
+
+    let srv = test_server(|| {
+        HttpService::build()
+            .h1_control(|req: h1::Control<_, _>| async move {
+                let ack = if let h1::Control::Upgrade(upg) = req {
+                    upg.handle(|req, io, codec| async move {
+                        let res = handshake_response(req.head()).finish();
+                        io.encode(
+                            h1::Message::Item((res.drop_body(), BodySize::None)),
+                            &codec,
+                        )
+                        .unwrap();
+
+                        // close connection right after handshake
+                        let codec = ws::Codec::default();
+                        io.send(ws::Message::Close(None), &codec).await.unwrap();
+                        io.close();
+                        Ok::<_, io::Error>(())
+                    })
+                } else {
+                    req.ack()
+                };
+                Ok::<_, io::Error>(ack)
+            })
+            .finish(|_| Ready::Ok::<_, io::Error>(Response::NotFound()))
+    });
+
+    let connects = Rc::new(Cell::new(0));
+    let c = connects.clone();
+    let res = ws::WsClient::build(srv.url("/"))
+        .address(srv.addr())
+        .timeout(Seconds(30))
+        .finish()
+        .unwrap()
+        .managed()
+        .reconnect(ws::Reconnect::default().delay(Millis(10), Millis(50)))
+        .start(move |_| {
+            c.set(c.get() + 1);
+            let num = c.get();
+            async move {
+                if num == 3 {
+                    Err("done")
+                } else {
+                    Ok(|_: ws::Frame| async { Ok::<_, &'static str>(None) })
+                }
+            }
+        })
+        .await;
+
+    assert!(matches!(
+        res,
+        Err(ws::error::WsManagedError::Ws(ws::error::WsError::Service(
+            "done"
+        )))
+    ));
+    assert_eq!(connects.get(), 3);
+}
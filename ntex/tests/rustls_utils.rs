@@ -64,6 +64,18 @@ impl tls_rustls::client::danger::ServerCertVerifier for NoCertificateVerificatio
     }
 
     fn supported_verify_schemes(&self) -> Vec<tls_rustls::SignatureScheme> {
-        vec![]
+        use tls_rustls::SignatureScheme;
+
+        vec![
+            SignatureScheme::RSA_PKCS1_SHA256,
+            SignatureScheme::RSA_PKCS1_SHA384,
+            SignatureScheme::RSA_PKCS1_SHA512,
+            SignatureScheme::ECDSA_NISTP256_SHA256,
+            SignatureScheme::ECDSA_NISTP384_SHA384,
+            SignatureScheme::RSA_PSS_SHA256,
+            SignatureScheme::RSA_PSS_SHA384,
+            SignatureScheme::RSA_PSS_SHA512,
+            SignatureScheme::ED25519,
+        ]
     }
 }
@@ -1,12 +1,12 @@
 #![allow(clippy::let_underscore_future)]
-use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
-use std::{io, io::Read, net, sync::mpsc, sync::Arc, thread, time};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering::Relaxed};
+use std::{io, io::Read, net, sync::mpsc, sync::Arc, task, thread, time};
 
 use ntex::codec::BytesCodec;
 use ntex::io::Io;
 use ntex::server::{build, TestServer};
-use ntex::service::fn_service;
-use ntex::util::{Bytes, Ready};
+use ntex::service::{fn_factory, fn_service, Service, ServiceCtx};
+use ntex::util::{BoxFuture, Bytes, Ready};
 
 #[test]
 fn test_bind() {
@@ -36,6 +36,46 @@ fn test_bind() {
     let _ = h.join();
 }
 
+#[test]
+fn test_bound_addrs() {
+    let (tx, rx) = mpsc::channel();
+    let reported = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let reported2 = reported.clone();
+
+    let h = thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+        sys.run(move || {
+            let srv = build()
+                .workers(2)
+                .disable_signals()
+                .startup_handler(move |report| {
+                    assert_eq!(report.workers(), 2);
+                    *reported2.lock().unwrap() = report.bound_addrs().to_vec();
+                })
+                .bind("test", "127.0.0.1:0", move |_| {
+                    fn_service(|_| Ready::Ok::<_, ()>(()))
+                })
+                .unwrap()
+                .run();
+            let _ = tx.send((srv, ntex::rt::System::current()));
+            Ok(())
+        })
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let addrs = srv.bound_addrs();
+    assert_eq!(addrs.len(), 1);
+    assert_eq!(addrs[0].0, "test");
+    let addr = addrs[0].1.as_tcp().unwrap();
+    assert_ne!(addr.port(), 0);
+    assert_eq!(reported.lock().unwrap()[0].1.as_tcp(), Some(addr));
+
+    thread::sleep(time::Duration::from_millis(300));
+    assert!(net::TcpStream::connect(addr).is_ok());
+    sys.stop();
+    let _ = h.join();
+}
+
 #[ntex::test]
 async fn test_listen() {
     let addr = TestServer::unused_addr();
@@ -187,6 +227,48 @@ fn test_on_worker_start() {
     let _ = h.join();
 }
 
+#[test]
+#[cfg(feature = "tokio")]
+fn test_worker_data() {
+    struct Shard(usize);
+
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let shards = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let shards2 = shards.clone();
+
+    let h = thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+        sys.run(move || {
+            let srv = build()
+                .disable_signals()
+                .workers(2)
+                .worker_data(|idx| Shard(idx + 10))
+                .bind("test", addr, move |cfg| {
+                    let worker = cfg.worker();
+                    assert!(worker.data::<usize>().is_none());
+                    let shard = worker.data::<Shard>().unwrap().0;
+                    assert_eq!(shard, worker.index() + 10);
+                    shards2.lock().unwrap().push(shard);
+                    fn_service(|_| Ready::Ok::<_, ()>(()))
+                })
+                .unwrap()
+                .run();
+            let _ = tx.send((srv, ntex::rt::System::current()));
+            Ok(())
+        })
+    });
+    let (_, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(500));
+
+    assert!(net::TcpStream::connect(addr).is_ok());
+    let mut shards = shards.lock().unwrap().clone();
+    shards.sort();
+    assert_eq!(shards, vec![10, 11]);
+    sys.stop();
+    let _ = h.join();
+}
+
 #[test]
 #[cfg(feature = "tokio")]
 fn test_configure_async() {
@@ -310,3 +392,160 @@ fn test_panic_in_worker() {
     sys.stop();
     let _ = h.join();
 }
+
+#[ntex::test]
+#[cfg(unix)]
+async fn test_reload_factory() {
+    fn read(addr: net::SocketAddr) -> [u8; 2] {
+        let mut buf = [0u8; 2];
+        let mut conn = net::TcpStream::connect(addr).unwrap();
+        let _ = conn.read_exact(&mut buf);
+        buf
+    }
+
+    fn reply(
+        msg: &'static [u8],
+    ) -> impl Fn(Io) -> BoxFuture<'static, Result<(), ()>> + Clone {
+        move |io: Io| {
+            Box::pin(async move {
+                io.send(Bytes::from_static(msg), &BytesCodec).await.unwrap();
+                Ok(())
+            })
+        }
+    }
+
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+        sys.run(move || {
+            let srv = build()
+                .workers(2)
+                .disable_signals()
+                .bind("test", addr, move |_| fn_service(reply(b"v1")))
+                .unwrap()
+                .run();
+            let _ = tx.send((srv, ntex::rt::System::current()));
+            Ok(())
+        })
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+    assert_eq!(read(addr), *b"v1");
+
+    srv.reload_factory("test", |_| fn_service(reply(b"v2")))
+        .await
+        .unwrap();
+    thread::sleep(time::Duration::from_millis(100));
+    assert_eq!(read(addr), *b"v2");
+    assert_eq!(read(addr), *b"v2");
+
+    // failed reload keeps current service
+    let res = srv
+        .reload_factory("test", |_| {
+            fn_factory(|| async {
+                if true {
+                    Err(())
+                } else {
+                    Ok(fn_service(reply(b"v3")))
+                }
+            })
+        })
+        .await;
+    assert!(res.is_err());
+    assert_eq!(read(addr), *b"v2");
+
+    // unknown service
+    assert!(srv
+        .reload_factory("unknown", |_| fn_service(reply(b"v3")))
+        .await
+        .is_err());
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[ntex::test]
+#[cfg(unix)]
+async fn test_reload_factory_active_conn() {
+    struct Srv(Arc<AtomicBool>);
+
+    impl Service<Io> for Srv {
+        type Response = ();
+        type Error = ();
+
+        fn poll_shutdown(&self, _: &mut task::Context<'_>) -> task::Poll<()> {
+            self.0.store(true, Relaxed);
+            task::Poll::Ready(())
+        }
+
+        async fn call(&self, io: Io, _: ServiceCtx<'_, Self>) -> Result<(), ()> {
+            io.send(Bytes::from_static(b"v1"), &BytesCodec)
+                .await
+                .unwrap();
+            // keep connection open until peer closes it
+            while let Ok(Some(_)) = io.recv(&BytesCodec).await {}
+            Ok(())
+        }
+    }
+
+    fn read(conn: &mut net::TcpStream) -> [u8; 2] {
+        let mut buf = [0u8; 2];
+        let _ = conn.read_exact(&mut buf);
+        buf
+    }
+
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let shutdown2 = shutdown.clone();
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+        sys.run(move || {
+            let srv = build()
+                .workers(1)
+                .disable_signals()
+                .bind("test", addr, move |_| {
+                    let shutdown = shutdown2.clone();
+                    fn_factory(move || {
+                        let shutdown = shutdown.clone();
+                        async move { Ok::<_, ()>(Srv(shutdown)) }
+                    })
+                })
+                .unwrap()
+                .run();
+            let _ = tx.send((srv, ntex::rt::System::current()));
+            Ok(())
+        })
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    assert_eq!(read(&mut conn), *b"v1");
+
+    srv.reload_factory("test", |_| {
+        fn_service(|io: Io| async move {
+            io.send(Bytes::from_static(b"v2"), &BytesCodec)
+                .await
+                .unwrap();
+            Ok::<_, ()>(())
+        })
+    })
+    .await
+    .unwrap();
+    thread::sleep(time::Duration::from_millis(100));
+    let mut conn2 = net::TcpStream::connect(addr).unwrap();
+    assert_eq!(read(&mut conn2), *b"v2");
+
+    // old service is not shut down while connection is active
+    assert!(!shutdown.load(Relaxed));
+    drop(conn);
+    thread::sleep(time::Duration::from_millis(200));
+    assert!(shutdown.load(Relaxed));
+
+    sys.stop();
+    let _ = h.join();
+}
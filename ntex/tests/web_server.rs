@@ -1180,3 +1180,123 @@ async fn web_no_ws_payload() {
     let body = response.body().await.unwrap();
     assert_eq!(body, STR);
 }
+
+#[ntex::test]
+async fn test_multipart() {
+    use ntex::http::client::multipart::{Form, Part};
+    use ntex::web::{error::MultipartError, types::Multipart};
+
+    let srv = test::server(|| {
+        App::new().service(web::resource("/").route(web::post().to(
+            |mut mp: Multipart| async move {
+                let mut fields = Vec::new();
+                while let Some(mut field) = mp.next_field().await? {
+                    let name = field.name().unwrap_or_default().to_string();
+                    let file = field.filename().unwrap_or_default().to_string();
+                    let size = field.bytes().await?.len();
+                    fields.push(format!("{}:{}:{}", name, file, size));
+                }
+                Ok::<_, MultipartError>(fields.join(","))
+            },
+        )))
+    });
+
+    let form = Form::new().text("name", "ntex").part(
+        "file",
+        Part::bytes(STR)
+            .file_name("test.txt")
+            .content_type(mime::TEXT_PLAIN),
+    );
+    let mut response = srv.post("/").send_multipart(form).await.unwrap();
+    assert!(response.status().is_success());
+    let body = response.body().await.unwrap();
+    assert_eq!(
+        body,
+        Bytes::from(format!("name::4,file:test.txt:{}", STR.len()))
+    );
+}
+
+#[ntex::test]
+async fn test_route_payload_read_rate() {
+    let (tx, rx) = std::sync::mpsc::channel();
+
+    std::thread::spawn(move || {
+        let sys = ntex::rt::System::new("test-server");
+        let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let local_addr = tcp.local_addr().unwrap();
+        tx.send((sys.system(), local_addr)).unwrap();
+
+        let _ = sys.block_on(async move {
+            web::server(|| {
+                App::new()
+                    // payload timer starts before route limits are applied
+                    .filter(ntex::service::fn_service(|req: web::WebRequest<_>| async {
+                        sleep(Millis(300)).await;
+                        Ok::<_, web::Error>(req)
+                    }))
+                    .service(
+                        web::resource("/default")
+                            .route(web::post().to(|body: Bytes| async move { body })),
+                    )
+                    .service(
+                        web::resource("/relaxed")
+                            .payload_read_rate(Seconds::ZERO, Seconds::ZERO, 0)
+                            .route(web::post().to(|body: Bytes| async move { body })),
+                    )
+                    .service(
+                        web::resource("/strict").route(
+                            web::post()
+                                .payload_read_rate(Seconds(1), Seconds::ZERO, 4096)
+                                .to(|body: Bytes| async move { body }),
+                        ),
+                    )
+            })
+            .payload_read_rate(Seconds(1), Seconds::ZERO, 64)
+            .listen(tcp)
+            .unwrap()
+            .run()
+            .await
+        });
+    });
+    let (system, addr) = rx.recv().unwrap();
+
+    // send headers with first chunk, then 7 chunks with 300ms delay
+    async fn upload(addr: std::net::SocketAddr, path: &str, chunk: &[u8]) -> String {
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        let mut data = format!(
+            "POST {} HTTP/1.1\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+            path,
+            chunk.len() * 8
+        )
+        .into_bytes();
+        data.extend_from_slice(chunk);
+        let _ = stream.write_all(&data);
+        for _ in 0..7 {
+            sleep(Millis(300)).await;
+            let _ = stream.write_all(chunk);
+        }
+        let mut data = String::new();
+        let _ = stream.read_to_string(&mut data);
+        data
+    }
+
+    // ~13 bytes per second
+    let slow = [b'x'; 4];
+    // ~426 bytes per second
+    let fast = [b'x'; 128];
+
+    let data = upload(addr, "/default", &fast).await;
+    assert!(data.starts_with("HTTP/1.1 200 OK"));
+    let data = upload(addr, "/default", &slow).await;
+    assert!(!data.starts_with("HTTP/1.1 200 OK"));
+
+    // route disables server-wide read rate
+    let data = upload(addr, "/relaxed", &slow).await;
+    assert!(data.starts_with("HTTP/1.1 200 OK"));
+
+    // route read rate is stricter than server-wide one
+    let data = upload(addr, "/strict", &fast).await;
+    assert!(!data.starts_with("HTTP/1.1 200 OK"));
+
+    system.stop();
+}
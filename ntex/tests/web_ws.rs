@@ -1,7 +1,9 @@
 use std::io;
+use std::sync::{Arc, Mutex};
 
 use ntex::http::StatusCode;
 use ntex::service::{fn_factory_with_config, fn_service};
+use ntex::time::{Millis, Seconds};
 use ntex::util::{ByteString, Bytes};
 use ntex::web::{self, test, ws, App, HttpRequest, HttpResponse};
 use ntex::ws::error::WsClientError;
@@ -66,6 +68,89 @@ async fn web_ws() {
     assert_eq!(item, ws::Frame::Close(Some(ws::CloseCode::Away.into())));
 }
 
+struct Handler(Arc<Mutex<Vec<String>>>);
+
+impl ws::WsHandler for Handler {
+    type Error = ws::ProtocolError;
+
+    async fn on_open(&self, session: &ws::WsSession) -> Result<(), Self::Error> {
+        self.0.lock().unwrap().push("open".to_string());
+        session.heartbeat(Seconds(1), Seconds(5));
+        Ok(())
+    }
+
+    async fn on_message(
+        &self,
+        msg: ws::Message,
+        session: &ws::WsSession,
+    ) -> Result<(), Self::Error> {
+        session.send(msg).await
+    }
+
+    async fn on_close(&self, reason: Option<ws::CloseReason>, _: &ws::WsSession) {
+        self.0
+            .lock()
+            .unwrap()
+            .push(format!("close {:?}", reason.map(|r| r.code)));
+    }
+}
+
+#[ntex::test]
+async fn web_ws_handler() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let ev = events.clone();
+    let srv = test::server(move || {
+        let ev = ev.clone();
+        App::new().service(web::resource("/").route(web::to(move |req: HttpRequest| {
+            let ev = ev.clone();
+            async move { ws::start_handler::<_, web::Error>(req, Handler(ev)).await }
+        })))
+    });
+
+    let (io, codec, _) = srv.ws().await.unwrap().into_inner();
+
+    // fragmented message is aggregated
+    io.send(
+        ws::Message::Continuation(ntex::ws::Item::FirstText(Bytes::from_static(b"te"))),
+        &codec,
+    )
+    .await
+    .unwrap();
+    io.send(
+        ws::Message::Continuation(ntex::ws::Item::Last(Bytes::from_static(b"xt"))),
+        &codec,
+    )
+    .await
+    .unwrap();
+    let item = io.recv(&codec).await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Text(Bytes::from_static(b"text")));
+
+    io.send(ws::Message::Ping("text".into()), &codec)
+        .await
+        .unwrap();
+    let item = io.recv(&codec).await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Pong("text".to_string().into()));
+
+    // heartbeat
+    let item = io.recv(&codec).await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Ping(Bytes::new()));
+
+    io.send(
+        ws::Message::Close(Some(ws::CloseCode::Normal.into())),
+        &codec,
+    )
+    .await
+    .unwrap();
+    let item = io.recv(&codec).await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Close(Some(ws::CloseCode::Normal.into())));
+
+    ntex::time::sleep(Millis(100)).await;
+    assert_eq!(
+        &*events.lock().unwrap(),
+        &["open".to_string(), "close Some(Normal)".to_string()]
+    );
+}
+
 #[ntex::test]
 async fn web_no_ws() {
     let srv = test::server(|| {
@@ -137,3 +222,57 @@ async fn web_ws_client() {
     // TODO fix
     on_disconnect.await
 }
+
+#[cfg(feature = "compress")]
+#[ntex::test]
+async fn web_ws_deflate() {
+    let srv =
+        test::server(|| {
+            App::new().state(ntex::ws::DeflateConfig::new()).service(
+                web::resource("/").route(web::to(|req: HttpRequest| async move {
+                    ws::start::<_, _, web::Error>(
+                        req,
+                        fn_factory_with_config(|_| async {
+                            Ok::<_, web::Error>(fn_service(service))
+                        }),
+                    )
+                    .await
+                })),
+            )
+        });
+
+    let conn = ntex::ws::WsClient::build(srv.url("/"))
+        .address(srv.addr())
+        .deflate(ntex::ws::DeflateConfig::new().client_no_context_takeover(true))
+        .finish()
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(
+        conn.response()
+            .headers()
+            .get(ntex::http::header::SEC_WEBSOCKET_EXTENSIONS)
+            .unwrap(),
+        "permessage-deflate; client_no_context_takeover"
+    );
+
+    let conn = conn.seal();
+    let sink = conn.sink();
+    let rx = conn.receiver();
+
+    for _ in 0..3 {
+        let text = "text text text text text text text text text text";
+        sink.send(ws::Message::Text(ByteString::from_static(text)))
+            .await
+            .unwrap();
+        let item = rx.recv().await.unwrap().unwrap();
+        assert_eq!(item, ws::Frame::Text(Bytes::from_static(text.as_bytes())));
+    }
+
+    sink.send(ws::Message::Binary(Bytes::from_static(&[1; 1024])))
+        .await
+        .unwrap();
+    let item = rx.recv().await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Binary(Bytes::from_static(&[1; 1024])));
+}